@@ -132,6 +132,10 @@ pub(crate) struct InstructionContent {
     pub can_trap: bool,
     /// Does this instruction have other side effects besides can_* flags?
     pub other_side_effects: bool,
+    /// Does this instruction clobber all caller-saved registers, like a call?
+    pub clobbers_all_regs: bool,
+    /// Can this instruction transfer control to its destination EBB by unwinding?
+    pub can_unwind: bool,
     /// Does this instruction write to CPU flags?
     pub writes_cpu_flags: bool,
 }
@@ -214,6 +218,8 @@ pub(crate) struct InstructionBuilder {
     can_store: bool,
     can_trap: bool,
     other_side_effects: bool,
+    clobbers_all_regs: bool,
+    can_unwind: bool,
 }
 
 impl InstructionBuilder {
@@ -236,6 +242,8 @@ impl InstructionBuilder {
             can_store: false,
             can_trap: false,
             other_side_effects: false,
+            clobbers_all_regs: false,
+            can_unwind: false,
         }
     }
 
@@ -313,6 +321,16 @@ impl InstructionBuilder {
         self
     }
 
+    pub fn clobbers_all_regs(mut self, val: bool) -> Self {
+        self.clobbers_all_regs = val;
+        self
+    }
+
+    pub fn can_unwind(mut self, val: bool) -> Self {
+        self.can_unwind = val;
+        self
+    }
+
     fn build(self, opcode_number: OpcodeNumber) -> Instruction {
         let operands_in = self.operands_in.unwrap_or_else(Vec::new);
         let operands_out = self.operands_out.unwrap_or_else(Vec::new);
@@ -368,6 +386,8 @@ impl InstructionBuilder {
             can_store: self.can_store,
             can_trap: self.can_trap,
             other_side_effects: self.other_side_effects,
+            clobbers_all_regs: self.clobbers_all_regs,
+            can_unwind: self.can_unwind,
             writes_cpu_flags,
         })
     }
@@ -792,6 +812,13 @@ pub(crate) enum FormatPredicateKind {
 
     /// Is the referenced data object colocated?
     IsColocatedData,
+
+    /// Does the function being compiled use the named calling convention and take arguments on
+    /// the stack? This doesn't look at the instruction itself.
+    HasStackArgsWithCallConv(&'static str),
+
+    /// The negation of `HasStackArgsWithCallConv`.
+    NoStackArgsWithCallConv(&'static str),
 }
 
 #[derive(Clone, Hash, PartialEq, Eq)]
@@ -834,6 +861,11 @@ impl FormatPredicateNode {
                 assert!(self.member_name == "args");
                 "ref args"
             }
+            FormatPredicateKind::HasStackArgsWithCallConv(_)
+            | FormatPredicateKind::NoStackArgsWithCallConv(_) => {
+                assert!(self.member_name == "args");
+                "args: _"
+            }
             _ => self.member_name,
         }
     }
@@ -878,6 +910,14 @@ impl FormatPredicateNode {
             FormatPredicateKind::IsColocatedData => {
                 format!("predicates::is_colocated_data({}, func)", self.member_name)
             }
+            FormatPredicateKind::HasStackArgsWithCallConv(call_conv) => format!(
+                "predicates::has_stack_args_with_call_conv(func, crate::isa::CallConv::{})",
+                call_conv
+            ),
+            FormatPredicateKind::NoStackArgsWithCallConv(call_conv) => format!(
+                "!predicates::has_stack_args_with_call_conv(func, crate::isa::CallConv::{})",
+                call_conv
+            ),
         }
     }
 }
@@ -1151,6 +1191,36 @@ impl InstructionPredicate {
         ))
     }
 
+    pub fn new_has_stack_args_with_call_conv(
+        format: &InstructionFormat,
+        call_conv: &'static str,
+    ) -> InstructionPredicateNode {
+        assert!(
+            format.has_value_list,
+            "the format must be variadic in number of arguments"
+        );
+        InstructionPredicateNode::FormatPredicate(FormatPredicateNode::new_raw(
+            format,
+            "args",
+            FormatPredicateKind::HasStackArgsWithCallConv(call_conv),
+        ))
+    }
+
+    pub fn new_no_stack_args_with_call_conv(
+        format: &InstructionFormat,
+        call_conv: &'static str,
+    ) -> InstructionPredicateNode {
+        assert!(
+            format.has_value_list,
+            "the format must be variadic in number of arguments"
+        );
+        InstructionPredicateNode::FormatPredicate(FormatPredicateNode::new_raw(
+            format,
+            "args",
+            FormatPredicateKind::NoStackArgsWithCallConv(call_conv),
+        ))
+    }
+
     pub fn new_is_colocated_data(formats: &Formats) -> InstructionPredicateNode {
         let format = &formats.unary_global_value;
         InstructionPredicateNode::FormatPredicate(FormatPredicateNode::new(
@@ -43,8 +43,10 @@ impl Stack {
         Self { regclass }
     }
     pub fn stack_base_mask(self) -> &'static str {
-        // TODO: Make this configurable instead of just using the SP.
-        "StackBaseMask(1)"
+        // Either the SP or the FP. `StackRef::masked` only picks the FP for frames whose stack
+        // pointer isn't fixed.
+        // TODO: Make this configurable per ISA.
+        "StackBaseMask(3)"
     }
 }
 
@@ -516,6 +516,20 @@ fn gen_opcodes(all_inst: &AllInstructions, fmt: &mut Formatter) {
             "Does this instruction have other side effects besides can_* flags?",
             fmt,
         );
+        gen_bool_accessor(
+            all_inst,
+            |inst| inst.clobbers_all_regs,
+            "clobbers_all_regs",
+            "Does this instruction clobber all caller-saved registers, like a call?",
+            fmt,
+        );
+        gen_bool_accessor(
+            all_inst,
+            |inst| inst.can_unwind,
+            "can_unwind",
+            "Can this instruction unwind to its destination EBB?",
+            fmt,
+        );
         gen_bool_accessor(
             all_inst,
             |inst| inst.writes_cpu_flags,
@@ -341,16 +341,41 @@ fn emit_dst_inst(def: &Def, def_pool: &DefPool, var_pool: &VarPool, fmt: &mut Fo
 
     if is_value_split(def) {
         // Split instructions are not emitted with the builder, but by calling special functions in
-        // the `legalizer::split` module. These functions will eliminate concat-split patterns.
+        // the `legalizer::split` module. These functions will eliminate concat-split patterns, so
+        // they return existing values and any result of the source pattern they define has to be
+        // turned into an alias of the returned value.
+        let split_vars = def
+            .defined_vars
+            .iter()
+            .map(|&var_index| {
+                let var = var_pool.get(var_index);
+                if var.is_output() {
+                    format!("{}_split", var.name)
+                } else {
+                    var.name.clone()
+                }
+            })
+            .collect::<Vec<_>>();
         fmt.line("let curpos = pos.position();");
         fmt.line("let srcloc = pos.srcloc();");
         fmtln!(
             fmt,
-            "let {} = split::{}(pos.func, cfg, curpos, srcloc, {});",
-            defined_vars,
+            "let ({}) = split::{}(pos.func, cfg, curpos, srcloc, {});",
+            split_vars.join(", "),
             def.apply.inst.snake_name(),
             def.apply.args[0].to_rust_code(var_pool)
         );
+        for &var_index in &def.defined_vars {
+            let var = var_pool.get(var_index);
+            if var.is_output() {
+                fmtln!(
+                    fmt,
+                    "pos.func.dfg.change_to_alias({}, {}_split);",
+                    var.name,
+                    var.name
+                );
+            }
+        }
         return;
     }
 
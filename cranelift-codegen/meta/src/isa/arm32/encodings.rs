@@ -0,0 +1,662 @@
+use crate::cdsl::encodings::{Encoding, EncodingBuilder};
+use crate::cdsl::instructions::BindParameter::Any;
+use crate::cdsl::instructions::{
+    Bindable, InstSpec, InstructionGroup, InstructionPredicate, InstructionPredicateNode,
+    InstructionPredicateRegistry,
+};
+use crate::cdsl::settings::{SettingGroup, SettingPredicateNumber};
+use crate::cdsl::types::LaneType;
+
+use crate::shared::types::Bool::B1;
+use crate::shared::types::Float::{F32, F64};
+use crate::shared::types::Int::{I16, I32, I8};
+use crate::shared::types::Reference::R32;
+use crate::shared::Definitions as SharedDefinitions;
+
+use super::recipes::{Mode, RecipeGroup};
+
+pub(crate) struct PerCpuModeEncodings<'defs> {
+    pub inst_pred_reg: InstructionPredicateRegistry,
+    pub enc_a32: Vec<Encoding>,
+    pub enc_t32: Vec<Encoding>,
+    recipes: &'defs RecipeGroup,
+}
+
+impl<'defs> PerCpuModeEncodings<'defs> {
+    fn new(recipes: &'defs RecipeGroup) -> Self {
+        Self {
+            inst_pred_reg: InstructionPredicateRegistry::new(),
+            enc_a32: Vec::new(),
+            enc_t32: Vec::new(),
+            recipes,
+        }
+    }
+
+    fn make_encoding<T>(
+        &mut self,
+        inst: InstSpec,
+        recipe: &str,
+        bits: u16,
+        builder_closure: T,
+    ) -> Encoding
+    where
+        T: FnOnce(EncodingBuilder) -> EncodingBuilder,
+    {
+        let recipe_number = self.recipes.by_name(recipe);
+        let builder = EncodingBuilder::new(inst, recipe_number, bits);
+        builder_closure(builder).build(&self.recipes.recipes, &mut self.inst_pred_reg)
+    }
+
+    /// Add an encoding to the T32 mode only, for the recipes that don't exist in A32.
+    fn enc_t32(&mut self, inst: impl Into<InstSpec>, recipe: &str, bits: u16) {
+        let encoding = self.make_encoding(inst.into(), recipe, bits, |x| x);
+        self.enc_t32.push(encoding);
+    }
+
+    /// Add an encoding to both modes, using the A32 `recipe` and its Thumb-2 counterpart.
+    fn enc_both_func<T>(
+        &mut self,
+        inst: impl Clone + Into<InstSpec>,
+        recipe: &str,
+        a32_bits: u16,
+        t32_bits: u16,
+        builder_closure: T,
+    ) where
+        T: Fn(EncodingBuilder) -> EncodingBuilder,
+    {
+        let encoding = self.make_encoding(
+            inst.clone().into(),
+            &Mode::A32.name(recipe),
+            a32_bits,
+            &builder_closure,
+        );
+        self.enc_a32.push(encoding);
+        // The recipes that emit no code are shared by both modes.
+        let t32_recipe = if self.recipes.contains(&Mode::T32.name(recipe)) {
+            Mode::T32.name(recipe)
+        } else {
+            recipe.to_string()
+        };
+        let encoding = self.make_encoding(inst.into(), &t32_recipe, t32_bits, &builder_closure);
+        self.enc_t32.push(encoding);
+    }
+    fn enc_both(&mut self, inst: impl Clone + Into<InstSpec>, recipe: &str, a32: u16, t32: u16) {
+        self.enc_both_func(inst, recipe, a32, t32, |x| x);
+    }
+    fn enc_both_isap(
+        &mut self,
+        inst: impl Clone + Into<InstSpec>,
+        recipe: &str,
+        a32: u16,
+        t32: u16,
+        isap: SettingPredicateNumber,
+    ) {
+        self.enc_both_func(inst, recipe, a32, t32, |encoding| {
+            encoding.isa_predicate(isap)
+        });
+    }
+    fn enc_both_instp(
+        &mut self,
+        inst: impl Clone + Into<InstSpec>,
+        recipe: &str,
+        a32: u16,
+        t32: u16,
+        instp: InstructionPredicateNode,
+    ) {
+        self.enc_both_func(inst, recipe, a32, t32, |encoding| {
+            encoding.inst_predicate(instp.clone())
+        });
+    }
+}
+
+// A32 and Thumb-2 instructions are 32 bits wide, which doesn't fit in the 16 encoding bits. Each
+// recipe family keeps the part of the instruction word that identifies the operation, and the
+// binary emitter fills in the operands. The functions below extract the encoding bits from a
+// full instruction word with all operand fields zeroed, so the words can be checked against the
+// manual.
+
+/// A32 data processing, multiplications and divisions: `word[27:20]`, for the `al` condition.
+fn a32_bits(word: u32) -> u16 {
+    assert_eq!(word >> 28, 0xe, "A32 words are unconditional");
+    ((word >> 20) & 0xff) as u16
+}
+
+/// A32 loads and stores, and the miscellaneous instructions that have an opcode in
+/// `word[7:4]`: `word[27:20] | word[7:4]`.
+fn a32_bits4(word: u32) -> u16 {
+    (a32_bits(word) << 4) | ((word >> 4) & 0xf) as u16
+}
+
+/// Thumb-2 instructions: the first halfword.
+fn t32_bits(word: u32) -> u16 {
+    (word >> 16) as u16
+}
+
+/// VFP data processing, which is identical in both modes: `word[23:16] | word[8:6]`.
+fn vfp_bits(word: u32) -> u16 {
+    assert_eq!(
+        word & 0xff00_0e00,
+        0xee00_0a00,
+        "not a VFP data processing word"
+    );
+    (((word >> 16) & 0xff) << 3 | (word >> 6) & 0x7) as u16
+}
+
+/// VFP loads and stores, which are identical in both modes: `word[27:20] | word[11:8]`.
+fn vldst_bits(word: u32) -> u16 {
+    assert_eq!(word >> 28, 0xe, "VFP words are unconditional");
+    (((word >> 20) & 0xff) << 4 | (word >> 8) & 0xf) as u16
+}
+
+/// The shift types of the shift instructions.
+const LSL: u16 = 0;
+const LSR: u16 = 1;
+const ASR: u16 = 2;
+const ROR: u16 = 3;
+
+/// The `eq` and `ne` condition codes.
+const COND_EQ: u16 = 0;
+const COND_NE: u16 = 1;
+
+#[allow(clippy::cognitive_complexity)]
+pub(crate) fn define<'defs>(
+    shared_defs: &'defs SharedDefinitions,
+    isa_settings: &SettingGroup,
+    arm32: &InstructionGroup,
+    recipes: &'defs RecipeGroup,
+) -> PerCpuModeEncodings<'defs> {
+    // Instructions shorthands.
+    let shared = &shared_defs.instructions;
+    let formats = &shared_defs.formats;
+
+    let adjust_sp_down_imm = shared.by_name("adjust_sp_down_imm");
+    let adjust_sp_up_imm = shared.by_name("adjust_sp_up_imm");
+    let band = shared.by_name("band");
+    let band_imm = shared.by_name("band_imm");
+    let band_not = shared.by_name("band_not");
+    let bconst = shared.by_name("bconst");
+    let bint = shared.by_name("bint");
+    let bitcast = shared.by_name("bitcast");
+    let bitrev = shared.by_name("bitrev");
+    let bmask = shared.by_name("bmask");
+    let bnot = shared.by_name("bnot");
+    let bor = shared.by_name("bor");
+    let bor_imm = shared.by_name("bor_imm");
+    let brff = shared.by_name("brff");
+    let brif = shared.by_name("brif");
+    let brnz = shared.by_name("brnz");
+    let brz = shared.by_name("brz");
+    let bxor = shared.by_name("bxor");
+    let bxor_imm = shared.by_name("bxor_imm");
+    let call = shared.by_name("call");
+    let call_indirect = shared.by_name("call_indirect");
+    let clz = shared.by_name("clz");
+    let copy = shared.by_name("copy");
+    let copy_nop = shared.by_name("copy_nop");
+    let copy_special = shared.by_name("copy_special");
+    let copy_to_ssa = shared.by_name("copy_to_ssa");
+    let ctz = shared.by_name("ctz");
+    let debugtrap = shared.by_name("debugtrap");
+    let fabs = shared.by_name("fabs");
+    let fadd = shared.by_name("fadd");
+    let fdemote = shared.by_name("fdemote");
+    let fdiv = shared.by_name("fdiv");
+    let ffcmp = shared.by_name("ffcmp");
+    let fill = shared.by_name("fill");
+    let fill_nop = shared.by_name("fill_nop");
+    let fmul = shared.by_name("fmul");
+    let fneg = shared.by_name("fneg");
+    let fpromote = shared.by_name("fpromote");
+    let fsub = shared.by_name("fsub");
+    let func_addr = shared.by_name("func_addr");
+    let iadd = shared.by_name("iadd");
+    let iadd_ifcarry = shared.by_name("iadd_ifcarry");
+    let iadd_ifcin = shared.by_name("iadd_ifcin");
+    let iadd_ifcout = shared.by_name("iadd_ifcout");
+    let iadd_imm = shared.by_name("iadd_imm");
+    let icmp = shared.by_name("icmp");
+    let icmp_imm = shared.by_name("icmp_imm");
+    let iconst = shared.by_name("iconst");
+    let ifcmp = shared.by_name("ifcmp");
+    let ifcmp_imm = shared.by_name("ifcmp_imm");
+    let imul = shared.by_name("imul");
+    let indirect_jump_table_br = shared.by_name("indirect_jump_table_br");
+    let ineg = shared.by_name("ineg");
+    let invoke = shared.by_name("invoke");
+    let invoke_indirect = shared.by_name("invoke_indirect");
+    let ireduce = shared.by_name("ireduce");
+    let irsub_imm = shared.by_name("irsub_imm");
+    let ishl = shared.by_name("ishl");
+    let ishl_imm = shared.by_name("ishl_imm");
+    let istore16 = shared.by_name("istore16");
+    let istore8 = shared.by_name("istore8");
+    let isub = shared.by_name("isub");
+    let isub_ifbin = shared.by_name("isub_ifbin");
+    let isub_ifborrow = shared.by_name("isub_ifborrow");
+    let isub_ifbout = shared.by_name("isub_ifbout");
+    let jump = shared.by_name("jump");
+    let jump_table_base = shared.by_name("jump_table_base");
+    let jump_table_entry = shared.by_name("jump_table_entry");
+    let landing_pad = shared.by_name("landing_pad");
+    let load = shared.by_name("load");
+    let regfill = shared.by_name("regfill");
+    let regmove = shared.by_name("regmove");
+    let regspill = shared.by_name("regspill");
+    let resumable_trap = shared.by_name("resumable_trap");
+    let return_ = shared.by_name("return");
+    let rotl_imm = shared.by_name("rotl_imm");
+    let rotr = shared.by_name("rotr");
+    let rotr_imm = shared.by_name("rotr_imm");
+    let safepoint = shared.by_name("safepoint");
+    let sdiv = shared.by_name("sdiv");
+    let select = shared.by_name("select");
+    let selectif = shared.by_name("selectif");
+    let sextend = shared.by_name("sextend");
+    let sload16 = shared.by_name("sload16");
+    let sload8 = shared.by_name("sload8");
+    let smulhi = shared.by_name("smulhi");
+    let spill = shared.by_name("spill");
+    let sqrt = shared.by_name("sqrt");
+    let srem = shared.by_name("srem");
+    let sshr = shared.by_name("sshr");
+    let sshr_imm = shared.by_name("sshr_imm");
+    let stack_addr = shared.by_name("stack_addr");
+    let store = shared.by_name("store");
+    let symbol_value = shared.by_name("symbol_value");
+    let trap = shared.by_name("trap");
+    let trapff = shared.by_name("trapff");
+    let trapif = shared.by_name("trapif");
+    let trapnz = shared.by_name("trapnz");
+    let trapz = shared.by_name("trapz");
+    let trueff = shared.by_name("trueff");
+    let trueif = shared.by_name("trueif");
+    let udiv = shared.by_name("udiv");
+    let uextend = shared.by_name("uextend");
+    let uload16 = shared.by_name("uload16");
+    let uload8 = shared.by_name("uload8");
+    let umulhi = shared.by_name("umulhi");
+    let urem = shared.by_name("urem");
+    let ushr = shared.by_name("ushr");
+    let ushr_imm = shared.by_name("ushr_imm");
+
+    let arm32_pop = arm32.by_name("arm32_pop");
+    let arm32_push = arm32.by_name("arm32_push");
+    let arm32_vcvt_from_sint = arm32.by_name("arm32_vcvt_from_sint");
+    let arm32_vcvt_from_uint = arm32.by_name("arm32_vcvt_from_uint");
+    let arm32_vcvt_to_sint = arm32.by_name("arm32_vcvt_to_sint");
+    let arm32_vcvt_to_uint = arm32.by_name("arm32_vcvt_to_uint");
+    let arm32_vmov_from_d = arm32.by_name("arm32_vmov_from_d");
+    let arm32_vmov_to_d = arm32.by_name("arm32_vmov_to_d");
+
+    // Predicates shorthands.
+    let use_hwdiv = isa_settings.predicate_by_name("use_hwdiv");
+
+    // Definitions.
+    let mut e = PerCpuModeEncodings::new(recipes);
+
+    // Integer arithmetic and logic.
+    for &(inst, a32, t32) in &[
+        (iadd, 0xe080_0000, 0xeb00_0000),
+        (isub, 0xe040_0000, 0xeba0_0000),
+        (band, 0xe000_0000, 0xea00_0000),
+        (bor, 0xe180_0000, 0xea40_0000),
+        (bxor, 0xe020_0000, 0xea80_0000),
+        (band_not, 0xe1c0_0000, 0xea20_0000),
+    ] {
+        e.enc_both(inst.bind(I32), "rrr", a32_bits(a32), t32_bits(t32));
+    }
+    // Booleans are 0 or 1, which the logical instructions preserve.
+    for &(inst, a32, t32) in &[
+        (band, 0xe000_0000, 0xea00_0000),
+        (bor, 0xe180_0000, 0xea40_0000),
+        (bxor, 0xe020_0000, 0xea80_0000),
+    ] {
+        e.enc_both(inst.bind(B1), "rrr", a32_bits(a32), t32_bits(t32));
+    }
+
+    // Carries and borrows live in the C flag. A subtraction sets it when it doesn't borrow,
+    // which `sbc` accounts for.
+    for &(inst, recipe, a32, t32) in &[
+        (iadd_ifcout, "rrr_fout", 0xe090_0000, 0xeb10_0000),
+        (isub_ifbout, "rrr_fout", 0xe050_0000, 0xebb0_0000),
+        (iadd_ifcin, "rrr_fin", 0xe0a0_0000, 0xeb40_0000),
+        (isub_ifbin, "rrr_fin", 0xe0c0_0000, 0xeb60_0000),
+        (iadd_ifcarry, "rrr_fio", 0xe0b0_0000, 0xeb50_0000),
+        (isub_ifborrow, "rrr_fio", 0xe0d0_0000, 0xeb70_0000),
+    ] {
+        e.enc_both(inst.bind(I32), recipe, a32_bits(a32), t32_bits(t32));
+    }
+
+    // Register copies are `mov`, and `bnot` is `mvn`.
+    let mov = (a32_bits(0xe1a0_0000), t32_bits(0xea4f_0000));
+    let copy_types: [LaneType; 4] = [I32.into(), I16.into(), I8.into(), B1.into()];
+    for &ty in &copy_types {
+        e.enc_both(copy.bind(ty), "mov", mov.0, mov.1);
+        e.enc_both(regmove.bind(ty), "rmov", mov.0, mov.1);
+        e.enc_both(copy_to_ssa.bind(ty), "copytossa", mov.0, mov.1);
+    }
+    e.enc_both(copy.bind(R32), "mov", mov.0, mov.1);
+    e.enc_both(regmove.bind(R32), "rmov", mov.0, mov.1);
+    e.enc_both(copy_to_ssa.bind(R32), "copytossa", mov.0, mov.1);
+    e.enc_both(
+        bnot.bind(I32),
+        "mov",
+        a32_bits(0xe1e0_0000),
+        t32_bits(0xea6f_0000),
+    );
+
+    // Negation and `bmask` are reverse subtractions from 0.
+    let rsb_imm = (a32_bits(0xe260_0000), t32_bits(0xf1c0_0000));
+    e.enc_both(ineg.bind(I32), "neg", rsb_imm.0, rsb_imm.1);
+    e.enc_both(bmask.bind(I32).bind(B1), "neg", rsb_imm.0, rsb_imm.1);
+
+    // Booleans are converted by extracting their lowest bit.
+    e.enc_both(
+        bint.bind(I32).bind(B1),
+        "bint",
+        a32_bits(0xe200_0000),
+        t32_bits(0xf000_0000),
+    );
+
+    // Shifts and rotates. The shift type is the same in both modes.
+    for &(inst, ty) in &[(ishl, LSL), (ushr, LSR), (sshr, ASR)] {
+        e.enc_both(inst.bind(I32).bind(I32), "shift", ty, ty);
+    }
+    e.enc_both(rotr.bind(I32).bind(I32), "rotr", ROR, ROR);
+    for &(inst, ty) in &[
+        (ishl_imm, LSL),
+        (ushr_imm, LSR),
+        (sshr_imm, ASR),
+        (rotr_imm, ROR),
+    ] {
+        e.enc_both(inst.bind(I32), "shift_imm", ty, ty);
+    }
+    e.enc_both(rotl_imm.bind(I32), "rotl_imm", ROR, ROR);
+
+    // Extensions.
+    for &(inst, from, a32, t32) in &[
+        (sextend, I8, 0xe6af_0070, 0xfa4f_f080),
+        (sextend, I16, 0xe6bf_0070, 0xfa0f_f080),
+        (uextend, I8, 0xe6ef_0070, 0xfa5f_f080),
+        (uextend, I16, 0xe6ff_0070, 0xfa1f_f080),
+    ] {
+        e.enc_both(
+            inst.bind(I32).bind(from),
+            "extend",
+            a32_bits(a32),
+            t32_bits(t32),
+        );
+    }
+
+    // A reduction only reads the low bits of the register.
+    for &(to, from) in &[(I8, I16), (I8, I32), (I16, I32)] {
+        e.enc_both(ireduce.bind(to).bind(from), "null", 0, 0);
+    }
+
+    // Multiplication.
+    e.enc_both(imul.bind(I32), "mul", 0, 0);
+    e.enc_both(
+        umulhi.bind(I32),
+        "mulhi",
+        a32_bits(0xe080_0090),
+        t32_bits(0xfba0_0000),
+    );
+    e.enc_both(
+        smulhi.bind(I32),
+        "mulhi",
+        a32_bits(0xe0c0_0090),
+        t32_bits(0xfb80_0000),
+    );
+
+    // Division is optional in ARMv7-A. Without it, division is a library call.
+    let sdiv_bits = (a32_bits(0xe710_f010), t32_bits(0xfb90_f0f0));
+    let udiv_bits = (a32_bits(0xe730_f010), t32_bits(0xfbb0_f0f0));
+    e.enc_both_isap(udiv.bind(I32), "div", udiv_bits.0, udiv_bits.1, use_hwdiv);
+    e.enc_both_isap(sdiv.bind(I32), "sdiv", sdiv_bits.0, sdiv_bits.1, use_hwdiv);
+    e.enc_both_isap(urem.bind(I32), "rem", udiv_bits.0, udiv_bits.1, use_hwdiv);
+    e.enc_both_isap(srem.bind(I32), "rem", sdiv_bits.0, sdiv_bits.1, use_hwdiv);
+
+    // Bit counting. `ctz` is `rbit` followed by `clz`, and the Thumb-2 encoding bits of `clz`
+    // and `rbit` have the opcode from the second halfword in place of the register.
+    e.enc_both(clz.bind(I32), "bitop", a32_bits4(0xe16f_0f10), 0xfab8);
+    e.enc_both(bitrev.bind(I32), "bitop", a32_bits4(0xe6ff_0f30), 0xfa9a);
+    e.enc_both(ctz.bind(I32), "ctz", 0, 0);
+
+    // Immediates.
+    for &(inst, a32, t32) in &[
+        (band_imm, 0xe200_0000, 0xf000_0000),
+        (bor_imm, 0xe380_0000, 0xf040_0000),
+        (bxor_imm, 0xe220_0000, 0xf080_0000),
+        (irsub_imm, 0xe260_0000, 0xf1c0_0000),
+    ] {
+        e.enc_both(inst.bind(I32), "rri", a32_bits(a32), t32_bits(t32));
+    }
+    e.enc_both(
+        iadd_imm.bind(I32),
+        "addi",
+        a32_bits(0xe280_0000),
+        t32_bits(0xf100_0000),
+    );
+    e.enc_both(iconst.bind(I32), "iconst", 0, 0);
+    e.enc_both(bconst.bind(B1), "bconst", 0, 0);
+
+    // Comparisons are `cmp`, which is `subs` without a destination.
+    let cmp = (a32_bits(0xe150_0000), t32_bits(0xebb0_0000));
+    let cmp_imm = (a32_bits(0xe350_0000), t32_bits(0xf1b0_0000));
+    e.enc_both(icmp.bind(I32), "icmp", cmp.0, cmp.1);
+    e.enc_both(icmp_imm.bind(I32), "icmp_imm", cmp_imm.0, cmp_imm.1);
+    e.enc_both(ifcmp.bind(I32), "ifcmp", cmp.0, cmp.1);
+    e.enc_both(ifcmp_imm.bind(I32), "ifcmp_imm", cmp_imm.0, cmp_imm.1);
+    e.enc_both(ifcmp_imm.bind(I32), "ifcmp_imm_ip", cmp.0, cmp.1);
+
+    e.enc_both(trueif, "trueif", 0, 0);
+    e.enc_both(trueff, "trueff", 0, 0);
+    e.enc_both(trueff, "trueff2", 0, 0);
+
+    e.enc_both(selectif.bind(I32), "selectif", 0, 0);
+    e.enc_both(select.bind(I32).bind(B1), "select", 0, 0);
+    e.enc_both(select.bind(B1).bind(B1), "select", 0, 0);
+    let vmov_s = vfp_bits(0xeeb0_0a40);
+    let vmov_d = vfp_bits(0xeeb0_0b40);
+    e.enc_both(select.bind(F32).bind(B1), "fselect_s", vmov_s, vmov_s);
+    e.enc_both(select.bind(F64).bind(B1), "fselect_d", vmov_d, vmov_d);
+
+    // Loads and stores.
+    let ldr = (a32_bits4(0xe590_0000), t32_bits(0xf8d0_0000));
+    let str_ = (a32_bits4(0xe580_0000), t32_bits(0xf8c0_0000));
+    for &(inst, recipe, a32, t32) in &[
+        (load, "ld", ldr.0, ldr.1),
+        (uload8, "ld", a32_bits4(0xe5d0_0000), t32_bits(0xf890_0000)),
+        (sload8, "ld", a32_bits4(0xe1d0_00d0), t32_bits(0xf990_0000)),
+        (uload16, "ld", a32_bits4(0xe1d0_00b0), t32_bits(0xf8b0_0000)),
+        (sload16, "ld", a32_bits4(0xe1d0_00f0), t32_bits(0xf9b0_0000)),
+        (store, "st", str_.0, str_.1),
+        (istore8, "st", a32_bits4(0xe5c0_0000), t32_bits(0xf880_0000)),
+        (
+            istore16,
+            "st",
+            a32_bits4(0xe1c0_00b0),
+            t32_bits(0xf8a0_0000),
+        ),
+    ] {
+        e.enc_both(inst.bind(I32).bind(Any), recipe, a32, t32);
+    }
+    e.enc_both(load.bind(R32).bind(Any), "ld", ldr.0, ldr.1);
+    e.enc_both(store.bind(R32).bind(Any), "st", str_.0, str_.1);
+    for &(ty, suffix, vldr, vstr) in &[
+        (F32, "_s", 0xed90_0a00, 0xed80_0a00),
+        (F64, "_d", 0xed90_0b00, 0xed80_0b00),
+    ] {
+        let (vldr, vstr) = (vldst_bits(vldr), vldst_bits(vstr));
+        e.enc_both(
+            load.bind(ty).bind(Any),
+            &format!("vld{}", suffix),
+            vldr,
+            vldr,
+        );
+        e.enc_both(
+            store.bind(ty).bind(Any),
+            &format!("vst{}", suffix),
+            vstr,
+            vstr,
+        );
+
+        // Spills and fills.
+        e.enc_both(spill.bind(ty), &format!("vspill{}", suffix), vstr, vstr);
+        e.enc_both(
+            regspill.bind(ty),
+            &format!("vregspill{}", suffix),
+            vstr,
+            vstr,
+        );
+        e.enc_both(fill.bind(ty), &format!("vfill{}", suffix), vldr, vldr);
+        e.enc_both(regfill.bind(ty), &format!("vregfill{}", suffix), vldr, vldr);
+        e.enc_both(fill_nop.bind(ty), &format!("fillnull{}", suffix), 0, 0);
+        e.enc_both(copy_nop.bind(ty), "stacknull", 0, 0);
+    }
+
+    // Spills and fills. Spill slots are at least 4 bytes, so the narrow types use 32-bit
+    // accesses.
+    for &ty in &copy_types {
+        e.enc_both(spill.bind(ty), "spill", str_.0, str_.1);
+        e.enc_both(regspill.bind(ty), "regspill", str_.0, str_.1);
+        e.enc_both(fill.bind(ty), "fill", ldr.0, ldr.1);
+        e.enc_both(regfill.bind(ty), "regfill", ldr.0, ldr.1);
+        e.enc_both(fill_nop.bind(ty), "fillnull", 0, 0);
+        e.enc_both(copy_nop.bind(ty), "stacknull", 0, 0);
+    }
+    e.enc_both(spill.bind(R32), "spill", str_.0, str_.1);
+    e.enc_both(regspill.bind(R32), "regspill", str_.0, str_.1);
+    e.enc_both(fill.bind(R32), "fill", ldr.0, ldr.1);
+    e.enc_both(regfill.bind(R32), "regfill", ldr.0, ldr.1);
+
+    e.enc_both(stack_addr.bind(I32), "spaddr", 0, 0);
+
+    // Frames.
+    e.enc_both(adjust_sp_down_imm, "adjustsp_down", 0, 0);
+    e.enc_both(adjust_sp_up_imm, "adjustsp_up", 0, 0);
+    e.enc_both(copy_special, "copysp", 0, 0);
+    e.enc_both(arm32_push.bind(I32), "push", 0, 0);
+    e.enc_both(arm32_pop.bind(I32), "pop", 0, 0);
+    e.enc_both(arm32_push.bind(F64), "vpush", 0, 0);
+    e.enc_both(arm32_pop.bind(F64), "vpop", 0, 0);
+
+    // Control flow. The Thumb-2 conditional branches have a long form for destinations they
+    // can't reach.
+    e.enc_both(jump, "b", 0, 0);
+    e.enc_both(brif, "brif", 0, 0);
+    e.enc_t32(brif, "t32_brif_long", 0);
+    e.enc_both(brff, "brff", 0, 0);
+    e.enc_both(brff, "brff2", 0, 0);
+    e.enc_t32(brff, "t32_brff_long", 0);
+    e.enc_t32(brff, "t32_brff2_long", 0);
+    for &ty in &[LaneType::from(I32), LaneType::from(B1)] {
+        e.enc_both(brz.bind(ty), "brz", COND_EQ, COND_EQ);
+        e.enc_t32(brz.bind(ty), "t32_brz_long", COND_EQ);
+        e.enc_both(brnz.bind(ty), "brz", COND_NE, COND_NE);
+        e.enc_t32(brnz.bind(ty), "t32_brz_long", COND_NE);
+
+        // Conditional traps branch over the trap when the condition doesn't hold.
+        e.enc_both(trapz.bind(ty), "trapz", COND_NE, COND_NE);
+        e.enc_both(trapnz.bind(ty), "trapz", COND_EQ, COND_EQ);
+    }
+
+    e.enc_both(jump_table_base.bind(I32), "jt_base", 0, 0);
+    e.enc_both(jump_table_entry.bind(I32), "jt_entry", 0, 0);
+    e.enc_both(indirect_jump_table_br.bind(I32), "indirect_jmp", 0, 0);
+
+    e.enc_both(return_, "ret", 0, 0);
+
+    // Calls. `bl` reaches 32 MB in A32 and 16 MB in Thumb-2, so it is only used for colocated
+    // functions. Other calls go through the scratch register.
+    let is_colocated_call = InstructionPredicate::new_is_colocated_func(&*formats.call, "func_ref");
+    e.enc_both_instp(call, "call", 0, 0, is_colocated_call);
+    e.enc_both(call, "call_abs", 0, 0);
+    e.enc_both(call_indirect.bind(I32), "call_r", 0, 0);
+
+    let is_colocated_invoke =
+        InstructionPredicate::new_is_colocated_func(&*formats.invoke, "func_ref");
+    e.enc_both_instp(invoke, "invoke", 0, 0, is_colocated_invoke);
+    e.enc_both(invoke, "invoke_abs", 0, 0);
+    e.enc_both(invoke_indirect.bind(I32), "invoke_r", 0, 0);
+    e.enc_both(landing_pad.bind(I32), "landing_pad", 0, 0);
+
+    // Function and symbol addresses.
+    e.enc_both(func_addr.bind(I32), "fnaddr", 0, 0);
+    e.enc_both(symbol_value.bind(I32), "gvaddr", 0, 0);
+
+    // Traps.
+    e.enc_both(trap, "trap", 0, 0);
+    e.enc_both(resumable_trap, "trap", 0, 0);
+    e.enc_both(debugtrap, "debugtrap", 0, 0);
+    e.enc_both(trapif, "trapif", 0, 0);
+    e.enc_both(trapff, "trapff", 0, 0);
+    e.enc_both(trapff, "trapff2", 0, 0);
+
+    e.enc_both(safepoint, "safepoint", 0, 0);
+
+    // Floating point. The double precision variants have the `sz` bit set.
+    for &(ty, suffix, sz) in &[(F32, "_s", 0), (F64, "_d", 1 << 8)] {
+        let recipe = |name: &str| format!("{}{}", name, suffix);
+        for &(inst, word) in &[
+            (fadd, 0xee30_0a00),
+            (fsub, 0xee30_0a40),
+            (fmul, 0xee20_0a00),
+            (fdiv, 0xee80_0a00),
+        ] {
+            let bits = vfp_bits(word | sz);
+            e.enc_both(inst.bind(ty), &recipe("vfp3"), bits, bits);
+        }
+        for &(inst, word) in &[
+            (fabs, 0xeeb0_0ac0),
+            (fneg, 0xeeb1_0a40),
+            (sqrt, 0xeeb1_0ac0),
+        ] {
+            let bits = vfp_bits(word | sz);
+            e.enc_both(inst.bind(ty), &recipe("vfp2"), bits, bits);
+        }
+
+        // `vcmp` doesn't raise an exception for quiet NaNs, as opposed to `vcmpe`.
+        let vcmp = vfp_bits(0xeeb4_0a40 | sz);
+        e.enc_both(ffcmp.bind(ty), &recipe("ffcmp"), vcmp, vcmp);
+
+        let vmov = vfp_bits(0xeeb0_0a40 | sz);
+        e.enc_both(copy.bind(ty), &recipe("vfp2"), vmov, vmov);
+        e.enc_both(regmove.bind(ty), &recipe("vrmov"), vmov, vmov);
+        e.enc_both(copy_to_ssa.bind(ty), &recipe("vcopytossa"), vmov, vmov);
+    }
+
+    // Conversions between integers and floating point, which keep the integers in single
+    // precision registers. The conversions to integers round towards zero and saturate.
+    for &(inst, word, double_recipe) in &[
+        (arm32_vcvt_to_sint, 0xeebd_0ac0, "vcvt_ds"),
+        (arm32_vcvt_to_uint, 0xeebc_0ac0, "vcvt_ds"),
+        (arm32_vcvt_from_sint, 0xeeb8_0ac0, "vcvt_sd"),
+        (arm32_vcvt_from_uint, 0xeeb8_0a40, "vcvt_sd"),
+    ] {
+        let (single, double) = (vfp_bits(word), vfp_bits(word | 1 << 8));
+        e.enc_both(inst.bind(F32), "vfp2_s", single, single);
+        e.enc_both(inst.bind(F64), double_recipe, double, double);
+    }
+
+    e.enc_both(
+        fpromote.bind(F64).bind(F32),
+        "vcvt_sd",
+        vfp_bits(0xeeb7_0ac0),
+        vfp_bits(0xeeb7_0ac0),
+    );
+    e.enc_both(
+        fdemote.bind(F32).bind(F64),
+        "vcvt_ds",
+        vfp_bits(0xeeb7_0bc0),
+        vfp_bits(0xeeb7_0bc0),
+    );
+
+    // Moves between the register banks. The encoding bit is the direction, `word[20]`.
+    e.enc_both(bitcast.bind(I32).bind(F32), "vmov_to_gpr", 1, 1);
+    e.enc_both(bitcast.bind(F32).bind(I32), "vmov_from_gpr", 0, 0);
+    e.enc_both(arm32_vmov_to_d, "vmov_to_d", 0, 0);
+    e.enc_both(arm32_vmov_from_d, "vmov_from_d", 1, 1);
+
+    e
+}
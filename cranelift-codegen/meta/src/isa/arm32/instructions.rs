@@ -0,0 +1,164 @@
+#![allow(non_snake_case)]
+
+use crate::cdsl::instructions::{
+    AllInstructions, InstructionBuilder as Inst, InstructionGroup, InstructionGroupBuilder,
+};
+use crate::cdsl::operands::Operand;
+use crate::cdsl::types::{LaneType, ValueType};
+use crate::cdsl::typevar::{Interval, TypeSetBuilder, TypeVar};
+
+use crate::shared::formats::Formats;
+use crate::shared::types;
+
+pub(crate) fn define(
+    mut all_instructions: &mut AllInstructions,
+    formats: &Formats,
+) -> InstructionGroup {
+    let mut ig = InstructionGroupBuilder::new(&mut all_instructions);
+
+    let iWord: &TypeVar = &ValueType::from(LaneType::from(types::Int::I32)).into();
+    let fSingle: &TypeVar = &ValueType::from(LaneType::from(types::Float::F32)).into();
+    let fDouble: &TypeVar = &ValueType::from(LaneType::from(types::Float::F64)).into();
+
+    let Saved = &TypeVar::new(
+        "Saved",
+        "A register saved on the stack by a prologue",
+        TypeSetBuilder::new().ints(32..32).floats(64..64).build(),
+    );
+    let x = &Operand::new("x", Saved);
+
+    ig.push(
+        Inst::new(
+            "arm32_push",
+            r#"
+    Pushes a register onto the stack.
+
+    Decrements the stack pointer by the size of ``x`` and stores ``x`` at
+    the new top of the stack. Integer registers are pushed with a
+    pre-indexed ``str`` and floating point registers with ``vpush``.
+    "#,
+            &formats.unary,
+        )
+        .operands_in(vec![x])
+        .other_side_effects(true)
+        .can_store(true),
+    );
+
+    ig.push(
+        Inst::new(
+            "arm32_pop",
+            r#"
+    Pops a register from the stack.
+
+    Loads ``x`` from the top of the stack and then increments the stack
+    pointer by the size of ``x``. This undoes `arm32_push`.
+    "#,
+            &formats.nullary,
+        )
+        .operands_out(vec![x])
+        .other_side_effects(true)
+        .can_load(true),
+    );
+
+    let Float = &TypeVar::new(
+        "Float",
+        "A scalar floating point number",
+        TypeSetBuilder::new().floats(Interval::All).build(),
+    );
+    let x = &Operand::new("x", Float);
+    let a = &Operand::new("a", fSingle).with_doc("The integer bits of the result");
+
+    ig.push(
+        Inst::new(
+            "arm32_vcvt_to_sint",
+            r#"
+    Convert a floating point number to a signed 32-bit integer held in a
+    single precision register, rounding towards zero.
+
+    Out of range inputs saturate and NaN converts to 0, like
+    `fcvt_to_sint_sat`. The result is moved to an integer register with a
+    `bitcast`.
+    "#,
+            &formats.unary,
+        )
+        .operands_in(vec![x])
+        .operands_out(vec![a]),
+    );
+
+    ig.push(
+        Inst::new(
+            "arm32_vcvt_to_uint",
+            r#"
+    Convert a floating point number to an unsigned 32-bit integer held in a
+    single precision register, rounding towards zero.
+
+    Out of range inputs saturate and NaN converts to 0, like
+    `fcvt_to_uint_sat`.
+    "#,
+            &formats.unary,
+        )
+        .operands_in(vec![x])
+        .operands_out(vec![a]),
+    );
+
+    let x = &Operand::new("x", fSingle).with_doc("The integer bits to convert");
+    let a = &Operand::new("a", Float);
+
+    ig.push(
+        Inst::new(
+            "arm32_vcvt_from_sint",
+            r#"
+    Convert a signed 32-bit integer held in a single precision register to
+    a floating point number.
+    "#,
+            &formats.unary,
+        )
+        .operands_in(vec![x])
+        .operands_out(vec![a]),
+    );
+
+    ig.push(
+        Inst::new(
+            "arm32_vcvt_from_uint",
+            r#"
+    Convert an unsigned 32-bit integer held in a single precision register
+    to a floating point number.
+    "#,
+            &formats.unary,
+        )
+        .operands_in(vec![x])
+        .operands_out(vec![a]),
+    );
+
+    let lo = &Operand::new("lo", iWord).with_doc("The low 32 bits");
+    let hi = &Operand::new("hi", iWord).with_doc("The high 32 bits");
+    let d = &Operand::new("d", fDouble);
+
+    ig.push(
+        Inst::new(
+            "arm32_vmov_to_d",
+            r#"
+    Move the bits of a pair of integer registers to a double precision
+    register.
+    "#,
+            &formats.binary,
+        )
+        .operands_in(vec![lo, hi])
+        .operands_out(vec![d]),
+    );
+
+    ig.push(
+        Inst::new(
+            "arm32_vmov_from_d",
+            r#"
+    Move the bits of a double precision register to a pair of integer
+    registers.
+    "#,
+            &formats.unary,
+        )
+        .operands_in(vec![d])
+        .operands_out(vec![lo, hi]),
+    );
+
+    ig.build()
+}
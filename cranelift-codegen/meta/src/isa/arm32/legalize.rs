@@ -0,0 +1,221 @@
+use crate::cdsl::ast::{var, ExprBuilder, Literal};
+use crate::cdsl::instructions::{Bindable, InstructionGroup};
+use crate::cdsl::xform::TransformGroupBuilder;
+use crate::shared::types::Float::{F32, F64};
+use crate::shared::types::Int::{I32, I64};
+use crate::shared::Definitions as SharedDefinitions;
+
+#[allow(clippy::many_single_char_names)]
+pub(crate) fn define(shared: &mut SharedDefinitions, arm32_instructions: &InstructionGroup) {
+    let mut group = TransformGroupBuilder::new(
+        "arm32_expand",
+        r#"
+    Legalize instructions by expansion.
+
+    Use arm32-specific instructions if needed."#,
+    )
+    .isa("arm32")
+    .chain_with(shared.transform_groups.by_name("expand_flags").id);
+
+    // List of instructions.
+    let insts = &shared.instructions;
+    let band = insts.by_name("band");
+    let bitcast = insts.by_name("bitcast");
+    let fcmp = insts.by_name("fcmp");
+    let fcvt_from_sint = insts.by_name("fcvt_from_sint");
+    let fcvt_from_uint = insts.by_name("fcvt_from_uint");
+    let fcvt_to_sint = insts.by_name("fcvt_to_sint");
+    let fcvt_to_sint_sat = insts.by_name("fcvt_to_sint_sat");
+    let fcvt_to_uint = insts.by_name("fcvt_to_uint");
+    let fcvt_to_uint_sat = insts.by_name("fcvt_to_uint_sat");
+    let ffcmp = insts.by_name("ffcmp");
+    let iadd = insts.by_name("iadd");
+    let iadd_ifcout = insts.by_name("iadd_ifcout");
+    let iconcat = insts.by_name("iconcat");
+    let iconst = insts.by_name("iconst");
+    let imul = insts.by_name("imul");
+    let ineg = insts.by_name("ineg");
+    let isplit = insts.by_name("isplit");
+    let isub = insts.by_name("isub");
+    let isub_ifbout = insts.by_name("isub_ifbout");
+    let popcnt = insts.by_name("popcnt");
+    let rotl = insts.by_name("rotl");
+    let rotr = insts.by_name("rotr");
+    let sdiv = insts.by_name("sdiv");
+    let srem = insts.by_name("srem");
+    let udiv = insts.by_name("udiv");
+    let urem = insts.by_name("urem");
+    let sadd_overflow = insts.by_name("sadd_overflow");
+    let ssub_overflow = insts.by_name("ssub_overflow");
+    let trueff = insts.by_name("trueff");
+    let trueif = insts.by_name("trueif");
+    let uadd_overflow = insts.by_name("uadd_overflow");
+    let usub_overflow = insts.by_name("usub_overflow");
+    let ushr_imm = insts.by_name("ushr_imm");
+
+    let arm32_vcvt_from_sint = arm32_instructions.by_name("arm32_vcvt_from_sint");
+    let arm32_vcvt_from_uint = arm32_instructions.by_name("arm32_vcvt_from_uint");
+    let arm32_vcvt_to_sint = arm32_instructions.by_name("arm32_vcvt_to_sint");
+    let arm32_vcvt_to_uint = arm32_instructions.by_name("arm32_vcvt_to_uint");
+    let arm32_vmov_from_d = arm32_instructions.by_name("arm32_vmov_from_d");
+    let arm32_vmov_to_d = arm32_instructions.by_name("arm32_vmov_to_d");
+
+    let imm = &shared.imm;
+
+    let a = var("a");
+    let b = var("b");
+    let c = var("c");
+    let d = var("d");
+    let e = var("e");
+    let f = var("f");
+    let g = var("g");
+    let h = var("h");
+    let k = var("k");
+    let m = var("m");
+    let n = var("n");
+    let p = var("p");
+    let q = var("q");
+    let r = var("r");
+    let s = var("s");
+    let t = var("t");
+    let x = var("x");
+    let xh = var("xh");
+    let xl = var("xl");
+    let y = var("y");
+    let cc = var("cc");
+
+    // There is no rotate left, but rotating right by the negated amount is the same thing.
+    group.legalize(
+        def!(a = rotl(x, y)),
+        vec![def!(b = ineg(y)), def!(a = rotr(x, b))],
+    );
+
+    // Overflow-reporting arithmetic reads the flags of `adds` and `subs`. The carry flag is set
+    // by an unsigned add that overflows, but cleared by a subtraction that borrows.
+    let intcc_of = Literal::enumerator_for(&imm.intcc, "of");
+    let intcc_uge = Literal::enumerator_for(&imm.intcc, "uge");
+    let intcc_ult = Literal::enumerator_for(&imm.intcc, "ult");
+    for &(op, flags_op, cc) in &[
+        (uadd_overflow, iadd_ifcout, &intcc_uge),
+        (sadd_overflow, iadd_ifcout, &intcc_of),
+        (usub_overflow, isub_ifbout, &intcc_ult),
+        (ssub_overflow, isub_ifbout, &intcc_of),
+    ] {
+        group.legalize(
+            def!((a, b) = op(x, y)),
+            vec![
+                def!((a, f) = flags_op(x, y)),
+                def!(b = trueif(cc.clone(), f)),
+            ],
+        );
+    }
+
+    // There is no population count outside of NEON. Count the bits of each byte in parallel
+    // instead, and sum the bytes with a multiplication.
+    let mask = |byte: u64| Literal::constant(&imm.imm64, (byte * 0x0101_0101) as i64);
+    group.legalize(
+        def!(a = popcnt.I32(x)),
+        vec![
+            def!(b = ushr_imm(x, Literal::constant(&imm.imm64, 1))),
+            def!(c = iconst(mask(0x55))),
+            def!(d = band(b, c)),
+            def!(e = isub(x, d)),
+            def!(f = ushr_imm(e, Literal::constant(&imm.imm64, 2))),
+            def!(g = iconst(mask(0x33))),
+            def!(h = band(e, g)),
+            def!(k = band(f, g)),
+            def!(m = iadd(h, k)),
+            def!(n = ushr_imm(m, Literal::constant(&imm.imm64, 4))),
+            def!(p = iadd(m, n)),
+            def!(q = iconst(mask(0x0f))),
+            def!(r = band(p, q)),
+            def!(s = iconst(mask(0x01))),
+            def!(t = imul(r, s)),
+            def!(a = ushr_imm(t, Literal::constant(&imm.imm64, 24))),
+        ],
+    );
+
+    // Floating point comparisons go through the flags, which `vmrs` copies from the FPSCR.
+    for &ty in &[F32, F64] {
+        let fcmp = fcmp.bind(ty);
+        group.legalize(
+            def!(a = fcmp(cc, x, y)),
+            vec![def!(f = ffcmp(x, y)), def!(a = trueff(cc, f))],
+        );
+    }
+
+    // Without the division instructions, divisions become library calls, which don't trap.
+    for &op in &[udiv, sdiv, urem, srem] {
+        group.custom_legalize(op, "expand_div_libcall");
+    }
+
+    // `vcvt` saturates instead of trapping, so check the input first.
+    group.custom_legalize(fcvt_to_sint, "expand_fcvt_to_sint");
+    group.custom_legalize(fcvt_to_uint, "expand_fcvt_to_uint");
+
+    // The conversions between floating point and integers operate on single precision
+    // registers, so the integer goes through one.
+    group.legalize(
+        def!(a = fcvt_to_sint_sat.I32(x)),
+        vec![def!(t = arm32_vcvt_to_sint(x)), def!(a = bitcast.I32(t))],
+    );
+    group.legalize(
+        def!(a = fcvt_to_uint_sat.I32(x)),
+        vec![def!(t = arm32_vcvt_to_uint(x)), def!(a = bitcast.I32(t))],
+    );
+    for &ty in &[F32, F64] {
+        let fcvt_from_sint = fcvt_from_sint.bind(ty).bind(I32);
+        let fcvt_from_uint = fcvt_from_uint.bind(ty).bind(I32);
+        group.legalize(
+            def!(a = fcvt_from_sint(x)),
+            vec![
+                def!(t = bitcast.F32(x)),
+                def!(a = arm32_vcvt_from_sint.ty(t)),
+            ],
+        );
+        group.legalize(
+            def!(a = fcvt_from_uint(x)),
+            vec![
+                def!(t = bitcast.F32(x)),
+                def!(a = arm32_vcvt_from_uint.ty(t)),
+            ],
+        );
+    }
+
+    // Double precision registers are moved from and to a pair of integer registers.
+    let bitcast_f64 = bitcast.bind(F64).bind(I64);
+    group.legalize(
+        def!(a = bitcast_f64(x)),
+        vec![
+            def!((xl, xh) = isplit(x)),
+            def!(a = arm32_vmov_to_d(xl, xh)),
+        ],
+    );
+
+    group.build_and_add_to(&mut shared.transform_groups);
+
+    let mut narrow = TransformGroupBuilder::new(
+        "arm32_narrow",
+        r#"
+    Legalize instructions by narrowing.
+
+    Use arm32-specific instructions if needed."#,
+    )
+    .isa("arm32")
+    .chain_with(shared.transform_groups.by_name("narrow_flags").id);
+
+    for &op in &[udiv, sdiv, urem, srem] {
+        narrow.custom_legalize(op, "expand_div_libcall");
+    }
+
+    let bitcast_i64 = bitcast.bind(I64).bind(F64);
+    narrow.legalize(
+        def!(a = bitcast_i64(x)),
+        vec![
+            def!((xl, xh) = arm32_vmov_from_d(x)),
+            def!(a = iconcat(xl, xh)),
+        ],
+    );
+
+    narrow.build_and_add_to(&mut shared.transform_groups);
+}
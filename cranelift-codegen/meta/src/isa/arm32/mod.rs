@@ -1,14 +1,34 @@
 use crate::cdsl::cpu_modes::CpuMode;
-use crate::cdsl::instructions::{InstructionGroupBuilder, InstructionPredicateMap};
 use crate::cdsl::isa::TargetIsa;
-use crate::cdsl::recipes::Recipes;
 use crate::cdsl::regs::{IsaRegs, IsaRegsBuilder, RegBankBuilder, RegClassBuilder};
 use crate::cdsl::settings::{SettingGroup, SettingGroupBuilder};
 
+use crate::shared::types::Bool::B1;
+use crate::shared::types::Float::{F32, F64};
+use crate::shared::types::Int::{I16, I32, I8};
 use crate::shared::Definitions as SharedDefinitions;
 
+mod encodings;
+mod instructions;
+mod legalize;
+mod recipes;
+
 fn define_settings(_shared: &SettingGroup) -> SettingGroup {
-    let setting = SettingGroupBuilder::new("arm32");
+    let mut setting = SettingGroupBuilder::new("arm32");
+
+    let has_hwdiv = setting.add_bool(
+        "has_hwdiv",
+        "CPU has the integer division instructions SDIV and UDIV",
+        false,
+    );
+    setting.add_bool(
+        "has_d32",
+        "CPU has 32 double precision floating point registers (VFPv3-D32)",
+        false,
+    );
+
+    setting.add_predicate("use_hwdiv", predicate!(has_hwdiv));
+
     setting.build()
 }
 
@@ -53,24 +73,39 @@ pub(crate) fn define(shared_defs: &mut SharedDefinitions) -> TargetIsa {
     let settings = define_settings(&shared_defs.settings);
     let regs = define_regs();
 
-    let inst_group = InstructionGroupBuilder::new(&mut shared_defs.all_instructions).build();
+    let inst_group = instructions::define(&mut shared_defs.all_instructions, &shared_defs.formats);
+    legalize::define(shared_defs, &inst_group);
 
     // CPU modes for 32-bit ARM and Thumb2.
     let mut a32 = CpuMode::new("A32");
     let mut t32 = CpuMode::new("T32");
 
-    // TODO refine these.
-    let narrow_flags = shared_defs.transform_groups.by_name("narrow_flags");
-    a32.legalize_default(narrow_flags);
-    t32.legalize_default(narrow_flags);
+    let expand_flags = shared_defs.transform_groups.by_name("expand_flags");
+    let widen = shared_defs.transform_groups.by_name("widen");
+    let arm32_expand = shared_defs.transform_groups.by_name("arm32_expand");
+    let arm32_narrow = shared_defs.transform_groups.by_name("arm32_narrow");
 
-    let cpu_modes = vec![a32, t32];
+    for mode in &mut [&mut a32, &mut t32] {
+        mode.legalize_monomorphic(expand_flags);
+        mode.legalize_default(arm32_narrow);
+        mode.legalize_type(B1, expand_flags);
+        mode.legalize_type(I8, widen);
+        mode.legalize_type(I16, widen);
+        mode.legalize_type(I32, arm32_expand);
+        mode.legalize_type(F32, arm32_expand);
+        mode.legalize_type(F64, arm32_expand);
+    }
+
+    let recipes = recipes::define(shared_defs, &regs);
 
-    // TODO implement arm32 recipes.
-    let recipes = Recipes::new();
+    let encodings = encodings::define(shared_defs, &settings, &inst_group, &recipes);
+    a32.set_encodings(encodings.enc_a32);
+    t32.set_encodings(encodings.enc_t32);
+    let encodings_predicates = encodings.inst_pred_reg.extract();
 
-    // TODO implement arm32 encodings and predicates.
-    let encodings_predicates = InstructionPredicateMap::new();
+    let recipes = recipes.collect();
+
+    let cpu_modes = vec![a32, t32];
 
     TargetIsa::new(
         "arm32",
@@ -0,0 +1,1393 @@
+use std::collections::HashMap;
+
+use crate::cdsl::ast::Literal;
+use crate::cdsl::formats::InstructionFormat;
+use crate::cdsl::instructions::InstructionPredicate;
+use crate::cdsl::recipes::{
+    EncodingRecipeBuilder, EncodingRecipeNumber, OperandConstraint, Recipes, Register, Stack,
+};
+use crate::cdsl::regs::IsaRegs;
+use crate::shared::Definitions as SharedDefinitions;
+
+/// An helper to create recipes and use them when defining the ARM32 encodings.
+pub(crate) struct RecipeGroup {
+    /// The actualy list of recipes explicitly created in this file.
+    pub recipes: Recipes,
+
+    /// Provides fast lookup from a name to an encoding recipe.
+    name_to_recipe: HashMap<String, EncodingRecipeNumber>,
+}
+
+impl RecipeGroup {
+    fn new() -> Self {
+        Self {
+            recipes: Recipes::new(),
+            name_to_recipe: HashMap::new(),
+        }
+    }
+
+    fn push(&mut self, builder: EncodingRecipeBuilder) {
+        assert!(
+            self.name_to_recipe.get(&builder.name).is_none(),
+            format!("arm32 recipe '{}' created twice", builder.name)
+        );
+        let name = builder.name.clone();
+        let number = self.recipes.push(builder.build());
+        self.name_to_recipe.insert(name, number);
+    }
+
+    pub fn by_name(&self, name: &str) -> EncodingRecipeNumber {
+        *self
+            .name_to_recipe
+            .get(name)
+            .unwrap_or_else(|| panic!("unknown arm32 recipe name {}", name))
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.name_to_recipe.contains_key(name)
+    }
+
+    pub fn collect(self) -> Recipes {
+        self.recipes
+    }
+}
+
+/// The two instruction sets of 32-bit ARM.
+///
+/// Most recipes exist for both, with the Thumb-2 ones prefixed by `t32_`. They differ in their
+/// sizes, and their emit code calls the helpers of the `a32` or the `t32` module of the binary
+/// emitter, which have the same names and signatures.
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) enum Mode {
+    A32,
+    T32,
+}
+
+impl Mode {
+    /// The name of the recipe `name` in this mode.
+    pub fn name(self, name: &str) -> String {
+        match self {
+            Mode::A32 => name.to_string(),
+            Mode::T32 => format!("t32_{}", name),
+        }
+    }
+
+    /// Pick the size, size function or anything else that differs between the modes.
+    fn pick<T>(self, a32: T, t32: T) -> T {
+        match self {
+            Mode::A32 => a32,
+            Mode::T32 => t32,
+        }
+    }
+
+    /// Adapt emit code written for A32 to this mode.
+    fn emit(self, code: &str) -> String {
+        match self {
+            Mode::A32 => code.to_string(),
+            Mode::T32 => code.replace("a32::", "t32::"),
+        }
+    }
+}
+
+/// Returns a predicate checking that the "cond" field of the instruction is one of `floatccs`.
+fn floatcc_predicate(floatccs: &[Literal], format: &InstructionFormat) -> InstructionPredicate {
+    floatccs
+        .iter()
+        .fold(InstructionPredicate::new(), |pred, literal| {
+            pred.or(InstructionPredicate::new_is_field_equal(
+                format,
+                "cond",
+                literal.to_rust_code(),
+            ))
+        })
+}
+
+#[allow(clippy::cognitive_complexity)]
+pub(crate) fn define(shared_defs: &SharedDefinitions, regs: &IsaRegs) -> RecipeGroup {
+    let formats = &shared_defs.formats;
+    let imm = &shared_defs.imm;
+
+    // Register classes shorthands.
+    let gpr = regs.class_by_name("GPR");
+    let s = regs.class_by_name("S");
+    let d = regs.class_by_name("D");
+    let flag = regs.class_by_name("FLAG");
+
+    // Operand constraints shorthands.
+    let reg_nzcv = Register::new(flag, regs.regunit_by_name(flag, "nzcv"));
+    let reg_r0 = Register::new(gpr, regs.regunit_by_name(gpr, "r0"));
+    let reg_r1 = Register::new(gpr, regs.regunit_by_name(gpr, "r1"));
+
+    let stack_gpr = Stack::new(gpr);
+    let stack_s = Stack::new(s);
+    let stack_d = Stack::new(d);
+
+    // Floating point condition codes that can't be tested with a single condition after
+    // `vcmp`. They are the union of two conditions, so they take an extra instruction.
+    let floatccs_two_conds: Vec<Literal> = ["one", "ueq"]
+        .iter()
+        .map(|name| Literal::enumerator_for(&imm.floatcc, name))
+        .collect();
+    let floatccs_one_cond: Vec<Literal> = [
+        "ord", "uno", "eq", "ne", "lt", "ge", "le", "gt", "ult", "uge", "ule", "ugt",
+    ]
+    .iter()
+    .map(|name| Literal::enumerator_for(&imm.floatcc, name))
+    .collect();
+
+    // Definitions.
+    let mut recipes = RecipeGroup::new();
+
+    // Recipes that don't emit anything are shared by both modes.
+
+    // A null unary instruction that takes a GPR register. Can be used for identity copies and
+    // no-op conversions.
+    recipes.push(
+        EncodingRecipeBuilder::new("null", &formats.unary, 0)
+            .operands_in(vec![gpr])
+            .operands_out(vec![0])
+            .clobbers_flags(false)
+            .emit(""),
+    );
+
+    // Stack-slot to same stack-slot copy, which is guaranteed to turn into a no-op.
+    recipes.push(
+        EncodingRecipeBuilder::new("stacknull", &formats.unary, 0)
+            .operands_in(vec![stack_gpr])
+            .operands_out(vec![stack_gpr])
+            .clobbers_flags(false)
+            .emit(""),
+    );
+
+    // No-op fills, created by late-stage redundant-fill removal.
+    recipes.push(
+        EncodingRecipeBuilder::new("fillnull", &formats.unary, 0)
+            .operands_in(vec![stack_gpr])
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .emit(""),
+    );
+    recipes.push(
+        EncodingRecipeBuilder::new("fillnull_s", &formats.unary, 0)
+            .operands_in(vec![stack_s])
+            .operands_out(vec![s])
+            .clobbers_flags(false)
+            .emit(""),
+    );
+    recipes.push(
+        EncodingRecipeBuilder::new("fillnull_d", &formats.unary, 0)
+            .operands_in(vec![stack_d])
+            .operands_out(vec![d])
+            .clobbers_flags(false)
+            .emit(""),
+    );
+
+    // The unwinder delivers the exception pointer and selector in r0 and r1.
+    recipes.push(
+        EncodingRecipeBuilder::new("landing_pad", &formats.nullary, 0)
+            .operands_out(vec![reg_r0, reg_r1])
+            .clobbers_flags(false)
+            .emit(""),
+    );
+
+    recipes.push(
+        EncodingRecipeBuilder::new("safepoint", &formats.multiary, 0).emit(
+            r#"
+                sink.add_stackmap(args, func, isa);
+            "#,
+        ),
+    );
+
+    for &mode in &[Mode::A32, Mode::T32] {
+        let name = |name: &str| mode.name(name);
+        let emit = |code: &str| mode.emit(code);
+
+        // Integer arithmetic.
+
+        // Data processing with three registers: add, sub and the logical instructions.
+        recipes.push(
+            EncodingRecipeBuilder::new(name("rrr"), &formats.binary, 4)
+                .operands_in(vec![gpr, gpr])
+                .operands_out(vec![gpr])
+                .clobbers_flags(false)
+                .emit(emit("a32::put_dp(bits, in_reg0, in_reg1, out_reg0, sink);")),
+        );
+
+        // The flag-setting variants producing a carry or a borrow.
+        recipes.push(
+            EncodingRecipeBuilder::new(name("rrr_fout"), &formats.binary, 4)
+                .operands_in(vec![gpr, gpr])
+                .operands_out(vec![
+                    OperandConstraint::RegClass(gpr),
+                    OperandConstraint::FixedReg(reg_nzcv),
+                ])
+                .emit(emit("a32::put_dp(bits, in_reg0, in_reg1, out_reg0, sink);")),
+        );
+
+        // The variants consuming a carry or a borrow.
+        recipes.push(
+            EncodingRecipeBuilder::new(name("rrr_fin"), &formats.ternary, 4)
+                .operands_in(vec![
+                    OperandConstraint::RegClass(gpr),
+                    OperandConstraint::RegClass(gpr),
+                    OperandConstraint::FixedReg(reg_nzcv),
+                ])
+                .operands_out(vec![gpr])
+                .emit(emit("a32::put_dp(bits, in_reg0, in_reg1, out_reg0, sink);")),
+        );
+
+        // The variants consuming and producing a carry or a borrow.
+        recipes.push(
+            EncodingRecipeBuilder::new(name("rrr_fio"), &formats.ternary, 4)
+                .operands_in(vec![
+                    OperandConstraint::RegClass(gpr),
+                    OperandConstraint::RegClass(gpr),
+                    OperandConstraint::FixedReg(reg_nzcv),
+                ])
+                .operands_out(vec![
+                    OperandConstraint::RegClass(gpr),
+                    OperandConstraint::FixedReg(reg_nzcv),
+                ])
+                .emit(emit("a32::put_dp(bits, in_reg0, in_reg1, out_reg0, sink);")),
+        );
+
+        // Register copies and `mvn`, which ignore the first operand register.
+        recipes.push(
+            EncodingRecipeBuilder::new(name("mov"), &formats.unary, 4)
+                .operands_in(vec![gpr])
+                .operands_out(vec![gpr])
+                .clobbers_flags(false)
+                .emit(emit("a32::put_dp(bits, 0, in_reg0, out_reg0, sink);")),
+        );
+        recipes.push(
+            EncodingRecipeBuilder::new(name("rmov"), &formats.reg_move, 4)
+                .operands_in(vec![gpr])
+                .clobbers_flags(false)
+                .emit(emit("a32::put_dp(bits, 0, src, dst, sink);")),
+        );
+        recipes.push(
+            EncodingRecipeBuilder::new(name("copytossa"), &formats.copy_to_ssa, 4)
+                // No operands_in to mention, because a source register is specified directly.
+                .operands_out(vec![gpr])
+                .clobbers_flags(false)
+                .emit(emit("a32::put_dp(bits, 0, src, out_reg0, sink);")),
+        );
+
+        // Negation is a reverse subtraction from 0.
+        recipes.push(
+            EncodingRecipeBuilder::new(name("neg"), &formats.unary, 4)
+                .operands_in(vec![gpr])
+                .operands_out(vec![gpr])
+                .clobbers_flags(false)
+                .emit(emit("a32::put_dp_imm(bits, in_reg0, 0, out_reg0, sink);")),
+        );
+
+        // Shifts by a register use the low byte of the amount, so the amount is masked first to
+        // take it modulo 32.
+        recipes.push(
+            EncodingRecipeBuilder::new(name("shift"), &formats.binary, 8)
+                .operands_in(vec![gpr, gpr])
+                .operands_out(vec![gpr])
+                .clobbers_flags(false)
+                .emit(emit(
+                    r#"
+                        a32::put_dp_imm(a32::AND_IMM, in_reg1, 31, scratch(), sink);
+                        a32::put_shift(bits, in_reg0, scratch(), out_reg0, sink);
+                    "#,
+                )),
+        );
+
+        // A rotation by any multiple of 32 is the identity, so rotations don't need the mask.
+        recipes.push(
+            EncodingRecipeBuilder::new(name("rotr"), &formats.binary, 4)
+                .operands_in(vec![gpr, gpr])
+                .operands_out(vec![gpr])
+                .clobbers_flags(false)
+                .emit(emit(
+                    "a32::put_shift(bits, in_reg0, in_reg1, out_reg0, sink);",
+                )),
+        );
+
+        // Shifts and rotates by an immediate amount.
+        recipes.push(
+            EncodingRecipeBuilder::new(name("shift_imm"), &formats.binary_imm, 4)
+                .operands_in(vec![gpr])
+                .operands_out(vec![gpr])
+                .clobbers_flags(false)
+                .emit(emit(
+                    r#"
+                        let amount = imm_shift_amount(imm);
+                        a32::put_shift_imm(bits, in_reg0, amount, out_reg0, sink);
+                    "#,
+                )),
+        );
+        recipes.push(
+            EncodingRecipeBuilder::new(name("rotl_imm"), &formats.binary_imm, 4)
+                .operands_in(vec![gpr])
+                .operands_out(vec![gpr])
+                .clobbers_flags(false)
+                .emit(emit(
+                    r#"
+                        let amount = (32 - imm_shift_amount(imm)) % 32;
+                        a32::put_shift_imm(bits, in_reg0, amount, out_reg0, sink);
+                    "#,
+                )),
+        );
+
+        // Multiplications.
+        recipes.push(
+            EncodingRecipeBuilder::new(name("mul"), &formats.binary, 4)
+                .operands_in(vec![gpr, gpr])
+                .operands_out(vec![gpr])
+                .clobbers_flags(false)
+                .emit(emit("a32::put_mul(in_reg0, in_reg1, out_reg0, sink);")),
+        );
+
+        // The high half of a widening multiplication. The low half goes to the scratch register.
+        recipes.push(
+            EncodingRecipeBuilder::new(name("mulhi"), &formats.binary, 4)
+                .operands_in(vec![gpr, gpr])
+                .operands_out(vec![gpr])
+                .clobbers_flags(false)
+                .emit(emit(
+                    "a32::put_mull(bits, in_reg0, in_reg1, scratch(), out_reg0, sink);",
+                )),
+        );
+
+        // Division traps when the divisor is zero, which `udiv` and `sdiv` don't do by
+        // themselves.
+        recipes.push(
+            EncodingRecipeBuilder::new(name("div"), &formats.binary, mode.pick(16, 12))
+                .operands_in(vec![gpr, gpr])
+                .operands_out(vec![gpr])
+                .emit(emit(
+                    r#"
+                        a32::put_trap_if_zero(in_reg1, func.srclocs[inst], sink);
+                        a32::put_div(bits, in_reg0, in_reg1, out_reg0, sink);
+                    "#,
+                )),
+        );
+
+        // Signed division also traps when the quotient doesn't fit: `MIN / -1`.
+        recipes.push(
+            EncodingRecipeBuilder::new(name("sdiv"), &formats.binary, mode.pick(32, 26))
+                .operands_in(vec![gpr, gpr])
+                .operands_out(vec![gpr])
+                .emit(emit(
+                    r#"
+                        a32::put_trap_if_zero(in_reg1, func.srclocs[inst], sink);
+                        a32::put_sdiv_overflow_check(in_reg0, in_reg1, func.srclocs[inst], sink);
+                        a32::put_div(bits, in_reg0, in_reg1, out_reg0, sink);
+                    "#,
+                )),
+        );
+
+        // The remainder is computed from the quotient in the scratch register. The signed
+        // remainder of `MIN / -1` is 0, which is what `mls` computes from the wrapped quotient,
+        // so there's no other check.
+        recipes.push(
+            EncodingRecipeBuilder::new(name("rem"), &formats.binary, mode.pick(20, 16))
+                .operands_in(vec![gpr, gpr])
+                .operands_out(vec![gpr])
+                .emit(emit(
+                    r#"
+                        a32::put_trap_if_zero(in_reg1, func.srclocs[inst], sink);
+                        a32::put_div(bits, in_reg0, in_reg1, scratch(), sink);
+                        a32::put_mls(scratch(), in_reg1, in_reg0, out_reg0, sink);
+                    "#,
+                )),
+        );
+
+        // Bit counting and reversal.
+        recipes.push(
+            EncodingRecipeBuilder::new(name("bitop"), &formats.unary, 4)
+                .operands_in(vec![gpr])
+                .operands_out(vec![gpr])
+                .clobbers_flags(false)
+                .emit(emit("a32::put_bitop(bits, in_reg0, out_reg0, sink);")),
+        );
+
+        // Count trailing zeros is a count of the leading zeros of the reversed bits.
+        recipes.push(
+            EncodingRecipeBuilder::new(name("ctz"), &formats.unary, 8)
+                .operands_in(vec![gpr])
+                .operands_out(vec![gpr])
+                .clobbers_flags(false)
+                .emit(emit(
+                    r#"
+                        a32::put_bitop(a32::RBIT, in_reg0, out_reg0, sink);
+                        a32::put_bitop(a32::CLZ, out_reg0, out_reg0, sink);
+                    "#,
+                )),
+        );
+
+        // Sign and zero extensions of bytes and halfwords.
+        recipes.push(
+            EncodingRecipeBuilder::new(name("extend"), &formats.unary, 4)
+                .operands_in(vec![gpr])
+                .operands_out(vec![gpr])
+                .clobbers_flags(false)
+                .emit(emit("a32::put_extend(bits, in_reg0, out_reg0, sink);")),
+        );
+
+        // Boolean conversions only look at the lowest bit of the boolean.
+        recipes.push(
+            EncodingRecipeBuilder::new(name("bint"), &formats.unary, 4)
+                .operands_in(vec![gpr])
+                .operands_out(vec![gpr])
+                .clobbers_flags(false)
+                .emit(emit("a32::put_dp_imm(bits, in_reg0, 1, out_reg0, sink);")),
+        );
+
+        // Data processing with an 8-bit immediate, which both modes can encode unrotated.
+        recipes.push(
+            EncodingRecipeBuilder::new(name("rri"), &formats.binary_imm, 4)
+                .operands_in(vec![gpr])
+                .operands_out(vec![gpr])
+                .inst_predicate(InstructionPredicate::new_is_unsigned_int(
+                    &*formats.binary_imm,
+                    "imm",
+                    8,
+                    0,
+                ))
+                .clobbers_flags(false)
+                .emit(emit(
+                    r#"
+                        let imm: i64 = imm.into();
+                        a32::put_dp_imm(bits, in_reg0, imm as u32, out_reg0, sink);
+                    "#,
+                )),
+        );
+
+        // Add and subtract with an 8-bit immediate. Negative immediates flip between the two.
+        recipes.push(
+            EncodingRecipeBuilder::new(name("addi"), &formats.binary_imm, 4)
+                .operands_in(vec![gpr])
+                .operands_out(vec![gpr])
+                .inst_predicate(InstructionPredicate::new_is_signed_int(
+                    &*formats.binary_imm,
+                    "imm",
+                    9,
+                    0,
+                ))
+                .clobbers_flags(false)
+                .emit(emit(
+                    "a32::put_addsub_imm(bits, in_reg0, imm.into(), out_reg0, sink);",
+                )),
+        );
+
+        // Integer constants take a `movw`, and a `movt` unless the high half is zero.
+        recipes.push(
+            EncodingRecipeBuilder::new(name("iconst"), &formats.unary_imm, 4)
+                .operands_out(vec![gpr])
+                .clobbers_flags(false)
+                .compute_size("size_iconst")
+                .emit(emit(
+                    r#"
+                        let imm: i64 = imm.into();
+                        a32::put_iconst(imm as u32, out_reg0, sink);
+                    "#,
+                )),
+        );
+        recipes.push(
+            EncodingRecipeBuilder::new(name("bconst"), &formats.unary_bool, 4)
+                .operands_out(vec![gpr])
+                .clobbers_flags(false)
+                .emit(emit("a32::put_iconst(imm.into(), out_reg0, sink);")),
+        );
+
+        // Comparisons.
+
+        // An integer comparison followed by setting the result from the flags.
+        recipes.push(
+            EncodingRecipeBuilder::new(name("icmp"), &formats.int_compare, mode.pick(12, 14))
+                .operands_in(vec![gpr, gpr])
+                .operands_out(vec![gpr])
+                .emit(emit(
+                    r#"
+                        a32::put_cmp(bits, in_reg0, in_reg1, sink);
+                        a32::put_cset(icc2cond(cond), out_reg0, sink);
+                    "#,
+                )),
+        );
+        recipes.push(
+            EncodingRecipeBuilder::new(
+                name("icmp_imm"),
+                &formats.int_compare_imm,
+                mode.pick(12, 14),
+            )
+            .operands_in(vec![gpr])
+            .operands_out(vec![gpr])
+            .inst_predicate(InstructionPredicate::new_is_signed_int(
+                &*formats.int_compare_imm,
+                "imm",
+                9,
+                0,
+            ))
+            .emit(emit(
+                r#"
+                    a32::put_cmp_imm(bits, in_reg0, imm.into(), sink);
+                    a32::put_cset(icc2cond(cond), out_reg0, sink);
+                "#,
+            )),
+        );
+
+        // Integer comparisons writing the flags.
+        recipes.push(
+            EncodingRecipeBuilder::new(name("ifcmp"), &formats.binary, 4)
+                .operands_in(vec![gpr, gpr])
+                .operands_out(vec![reg_nzcv])
+                .emit(emit("a32::put_cmp(bits, in_reg0, in_reg1, sink);")),
+        );
+        recipes.push(
+            EncodingRecipeBuilder::new(name("ifcmp_imm"), &formats.binary_imm, 4)
+                .operands_in(vec![gpr])
+                .operands_out(vec![reg_nzcv])
+                .inst_predicate(InstructionPredicate::new_is_signed_int(
+                    &*formats.binary_imm,
+                    "imm",
+                    9,
+                    0,
+                ))
+                .emit(emit("a32::put_cmp_imm(bits, in_reg0, imm.into(), sink);")),
+        );
+
+        // Larger immediates are materialized in the scratch register first. The
+        // post-legalization optimizer creates `ifcmp_imm` instructions with any immediate.
+        recipes.push(
+            EncodingRecipeBuilder::new(name("ifcmp_imm_ip"), &formats.binary_imm, 4)
+                .operands_in(vec![gpr])
+                .operands_out(vec![reg_nzcv])
+                .compute_size("size_ifcmp_imm_ip")
+                .emit(emit(
+                    r#"
+                        let imm: i64 = imm.into();
+                        a32::put_iconst(imm as u32, scratch(), sink);
+                        a32::put_cmp(bits, in_reg0, scratch(), sink);
+                    "#,
+                )),
+        );
+
+        // Materialize a condition from the flags.
+        recipes.push(
+            EncodingRecipeBuilder::new(name("trueif"), &formats.int_cond, mode.pick(8, 10))
+                .operands_in(vec![reg_nzcv])
+                .operands_out(vec![gpr])
+                .clobbers_flags(false)
+                .emit(emit("a32::put_cset(icc2cond(cond), out_reg0, sink);")),
+        );
+        recipes.push(
+            EncodingRecipeBuilder::new(name("trueff"), &formats.float_cond, mode.pick(8, 10))
+                .operands_in(vec![reg_nzcv])
+                .operands_out(vec![gpr])
+                .clobbers_flags(false)
+                .inst_predicate(floatcc_predicate(&floatccs_one_cond, &*formats.float_cond))
+                .emit(emit("a32::put_cset(fcc2cond(cond).0, out_reg0, sink);")),
+        );
+        recipes.push(
+            EncodingRecipeBuilder::new(name("trueff2"), &formats.float_cond, mode.pick(12, 16))
+                .operands_in(vec![reg_nzcv])
+                .operands_out(vec![gpr])
+                .clobbers_flags(false)
+                .inst_predicate(floatcc_predicate(&floatccs_two_conds, &*formats.float_cond))
+                .emit(emit(
+                    r#"
+                        let (cond1, cond2) = fcc2cond(cond);
+                        a32::put_cset2(cond1, cond2, out_reg0, sink);
+                    "#,
+                )),
+        );
+
+        // Select based on the flags. The result is tied to the value selected when the condition
+        // doesn't hold, and the other value is moved over it when it does.
+        recipes.push(
+            EncodingRecipeBuilder::new(name("selectif"), &formats.int_select, 4)
+                .operands_in(vec![
+                    OperandConstraint::FixedReg(reg_nzcv),
+                    OperandConstraint::RegClass(gpr),
+                    OperandConstraint::RegClass(gpr),
+                ])
+                .operands_out(vec![2])
+                .clobbers_flags(false)
+                .emit(emit(
+                    "a32::put_mov_cond(icc2cond(cond), in_reg1, in_reg2, sink);",
+                )),
+        );
+
+        // Select based on a boolean, which is tested with `tst rc, #1`.
+        recipes.push(
+            EncodingRecipeBuilder::new(name("select"), &formats.ternary, 8)
+                .operands_in(vec![gpr, gpr, gpr])
+                .operands_out(vec![2])
+                .emit(emit(
+                    r#"
+                        a32::put_tst_bool(in_reg0, sink);
+                        a32::put_mov_cond(COND_NE, in_reg1, in_reg2, sink);
+                    "#,
+                )),
+        );
+        for &(suffix, fpr, reg) in &[("_s", s, "sreg"), ("_d", d, "dreg")] {
+            recipes.push(
+                EncodingRecipeBuilder::new(
+                    name(&format!("fselect{}", suffix)),
+                    &formats.ternary,
+                    mode.pick(8, 10),
+                )
+                .operands_in(vec![gpr, fpr, fpr])
+                .operands_out(vec![2])
+                .emit(emit(&format!(
+                    r#"
+                        a32::put_tst_bool(in_reg0, sink);
+                        a32::put_vfp_cond(bits, COND_NE, {0}(in_reg2), {0}(in_reg1), sink);
+                    "#,
+                    reg
+                ))),
+            );
+        }
+
+        // Memory accesses.
+
+        // Loads and stores with a base register and an immediate offset. Offsets that don't fit
+        // in the instruction are materialized in the scratch register.
+        let size_ldst = mode.pick("size_ldst", "t32_size_ldst");
+        recipes.push(
+            EncodingRecipeBuilder::new(name("ld"), &formats.load, 4)
+                .operands_in(vec![gpr])
+                .operands_out(vec![gpr])
+                .clobbers_flags(false)
+                .compute_size(size_ldst)
+                .emit(emit(
+                    r#"
+                        let offset: i32 = offset.into();
+                        let offset = a32::ldst_prepare(bits, offset.into(), sink);
+                        if !flags.notrap() {
+                            sink.trap(TrapCode::HeapOutOfBounds, func.srclocs[inst]);
+                        }
+                        a32::put_ldst(bits, in_reg0, offset, out_reg0, sink);
+                    "#,
+                )),
+        );
+        recipes.push(
+            EncodingRecipeBuilder::new(name("st"), &formats.store, 4)
+                .operands_in(vec![gpr, gpr])
+                .clobbers_flags(false)
+                .compute_size(size_ldst)
+                .emit(emit(
+                    r#"
+                        let offset: i32 = offset.into();
+                        let offset = a32::ldst_prepare(bits, offset.into(), sink);
+                        if !flags.notrap() {
+                            sink.trap(TrapCode::HeapOutOfBounds, func.srclocs[inst]);
+                        }
+                        a32::put_ldst(bits, in_reg1, offset, in_reg0, sink);
+                    "#,
+                )),
+        );
+
+        // Spills and fills address the stack slot relative to the stack pointer.
+        let size_stack_ldst = mode.pick("size_stack_ldst", "t32_size_stack_ldst");
+        recipes.push(
+            EncodingRecipeBuilder::new(name("spill"), &formats.unary, 4)
+                .operands_in(vec![gpr])
+                .operands_out(vec![stack_gpr])
+                .clobbers_flags(false)
+                .compute_size(size_stack_ldst)
+                .emit(emit(
+                    r#"
+                        let base = a32::stk_base(out_stk0.base);
+                        let offset = a32::ldst_prepare(bits, out_stk0.offset.into(), sink);
+                        sink.trap(TrapCode::StackOverflow, func.srclocs[inst]);
+                        a32::put_ldst(bits, base, offset, in_reg0, sink);
+                    "#,
+                )),
+        );
+        recipes.push(
+            EncodingRecipeBuilder::new(name("regspill"), &formats.reg_spill, 4)
+                .operands_in(vec![gpr])
+                .clobbers_flags(false)
+                .compute_size(size_stack_ldst)
+                .emit(emit(
+                    r#"
+                        let dst = stk_ref(dst, func);
+                        let base = a32::stk_base(dst.base);
+                        let offset = a32::ldst_prepare(bits, dst.offset.into(), sink);
+                        sink.trap(TrapCode::StackOverflow, func.srclocs[inst]);
+                        a32::put_ldst(bits, base, offset, src, sink);
+                    "#,
+                )),
+        );
+        recipes.push(
+            EncodingRecipeBuilder::new(name("fill"), &formats.unary, 4)
+                .operands_in(vec![stack_gpr])
+                .operands_out(vec![gpr])
+                .clobbers_flags(false)
+                .compute_size(size_stack_ldst)
+                .emit(emit(
+                    r#"
+                        let base = a32::stk_base(in_stk0.base);
+                        let offset = a32::ldst_prepare(bits, in_stk0.offset.into(), sink);
+                        a32::put_ldst(bits, base, offset, out_reg0, sink);
+                    "#,
+                )),
+        );
+        recipes.push(
+            EncodingRecipeBuilder::new(name("regfill"), &formats.reg_fill, 4)
+                .operands_in(vec![stack_gpr])
+                .clobbers_flags(false)
+                .compute_size(size_stack_ldst)
+                .emit(emit(
+                    r#"
+                        let src = stk_ref(src, func);
+                        let base = a32::stk_base(src.base);
+                        let offset = a32::ldst_prepare(bits, src.offset.into(), sink);
+                        a32::put_ldst(bits, base, offset, dst, sink);
+                    "#,
+                )),
+        );
+
+        // The floating point loads and stores have an offset of up to 1020 bytes in both modes,
+        // and larger ones compute the address in the scratch register.
+        for &(suffix, fpr, stack_fpr, reg) in
+            &[("_s", s, stack_s, "sreg"), ("_d", d, stack_d, "dreg")]
+        {
+            recipes.push(
+                EncodingRecipeBuilder::new(name(&format!("vld{}", suffix)), &formats.load, 4)
+                    .operands_in(vec![gpr])
+                    .operands_out(vec![fpr])
+                    .clobbers_flags(false)
+                    .compute_size("size_vldst")
+                    .emit(emit(&format!(
+                        r#"
+                            let offset: i32 = offset.into();
+                            let (base, offset) = a32::vldst_prepare(in_reg0, offset.into(), sink);
+                            if !flags.notrap() {{
+                                sink.trap(TrapCode::HeapOutOfBounds, func.srclocs[inst]);
+                            }}
+                            a32::put_vldst(bits, base, offset, {}(out_reg0), sink);
+                        "#,
+                        reg
+                    ))),
+            );
+            recipes.push(
+                EncodingRecipeBuilder::new(name(&format!("vst{}", suffix)), &formats.store, 4)
+                    .operands_in(vec![fpr, gpr])
+                    .clobbers_flags(false)
+                    .compute_size("size_vldst")
+                    .emit(emit(&format!(
+                        r#"
+                            let offset: i32 = offset.into();
+                            let (base, offset) = a32::vldst_prepare(in_reg1, offset.into(), sink);
+                            if !flags.notrap() {{
+                                sink.trap(TrapCode::HeapOutOfBounds, func.srclocs[inst]);
+                            }}
+                            a32::put_vldst(bits, base, offset, {}(in_reg0), sink);
+                        "#,
+                        reg
+                    ))),
+            );
+            recipes.push(
+                EncodingRecipeBuilder::new(name(&format!("vspill{}", suffix)), &formats.unary, 4)
+                    .operands_in(vec![fpr])
+                    .operands_out(vec![stack_fpr])
+                    .clobbers_flags(false)
+                    .compute_size("size_stack_vldst")
+                    .emit(emit(&format!(
+                        r#"
+                            let base = a32::stk_base(out_stk0.base);
+                            let (base, offset) = a32::vldst_prepare(base, out_stk0.offset.into(), sink);
+                            sink.trap(TrapCode::StackOverflow, func.srclocs[inst]);
+                            a32::put_vldst(bits, base, offset, {}(in_reg0), sink);
+                        "#,
+                        reg
+                    ))),
+            );
+            recipes.push(
+                EncodingRecipeBuilder::new(
+                    name(&format!("vregspill{}", suffix)),
+                    &formats.reg_spill,
+                    4,
+                )
+                .operands_in(vec![fpr])
+                .clobbers_flags(false)
+                .compute_size("size_stack_vldst")
+                .emit(emit(&format!(
+                    r#"
+                        let dst = stk_ref(dst, func);
+                        let base = a32::stk_base(dst.base);
+                        let (base, offset) = a32::vldst_prepare(base, dst.offset.into(), sink);
+                        sink.trap(TrapCode::StackOverflow, func.srclocs[inst]);
+                        a32::put_vldst(bits, base, offset, {}(src), sink);
+                    "#,
+                    reg
+                ))),
+            );
+            recipes.push(
+                EncodingRecipeBuilder::new(name(&format!("vfill{}", suffix)), &formats.unary, 4)
+                    .operands_in(vec![stack_fpr])
+                    .operands_out(vec![fpr])
+                    .clobbers_flags(false)
+                    .compute_size("size_stack_vldst")
+                    .emit(emit(&format!(
+                        r#"
+                            let base = a32::stk_base(in_stk0.base);
+                            let (base, offset) = a32::vldst_prepare(base, in_stk0.offset.into(), sink);
+                            a32::put_vldst(bits, base, offset, {}(out_reg0), sink);
+                        "#,
+                        reg
+                    ))),
+            );
+            recipes.push(
+                EncodingRecipeBuilder::new(
+                    name(&format!("vregfill{}", suffix)),
+                    &formats.reg_fill,
+                    4,
+                )
+                .operands_in(vec![stack_fpr])
+                .clobbers_flags(false)
+                .compute_size("size_stack_vldst")
+                .emit(emit(&format!(
+                    r#"
+                        let src = stk_ref(src, func);
+                        let base = a32::stk_base(src.base);
+                        let (base, offset) = a32::vldst_prepare(base, src.offset.into(), sink);
+                        a32::put_vldst(bits, base, offset, {}(dst), sink);
+                    "#,
+                    reg
+                ))),
+            );
+        }
+
+        // Address of a stack slot.
+        recipes.push(
+            EncodingRecipeBuilder::new(name("spaddr"), &formats.stack_load, 4)
+                .operands_out(vec![gpr])
+                .clobbers_flags(false)
+                .compute_size("size_stack_addr")
+                .emit(emit(
+                    r#"
+                        let sp = stk_ref(stack_slot, func);
+                        let offset: i64 = offset.into();
+                        let offset = i64::from(sp.offset) + offset;
+                        a32::put_add_imm(a32::stk_base(sp.base), offset, out_reg0, sink);
+                    "#,
+                )),
+        );
+
+        // Stack pointer adjustments for the prologue and the epilogue.
+        recipes.push(
+            EncodingRecipeBuilder::new(name("adjustsp_down"), &formats.unary_imm, 4)
+                .clobbers_flags(false)
+                .compute_size("size_adjust_sp")
+                .emit(emit(
+                    r#"
+                        let amount: i64 = imm.into();
+                        a32::put_add_imm(RU::r13.into(), -amount, RU::r13.into(), sink);
+                    "#,
+                )),
+        );
+        recipes.push(
+            EncodingRecipeBuilder::new(name("adjustsp_up"), &formats.unary_imm, 4)
+                .clobbers_flags(false)
+                .compute_size("size_adjust_sp")
+                .emit(emit(
+                    "a32::put_add_imm(RU::r13.into(), imm.into(), RU::r13.into(), sink);",
+                )),
+        );
+
+        // A copy involving the stack pointer, which is a `mov` like the other copies.
+        recipes.push(
+            EncodingRecipeBuilder::new(name("copysp"), &formats.copy_special, 4)
+                .clobbers_flags(false)
+                .emit(emit("a32::put_dp(a32::MOV, 0, src, dst, sink);")),
+        );
+
+        // Pushes and pops of single registers.
+        recipes.push(
+            EncodingRecipeBuilder::new(name("push"), &formats.unary, 4)
+                .operands_in(vec![gpr])
+                .clobbers_flags(false)
+                .emit(emit(
+                    r#"
+                        sink.trap(TrapCode::StackOverflow, func.srclocs[inst]);
+                        a32::put_push(in_reg0, sink);
+                    "#,
+                )),
+        );
+        recipes.push(
+            EncodingRecipeBuilder::new(name("pop"), &formats.nullary, 4)
+                .operands_out(vec![gpr])
+                .clobbers_flags(false)
+                .emit(emit("a32::put_pop(out_reg0, sink);")),
+        );
+        recipes.push(
+            EncodingRecipeBuilder::new(name("vpush"), &formats.unary, 4)
+                .operands_in(vec![d])
+                .clobbers_flags(false)
+                .emit(emit(
+                    r#"
+                        sink.trap(TrapCode::StackOverflow, func.srclocs[inst]);
+                        a32::put_vpush(dreg(in_reg0), sink);
+                    "#,
+                )),
+        );
+        recipes.push(
+            EncodingRecipeBuilder::new(name("vpop"), &formats.nullary, 4)
+                .operands_out(vec![d])
+                .clobbers_flags(false)
+                .emit(emit("a32::put_vpop(dreg(out_reg0), sink);")),
+        );
+
+        // Branches.
+
+        recipes.push(
+            EncodingRecipeBuilder::new(name("b"), &formats.jump, 4)
+                .branch_range(mode.pick((8, 26), (4, 25)))
+                .clobbers_flags(false)
+                .emit(emit(
+                    "a32::put_b(branch_disp(func.offsets[destination], sink), sink);",
+                )),
+        );
+
+        // Conditional branches on the flags.
+        recipes.push(
+            EncodingRecipeBuilder::new(name("brif"), &formats.branch_int, 4)
+                .operands_in(vec![reg_nzcv])
+                .branch_range(mode.pick((8, 26), (4, 21)))
+                .clobbers_flags(false)
+                .emit(emit(
+                    r#"
+                        let disp = branch_disp(func.offsets[destination], sink);
+                        a32::put_b_cond(icc2cond(cond), disp, sink);
+                    "#,
+                )),
+        );
+        recipes.push(
+            EncodingRecipeBuilder::new(name("brff"), &formats.branch_float, 4)
+                .operands_in(vec![reg_nzcv])
+                .branch_range(mode.pick((8, 26), (4, 21)))
+                .clobbers_flags(false)
+                .inst_predicate(floatcc_predicate(
+                    &floatccs_one_cond,
+                    &*formats.branch_float,
+                ))
+                .emit(emit(
+                    r#"
+                        let disp = branch_disp(func.offsets[destination], sink);
+                        a32::put_b_cond(fcc2cond(cond).0, disp, sink);
+                    "#,
+                )),
+        );
+        // The second branch is 4 bytes further from the destination, which the range accounts
+        // for by being one bit shorter.
+        recipes.push(
+            EncodingRecipeBuilder::new(name("brff2"), &formats.branch_float, 8)
+                .operands_in(vec![reg_nzcv])
+                .branch_range(mode.pick((8, 25), (4, 20)))
+                .clobbers_flags(false)
+                .inst_predicate(floatcc_predicate(
+                    &floatccs_two_conds,
+                    &*formats.branch_float,
+                ))
+                .emit(emit(
+                    r#"
+                        let (cond1, cond2) = fcc2cond(cond);
+                        let disp = branch_disp(func.offsets[destination], sink);
+                        a32::put_b_cond(cond1, disp, sink);
+                        let disp = branch_disp(func.offsets[destination], sink);
+                        a32::put_b_cond(cond2, disp, sink);
+                    "#,
+                )),
+        );
+
+        // Compare a register against zero and branch.
+        recipes.push(
+            EncodingRecipeBuilder::new(name("brz"), &formats.branch, 8)
+                .operands_in(vec![gpr])
+                .branch_range(mode.pick((12, 26), (8, 21)))
+                .emit(emit(
+                    r#"
+                        a32::put_cmp_imm(a32::CMP_IMM, in_reg0, 0, sink);
+                        let disp = branch_disp(func.offsets[destination], sink);
+                        a32::put_b_cond(u32::from(bits), disp, sink);
+                    "#,
+                )),
+        );
+
+        // Jump tables are emitted after the function body, as 32-bit offsets relative to the
+        // start of the table. The address of the table is computed relative to the PC.
+        recipes.push(
+            EncodingRecipeBuilder::new(
+                name("jt_base"),
+                &formats.branch_table_base,
+                mode.pick(12, 10),
+            )
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .emit(emit(
+                r#"
+                    let disp = branch_disp(func.jt_offsets[table], sink);
+                    a32::put_pc_relative(disp, out_reg0, sink);
+                "#,
+            )),
+        );
+        recipes.push(
+            EncodingRecipeBuilder::new(name("jt_entry"), &formats.branch_table_entry, 4)
+                .operands_in(vec![gpr, gpr])
+                .operands_out(vec![gpr])
+                .clobbers_flags(false)
+                .inst_predicate(InstructionPredicate::new_is_field_equal(
+                    &*formats.branch_table_entry,
+                    "imm",
+                    "4".into(),
+                ))
+                .emit(emit(
+                    "a32::put_ldr_scaled(in_reg0, in_reg1, out_reg0, sink);",
+                )),
+        );
+        recipes.push(
+            EncodingRecipeBuilder::new(
+                name("indirect_jmp"),
+                &formats.indirect_jump,
+                mode.pick(4, 2),
+            )
+            .operands_in(vec![gpr])
+            .clobbers_flags(false)
+            .emit(emit("a32::put_indirect_jump(in_reg0, sink);")),
+        );
+
+        // The return address is provided as a special-purpose link argument.
+        recipes.push(
+            EncodingRecipeBuilder::new(name("ret"), &formats.multiary, mode.pick(4, 2))
+                .clobbers_flags(false)
+                .emit(emit("a32::put_bx(RU::r14.into(), sink);")),
+        );
+
+        // Calls.
+
+        // `bl` to a colocated function.
+        recipes.push(
+            EncodingRecipeBuilder::new(name("call"), &formats.call, 4).emit(emit(
+                r#"
+                    a32::put_bl(&func.dfg.ext_funcs[func_ref].name, sink);
+                "#,
+            )),
+        );
+
+        // Other functions may be anywhere, so their absolute address is materialized in the
+        // scratch register.
+        recipes.push(
+            EncodingRecipeBuilder::new(name("call_abs"), &formats.call, mode.pick(12, 10)).emit(
+                emit(
+                    r#"
+                        a32::put_movw_movt(&func.dfg.ext_funcs[func_ref].name, scratch(), sink);
+                        a32::put_blx(scratch(), sink);
+                    "#,
+                ),
+            ),
+        );
+        recipes.push(
+            EncodingRecipeBuilder::new(name("call_r"), &formats.call_indirect, mode.pick(4, 2))
+                .operands_in(vec![gpr])
+                .emit(emit("a32::put_blx(in_reg0, sink);")),
+        );
+
+        // Invokes are calls that also record their landing pad at the return address.
+        recipes.push(
+            EncodingRecipeBuilder::new(name("invoke"), &formats.invoke, 4).emit(emit(
+                r#"
+                    a32::put_bl(&func.dfg.ext_funcs[func_ref].name, sink);
+                    sink.add_call_site(func.offsets[destination], func.srclocs[inst]);
+                "#,
+            )),
+        );
+        recipes.push(
+            EncodingRecipeBuilder::new(name("invoke_abs"), &formats.invoke, mode.pick(12, 10))
+                .emit(emit(
+                    r#"
+                        a32::put_movw_movt(&func.dfg.ext_funcs[func_ref].name, scratch(), sink);
+                        a32::put_blx(scratch(), sink);
+                        sink.add_call_site(func.offsets[destination], func.srclocs[inst]);
+                    "#,
+                )),
+        );
+        recipes.push(
+            EncodingRecipeBuilder::new(name("invoke_r"), &formats.invoke_indirect, mode.pick(4, 2))
+                .operands_in(vec![gpr])
+                .emit(emit(
+                    r#"
+                    a32::put_blx(in_reg0, sink);
+                    sink.add_call_site(func.offsets[destination], func.srclocs[inst]);
+                "#,
+                )),
+        );
+
+        // Absolute addresses of functions and symbols, from a `movw` and `movt` pair.
+        recipes.push(
+            EncodingRecipeBuilder::new(name("fnaddr"), &formats.func_addr, 8)
+                .operands_out(vec![gpr])
+                .clobbers_flags(false)
+                .emit(emit(
+                    r#"
+                        a32::put_movw_movt(&func.dfg.ext_funcs[func_ref].name, out_reg0, sink);
+                    "#,
+                )),
+        );
+        recipes.push(
+            EncodingRecipeBuilder::new(name("gvaddr"), &formats.unary_global_value, 8)
+                .operands_out(vec![gpr])
+                .clobbers_flags(false)
+                .emit(emit(
+                    r#"
+                        a32::put_movw_movt(
+                            &func.global_values[global_value].symbol_name(),
+                            out_reg0,
+                            sink,
+                        );
+                    "#,
+                )),
+        );
+
+        // Traps.
+
+        recipes.push(
+            EncodingRecipeBuilder::new(name("trap"), &formats.trap, mode.pick(4, 2))
+                .clobbers_flags(false)
+                .emit(emit(
+                    r#"
+                        sink.trap(code, func.srclocs[inst]);
+                        a32::put_udf(sink);
+                    "#,
+                )),
+        );
+        recipes.push(
+            EncodingRecipeBuilder::new(name("debugtrap"), &formats.nullary, mode.pick(4, 2))
+                .clobbers_flags(false)
+                .emit(emit("a32::put_bkpt(sink);")),
+        );
+
+        // Conditional traps branch over an `udf`.
+        recipes.push(
+            EncodingRecipeBuilder::new(name("trapif"), &formats.int_cond_trap, mode.pick(8, 4))
+                .operands_in(vec![reg_nzcv])
+                .clobbers_flags(false)
+                .emit(emit(
+                    "a32::put_trap_unless(icc2cond(cond) ^ 1, code, func.srclocs[inst], sink);",
+                )),
+        );
+        recipes.push(
+            EncodingRecipeBuilder::new(name("trapff"), &formats.float_cond_trap, mode.pick(8, 4))
+                .operands_in(vec![reg_nzcv])
+                .clobbers_flags(false)
+                .inst_predicate(floatcc_predicate(
+                    &floatccs_one_cond,
+                    &*formats.float_cond_trap,
+                ))
+                .emit(emit(
+                    "a32::put_trap_unless(fcc2cond(cond).0 ^ 1, code, func.srclocs[inst], sink);",
+                )),
+        );
+        recipes.push(
+            EncodingRecipeBuilder::new(name("trapff2"), &formats.float_cond_trap, mode.pick(12, 6))
+                .operands_in(vec![reg_nzcv])
+                .clobbers_flags(false)
+                .inst_predicate(floatcc_predicate(
+                    &floatccs_two_conds,
+                    &*formats.float_cond_trap,
+                ))
+                .emit(emit(
+                    r#"
+                    let (cond1, cond2) = fcc2cond(cond);
+                    a32::put_trap_if_either(cond1, cond2, code, func.srclocs[inst], sink);
+                "#,
+                )),
+        );
+        recipes.push(
+            EncodingRecipeBuilder::new(name("trapz"), &formats.cond_trap, mode.pick(12, 8))
+                .operands_in(vec![gpr])
+                .emit(emit(
+                    r#"
+                        a32::put_cmp_imm(a32::CMP_IMM, in_reg0, 0, sink);
+                        a32::put_trap_unless(u32::from(bits), code, func.srclocs[inst], sink);
+                    "#,
+                )),
+        );
+
+        // Floating point.
+
+        for &(suffix, fpr, reg) in &[("_s", s, "sreg"), ("_d", d, "dreg")] {
+            let name = |n: &str| name(&format!("{}{}", n, suffix));
+            let emit = |code: &str| emit(&code.replace("vreg", reg));
+
+            recipes.push(
+                EncodingRecipeBuilder::new(name("vfp3"), &formats.binary, 4)
+                    .operands_in(vec![fpr, fpr])
+                    .operands_out(vec![fpr])
+                    .clobbers_flags(false)
+                    .emit(emit(
+                        "a32::put_vfp(bits, vreg(out_reg0), vreg(in_reg0), vreg(in_reg1), sink);",
+                    )),
+            );
+            recipes.push(
+                EncodingRecipeBuilder::new(name("vfp2"), &formats.unary, 4)
+                    .operands_in(vec![fpr])
+                    .operands_out(vec![fpr])
+                    .clobbers_flags(false)
+                    .emit(emit(
+                        "a32::put_vfp(bits, vreg(out_reg0), NO_VREG, vreg(in_reg0), sink);",
+                    )),
+            );
+            recipes.push(
+                EncodingRecipeBuilder::new(name("vrmov"), &formats.reg_move, 4)
+                    .operands_in(vec![fpr])
+                    .clobbers_flags(false)
+                    .emit(emit(
+                        "a32::put_vfp(bits, vreg(dst), NO_VREG, vreg(src), sink);",
+                    )),
+            );
+            recipes.push(
+                EncodingRecipeBuilder::new(name("vcopytossa"), &formats.copy_to_ssa, 4)
+                    .operands_out(vec![fpr])
+                    .clobbers_flags(false)
+                    .emit(emit(
+                        "a32::put_vfp(bits, vreg(out_reg0), NO_VREG, vreg(src), sink);",
+                    )),
+            );
+
+            // Floating point comparisons set the flags in the FPSCR, which `vmrs` copies to the
+            // APSR.
+            recipes.push(
+                EncodingRecipeBuilder::new(name("ffcmp"), &formats.binary, 8)
+                    .operands_in(vec![fpr, fpr])
+                    .operands_out(vec![reg_nzcv])
+                    .emit(emit(
+                        r#"
+                            a32::put_vfp(bits, vreg(in_reg0), NO_VREG, vreg(in_reg1), sink);
+                            a32::put_vmrs(sink);
+                        "#,
+                    )),
+            );
+        }
+
+        // Conversions between single and double precision. The integer conversions also use
+        // single precision registers for the integers.
+        recipes.push(
+            EncodingRecipeBuilder::new(name("vcvt_sd"), &formats.unary, 4)
+                .operands_in(vec![s])
+                .operands_out(vec![d])
+                .clobbers_flags(false)
+                .emit(emit(
+                    "a32::put_vfp(bits, dreg(out_reg0), NO_VREG, sreg(in_reg0), sink);",
+                )),
+        );
+        recipes.push(
+            EncodingRecipeBuilder::new(name("vcvt_ds"), &formats.unary, 4)
+                .operands_in(vec![d])
+                .operands_out(vec![s])
+                .clobbers_flags(false)
+                .emit(emit(
+                    "a32::put_vfp(bits, sreg(out_reg0), NO_VREG, dreg(in_reg0), sink);",
+                )),
+        );
+
+        // Moves between the integer and floating point registers.
+        recipes.push(
+            EncodingRecipeBuilder::new(name("vmov_to_gpr"), &formats.unary, 4)
+                .operands_in(vec![s])
+                .operands_out(vec![gpr])
+                .clobbers_flags(false)
+                .emit(emit(
+                    "a32::put_vmov_core(bits, out_reg0, sreg(in_reg0), sink);",
+                )),
+        );
+        recipes.push(
+            EncodingRecipeBuilder::new(name("vmov_from_gpr"), &formats.unary, 4)
+                .operands_in(vec![gpr])
+                .operands_out(vec![s])
+                .clobbers_flags(false)
+                .emit(emit(
+                    "a32::put_vmov_core(bits, in_reg0, sreg(out_reg0), sink);",
+                )),
+        );
+        recipes.push(
+            EncodingRecipeBuilder::new(name("vmov_to_d"), &formats.binary, 4)
+                .operands_in(vec![gpr, gpr])
+                .operands_out(vec![d])
+                .clobbers_flags(false)
+                .emit(emit(
+                    "a32::put_vmov_pair(bits, in_reg0, in_reg1, dreg(out_reg0), sink);",
+                )),
+        );
+        recipes.push(
+            EncodingRecipeBuilder::new(name("vmov_from_d"), &formats.unary, 4)
+                .operands_in(vec![d])
+                .operands_out(vec![gpr, gpr])
+                .clobbers_flags(false)
+                .emit(emit(
+                    "a32::put_vmov_pair(bits, out_reg0, out_reg1, dreg(in_reg0), sink);",
+                )),
+        );
+    }
+
+    // The conditional branches of Thumb-2 only reach 1 MB, so the long forms branch over an
+    // unconditional branch with a narrow conditional branch. A32 branches all reach 32 MB.
+    recipes.push(
+        EncodingRecipeBuilder::new("t32_brif_long", &formats.branch_int, 6)
+            .operands_in(vec![reg_nzcv])
+            .branch_range((6, 25))
+            .clobbers_flags(false)
+            .emit(
+                r#"
+                    t32::put_b_cond_narrow(icc2cond(cond) ^ 1, 6, sink);
+                    t32::put_b(branch_disp(func.offsets[destination], sink), sink);
+                "#,
+            ),
+    );
+    recipes.push(
+        EncodingRecipeBuilder::new("t32_brff_long", &formats.branch_float, 6)
+            .operands_in(vec![reg_nzcv])
+            .branch_range((6, 25))
+            .clobbers_flags(false)
+            .inst_predicate(floatcc_predicate(
+                &floatccs_one_cond,
+                &*formats.branch_float,
+            ))
+            .emit(
+                r#"
+                    t32::put_b_cond_narrow(fcc2cond(cond).0 ^ 1, 6, sink);
+                    t32::put_b(branch_disp(func.offsets[destination], sink), sink);
+                "#,
+            ),
+    );
+    recipes.push(
+        EncodingRecipeBuilder::new("t32_brff2_long", &formats.branch_float, 8)
+            .operands_in(vec![reg_nzcv])
+            .branch_range((8, 25))
+            .clobbers_flags(false)
+            .inst_predicate(floatcc_predicate(
+                &floatccs_two_conds,
+                &*formats.branch_float,
+            ))
+            .emit(
+                r#"
+                    let (cond1, cond2) = fcc2cond(cond);
+                    t32::put_b_cond_narrow(cond1, 4, sink);
+                    t32::put_b_cond_narrow(cond2 ^ 1, 6, sink);
+                    t32::put_b(branch_disp(func.offsets[destination], sink), sink);
+                "#,
+            ),
+    );
+    recipes.push(
+        EncodingRecipeBuilder::new("t32_brz_long", &formats.branch, 10)
+            .operands_in(vec![gpr])
+            .branch_range((10, 25))
+            .emit(
+                r#"
+                    t32::put_cmp_imm(t32::CMP_IMM, in_reg0, 0, sink);
+                    t32::put_b_cond_narrow(u32::from(bits) ^ 1, 6, sink);
+                    t32::put_b(branch_disp(func.offsets[destination], sink), sink);
+                "#,
+            ),
+    );
+
+    recipes
+}
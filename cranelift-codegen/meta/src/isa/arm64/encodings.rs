@@ -0,0 +1,1168 @@
+use crate::cdsl::ast::Literal;
+use crate::cdsl::encodings::{Encoding, EncodingBuilder};
+use crate::cdsl::instructions::BindParameter::Any;
+use crate::cdsl::instructions::{
+    vector, Bindable, InstSpec, InstructionGroup, InstructionPredicate,
+    InstructionPredicateRegistry,
+};
+use crate::cdsl::recipes::{EncodingRecipeNumber, Recipes};
+use crate::cdsl::settings::SettingGroup;
+use crate::cdsl::types::{LaneType, ValueType};
+
+use crate::shared::types::Bool::B1;
+use crate::shared::types::Float::{F32, F64};
+use crate::shared::types::Int::{I16, I32, I64, I8};
+use crate::shared::types::Reference::R64;
+use crate::shared::Definitions as SharedDefinitions;
+
+use super::recipes::RecipeGroup;
+
+pub(crate) struct PerCpuModeEncodings<'defs> {
+    pub inst_pred_reg: InstructionPredicateRegistry,
+    pub enc64: Vec<Encoding>,
+    recipes: &'defs Recipes,
+}
+
+impl<'defs> PerCpuModeEncodings<'defs> {
+    fn new(recipes: &'defs Recipes) -> Self {
+        Self {
+            inst_pred_reg: InstructionPredicateRegistry::new(),
+            enc64: Vec::new(),
+            recipes,
+        }
+    }
+    fn enc(
+        &self,
+        inst: impl Into<InstSpec>,
+        recipe: EncodingRecipeNumber,
+        bits: u16,
+    ) -> EncodingBuilder {
+        EncodingBuilder::new(inst.into(), recipe, bits)
+    }
+    fn add64(&mut self, encoding: EncodingBuilder) {
+        self.enc64
+            .push(encoding.build(self.recipes, &mut self.inst_pred_reg));
+    }
+}
+
+// All A64 instructions are 32 bits wide, which doesn't fit in the 16 encoding bits. Each recipe
+// family keeps the part of the instruction word that identifies the operation, and the recipe
+// fills in the operands. The functions below extract the encoding bits from a full instruction
+// word with all operand fields zeroed, so the words can be checked against the manual.
+
+/// Three-register data processing: `word[31:21]`.
+fn rrr_bits(word: u32) -> u16 {
+    assert_eq!(word & 0x1f_ffff, 0);
+    (word >> 21) as u16
+}
+
+/// Add and subtract with an immediate: `word[31:23]`.
+fn addsub_imm_bits(word: u32) -> u16 {
+    assert_eq!(word & 0x7f_ffff, 0);
+    (word >> 23) as u16
+}
+
+/// Bitfield moves, `extr` and loads and stores with an unsigned offset: `word[31:22]`.
+fn top10_bits(word: u32) -> u16 {
+    assert_eq!(word & 0x3f_ffff, 0);
+    (word >> 22) as u16
+}
+
+/// Pre- and post-indexed loads and stores of a single register: `word[31:21]`.
+fn indexed_bits(word: u32) -> u16 {
+    rrr_bits(word)
+}
+
+/// Branches with an immediate displacement: `word[31:26]`.
+fn b_bits(word: u32) -> u16 {
+    assert_eq!(word & 0x3ff_ffff, 0);
+    (word >> 26) as u16
+}
+
+/// Compare and branch: `word[31:24]`.
+fn cb_bits(word: u32) -> u16 {
+    assert_eq!(word & 0xff_ffff, 0);
+    (word >> 24) as u16
+}
+
+/// Branches to a register: `word[31:16]`.
+fn br_bits(word: u32) -> u16 {
+    assert_eq!(word & 0xffff, 0);
+    (word >> 16) as u16
+}
+
+/// Data processing with one or two sources: `sf | opcode`.
+fn dp_bits(sf: u16, opcode: u16) -> u16 {
+    assert!(sf <= 1 && opcode <= 0b11_1111);
+    (sf << 6) | opcode
+}
+
+/// Data processing with three sources: `sf | op31 | o0`.
+fn dp3_bits(sf: u16, op31: u16, o0: u16) -> u16 {
+    assert!(sf <= 1 && op31 <= 0b111 && o0 <= 1);
+    (sf << 4) | (op31 << 1) | o0
+}
+
+/// Floating point data processing with one source: `ftype | opcode`.
+fn fp1_bits(ftype: u16, opcode: u16) -> u16 {
+    assert!(ftype <= 0b11 && opcode <= 0b11_1111);
+    (ftype << 6) | opcode
+}
+
+/// Floating point data processing with two sources: `ftype | opcode`.
+fn fp2_bits(ftype: u16, opcode: u16) -> u16 {
+    assert!(ftype <= 0b11 && opcode <= 0b1111);
+    (ftype << 4) | opcode
+}
+
+/// Conversions between floating point and integer: `sf | ftype | rmode | opcode`.
+fn fpint_bits(sf: u16, ftype: u16, rmode: u16, opcode: u16) -> u16 {
+    assert!(sf <= 1 && ftype <= 0b11 && rmode <= 0b11 && opcode <= 0b111);
+    (sf << 7) | (ftype << 5) | (rmode << 3) | opcode
+}
+
+/// SIMD three same and two-register miscellaneous: `Q | U | size | opcode`.
+fn simd_bits(q: u16, u: u16, size: u16, opcode: u16) -> u16 {
+    assert!(q <= 1 && u <= 1 && size <= 0b11 && opcode <= 0b1_1111);
+    (q << 8) | (u << 7) | (size << 5) | opcode
+}
+
+/// `movi` with the 8-bit immediate expanded into bytes: `Q | imm8`.
+fn movi_bits(q: u16, imm8: u16) -> u16 {
+    assert!(q <= 1 && imm8 <= 0xff);
+    (q << 8) | imm8
+}
+
+/// The `sf` bit selecting the 64-bit variant of an integer instruction.
+/// The `ftype` field of a scalar floating point instruction.
+fn ftype(ty: impl Into<LaneType>) -> u16 {
+    let ty = ty.into();
+    match ty.lane_bits() {
+        32 => 0,
+        64 => 1,
+        _ => panic!("unexpected floating point type {}", ty),
+    }
+}
+
+/// The log2 of the lane size in bytes, which is the `size` field of the SIMD instructions.
+fn lane_size(ty: LaneType) -> u16 {
+    match ty.lane_bits() {
+        8 => 0,
+        16 => 1,
+        32 => 2,
+        64 => 3,
+        _ => panic!("unexpected lane type {}", ty),
+    }
+}
+
+#[allow(clippy::cognitive_complexity)]
+pub(crate) fn define<'defs>(
+    shared_defs: &'defs SharedDefinitions,
+    isa_settings: &SettingGroup,
+    arm64: &InstructionGroup,
+    recipes: &'defs RecipeGroup,
+) -> PerCpuModeEncodings<'defs> {
+    // Instructions shorthands.
+    let shared = &shared_defs.instructions;
+    let formats = &shared_defs.formats;
+    let imm = &shared_defs.imm;
+
+    let adjust_sp_down_imm = shared.by_name("adjust_sp_down_imm");
+    let adjust_sp_up_imm = shared.by_name("adjust_sp_up_imm");
+    let band = shared.by_name("band");
+    let band_not = shared.by_name("band_not");
+    let bconst = shared.by_name("bconst");
+    let bint = shared.by_name("bint");
+    let bitcast = shared.by_name("bitcast");
+    let bitrev = shared.by_name("bitrev");
+    let bmask = shared.by_name("bmask");
+    let bnot = shared.by_name("bnot");
+    let bor = shared.by_name("bor");
+    let bor_not = shared.by_name("bor_not");
+    let brff = shared.by_name("brff");
+    let brif = shared.by_name("brif");
+    let brnz = shared.by_name("brnz");
+    let brz = shared.by_name("brz");
+    let bxor = shared.by_name("bxor");
+    let bxor_not = shared.by_name("bxor_not");
+    let call = shared.by_name("call");
+    let call_indirect = shared.by_name("call_indirect");
+    let ceil = shared.by_name("ceil");
+    let cls = shared.by_name("cls");
+    let clz = shared.by_name("clz");
+    let copy = shared.by_name("copy");
+    let copy_nop = shared.by_name("copy_nop");
+    let copy_special = shared.by_name("copy_special");
+    let copy_to_ssa = shared.by_name("copy_to_ssa");
+    let ctz = shared.by_name("ctz");
+    let debugtrap = shared.by_name("debugtrap");
+    let extractlane = shared.by_name("extractlane");
+    let f32const = shared.by_name("f32const");
+    let f64const = shared.by_name("f64const");
+    let fabs = shared.by_name("fabs");
+    let fadd = shared.by_name("fadd");
+    let fcmp = shared.by_name("fcmp");
+    let fcvt_from_sint = shared.by_name("fcvt_from_sint");
+    let fcvt_from_uint = shared.by_name("fcvt_from_uint");
+    let fcvt_to_sint_sat = shared.by_name("fcvt_to_sint_sat");
+    let fcvt_to_uint_sat = shared.by_name("fcvt_to_uint_sat");
+    let fdemote = shared.by_name("fdemote");
+    let fdiv = shared.by_name("fdiv");
+    let ffcmp = shared.by_name("ffcmp");
+    let fill = shared.by_name("fill");
+    let fill_nop = shared.by_name("fill_nop");
+    let floor = shared.by_name("floor");
+    let fma = shared.by_name("fma");
+    let fmax = shared.by_name("fmax");
+    let fmin = shared.by_name("fmin");
+    let fmul = shared.by_name("fmul");
+    let fneg = shared.by_name("fneg");
+    let fpromote = shared.by_name("fpromote");
+    let fsub = shared.by_name("fsub");
+    let func_addr = shared.by_name("func_addr");
+    let get_pinned_reg = shared.by_name("get_pinned_reg");
+    let iadd = shared.by_name("iadd");
+    let iadd_ifcarry = shared.by_name("iadd_ifcarry");
+    let iadd_ifcin = shared.by_name("iadd_ifcin");
+    let iadd_ifcout = shared.by_name("iadd_ifcout");
+    let iadd_imm = shared.by_name("iadd_imm");
+    let icmp = shared.by_name("icmp");
+    let icmp_imm = shared.by_name("icmp_imm");
+    let iconst = shared.by_name("iconst");
+    let ifcmp = shared.by_name("ifcmp");
+    let ifcmp_imm = shared.by_name("ifcmp_imm");
+    let imul = shared.by_name("imul");
+    let indirect_jump_table_br = shared.by_name("indirect_jump_table_br");
+    let ineg = shared.by_name("ineg");
+    let insertlane = shared.by_name("insertlane");
+    let invoke = shared.by_name("invoke");
+    let invoke_indirect = shared.by_name("invoke_indirect");
+    let ireduce = shared.by_name("ireduce");
+    let ishl = shared.by_name("ishl");
+    let ishl_imm = shared.by_name("ishl_imm");
+    let istore16 = shared.by_name("istore16");
+    let istore32 = shared.by_name("istore32");
+    let istore8 = shared.by_name("istore8");
+    let isub = shared.by_name("isub");
+    let isub_ifbin = shared.by_name("isub_ifbin");
+    let isub_ifborrow = shared.by_name("isub_ifborrow");
+    let isub_ifbout = shared.by_name("isub_ifbout");
+    let jump = shared.by_name("jump");
+    let jump_table_base = shared.by_name("jump_table_base");
+    let jump_table_entry = shared.by_name("jump_table_entry");
+    let landing_pad = shared.by_name("landing_pad");
+    let load = shared.by_name("load");
+    let nearest = shared.by_name("nearest");
+    let raw_bitcast = shared.by_name("raw_bitcast");
+    let regfill = shared.by_name("regfill");
+    let regmove = shared.by_name("regmove");
+    let regspill = shared.by_name("regspill");
+    let resumable_trap = shared.by_name("resumable_trap");
+    let return_ = shared.by_name("return");
+    let rotl_imm = shared.by_name("rotl_imm");
+    let rotr = shared.by_name("rotr");
+    let rotr_imm = shared.by_name("rotr_imm");
+    let sadd_sat = shared.by_name("sadd_sat");
+    let safepoint = shared.by_name("safepoint");
+    let scalar_to_vector = shared.by_name("scalar_to_vector");
+    let sdiv = shared.by_name("sdiv");
+    let select = shared.by_name("select");
+    let selectif = shared.by_name("selectif");
+    let set_pinned_reg = shared.by_name("set_pinned_reg");
+    let sextend = shared.by_name("sextend");
+    let sload16 = shared.by_name("sload16");
+    let sload32 = shared.by_name("sload32");
+    let sload8 = shared.by_name("sload8");
+    let smulhi = shared.by_name("smulhi");
+    let spill = shared.by_name("spill");
+    let splat = shared.by_name("splat");
+    let sqrt = shared.by_name("sqrt");
+    let srem = shared.by_name("srem");
+    let sshr = shared.by_name("sshr");
+    let sshr_imm = shared.by_name("sshr_imm");
+    let ssub_sat = shared.by_name("ssub_sat");
+    let stack_addr = shared.by_name("stack_addr");
+    let store = shared.by_name("store");
+    let symbol_value = shared.by_name("symbol_value");
+    let trap = shared.by_name("trap");
+    let trapff = shared.by_name("trapff");
+    let trapif = shared.by_name("trapif");
+    let trapnz = shared.by_name("trapnz");
+    let trapz = shared.by_name("trapz");
+    let trueff = shared.by_name("trueff");
+    let trueif = shared.by_name("trueif");
+    let trunc = shared.by_name("trunc");
+    let uadd_sat = shared.by_name("uadd_sat");
+    let udiv = shared.by_name("udiv");
+    let uextend = shared.by_name("uextend");
+    let uload16 = shared.by_name("uload16");
+    let uload32 = shared.by_name("uload32");
+    let uload8 = shared.by_name("uload8");
+    let umulhi = shared.by_name("umulhi");
+    let urem = shared.by_name("urem");
+    let ushr = shared.by_name("ushr");
+    let ushr_imm = shared.by_name("ushr_imm");
+    let usub_sat = shared.by_name("usub_sat");
+    let vconst = shared.by_name("vconst");
+
+    let arm64_pop = arm64.by_name("arm64_pop");
+    let arm64_pop_pair = arm64.by_name("arm64_pop_pair");
+    let arm64_push = arm64.by_name("arm64_push");
+    let arm64_push_pair = arm64.by_name("arm64_push_pair");
+
+    // Recipes shorthands, prefixed with r_.
+    let r_abs_fnaddr = recipes.by_name("abs_fnaddr");
+    let r_abs_gvaddr = recipes.by_name("abs_gvaddr");
+    let r_addi = recipes.by_name("addi");
+    let r_adjustsp_down = recipes.by_name("adjustsp_down");
+    let r_adjustsp_up = recipes.by_name("adjustsp_up");
+    let r_b = recipes.by_name("b");
+    let r_bconst = recipes.by_name("bconst");
+    let r_bint = recipes.by_name("bint");
+    let r_brff = recipes.by_name("brff");
+    let r_brff2 = recipes.by_name("brff2");
+    let r_brff2_long = recipes.by_name("brff2_long");
+    let r_brff_long = recipes.by_name("brff_long");
+    let r_brif = recipes.by_name("brif");
+    let r_brif_long = recipes.by_name("brif_long");
+    let r_call = recipes.by_name("call");
+    let r_call_r = recipes.by_name("call_r");
+    let r_cbz = recipes.by_name("cbz");
+    let r_cbz_long = recipes.by_name("cbz_long");
+    let r_copysp = recipes.by_name("copysp");
+    let r_copytossa = recipes.by_name("copytossa");
+    let r_ctz = recipes.by_name("ctz");
+    let r_debugtrap = recipes.by_name("debugtrap");
+    let r_div = recipes.by_name("div");
+    let r_dp1 = recipes.by_name("dp1");
+    let r_dp2 = recipes.by_name("dp2");
+    let r_dp3 = recipes.by_name("dp3");
+    let r_extend = recipes.by_name("extend");
+    let r_extract_fpr = recipes.by_name("extract_fpr");
+    let r_extract_gpr = recipes.by_name("extract_gpr");
+    let r_f32zero = recipes.by_name("f32zero");
+    let r_f64zero = recipes.by_name("f64zero");
+    let r_fcmp = recipes.by_name("fcmp");
+    let r_fcmp2 = recipes.by_name("fcmp2");
+    let r_fcopytossa = recipes.by_name("fcopytossa");
+    let r_ffcmp = recipes.by_name("ffcmp");
+    let r_ffill = recipes.by_name("ffill");
+    let r_ffillnull = recipes.by_name("ffillnull");
+    let r_fill = recipes.by_name("fill");
+    let r_fillnull = recipes.by_name("fillnull");
+    let r_fld = recipes.by_name("fld");
+    let r_fmov = recipes.by_name("fmov");
+    let r_fnaddr = recipes.by_name("fnaddr");
+    let r_fp1 = recipes.by_name("fp1");
+    let r_fp2 = recipes.by_name("fp2");
+    let r_fp3 = recipes.by_name("fp3");
+    let r_fpint = recipes.by_name("fpint");
+    let r_fpop = recipes.by_name("fpop");
+    let r_fpopp = recipes.by_name("fpopp");
+    let r_fpush = recipes.by_name("fpush");
+    let r_fpushp = recipes.by_name("fpushp");
+    let r_fregfill = recipes.by_name("fregfill");
+    let r_fregspill = recipes.by_name("fregspill");
+    let r_frmov = recipes.by_name("frmov");
+    let r_fselect = recipes.by_name("fselect");
+    let r_fspill = recipes.by_name("fspill");
+    let r_fst = recipes.by_name("fst");
+    let r_get_pinned_reg = recipes.by_name("get_pinned_reg");
+    let r_got_fnaddr = recipes.by_name("got_fnaddr");
+    let r_got_gvaddr = recipes.by_name("got_gvaddr");
+    let r_gvaddr = recipes.by_name("gvaddr");
+    let r_icmp = recipes.by_name("icmp");
+    let r_icmp_imm = recipes.by_name("icmp_imm");
+    let r_iconst = recipes.by_name("iconst");
+    let r_ifcmp = recipes.by_name("ifcmp");
+    let r_ifcmp_imm = recipes.by_name("ifcmp_imm");
+    let r_ifcmp_imm_x16 = recipes.by_name("ifcmp_imm_x16");
+    let r_indirect_jmp = recipes.by_name("indirect_jmp");
+    let r_insert_fpr = recipes.by_name("insert_fpr");
+    let r_insert_gpr = recipes.by_name("insert_gpr");
+    let r_intfp = recipes.by_name("intfp");
+    let r_invoke = recipes.by_name("invoke");
+    let r_invoke_r = recipes.by_name("invoke_r");
+    let r_jt_base = recipes.by_name("jt_base");
+    let r_jt_entry = recipes.by_name("jt_entry");
+    let r_landing_pad = recipes.by_name("landing_pad");
+    let r_ld = recipes.by_name("ld");
+    let r_lsl_imm = recipes.by_name("lsl_imm");
+    let r_mov = recipes.by_name("mov");
+    let r_mulhi32 = recipes.by_name("mulhi32");
+    let r_null = recipes.by_name("null");
+    let r_null_fpr = recipes.by_name("null_fpr");
+    let r_pop = recipes.by_name("pop");
+    let r_popp = recipes.by_name("popp");
+    let r_push = recipes.by_name("push");
+    let r_pushp = recipes.by_name("pushp");
+    let r_regfill = recipes.by_name("regfill");
+    let r_regspill = recipes.by_name("regspill");
+    let r_rem = recipes.by_name("rem");
+    let r_ret = recipes.by_name("ret");
+    let r_rmov = recipes.by_name("rmov");
+    let r_rol_imm = recipes.by_name("rol_imm");
+    let r_ror_imm = recipes.by_name("ror_imm");
+    let r_rrr = recipes.by_name("rrr");
+    let r_rrr_fin = recipes.by_name("rrr_fin");
+    let r_rrr_fio = recipes.by_name("rrr_fio");
+    let r_rrr_fout = recipes.by_name("rrr_fout");
+    let r_safepoint = recipes.by_name("safepoint");
+    let r_sdiv = recipes.by_name("sdiv");
+    let r_select = recipes.by_name("select");
+    let r_selectif = recipes.by_name("selectif");
+    let r_set_pinned_reg = recipes.by_name("set_pinned_reg");
+    let r_shr_imm = recipes.by_name("shr_imm");
+    let r_spaddr = recipes.by_name("spaddr");
+    let r_spill = recipes.by_name("spill");
+    let r_splat_fpr = recipes.by_name("splat_fpr");
+    let r_splat_gpr = recipes.by_name("splat_gpr");
+    let r_st = recipes.by_name("st");
+    let r_stacknull = recipes.by_name("stacknull");
+    let r_trap = recipes.by_name("trap");
+    let r_trapff = recipes.by_name("trapff");
+    let r_trapff2 = recipes.by_name("trapff2");
+    let r_trapif = recipes.by_name("trapif");
+    let r_trapz = recipes.by_name("trapz");
+    let r_trueff = recipes.by_name("trueff");
+    let r_trueff2 = recipes.by_name("trueff2");
+    let r_trueif = recipes.by_name("trueif");
+    let r_vconst = recipes.by_name("vconst");
+    let r_vfcmp = recipes.by_name("vfcmp");
+    let r_vicmp = recipes.by_name("vicmp");
+    let r_vmisc = recipes.by_name("vmisc");
+    let r_vones = recipes.by_name("vones");
+    let r_vrrr = recipes.by_name("vrrr");
+    let r_vzero = recipes.by_name("vzero");
+
+    // Predicates shorthands.
+    let is_pic = isa_settings.predicate_by_name("is_pic");
+    let not_is_pic = isa_settings.predicate_by_name("not_is_pic");
+    let use_simd = isa_settings.predicate_by_name("use_simd");
+
+    // Definitions.
+    let mut e = PerCpuModeEncodings::new(&recipes.recipes);
+
+    // Integer arithmetic and logic. The 32-bit variants have `sf` (bit 31) cleared.
+    for &(inst, word) in &[
+        (iadd, 0x8b00_0000),
+        (isub, 0xcb00_0000),
+        (band, 0x8a00_0000),
+        (bor, 0xaa00_0000),
+        (bxor, 0xca00_0000),
+        (band_not, 0x8a20_0000),
+        (bor_not, 0xaa20_0000),
+        (bxor_not, 0xca20_0000),
+    ] {
+        e.add64(e.enc(inst.bind(I64), r_rrr, rrr_bits(word)));
+        e.add64(e.enc(inst.bind(I32), r_rrr, rrr_bits(word & !(1 << 31))));
+    }
+    // Booleans are 0 or 1, which the 32-bit logical instructions preserve.
+    for &(inst, word) in &[(band, 0x0a00_0000), (bor, 0x2a00_0000), (bxor, 0x4a00_0000)] {
+        e.add64(e.enc(inst.bind(B1), r_rrr, rrr_bits(word)));
+    }
+
+    // Carries and borrows live in the C flag. A subtraction sets it when it doesn't borrow,
+    // which `sbc` accounts for.
+    for &(inst, recipe, word) in &[
+        (iadd_ifcout, r_rrr_fout, 0xab00_0000),
+        (isub_ifbout, r_rrr_fout, 0xeb00_0000),
+        (iadd_ifcin, r_rrr_fin, 0x9a00_0000),
+        (isub_ifbin, r_rrr_fin, 0xda00_0000),
+        (iadd_ifcarry, r_rrr_fio, 0xba00_0000),
+        (isub_ifborrow, r_rrr_fio, 0xfa00_0000),
+    ] {
+        e.add64(e.enc(inst.bind(I64), recipe, rrr_bits(word)));
+        e.add64(e.enc(inst.bind(I32), recipe, rrr_bits(word & !(1 << 31))));
+    }
+
+    // Register copies are `orr` from the zero register, and `neg` and `mvn` are `sub` and
+    // `orn` from it.
+    e.add64(e.enc(copy.bind(I64), r_mov, rrr_bits(0xaa00_0000)));
+    e.add64(e.enc(copy.bind(R64), r_mov, rrr_bits(0xaa00_0000)));
+    e.add64(e.enc(regmove.bind(I64), r_rmov, rrr_bits(0xaa00_0000)));
+    e.add64(e.enc(regmove.bind(R64), r_rmov, rrr_bits(0xaa00_0000)));
+    e.add64(e.enc(copy_to_ssa.bind(I64), r_copytossa, rrr_bits(0xaa00_0000)));
+    e.add64(e.enc(copy_to_ssa.bind(R64), r_copytossa, rrr_bits(0xaa00_0000)));
+    for &ty in &[I32, I16, I8] {
+        e.add64(e.enc(copy.bind(ty), r_mov, rrr_bits(0x2a00_0000)));
+        e.add64(e.enc(regmove.bind(ty), r_rmov, rrr_bits(0x2a00_0000)));
+        e.add64(e.enc(copy_to_ssa.bind(ty), r_copytossa, rrr_bits(0x2a00_0000)));
+    }
+    e.add64(e.enc(copy.bind(B1), r_mov, rrr_bits(0x2a00_0000)));
+    e.add64(e.enc(regmove.bind(B1), r_rmov, rrr_bits(0x2a00_0000)));
+    e.add64(e.enc(copy_to_ssa.bind(B1), r_copytossa, rrr_bits(0x2a00_0000)));
+
+    e.add64(e.enc(ineg.bind(I64), r_mov, rrr_bits(0xcb00_0000)));
+    e.add64(e.enc(ineg.bind(I32), r_mov, rrr_bits(0x4b00_0000)));
+    e.add64(e.enc(bnot.bind(I64), r_mov, rrr_bits(0xaa20_0000)));
+    e.add64(e.enc(bnot.bind(I32), r_mov, rrr_bits(0x2a20_0000)));
+
+    // Variable shifts and rotates. The amount is taken modulo the register width.
+    for &(inst, opcode) in &[
+        (ishl, 0b1000),
+        (ushr, 0b1001),
+        (sshr, 0b1010),
+        (rotr, 0b1011),
+    ] {
+        e.add64(e.enc(inst.bind(I64).bind(I64), r_dp2, dp_bits(1, opcode)));
+        e.add64(e.enc(inst.bind(I64).bind(I32), r_dp2, dp_bits(1, opcode)));
+        e.add64(e.enc(inst.bind(I32).bind(I64), r_dp2, dp_bits(0, opcode)));
+        e.add64(e.enc(inst.bind(I32).bind(I32), r_dp2, dp_bits(0, opcode)));
+    }
+
+    // Shifts and rotates by an immediate.
+    for &ty in &[I32, I64] {
+        let (ubfm, sbfm, extr) = if ty == I64 {
+            (0xd340_0000, 0x9340_0000, 0x93c0_0000)
+        } else {
+            (0x5300_0000, 0x1300_0000, 0x1380_0000)
+        };
+        e.add64(e.enc(ishl_imm.bind(ty), r_lsl_imm, top10_bits(ubfm)));
+        e.add64(e.enc(ushr_imm.bind(ty), r_shr_imm, top10_bits(ubfm)));
+        e.add64(e.enc(sshr_imm.bind(ty), r_shr_imm, top10_bits(sbfm)));
+        e.add64(e.enc(rotr_imm.bind(ty), r_ror_imm, top10_bits(extr)));
+        e.add64(e.enc(rotl_imm.bind(ty), r_rol_imm, top10_bits(extr)));
+
+        // Booleans are converted by extracting their lowest bit.
+        e.add64(e.enc(bint.bind(ty).bind(B1), r_bint, top10_bits(ubfm)));
+        e.add64(e.enc(bmask.bind(ty).bind(B1), r_bint, top10_bits(sbfm)));
+    }
+
+    // Extensions are bitfield moves from bit 0.
+    for &from in &[I8, I16, I32] {
+        e.add64(e.enc(
+            uextend.bind(I64).bind(from),
+            r_extend,
+            top10_bits(0xd340_0000),
+        ));
+        e.add64(e.enc(
+            sextend.bind(I64).bind(from),
+            r_extend,
+            top10_bits(0x9340_0000),
+        ));
+    }
+    for &from in &[I8, I16] {
+        e.add64(e.enc(
+            uextend.bind(I32).bind(from),
+            r_extend,
+            top10_bits(0x5300_0000),
+        ));
+        e.add64(e.enc(
+            sextend.bind(I32).bind(from),
+            r_extend,
+            top10_bits(0x1300_0000),
+        ));
+    }
+
+    // A reduction only reads the low bits of the register.
+    for &(to, from) in &[
+        (I8, I16),
+        (I8, I32),
+        (I8, I64),
+        (I16, I32),
+        (I16, I64),
+        (I32, I64),
+    ] {
+        e.add64(e.enc(ireduce.bind(to).bind(from), r_null, 0));
+    }
+
+    // Multiplication and division.
+    e.add64(e.enc(imul.bind(I64), r_dp3, dp3_bits(1, 0b000, 0)));
+    e.add64(e.enc(imul.bind(I32), r_dp3, dp3_bits(0, 0b000, 0)));
+    e.add64(e.enc(smulhi.bind(I64), r_dp3, dp3_bits(1, 0b010, 0)));
+    e.add64(e.enc(umulhi.bind(I64), r_dp3, dp3_bits(1, 0b110, 0)));
+    e.add64(e.enc(smulhi.bind(I32), r_mulhi32, dp3_bits(1, 0b001, 0)));
+    e.add64(e.enc(umulhi.bind(I32), r_mulhi32, dp3_bits(1, 0b101, 0)));
+
+    for &(ty, sf) in &[(I64, 1), (I32, 0)] {
+        e.add64(e.enc(udiv.bind(ty), r_div, dp_bits(sf, 0b10)));
+        e.add64(e.enc(sdiv.bind(ty), r_sdiv, dp_bits(sf, 0b11)));
+        e.add64(e.enc(urem.bind(ty), r_rem, dp_bits(sf, 0b10)));
+        e.add64(e.enc(srem.bind(ty), r_rem, dp_bits(sf, 0b11)));
+
+        // Bit counting. `ctz` is `rbit` followed by `clz`.
+        e.add64(e.enc(bitrev.bind(ty), r_dp1, dp_bits(sf, 0b000)));
+        e.add64(e.enc(clz.bind(ty), r_dp1, dp_bits(sf, 0b100)));
+        e.add64(e.enc(cls.bind(ty), r_dp1, dp_bits(sf, 0b101)));
+        e.add64(e.enc(ctz.bind(ty), r_ctz, dp_bits(sf, 0b000)));
+    }
+
+    // Immediates.
+    e.add64(e.enc(iadd_imm.bind(I64), r_addi, addsub_imm_bits(0x9100_0000)));
+    e.add64(e.enc(iadd_imm.bind(I32), r_addi, addsub_imm_bits(0x1100_0000)));
+    e.add64(e.enc(iconst.bind(I64), r_iconst, 1));
+    e.add64(e.enc(iconst.bind(I32), r_iconst, 0));
+    e.add64(e.enc(bconst.bind(B1), r_bconst, 0));
+
+    // Comparisons are `subs` to the zero register.
+    for &(ty, subs, subs_imm) in &[
+        (I64, 0xeb00_0000, 0xf100_0000),
+        (I32, 0x6b00_0000, 0x7100_0000),
+    ] {
+        e.add64(e.enc(icmp.bind(ty), r_icmp, rrr_bits(subs)));
+        e.add64(e.enc(icmp_imm.bind(ty), r_icmp_imm, addsub_imm_bits(subs_imm)));
+        e.add64(e.enc(ifcmp.bind(ty), r_ifcmp, rrr_bits(subs)));
+        e.add64(e.enc(ifcmp_imm.bind(ty), r_ifcmp_imm, addsub_imm_bits(subs_imm)));
+        e.add64(e.enc(ifcmp_imm.bind(ty), r_ifcmp_imm_x16, rrr_bits(subs)));
+    }
+
+    e.add64(e.enc(trueif, r_trueif, 0));
+    e.add64(e.enc(trueff, r_trueff, 0));
+    e.add64(e.enc(trueff, r_trueff2, 0));
+
+    e.add64(e.enc(selectif.bind(I64), r_selectif, 1));
+    e.add64(e.enc(selectif.bind(I32), r_selectif, 0));
+    e.add64(e.enc(select.bind(I64).bind(B1), r_select, 1));
+    e.add64(e.enc(select.bind(I32).bind(B1), r_select, 0));
+    e.add64(e.enc(select.bind(B1).bind(B1), r_select, 0));
+    e.add64(e.enc(select.bind(F64).bind(B1), r_fselect, ftype(F64)));
+    e.add64(e.enc(select.bind(F32).bind(B1), r_fselect, ftype(F32)));
+
+    // Loads and stores.
+    for &(inst, ty, recipe, word) in &[
+        (load, I64, r_ld, 0xf940_0000),
+        (load, I32, r_ld, 0xb940_0000),
+        (uload8, I64, r_ld, 0x3940_0000),
+        (uload8, I32, r_ld, 0x3940_0000),
+        (sload8, I64, r_ld, 0x3980_0000),
+        (sload8, I32, r_ld, 0x39c0_0000),
+        (uload16, I64, r_ld, 0x7940_0000),
+        (uload16, I32, r_ld, 0x7940_0000),
+        (sload16, I64, r_ld, 0x7980_0000),
+        (sload16, I32, r_ld, 0x79c0_0000),
+        (store, I64, r_st, 0xf900_0000),
+        (store, I32, r_st, 0xb900_0000),
+        (istore8, I64, r_st, 0x3900_0000),
+        (istore8, I32, r_st, 0x3900_0000),
+        (istore16, I64, r_st, 0x7900_0000),
+        (istore16, I32, r_st, 0x7900_0000),
+        (istore32, I64, r_st, 0xb900_0000),
+    ] {
+        e.add64(e.enc(inst.bind(ty).bind(Any), recipe, top10_bits(word)));
+    }
+    e.add64(e.enc(uload32.bind(I64), r_ld, top10_bits(0xb940_0000)));
+    e.add64(e.enc(sload32.bind(I64), r_ld, top10_bits(0xb980_0000)));
+    e.add64(e.enc(load.bind(R64).bind(Any), r_ld, top10_bits(0xf940_0000)));
+    e.add64(e.enc(store.bind(R64).bind(Any), r_st, top10_bits(0xf900_0000)));
+    for &(ty, ldr, str_) in &[
+        (F64, 0xfd40_0000, 0xfd00_0000),
+        (F32, 0xbd40_0000, 0xbd00_0000),
+    ] {
+        e.add64(e.enc(load.bind(ty).bind(Any), r_fld, top10_bits(ldr)));
+        e.add64(e.enc(store.bind(ty).bind(Any), r_fst, top10_bits(str_)));
+    }
+
+    // Spills and fills. Spill slots are at least 4 bytes, so the narrow types use 32-bit
+    // accesses.
+    let gpr_types: [LaneType; 5] = [I64.into(), I32.into(), I16.into(), I8.into(), B1.into()];
+    for &ty in &gpr_types {
+        let (ldr, str_) = if ty.lane_bits() == 64 {
+            (0xf940_0000, 0xf900_0000)
+        } else {
+            (0xb940_0000, 0xb900_0000)
+        };
+        e.add64(e.enc(spill.bind(ty), r_spill, top10_bits(str_)));
+        e.add64(e.enc(regspill.bind(ty), r_regspill, top10_bits(str_)));
+        e.add64(e.enc(fill.bind(ty), r_fill, top10_bits(ldr)));
+        e.add64(e.enc(regfill.bind(ty), r_regfill, top10_bits(ldr)));
+        e.add64(e.enc(fill_nop.bind(ty), r_fillnull, 0));
+        e.add64(e.enc(copy_nop.bind(ty), r_stacknull, 0));
+    }
+    e.add64(e.enc(spill.bind(R64), r_spill, top10_bits(0xf900_0000)));
+    e.add64(e.enc(regspill.bind(R64), r_regspill, top10_bits(0xf900_0000)));
+    e.add64(e.enc(fill.bind(R64), r_fill, top10_bits(0xf940_0000)));
+    e.add64(e.enc(regfill.bind(R64), r_regfill, top10_bits(0xf940_0000)));
+    e.add64(e.enc(fill_nop.bind(R64), r_fillnull, 0));
+    e.add64(e.enc(copy_nop.bind(R64), r_stacknull, 0));
+    for &(ty, ldr, str_) in &[
+        (F64, 0xfd40_0000, 0xfd00_0000),
+        (F32, 0xbd40_0000, 0xbd00_0000),
+    ] {
+        e.add64(e.enc(spill.bind(ty), r_fspill, top10_bits(str_)));
+        e.add64(e.enc(regspill.bind(ty), r_fregspill, top10_bits(str_)));
+        e.add64(e.enc(fill.bind(ty), r_ffill, top10_bits(ldr)));
+        e.add64(e.enc(regfill.bind(ty), r_fregfill, top10_bits(ldr)));
+        e.add64(e.enc(fill_nop.bind(ty), r_ffillnull, 0));
+        e.add64(e.enc(copy_nop.bind(ty), r_stacknull, 0));
+    }
+
+    e.add64(e.enc(stack_addr.bind(I64), r_spaddr, 0));
+
+    // Frames.
+    e.add64(e.enc(adjust_sp_down_imm, r_adjustsp_down, 0));
+    e.add64(e.enc(adjust_sp_up_imm, r_adjustsp_up, 0));
+    e.add64(e.enc(copy_special, r_copysp, addsub_imm_bits(0x9100_0000)));
+
+    // `stp` and `ldp` with pre- and post-indexing, and the single-register `str` and `ldr`.
+    e.add64(e.enc(arm64_push_pair.bind(I64), r_pushp, top10_bits(0xa980_0000)));
+    e.add64(e.enc(arm64_push_pair.bind(F64), r_fpushp, top10_bits(0x6d80_0000)));
+    e.add64(e.enc(arm64_pop_pair.bind(I64), r_popp, top10_bits(0xa8c0_0000)));
+    e.add64(e.enc(arm64_pop_pair.bind(F64), r_fpopp, top10_bits(0x6cc0_0000)));
+    e.add64(e.enc(arm64_push.bind(I64), r_push, indexed_bits(0xf800_0000)));
+    e.add64(e.enc(arm64_push.bind(F64), r_fpush, indexed_bits(0xfc00_0000)));
+    e.add64(e.enc(arm64_pop.bind(I64), r_pop, indexed_bits(0xf840_0000)));
+    e.add64(e.enc(arm64_pop.bind(F64), r_fpop, indexed_bits(0xfc40_0000)));
+
+    e.add64(e.enc(get_pinned_reg.bind(I64), r_get_pinned_reg, 0));
+    e.add64(e.enc(
+        set_pinned_reg.bind(I64),
+        r_set_pinned_reg,
+        rrr_bits(0xaa00_0000),
+    ));
+
+    // Control flow.
+    e.add64(e.enc(jump, r_b, b_bits(0x1400_0000)));
+    e.add64(e.enc(brif, r_brif, 0));
+    e.add64(e.enc(brif, r_brif_long, 0));
+    e.add64(e.enc(brff, r_brff, 0));
+    e.add64(e.enc(brff, r_brff2, 0));
+    e.add64(e.enc(brff, r_brff_long, 0));
+    e.add64(e.enc(brff, r_brff2_long, 0));
+    let cbz_types: [(LaneType, u32); 3] = [
+        (I64.into(), 0xb400_0000),
+        (I32.into(), 0x3400_0000),
+        (B1.into(), 0x3400_0000),
+    ];
+    for &(ty, cbz) in &cbz_types {
+        let cbnz = cbz | (1 << 24);
+        e.add64(e.enc(brz.bind(ty), r_cbz, cb_bits(cbz)));
+        e.add64(e.enc(brz.bind(ty), r_cbz_long, cb_bits(cbz)));
+        e.add64(e.enc(brnz.bind(ty), r_cbz, cb_bits(cbnz)));
+        e.add64(e.enc(brnz.bind(ty), r_cbz_long, cb_bits(cbnz)));
+
+        // Conditional traps branch over the trap when the condition doesn't hold.
+        e.add64(e.enc(trapz.bind(ty), r_trapz, cb_bits(cbnz)));
+        e.add64(e.enc(trapnz.bind(ty), r_trapz, cb_bits(cbz)));
+    }
+
+    e.add64(e.enc(jump_table_base.bind(I64), r_jt_base, 0));
+    e.add64(e.enc(
+        jump_table_entry.bind(I64),
+        r_jt_entry,
+        top10_bits(0xb980_0000),
+    ));
+    e.add64(e.enc(
+        indirect_jump_table_br.bind(I64),
+        r_indirect_jmp,
+        br_bits(0xd61f_0000),
+    ));
+
+    e.add64(e.enc(return_, r_ret, br_bits(0xd65f_0000)));
+
+    // Calls. `bl` reaches 128 MB, so it is only used for colocated functions and, in PIC, for
+    // calls through the PLT that the linker can place.
+    let is_colocated_call = InstructionPredicate::new_is_colocated_func(&*formats.call, "func_ref");
+    e.add64(
+        e.enc(call, r_call, b_bits(0x9400_0000))
+            .inst_predicate(is_colocated_call.clone()),
+    );
+    e.add64(
+        e.enc(call, r_call, b_bits(0x9400_0000))
+            .isa_predicate(is_pic),
+    );
+    e.add64(e.enc(call_indirect.bind(I64), r_call_r, br_bits(0xd63f_0000)));
+
+    let is_colocated_invoke =
+        InstructionPredicate::new_is_colocated_func(&*formats.invoke, "func_ref");
+    e.add64(
+        e.enc(invoke, r_invoke, b_bits(0x9400_0000))
+            .inst_predicate(is_colocated_invoke),
+    );
+    e.add64(
+        e.enc(invoke, r_invoke, b_bits(0x9400_0000))
+            .isa_predicate(is_pic),
+    );
+    e.add64(e.enc(invoke_indirect.bind(I64), r_invoke_r, br_bits(0xd63f_0000)));
+    e.add64(e.enc(landing_pad.bind(I64), r_landing_pad, 0));
+
+    // Function and symbol addresses.
+    let is_colocated_func =
+        InstructionPredicate::new_is_colocated_func(&*formats.func_addr, "func_ref");
+    e.add64(
+        e.enc(func_addr.bind(I64), r_fnaddr, 0)
+            .inst_predicate(is_colocated_func),
+    );
+    e.add64(
+        e.enc(func_addr.bind(I64), r_got_fnaddr, 0)
+            .isa_predicate(is_pic),
+    );
+    e.add64(
+        e.enc(func_addr.bind(I64), r_abs_fnaddr, 0)
+            .isa_predicate(not_is_pic),
+    );
+
+    e.add64(
+        e.enc(symbol_value.bind(I64), r_abs_gvaddr, 0)
+            .isa_predicate(not_is_pic),
+    );
+    e.add64(
+        e.enc(symbol_value.bind(I64), r_gvaddr, 0)
+            .isa_predicate(is_pic)
+            .inst_predicate(InstructionPredicate::new_is_colocated_data(formats)),
+    );
+    e.add64(
+        e.enc(symbol_value.bind(I64), r_got_gvaddr, 0)
+            .isa_predicate(is_pic),
+    );
+
+    // Traps.
+    e.add64(e.enc(trap, r_trap, 0));
+    e.add64(e.enc(resumable_trap, r_trap, 0));
+    e.add64(e.enc(debugtrap, r_debugtrap, 0));
+    e.add64(e.enc(trapif, r_trapif, 0));
+    e.add64(e.enc(trapff, r_trapff, 0));
+    e.add64(e.enc(trapff, r_trapff2, 0));
+
+    e.add64(e.enc(safepoint, r_safepoint, 0));
+
+    // Floating point.
+    for &ty in &[F32, F64] {
+        let ft = ftype(ty);
+        for &(inst, opcode) in &[
+            (fmul, 0b0000),
+            (fdiv, 0b0001),
+            (fadd, 0b0010),
+            (fsub, 0b0011),
+            (fmax, 0b0100),
+            (fmin, 0b0101),
+        ] {
+            e.add64(e.enc(inst.bind(ty), r_fp2, fp2_bits(ft, opcode)));
+        }
+        for &(inst, opcode) in &[
+            (fabs, 0b00_0001),
+            (fneg, 0b00_0010),
+            (sqrt, 0b00_0011),
+            (nearest, 0b00_1000),
+            (ceil, 0b00_1001),
+            (floor, 0b00_1010),
+            (trunc, 0b00_1011),
+        ] {
+            e.add64(e.enc(inst.bind(ty), r_fp1, fp1_bits(ft, opcode)));
+        }
+        e.add64(e.enc(fma.bind(ty), r_fp3, ft));
+
+        e.add64(e.enc(fcmp.bind(ty), r_fcmp, ft));
+        e.add64(e.enc(fcmp.bind(ty), r_fcmp2, ft));
+        e.add64(e.enc(ffcmp.bind(ty), r_ffcmp, ft));
+
+        // Bitwise operations use the 64-bit SIMD instructions.
+        for &(inst, u, size) in &[
+            (band, 0, 0b00),
+            (band_not, 0, 0b01),
+            (bor, 0, 0b10),
+            (bxor, 1, 0b00),
+        ] {
+            e.add64(e.enc(inst.bind(ty), r_vrrr, simd_bits(0, u, size, 0b00011)));
+        }
+        e.add64(e.enc(bnot.bind(ty), r_vmisc, simd_bits(0, 1, 0b00, 0b00101)));
+
+        // Conversions from and to integers.
+        for &(int, sf) in &[(I32, 0), (I64, 1)] {
+            e.add64(e.enc(
+                fcvt_from_sint.bind(ty).bind(int),
+                r_intfp,
+                fpint_bits(sf, ft, 0b00, 0b010),
+            ));
+            e.add64(e.enc(
+                fcvt_from_uint.bind(ty).bind(int),
+                r_intfp,
+                fpint_bits(sf, ft, 0b00, 0b011),
+            ));
+            e.add64(e.enc(
+                fcvt_to_sint_sat.bind(int).bind(ty),
+                r_fpint,
+                fpint_bits(sf, ft, 0b11, 0b000),
+            ));
+            e.add64(e.enc(
+                fcvt_to_uint_sat.bind(int).bind(ty),
+                r_fpint,
+                fpint_bits(sf, ft, 0b11, 0b001),
+            ));
+        }
+
+        // Copies move the whole vector register with `orr`.
+        let orr16b = simd_bits(1, 0, 0b10, 0b00011);
+        e.add64(e.enc(copy.bind(ty), r_fmov, orr16b));
+        e.add64(e.enc(regmove.bind(ty), r_frmov, orr16b));
+        e.add64(e.enc(copy_to_ssa.bind(ty), r_fcopytossa, orr16b));
+    }
+
+    // Moves between the register banks.
+    e.add64(e.enc(
+        bitcast.bind(I32).bind(F32),
+        r_fpint,
+        fpint_bits(0, 0, 0b00, 0b110),
+    ));
+    e.add64(e.enc(
+        bitcast.bind(F32).bind(I32),
+        r_intfp,
+        fpint_bits(0, 0, 0b00, 0b111),
+    ));
+    e.add64(e.enc(
+        bitcast.bind(I64).bind(F64),
+        r_fpint,
+        fpint_bits(1, 1, 0b00, 0b110),
+    ));
+    e.add64(e.enc(
+        bitcast.bind(F64).bind(I64),
+        r_intfp,
+        fpint_bits(1, 1, 0b00, 0b111),
+    ));
+
+    e.add64(e.enc(fpromote.bind(F64).bind(F32), r_fp1, fp1_bits(0, 0b00_0101)));
+    e.add64(e.enc(fdemote.bind(F32).bind(F64), r_fp1, fp1_bits(1, 0b00_0100)));
+
+    // Zero constants come from `movi`. Other constants are legalized to an integer constant and
+    // a `bitcast`.
+    e.add64(e.enc(f32const, r_f32zero, movi_bits(0, 0)));
+    e.add64(e.enc(f64const, r_f64zero, movi_bits(0, 0)));
+
+    // SIMD.
+    let neon_vector_size: u64 = 128;
+    let allowed_simd_type = |t: &LaneType| t.lane_bits() >= 8 && t.lane_bits() < 128;
+    let orr16b = simd_bits(1, 0, 0b10, 0b00011);
+
+    for ty in ValueType::all_lane_types().filter(allowed_simd_type) {
+        let vty = || vector(ty, neon_vector_size);
+        let size = lane_size(ty);
+
+        // Moves, loads and stores of the whole register.
+        e.add64(
+            e.enc(copy.bind(vty()), r_fmov, orr16b)
+                .isa_predicate(use_simd),
+        );
+        e.add64(
+            e.enc(regmove.bind(vty()), r_frmov, orr16b)
+                .isa_predicate(use_simd),
+        );
+        e.add64(
+            e.enc(copy_to_ssa.bind(vty()), r_fcopytossa, orr16b)
+                .isa_predicate(use_simd),
+        );
+        e.add64(
+            e.enc(load.bind(vty()).bind(Any), r_fld, top10_bits(0x3dc0_0000))
+                .isa_predicate(use_simd),
+        );
+        e.add64(
+            e.enc(store.bind(vty()).bind(Any), r_fst, top10_bits(0x3d80_0000))
+                .isa_predicate(use_simd),
+        );
+        e.add64(
+            e.enc(spill.bind(vty()), r_fspill, top10_bits(0x3d80_0000))
+                .isa_predicate(use_simd),
+        );
+        e.add64(
+            e.enc(regspill.bind(vty()), r_fregspill, top10_bits(0x3d80_0000))
+                .isa_predicate(use_simd),
+        );
+        e.add64(
+            e.enc(fill.bind(vty()), r_ffill, top10_bits(0x3dc0_0000))
+                .isa_predicate(use_simd),
+        );
+        e.add64(
+            e.enc(regfill.bind(vty()), r_fregfill, top10_bits(0x3dc0_0000))
+                .isa_predicate(use_simd),
+        );
+        e.add64(
+            e.enc(fill_nop.bind(vty()), r_ffillnull, 0)
+                .isa_predicate(use_simd),
+        );
+        e.add64(
+            e.enc(copy_nop.bind(vty()), r_stacknull, 0)
+                .isa_predicate(use_simd),
+        );
+
+        // Bit casts don't change the register contents.
+        for to in ValueType::all_lane_types().filter(|t| allowed_simd_type(t) && *t != ty) {
+            e.add64(
+                e.enc(
+                    raw_bitcast.bind(vector(to, neon_vector_size)).bind(vty()),
+                    r_null_fpr,
+                    0,
+                )
+                .isa_predicate(use_simd),
+            );
+        }
+        for &float in &[F32, F64] {
+            e.add64(
+                e.enc(raw_bitcast.bind(vty()).bind(float), r_null_fpr, 0)
+                    .isa_predicate(use_simd),
+            );
+            e.add64(
+                e.enc(raw_bitcast.bind(float).bind(vty()), r_null_fpr, 0)
+                    .isa_predicate(use_simd),
+            );
+        }
+
+        // Constants.
+        let is_zero =
+            InstructionPredicate::new_is_all_zeroes(&*formats.unary_const, "constant_handle");
+        e.add64(
+            e.enc(vconst.bind(vty()), r_vzero, movi_bits(1, 0))
+                .inst_predicate(is_zero)
+                .isa_predicate(use_simd),
+        );
+        let is_ones =
+            InstructionPredicate::new_is_all_ones(&*formats.unary_const, "constant_handle");
+        e.add64(
+            e.enc(vconst.bind(vty()), r_vones, movi_bits(1, 0xff))
+                .inst_predicate(is_ones)
+                .isa_predicate(use_simd),
+        );
+        e.add64(
+            e.enc(vconst.bind(vty()), r_vconst, cb_bits(0x9c00_0000))
+                .isa_predicate(use_simd),
+        );
+
+        // Bitwise operations.
+        for &(inst, u, size) in &[
+            (band, 0, 0b00),
+            (band_not, 0, 0b01),
+            (bor, 0, 0b10),
+            (bxor, 1, 0b00),
+        ] {
+            e.add64(
+                e.enc(inst.bind(vty()), r_vrrr, simd_bits(1, u, size, 0b00011))
+                    .isa_predicate(use_simd),
+            );
+        }
+        e.add64(
+            e.enc(bnot.bind(vty()), r_vmisc, simd_bits(1, 1, 0b00, 0b00101))
+                .isa_predicate(use_simd),
+        );
+
+        if ty.is_int() {
+            for &(inst, u, opcode) in &[
+                (iadd, 0, 0b10000),
+                (isub, 1, 0b10000),
+                (sadd_sat, 0, 0b00001),
+                (uadd_sat, 1, 0b00001),
+                (ssub_sat, 0, 0b00101),
+                (usub_sat, 1, 0b00101),
+            ] {
+                e.add64(
+                    e.enc(inst.bind(vty()), r_vrrr, simd_bits(1, u, size, opcode))
+                        .isa_predicate(use_simd),
+                );
+            }
+            if ty.lane_bits() < 64 {
+                e.add64(
+                    e.enc(imul.bind(vty()), r_vrrr, simd_bits(1, 0, size, 0b10011))
+                        .isa_predicate(use_simd),
+                );
+            }
+            e.add64(
+                e.enc(ineg.bind(vty()), r_vmisc, simd_bits(1, 1, size, 0b01011))
+                    .isa_predicate(use_simd),
+            );
+
+            // The other comparisons are legalized by swapping the operands or inverting the
+            // result.
+            for &(cc, u, opcode) in &[
+                ("eq", 1, 0b10001),
+                ("sgt", 0, 0b00110),
+                ("sge", 0, 0b00111),
+                ("ugt", 1, 0b00110),
+                ("uge", 1, 0b00111),
+            ] {
+                let cc = Literal::enumerator_for(&imm.intcc, cc);
+                e.add64(
+                    e.enc(icmp.bind(vty()), r_vicmp, simd_bits(1, u, size, opcode))
+                        .inst_predicate(InstructionPredicate::new_is_field_equal(
+                            &*formats.int_compare,
+                            "cond",
+                            cc.to_rust_code(),
+                        ))
+                        .isa_predicate(use_simd),
+                );
+            }
+
+            // Lanes are moved from and to the general purpose registers.
+            e.add64(
+                e.enc(splat.bind(vty()), r_splat_gpr, size)
+                    .isa_predicate(use_simd),
+            );
+            e.add64(
+                e.enc(extractlane.bind(vty()), r_extract_gpr, size)
+                    .isa_predicate(use_simd),
+            );
+            e.add64(
+                e.enc(insertlane.bind(vty()), r_insert_gpr, size)
+                    .isa_predicate(use_simd),
+            );
+            let fmov = if ty.lane_bits() == 64 {
+                fpint_bits(1, 1, 0b00, 0b111)
+            } else {
+                fpint_bits(0, 0, 0b00, 0b111)
+            };
+            e.add64(
+                e.enc(scalar_to_vector.bind(vty()), r_intfp, fmov)
+                    .isa_predicate(use_simd),
+            );
+        } else if ty.is_float() {
+            // The floating point instructions use the low bit of the size field for the lane
+            // size, and the high bit as part of the opcode.
+            let sz = size & 1;
+            for &(inst, u, size, opcode) in &[
+                (fadd, 0, sz, 0b11010),
+                (fsub, 0, 0b10 | sz, 0b11010),
+                (fmul, 1, sz, 0b11011),
+                (fdiv, 1, sz, 0b11111),
+                (fmax, 0, sz, 0b11110),
+                (fmin, 0, 0b10 | sz, 0b11110),
+            ] {
+                e.add64(
+                    e.enc(inst.bind(vty()), r_vrrr, simd_bits(1, u, size, opcode))
+                        .isa_predicate(use_simd),
+                );
+            }
+            for &(inst, u, size, opcode) in &[
+                (fneg, 1, 0b10 | sz, 0b01111),
+                (fabs, 0, 0b10 | sz, 0b01111),
+                (sqrt, 1, 0b10 | sz, 0b11111),
+            ] {
+                e.add64(
+                    e.enc(inst.bind(vty()), r_vmisc, simd_bits(1, u, size, opcode))
+                        .isa_predicate(use_simd),
+                );
+            }
+            for &(cc, u, size, opcode) in &[
+                ("eq", 0, sz, 0b11100),
+                ("ge", 1, sz, 0b11100),
+                ("gt", 1, 0b10 | sz, 0b11100),
+            ] {
+                let cc = Literal::enumerator_for(&imm.floatcc, cc);
+                e.add64(
+                    e.enc(fcmp.bind(vty()), r_vfcmp, simd_bits(1, u, size, opcode))
+                        .inst_predicate(InstructionPredicate::new_is_field_equal(
+                            &*formats.float_compare,
+                            "cond",
+                            cc.to_rust_code(),
+                        ))
+                        .isa_predicate(use_simd),
+                );
+            }
+
+            // Scalar floating point values already live in lane 0 of their register.
+            e.add64(
+                e.enc(splat.bind(vty()), r_splat_fpr, size)
+                    .isa_predicate(use_simd),
+            );
+            e.add64(
+                e.enc(extractlane.bind(vty()), r_extract_fpr, size)
+                    .isa_predicate(use_simd),
+            );
+            e.add64(
+                e.enc(insertlane.bind(vty()), r_insert_fpr, size)
+                    .isa_predicate(use_simd),
+            );
+            e.add64(
+                e.enc(scalar_to_vector.bind(vty()), r_null_fpr, 0)
+                    .isa_predicate(use_simd),
+            );
+        }
+    }
+
+    e
+}
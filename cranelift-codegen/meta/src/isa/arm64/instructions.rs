@@ -0,0 +1,92 @@
+#![allow(non_snake_case)]
+
+use crate::cdsl::instructions::{
+    AllInstructions, InstructionBuilder as Inst, InstructionGroup, InstructionGroupBuilder,
+};
+use crate::cdsl::operands::Operand;
+use crate::cdsl::typevar::{TypeSetBuilder, TypeVar};
+
+use crate::shared::formats::Formats;
+
+pub(crate) fn define(
+    mut all_instructions: &mut AllInstructions,
+    formats: &Formats,
+) -> InstructionGroup {
+    let mut ig = InstructionGroupBuilder::new(&mut all_instructions);
+
+    let Saved = &TypeVar::new(
+        "Saved",
+        "A 64-bit register saved on the stack by a prologue",
+        TypeSetBuilder::new().ints(64..64).floats(64..64).build(),
+    );
+    let x = &Operand::new("x", Saved).with_doc("Value stored at the lower address");
+    let y = &Operand::new("y", Saved).with_doc("Value stored at the higher address");
+
+    ig.push(
+        Inst::new(
+            "arm64_push_pair",
+            r#"
+    Pushes a pair of registers onto the stack.
+
+    Decrements the stack pointer by 16 and stores ``x`` and ``y`` in the
+    freed space, ``x`` at the new top of the stack. This is the
+    pre-indexed ``stp`` used to build a frame record and to save
+    callee-saved registers.
+    "#,
+            &formats.binary,
+        )
+        .operands_in(vec![x, y])
+        .other_side_effects(true)
+        .can_store(true),
+    );
+
+    ig.push(
+        Inst::new(
+            "arm64_pop_pair",
+            r#"
+    Pops a pair of registers from the stack.
+
+    Loads ``x`` and ``y`` from the top of the stack and then increments the
+    stack pointer by 16. This undoes `arm64_push_pair`.
+    "#,
+            &formats.nullary,
+        )
+        .operands_out(vec![x, y])
+        .other_side_effects(true)
+        .can_load(true),
+    );
+
+    ig.push(
+        Inst::new(
+            "arm64_push",
+            r#"
+    Pushes a single register onto the stack.
+
+    Decrements the stack pointer by 16 to keep it aligned, and stores ``x``
+    at the new top of the stack.
+    "#,
+            &formats.unary,
+        )
+        .operands_in(vec![x])
+        .other_side_effects(true)
+        .can_store(true),
+    );
+
+    ig.push(
+        Inst::new(
+            "arm64_pop",
+            r#"
+    Pops a single register from the stack.
+
+    Loads ``x`` from the top of the stack and then increments the stack
+    pointer by 16. This undoes `arm64_push`.
+    "#,
+            &formats.nullary,
+        )
+        .operands_out(vec![x])
+        .other_side_effects(true)
+        .can_load(true),
+    );
+
+    ig.build()
+}
@@ -0,0 +1,188 @@
+use crate::cdsl::ast::{var, ExprBuilder, Literal};
+use crate::cdsl::instructions::{vector, Bindable};
+use crate::cdsl::xform::TransformGroupBuilder;
+use crate::shared::types::Float::{F32, F64};
+use crate::shared::types::Int::{I16, I32, I64, I8};
+use crate::shared::Definitions as SharedDefinitions;
+
+#[allow(clippy::many_single_char_names)]
+pub(crate) fn define(shared: &mut SharedDefinitions) {
+    let mut group = TransformGroupBuilder::new(
+        "arm64_expand",
+        r#"
+    Legalize instructions by expansion.
+
+    Use arm64-specific instructions if needed."#,
+    )
+    .isa("arm64")
+    .chain_with(shared.transform_groups.by_name("expand_flags").id);
+
+    // List of instructions.
+    let insts = &shared.instructions;
+    let bnot = insts.by_name("bnot");
+    let fcmp = insts.by_name("fcmp");
+    let fcvt_to_sint = insts.by_name("fcvt_to_sint");
+    let fcvt_to_uint = insts.by_name("fcvt_to_uint");
+    let band = insts.by_name("band");
+    let iadd = insts.by_name("iadd");
+    let iadd_ifcout = insts.by_name("iadd_ifcout");
+    let icmp = insts.by_name("icmp");
+    let iconst = insts.by_name("iconst");
+    let imul = insts.by_name("imul");
+    let ineg = insts.by_name("ineg");
+    let isub = insts.by_name("isub");
+    let isub_ifbout = insts.by_name("isub_ifbout");
+    let popcnt = insts.by_name("popcnt");
+    let rotl = insts.by_name("rotl");
+    let rotr = insts.by_name("rotr");
+    let sadd_overflow = insts.by_name("sadd_overflow");
+    let ssub_overflow = insts.by_name("ssub_overflow");
+    let trueif = insts.by_name("trueif");
+    let uadd_overflow = insts.by_name("uadd_overflow");
+    let usub_overflow = insts.by_name("usub_overflow");
+    let ushr_imm = insts.by_name("ushr_imm");
+
+    let imm = &shared.imm;
+
+    let a = var("a");
+    let b = var("b");
+    let c = var("c");
+    let d = var("d");
+    let e = var("e");
+    let f = var("f");
+    let g = var("g");
+    let h = var("h");
+    let k = var("k");
+    let m = var("m");
+    let n = var("n");
+    let p = var("p");
+    let q = var("q");
+    let r = var("r");
+    let s = var("s");
+    let t = var("t");
+    let x = var("x");
+    let y = var("y");
+
+    // There is no rotate left, but rotating right by the negated amount is the same thing.
+    group.legalize(
+        def!(a = rotl(x, y)),
+        vec![def!(b = ineg(y)), def!(a = rotr(x, b))],
+    );
+
+    // Overflow-reporting arithmetic reads the flags of `adds` and `subs`. The carry flag is set
+    // by an unsigned add that overflows, but cleared by a subtraction that borrows.
+    let intcc_of = Literal::enumerator_for(&imm.intcc, "of");
+    let intcc_uge = Literal::enumerator_for(&imm.intcc, "uge");
+    let intcc_ult = Literal::enumerator_for(&imm.intcc, "ult");
+    for &(op, flags_op, cc) in &[
+        (uadd_overflow, iadd_ifcout, &intcc_uge),
+        (sadd_overflow, iadd_ifcout, &intcc_of),
+        (usub_overflow, isub_ifbout, &intcc_ult),
+        (ssub_overflow, isub_ifbout, &intcc_of),
+    ] {
+        group.legalize(
+            def!((a, b) = op(x, y)),
+            vec![
+                def!((a, f) = flags_op(x, y)),
+                def!(b = trueif(cc.clone(), f)),
+            ],
+        );
+    }
+
+    // Population count only exists for SIMD registers. Count the bits of each byte in parallel
+    // instead, and sum the bytes with a multiplication.
+    for &(ty, bits, ones) in &[(I32, 32, 0x0101_0101), (I64, 64, 0x0101_0101_0101_0101)] {
+        let mask = |byte: u64| Literal::constant(&imm.imm64, (byte * ones) as i64);
+        let popcnt = popcnt.bind(ty);
+        group.legalize(
+            def!(a = popcnt(x)),
+            vec![
+                def!(b = ushr_imm(x, Literal::constant(&imm.imm64, 1))),
+                def!(c = iconst(mask(0x55))),
+                def!(d = band(b, c)),
+                def!(e = isub(x, d)),
+                def!(f = ushr_imm(e, Literal::constant(&imm.imm64, 2))),
+                def!(g = iconst(mask(0x33))),
+                def!(h = band(e, g)),
+                def!(k = band(f, g)),
+                def!(m = iadd(h, k)),
+                def!(n = ushr_imm(m, Literal::constant(&imm.imm64, 4))),
+                def!(p = iadd(m, n)),
+                def!(q = iconst(mask(0x0f))),
+                def!(r = band(p, q)),
+                def!(s = iconst(mask(0x01))),
+                def!(t = imul(r, s)),
+                def!(a = ushr_imm(t, Literal::constant(&imm.imm64, bits - 8))),
+            ],
+        );
+    }
+
+    // `fcvtzs` and `fcvtzu` saturate instead of trapping, so check the input first.
+    group.custom_legalize(fcvt_to_sint, "expand_fcvt_to_sint");
+    group.custom_legalize(fcvt_to_uint, "expand_fcvt_to_uint");
+
+    group.build_and_add_to(&mut shared.transform_groups);
+
+    let mut narrow = TransformGroupBuilder::new(
+        "arm64_narrow",
+        r#"
+    Legalize instructions by narrowing.
+
+    Use arm64-specific instructions if needed."#,
+    )
+    .isa("arm64")
+    .chain_with(shared.transform_groups.by_name("narrow_flags").id);
+
+    // SIMD comparisons only exist for "greater than" and equality, so the remaining condition
+    // codes swap the operands or invert the result.
+    let neon_vector_size: u64 = 128;
+
+    let intcc_eq = Literal::enumerator_for(&imm.intcc, "eq");
+    let intcc_ne = Literal::enumerator_for(&imm.intcc, "ne");
+    for &(cc, rev_cc) in &[
+        ("slt", "sgt"),
+        ("sle", "sge"),
+        ("ult", "ugt"),
+        ("ule", "uge"),
+    ] {
+        let cc = Literal::enumerator_for(&imm.intcc, cc);
+        let rev_cc = Literal::enumerator_for(&imm.intcc, rev_cc);
+        for &ty in &[I8, I16, I32, I64] {
+            let icmp_ = icmp.bind(vector(ty, neon_vector_size));
+            narrow.legalize(
+                def!(c = icmp_(cc.clone(), x, y)),
+                vec![def!(c = icmp(rev_cc.clone(), y, x))],
+            );
+        }
+    }
+    for &ty in &[I8, I16, I32, I64] {
+        let icmp_ = icmp.bind(vector(ty, neon_vector_size));
+        narrow.legalize(
+            def!(c = icmp_(intcc_ne.clone(), x, y)),
+            vec![def!(a = icmp(intcc_eq.clone(), x, y)), def!(c = bnot(a))],
+        );
+    }
+
+    let floatcc_eq = Literal::enumerator_for(&imm.floatcc, "eq");
+    let floatcc_ne = Literal::enumerator_for(&imm.floatcc, "ne");
+    for &(cc, rev_cc) in &[("lt", "gt"), ("le", "ge")] {
+        let cc = Literal::enumerator_for(&imm.floatcc, cc);
+        let rev_cc = Literal::enumerator_for(&imm.floatcc, rev_cc);
+        for &ty in &[F32, F64] {
+            let fcmp_ = fcmp.bind(vector(ty, neon_vector_size));
+            narrow.legalize(
+                def!(c = fcmp_(cc.clone(), x, y)),
+                vec![def!(c = fcmp(rev_cc.clone(), y, x))],
+            );
+        }
+    }
+    for &ty in &[F32, F64] {
+        let fcmp_ = fcmp.bind(vector(ty, neon_vector_size));
+        narrow.legalize(
+            def!(c = fcmp_(floatcc_ne.clone(), x, y)),
+            vec![def!(a = fcmp(floatcc_eq.clone(), x, y)), def!(c = bnot(a))],
+        );
+    }
+
+    narrow.build_and_add_to(&mut shared.transform_groups);
+}
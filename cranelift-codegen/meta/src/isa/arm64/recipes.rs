@@ -0,0 +1,1467 @@
+use std::collections::HashMap;
+
+use crate::cdsl::ast::Literal;
+use crate::cdsl::formats::InstructionFormat;
+use crate::cdsl::instructions::InstructionPredicate;
+use crate::cdsl::recipes::{
+    EncodingRecipeBuilder, EncodingRecipeNumber, OperandConstraint, Recipes, Register, Stack,
+};
+use crate::cdsl::regs::IsaRegs;
+use crate::shared::Definitions as SharedDefinitions;
+
+/// An helper to create recipes and use them when defining the ARM64 encodings.
+pub(crate) struct RecipeGroup {
+    /// The actualy list of recipes explicitly created in this file.
+    pub recipes: Recipes,
+
+    /// Provides fast lookup from a name to an encoding recipe.
+    name_to_recipe: HashMap<String, EncodingRecipeNumber>,
+}
+
+impl RecipeGroup {
+    fn new() -> Self {
+        Self {
+            recipes: Recipes::new(),
+            name_to_recipe: HashMap::new(),
+        }
+    }
+
+    fn push(&mut self, builder: EncodingRecipeBuilder) {
+        assert!(
+            self.name_to_recipe.get(&builder.name).is_none(),
+            format!("arm64 recipe '{}' created twice", builder.name)
+        );
+        let name = builder.name.clone();
+        let number = self.recipes.push(builder.build());
+        self.name_to_recipe.insert(name, number);
+    }
+
+    pub fn by_name(&self, name: &str) -> EncodingRecipeNumber {
+        *self
+            .name_to_recipe
+            .get(name)
+            .unwrap_or_else(|| panic!("unknown arm64 recipe name {}", name))
+    }
+
+    pub fn collect(self) -> Recipes {
+        self.recipes
+    }
+}
+
+/// Returns a predicate checking that the "cond" field of the instruction is one of `floatccs`.
+fn floatcc_predicate(floatccs: &[Literal], format: &InstructionFormat) -> InstructionPredicate {
+    floatccs
+        .iter()
+        .fold(InstructionPredicate::new(), |pred, literal| {
+            pred.or(InstructionPredicate::new_is_field_equal(
+                format,
+                "cond",
+                literal.to_rust_code(),
+            ))
+        })
+}
+
+pub(crate) fn define(shared_defs: &SharedDefinitions, regs: &IsaRegs) -> RecipeGroup {
+    let formats = &shared_defs.formats;
+    let imm = &shared_defs.imm;
+
+    // Register classes shorthands.
+    let gpr = regs.class_by_name("GPR");
+    let fpr = regs.class_by_name("FPR");
+    let flag = regs.class_by_name("FLAG");
+
+    // Operand constraints shorthands.
+    let reg_nzcv = Register::new(flag, regs.regunit_by_name(flag, "nzcv"));
+    let reg_x0 = Register::new(gpr, regs.regunit_by_name(gpr, "x0"));
+    let reg_x1 = Register::new(gpr, regs.regunit_by_name(gpr, "x1"));
+    let reg_x28 = Register::new(gpr, regs.regunit_by_name(gpr, "x28"));
+
+    let stack_gpr = Stack::new(gpr);
+    let stack_fpr = Stack::new(fpr);
+
+    // Floating point condition codes that can't be tested with a single condition after `fcmp`.
+    // They are the union of two conditions, so they take an extra instruction.
+    let floatccs_two_conds: Vec<Literal> = ["one", "ueq"]
+        .iter()
+        .map(|name| Literal::enumerator_for(&imm.floatcc, name))
+        .collect();
+    let floatccs_one_cond: Vec<Literal> = [
+        "ord", "uno", "eq", "ne", "lt", "ge", "le", "gt", "ult", "uge", "ule", "ugt",
+    ]
+    .iter()
+    .map(|name| Literal::enumerator_for(&imm.floatcc, name))
+    .collect();
+
+    // Definitions.
+    let mut recipes = RecipeGroup::new();
+
+    // A null unary instruction that takes a GPR register. Can be used for identity copies and
+    // no-op conversions.
+    recipes.push(
+        EncodingRecipeBuilder::new("null", &formats.unary, 0)
+            .operands_in(vec![gpr])
+            .operands_out(vec![0])
+            .clobbers_flags(false)
+            .emit(""),
+    );
+    recipes.push(
+        EncodingRecipeBuilder::new("null_fpr", &formats.unary, 0)
+            .operands_in(vec![fpr])
+            .operands_out(vec![0])
+            .clobbers_flags(false)
+            .emit(""),
+    );
+
+    // Stack-slot to same stack-slot copy, which is guaranteed to turn into a no-op.
+    recipes.push(
+        EncodingRecipeBuilder::new("stacknull", &formats.unary, 0)
+            .operands_in(vec![stack_gpr])
+            .operands_out(vec![stack_gpr])
+            .clobbers_flags(false)
+            .emit(""),
+    );
+
+    // No-op fills, created by late-stage redundant-fill removal.
+    recipes.push(
+        EncodingRecipeBuilder::new("fillnull", &formats.unary, 0)
+            .operands_in(vec![stack_gpr])
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .emit(""),
+    );
+    recipes.push(
+        EncodingRecipeBuilder::new("ffillnull", &formats.unary, 0)
+            .operands_in(vec![stack_fpr])
+            .operands_out(vec![fpr])
+            .clobbers_flags(false)
+            .emit(""),
+    );
+
+    // The pinned register is x28, the last callee-saved register.
+    recipes.push(
+        EncodingRecipeBuilder::new("get_pinned_reg", &formats.nullary, 0)
+            .operands_out(vec![reg_x28])
+            .clobbers_flags(false)
+            .emit(""),
+    );
+    recipes.push(
+        EncodingRecipeBuilder::new("set_pinned_reg", &formats.unary, 4)
+            .operands_in(vec![gpr])
+            .clobbers_flags(false)
+            .emit("put_rrr(bits, RU::x31.into(), in_reg0, RU::x28.into(), sink);"),
+    );
+
+    // The unwinder delivers the exception pointer and selector in x0 and x1.
+    recipes.push(
+        EncodingRecipeBuilder::new("landing_pad", &formats.nullary, 0)
+            .operands_out(vec![reg_x0, reg_x1])
+            .clobbers_flags(false)
+            .emit(""),
+    );
+
+    recipes.push(
+        EncodingRecipeBuilder::new("safepoint", &formats.multiary, 0).emit(
+            r#"
+                sink.add_stackmap(args, func, isa);
+            "#,
+        ),
+    );
+
+    // Integer arithmetic.
+
+    // Data processing with three registers: add, sub and the logical instructions.
+    recipes.push(
+        EncodingRecipeBuilder::new("rrr", &formats.binary, 4)
+            .operands_in(vec![gpr, gpr])
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .emit("put_rrr(bits, in_reg0, in_reg1, out_reg0, sink);"),
+    );
+
+    // The flag-setting variants producing a carry or a borrow.
+    recipes.push(
+        EncodingRecipeBuilder::new("rrr_fout", &formats.binary, 4)
+            .operands_in(vec![gpr, gpr])
+            .operands_out(vec![
+                OperandConstraint::RegClass(gpr),
+                OperandConstraint::FixedReg(reg_nzcv),
+            ])
+            .emit("put_rrr(bits, in_reg0, in_reg1, out_reg0, sink);"),
+    );
+
+    // The variants consuming a carry or a borrow.
+    recipes.push(
+        EncodingRecipeBuilder::new("rrr_fin", &formats.ternary, 4)
+            .operands_in(vec![
+                OperandConstraint::RegClass(gpr),
+                OperandConstraint::RegClass(gpr),
+                OperandConstraint::FixedReg(reg_nzcv),
+            ])
+            .operands_out(vec![gpr])
+            .emit("put_rrr(bits, in_reg0, in_reg1, out_reg0, sink);"),
+    );
+
+    // The variants consuming and producing a carry or a borrow.
+    recipes.push(
+        EncodingRecipeBuilder::new("rrr_fio", &formats.ternary, 4)
+            .operands_in(vec![
+                OperandConstraint::RegClass(gpr),
+                OperandConstraint::RegClass(gpr),
+                OperandConstraint::FixedReg(reg_nzcv),
+            ])
+            .operands_out(vec![
+                OperandConstraint::RegClass(gpr),
+                OperandConstraint::FixedReg(reg_nzcv),
+            ])
+            .emit("put_rrr(bits, in_reg0, in_reg1, out_reg0, sink);"),
+    );
+
+    // A register copy is an `orr` from the zero register.
+    recipes.push(
+        EncodingRecipeBuilder::new("mov", &formats.unary, 4)
+            .operands_in(vec![gpr])
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .emit("put_rrr(bits, RU::x31.into(), in_reg0, out_reg0, sink);"),
+    );
+    recipes.push(
+        EncodingRecipeBuilder::new("rmov", &formats.reg_move, 4)
+            .operands_in(vec![gpr])
+            .clobbers_flags(false)
+            .emit("put_rrr(bits, RU::x31.into(), src, dst, sink);"),
+    );
+    recipes.push(
+        EncodingRecipeBuilder::new("copytossa", &formats.copy_to_ssa, 4)
+            // No operands_in to mention, because a source register is specified directly.
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .emit("put_rrr(bits, RU::x31.into(), src, out_reg0, sink);"),
+    );
+
+    // Data processing with two source registers: variable shifts and rotates.
+    recipes.push(
+        EncodingRecipeBuilder::new("dp2", &formats.binary, 4)
+            .operands_in(vec![gpr, gpr])
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .emit("put_dp2(bits, in_reg0, in_reg1, out_reg0, sink);"),
+    );
+
+    // Data processing with one source register: bit counting and reversal.
+    recipes.push(
+        EncodingRecipeBuilder::new("dp1", &formats.unary, 4)
+            .operands_in(vec![gpr])
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .emit("put_dp1(bits, in_reg0, out_reg0, sink);"),
+    );
+
+    // Count trailing zeros is a count of the leading zeros of the reversed bits.
+    recipes.push(
+        EncodingRecipeBuilder::new("ctz", &formats.unary, 8)
+            .operands_in(vec![gpr])
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .emit(
+                r#"
+                    put_dp1(bits, in_reg0, out_reg0, sink);
+                    put_dp1(bits | DP1_CLZ, out_reg0, out_reg0, sink);
+                "#,
+            ),
+    );
+
+    // Data processing with three source registers, with the addend tied to the zero register.
+    recipes.push(
+        EncodingRecipeBuilder::new("dp3", &formats.binary, 4)
+            .operands_in(vec![gpr, gpr])
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .emit("put_dp3(bits, in_reg0, in_reg1, RU::x31.into(), out_reg0, sink);"),
+    );
+
+    // The high half of a 32-bit multiplication, from a widening multiplication and a shift.
+    recipes.push(
+        EncodingRecipeBuilder::new("mulhi32", &formats.binary, 8)
+            .operands_in(vec![gpr, gpr])
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .emit(
+                r#"
+                    put_dp3(bits, in_reg0, in_reg1, RU::x31.into(), out_reg0, sink);
+                    // The unsigned variants use `lsr` instead of `asr`.
+                    let shift = if bits & DP3_U != 0 { BF_UBFM64 } else { BF_SBFM64 };
+                    put_bitfield(shift, 32, 63, out_reg0, out_reg0, sink);
+                "#,
+            ),
+    );
+
+    // Division traps when the divisor is zero, which `udiv` and `sdiv` don't do by themselves.
+    recipes.push(
+        EncodingRecipeBuilder::new("div", &formats.binary, 12)
+            .operands_in(vec![gpr, gpr])
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .emit(
+                r#"
+                    put_trap_if_zero(bits, in_reg1, func.srclocs[inst], sink);
+                    put_dp2(bits, in_reg0, in_reg1, out_reg0, sink);
+                "#,
+            ),
+    );
+
+    // Signed division also traps when the quotient doesn't fit: `MIN / -1`.
+    recipes.push(
+        EncodingRecipeBuilder::new("sdiv", &formats.binary, 28)
+            .operands_in(vec![gpr, gpr])
+            .operands_out(vec![gpr])
+            .emit(
+                r#"
+                    put_trap_if_zero(bits, in_reg1, func.srclocs[inst], sink);
+                    put_sdiv_overflow_check(bits, in_reg0, in_reg1, func.srclocs[inst], sink);
+                    put_dp2(bits, in_reg0, in_reg1, out_reg0, sink);
+                "#,
+            ),
+    );
+
+    // The remainder is computed from the quotient in x16. The signed remainder of `MIN / -1`
+    // is 0, which is what `msub` computes from the wrapped quotient, so there's no other check.
+    recipes.push(
+        EncodingRecipeBuilder::new("rem", &formats.binary, 16)
+            .operands_in(vec![gpr, gpr])
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .emit(
+                r#"
+                    put_trap_if_zero(bits, in_reg1, func.srclocs[inst], sink);
+                    put_dp2(bits, in_reg0, in_reg1, RU::x16.into(), sink);
+                    put_msub(bits, RU::x16.into(), in_reg1, in_reg0, out_reg0, sink);
+                "#,
+            ),
+    );
+
+    // Add and subtract with a 12-bit immediate. Negative immediates flip between the two.
+    recipes.push(
+        EncodingRecipeBuilder::new("addi", &formats.binary_imm, 4)
+            .operands_in(vec![gpr])
+            .operands_out(vec![gpr])
+            .inst_predicate(InstructionPredicate::new_is_signed_int(
+                &*formats.binary_imm,
+                "imm",
+                13,
+                0,
+            ))
+            .clobbers_flags(false)
+            .emit("put_addsub_imm(bits, in_reg0, imm.into(), out_reg0, sink);"),
+    );
+
+    // Shifts and rotates by an immediate amount are aliases of the bitfield moves and `extr`.
+    recipes.push(
+        EncodingRecipeBuilder::new("lsl_imm", &formats.binary_imm, 4)
+            .operands_in(vec![gpr])
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .emit(
+                r#"
+                    let width = bitfield_width(bits);
+                    let amount = imm_shift_amount(imm, width);
+                    put_bitfield(bits, (width - amount) % width, width - 1 - amount, in_reg0, out_reg0, sink);
+                "#,
+            ),
+    );
+    recipes.push(
+        EncodingRecipeBuilder::new("shr_imm", &formats.binary_imm, 4)
+            .operands_in(vec![gpr])
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .emit(
+                r#"
+                    let width = bitfield_width(bits);
+                    let amount = imm_shift_amount(imm, width);
+                    put_bitfield(bits, amount, width - 1, in_reg0, out_reg0, sink);
+                "#,
+            ),
+    );
+    recipes.push(
+        EncodingRecipeBuilder::new("ror_imm", &formats.binary_imm, 4)
+            .operands_in(vec![gpr])
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .emit(
+                r#"
+                    let width = bitfield_width(bits);
+                    let amount = imm_shift_amount(imm, width);
+                    put_extr(bits, in_reg0, in_reg0, amount, out_reg0, sink);
+                "#,
+            ),
+    );
+    recipes.push(
+        EncodingRecipeBuilder::new("rol_imm", &formats.binary_imm, 4)
+            .operands_in(vec![gpr])
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .emit(
+                r#"
+                    let width = bitfield_width(bits);
+                    let amount = imm_shift_amount(imm, width);
+                    put_extr(bits, in_reg0, in_reg0, (width - amount) % width, out_reg0, sink);
+                "#,
+            ),
+    );
+
+    // Sign and zero extensions are bitfield moves of the low bits of the input type.
+    recipes.push(
+        EncodingRecipeBuilder::new("extend", &formats.unary, 4)
+            .operands_in(vec![gpr])
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .emit(
+                r#"
+                    let from_bits = func.dfg.value_type(args[0]).bits() as u32;
+                    put_bitfield(bits, 0, from_bits - 1, in_reg0, out_reg0, sink);
+                "#,
+            ),
+    );
+
+    // Boolean conversions only look at the lowest bit of the boolean.
+    recipes.push(
+        EncodingRecipeBuilder::new("bint", &formats.unary, 4)
+            .operands_in(vec![gpr])
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .emit("put_bitfield(bits, 0, 0, in_reg0, out_reg0, sink);"),
+    );
+
+    // Integer constants take between one and four `movz`, `movn` and `movk` instructions.
+    recipes.push(
+        EncodingRecipeBuilder::new("iconst", &formats.unary_imm, 4)
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .compute_size("size_iconst")
+            .emit("put_iconst(bits, imm.into(), out_reg0, sink);"),
+    );
+
+    recipes.push(
+        EncodingRecipeBuilder::new("bconst", &formats.unary_bool, 4)
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .emit("put_iconst(bits, imm.into(), out_reg0, sink);"),
+    );
+
+    // Comparisons.
+
+    // An integer comparison followed by a `cset`.
+    recipes.push(
+        EncodingRecipeBuilder::new("icmp", &formats.int_compare, 8)
+            .operands_in(vec![gpr, gpr])
+            .operands_out(vec![gpr])
+            .emit(
+                r#"
+                    put_rrr(bits, in_reg0, in_reg1, RU::x31.into(), sink);
+                    put_cset(icc2cond(cond), out_reg0, sink);
+                "#,
+            ),
+    );
+    recipes.push(
+        EncodingRecipeBuilder::new("icmp_imm", &formats.int_compare_imm, 8)
+            .operands_in(vec![gpr])
+            .operands_out(vec![gpr])
+            .inst_predicate(InstructionPredicate::new_is_signed_int(
+                &*formats.int_compare_imm,
+                "imm",
+                13,
+                0,
+            ))
+            .emit(
+                r#"
+                    put_addsub_imm(bits, in_reg0, imm.into(), RU::x31.into(), sink);
+                    put_cset(icc2cond(cond), out_reg0, sink);
+                "#,
+            ),
+    );
+
+    // Integer comparisons writing the flags.
+    recipes.push(
+        EncodingRecipeBuilder::new("ifcmp", &formats.binary, 4)
+            .operands_in(vec![gpr, gpr])
+            .operands_out(vec![reg_nzcv])
+            .emit("put_rrr(bits, in_reg0, in_reg1, RU::x31.into(), sink);"),
+    );
+    recipes.push(
+        EncodingRecipeBuilder::new("ifcmp_imm", &formats.binary_imm, 4)
+            .operands_in(vec![gpr])
+            .operands_out(vec![reg_nzcv])
+            .inst_predicate(InstructionPredicate::new_is_signed_int(
+                &*formats.binary_imm,
+                "imm",
+                13,
+                0,
+            ))
+            .emit("put_addsub_imm(bits, in_reg0, imm.into(), RU::x31.into(), sink);"),
+    );
+
+    // Larger immediates are materialized in x16 first. The post-legalization optimizer creates
+    // `ifcmp_imm` instructions with any immediate.
+    recipes.push(
+        EncodingRecipeBuilder::new("ifcmp_imm_x16", &formats.binary_imm, 4)
+            .operands_in(vec![gpr])
+            .operands_out(vec![reg_nzcv])
+            .compute_size("size_ifcmp_imm_x16")
+            .emit(
+                r#"
+                    put_iconst(rrr_sf(bits), imm.into(), RU::x16.into(), sink);
+                    put_rrr(bits, in_reg0, RU::x16.into(), RU::x31.into(), sink);
+                "#,
+            ),
+    );
+
+    // Materialize a condition from the flags.
+    recipes.push(
+        EncodingRecipeBuilder::new("trueif", &formats.int_cond, 4)
+            .operands_in(vec![reg_nzcv])
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .emit("put_cset(icc2cond(cond), out_reg0, sink);"),
+    );
+    recipes.push(
+        EncodingRecipeBuilder::new("trueff", &formats.float_cond, 4)
+            .operands_in(vec![reg_nzcv])
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .inst_predicate(floatcc_predicate(&floatccs_one_cond, &*formats.float_cond))
+            .emit("put_cset(fcc2cond(cond).0, out_reg0, sink);"),
+    );
+    recipes.push(
+        EncodingRecipeBuilder::new("trueff2", &formats.float_cond, 8)
+            .operands_in(vec![reg_nzcv])
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .inst_predicate(floatcc_predicate(&floatccs_two_conds, &*formats.float_cond))
+            .emit("put_cset_fcc(cond, out_reg0, sink);"),
+    );
+
+    // Select based on the flags.
+    recipes.push(
+        EncodingRecipeBuilder::new("selectif", &formats.int_select, 4)
+            .operands_in(vec![
+                OperandConstraint::FixedReg(reg_nzcv),
+                OperandConstraint::RegClass(gpr),
+                OperandConstraint::RegClass(gpr),
+            ])
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .emit("put_csel(bits, icc2cond(cond), in_reg1, in_reg2, out_reg0, sink);"),
+    );
+
+    // Select based on a boolean, which is tested with `tst wc, #1`.
+    recipes.push(
+        EncodingRecipeBuilder::new("select", &formats.ternary, 8)
+            .operands_in(vec![gpr, gpr, gpr])
+            .operands_out(vec![gpr])
+            .emit(
+                r#"
+                    put_tst_bool(in_reg0, sink);
+                    put_csel(bits, COND_NE, in_reg1, in_reg2, out_reg0, sink);
+                "#,
+            ),
+    );
+    recipes.push(
+        EncodingRecipeBuilder::new("fselect", &formats.ternary, 8)
+            .operands_in(vec![gpr, fpr, fpr])
+            .operands_out(vec![fpr])
+            .emit(
+                r#"
+                    put_tst_bool(in_reg0, sink);
+                    put_fcsel(bits, COND_NE, in_reg1, in_reg2, out_reg0, sink);
+                "#,
+            ),
+    );
+
+    // Memory accesses.
+
+    // Loads and stores with a base register and an immediate offset. The offset is scaled and
+    // unsigned, unscaled and 9-bit signed, or else materialized in x16.
+    recipes.push(
+        EncodingRecipeBuilder::new("ld", &formats.load, 4)
+            .operands_in(vec![gpr])
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .compute_size("size_ldst")
+            .emit(
+                r#"
+                    let i = ldst_form(bits, offset.into(), sink);
+                    if !flags.notrap() {
+                        sink.trap(TrapCode::HeapOutOfBounds, func.srclocs[inst]);
+                    }
+                    put_ldst(i, in_reg0, out_reg0, sink);
+                "#,
+            ),
+    );
+    recipes.push(
+        EncodingRecipeBuilder::new("fld", &formats.load, 4)
+            .operands_in(vec![gpr])
+            .operands_out(vec![fpr])
+            .clobbers_flags(false)
+            .compute_size("size_ldst")
+            .emit(
+                r#"
+                    let i = ldst_form(bits, offset.into(), sink);
+                    if !flags.notrap() {
+                        sink.trap(TrapCode::HeapOutOfBounds, func.srclocs[inst]);
+                    }
+                    put_ldst(i, in_reg0, out_reg0, sink);
+                "#,
+            ),
+    );
+    recipes.push(
+        EncodingRecipeBuilder::new("st", &formats.store, 4)
+            .operands_in(vec![gpr, gpr])
+            .clobbers_flags(false)
+            .compute_size("size_ldst")
+            .emit(
+                r#"
+                    let i = ldst_form(bits, offset.into(), sink);
+                    if !flags.notrap() {
+                        sink.trap(TrapCode::HeapOutOfBounds, func.srclocs[inst]);
+                    }
+                    put_ldst(i, in_reg1, in_reg0, sink);
+                "#,
+            ),
+    );
+    recipes.push(
+        EncodingRecipeBuilder::new("fst", &formats.store, 4)
+            .operands_in(vec![fpr, gpr])
+            .clobbers_flags(false)
+            .compute_size("size_ldst")
+            .emit(
+                r#"
+                    let i = ldst_form(bits, offset.into(), sink);
+                    if !flags.notrap() {
+                        sink.trap(TrapCode::HeapOutOfBounds, func.srclocs[inst]);
+                    }
+                    put_ldst(i, in_reg1, in_reg0, sink);
+                "#,
+            ),
+    );
+
+    // Spills and fills address the stack slot relative to the stack pointer.
+    recipes.push(
+        EncodingRecipeBuilder::new("spill", &formats.unary, 4)
+            .operands_in(vec![gpr])
+            .operands_out(vec![stack_gpr])
+            .clobbers_flags(false)
+            .compute_size("size_stack_ldst")
+            .emit(
+                r#"
+                    let i = ldst_form(bits, out_stk0.offset.into(), sink);
+                    sink.trap(TrapCode::StackOverflow, func.srclocs[inst]);
+                    put_ldst(i, stk_base(out_stk0.base), in_reg0, sink);
+                "#,
+            ),
+    );
+    recipes.push(
+        EncodingRecipeBuilder::new("fspill", &formats.unary, 4)
+            .operands_in(vec![fpr])
+            .operands_out(vec![stack_fpr])
+            .clobbers_flags(false)
+            .compute_size("size_stack_ldst")
+            .emit(
+                r#"
+                    let i = ldst_form(bits, out_stk0.offset.into(), sink);
+                    sink.trap(TrapCode::StackOverflow, func.srclocs[inst]);
+                    put_ldst(i, stk_base(out_stk0.base), in_reg0, sink);
+                "#,
+            ),
+    );
+    recipes.push(
+        EncodingRecipeBuilder::new("regspill", &formats.reg_spill, 4)
+            .operands_in(vec![gpr])
+            .clobbers_flags(false)
+            .compute_size("size_stack_ldst")
+            .emit(
+                r#"
+                    let dst = stk_ref(dst, func);
+                    let i = ldst_form(bits, dst.offset.into(), sink);
+                    sink.trap(TrapCode::StackOverflow, func.srclocs[inst]);
+                    put_ldst(i, stk_base(dst.base), src, sink);
+                "#,
+            ),
+    );
+    recipes.push(
+        EncodingRecipeBuilder::new("fregspill", &formats.reg_spill, 4)
+            .operands_in(vec![fpr])
+            .clobbers_flags(false)
+            .compute_size("size_stack_ldst")
+            .emit(
+                r#"
+                    let dst = stk_ref(dst, func);
+                    let i = ldst_form(bits, dst.offset.into(), sink);
+                    sink.trap(TrapCode::StackOverflow, func.srclocs[inst]);
+                    put_ldst(i, stk_base(dst.base), src, sink);
+                "#,
+            ),
+    );
+    recipes.push(
+        EncodingRecipeBuilder::new("fill", &formats.unary, 4)
+            .operands_in(vec![stack_gpr])
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .compute_size("size_stack_ldst")
+            .emit(
+                r#"
+                    let i = ldst_form(bits, in_stk0.offset.into(), sink);
+                    put_ldst(i, stk_base(in_stk0.base), out_reg0, sink);
+                "#,
+            ),
+    );
+    recipes.push(
+        EncodingRecipeBuilder::new("ffill", &formats.unary, 4)
+            .operands_in(vec![stack_fpr])
+            .operands_out(vec![fpr])
+            .clobbers_flags(false)
+            .compute_size("size_stack_ldst")
+            .emit(
+                r#"
+                    let i = ldst_form(bits, in_stk0.offset.into(), sink);
+                    put_ldst(i, stk_base(in_stk0.base), out_reg0, sink);
+                "#,
+            ),
+    );
+    recipes.push(
+        EncodingRecipeBuilder::new("regfill", &formats.reg_fill, 4)
+            .operands_in(vec![stack_gpr])
+            .clobbers_flags(false)
+            .compute_size("size_stack_ldst")
+            .emit(
+                r#"
+                    let src = stk_ref(src, func);
+                    let i = ldst_form(bits, src.offset.into(), sink);
+                    put_ldst(i, stk_base(src.base), dst, sink);
+                "#,
+            ),
+    );
+    recipes.push(
+        EncodingRecipeBuilder::new("fregfill", &formats.reg_fill, 4)
+            .operands_in(vec![stack_fpr])
+            .clobbers_flags(false)
+            .compute_size("size_stack_ldst")
+            .emit(
+                r#"
+                    let src = stk_ref(src, func);
+                    let i = ldst_form(bits, src.offset.into(), sink);
+                    put_ldst(i, stk_base(src.base), dst, sink);
+                "#,
+            ),
+    );
+
+    // Address of a stack slot.
+    recipes.push(
+        EncodingRecipeBuilder::new("spaddr", &formats.stack_load, 4)
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .compute_size("size_stack_addr")
+            .emit(
+                r#"
+                    let sp = stk_ref(stack_slot, func);
+                    let offset: i64 = offset.into();
+                    let offset = i64::from(sp.offset) + offset;
+                    put_add_imm(stk_base(sp.base), offset, out_reg0, sink);
+                "#,
+            ),
+    );
+
+    // Stack pointer adjustments for the prologue and the epilogue.
+    recipes.push(
+        EncodingRecipeBuilder::new("adjustsp_down", &formats.unary_imm, 4)
+            .clobbers_flags(false)
+            .compute_size("size_adjust_sp")
+            .emit(
+                r#"
+                    let amount: i64 = imm.into();
+                    put_add_imm(RU::x31.into(), -amount, RU::x31.into(), sink);
+                "#,
+            ),
+    );
+    recipes.push(
+        EncodingRecipeBuilder::new("adjustsp_up", &formats.unary_imm, 4)
+            .clobbers_flags(false)
+            .compute_size("size_adjust_sp")
+            .emit(
+                r#"
+                    put_add_imm(RU::x31.into(), imm.into(), RU::x31.into(), sink);
+                "#,
+            ),
+    );
+
+    // A copy involving the stack pointer, which `orr` can't read or write.
+    recipes.push(
+        EncodingRecipeBuilder::new("copysp", &formats.copy_special, 4)
+            .clobbers_flags(false)
+            .emit("put_add_imm(src, 0, dst, sink);"),
+    );
+
+    // Pushes and pops of pairs and single registers. The stack pointer stays 16-byte aligned.
+    recipes.push(
+        EncodingRecipeBuilder::new("pushp", &formats.binary, 4)
+            .operands_in(vec![gpr, gpr])
+            .clobbers_flags(false)
+            .emit(
+                r#"
+                    sink.trap(TrapCode::StackOverflow, func.srclocs[inst]);
+                    put_ldst_pair(bits, -16, in_reg0, in_reg1, sink);
+                "#,
+            ),
+    );
+    recipes.push(
+        EncodingRecipeBuilder::new("fpushp", &formats.binary, 4)
+            .operands_in(vec![fpr, fpr])
+            .clobbers_flags(false)
+            .emit(
+                r#"
+                    sink.trap(TrapCode::StackOverflow, func.srclocs[inst]);
+                    put_ldst_pair(bits, -16, in_reg0, in_reg1, sink);
+                "#,
+            ),
+    );
+    recipes.push(
+        EncodingRecipeBuilder::new("popp", &formats.nullary, 4)
+            .operands_out(vec![gpr, gpr])
+            .clobbers_flags(false)
+            .emit("put_ldst_pair(bits, 16, out_reg0, out_reg1, sink);"),
+    );
+    recipes.push(
+        EncodingRecipeBuilder::new("fpopp", &formats.nullary, 4)
+            .operands_out(vec![fpr, fpr])
+            .clobbers_flags(false)
+            .emit("put_ldst_pair(bits, 16, out_reg0, out_reg1, sink);"),
+    );
+    recipes.push(
+        EncodingRecipeBuilder::new("push", &formats.unary, 4)
+            .operands_in(vec![gpr])
+            .clobbers_flags(false)
+            .emit(
+                r#"
+                    sink.trap(TrapCode::StackOverflow, func.srclocs[inst]);
+                    put_ldst_indexed(bits, -16, in_reg0, sink);
+                "#,
+            ),
+    );
+    recipes.push(
+        EncodingRecipeBuilder::new("fpush", &formats.unary, 4)
+            .operands_in(vec![fpr])
+            .clobbers_flags(false)
+            .emit(
+                r#"
+                    sink.trap(TrapCode::StackOverflow, func.srclocs[inst]);
+                    put_ldst_indexed(bits, -16, in_reg0, sink);
+                "#,
+            ),
+    );
+    recipes.push(
+        EncodingRecipeBuilder::new("pop", &formats.nullary, 4)
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .emit("put_ldst_indexed(bits, 16, out_reg0, sink);"),
+    );
+    recipes.push(
+        EncodingRecipeBuilder::new("fpop", &formats.nullary, 4)
+            .operands_out(vec![fpr])
+            .clobbers_flags(false)
+            .emit("put_ldst_indexed(bits, 16, out_reg0, sink);"),
+    );
+
+    // Branches.
+
+    recipes.push(
+        EncodingRecipeBuilder::new("b", &formats.jump, 4)
+            .branch_range((0, 28))
+            .clobbers_flags(false)
+            .emit("put_b(bits, branch_disp(func.offsets[destination], sink), sink);"),
+    );
+
+    // Conditional branches on the flags. The long forms branch over an unconditional branch.
+    recipes.push(
+        EncodingRecipeBuilder::new("brif", &formats.branch_int, 4)
+            .operands_in(vec![reg_nzcv])
+            .branch_range((0, 21))
+            .clobbers_flags(false)
+            .emit(
+                r#"
+                    let disp = branch_disp(func.offsets[destination], sink);
+                    put_b_cond(icc2cond(cond), disp, sink);
+                "#,
+            ),
+    );
+    recipes.push(
+        EncodingRecipeBuilder::new("brif_long", &formats.branch_int, 8)
+            .operands_in(vec![reg_nzcv])
+            .branch_range((4, 28))
+            .clobbers_flags(false)
+            .emit(
+                r#"
+                    put_b_cond(icc2cond(cond) ^ 1, 8, sink);
+                    put_b(B, branch_disp(func.offsets[destination], sink), sink);
+                "#,
+            ),
+    );
+    recipes.push(
+        EncodingRecipeBuilder::new("brff", &formats.branch_float, 4)
+            .operands_in(vec![reg_nzcv])
+            .branch_range((0, 21))
+            .clobbers_flags(false)
+            .inst_predicate(floatcc_predicate(
+                &floatccs_one_cond,
+                &*formats.branch_float,
+            ))
+            .emit(
+                r#"
+                    let disp = branch_disp(func.offsets[destination], sink);
+                    put_b_cond(fcc2cond(cond).0, disp, sink);
+                "#,
+            ),
+    );
+    // The second branch is 4 bytes further from the destination, which the range accounts for
+    // by being one bit shorter.
+    recipes.push(
+        EncodingRecipeBuilder::new("brff2", &formats.branch_float, 8)
+            .operands_in(vec![reg_nzcv])
+            .branch_range((0, 20))
+            .clobbers_flags(false)
+            .inst_predicate(floatcc_predicate(
+                &floatccs_two_conds,
+                &*formats.branch_float,
+            ))
+            .emit(
+                r#"
+                    let (cond1, cond2) = fcc2cond(cond);
+                    let disp = branch_disp(func.offsets[destination], sink);
+                    put_b_cond(cond1, disp, sink);
+                    let disp = branch_disp(func.offsets[destination], sink);
+                    put_b_cond(cond2, disp, sink);
+                "#,
+            ),
+    );
+    recipes.push(
+        EncodingRecipeBuilder::new("brff_long", &formats.branch_float, 8)
+            .operands_in(vec![reg_nzcv])
+            .branch_range((4, 28))
+            .clobbers_flags(false)
+            .inst_predicate(floatcc_predicate(
+                &floatccs_one_cond,
+                &*formats.branch_float,
+            ))
+            .emit(
+                r#"
+                    put_b_cond(fcc2cond(cond).0 ^ 1, 8, sink);
+                    put_b(B, branch_disp(func.offsets[destination], sink), sink);
+                "#,
+            ),
+    );
+    recipes.push(
+        EncodingRecipeBuilder::new("brff2_long", &formats.branch_float, 12)
+            .operands_in(vec![reg_nzcv])
+            .branch_range((8, 28))
+            .clobbers_flags(false)
+            .inst_predicate(floatcc_predicate(
+                &floatccs_two_conds,
+                &*formats.branch_float,
+            ))
+            .emit(
+                r#"
+                    let (cond1, cond2) = fcc2cond(cond);
+                    put_b_cond(cond1, 8, sink);
+                    put_b_cond(cond2 ^ 1, 8, sink);
+                    put_b(B, branch_disp(func.offsets[destination], sink), sink);
+                "#,
+            ),
+    );
+
+    // Compare a register against zero and branch.
+    recipes.push(
+        EncodingRecipeBuilder::new("cbz", &formats.branch, 4)
+            .operands_in(vec![gpr])
+            .branch_range((0, 21))
+            .clobbers_flags(false)
+            .emit(
+                r#"
+                    let disp = branch_disp(func.offsets[destination], sink);
+                    put_cbz(bits, in_reg0, disp, sink);
+                "#,
+            ),
+    );
+    recipes.push(
+        EncodingRecipeBuilder::new("cbz_long", &formats.branch, 8)
+            .operands_in(vec![gpr])
+            .branch_range((4, 28))
+            .clobbers_flags(false)
+            .emit(
+                r#"
+                    put_cbz(bits ^ CB_NZ, in_reg0, 8, sink);
+                    put_b(B, branch_disp(func.offsets[destination], sink), sink);
+                "#,
+            ),
+    );
+
+    // Jump tables are emitted after the function body, as 32-bit offsets relative to the start
+    // of the table.
+    recipes.push(
+        EncodingRecipeBuilder::new("jt_base", &formats.branch_table_base, 4)
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .emit(
+                r#"
+                    let disp = branch_disp(func.jt_offsets[table], sink);
+                    put_adr(disp, out_reg0, sink);
+                "#,
+            ),
+    );
+    recipes.push(
+        EncodingRecipeBuilder::new("jt_entry", &formats.branch_table_entry, 4)
+            .operands_in(vec![gpr, gpr])
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .inst_predicate(InstructionPredicate::new_is_field_equal(
+                &*formats.branch_table_entry,
+                "imm",
+                "4".into(),
+            ))
+            .emit("put_ldst_reg_scaled(bits, in_reg1, in_reg0, out_reg0, sink);"),
+    );
+    recipes.push(
+        EncodingRecipeBuilder::new("indirect_jmp", &formats.indirect_jump, 4)
+            .operands_in(vec![gpr])
+            .clobbers_flags(false)
+            .emit("put_br(bits, in_reg0, sink);"),
+    );
+
+    // The return address is provided as a special-purpose link argument.
+    recipes.push(
+        EncodingRecipeBuilder::new("ret", &formats.multiary, 4)
+            .clobbers_flags(false)
+            .emit("put_br(bits, RU::x30.into(), sink);"),
+    );
+
+    // Calls.
+
+    recipes.push(EncodingRecipeBuilder::new("call", &formats.call, 4).emit(
+        r#"
+                sink.reloc_external(Reloc::Arm64Call,
+                                    &func.dfg.ext_funcs[func_ref].name,
+                                    0);
+                put_b(bits, 0, sink);
+            "#,
+    ));
+    recipes.push(
+        EncodingRecipeBuilder::new("call_r", &formats.call_indirect, 4)
+            .operands_in(vec![gpr])
+            .emit("put_br(bits, in_reg0, sink);"),
+    );
+
+    // Invokes are calls that also record their landing pad at the return address.
+    recipes.push(
+        EncodingRecipeBuilder::new("invoke", &formats.invoke, 4).emit(
+            r#"
+                sink.reloc_external(Reloc::Arm64Call,
+                                    &func.dfg.ext_funcs[func_ref].name,
+                                    0);
+                put_b(bits, 0, sink);
+                sink.add_call_site(func.offsets[destination], func.srclocs[inst]);
+            "#,
+        ),
+    );
+    recipes.push(
+        EncodingRecipeBuilder::new("invoke_r", &formats.invoke_indirect, 4)
+            .operands_in(vec![gpr])
+            .emit(
+                r#"
+                    put_br(bits, in_reg0, sink);
+                    sink.add_call_site(func.offsets[destination], func.srclocs[inst]);
+                "#,
+            ),
+    );
+
+    // Addresses of functions and symbols.
+
+    // A PC-relative `adrp` and `add` pair, for colocated symbols.
+    recipes.push(
+        EncodingRecipeBuilder::new("fnaddr", &formats.func_addr, 8)
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .emit(
+                r#"
+                    put_adrp_add(&func.dfg.ext_funcs[func_ref].name, 0, out_reg0, sink);
+                "#,
+            ),
+    );
+    recipes.push(
+        EncodingRecipeBuilder::new("gvaddr", &formats.unary_global_value, 8)
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .emit(
+                r#"
+                    put_adrp_add(
+                        &func.global_values[global_value].symbol_name(),
+                        0,
+                        out_reg0,
+                        sink,
+                    );
+                "#,
+            ),
+    );
+
+    // An `adrp` and `ldr` pair reading the address from the GOT, for PIC.
+    recipes.push(
+        EncodingRecipeBuilder::new("got_fnaddr", &formats.func_addr, 8)
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .emit(
+                r#"
+                    put_adrp_ldr_got(&func.dfg.ext_funcs[func_ref].name, out_reg0, sink);
+                "#,
+            ),
+    );
+    recipes.push(
+        EncodingRecipeBuilder::new("got_gvaddr", &formats.unary_global_value, 8)
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .emit(
+                r#"
+                    put_adrp_ldr_got(
+                        &func.global_values[global_value].symbol_name(),
+                        out_reg0,
+                        sink,
+                    );
+                "#,
+            ),
+    );
+
+    // A literal load of an absolute address placed inline, for anything else.
+    recipes.push(
+        EncodingRecipeBuilder::new("abs_fnaddr", &formats.func_addr, 16)
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .emit(
+                r#"
+                    put_abs_literal(&func.dfg.ext_funcs[func_ref].name, out_reg0, sink);
+                "#,
+            ),
+    );
+    recipes.push(
+        EncodingRecipeBuilder::new("abs_gvaddr", &formats.unary_global_value, 16)
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .emit(
+                r#"
+                    put_abs_literal(
+                        &func.global_values[global_value].symbol_name(),
+                        out_reg0,
+                        sink,
+                    );
+                "#,
+            ),
+    );
+
+    // Traps.
+
+    recipes.push(
+        EncodingRecipeBuilder::new("trap", &formats.trap, 4)
+            .clobbers_flags(false)
+            .emit(
+                r#"
+                    sink.trap(code, func.srclocs[inst]);
+                    put_udf(sink);
+                "#,
+            ),
+    );
+    recipes.push(
+        EncodingRecipeBuilder::new("debugtrap", &formats.nullary, 4)
+            .clobbers_flags(false)
+            .emit("sink.put4(BRK_DEBUG);"),
+    );
+
+    // Conditional traps branch over an `udf`.
+    recipes.push(
+        EncodingRecipeBuilder::new("trapif", &formats.int_cond_trap, 8)
+            .operands_in(vec![reg_nzcv])
+            .clobbers_flags(false)
+            .emit(
+                r#"
+                    put_b_cond(icc2cond(cond) ^ 1, 8, sink);
+                    sink.trap(code, func.srclocs[inst]);
+                    put_udf(sink);
+                "#,
+            ),
+    );
+    recipes.push(
+        EncodingRecipeBuilder::new("trapff", &formats.float_cond_trap, 8)
+            .operands_in(vec![reg_nzcv])
+            .clobbers_flags(false)
+            .inst_predicate(floatcc_predicate(
+                &floatccs_one_cond,
+                &*formats.float_cond_trap,
+            ))
+            .emit(
+                r#"
+                    put_b_cond(fcc2cond(cond).0 ^ 1, 8, sink);
+                    sink.trap(code, func.srclocs[inst]);
+                    put_udf(sink);
+                "#,
+            ),
+    );
+    recipes.push(
+        EncodingRecipeBuilder::new("trapff2", &formats.float_cond_trap, 12)
+            .operands_in(vec![reg_nzcv])
+            .clobbers_flags(false)
+            .inst_predicate(floatcc_predicate(
+                &floatccs_two_conds,
+                &*formats.float_cond_trap,
+            ))
+            .emit(
+                r#"
+                    let (cond1, cond2) = fcc2cond(cond);
+                    put_b_cond(cond1, 8, sink);
+                    put_b_cond(cond2 ^ 1, 8, sink);
+                    sink.trap(code, func.srclocs[inst]);
+                    put_udf(sink);
+                "#,
+            ),
+    );
+    recipes.push(
+        EncodingRecipeBuilder::new("trapz", &formats.cond_trap, 8)
+            .operands_in(vec![gpr])
+            .clobbers_flags(false)
+            .emit(
+                r#"
+                    put_cbz(bits, in_reg0, 8, sink);
+                    sink.trap(code, func.srclocs[inst]);
+                    put_udf(sink);
+                "#,
+            ),
+    );
+
+    // Floating point.
+
+    recipes.push(
+        EncodingRecipeBuilder::new("fp2", &formats.binary, 4)
+            .operands_in(vec![fpr, fpr])
+            .operands_out(vec![fpr])
+            .clobbers_flags(false)
+            .emit("put_fp2(bits, in_reg0, in_reg1, out_reg0, sink);"),
+    );
+    recipes.push(
+        EncodingRecipeBuilder::new("fp1", &formats.unary, 4)
+            .operands_in(vec![fpr])
+            .operands_out(vec![fpr])
+            .clobbers_flags(false)
+            .emit("put_fp1(bits, in_reg0, out_reg0, sink);"),
+    );
+    recipes.push(
+        EncodingRecipeBuilder::new("fp3", &formats.ternary, 4)
+            .operands_in(vec![fpr, fpr, fpr])
+            .operands_out(vec![fpr])
+            .clobbers_flags(false)
+            .emit("put_fp3(bits, in_reg0, in_reg1, in_reg2, out_reg0, sink);"),
+    );
+
+    // Floating point comparisons followed by a `cset`.
+    recipes.push(
+        EncodingRecipeBuilder::new("fcmp", &formats.float_compare, 8)
+            .operands_in(vec![fpr, fpr])
+            .operands_out(vec![gpr])
+            .inst_predicate(floatcc_predicate(
+                &floatccs_one_cond,
+                &*formats.float_compare,
+            ))
+            .emit(
+                r#"
+                    put_fcmp(bits, in_reg0, in_reg1, sink);
+                    put_cset(fcc2cond(cond).0, out_reg0, sink);
+                "#,
+            ),
+    );
+    recipes.push(
+        EncodingRecipeBuilder::new("fcmp2", &formats.float_compare, 12)
+            .operands_in(vec![fpr, fpr])
+            .operands_out(vec![gpr])
+            .inst_predicate(floatcc_predicate(
+                &floatccs_two_conds,
+                &*formats.float_compare,
+            ))
+            .emit(
+                r#"
+                    put_fcmp(bits, in_reg0, in_reg1, sink);
+                    put_cset_fcc(cond, out_reg0, sink);
+                "#,
+            ),
+    );
+    recipes.push(
+        EncodingRecipeBuilder::new("ffcmp", &formats.binary, 4)
+            .operands_in(vec![fpr, fpr])
+            .operands_out(vec![reg_nzcv])
+            .emit("put_fcmp(bits, in_reg0, in_reg1, sink);"),
+    );
+
+    // Conversions and moves between the integer and floating point registers.
+    recipes.push(
+        EncodingRecipeBuilder::new("fpint", &formats.unary, 4)
+            .operands_in(vec![fpr])
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .emit("put_fpint(bits, in_reg0, out_reg0, sink);"),
+    );
+    recipes.push(
+        EncodingRecipeBuilder::new("intfp", &formats.unary, 4)
+            .operands_in(vec![gpr])
+            .operands_out(vec![fpr])
+            .clobbers_flags(false)
+            .emit("put_fpint(bits, in_reg0, out_reg0, sink);"),
+    );
+
+    // Copies of floating point and vector registers move all 128 bits with `orr`.
+    recipes.push(
+        EncodingRecipeBuilder::new("fmov", &formats.unary, 4)
+            .operands_in(vec![fpr])
+            .operands_out(vec![fpr])
+            .clobbers_flags(false)
+            .emit("put_vrrr(bits, in_reg0, in_reg0, out_reg0, sink);"),
+    );
+    recipes.push(
+        EncodingRecipeBuilder::new("frmov", &formats.reg_move, 4)
+            .operands_in(vec![fpr])
+            .clobbers_flags(false)
+            .emit("put_vrrr(bits, src, src, dst, sink);"),
+    );
+    recipes.push(
+        EncodingRecipeBuilder::new("fcopytossa", &formats.copy_to_ssa, 4)
+            .operands_out(vec![fpr])
+            .clobbers_flags(false)
+            .emit("put_vrrr(bits, src, src, out_reg0, sink);"),
+    );
+
+    // Floating point zero, from `movi`.
+    recipes.push(
+        EncodingRecipeBuilder::new("f32zero", &formats.unary_ieee32, 4)
+            .operands_out(vec![fpr])
+            .clobbers_flags(false)
+            .inst_predicate(InstructionPredicate::new_is_zero_32bit_float(
+                &*formats.unary_ieee32,
+                "imm",
+            ))
+            .emit("put_movi(bits, out_reg0, sink);"),
+    );
+    recipes.push(
+        EncodingRecipeBuilder::new("f64zero", &formats.unary_ieee64, 4)
+            .operands_out(vec![fpr])
+            .clobbers_flags(false)
+            .inst_predicate(InstructionPredicate::new_is_zero_64bit_float(
+                &*formats.unary_ieee64,
+                "imm",
+            ))
+            .emit("put_movi(bits, out_reg0, sink);"),
+    );
+
+    // SIMD.
+
+    // Three registers of the same type. This also covers the bitwise operations on scalar
+    // floating point values, which only use the low 64 bits.
+    recipes.push(
+        EncodingRecipeBuilder::new("vrrr", &formats.binary, 4)
+            .operands_in(vec![fpr, fpr])
+            .operands_out(vec![fpr])
+            .clobbers_flags(false)
+            .emit("put_vrrr(bits, in_reg0, in_reg1, out_reg0, sink);"),
+    );
+    recipes.push(
+        EncodingRecipeBuilder::new("vmisc", &formats.unary, 4)
+            .operands_in(vec![fpr])
+            .operands_out(vec![fpr])
+            .clobbers_flags(false)
+            .emit("put_vmisc(bits, in_reg0, out_reg0, sink);"),
+    );
+
+    // Lane-wise comparisons produce a mask in a vector register.
+    recipes.push(
+        EncodingRecipeBuilder::new("vicmp", &formats.int_compare, 4)
+            .operands_in(vec![fpr, fpr])
+            .operands_out(vec![fpr])
+            .clobbers_flags(false)
+            .emit("put_vrrr(bits, in_reg0, in_reg1, out_reg0, sink);"),
+    );
+    recipes.push(
+        EncodingRecipeBuilder::new("vfcmp", &formats.float_compare, 4)
+            .operands_in(vec![fpr, fpr])
+            .operands_out(vec![fpr])
+            .clobbers_flags(false)
+            .emit("put_vrrr(bits, in_reg0, in_reg1, out_reg0, sink);"),
+    );
+
+    // Lane moves. The encoding bits are the log2 of the lane size in bytes.
+    recipes.push(
+        EncodingRecipeBuilder::new("splat_gpr", &formats.unary, 4)
+            .operands_in(vec![gpr])
+            .operands_out(vec![fpr])
+            .clobbers_flags(false)
+            .emit("put_lane(DUP_GENERAL, bits, 0, in_reg0, out_reg0, sink);"),
+    );
+    recipes.push(
+        EncodingRecipeBuilder::new("splat_fpr", &formats.unary, 4)
+            .operands_in(vec![fpr])
+            .operands_out(vec![fpr])
+            .clobbers_flags(false)
+            .emit("put_lane(DUP_ELEMENT, bits, 0, in_reg0, out_reg0, sink);"),
+    );
+    recipes.push(
+        EncodingRecipeBuilder::new("extract_gpr", &formats.extract_lane, 4)
+            .operands_in(vec![fpr])
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .emit("put_lane(UMOV, bits, lane, in_reg0, out_reg0, sink);"),
+    );
+    recipes.push(
+        EncodingRecipeBuilder::new("extract_fpr", &formats.extract_lane, 4)
+            .operands_in(vec![fpr])
+            .operands_out(vec![fpr])
+            .clobbers_flags(false)
+            .emit("put_lane(DUP_SCALAR, bits, lane, in_reg0, out_reg0, sink);"),
+    );
+    recipes.push(
+        EncodingRecipeBuilder::new("insert_gpr", &formats.insert_lane, 4)
+            .operands_in(vec![fpr, gpr])
+            .operands_out(vec![0])
+            .clobbers_flags(false)
+            .emit("put_lane(INS_GENERAL, bits, lane, in_reg1, in_reg0, sink);"),
+    );
+    recipes.push(
+        EncodingRecipeBuilder::new("insert_fpr", &formats.insert_lane, 4)
+            .operands_in(vec![fpr, fpr])
+            .operands_out(vec![0])
+            .clobbers_flags(false)
+            .emit("put_lane(INS_ELEMENT, bits, lane, in_reg1, in_reg0, sink);"),
+    );
+
+    // Vector constants are loaded from the constant pool, except for all zeros and all ones.
+    recipes.push(
+        EncodingRecipeBuilder::new("vconst", &formats.unary_const, 4)
+            .operands_out(vec![fpr])
+            .clobbers_flags(false)
+            .emit(
+                r#"
+                    let disp = branch_disp(func.dfg.constants.get_offset(constant_handle), sink);
+                    put_ldr_literal(bits, disp, out_reg0, sink);
+                "#,
+            ),
+    );
+    recipes.push(
+        EncodingRecipeBuilder::new("vzero", &formats.unary_const, 4)
+            .operands_out(vec![fpr])
+            .clobbers_flags(false)
+            .emit("put_movi(bits, out_reg0, sink);"),
+    );
+    recipes.push(
+        EncodingRecipeBuilder::new("vones", &formats.unary_const, 4)
+            .operands_out(vec![fpr])
+            .clobbers_flags(false)
+            .emit("put_movi(bits, out_reg0, sink);"),
+    );
+
+    recipes
+}
@@ -1,7 +1,9 @@
 use crate::cdsl::ast::{Apply, Expr, Literal, VarPool};
 use crate::cdsl::encodings::{Encoding, EncodingBuilder};
+use crate::cdsl::instructions::BindParameter::Any;
 use crate::cdsl::instructions::{
-    Bindable, BoundInstruction, InstSpec, InstructionPredicateNode, InstructionPredicateRegistry,
+    Bindable, BoundInstruction, InstSpec, InstructionPredicate, InstructionPredicateNode,
+    InstructionPredicateRegistry,
 };
 use crate::cdsl::recipes::{EncodingRecipeNumber, Recipes};
 use crate::cdsl::settings::SettingGroup;
@@ -105,6 +107,76 @@ fn lui_bits() -> u16 {
     0b01101
 }
 
+fn load_fp_bits(funct3: u16) -> u16 {
+    assert!(funct3 <= 0b111);
+    0b00001 | (funct3 << 5)
+}
+
+fn store_fp_bits(funct3: u16) -> u16 {
+    assert!(funct3 <= 0b111);
+    0b01001 | (funct3 << 5)
+}
+
+fn opfp_bits(funct3: u16, funct7: u16) -> u16 {
+    assert!(funct3 <= 0b111);
+    assert!(funct7 <= 0b111_1111);
+    0b10100 | (funct3 << 5) | (funct7 << 8)
+}
+
+// The OP-FP instructions which use the rs2 field to select the operation have their own encbits
+// layout: `funct3 | (funct7 << 3) | (rs2 << 10)`.
+fn opfp_unary_bits(funct3: u16, funct7: u16, rs2: u16) -> u16 {
+    assert!(funct3 <= 0b111);
+    assert!(funct7 <= 0b111_1111);
+    assert!(rs2 <= 0b1_1111);
+    funct3 | (funct7 << 3) | (rs2 << 10)
+}
+
+fn fmadd_bits(funct3: u16, funct2: u16) -> u16 {
+    assert!(funct3 <= 0b111);
+    assert!(funct2 <= 0b11);
+    0b10000 | (funct3 << 5) | (funct2 << 8)
+}
+
+// The aq and rl bits of the AMO instructions are left clear, and set from the instruction's
+// memory ordering when emitting it.
+fn amo_bits(funct3: u16, funct5: u16) -> u16 {
+    assert!(funct3 <= 0b111);
+    assert!(funct5 <= 0b1_1111);
+    0b01011 | (funct3 << 5) | (funct5 << 10)
+}
+
+// Encbits for the 16-bit compressed recipes are the instruction itself with all the register and
+// immediate fields set to zero.
+
+fn cr_bits(funct4: u16, op: u16) -> u16 {
+    assert!(funct4 <= 0b1111);
+    assert!(op <= 0b11);
+    (funct4 << 12) | op
+}
+
+fn ci_bits(funct3: u16, op: u16) -> u16 {
+    assert!(funct3 <= 0b111);
+    assert!(op <= 0b11);
+    (funct3 << 13) | op
+}
+
+fn cj_bits(funct3: u16, op: u16) -> u16 {
+    ci_bits(funct3, op)
+}
+
+fn cb_bits(funct3: u16, funct2: u16, op: u16) -> u16 {
+    assert!(funct2 <= 0b11);
+    ci_bits(funct3, op) | (funct2 << 10)
+}
+
+fn ca_bits(funct6: u16, funct2: u16, op: u16) -> u16 {
+    assert!(funct6 <= 0b11_1111);
+    assert!(funct2 <= 0b11);
+    assert!(op <= 0b11);
+    (funct6 << 10) | (funct2 << 5) | op
+}
+
 pub(crate) fn define<'defs>(
     shared_defs: &'defs SharedDefinitions,
     isa_settings: &SettingGroup,
@@ -171,6 +243,7 @@ pub(crate) fn define<'defs>(
 
     // Predicates shorthands.
     let use_m = isa_settings.predicate_by_name("use_m");
+    let use_c = isa_settings.predicate_by_name("use_c");
 
     // Definitions.
     let mut e = PerCpuModeEncodings::new(&recipes.recipes);
@@ -427,5 +500,539 @@ pub(crate) fn define<'defs>(
     e.add32(e.enc(copy_to_ssa.bind(R32), r_copytossa, opimm_bits(0b000, 0)));
     e.add64(e.enc(copy_to_ssa.bind(R64), r_copytossa, opimm_bits(0b000, 0)));
 
+    define_float(&mut e, shared_defs, isa_settings, recipes);
+    define_atomics(&mut e, shared_defs, isa_settings, recipes);
+
+    // "C" Standard Extension for Compressed Instructions.
+    //
+    // These encodings come last so they are only used when shrinking instructions. They are
+    // gated by the `use_c` flag.
+    let r_ca = recipes.by_name("CA");
+    let r_cb = recipes.by_name("CB");
+    let r_cb_shamt = recipes.by_name("CBshamt");
+    let r_ci = recipes.by_name("CI");
+    let r_ci_shamt = recipes.by_name("CIshamt");
+    let r_ciz = recipes.by_name("CIz");
+    let r_cj = recipes.by_name("CJ");
+    let r_cr = recipes.by_name("CR");
+    let r_cr_call = recipes.by_name("CRcall");
+    let r_cr_copy = recipes.by_name("CRcopy");
+    let r_cr_ret = recipes.by_name("CRret");
+
+    // Compressed instructions operate on full registers, so only the native integer width is
+    // encoded in each mode, except for the 'w' variants in RV64.
+    e.add32(
+        e.enc(iadd.bind(I32), r_cr, cr_bits(0b1001, 0b10))
+            .isa_predicate(use_c),
+    );
+    e.add64(
+        e.enc(iadd.bind(I64), r_cr, cr_bits(0b1001, 0b10))
+            .isa_predicate(use_c),
+    );
+    e.add64(
+        e.enc(iadd.bind(I32), r_ca, ca_bits(0b10_0111, 0b01, 0b01))
+            .isa_predicate(use_c),
+    );
+    e.add64(
+        e.enc(isub.bind(I32), r_ca, ca_bits(0b10_0111, 0b00, 0b01))
+            .isa_predicate(use_c),
+    );
+    for &(inst, f2) in &[(isub, 0b00), (bxor, 0b01), (bor, 0b10), (band, 0b11)] {
+        e.add32(
+            e.enc(inst.bind(I32), r_ca, ca_bits(0b10_0011, f2, 0b01))
+                .isa_predicate(use_c),
+        );
+        e.add64(
+            e.enc(inst.bind(I64), r_ca, ca_bits(0b10_0011, f2, 0b01))
+                .isa_predicate(use_c),
+        );
+    }
+
+    e.add32(
+        e.enc(iadd_imm.bind(I32), r_ci, ci_bits(0b000, 0b01))
+            .isa_predicate(use_c),
+    );
+    e.add64(
+        e.enc(iadd_imm.bind(I64), r_ci, ci_bits(0b000, 0b01))
+            .isa_predicate(use_c),
+    );
+    e.add64(
+        e.enc(iadd_imm.bind(I32), r_ci, ci_bits(0b001, 0b01))
+            .isa_predicate(use_c),
+    );
+    e.add32(
+        e.enc(band_imm.bind(I32), r_cb, cb_bits(0b100, 0b10, 0b01))
+            .isa_predicate(use_c),
+    );
+    e.add64(
+        e.enc(band_imm.bind(I64), r_cb, cb_bits(0b100, 0b10, 0b01))
+            .isa_predicate(use_c),
+    );
+
+    e.add32(
+        e.enc(iconst.bind(I32), r_ciz, ci_bits(0b010, 0b01))
+            .isa_predicate(use_c),
+    );
+    e.add64(
+        e.enc(iconst.bind(I32), r_ciz, ci_bits(0b010, 0b01))
+            .isa_predicate(use_c),
+    );
+    e.add64(
+        e.enc(iconst.bind(I64), r_ciz, ci_bits(0b010, 0b01))
+            .isa_predicate(use_c),
+    );
+
+    e.add32(
+        e.enc(ishl_imm.bind(I32), r_ci_shamt, ci_bits(0b000, 0b10))
+            .isa_predicate(use_c),
+    );
+    e.add64(
+        e.enc(ishl_imm.bind(I64), r_ci_shamt, ci_bits(0b000, 0b10))
+            .isa_predicate(use_c),
+    );
+    for &(inst, f2) in &[(ushr_imm, 0b00), (sshr_imm, 0b01)] {
+        e.add32(
+            e.enc(inst.bind(I32), r_cb_shamt, cb_bits(0b100, f2, 0b01))
+                .isa_predicate(use_c),
+        );
+        e.add64(
+            e.enc(inst.bind(I64), r_cb_shamt, cb_bits(0b100, f2, 0b01))
+                .isa_predicate(use_c),
+        );
+    }
+
+    e.add32(
+        e.enc(copy.bind(I32), r_cr_copy, cr_bits(0b1000, 0b10))
+            .isa_predicate(use_c),
+    );
+    e.add64(
+        e.enc(copy.bind(I64), r_cr_copy, cr_bits(0b1000, 0b10))
+            .isa_predicate(use_c),
+    );
+    e.add32(
+        e.enc(copy.bind(B1), r_cr_copy, cr_bits(0b1000, 0b10))
+            .isa_predicate(use_c),
+    );
+    e.add64(
+        e.enc(copy.bind(B1), r_cr_copy, cr_bits(0b1000, 0b10))
+            .isa_predicate(use_c),
+    );
+
+    e.add32(e.enc(jump, r_cj, cj_bits(0b101, 0b01)).isa_predicate(use_c));
+    e.add64(e.enc(jump, r_cj, cj_bits(0b101, 0b01)).isa_predicate(use_c));
+    e.add32(
+        e.enc(return_, r_cr_ret, cr_bits(0b1000, 0b10))
+            .isa_predicate(use_c),
+    );
+    e.add64(
+        e.enc(return_, r_cr_ret, cr_bits(0b1000, 0b10))
+            .isa_predicate(use_c),
+    );
+    e.add32(
+        e.enc(call_indirect.bind(I32), r_cr_call, cr_bits(0b1001, 0b10))
+            .isa_predicate(use_c),
+    );
+    e.add64(
+        e.enc(call_indirect.bind(I64), r_cr_call, cr_bits(0b1001, 0b10))
+            .isa_predicate(use_c),
+    );
+
     e
 }
+
+/// "F" and "D" Standard Extensions for single and double precision floating point.
+fn define_float(
+    e: &mut PerCpuModeEncodings,
+    shared_defs: &SharedDefinitions,
+    isa_settings: &SettingGroup,
+    recipes: &RecipeGroup,
+) {
+    let shared = &shared_defs.instructions;
+
+    // Instructions shorthands.
+    let bitcast = shared.by_name("bitcast");
+    let copy = shared.by_name("copy");
+    let copy_to_ssa = shared.by_name("copy_to_ssa");
+    let fabs = shared.by_name("fabs");
+    let fadd = shared.by_name("fadd");
+    let fcmp = shared.by_name("fcmp");
+    let fcopysign = shared.by_name("fcopysign");
+    let fcvt_from_sint = shared.by_name("fcvt_from_sint");
+    let fcvt_from_uint = shared.by_name("fcvt_from_uint");
+    let fdemote = shared.by_name("fdemote");
+    let fdiv = shared.by_name("fdiv");
+    let fill = shared.by_name("fill");
+    let fill_nop = shared.by_name("fill_nop");
+    let fma = shared.by_name("fma");
+    let fmul = shared.by_name("fmul");
+    let fneg = shared.by_name("fneg");
+    let fpromote = shared.by_name("fpromote");
+    let sqrt = shared.by_name("sqrt");
+    let fsub = shared.by_name("fsub");
+    let load = shared.by_name("load");
+    let regmove = shared.by_name("regmove");
+    let spill = shared.by_name("spill");
+    let store = shared.by_name("store");
+
+    // Recipes shorthands, prefixed with r_.
+    let r_fcopytossa = recipes.by_name("fcopytossa");
+    let r_ffillnull = recipes.by_name("ffillnull");
+    let r_fp_fi = recipes.by_name("FPfi");
+    let r_fp_sp = recipes.by_name("FPsp");
+    let r_ifld = recipes.by_name("Ifld");
+    let r_r4 = recipes.by_name("R4");
+    let r_rf = recipes.by_name("Rf");
+    let r_rfcmp = recipes.by_name("Rfcmp");
+    let r_rfcmp_not = recipes.by_name("Rfcmpnot");
+    let r_rfcmp_not_swap = recipes.by_name("Rfcmpnotswap");
+    let r_rfcmp_swap = recipes.by_name("Rfcmpswap");
+    let r_rfcvtfi = recipes.by_name("Rfcvtfi");
+    let r_rfcvtif = recipes.by_name("Rfcvtif");
+    let r_rfrmov = recipes.by_name("Rfrmov");
+    let r_rfsgnj = recipes.by_name("Rfsgnj");
+    let r_rfunary = recipes.by_name("Rfunary");
+    let r_sfst = recipes.by_name("Sfst");
+
+    // Predicates shorthands.
+    let use_f = isa_settings.predicate_by_name("use_f");
+    let use_d = isa_settings.predicate_by_name("use_d");
+
+    // Rounding modes. Arithmetic uses the dynamic rounding mode from `fcsr`, which defaults to
+    // round-to-nearest-even. Exact conversions don't round.
+    let rm_dyn = 0b111;
+    let rm_exact = 0b000;
+
+    let mut var_pool = VarPool::new();
+
+    // Helper that creates an instruction predicate for an fcmp instruction.
+    let mut fcmp_instp =
+        |bound_inst: &BoundInstruction, floatcc_field: &'static str| -> InstructionPredicateNode {
+            let x = var_pool.create("x");
+            let y = var_pool.create("y");
+            let cc = Literal::enumerator_for(&shared_defs.imm.floatcc, floatcc_field);
+            Apply::new(
+                bound_inst.clone().into(),
+                vec![Expr::Literal(cc), Expr::Var(x), Expr::Var(y)],
+            )
+            .inst_predicate(&var_pool)
+            .unwrap()
+        };
+
+    // The fmt field in funct7 is 0b00 for single precision and 0b01 for double precision.
+    for &(ty, fmt, ldst_f3, use_fd) in &[(F32, 0b00, 0b010, use_f), (F64, 0b01, 0b011, use_d)] {
+        for &(inst, f3, funct5) in &[
+            (fadd, rm_dyn, 0b00000),
+            (fsub, rm_dyn, 0b00001),
+            (fmul, rm_dyn, 0b00010),
+            (fdiv, rm_dyn, 0b00011),
+            (fcopysign, 0b000, 0b00100),
+        ] {
+            let bits = opfp_bits(f3, (funct5 << 2) | fmt);
+            e.add32(e.enc(inst.bind(ty), r_rf, bits).isa_predicate(use_fd));
+            e.add64(e.enc(inst.bind(ty), r_rf, bits).isa_predicate(use_fd));
+        }
+
+        let bits = opfp_unary_bits(rm_dyn, (0b01011 << 2) | fmt, 0);
+        e.add32(e.enc(sqrt.bind(ty), r_rfunary, bits).isa_predicate(use_fd));
+        e.add64(e.enc(sqrt.bind(ty), r_rfunary, bits).isa_predicate(use_fd));
+
+        let bits = fmadd_bits(rm_dyn, fmt);
+        e.add32(e.enc(fma.bind(ty), r_r4, bits).isa_predicate(use_fd));
+        e.add64(e.enc(fma.bind(ty), r_r4, bits).isa_predicate(use_fd));
+
+        // Sign injection: fsgnj, fsgnjn and fsgnjx with both sources the same register.
+        for &(inst, r, f3) in &[
+            (copy, r_rfsgnj, 0b000),
+            (regmove, r_rfrmov, 0b000),
+            (copy_to_ssa, r_fcopytossa, 0b000),
+            (fneg, r_rfsgnj, 0b001),
+            (fabs, r_rfsgnj, 0b010),
+        ] {
+            let bits = opfp_bits(f3, (0b00100 << 2) | fmt);
+            e.add32(e.enc(inst.bind(ty), r, bits).isa_predicate(use_fd));
+            e.add64(e.enc(inst.bind(ty), r, bits).isa_predicate(use_fd));
+        }
+
+        // Comparisons. There are only feq, flt and fle instructions, so the other condition codes
+        // swap the operands or invert the result.
+        let fcmp_ty = fcmp.bind(ty);
+        for &(cond, r, f3) in &[
+            ("eq", r_rfcmp, 0b010),
+            ("lt", r_rfcmp, 0b001),
+            ("le", r_rfcmp, 0b000),
+            ("gt", r_rfcmp_swap, 0b001),
+            ("ge", r_rfcmp_swap, 0b000),
+            ("ne", r_rfcmp_not, 0b010),
+            ("uge", r_rfcmp_not, 0b001),
+            ("ugt", r_rfcmp_not, 0b000),
+            ("ule", r_rfcmp_not_swap, 0b001),
+            ("ult", r_rfcmp_not_swap, 0b000),
+        ] {
+            let bits = opfp_bits(f3, (0b10100 << 2) | fmt);
+            e.add32(
+                e.enc(fcmp_ty.clone(), r, bits)
+                    .isa_predicate(use_fd)
+                    .inst_predicate(fcmp_instp(&fcmp_ty, cond)),
+            );
+            e.add64(
+                e.enc(fcmp_ty.clone(), r, bits)
+                    .isa_predicate(use_fd)
+                    .inst_predicate(fcmp_instp(&fcmp_ty, cond)),
+            );
+        }
+
+        // Conversions from integers. The rs2 field selects the source type. Only RV64 has 64-bit
+        // integer registers.
+        for &(inst, rs2_base) in &[(fcvt_from_sint, 0b00), (fcvt_from_uint, 0b01)] {
+            // Every 32-bit integer is exactly representable as a double.
+            let rm = if ty == F64 { rm_exact } else { rm_dyn };
+            let bits = opfp_unary_bits(rm, (0b11010 << 2) | fmt, rs2_base);
+            e.add32(
+                e.enc(inst.bind(ty).bind(I32), r_rfcvtif, bits)
+                    .isa_predicate(use_fd),
+            );
+            e.add64(
+                e.enc(inst.bind(ty).bind(I32), r_rfcvtif, bits)
+                    .isa_predicate(use_fd),
+            );
+            let bits = opfp_unary_bits(rm_dyn, (0b11010 << 2) | fmt, 0b10 | rs2_base);
+            e.add64(
+                e.enc(inst.bind(ty).bind(I64), r_rfcvtif, bits)
+                    .isa_predicate(use_fd),
+            );
+        }
+
+        // Loads and stores.
+        let bits = load_fp_bits(ldst_f3);
+        e.add32(
+            e.enc(load.bind(ty).bind(Any), r_ifld, bits)
+                .isa_predicate(use_fd),
+        );
+        e.add64(
+            e.enc(load.bind(ty).bind(Any), r_ifld, bits)
+                .isa_predicate(use_fd),
+        );
+        e.add32(e.enc(fill.bind(ty), r_fp_fi, bits).isa_predicate(use_fd));
+        e.add64(e.enc(fill.bind(ty), r_fp_fi, bits).isa_predicate(use_fd));
+        let bits = store_fp_bits(ldst_f3);
+        e.add32(
+            e.enc(store.bind(ty).bind(Any), r_sfst, bits)
+                .isa_predicate(use_fd),
+        );
+        e.add64(
+            e.enc(store.bind(ty).bind(Any), r_sfst, bits)
+                .isa_predicate(use_fd),
+        );
+        e.add32(e.enc(spill.bind(ty), r_fp_sp, bits).isa_predicate(use_fd));
+        e.add64(e.enc(spill.bind(ty), r_fp_sp, bits).isa_predicate(use_fd));
+
+        // No-op fills, created by late-stage redundant-fill removal.
+        e.add32(
+            e.enc(fill_nop.bind(ty), r_ffillnull, 0)
+                .isa_predicate(use_fd),
+        );
+        e.add64(
+            e.enc(fill_nop.bind(ty), r_ffillnull, 0)
+                .isa_predicate(use_fd),
+        );
+    }
+
+    // Bit-preserving moves between register banks. Only RV64 can move a double in one
+    // instruction.
+    e.add32(
+        e.enc(
+            bitcast.bind(F32).bind(I32),
+            r_rfcvtif,
+            opfp_unary_bits(0b000, 0b111_1000, 0),
+        )
+        .isa_predicate(use_f),
+    );
+    e.add64(
+        e.enc(
+            bitcast.bind(F32).bind(I32),
+            r_rfcvtif,
+            opfp_unary_bits(0b000, 0b111_1000, 0),
+        )
+        .isa_predicate(use_f),
+    );
+    e.add32(
+        e.enc(
+            bitcast.bind(I32).bind(F32),
+            r_rfcvtfi,
+            opfp_unary_bits(0b000, 0b111_0000, 0),
+        )
+        .isa_predicate(use_f),
+    );
+    e.add64(
+        e.enc(
+            bitcast.bind(I32).bind(F32),
+            r_rfcvtfi,
+            opfp_unary_bits(0b000, 0b111_0000, 0),
+        )
+        .isa_predicate(use_f),
+    );
+    e.add64(
+        e.enc(
+            bitcast.bind(F64).bind(I64),
+            r_rfcvtif,
+            opfp_unary_bits(0b000, 0b111_1001, 0),
+        )
+        .isa_predicate(use_d),
+    );
+    e.add64(
+        e.enc(
+            bitcast.bind(I64).bind(F64),
+            r_rfcvtfi,
+            opfp_unary_bits(0b000, 0b111_0001, 0),
+        )
+        .isa_predicate(use_d),
+    );
+
+    // Conversions between single and double precision.
+    let bits = opfp_unary_bits(rm_exact, 0b010_0001, 0b00000);
+    e.add32(
+        e.enc(fpromote.bind(F64).bind(F32), r_rfunary, bits)
+            .isa_predicate(use_d),
+    );
+    e.add64(
+        e.enc(fpromote.bind(F64).bind(F32), r_rfunary, bits)
+            .isa_predicate(use_d),
+    );
+    let bits = opfp_unary_bits(rm_dyn, 0b010_0000, 0b00001);
+    e.add32(
+        e.enc(fdemote.bind(F32).bind(F64), r_rfunary, bits)
+            .isa_predicate(use_d),
+    );
+    e.add64(
+        e.enc(fdemote.bind(F32).bind(F64), r_rfunary, bits)
+            .isa_predicate(use_d),
+    );
+}
+
+/// Atomic memory accesses and the "A" Standard Extension for Atomic Instructions.
+fn define_atomics(
+    e: &mut PerCpuModeEncodings,
+    shared_defs: &SharedDefinitions,
+    isa_settings: &SettingGroup,
+    recipes: &RecipeGroup,
+) {
+    let shared = &shared_defs.instructions;
+    let formats = &shared_defs.formats;
+    let imm = &shared_defs.imm;
+
+    // Instructions shorthands.
+    let atomic_cas = shared.by_name("atomic_cas");
+    let atomic_load = shared.by_name("atomic_load");
+    let atomic_rmw = shared.by_name("atomic_rmw");
+    let atomic_store = shared.by_name("atomic_store");
+    let fence = shared.by_name("fence");
+
+    // Recipes shorthands, prefixed with r_.
+    let r_fence = recipes.by_name("fence");
+    let r_ild_atomic = recipes.by_name("Ild_atomic");
+    let r_lrsc = recipes.by_name("lrsc");
+    let r_ramo = recipes.by_name("Ramo");
+    let r_sst_atomic = recipes.by_name("Sst_atomic");
+
+    // Predicates shorthands.
+    let use_a = isa_settings.predicate_by_name("use_a");
+
+    // Naturally aligned loads and stores are atomic in the base ISA. Small integers are loaded
+    // with zero extension.
+    for &(ty, ld_f3, st_f3) in &[(I8, 0b100, 0b000), (I16, 0b101, 0b001), (I32, 0b010, 0b010)] {
+        e.add32(e.enc(
+            atomic_load.bind(ty).bind(Any),
+            r_ild_atomic,
+            load_bits(ld_f3),
+        ));
+        e.add64(e.enc(
+            atomic_load.bind(ty).bind(Any),
+            r_ild_atomic,
+            load_bits(ld_f3),
+        ));
+        e.add32(e.enc(
+            atomic_store.bind(ty).bind(Any),
+            r_sst_atomic,
+            store_bits(st_f3),
+        ));
+        e.add64(e.enc(
+            atomic_store.bind(ty).bind(Any),
+            r_sst_atomic,
+            store_bits(st_f3),
+        ));
+    }
+    e.add64(e.enc(
+        atomic_load.bind(I64).bind(Any),
+        r_ild_atomic,
+        load_bits(0b011),
+    ));
+    e.add64(e.enc(
+        atomic_store.bind(I64).bind(Any),
+        r_sst_atomic,
+        store_bits(0b011),
+    ));
+
+    e.add32(e.enc(fence, r_fence, 0));
+    e.add64(e.enc(fence, r_fence, 0));
+
+    // Read-modify-write operations with an AMO instruction. Subtraction is legalized into an
+    // addition, and there are no AMO instructions for 8-bit and 16-bit accesses.
+    for &(op, funct5) in &[
+        ("add", 0b00000),
+        ("xchg", 0b00001),
+        ("xor", 0b00100),
+        ("and", 0b01100),
+        ("or", 0b01000),
+        ("smin", 0b10000),
+        ("smax", 0b10100),
+        ("umin", 0b11000),
+        ("umax", 0b11100),
+    ] {
+        let is_op = InstructionPredicate::new_is_field_equal(
+            &*formats.atomic_rmw,
+            "op",
+            Literal::enumerator_for(&imm.atomic_rmw_op, op).to_rust_code(),
+        );
+        e.add32(
+            e.enc(
+                atomic_rmw.bind(I32).bind(Any),
+                r_ramo,
+                amo_bits(0b010, funct5),
+            )
+            .isa_predicate(use_a)
+            .inst_predicate(is_op.clone()),
+        );
+        e.add64(
+            e.enc(
+                atomic_rmw.bind(I32).bind(Any),
+                r_ramo,
+                amo_bits(0b010, funct5),
+            )
+            .isa_predicate(use_a)
+            .inst_predicate(is_op.clone()),
+        );
+        e.add64(
+            e.enc(
+                atomic_rmw.bind(I64).bind(Any),
+                r_ramo,
+                amo_bits(0b011, funct5),
+            )
+            .isa_predicate(use_a)
+            .inst_predicate(is_op),
+        );
+    }
+
+    // Compare-and-swap is a loop around lr/sc.
+    e.add32(
+        e.enc(atomic_cas.bind(I32).bind(Any), r_lrsc, amo_bits(0b010, 0))
+            .isa_predicate(use_a),
+    );
+    e.add64(
+        e.enc(atomic_cas.bind(I32).bind(Any), r_lrsc, amo_bits(0b010, 0))
+            .isa_predicate(use_a),
+    );
+    e.add64(
+        e.enc(atomic_cas.bind(I64).bind(Any), r_lrsc, amo_bits(0b011, 0))
+            .isa_predicate(use_a),
+    );
+}
@@ -47,6 +47,12 @@ fn define_settings(shared: &SettingGroup) -> SettingGroup {
         false,
     );
 
+    let has_c = setting.add_bool(
+        "has_c",
+        "Use 'C' extension instructions (compressed) when shrinking",
+        false,
+    );
+
     let shared_enable_atomics = shared.get_bool("enable_atomics");
     let shared_enable_float = shared.get_bool("enable_float");
     let shared_enable_simd = shared.get_bool("enable_simd");
@@ -59,6 +65,7 @@ fn define_settings(shared: &SettingGroup) -> SettingGroup {
         "full_float",
         predicate!(shared_enable_simd && supports_f && supports_d),
     );
+    setting.add_predicate("use_c", predicate!(has_c));
 
     setting.build()
 }
@@ -77,11 +84,15 @@ fn define_registers() -> IsaRegs {
     let float_regs = regs.add_bank(builder);
 
     let builder = RegClassBuilder::new_toplevel("GPR", int_regs);
-    regs.add_class(builder);
+    let gpr = regs.add_class(builder);
 
     let builder = RegClassBuilder::new_toplevel("FPR", float_regs);
     regs.add_class(builder);
 
+    // Registers %x8-%x15 are the only ones addressable by most compressed instructions.
+    let builder = RegClassBuilder::subclass_of("GPRC", gpr, 8, 16);
+    regs.add_class(builder);
+
     regs.build()
 }
 
@@ -1,7 +1,7 @@
 use std::collections::HashMap;
 
 use crate::cdsl::instructions::InstructionPredicate;
-use crate::cdsl::recipes::{EncodingRecipeBuilder, EncodingRecipeNumber, Recipes, Stack};
+use crate::cdsl::recipes::{EncodingRecipeBuilder, EncodingRecipeNumber, Recipes, Register, Stack};
 use crate::cdsl::regs::IsaRegs;
 use crate::shared::Definitions as SharedDefinitions;
 
@@ -49,6 +49,7 @@ pub(crate) fn define(shared_defs: &SharedDefinitions, regs: &IsaRegs) -> RecipeG
 
     // Register classes shorthands.
     let gpr = regs.class_by_name("GPR");
+    let fpr = regs.class_by_name("FPR");
 
     // Definitions.
     let mut recipes = RecipeGroup::new();
@@ -246,7 +247,7 @@ pub(crate) fn define(shared_defs: &SharedDefinitions, regs: &IsaRegs) -> RecipeG
         EncodingRecipeBuilder::new("GPsp", &formats.unary, 4)
             .operands_in(vec![gpr])
             .operands_out(vec![Stack::new(gpr)])
-            .emit("unimplemented!();"),
+            .emit("put_s(bits, stk_base(out_stk0.base), out_stk0.offset.into(), in_reg0, sink);"),
     );
 
     // Fill of a GPR.
@@ -254,7 +255,7 @@ pub(crate) fn define(shared_defs: &SharedDefinitions, regs: &IsaRegs) -> RecipeG
         EncodingRecipeBuilder::new("GPfi", &formats.unary, 4)
             .operands_in(vec![Stack::new(gpr)])
             .operands_out(vec![gpr])
-            .emit("unimplemented!();"),
+            .emit("put_i(bits, stk_base(in_stk0.base), in_stk0.offset.into(), out_reg0, sink);"),
     );
 
     // Stack-slot to same stack-slot copy, which is guaranteed to turn into a no-op.
@@ -273,6 +274,406 @@ pub(crate) fn define(shared_defs: &SharedDefinitions, regs: &IsaRegs) -> RecipeG
             .clobbers_flags(false)
             .emit(""),
     );
+    recipes.push(
+        EncodingRecipeBuilder::new("ffillnull", &formats.unary, 0)
+            .operands_in(vec![Stack::new(fpr)])
+            .operands_out(vec![fpr])
+            .clobbers_flags(false)
+            .emit(""),
+    );
+
+    define_float(&mut recipes, shared_defs, regs);
+    define_atomics(&mut recipes, shared_defs, regs);
+    define_compressed(&mut recipes, shared_defs, regs);
 
     recipes
 }
+
+/// Recipes for the 'F' and 'D' standard extensions.
+fn define_float(recipes: &mut RecipeGroup, shared_defs: &SharedDefinitions, regs: &IsaRegs) {
+    let formats = &shared_defs.formats;
+    let gpr = regs.class_by_name("GPR");
+    let fpr = regs.class_by_name("FPR");
+
+    // R-type binary float arithmetic.
+    recipes.push(
+        EncodingRecipeBuilder::new("Rf", &formats.binary, 4)
+            .operands_in(vec![fpr, fpr])
+            .operands_out(vec![fpr])
+            .emit("put_r(bits, in_reg0, in_reg1, out_reg0, sink);"),
+    );
+
+    // R-type unary float instructions where the rs2 field selects the operation. The encbits are
+    // `funct3 | (funct7 << 3) | (rs2 << 10)` and the opcode is always OP-FP.
+    recipes.push(
+        EncodingRecipeBuilder::new("Rfunary", &formats.unary, 4)
+            .operands_in(vec![fpr])
+            .operands_out(vec![fpr])
+            .emit("put_opfp(bits, in_reg0, out_reg0, sink);"),
+    );
+
+    // Same, for conversions and moves from a GPR to an FPR.
+    recipes.push(
+        EncodingRecipeBuilder::new("Rfcvtif", &formats.unary, 4)
+            .operands_in(vec![gpr])
+            .operands_out(vec![fpr])
+            .emit("put_opfp(bits, in_reg0, out_reg0, sink);"),
+    );
+
+    // Same, for moves from an FPR to a GPR.
+    recipes.push(
+        EncodingRecipeBuilder::new("Rfcvtfi", &formats.unary, 4)
+            .operands_in(vec![fpr])
+            .operands_out(vec![gpr])
+            .emit("put_opfp(bits, in_reg0, out_reg0, sink);"),
+    );
+
+    // Sign injection with both sources set to the same register. This implements float copies
+    // (`fsgnj`), `fneg` (`fsgnjn`) and `fabs` (`fsgnjx`).
+    recipes.push(
+        EncodingRecipeBuilder::new("Rfsgnj", &formats.unary, 4)
+            .operands_in(vec![fpr])
+            .operands_out(vec![fpr])
+            .emit("put_r(bits, in_reg0, in_reg0, out_reg0, sink);"),
+    );
+
+    // Same for an FPR regmove.
+    recipes.push(
+        EncodingRecipeBuilder::new("Rfrmov", &formats.reg_move, 4)
+            .operands_in(vec![fpr])
+            .emit("put_r(bits, src, src, dst, sink);"),
+    );
+
+    // Same for copy-to-SSA -- FPR regmove.
+    recipes.push(
+        EncodingRecipeBuilder::new("fcopytossa", &formats.copy_to_ssa, 4)
+            .operands_out(vec![fpr])
+            .emit("put_r(bits, src, src, out_reg0, sink);"),
+    );
+
+    // R4-type fused multiply-add. The encbits are `opcode[6:2] | (funct3 << 5) | (funct2 << 8)`.
+    recipes.push(
+        EncodingRecipeBuilder::new("R4", &formats.ternary, 4)
+            .operands_in(vec![fpr, fpr, fpr])
+            .operands_out(vec![fpr])
+            .emit("put_r4(bits, in_reg0, in_reg1, in_reg2, out_reg0, sink);"),
+    );
+
+    // R-type float comparisons writing 0 or 1 to a GPR. The `swap` variants compare the operands
+    // in the opposite order, and the `not` variants invert the result with an `xori`.
+    for &(name, swap, invert) in &[
+        ("Rfcmp", false, false),
+        ("Rfcmpswap", true, false),
+        ("Rfcmpnot", false, true),
+        ("Rfcmpnotswap", true, true),
+    ] {
+        let (rs1, rs2) = if swap {
+            ("in_reg1", "in_reg0")
+        } else {
+            ("in_reg0", "in_reg1")
+        };
+        let mut emit = format!("put_r(bits, {}, {}, out_reg0, sink);", rs1, rs2);
+        if invert {
+            emit.push_str("\nput_i(XORI_BITS, out_reg0, 1, out_reg0, sink);");
+        }
+        recipes.push(
+            EncodingRecipeBuilder::new(name, &formats.float_compare, if invert { 8 } else { 4 })
+                .operands_in(vec![fpr, fpr])
+                .operands_out(vec![gpr])
+                .emit(emit),
+        );
+    }
+
+    // I-type float load.
+    recipes.push(
+        EncodingRecipeBuilder::new("Ifld", &formats.load, 4)
+            .operands_in(vec![gpr])
+            .operands_out(vec![fpr])
+            .inst_predicate(InstructionPredicate::new_is_signed_int(
+                &formats.load,
+                "offset",
+                12,
+                0,
+            ))
+            .emit(
+                r#"
+                    if !flags.notrap() {
+                        sink.trap(TrapCode::HeapOutOfBounds, func.srclocs[inst]);
+                    }
+                    put_i(bits, in_reg0, offset.into(), out_reg0, sink);
+                "#,
+            ),
+    );
+
+    // S-type float store.
+    recipes.push(
+        EncodingRecipeBuilder::new("Sfst", &formats.store, 4)
+            .operands_in(vec![fpr, gpr])
+            .inst_predicate(InstructionPredicate::new_is_signed_int(
+                &formats.store,
+                "offset",
+                12,
+                0,
+            ))
+            .emit(
+                r#"
+                    if !flags.notrap() {
+                        sink.trap(TrapCode::HeapOutOfBounds, func.srclocs[inst]);
+                    }
+                    put_s(bits, in_reg1, offset.into(), in_reg0, sink);
+                "#,
+            ),
+    );
+
+    // Spill of an FPR.
+    recipes.push(
+        EncodingRecipeBuilder::new("FPsp", &formats.unary, 4)
+            .operands_in(vec![fpr])
+            .operands_out(vec![Stack::new(fpr)])
+            .emit(
+                r#"
+                    put_s(bits, stk_base(out_stk0.base), out_stk0.offset.into(), in_reg0, sink);
+                "#,
+            ),
+    );
+
+    // Fill of an FPR.
+    recipes.push(
+        EncodingRecipeBuilder::new("FPfi", &formats.unary, 4)
+            .operands_in(vec![Stack::new(fpr)])
+            .operands_out(vec![fpr])
+            .emit(
+                r#"
+                    put_i(bits, stk_base(in_stk0.base), in_stk0.offset.into(), out_reg0, sink);
+                "#,
+            ),
+    );
+}
+
+/// Recipes for atomic memory accesses, including the 'A' standard extension.
+fn define_atomics(recipes: &mut RecipeGroup, shared_defs: &SharedDefinitions, regs: &IsaRegs) {
+    let formats = &shared_defs.formats;
+    let gpr = regs.class_by_name("GPR");
+    let reg_x10 = Register::new(gpr, regs.regunit_by_name(gpr, "x10"));
+    let reg_x11 = Register::new(gpr, regs.regunit_by_name(gpr, "x11"));
+    let reg_x12 = Register::new(gpr, regs.regunit_by_name(gpr, "x12"));
+    let reg_x13 = Register::new(gpr, regs.regunit_by_name(gpr, "x13"));
+
+    // Aligned loads and stores are atomic. The fences required by the ordering are emitted
+    // around the access, following the mapping recommended by the RISC-V memory model.
+    recipes.push(
+        EncodingRecipeBuilder::new("Ild_atomic", &formats.atomic_load, 4)
+            .operands_in(vec![gpr])
+            .operands_out(vec![gpr])
+            .inst_predicate(InstructionPredicate::new_is_signed_int(
+                &formats.atomic_load,
+                "offset",
+                12,
+                0,
+            ))
+            .compute_size("size_plus_fences")
+            .emit(
+                r#"
+                    let (before, after) = access_fences(&func.dfg[inst]);
+                    put_fence(before, sink);
+                    if !flags.notrap() {
+                        sink.trap(TrapCode::HeapOutOfBounds, func.srclocs[inst]);
+                    }
+                    put_i(bits, in_reg0, offset.into(), out_reg0, sink);
+                    put_fence(after, sink);
+                "#,
+            ),
+    );
+
+    recipes.push(
+        EncodingRecipeBuilder::new("Sst_atomic", &formats.atomic_store, 4)
+            .operands_in(vec![gpr, gpr])
+            .inst_predicate(InstructionPredicate::new_is_signed_int(
+                &formats.atomic_store,
+                "offset",
+                12,
+                0,
+            ))
+            .compute_size("size_plus_fences")
+            .emit(
+                r#"
+                    let (before, after) = access_fences(&func.dfg[inst]);
+                    put_fence(before, sink);
+                    if !flags.notrap() {
+                        sink.trap(TrapCode::HeapOutOfBounds, func.srclocs[inst]);
+                    }
+                    put_s(bits, in_reg1, offset.into(), in_reg0, sink);
+                    put_fence(after, sink);
+                "#,
+            ),
+    );
+
+    // Memory fence.
+    recipes.push(
+        EncodingRecipeBuilder::new("fence", &formats.fence, 4)
+            .emit("put_fence(Some(fence_for(ordering)), sink);"),
+    );
+
+    // R-type atomic memory operation. The aq and rl bits are set from the ordering.
+    recipes.push(
+        EncodingRecipeBuilder::new("Ramo", &formats.atomic_rmw, 4)
+            .operands_in(vec![gpr, gpr])
+            .operands_out(vec![gpr])
+            .emit(
+                r#"
+                    if !flags.notrap() {
+                        sink.trap(TrapCode::HeapOutOfBounds, func.srclocs[inst]);
+                    }
+                    put_r(bits | amo_aqrl(ordering), in_reg1, in_reg0, out_reg0, sink);
+                "#,
+            ),
+    );
+
+    // Compare-and-swap loop using load-reserved and store-conditional:
+    //
+    //   loop: lr    a, (p)
+    //         bne   a, expected, done
+    //         sc    a, replacement, (p)
+    //         bnez  a, loop
+    //         mv    a, expected
+    //   done:
+    //
+    // The result register is written before the inputs have been read for the last time, so all
+    // operands are pinned to distinct registers: %x10 for the result, and %x11, %x12, %x13 for
+    // `expected`, `replacement` and `p`.
+    recipes.push(
+        EncodingRecipeBuilder::new("lrsc", &formats.atomic_cas, 20)
+            .operands_in(vec![reg_x11, reg_x12, reg_x13])
+            .operands_out(vec![reg_x10])
+            .emit(
+                r#"
+                    let (lr_aqrl, sc_aqrl) = lrsc_aqrl(ordering);
+                    if !flags.notrap() {
+                        sink.trap(TrapCode::HeapOutOfBounds, func.srclocs[inst]);
+                    }
+                    put_r(bits | LR_BITS | lr_aqrl, 13, 0, 10, sink);
+                    put_sb(BNE_BITS, 16, 10, 11, sink);
+                    put_r(bits | SC_BITS | sc_aqrl, 13, 12, 10, sink);
+                    put_sb(BNE_BITS, -12, 10, 0, sink);
+                    put_i(ADDI_BITS, 11, 0, 10, sink);
+                "#,
+            ),
+    );
+}
+
+/// Recipes for the 'C' standard extension.
+///
+/// The encbits of these 16-bit recipes are the instruction itself, with all the register and
+/// immediate fields set to zero. They come after the 32-bit recipes in the encoding lists so they
+/// are only picked when shrinking instructions after register allocation.
+fn define_compressed(recipes: &mut RecipeGroup, shared_defs: &SharedDefinitions, regs: &IsaRegs) {
+    let formats = &shared_defs.formats;
+    let gpr = regs.class_by_name("GPR");
+    let gprc = regs.class_by_name("GPRC");
+
+    // CR-type register-register operation with rd tied to rs1.
+    recipes.push(
+        EncodingRecipeBuilder::new("CR", &formats.binary, 2)
+            .operands_in(vec![gpr, gpr])
+            .operands_out(vec![0])
+            .emit("put_cr(bits, in_reg0, in_reg1, sink);"),
+    );
+
+    // CR-type register copy, `c.mv`.
+    recipes.push(
+        EncodingRecipeBuilder::new("CRcopy", &formats.unary, 2)
+            .operands_in(vec![gpr])
+            .operands_out(vec![gpr])
+            .emit("put_cr(bits, out_reg0, in_reg0, sink);"),
+    );
+
+    // CR-type `c.jr` to %x1 as a return instruction.
+    recipes.push(
+        EncodingRecipeBuilder::new("CRret", &formats.multiary, 2).emit("put_cr(bits, 1, 0, sink);"),
+    );
+
+    // CR-type `c.jalr` as a call_indirect.
+    recipes.push(
+        EncodingRecipeBuilder::new("CRcall", &formats.call_indirect, 2)
+            .operands_in(vec![gpr])
+            .emit("put_cr(bits, in_reg0, 0, sink);"),
+    );
+
+    // CI-type instruction with a 6-bit signed immediate and rd tied to rs1.
+    recipes.push(
+        EncodingRecipeBuilder::new("CI", &formats.binary_imm, 2)
+            .operands_in(vec![gpr])
+            .operands_out(vec![0])
+            .inst_predicate(InstructionPredicate::new_is_signed_int(
+                &*formats.binary_imm,
+                "imm",
+                6,
+                0,
+            ))
+            .emit("put_ci(bits, in_reg0, imm.into(), sink);"),
+    );
+
+    // CI-type shift by an immediate amount with rd tied to rs1.
+    recipes.push(
+        EncodingRecipeBuilder::new("CIshamt", &formats.binary_imm, 2)
+            .operands_in(vec![gpr])
+            .operands_out(vec![0])
+            .emit("put_ci(bits, in_reg0, imm.into(), sink);"),
+    );
+
+    // CI-type `c.li`, loading a 6-bit signed constant.
+    recipes.push(
+        EncodingRecipeBuilder::new("CIz", &formats.unary_imm, 2)
+            .operands_out(vec![gpr])
+            .inst_predicate(InstructionPredicate::new_is_signed_int(
+                &formats.unary_imm,
+                "imm",
+                6,
+                0,
+            ))
+            .emit("put_ci(bits, out_reg0, imm.into(), sink);"),
+    );
+
+    // CA-type register-register operation on %x8-%x15 with rd tied to rs1.
+    recipes.push(
+        EncodingRecipeBuilder::new("CA", &formats.binary, 2)
+            .operands_in(vec![gprc, gprc])
+            .operands_out(vec![0])
+            .emit("put_ca(bits, in_reg0, in_reg1, sink);"),
+    );
+
+    // CB-type operation with a 6-bit signed immediate on %x8-%x15 with rd tied to rs1.
+    recipes.push(
+        EncodingRecipeBuilder::new("CB", &formats.binary_imm, 2)
+            .operands_in(vec![gprc])
+            .operands_out(vec![0])
+            .inst_predicate(InstructionPredicate::new_is_signed_int(
+                &*formats.binary_imm,
+                "imm",
+                6,
+                0,
+            ))
+            .emit("put_cb(bits, in_reg0, imm.into(), sink);"),
+    );
+
+    // CB-type shift by an immediate amount on %x8-%x15 with rd tied to rs1.
+    recipes.push(
+        EncodingRecipeBuilder::new("CBshamt", &formats.binary_imm, 2)
+            .operands_in(vec![gprc])
+            .operands_out(vec![0])
+            .emit("put_cb(bits, in_reg0, imm.into(), sink);"),
+    );
+
+    // CJ-type unconditional branch.
+    recipes.push(
+        EncodingRecipeBuilder::new("CJ", &formats.jump, 2)
+            .branch_range((0, 12))
+            .emit(
+                r#"
+                    let dest = i64::from(func.offsets[destination]);
+                    let disp = dest - i64::from(sink.offset());
+                    put_cj(bits, disp, sink);
+                "#,
+            ),
+    );
+}
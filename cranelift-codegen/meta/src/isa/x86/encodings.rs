@@ -3,6 +3,7 @@
 use cranelift_codegen_shared::condcodes::IntCC;
 use std::collections::HashMap;
 
+use crate::cdsl::ast::Literal;
 use crate::cdsl::encodings::{Encoding, EncodingBuilder};
 use crate::cdsl::instructions::{
     vector, Bindable, Immediate, InstSpec, Instruction, InstructionGroup, InstructionPredicate,
@@ -159,6 +160,22 @@ impl PerCpuModeEncodings {
         self.enc64(inst.bind(I64), template.infer_rex().w());
     }
 
+    /// Adds I32/I64 encodings of a VEX-encoded instruction, guarded by an ISA predicate.
+    ///
+    /// Add encodings for `inst.i32` to X86_32 and X86_64.
+    /// Add encodings for `inst.i64` to X86_64 with VEX.W set.
+    fn enc_i32_i64_vex_isap(
+        &mut self,
+        inst: impl Into<InstSpec>,
+        template: Template,
+        isap: SettingPredicateNumber,
+    ) {
+        let inst: InstSpec = inst.into();
+        self.enc32_isap(inst.bind(I32), template.clone(), isap);
+        self.enc64_isap(inst.bind(I32), template.clone(), isap);
+        self.enc64_isap(inst.bind(I64), template.w(), isap);
+    }
+
     /// Adds I32/I64 encodings as appropriate for a typed instruction.
     /// All variants of REX prefix are explicitly emitted, not inferred.
     ///
@@ -330,6 +347,17 @@ impl PerCpuModeEncodings {
         self.enc64(inst, template);
     }
 
+    /// Add the same encoding/template pairing to both X86_32 and X86_64, with an ISA predicate.
+    fn enc_32_64_isap(
+        &mut self,
+        inst: impl Clone + Into<InstSpec>,
+        template: Template,
+        isap: SettingPredicateNumber,
+    ) {
+        self.enc32_isap(inst.clone(), template.clone(), isap);
+        self.enc64_isap(inst, template, isap);
+    }
+
     /// Add the same encoding/recipe pairing to both X86_32 and X86_64
     fn enc_32_64_rec(
         &mut self,
@@ -732,16 +760,24 @@ fn define_memory(
     let uload32_complex = shared.by_name("uload32_complex");
     let uload8 = shared.by_name("uload8");
     let uload8_complex = shared.by_name("uload8_complex");
+    let x86_dynamic_stack_alloc = x86.by_name("x86_dynamic_stack_alloc");
+    let x86_fld = x86.by_name("x86_fld");
+    let x86_fstp = x86.by_name("x86_fstp");
     let x86_pop = x86.by_name("x86_pop");
+    let x86_probe_page = x86.by_name("x86_probe_page");
+    let x86_probe_stack = x86.by_name("x86_probe_stack");
     let x86_push = x86.by_name("x86_push");
 
     // Shorthands for recipes.
     let rec_adjustsp = r.template("adjustsp");
     let rec_adjustsp_ib = r.template("adjustsp_ib");
     let rec_adjustsp_id = r.template("adjustsp_id");
+    let rec_dynstackalloc = r.template("dynstackalloc");
     let rec_ffillnull = r.recipe("ffillnull");
     let rec_fillnull = r.recipe("fillnull");
     let rec_fillSib32 = r.template("fillSib32");
+    let rec_fld_st0 = r.template("fld_st0");
+    let rec_fstp_st0 = r.template("fstp_st0");
     let rec_ld = r.template("ld");
     let rec_ldDisp32 = r.template("ldDisp32");
     let rec_ldDisp8 = r.template("ldDisp8");
@@ -749,6 +785,9 @@ fn define_memory(
     let rec_ldWithIndexDisp32 = r.template("ldWithIndexDisp32");
     let rec_ldWithIndexDisp8 = r.template("ldWithIndexDisp8");
     let rec_popq = r.template("popq");
+    let rec_probe_page = r.template("probe_page");
+    let rec_probestack = r.template("probestack");
+    let rec_probestack_r11 = r.template("probestack_r11");
     let rec_pushq = r.template("pushq");
     let rec_regfill32 = r.template("regfill32");
     let rec_regspill32 = r.template("regspill32");
@@ -930,6 +969,12 @@ fn define_memory(
     e.enc32(x86_pop.bind(I32), rec_popq.opcodes(&POP_REG));
     e.enc_x86_64(x86_pop.bind(I64), rec_popq.opcodes(&POP_REG));
 
+    // Floats returned in `st(0)` by the native 32-bit calling conventions.
+    e.enc32(x86_fld.bind(F32), rec_fld_st0.opcodes(&MOVSS_STORE));
+    e.enc32(x86_fld.bind(F64), rec_fld_st0.opcodes(&MOVSD_STORE));
+    e.enc32(x86_fstp.bind(F32), rec_fstp_st0.opcodes(&MOVSS_LOAD));
+    e.enc32(x86_fstp.bind(F64), rec_fstp_st0.opcodes(&MOVSD_LOAD));
+
     // Stack-slot-to-the-same-stack-slot copy, which is guaranteed to turn
     // into a no-op.
     // The same encoding is generated for both the 64- and 32-bit architectures.
@@ -949,6 +994,38 @@ fn define_memory(
         rec_adjustsp.opcodes(&SUB).rex().w(),
     );
 
+    // Dynamic stack allocations, which start with a `sub` like `adjust_sp_down`.
+    e.enc32(
+        x86_dynamic_stack_alloc.bind(I32),
+        rec_dynstackalloc.opcodes(&SUB),
+    );
+    e.enc64(
+        x86_dynamic_stack_alloc.bind(I64),
+        rec_dynstackalloc.opcodes(&SUB).rex().w(),
+    );
+
+    // Stack probes. The loop starts with a `mov` of the size to its scratch register.
+    e.enc32(
+        x86_probe_stack.bind(I32),
+        rec_probestack.opcodes(&MOV_STORE),
+    );
+    e.enc64(
+        x86_probe_stack.bind(I64),
+        rec_probestack_r11.opcodes(&MOV_STORE).rex().w(),
+    );
+    e.enc32(
+        x86_probe_page,
+        rec_probe_page.opcodes(&OR_IMM8_SIGN_EXTEND).rrr(1),
+    );
+    e.enc64(
+        x86_probe_page,
+        rec_probe_page
+            .opcodes(&OR_IMM8_SIGN_EXTEND)
+            .rrr(1)
+            .rex()
+            .w(),
+    );
+
     // Adjust SP up by an immediate (or down, with a negative immediate).
     e.enc32(adjust_sp_up_imm, rec_adjustsp_ib.opcodes(&CMP_IMM8));
     e.enc32(adjust_sp_up_imm, rec_adjustsp_id.opcodes(&CMP_IMM));
@@ -1326,8 +1403,12 @@ fn define_alu(
     let trueif = shared.by_name("trueif");
     let ushr = shared.by_name("ushr");
     let ushr_imm = shared.by_name("ushr_imm");
+    let x86_blsi = x86.by_name("x86_blsi");
+    let x86_blsmsk = x86.by_name("x86_blsmsk");
+    let x86_blsr = x86.by_name("x86_blsr");
     let x86_bsf = x86.by_name("x86_bsf");
     let x86_bsr = x86.by_name("x86_bsr");
+    let x86_bzhi = x86.by_name("x86_bzhi");
 
     // Shorthands for recipes.
     let rec_bsf_and_bsr = r.template("bsf_and_bsr");
@@ -1343,11 +1424,17 @@ fn define_alu(
     let rec_setf_abcd = r.template("setf_abcd");
     let rec_seti_abcd = r.template("seti_abcd");
     let rec_urm = r.template("urm");
+    let rec_vmulx = r.template("vmulx");
+    let rec_vr_ib = r.template("vr_ib");
+    let rec_vr_ib_neg = r.template("vr_ib_neg");
+    let rec_vrr = r.template("vrr");
+    let rec_vur = r.template("vur");
 
     // Predicates shorthands.
     let use_popcnt = settings.predicate_by_name("use_popcnt");
     let use_lzcnt = settings.predicate_by_name("use_lzcnt");
     let use_bmi1 = settings.predicate_by_name("use_bmi1");
+    let use_bmi2 = settings.predicate_by_name("use_bmi2");
 
     let band = shared.by_name("band");
     let band_imm = shared.by_name("band_imm");
@@ -1368,6 +1455,9 @@ fn define_alu(
     let isub_ifborrow = shared.by_name("isub_ifborrow");
     let isub_ifbout = shared.by_name("isub_ifbout");
     let x86_sdivmodx = x86.by_name("x86_sdivmodx");
+    let x86_smul_ifout = x86.by_name("x86_smul_ifout");
+    let x86_band_ifout = x86.by_name("x86_band_ifout");
+    let x86_band_imm_ifout = x86.by_name("x86_band_imm_ifout");
     let x86_smulx = x86.by_name("x86_smulx");
     let x86_udivmodx = x86.by_name("x86_udivmodx");
     let x86_umulx = x86.by_name("x86_umulx");
@@ -1378,11 +1468,14 @@ fn define_alu(
     let rec_mulx = r.template("mulx");
     let rec_r_ib = r.template("r_ib");
     let rec_r_id = r.template("r_id");
+    let rec_r_ib_out = r.template("r_ib_out");
+    let rec_r_id_out = r.template("r_id_out");
     let rec_rin = r.template("rin");
     let rec_rio = r.template("rio");
     let rec_rout = r.template("rout");
     let rec_rr = r.template("rr");
     let rec_rrx = r.template("rrx");
+    let rec_rrxout = r.template("rrxout");
     let rec_ur = r.template("ur");
 
     e.enc_i32_i64(iadd, rec_rr.opcodes(&ADD));
@@ -1406,6 +1499,13 @@ fn define_alu(
     e.enc_i32_i64(band_imm, rec_r_ib.opcodes(&AND_IMM8_SIGN_EXTEND).rrr(4));
     e.enc_i32_i64(band_imm, rec_r_id.opcodes(&AND_IMM).rrr(4));
 
+    e.enc_i32_i64(x86_band_ifout, rec_rout.opcodes(&AND));
+    e.enc_i32_i64(
+        x86_band_imm_ifout,
+        rec_r_ib_out.opcodes(&AND_IMM8_SIGN_EXTEND).rrr(4),
+    );
+    e.enc_i32_i64(x86_band_imm_ifout, rec_r_id_out.opcodes(&AND_IMM).rrr(4));
+
     e.enc_i32_i64(bor, rec_rr.opcodes(&OR));
     e.enc_b32_b64(bor, rec_rr.opcodes(&OR));
     e.enc_i32_i64(bor_imm, rec_r_ib.opcodes(&OR_IMM8_SIGN_EXTEND).rrr(1));
@@ -1428,10 +1528,14 @@ fn define_alu(
     e.enc_both(bxor.bind(B1), rec_rr.opcodes(&XOR));
 
     e.enc_i32_i64(imul, rec_rrx.opcodes(&IMUL));
+    e.enc_i32_i64(x86_smul_ifout, rec_rrxout.opcodes(&IMUL));
     e.enc_i32_i64(x86_sdivmodx, rec_div.opcodes(&IDIV).rrr(7));
     e.enc_i32_i64(x86_udivmodx, rec_div.opcodes(&DIV).rrr(6));
 
     e.enc_i32_i64(x86_smulx, rec_mulx.opcodes(&IMUL_RDX_RAX).rrr(5));
+    // MULX takes its implicit input in %rdx rather than %rax, doesn't affect flags, and writes its
+    // outputs to any registers. It must come first to be preferred over MUL.
+    e.enc_i32_i64_vex_isap(x86_umulx, rec_vmulx.opcodes(&MULX), use_bmi2);
     e.enc_i32_i64(x86_umulx, rec_mulx.opcodes(&MUL).rrr(4));
 
     // Binary bitwise ops.
@@ -1451,10 +1555,27 @@ fn define_alu(
     e.enc_both(band_not.bind(F32), rec_fax.opcodes(&ANDNPS));
     e.enc_both(band_not.bind(F64), rec_fax.opcodes(&ANDNPS));
 
+    // BMI1 bit manipulation. The `andn(x,y)` instruction computes `~x&y` as well, so `band_not`
+    // puts its second operand in VEX.vvvv. Without BMI1, `band_not` is legalized.
+    e.enc_i32_i64_vex_isap(band_not, rec_vrr.opcodes(&ANDN), use_bmi1);
+    e.enc_i32_i64_vex_isap(x86_blsr, rec_vur.opcodes(&BLS).rrr(1), use_bmi1);
+    e.enc_i32_i64_vex_isap(x86_blsmsk, rec_vur.opcodes(&BLS).rrr(2), use_bmi1);
+    e.enc_i32_i64_vex_isap(x86_blsi, rec_vur.opcodes(&BLS).rrr(3), use_bmi1);
+    e.enc_i32_i64_vex_isap(x86_bzhi, rec_vrr.opcodes(&BZHI), use_bmi2);
+
     // Shifts and rotates.
     // Note that the dynamic shift amount is only masked by 5 or 6 bits; the 8-bit
     // and 16-bit shifts would need explicit masking.
 
+    // The BMI2 shifts take the shift amount in any register rather than %cl, and don't tie the
+    // output to the shifted value. They must come first to be preferred.
+    for &(inst, opcodes) in &[(ishl, &SHLX), (ushr, &SHRX), (sshr, &SARX)] {
+        let template = rec_vrr.opcodes(opcodes);
+        e.enc32_isap(inst.bind(I32).bind(Any), template.clone(), use_bmi2);
+        e.enc64_isap(inst.bind(I64).bind(Any), template.w(), use_bmi2);
+        e.enc64_isap(inst.bind(I32).bind(Any), template, use_bmi2);
+    }
+
     for &(inst, rrr) in &[(rotl, 0), (rotr, 1), (ishl, 4), (ushr, 5), (sshr, 7)] {
         // Cannot use enc_i32_i64 for this pattern because instructions require
         // to bind any.
@@ -1476,6 +1597,10 @@ fn define_alu(
         );
     }
 
+    // RORX is a non-destructive rotation by an immediate, which doesn't affect flags. Left
+    // rotations use the negated rotation count.
+    e.enc_i32_i64_vex_isap(rotl_imm, rec_vr_ib_neg.opcodes(&RORX), use_bmi2);
+    e.enc_i32_i64_vex_isap(rotr_imm, rec_vr_ib.opcodes(&RORX), use_bmi2);
     e.enc_i32_i64(rotl_imm, rec_r_ib.opcodes(&ROTATE_IMM8).rrr(0));
     e.enc_i32_i64(rotr_imm, rec_r_ib.opcodes(&ROTATE_IMM8).rrr(1));
     e.enc_i32_i64(ishl_imm, rec_r_ib.opcodes(&ROTATE_IMM8).rrr(4));
@@ -1620,10 +1745,14 @@ fn define_simd(
     let rec_stacknull = r.recipe("stacknull");
     let rec_vconst = r.template("vconst");
     let rec_vconst_optimized = r.template("vconst_optimized");
+    let rec_vconst_abs4 = r.template("vconst_abs4");
+    let rec_vconst_gotoff4 = r.template("vconst_gotoff4");
 
     // Predicates shorthands.
     settings.predicate_by_name("all_ones_funcaddrs_and_not_is_pic");
     settings.predicate_by_name("not_all_ones_funcaddrs_and_not_is_pic");
+    let is_pic = settings.predicate_by_name("is_pic");
+    let not_is_pic = settings.predicate_by_name("not_is_pic");
     let use_ssse3_simd = settings.predicate_by_name("use_ssse3_simd");
     let use_sse41_simd = settings.predicate_by_name("use_sse41_simd");
     let use_sse42_simd = settings.predicate_by_name("use_sse42_simd");
@@ -1798,7 +1927,13 @@ fn define_simd(
     for ty in ValueType::all_lane_types().filter(allowed_simd_type) {
         let instruction = vconst.bind(vector(ty, sse_vector_size));
         let template = rec_vconst.nonrex().opcodes(&MOVUPS_LOAD);
-        e.enc_32_64_maybe_isap(instruction, template, None); // from SSE
+        e.enc64(instruction.clone(), template); // from SSE
+
+        // 32-bit code addresses the constant pool through an absolute or GOT-relative address.
+        let template = rec_vconst_abs4.nonrex().opcodes(&MOVUPS_LOAD);
+        e.enc32_isap(instruction.clone(), template, not_is_pic);
+        let template = rec_vconst_gotoff4.nonrex().opcodes(&MOVUPS_LOAD);
+        e.enc32_isap(instruction, template, is_pic);
     }
 
     // SIMD register movement: store, load, spill, fill, regmove. All of these use encodings of
@@ -2044,11 +2179,379 @@ fn define_simd(
     }
 }
 
+/// Select the VEX.L bit of a template for the given vector size.
+fn vex(vector_size: u64, template: Template) -> Template {
+    if vector_size == 256 {
+        template.l()
+    } else {
+        template
+    }
+}
+
+/// Define the VEX encodings of AVX and AVX2.
+///
+/// These must be defined before the SSE encodings of the same instructions, so that they are
+/// preferred when AVX is available. Unlike their SSE counterparts, the three-operand forms don't
+/// tie the output to the first input, which saves the copies that register allocation inserts for
+/// the two-operand forms.
+#[inline(never)]
+fn define_avx(
+    e: &mut PerCpuModeEncodings,
+    shared_defs: &SharedDefinitions,
+    settings: &SettingGroup,
+    x86: &InstructionGroup,
+    r: &RecipeGroup,
+) {
+    let shared = &shared_defs.instructions;
+
+    // Shorthands for instructions.
+    let band = shared.by_name("band");
+    let band_not = shared.by_name("band_not");
+    let bor = shared.by_name("bor");
+    let bxor = shared.by_name("bxor");
+    let copy = shared.by_name("copy");
+    let copy_nop = shared.by_name("copy_nop");
+    let fadd = shared.by_name("fadd");
+    let fcmp = shared.by_name("fcmp");
+    let fdemote = shared.by_name("fdemote");
+    let fdiv = shared.by_name("fdiv");
+    let fill = shared.by_name("fill");
+    let fill_nop = shared.by_name("fill_nop");
+    let fmax = shared.by_name("fmax");
+    let fmin = shared.by_name("fmin");
+    let fmul = shared.by_name("fmul");
+    let fpromote = shared.by_name("fpromote");
+    let fsub = shared.by_name("fsub");
+    let iadd = shared.by_name("iadd");
+    let icmp = shared.by_name("icmp");
+    let imul = shared.by_name("imul");
+    let ishl_imm = shared.by_name("ishl_imm");
+    let isub = shared.by_name("isub");
+    let load = shared.by_name("load");
+    let raw_bitcast = shared.by_name("raw_bitcast");
+    let regfill = shared.by_name("regfill");
+    let regmove = shared.by_name("regmove");
+    let regspill = shared.by_name("regspill");
+    let sadd_sat = shared.by_name("sadd_sat");
+    let spill = shared.by_name("spill");
+    let sqrt = shared.by_name("sqrt");
+    let sshr_imm = shared.by_name("sshr_imm");
+    let ssub_sat = shared.by_name("ssub_sat");
+    let store = shared.by_name("store");
+    let uadd_sat = shared.by_name("uadd_sat");
+    let ushr_imm = shared.by_name("ushr_imm");
+    let usub_sat = shared.by_name("usub_sat");
+    let x86_fmax = x86.by_name("x86_fmax");
+    let x86_fmin = x86.by_name("x86_fmin");
+    let x86_pmaxs = x86.by_name("x86_pmaxs");
+    let x86_pmaxu = x86.by_name("x86_pmaxu");
+    let x86_pmins = x86.by_name("x86_pmins");
+    let x86_pminu = x86.by_name("x86_pminu");
+    let x86_pshufb = x86.by_name("x86_pshufb");
+    let x86_psll = x86.by_name("x86_psll");
+    let x86_psra = x86.by_name("x86_psra");
+    let x86_psrl = x86.by_name("x86_psrl");
+
+    // Shorthands for recipes.
+    let rec_ffillnull = r.recipe("ffillnull");
+    let rec_null_fpr = r.recipe("null_fpr");
+    let rec_stacknull = r.recipe("stacknull");
+    let rec_vf_ib = r.template("vf_ib");
+    let rec_vfa = r.template("vfa");
+    let rec_vfax = r.template("vfax");
+    let rec_vffillSib32 = r.template("vffillSib32");
+    let rec_vfld = r.template("vfld");
+    let rec_vfldDisp32 = r.template("vfldDisp32");
+    let rec_vfldDisp8 = r.template("vfldDisp8");
+    let rec_vfregfill32 = r.template("vfregfill32");
+    let rec_vfregspill32 = r.template("vfregspill32");
+    let rec_vfrmov = r.template("vfrmov");
+    let rec_vfspillSib32 = r.template("vfspillSib32");
+    let rec_vfst = r.template("vfst");
+    let rec_vfstDisp32 = r.template("vfstDisp32");
+    let rec_vfstDisp8 = r.template("vfstDisp8");
+    let rec_vfurm = r.template("vfurm");
+    let rec_vfurm_nds = r.template("vfurm_nds");
+    let rec_vicscc_fpr = r.template("vicscc_fpr");
+    let rec_vpfcmp = r.template("vpfcmp");
+
+    // Predicates shorthands.
+    let use_avx = settings.predicate_by_name("use_avx");
+    let use_avx_simd = settings.predicate_by_name("use_avx_simd");
+    let use_avx2_simd = settings.predicate_by_name("use_avx2_simd");
+
+    // Scalar floating point arithmetic.
+    for (inst, opcodes32, opcodes64) in &[
+        (fadd, &ADDSS, &ADDSD),
+        (fsub, &SUBSS, &SUBSD),
+        (fmul, &MULSS, &MULSD),
+        (fdiv, &DIVSS, &DIVSD),
+        (x86_fmin, &MINSS, &MINSD),
+        (x86_fmax, &MAXSS, &MAXSD),
+    ] {
+        e.enc_32_64_isap(inst.bind(F32), rec_vfa.opcodes(*opcodes32), use_avx);
+        e.enc_32_64_isap(inst.bind(F64), rec_vfa.opcodes(*opcodes64), use_avx);
+    }
+    e.enc_32_64_isap(sqrt.bind(F32), rec_vfurm_nds.opcodes(&SQRTSS), use_avx);
+    e.enc_32_64_isap(sqrt.bind(F64), rec_vfurm_nds.opcodes(&SQRTSD), use_avx);
+    e.enc_32_64_isap(
+        fpromote.bind(F64).bind(F32),
+        rec_vfurm_nds.opcodes(&CVTSS2SD),
+        use_avx,
+    );
+    e.enc_32_64_isap(
+        fdemote.bind(F32).bind(F64),
+        rec_vfurm_nds.opcodes(&CVTSD2SS),
+        use_avx,
+    );
+
+    // Scalar floating point bitwise operations.
+    for ty in &[F32, F64] {
+        e.enc_32_64_isap(band.bind(*ty), rec_vfa.opcodes(&ANDPS), use_avx);
+        e.enc_32_64_isap(bor.bind(*ty), rec_vfa.opcodes(&ORPS), use_avx);
+        e.enc_32_64_isap(bxor.bind(*ty), rec_vfa.opcodes(&XORPS), use_avx);
+        e.enc_32_64_isap(band_not.bind(*ty), rec_vfax.opcodes(&ANDNPS), use_avx);
+    }
+
+    // Vector operations are available for 128-bit vectors with AVX. Floating point operations on
+    // 256-bit vectors also need only AVX, but integer ones need AVX2.
+    let allowed_simd_type = |t: &LaneType| t.lane_bits() >= 8 && t.lane_bits() < 128;
+    let vector_sizes = [
+        (128, use_avx_simd, use_avx_simd),
+        (256, use_avx_simd, use_avx2_simd),
+    ];
+
+    for &(vector_size, float_isap, int_isap) in &vector_sizes {
+        // Integer arithmetic.
+        for (ty, inst, opcodes) in &[
+            (I8, iadd, &PADDB[..]),
+            (I16, iadd, &PADDW[..]),
+            (I32, iadd, &PADDD[..]),
+            (I64, iadd, &PADDQ[..]),
+            (I8, sadd_sat, &PADDSB[..]),
+            (I16, sadd_sat, &PADDSW[..]),
+            (I8, uadd_sat, &PADDUSB[..]),
+            (I16, uadd_sat, &PADDUSW[..]),
+            (I8, isub, &PSUBB[..]),
+            (I16, isub, &PSUBW[..]),
+            (I32, isub, &PSUBD[..]),
+            (I64, isub, &PSUBQ[..]),
+            (I8, ssub_sat, &PSUBSB[..]),
+            (I16, ssub_sat, &PSUBSW[..]),
+            (I8, usub_sat, &PSUBUSB[..]),
+            (I16, usub_sat, &PSUBUSW[..]),
+            (I16, imul, &PMULLW[..]),
+            (I32, imul, &PMULLD[..]),
+            (I8, x86_pmaxs, &PMAXSB[..]),
+            (I16, x86_pmaxs, &PMAXSW[..]),
+            (I32, x86_pmaxs, &PMAXSD[..]),
+            (I8, x86_pmaxu, &PMAXUB[..]),
+            (I16, x86_pmaxu, &PMAXUW[..]),
+            (I32, x86_pmaxu, &PMAXUD[..]),
+            (I8, x86_pmins, &PMINSB[..]),
+            (I16, x86_pmins, &PMINSW[..]),
+            (I32, x86_pmins, &PMINSD[..]),
+            (I8, x86_pminu, &PMINUB[..]),
+            (I16, x86_pminu, &PMINUW[..]),
+            (I32, x86_pminu, &PMINUD[..]),
+            (I16, x86_psll, &PSLLW[..]),
+            (I32, x86_psll, &PSLLD[..]),
+            (I64, x86_psll, &PSLLQ[..]),
+            (I16, x86_psrl, &PSRLW[..]),
+            (I32, x86_psrl, &PSRLD[..]),
+            (I64, x86_psrl, &PSRLQ[..]),
+            (I16, x86_psra, &PSRAW[..]),
+            (I32, x86_psra, &PSRAD[..]),
+        ] {
+            let inst = inst.bind(vector(*ty, vector_size));
+            e.enc_32_64_isap(inst, vex(vector_size, rec_vfa.opcodes(opcodes)), int_isap);
+        }
+
+        // Shuffling bytes only works within each 128-bit half of a 256-bit vector.
+        if vector_size == 128 {
+            for ty in ValueType::all_lane_types().filter(allowed_simd_type) {
+                let inst = x86_pshufb.bind(vector(ty, vector_size));
+                e.enc_32_64_isap(inst, rec_vfa.opcodes(&PSHUFB), int_isap);
+            }
+        }
+
+        // Integer shifts by an immediate.
+        for (ty, opcodes) in &[(I16, &PS_W_IMM), (I32, &PS_D_IMM), (I64, &PS_Q_IMM)] {
+            let template = vex(vector_size, rec_vf_ib.opcodes(*opcodes));
+            let ishl_imm = ishl_imm.bind(vector(*ty, vector_size));
+            e.enc_32_64_isap(ishl_imm, template.rrr(6), int_isap);
+            let ushr_imm = ushr_imm.bind(vector(*ty, vector_size));
+            e.enc_32_64_isap(ushr_imm, template.rrr(2), int_isap);
+            if *ty != I64 {
+                let sshr_imm = sshr_imm.bind(vector(*ty, vector_size));
+                e.enc_32_64_isap(sshr_imm, template.rrr(4), int_isap);
+            }
+        }
+
+        // Integer comparisons.
+        {
+            use IntCC::*;
+            for (ty, cc, opcodes) in &[
+                (I8, Equal, &PCMPEQB[..]),
+                (I16, Equal, &PCMPEQW[..]),
+                (I32, Equal, &PCMPEQD[..]),
+                (I64, Equal, &PCMPEQQ[..]),
+                (I8, SignedGreaterThan, &PCMPGTB[..]),
+                (I16, SignedGreaterThan, &PCMPGTW[..]),
+                (I32, SignedGreaterThan, &PCMPGTD[..]),
+                (I64, SignedGreaterThan, &PCMPGTQ[..]),
+            ] {
+                let inst = icmp
+                    .bind(Immediate::IntCC(*cc))
+                    .bind(vector(*ty, vector_size));
+                e.enc_32_64_isap(
+                    inst,
+                    vex(vector_size, rec_vicscc_fpr.opcodes(opcodes)),
+                    int_isap,
+                );
+            }
+        }
+
+        // Bitwise operations. The integer forms need AVX2 for 256-bit vectors, so the floating
+        // point forms are used for float vectors.
+        for ty in ValueType::all_lane_types().filter(allowed_simd_type) {
+            let (and, andn, or, xor, isap): (&[_], &[_], &[_], &[_], _) = if ty.is_float() {
+                (&ANDPS, &ANDNPS, &ORPS, &XORPS, float_isap)
+            } else {
+                (&PAND, &PANDN, &POR, &PXOR, int_isap)
+            };
+            e.enc_32_64_isap(
+                band.bind(vector(ty, vector_size)),
+                vex(vector_size, rec_vfa.opcodes(and)),
+                isap,
+            );
+            e.enc_32_64_isap(
+                band_not.bind(vector(ty, vector_size)),
+                vex(vector_size, rec_vfax.opcodes(andn)),
+                isap,
+            );
+            e.enc_32_64_isap(
+                bor.bind(vector(ty, vector_size)),
+                vex(vector_size, rec_vfa.opcodes(or)),
+                isap,
+            );
+            e.enc_32_64_isap(
+                bxor.bind(vector(ty, vector_size)),
+                vex(vector_size, rec_vfa.opcodes(xor)),
+                isap,
+            );
+        }
+
+        // Floating point arithmetic.
+        for (ty, inst, opcodes) in &[
+            (F32, fadd, &ADDPS[..]),
+            (F64, fadd, &ADDPD[..]),
+            (F32, fsub, &SUBPS[..]),
+            (F64, fsub, &SUBPD[..]),
+            (F32, fmul, &MULPS[..]),
+            (F64, fmul, &MULPD[..]),
+            (F32, fdiv, &DIVPS[..]),
+            (F64, fdiv, &DIVPD[..]),
+            (F32, fmin, &MINPS[..]),
+            (F64, fmin, &MINPD[..]),
+            (F32, fmax, &MAXPS[..]),
+            (F64, fmax, &MAXPD[..]),
+        ] {
+            let inst = inst.bind(vector(*ty, vector_size));
+            e.enc_32_64_isap(inst, vex(vector_size, rec_vfa.opcodes(opcodes)), float_isap);
+        }
+        for (ty, opcodes) in &[(F32, &SQRTPS[..]), (F64, &SQRTPD[..])] {
+            let inst = sqrt.bind(vector(*ty, vector_size));
+            e.enc_32_64_isap(
+                inst,
+                vex(vector_size, rec_vfurm.opcodes(opcodes)),
+                float_isap,
+            );
+        }
+        for (ty, opcodes) in &[(F32, &CMPPS[..]), (F64, &CMPPD[..])] {
+            let inst = fcmp.bind(vector(*ty, vector_size));
+            e.enc_32_64_isap(
+                inst,
+                vex(vector_size, rec_vpfcmp.opcodes(opcodes)),
+                float_isap,
+            );
+        }
+    }
+
+    // 256-bit vectors only exist with AVX, so they need their own register movement encodings.
+    // These use VMOVUPS and VMOVAPS, which are available for all lane types with AVX.
+    let vector_size = 256;
+    for ty in ValueType::all_lane_types().filter(allowed_simd_type) {
+        // Store
+        let bound_store = store.bind(vector(ty, vector_size)).bind(Any);
+        for template in &[&rec_vfst, &rec_vfstDisp8, &rec_vfstDisp32] {
+            let template = template.opcodes(&MOVUPS_STORE).l();
+            e.enc_32_64_isap(bound_store.clone(), template, use_avx_simd);
+        }
+
+        // Load
+        let bound_load = load.bind(vector(ty, vector_size)).bind(Any);
+        for template in &[&rec_vfld, &rec_vfldDisp8, &rec_vfldDisp32] {
+            let template = template.opcodes(&MOVUPS_LOAD).l();
+            e.enc_32_64_isap(bound_load.clone(), template, use_avx_simd);
+        }
+
+        // Spill
+        e.enc_32_64_isap(
+            spill.bind(vector(ty, vector_size)),
+            rec_vfspillSib32.opcodes(&MOVUPS_STORE).l(),
+            use_avx_simd,
+        );
+        e.enc_32_64_isap(
+            regspill.bind(vector(ty, vector_size)),
+            rec_vfregspill32.opcodes(&MOVUPS_STORE).l(),
+            use_avx_simd,
+        );
+
+        // Fill
+        e.enc_32_64_isap(
+            fill.bind(vector(ty, vector_size)),
+            rec_vffillSib32.opcodes(&MOVUPS_LOAD).l(),
+            use_avx_simd,
+        );
+        e.enc_32_64_isap(
+            regfill.bind(vector(ty, vector_size)),
+            rec_vfregfill32.opcodes(&MOVUPS_LOAD).l(),
+            use_avx_simd,
+        );
+        e.enc_32_64_rec(fill_nop.bind(vector(ty, vector_size)), rec_ffillnull, 0);
+
+        // Regmove
+        e.enc_32_64_isap(
+            regmove.bind(vector(ty, vector_size)),
+            rec_vfrmov.opcodes(&MOVAPS_LOAD).l(),
+            use_avx_simd,
+        );
+
+        // Copy
+        e.enc_32_64_isap(
+            copy.bind(vector(ty, vector_size)),
+            rec_vfurm.opcodes(&MOVAPS_LOAD).l(),
+            use_avx_simd,
+        );
+        e.enc_32_64_rec(copy_nop.bind(vector(ty, vector_size)), rec_stacknull, 0);
+
+        // Bitcasts between 256-bit vectors don't change the register contents.
+        for from_type in ValueType::all_lane_types().filter(|t| allowed_simd_type(t) && *t != ty) {
+            let inst = raw_bitcast
+                .bind(vector(ty, vector_size))
+                .bind(vector(from_type, vector_size));
+            e.enc_32_64_rec(inst, rec_null_fpr, 0);
+        }
+    }
+}
+
 #[inline(never)]
 fn define_entity_ref(
     e: &mut PerCpuModeEncodings,
     shared_defs: &SharedDefinitions,
     settings: &SettingGroup,
+    x86: &InstructionGroup,
     r: &RecipeGroup,
 ) {
     let shared = &shared_defs.instructions;
@@ -2059,13 +2562,24 @@ fn define_entity_ref(
     let stack_addr = shared.by_name("stack_addr");
     let symbol_value = shared.by_name("symbol_value");
 
+    let x86_elf_tls_gd_addr = x86.by_name("x86_elf_tls_gd_addr");
+    let x86_elf_tls_ie_addr = x86.by_name("x86_elf_tls_ie_addr");
+    let x86_got_base = x86.by_name("x86_got_base");
+
     // Shorthands for recipes.
     let rec_allones_fnaddr4 = r.template("allones_fnaddr4");
+    let rec_elf_tls_gd_addr = r.recipe("elf_tls_gd_addr");
+    let rec_elf_tls_ie_addr = r.recipe("elf_tls_ie_addr");
     let rec_allones_fnaddr8 = r.template("allones_fnaddr8");
     let rec_fnaddr4 = r.template("fnaddr4");
     let rec_fnaddr8 = r.template("fnaddr8");
+    let rec_got_base = r.recipe("got_base");
+    let rec_got_fnaddr4 = r.template("got_fnaddr4");
     let rec_got_fnaddr8 = r.template("got_fnaddr8");
+    let rec_got_gvaddr4 = r.template("got_gvaddr4");
     let rec_got_gvaddr8 = r.template("got_gvaddr8");
+    let rec_gotoff_fnaddr4 = r.template("gotoff_fnaddr4");
+    let rec_gotoff_gvaddr4 = r.template("gotoff_gvaddr4");
     let rec_gvaddr4 = r.template("gvaddr4");
     let rec_gvaddr8 = r.template("gvaddr8");
     let rec_pcrel_fnaddr8 = r.template("pcrel_fnaddr8");
@@ -2113,7 +2627,7 @@ fn define_entity_ref(
     e.enc64_instp(
         func_addr.bind(I64),
         rec_pcrel_fnaddr8.opcodes(&LEA).rex().w(),
-        is_colocated_func,
+        is_colocated_func.clone(),
     );
 
     // 64-bit, non-colocated, PIC.
@@ -2123,6 +2637,22 @@ fn define_entity_ref(
         is_pic,
     );
 
+    // 32-bit PIC, relative to the GOT base in %ebx.
+    e.enc32_func(
+        func_addr.bind(I32),
+        rec_gotoff_fnaddr4.opcodes(&LEA),
+        |encoding| {
+            encoding
+                .isa_predicate(is_pic)
+                .inst_predicate(is_colocated_func)
+        },
+    );
+    e.enc32_isap(
+        func_addr.bind(I32),
+        rec_got_fnaddr4.opcodes(&MOV_LOAD),
+        is_pic,
+    );
+
     // Global addresses.
 
     // Non-PIC.
@@ -2155,6 +2685,29 @@ fn define_entity_ref(
         is_pic,
     );
 
+    // 32-bit PIC, relative to the GOT base in %ebx.
+    e.enc32_func(
+        symbol_value.bind(I32),
+        rec_gotoff_gvaddr4.opcodes(&LEA),
+        |encoding| {
+            encoding
+                .isa_predicate(is_pic)
+                .inst_predicate(InstructionPredicate::new_is_colocated_data(formats))
+        },
+    );
+    e.enc32_isap(
+        symbol_value.bind(I32),
+        rec_got_gvaddr4.opcodes(&MOV_LOAD),
+        is_pic,
+    );
+
+    // The GOT base itself, computed in the prologue of 32-bit PIC functions.
+    e.enc32_rec(x86_got_base.bind(I32), rec_got_base, 0);
+
+    // Thread-local storage, selected by the `tls_model` setting during legalization.
+    e.enc64_rec(x86_elf_tls_gd_addr, rec_elf_tls_gd_addr, 0);
+    e.enc64_rec(x86_elf_tls_ie_addr, rec_elf_tls_ie_addr, 0);
+
     // Stack addresses.
     //
     // TODO: Add encoding rules for stack_load and stack_store, so that they
@@ -2169,6 +2722,7 @@ fn define_control_flow(
     e: &mut PerCpuModeEncodings,
     shared_defs: &SharedDefinitions,
     settings: &SettingGroup,
+    x86: &InstructionGroup,
     r: &RecipeGroup,
 ) {
     let shared = &shared_defs.instructions;
@@ -2183,14 +2737,20 @@ fn define_control_flow(
     let call_indirect = shared.by_name("call_indirect");
     let debugtrap = shared.by_name("debugtrap");
     let indirect_jump_table_br = shared.by_name("indirect_jump_table_br");
+    let invoke = shared.by_name("invoke");
+    let invoke_indirect = shared.by_name("invoke_indirect");
     let jump = shared.by_name("jump");
     let jump_table_base = shared.by_name("jump_table_base");
     let jump_table_entry = shared.by_name("jump_table_entry");
+    let landing_pad = shared.by_name("landing_pad");
     let return_ = shared.by_name("return");
+    let return_call = shared.by_name("return_call");
+    let return_call_indirect = shared.by_name("return_call_indirect");
     let trap = shared.by_name("trap");
     let trapff = shared.by_name("trapff");
     let trapif = shared.by_name("trapif");
     let resumable_trap = shared.by_name("resumable_trap");
+    let x86_endbr = x86.by_name("x86_endbr");
 
     // Shorthands for recipes.
     let rec_brfb = r.template("brfb");
@@ -2200,13 +2760,36 @@ fn define_control_flow(
     let rec_call_id = r.template("call_id");
     let rec_call_plt_id = r.template("call_plt_id");
     let rec_call_r = r.template("call_r");
+    let rec_call_thunk = r.template("call_thunk");
+    let rec_call_thunk_r11 = r.template("call_thunk_r11");
     let rec_debugtrap = r.recipe("debugtrap");
+    let rec_endbr32 = r.template("endbr32");
+    let rec_endbr64 = r.template("endbr64");
     let rec_indirect_jmp = r.template("indirect_jmp");
+    let rec_invoke_id = r.template("invoke_id");
+    let rec_invoke_plt_id = r.template("invoke_plt_id");
+    let rec_invoke_r = r.template("invoke_r");
+    let rec_invoke_thunk = r.template("invoke_thunk");
+    let rec_invoke_thunk_r11 = r.template("invoke_thunk_r11");
     let rec_jmpb = r.template("jmpb");
+    let rec_jmp_thunk = r.template("jmp_thunk");
+    let rec_jmp_thunk_r11 = r.template("jmp_thunk_r11");
     let rec_jmpd = r.template("jmpd");
     let rec_jt_base = r.template("jt_base");
+    let rec_jt_base_abs4 = r.template("jt_base_abs4");
+    let rec_jt_base_gotoff4 = r.template("jt_base_gotoff4");
     let rec_jt_entry = r.template("jt_entry");
+    let rec_landing_pad = r.recipe("landing_pad");
     let rec_ret = r.template("ret");
+    let rec_ret_iw = r.template("ret_iw");
+    let rec_retpoline_call = r.template("retpoline_call");
+    let rec_retpoline_invoke = r.template("retpoline_invoke");
+    let rec_retpoline_jmp = r.template("retpoline_jmp");
+    let rec_tail_call_id = r.template("tail_call_id");
+    let rec_tail_call_plt_id = r.template("tail_call_plt_id");
+    let rec_tail_call_r = r.template("tail_call_r");
+    let rec_tail_call_retpoline = r.template("tail_call_retpoline");
+    let rec_tail_call_thunk_r11 = r.template("tail_call_thunk_r11");
     let rec_t8jccb_abcd = r.template("t8jccb_abcd");
     let rec_t8jccd_abcd = r.template("t8jccd_abcd");
     let rec_t8jccd_long = r.template("t8jccd_long");
@@ -2218,14 +2801,25 @@ fn define_control_flow(
 
     // Predicates shorthands.
     let is_pic = settings.predicate_by_name("is_pic");
+    let not_is_pic = settings.predicate_by_name("not_is_pic");
+    let not_enable_retpoline = settings.predicate_by_name("not_enable_retpoline");
+    let use_retpoline_inline = settings.predicate_by_name("use_retpoline_inline");
+    let use_retpoline_thunks = settings.predicate_by_name("use_retpoline_thunks");
 
     // Call/return
 
-    // 32-bit, both PIC and non-PIC.
+    // 32-bit, colocated or non-PIC. Non-colocated PIC calls go through the PLT, which expects
+    // the GOT base in %ebx.
+    let is_colocated_func = InstructionPredicate::new_is_colocated_func(&*formats.call, "func_ref");
+    e.enc32_instp(
+        call,
+        rec_call_id.opcodes(&CALL_RELATIVE),
+        is_colocated_func.clone(),
+    );
+    e.enc32_isap(call, rec_call_plt_id.opcodes(&CALL_RELATIVE), is_pic);
     e.enc32(call, rec_call_id.opcodes(&CALL_RELATIVE));
 
     // 64-bit, colocated, both PIC and non-PIC. Use the call instruction's pc-relative field.
-    let is_colocated_func = InstructionPredicate::new_is_colocated_func(&*formats.call, "func_ref");
     e.enc64_instp(call, rec_call_id.opcodes(&CALL_RELATIVE), is_colocated_func);
 
     // 64-bit, non-colocated, PIC. There is no 64-bit non-colocated non-PIC version, since non-PIC
@@ -2233,22 +2827,148 @@ fn define_control_flow(
     // func_addr+call_indirect.
     e.enc64_isap(call, rec_call_plt_id.opcodes(&CALL_RELATIVE), is_pic);
 
-    e.enc32(
+    e.enc32_isap(
         call_indirect.bind(I32),
         rec_call_r.opcodes(&JUMP_ABSOLUTE).rrr(2),
+        not_enable_retpoline,
     );
-    e.enc64(
+    e.enc64_isap(
         call_indirect.bind(I64),
         rec_call_r.opcodes(&JUMP_ABSOLUTE).rrr(2).rex(),
+        not_enable_retpoline,
     );
-    e.enc64(
+    e.enc64_isap(
         call_indirect.bind(I64),
         rec_call_r.opcodes(&JUMP_ABSOLUTE).rrr(2),
+        not_enable_retpoline,
     );
 
-    e.enc32(return_, rec_ret.opcodes(&RET_NEAR));
+    // Retpolines store the callee over a return address with a `mov`, either inline or in the
+    // module's shared thunk.
+    e.enc32_isap(
+        call_indirect.bind(I32),
+        rec_retpoline_call.opcodes(&MOV_STORE),
+        use_retpoline_inline,
+    );
+    e.enc64_isap(
+        call_indirect.bind(I64),
+        rec_retpoline_call.opcodes(&MOV_STORE).rex().w(),
+        use_retpoline_inline,
+    );
+    e.enc32_isap(
+        call_indirect.bind(I32),
+        rec_call_thunk.opcodes(&CALL_RELATIVE),
+        use_retpoline_thunks,
+    );
+    e.enc64_isap(
+        call_indirect.bind(I64),
+        rec_call_thunk_r11.opcodes(&CALL_RELATIVE),
+        use_retpoline_thunks,
+    );
+
+    // Invokes are encoded like calls.
+    let is_colocated_func =
+        InstructionPredicate::new_is_colocated_func(&*formats.invoke, "func_ref");
+    e.enc32_instp(
+        invoke,
+        rec_invoke_id.opcodes(&CALL_RELATIVE),
+        is_colocated_func.clone(),
+    );
+    e.enc32_isap(invoke, rec_invoke_plt_id.opcodes(&CALL_RELATIVE), is_pic);
+    e.enc32(invoke, rec_invoke_id.opcodes(&CALL_RELATIVE));
+    e.enc64_instp(
+        invoke,
+        rec_invoke_id.opcodes(&CALL_RELATIVE),
+        is_colocated_func,
+    );
+    e.enc64_isap(invoke, rec_invoke_plt_id.opcodes(&CALL_RELATIVE), is_pic);
+
+    e.enc32_isap(
+        invoke_indirect.bind(I32),
+        rec_invoke_r.opcodes(&JUMP_ABSOLUTE).rrr(2),
+        not_enable_retpoline,
+    );
+    e.enc64_isap(
+        invoke_indirect.bind(I64),
+        rec_invoke_r.opcodes(&JUMP_ABSOLUTE).rrr(2).rex(),
+        not_enable_retpoline,
+    );
+    e.enc64_isap(
+        invoke_indirect.bind(I64),
+        rec_invoke_r.opcodes(&JUMP_ABSOLUTE).rrr(2),
+        not_enable_retpoline,
+    );
+    e.enc32_isap(
+        invoke_indirect.bind(I32),
+        rec_retpoline_invoke.opcodes(&MOV_STORE),
+        use_retpoline_inline,
+    );
+    e.enc64_isap(
+        invoke_indirect.bind(I64),
+        rec_retpoline_invoke.opcodes(&MOV_STORE).rex().w(),
+        use_retpoline_inline,
+    );
+    e.enc32_isap(
+        invoke_indirect.bind(I32),
+        rec_invoke_thunk.opcodes(&CALL_RELATIVE),
+        use_retpoline_thunks,
+    );
+    e.enc64_isap(
+        invoke_indirect.bind(I64),
+        rec_invoke_thunk_r11.opcodes(&CALL_RELATIVE),
+        use_retpoline_thunks,
+    );
+
+    e.enc32_rec(landing_pad.bind(I32), rec_landing_pad, 0);
+    e.enc64_rec(landing_pad.bind(I64), rec_landing_pad, 0);
+
+    // 32-bit `windows_fastcall` functions pop their own stack arguments. The plain return must
+    // not be legal for them, or instruction shrinking would pick it.
+    let has_fastcall_stack_args = InstructionPredicate::new_has_stack_args_with_call_conv(
+        &*formats.multiary,
+        "WindowsFastcall",
+    );
+    e.enc32_instp(
+        return_,
+        rec_ret_iw.opcodes(&RET_NEAR_IMM),
+        has_fastcall_stack_args,
+    );
+    let no_fastcall_stack_args = InstructionPredicate::new_no_stack_args_with_call_conv(
+        &*formats.multiary,
+        "WindowsFastcall",
+    );
+    e.enc32_instp(return_, rec_ret.opcodes(&RET_NEAR), no_fastcall_stack_args);
     e.enc64(return_, rec_ret.opcodes(&RET_NEAR));
 
+    // Tail calls are only supported in 64-bit mode. Like calls, non-colocated non-PIC tail calls
+    // are lowered to func_addr+return_call_indirect.
+    let is_colocated_func = InstructionPredicate::new_is_colocated_func(&*formats.call, "func_ref");
+    e.enc64_instp(
+        return_call,
+        rec_tail_call_id.opcodes(&JUMP_NEAR_RELATIVE),
+        is_colocated_func,
+    );
+    e.enc64_isap(
+        return_call,
+        rec_tail_call_plt_id.opcodes(&JUMP_NEAR_RELATIVE),
+        is_pic,
+    );
+    e.enc64_isap(
+        return_call_indirect.bind(I64),
+        rec_tail_call_r.opcodes(&JUMP_ABSOLUTE).rrr(4).rex(),
+        not_enable_retpoline,
+    );
+    e.enc64_isap(
+        return_call_indirect.bind(I64),
+        rec_tail_call_retpoline.opcodes(&MOV_STORE).rex().w(),
+        use_retpoline_inline,
+    );
+    e.enc64_isap(
+        return_call_indirect.bind(I64),
+        rec_tail_call_thunk_r11.opcodes(&JUMP_NEAR_RELATIVE),
+        use_retpoline_thunks,
+    );
+
     // Branches.
     e.enc32(jump, rec_jmpb.opcodes(&JUMP_SHORT));
     e.enc64(jump, rec_jmpb.opcodes(&JUMP_SHORT));
@@ -2295,15 +3015,47 @@ fn define_control_flow(
         jump_table_base.bind(I64),
         rec_jt_base.opcodes(&LEA).rex().w(),
     );
-    e.enc32(jump_table_base.bind(I32), rec_jt_base.opcodes(&LEA));
+    e.enc32_isap(
+        jump_table_base.bind(I32),
+        rec_jt_base_abs4.opcodes(&MOV_IMM),
+        not_is_pic,
+    );
+    e.enc32_isap(
+        jump_table_base.bind(I32),
+        rec_jt_base_gotoff4.opcodes(&LEA),
+        is_pic,
+    );
 
-    e.enc_x86_64(
+    e.enc_x86_64_isap(
         indirect_jump_table_br.bind(I64),
         rec_indirect_jmp.opcodes(&JUMP_ABSOLUTE).rrr(4),
+        not_enable_retpoline,
     );
-    e.enc32(
+    e.enc32_isap(
         indirect_jump_table_br.bind(I32),
         rec_indirect_jmp.opcodes(&JUMP_ABSOLUTE).rrr(4),
+        not_enable_retpoline,
+    );
+
+    e.enc32_isap(
+        indirect_jump_table_br.bind(I32),
+        rec_retpoline_jmp.opcodes(&MOV_STORE),
+        use_retpoline_inline,
+    );
+    e.enc64_isap(
+        indirect_jump_table_br.bind(I64),
+        rec_retpoline_jmp.opcodes(&MOV_STORE).rex().w(),
+        use_retpoline_inline,
+    );
+    e.enc32_isap(
+        indirect_jump_table_br.bind(I32),
+        rec_jmp_thunk.opcodes(&JUMP_NEAR_RELATIVE),
+        use_retpoline_thunks,
+    );
+    e.enc64_isap(
+        indirect_jump_table_br.bind(I64),
+        rec_jmp_thunk_r11.opcodes(&JUMP_NEAR_RELATIVE),
+        use_retpoline_thunks,
     );
 
     // Trap as ud2
@@ -2316,6 +3068,10 @@ fn define_control_flow(
     e.enc32_rec(debugtrap, rec_debugtrap, 0);
     e.enc64_rec(debugtrap, rec_debugtrap, 0);
 
+    // Indirect branch landing pads.
+    e.enc32(x86_endbr, rec_endbr32.opcodes(&ENDBR));
+    e.enc64(x86_endbr, rec_endbr64.opcodes(&ENDBR));
+
     e.enc32_rec(trapif, rec_trapif, 0);
     e.enc64_rec(trapif, rec_trapif, 0);
     e.enc32_rec(trapff, rec_trapff, 0);
@@ -2349,6 +3105,128 @@ fn define_reftypes(e: &mut PerCpuModeEncodings, shared_defs: &SharedDefinitions,
     e.enc64_rec(safepoint, rec_safepoint, 0);
 }
 
+#[inline(never)]
+fn define_atomics(e: &mut PerCpuModeEncodings, shared_defs: &SharedDefinitions, r: &RecipeGroup) {
+    let shared = &shared_defs.instructions;
+    let formats = &shared_defs.formats;
+    let imm = &shared_defs.imm;
+
+    // Shorthands for instructions.
+    let atomic_cas = shared.by_name("atomic_cas");
+    let atomic_load = shared.by_name("atomic_load");
+    let atomic_rmw = shared.by_name("atomic_rmw");
+    let atomic_store = shared.by_name("atomic_store");
+    let fence = shared.by_name("fence");
+
+    // Shorthands for recipes.
+    let rec_atomic_cas = r.template("atomic_cas");
+    let rec_atomic_cas_abcd = r.template("atomic_cas_abcd");
+    let rec_atomic_ld = r.template("atomic_ld");
+    let rec_atomic_rmw = r.template("atomic_rmw");
+    let rec_atomic_rmw_abcd = r.template("atomic_rmw_abcd");
+    let rec_atomic_rmw_lock = r.template("atomic_rmw_lock");
+    let rec_atomic_rmw_lock_abcd = r.template("atomic_rmw_lock_abcd");
+    let rec_atomic_st = r.template("atomic_st");
+    let rec_atomic_st_abcd = r.template("atomic_st_abcd");
+    let rec_atomic_st_mfence = r.template("atomic_st_mfence");
+    let rec_atomic_st_mfence_abcd = r.template("atomic_st_mfence_abcd");
+    let rec_fence_null = r.recipe("fence_null");
+    let rec_mfence = r.recipe("mfence");
+
+    // Aligned loads and stores are atomic on x86, and loads already have acquire semantics.
+    e.enc_i32_i64_ld_st(atomic_load, true, rec_atomic_ld.opcodes(&MOV_LOAD));
+    e.enc_both(
+        atomic_load.bind(I16).bind(Any),
+        rec_atomic_ld.opcodes(&MOVZX_WORD),
+    );
+    e.enc_both(
+        atomic_load.bind(I8).bind(Any),
+        rec_atomic_ld.opcodes(&MOVZX_BYTE),
+    );
+
+    // Sequentially consistent stores need a trailing `mfence`, so their encodings come first.
+    for recipe in &[rec_atomic_st_mfence, rec_atomic_st] {
+        e.enc_i32_i64_ld_st(atomic_store, true, recipe.opcodes(&MOV_STORE));
+        e.enc_both(
+            atomic_store.bind(I16).bind(Any),
+            recipe.opcodes(&MOV_STORE_16),
+        );
+    }
+    for recipe in &[rec_atomic_st_mfence_abcd, rec_atomic_st_abcd] {
+        e.enc_both(
+            atomic_store.bind(I8).bind(Any),
+            recipe.opcodes(&MOV_BYTE_STORE),
+        );
+    }
+
+    // Read-modify-write operations with a native instruction. The remaining operations are
+    // legalized into compare-and-swap loops.
+    for &(op, recipe, recipe_abcd, opcodes, opcodes_16, opcodes_8) in &[
+        (
+            "add",
+            rec_atomic_rmw_lock,
+            rec_atomic_rmw_lock_abcd,
+            &XADD[..],
+            &XADD_16[..],
+            &XADD_BYTE[..],
+        ),
+        (
+            "xchg",
+            rec_atomic_rmw,
+            rec_atomic_rmw_abcd,
+            &XCHG[..],
+            &XCHG_16[..],
+            &XCHG_BYTE[..],
+        ),
+    ] {
+        let is_op = InstructionPredicate::new_is_field_equal(
+            &*formats.atomic_rmw,
+            "op",
+            Literal::enumerator_for(&imm.atomic_rmw_op, op).to_rust_code(),
+        );
+
+        e.enc32_instp(
+            atomic_rmw.bind(I32).bind(Any),
+            recipe.opcodes(opcodes),
+            is_op.clone(),
+        );
+        e.enc_x86_64_instp(
+            atomic_rmw.bind(I32).bind(Any),
+            recipe.opcodes(opcodes),
+            is_op.clone(),
+        );
+        e.enc64_instp(
+            atomic_rmw.bind(I64).bind(Any),
+            recipe.opcodes(opcodes).rex().w(),
+            is_op.clone(),
+        );
+        e.enc_both_instp(
+            atomic_rmw.bind(I16).bind(Any),
+            recipe.opcodes(opcodes_16),
+            is_op.clone(),
+        );
+        e.enc_both_instp(
+            atomic_rmw.bind(I8).bind(Any),
+            recipe_abcd.opcodes(opcodes_8),
+            is_op,
+        );
+    }
+
+    e.enc_i32_i64_ld_st(atomic_cas, true, rec_atomic_cas.opcodes(&CMPXCHG));
+    e.enc_both(
+        atomic_cas.bind(I16).bind(Any),
+        rec_atomic_cas.opcodes(&CMPXCHG_16),
+    );
+    e.enc_both(
+        atomic_cas.bind(I8).bind(Any),
+        rec_atomic_cas_abcd.opcodes(&CMPXCHG_BYTE),
+    );
+
+    // Only sequentially consistent fences emit code; see the `mfence` recipe.
+    e.enc_32_64_rec(fence, rec_mfence, 0);
+    e.enc_32_64_rec(fence, rec_fence_null, 0);
+}
+
 #[allow(clippy::cognitive_complexity)]
 pub(crate) fn define(
     shared_defs: &SharedDefinitions,
@@ -2363,12 +3241,14 @@ pub(crate) fn define(
     define_memory(&mut e, shared_defs, x86, r);
     define_fpu_moves(&mut e, shared_defs, r);
     define_fpu_memory(&mut e, shared_defs, r);
+    define_avx(&mut e, shared_defs, settings, x86, r);
     define_fpu_ops(&mut e, shared_defs, settings, x86, r);
     define_alu(&mut e, shared_defs, settings, x86, r);
     define_simd(&mut e, shared_defs, settings, x86, r);
-    define_entity_ref(&mut e, shared_defs, settings, r);
-    define_control_flow(&mut e, shared_defs, settings, r);
+    define_entity_ref(&mut e, shared_defs, settings, x86, r);
+    define_control_flow(&mut e, shared_defs, settings, x86, r);
     define_reftypes(&mut e, shared_defs, r);
+    define_atomics(&mut e, shared_defs, r);
 
     e
 }
@@ -7,6 +7,7 @@ use crate::cdsl::operands::Operand;
 use crate::cdsl::types::ValueType;
 use crate::cdsl::typevar::{Interval, TypeSetBuilder, TypeVar};
 
+use crate::shared::entities::EntityRefs;
 use crate::shared::formats::Formats;
 use crate::shared::immediates::Immediates;
 use crate::shared::types;
@@ -16,6 +17,7 @@ pub(crate) fn define(
     mut all_instructions: &mut AllInstructions,
     formats: &Formats,
     immediates: &Immediates,
+    entities: &EntityRefs,
 ) -> InstructionGroup {
     let mut ig = InstructionGroupBuilder::new(&mut all_instructions);
 
@@ -107,6 +109,56 @@ pub(crate) fn define(
         .operands_out(vec![resLo, resHi]),
     );
 
+    let rflags = &Operand::new("rflags", iflags);
+
+    ig.push(
+        Inst::new(
+            "x86_smul_ifout",
+            r#"
+        Signed integer multiplication, producing the low half of the result
+        and the flags.
+
+        The overflow flag is set when the product doesn't fit in the result
+        type.
+        "#,
+            &formats.binary,
+        )
+        .operands_in(vec![argL, argR])
+        .operands_out(vec![resLo, rflags]),
+    );
+
+    ig.push(
+        Inst::new(
+            "x86_band_ifout",
+            r#"
+        Bitwise and, producing the result and the flags.
+
+        The zero and sign flags describe the result, so `eq` and `ne` test
+        whether it is zero. The carry and overflow flags are cleared.
+        "#,
+            &formats.binary,
+        )
+        .operands_in(vec![argL, argR])
+        .operands_out(vec![resLo, rflags]),
+    );
+
+    let imm = &Operand::new("imm", &immediates.imm64);
+
+    ig.push(
+        Inst::new(
+            "x86_band_imm_ifout",
+            r#"
+        Bitwise and with an immediate, producing the result and the flags.
+
+        This is the immediate form of `x86_band_ifout`. The immediate is
+        sign-extended from 32 bits.
+        "#,
+            &formats.binary_imm,
+        )
+        .operands_in(vec![argL, imm])
+        .operands_out(vec![resLo, rflags]),
+    );
+
     let Float = &TypeVar::new(
         "Float",
         "A scalar or vector floating point number",
@@ -225,6 +277,67 @@ pub(crate) fn define(
         .can_load(true),
     );
 
+    let ScalarFloat = &TypeVar::new(
+        "ScalarFloat",
+        "A scalar floating point number",
+        TypeSetBuilder::new().floats(Interval::All).build(),
+    );
+    let f = &Operand::new("f", ScalarFloat);
+
+    ig.push(
+        Inst::new(
+            "x86_fld",
+            r#"
+    Loads a float onto the x87 register stack.
+
+    The native 32-bit calling conventions return floats in ``st(0)``. This
+    moves the value being returned there from the SSE register it was
+    computed in, going through memory below the stack pointer.
+
+    This is only implemented in 32-bit mode.
+    "#,
+            &formats.unary,
+        )
+        .operands_in(vec![f])
+        .other_side_effects(true),
+    );
+
+    ig.push(
+        Inst::new(
+            "x86_fstp",
+            r#"
+    Pops a float from the x87 register stack.
+
+    This moves a float returned in ``st(0)`` by a callee using a native
+    32-bit calling convention to an SSE register, going through memory below
+    the stack pointer.
+
+    This is only implemented in 32-bit mode.
+    "#,
+            &formats.nullary,
+        )
+        .operands_out(vec![f])
+        .other_side_effects(true),
+    );
+
+    ig.push(
+        Inst::new(
+            "x86_got_base",
+            r#"
+    Computes the address of the global offset table.
+
+    This gets the current program counter with a call to the next
+    instruction, then adds the distance to the GOT. Position-independent
+    32-bit code keeps the result in ``%ebx`` to address GOT entries and
+    to call through the PLT.
+
+    This is only implemented in 32-bit mode.
+    "#,
+            &formats.nullary,
+        )
+        .operands_out(vec![x]),
+    );
+
     let y = &Operand::new("y", iWord);
     let rflags = &Operand::new("rflags", iflags);
 
@@ -259,6 +372,73 @@ pub(crate) fn define(
         .operands_out(vec![y, rflags]),
     );
 
+    ig.push(
+        Inst::new(
+            "x86_blsr",
+            r#"
+    Reset Lowest Set Bit -- clears the least significant 1 bit of the word,
+    computing `x & (x - 1)`.
+
+    This is polymorphic in i32 and i64. It is implemented for both i64 and
+    i32 in 64-bit mode, and only for i32 in 32-bit mode. It requires BMI1.
+    "#,
+            &formats.unary,
+        )
+        .operands_in(vec![x])
+        .operands_out(vec![y]),
+    );
+
+    ig.push(
+        Inst::new(
+            "x86_blsi",
+            r#"
+    Extract Lowest Set Isolated Bit -- keeps only the least significant 1
+    bit of the word, computing `x & -x`.
+
+    This is polymorphic in i32 and i64 and requires BMI1.
+    "#,
+            &formats.unary,
+        )
+        .operands_in(vec![x])
+        .operands_out(vec![y]),
+    );
+
+    ig.push(
+        Inst::new(
+            "x86_blsmsk",
+            r#"
+    Get Mask Up to Lowest Set Bit -- sets all the bits up to and including
+    the least significant 1 bit of the word, computing `x ^ (x - 1)`.
+
+    This is polymorphic in i32 and i64 and requires BMI1.
+    "#,
+            &formats.unary,
+        )
+        .operands_in(vec![x])
+        .operands_out(vec![y]),
+    );
+
+    let n = &Operand::new("n", iWord).with_doc("Index of the first bit to clear");
+
+    ig.push(
+        Inst::new(
+            "x86_bzhi",
+            r#"
+    Zero High Bits Starting with Specified Bit Position -- clears the bits
+    of `x` at positions `n` and above.
+
+    Only the low 8 bits of `n` are used. Unlike a mask computed with
+    `ishl`, an index larger than or equal to the width of `x` leaves `x`
+    unchanged.
+
+    This is polymorphic in i32 and i64 and requires BMI2.
+    "#,
+            &formats.binary,
+        )
+        .operands_in(vec![x, n])
+        .operands_out(vec![y]),
+    );
+
     let uimm8 = &immediates.uimm8;
     let TxN = &TypeVar::new(
         "TxN",
@@ -542,5 +722,119 @@ pub(crate) fn define(
         .operands_out(vec![a]),
     );
 
+    let i64_t = &TypeVar::new(
+        "i64_t",
+        "A scalar 64bit integer",
+        TypeSetBuilder::new().ints(64..64).build(),
+    );
+
+    let GV = &Operand::new("GV", &entities.global_value);
+    let addr = &Operand::new("addr", i64_t);
+
+    ig.push(
+        Inst::new(
+            "x86_elf_tls_gd_addr",
+            r#"
+        Elf tls gd addr -- This implements the GD TLS model for ELF. It computes the address of
+        the TLS variable GV by calling ``__tls_get_addr``.
+        "#,
+            &formats.unary_global_value,
+        )
+        // This is a bit overly broad to mark as clobbering *all* the registers, because it should
+        // only preserve caller-saved registers. There's no way to indicate this to register
+        // allocation yet, though, so mark as clobbering all registers instead.
+        .clobbers_all_regs(true)
+        .operands_in(vec![GV])
+        .operands_out(vec![addr]),
+    );
+
+    ig.push(
+        Inst::new(
+            "x86_elf_tls_ie_addr",
+            r#"
+        Elf tls ie addr -- This implements the IE TLS model for ELF. It loads the offset of the
+        variable from the thread pointer out of the GOT and adds the thread pointer to it.
+        "#,
+            &formats.unary_global_value,
+        )
+        .operands_in(vec![GV])
+        .operands_out(vec![addr]),
+    );
+
+    let size = &Operand::new("size", iWord).with_doc("Number of bytes to allocate");
+    let Align = &Operand::new("Align", &immediates.imm64);
+    let addr = &Operand::new("addr", iWord);
+
+    ig.push(
+        Inst::new(
+            "x86_dynamic_stack_alloc",
+            r#"
+        Move the stack pointer down by ``size`` bytes, align it to ``Align``, and
+        return the address of the allocated region.
+
+        Room for the outgoing arguments of the function stays below the region,
+        so the stack pointer ends up that much further down.
+        "#,
+            &formats.binary_imm,
+        )
+        .operands_in(vec![size, Align])
+        .operands_out(vec![addr])
+        .other_side_effects(true),
+    );
+
+    let size = &Operand::new("size", iWord).with_doc("Number of bytes to probe");
+    let Page = &Operand::new("Page", &immediates.imm64).with_doc("Size of a page");
+    let scratch = &Operand::new("scratch", iWord);
+
+    ig.push(
+        Inst::new(
+            "x86_probe_stack",
+            r#"
+        Touch each page in the ``size`` bytes below the stack pointer, from the
+        top down.
+
+        Like the probestack function, this moves the stack pointer down one
+        page at a time as it goes, and moves it back up by ``size`` at the end.
+        The ``scratch`` result counts down the bytes left to probe and is
+        clobbered.
+        "#,
+            &formats.binary_imm,
+        )
+        .operands_in(vec![size, Page])
+        .operands_out(vec![scratch])
+        .other_side_effects(true),
+    );
+
+    let Offset =
+        &Operand::new("Offset", &immediates.imm64).with_doc("Offset from the stack pointer");
+
+    ig.push(
+        Inst::new(
+            "x86_probe_page",
+            r#"
+        Touch the word ``Offset`` bytes from the stack pointer, to probe the page
+        it is in.
+        "#,
+            &formats.unary_imm,
+        )
+        .operands_in(vec![Offset])
+        .other_side_effects(true),
+    );
+
+    ig.push(
+        Inst::new(
+            "x86_endbr",
+            r#"
+        Mark an indirect branch or indirect call target, with an ``endbr32`` or
+        ``endbr64`` depending on the mode.
+
+        This is a no-op unless Intel CET indirect branch tracking is enabled, in
+        which case an indirect branch must land on one.
+        "#,
+            &formats.nullary,
+        )
+        .other_side_effects(true),
+    );
+
     ig.build()
 }
@@ -27,8 +27,10 @@ pub(crate) fn define(shared: &mut SharedDefinitions, x86_instructions: &Instruct
     let bor = insts.by_name("bor");
     let bnot = insts.by_name("bnot");
     let bxor = insts.by_name("bxor");
+    let cls = insts.by_name("cls");
     let clz = insts.by_name("clz");
     let ctz = insts.by_name("ctz");
+    let dynamic_stack_alloc = insts.by_name("dynamic_stack_alloc");
     let extractlane = insts.by_name("extractlane");
     let fcmp = insts.by_name("fcmp");
     let fcvt_from_uint = insts.by_name("fcvt_from_uint");
@@ -41,7 +43,10 @@ pub(crate) fn define(shared: &mut SharedDefinitions, x86_instructions: &Instruct
     let fmin = insts.by_name("fmin");
     let fneg = insts.by_name("fneg");
     let iadd = insts.by_name("iadd");
+    let iadd_imm = insts.by_name("iadd_imm");
+    let iadd_ifcout = insts.by_name("iadd_ifcout");
     let icmp = insts.by_name("icmp");
+    let icmp_imm = insts.by_name("icmp_imm");
     let iconst = insts.by_name("iconst");
     let imul = insts.by_name("imul");
     let ineg = insts.by_name("ineg");
@@ -49,21 +54,30 @@ pub(crate) fn define(shared: &mut SharedDefinitions, x86_instructions: &Instruct
     let ishl = insts.by_name("ishl");
     let ishl_imm = insts.by_name("ishl_imm");
     let isub = insts.by_name("isub");
+    let isub_ifbout = insts.by_name("isub_ifbout");
     let popcnt = insts.by_name("popcnt");
     let raw_bitcast = insts.by_name("raw_bitcast");
+    let sadd_overflow = insts.by_name("sadd_overflow");
     let scalar_to_vector = insts.by_name("scalar_to_vector");
     let sdiv = insts.by_name("sdiv");
     let selectif = insts.by_name("selectif");
+    let smul_overflow = insts.by_name("smul_overflow");
     let smulhi = insts.by_name("smulhi");
     let splat = insts.by_name("splat");
     let shuffle = insts.by_name("shuffle");
     let srem = insts.by_name("srem");
     let sshr = insts.by_name("sshr");
+    let sshr_imm = insts.by_name("sshr_imm");
+    let ssub_overflow = insts.by_name("ssub_overflow");
+    let tls_value = insts.by_name("tls_value");
     let trueif = insts.by_name("trueif");
+    let uadd_overflow = insts.by_name("uadd_overflow");
     let udiv = insts.by_name("udiv");
+    let umul_overflow = insts.by_name("umul_overflow");
     let umulhi = insts.by_name("umulhi");
     let ushr_imm = insts.by_name("ushr_imm");
     let urem = insts.by_name("urem");
+    let usub_overflow = insts.by_name("usub_overflow");
     let ushr = insts.by_name("ushr");
     let vconst = insts.by_name("vconst");
     let vall_true = insts.by_name("vall_true");
@@ -71,6 +85,7 @@ pub(crate) fn define(shared: &mut SharedDefinitions, x86_instructions: &Instruct
 
     let x86_bsf = x86_instructions.by_name("x86_bsf");
     let x86_bsr = x86_instructions.by_name("x86_bsr");
+    let x86_cvtt2si = x86_instructions.by_name("x86_cvtt2si");
     let x86_pmaxu = x86_instructions.by_name("x86_pmaxu");
     let x86_pmins = x86_instructions.by_name("x86_pmins");
     let x86_pminu = x86_instructions.by_name("x86_pminu");
@@ -82,6 +97,7 @@ pub(crate) fn define(shared: &mut SharedDefinitions, x86_instructions: &Instruct
     let x86_ptest = x86_instructions.by_name("x86_ptest");
     let x86_umulx = x86_instructions.by_name("x86_umulx");
     let x86_smulx = x86_instructions.by_name("x86_smulx");
+    let x86_smul_ifout = x86_instructions.by_name("x86_smul_ifout");
 
     let imm = &shared.imm;
 
@@ -113,6 +129,38 @@ pub(crate) fn define(shared: &mut SharedDefinitions, x86_instructions: &Instruct
         vec![def!((res_lo, res_hi) = x86_smulx(x, y))],
     );
 
+    // Overflow-reporting arithmetic. The flags of `add`, `sub` and two-operand `imul` give the
+    // overflow directly. `mul` sets them too, but it clobbers the high half register anyway, so
+    // test that instead.
+    let b = var("b");
+    let f = var("f");
+    let intcc_of = Literal::enumerator_for(&imm.intcc, "of");
+    let intcc_ult = Literal::enumerator_for(&imm.intcc, "ult");
+    let intcc_ne = Literal::enumerator_for(&imm.intcc, "ne");
+    for &(op, flags_op, cc) in &[
+        (uadd_overflow, iadd_ifcout, &intcc_ult),
+        (sadd_overflow, iadd_ifcout, &intcc_of),
+        (usub_overflow, isub_ifbout, &intcc_ult),
+        (ssub_overflow, isub_ifbout, &intcc_of),
+        (smul_overflow, x86_smul_ifout, &intcc_of),
+    ] {
+        group.legalize(
+            def!((a, b) = op(x, y)),
+            vec![
+                def!((a, f) = flags_op(x, y)),
+                def!(b = trueif(cc.clone(), f)),
+            ],
+        );
+    }
+
+    group.legalize(
+        def!((a, b) = umul_overflow(x, y)),
+        vec![
+            def!((a, res_hi) = x86_umulx(x, y)),
+            def!(b = icmp_imm(intcc_ne, res_hi, Literal::constant(&imm.imm64, 0))),
+        ],
+    );
+
     // Floating point condition codes.
     //
     // The 8 condition codes in `supported_floatccs` are directly supported by a
@@ -175,6 +223,15 @@ pub(crate) fn define(shared: &mut SharedDefinitions, x86_instructions: &Instruct
     group.custom_legalize(fcvt_to_sint_sat, "expand_fcvt_to_sint_sat");
     group.custom_legalize(fcvt_to_uint_sat, "expand_fcvt_to_uint_sat");
 
+    // Bitcasts between a register pair and a float register go through memory.
+    group.custom_legalize(bitcast, "expand_bitcast");
+
+    // The TLS access sequence depends on the `tls_model` setting.
+    group.custom_legalize(tls_value, "expand_tls_value");
+
+    // Dynamic stack allocations may need a stack probe first.
+    group.custom_legalize(dynamic_stack_alloc, "expand_dynamic_stack_alloc");
+
     // Count leading and trailing zeroes, for baseline x86_64
     let c_minus_one = var("c_minus_one");
     let c_thirty_one = var("c_thirty_one");
@@ -231,6 +288,23 @@ pub(crate) fn define(shared: &mut SharedDefinitions, x86_instructions: &Instruct
         ],
     );
 
+    // Count leading sign bits by counting the leading zeroes once the sign is flipped away. The
+    // sign bit itself is not counted.
+    let sign = var("sign");
+    let flipped = var("flipped");
+    let zeroes = var("zeroes");
+    for &(ty, imm64_bits_m1) in &[(I64, &imm64_63), (I32, &imm64_31)] {
+        group.legalize(
+            def!(a = cls.ty(x)),
+            vec![
+                def!(sign = sshr_imm(x, imm64_bits_m1)),
+                def!(flipped = bxor(x, sign)),
+                def!(zeroes = clz(flipped)),
+                def!(a = iadd_imm(zeroes, imm64_minus_one)),
+            ],
+        );
+    }
+
     // Population count for baseline x86_64
     let x = var("x");
     let r = var("r");
@@ -611,5 +685,13 @@ pub(crate) fn define(shared: &mut SharedDefinitions, x86_instructions: &Instruct
     narrow.custom_legalize(insertlane, "convert_insertlane");
     narrow.custom_legalize(ineg, "convert_ineg");
 
+    // Conversions between floats and `i64` on 32-bit x86.
+    narrow.custom_legalize(bitcast, "expand_bitcast");
+    narrow.custom_legalize(fcvt_to_sint, "expand_fcvt_to_sint");
+    narrow.custom_legalize(fcvt_to_uint, "expand_fcvt_to_uint");
+    narrow.custom_legalize(fcvt_to_sint_sat, "expand_fcvt_to_sint_sat");
+    narrow.custom_legalize(fcvt_to_uint_sat, "expand_fcvt_to_uint_sat");
+    narrow.custom_legalize(x86_cvtt2si, "expand_x86_cvtt2si");
+
     narrow.build_and_add_to(&mut shared.transform_groups);
 }
@@ -40,6 +40,9 @@ pub static AND_IMM: [u8; 1] = [0x81];
 /// r/m{16,32,64} AND sign-extended imm8.
 pub static AND_IMM8_SIGN_EXTEND: [u8; 1] = [0x83];
 
+/// Bitwise AND of the inverted first source operand with r/m{32,64} (BMI1, VEX-encoded).
+pub static ANDN: [u8; 3] = [0x0f, 0x38, 0xf2];
+
 /// Return the bitwise logical AND NOT of packed single-precision floating-point
 /// values in xmm1 and xmm2/mem.
 pub static ANDNPS: [u8; 2] = [0x0f, 0x55];
@@ -54,6 +57,14 @@ pub static BIT_SCAN_FORWARD: [u8; 2] = [0x0f, 0xbc];
 /// Bit scan reverse (stores index of first encountered 1 from the back).
 pub static BIT_SCAN_REVERSE: [u8; 2] = [0x0f, 0xbd];
 
+/// Reset lowest set bit (/1), get mask up to lowest set bit (/2) or extract lowest set isolated
+/// bit (/3) of r/m{32,64} (BMI1, VEX-encoded).
+pub static BLS: [u8; 3] = [0x0f, 0x38, 0xf3];
+
+/// Zero the bits of r/m{32,64} starting with the position in the second source operand (BMI2,
+/// VEX-encoded).
+pub static BZHI: [u8; 3] = [0x0f, 0x38, 0xf5];
+
 /// Call near, relative, displacement relative to next instruction (sign-extended).
 pub static CALL_RELATIVE: [u8; 1] = [0xe8];
 
@@ -77,6 +88,15 @@ pub static CMPPD: [u8; 3] = [0x66, 0x0f, 0xc2];
 /// imm8 as comparison predicate (SSE).
 pub static CMPPS: [u8; 2] = [0x0f, 0xc2];
 
+/// Compare rax with r/m{16,32,64}; if equal, store r{16,32,64} into r/m, else load r/m into rax.
+pub static CMPXCHG: [u8; 2] = [0x0f, 0xb1];
+
+/// Compare ax with r/m16; if equal, store r16 into r/m16, else load r/m16 into ax.
+pub static CMPXCHG_16: [u8; 3] = [0x66, 0x0f, 0xb1];
+
+/// Compare al with r/m8; if equal, store r8 into r/m8, else load r/m8 into al.
+pub static CMPXCHG_BYTE: [u8; 2] = [0x0f, 0xb0];
+
 /// Convert scalar double-precision floating-point value to scalar single-precision
 /// floating-point value.
 pub static CVTSD2SS: [u8; 3] = [0xf2, 0x0f, 0x5a];
@@ -117,6 +137,9 @@ pub static DIVSD: [u8; 3] = [0xf2, 0x0f, 0x5e];
 /// floating-point value in xmm2/m32.
 pub static DIVSS: [u8; 3] = [0xf3, 0x0f, 0x5e];
 
+/// Terminate an indirect branch in 32-bit or 64-bit mode (CET), with a fixed ModR/M byte.
+pub static ENDBR: [u8; 3] = [0xf3, 0x0f, 0x1e];
+
 /// Signed divide for {16,32,64}-bit.
 pub static IDIV: [u8; 1] = [0xf7];
 
@@ -272,6 +295,9 @@ pub static MULSD: [u8; 3] = [0xf2, 0x0f, 0x59];
 /// low single-precision floating-point value in xmm1.
 pub static MULSS: [u8; 3] = [0xf3, 0x0f, 0x59];
 
+/// Unsigned multiply of rdx by r/m{32,64} without affecting flags (BMI2, VEX-encoded).
+pub static MULX: [u8; 4] = [0xf2, 0x0f, 0x38, 0xf6];
+
 /// Reverse each bit of r/m{16,32,64}.
 pub static NOT: [u8; 1] = [0xf7];
 
@@ -508,24 +534,42 @@ pub static PXOR: [u8; 3] = [0x66, 0x0f, 0xef];
 /// Near return to calling procedure.
 pub static RET_NEAR: [u8; 1] = [0xc3];
 
+/// Near return to calling procedure and pop imm16 bytes from stack.
+pub static RET_NEAR_IMM: [u8; 1] = [0xc2];
+
 /// General rotation opcode. Kind of rotation depends on encoding.
 pub static ROTATE_CL: [u8; 1] = [0xd3];
 
 /// General rotation opcode. Kind of rotation depends on encoding.
 pub static ROTATE_IMM8: [u8; 1] = [0xc1];
 
+/// Rotate r/m{32,64} right by imm8 without affecting flags (BMI2, VEX-encoded).
+pub static RORX: [u8; 4] = [0xf2, 0x0f, 0x3a, 0xf0];
+
 /// Round scalar doubl-precision floating-point values.
 pub static ROUNDSD: [u8; 4] = [0x66, 0x0f, 0x3a, 0x0b];
 
 /// Round scalar single-precision floating-point values.
 pub static ROUNDSS: [u8; 4] = [0x66, 0x0f, 0x3a, 0x0a];
 
+/// Shift r/m{32,64} arithmetically right by the second source operand, without affecting flags
+/// (BMI2, VEX-encoded).
+pub static SARX: [u8; 4] = [0xf3, 0x0f, 0x38, 0xf7];
+
 /// Subtract with borrow r{16,32,64} from r/m of the same size.
 pub static SBB: [u8; 1] = [0x19];
 
 /// Set byte if overflow (OF=1).
 pub static SET_BYTE_IF_OVERFLOW: [u8; 2] = [0x0f, 0x90];
 
+/// Shift r/m{32,64} logically left by the second source operand, without affecting flags (BMI2,
+/// VEX-encoded).
+pub static SHLX: [u8; 4] = [0x66, 0x0f, 0x38, 0xf7];
+
+/// Shift r/m{32,64} logically right by the second source operand, without affecting flags
+/// (BMI2, VEX-encoded).
+pub static SHRX: [u8; 4] = [0xf2, 0x0f, 0x38, 0xf7];
+
 /// Compute the square root of the packed double-precision floating-point values and store the
 /// result in xmm1 (SSE2).
 pub static SQRTPD: [u8; 3] = [0x66, 0x0f, 0x51];
@@ -579,6 +623,24 @@ pub static UCOMISS: [u8; 2] = [0x0f, 0x2e];
 /// Raise invalid opcode instruction.
 pub static UNDEFINED2: [u8; 2] = [0x0f, 0x0b];
 
+/// Exchange r/m{16,32,64} and register of the same size, then store their sum in r/m.
+pub static XADD: [u8; 2] = [0x0f, 0xc1];
+
+/// Exchange r/m16 and r16, then store their sum in r/m16.
+pub static XADD_16: [u8; 3] = [0x66, 0x0f, 0xc1];
+
+/// Exchange r/m8 and r8, then store their sum in r/m8.
+pub static XADD_BYTE: [u8; 2] = [0x0f, 0xc0];
+
+/// Exchange r{16,32,64} with r/m of the same size.
+pub static XCHG: [u8; 1] = [0x87];
+
+/// Exchange r16 with r/m16.
+pub static XCHG_16: [u8; 2] = [0x66, 0x87];
+
+/// Exchange r8 with r/m8.
+pub static XCHG_BYTE: [u8; 1] = [0x86];
+
 /// imm{16,32} XOR r/m{16,32,64}, possibly sign-extended.
 pub static XOR_IMM: [u8; 1] = [0x81];
 
@@ -72,8 +72,8 @@ impl<'builder> RecipeGroup<'builder> {
 // opcode format is indicated by the recipe name prefix.
 //
 // The match case below does not include the REX prefix which goes after the mandatory prefix.
-// VEX/XOP and EVEX prefixes are not yet supported. Encodings using any of these prefixes are
-// represented by separate recipes.
+// VEX-prefixed encodings use separate `Vex*` recipes, whose VEX prefix is built from the same pp,
+// mm and W bits. XOP and EVEX prefixes are not yet supported.
 //
 // The encoding bits are:
 //
@@ -92,7 +92,7 @@ impl<'builder> RecipeGroup<'builder> {
 // 15:    REX.W bit (or VEX.W/E)
 //
 // There is some redundancy between bits 8-11 and the recipe names, but we have enough bits, and
-// the pp+mm format is ready for supporting VEX prefixes.
+// the pp+mm format maps directly onto the fields of the VEX prefix.
 //
 // TODO Cranelift doesn't actually require recipe to have different encoding sizes anymore, so this
 // could be simplified.
@@ -151,6 +151,14 @@ pub enum RexRecipeKind {
     /// Because such a Recipe has a non-constant instruction size, it must have
     /// a special `compute_size` handler for the inferrable-REX case.
     InferRex,
+
+    /// The Recipe emits a VEX prefix, which replaces the mandatory prefix, the opcode escape
+    /// bytes and the REX prefix.
+    ///
+    /// The REX.RXB bits are inferred from registers like for `InferRex`, and they decide whether
+    /// the two-byte or the three-byte VEX prefix is used, so such a Recipe must also have a
+    /// special `compute_size` handler.
+    Vex,
 }
 
 impl Default for RexRecipeKind {
@@ -186,6 +194,8 @@ pub(crate) struct Template<'builder> {
     w_bit: u16,
     /// Value of the RRR bits (between 0 and 0b111).
     rrr_bits: u16,
+    /// Value of the VEX.L bit (0 or 1), selecting 256-bit vectors.
+    vex_l_bit: u16,
     /// Opcode bytes.
     op_bytes: &'static [u8],
 }
@@ -200,6 +210,7 @@ impl<'builder> Template<'builder> {
             when_prefixed: None,
             w_bit: 0,
             rrr_bits: 0,
+            vex_l_bit: 0,
             op_bytes: &opcodes::EMPTY,
         }
     }
@@ -245,11 +256,24 @@ impl<'builder> Template<'builder> {
         copy.rrr_bits = value;
         copy
     }
+    pub fn l(&self) -> Self {
+        assert!(
+            self.rex_kind == RexRecipeKind::Vex,
+            "VEX.L is only available with a VEX prefix."
+        );
+        let mut copy = self.clone();
+        copy.vex_l_bit = 1;
+        copy
+    }
     pub fn nonrex(&self) -> Self {
         assert!(
             self.rex_kind != RexRecipeKind::AlwaysEmitRex,
             "Template requires REX prefix."
         );
+        assert!(
+            self.rex_kind != RexRecipeKind::Vex,
+            "Template requires VEX prefix."
+        );
         let mut copy = self.clone();
         copy.rex_kind = RexRecipeKind::NeverEmitRex;
         copy
@@ -259,6 +283,10 @@ impl<'builder> Template<'builder> {
             self.rex_kind != RexRecipeKind::NeverEmitRex,
             "Template requires no REX prefix."
         );
+        assert!(
+            self.rex_kind != RexRecipeKind::Vex,
+            "Template requires VEX prefix."
+        );
         if let Some(prefixed) = &self.when_prefixed {
             let mut ret = prefixed.rex();
             // Forward specialized parameters.
@@ -276,6 +304,10 @@ impl<'builder> Template<'builder> {
             self.rex_kind != RexRecipeKind::NeverEmitRex,
             "Template requires no REX prefix."
         );
+        assert!(
+            self.rex_kind != RexRecipeKind::Vex,
+            "Template requires VEX prefix."
+        );
         assert!(
             self.when_prefixed.is_none(),
             "infer_rex used with when_prefixed()."
@@ -288,6 +320,10 @@ impl<'builder> Template<'builder> {
     pub fn build(mut self) -> (EncodingRecipe, u16) {
         let (opcode, bits) = decode_opcodes(&self.op_bytes, self.rrr_bits, self.w_bit);
 
+        if self.rex_kind == RexRecipeKind::Vex {
+            return self.build_vex(bits);
+        }
+
         let (recipe_name, rex_prefix_size) = match self.rex_kind {
             RexRecipeKind::Unspecified | RexRecipeKind::NeverEmitRex => {
                 // Ensure the operands are limited to non-REX constraints.
@@ -311,6 +347,7 @@ impl<'builder> Template<'builder> {
 
                 ("DynRex".to_string() + opcode, 0)
             }
+            RexRecipeKind::Vex => unreachable!(),
         };
 
         let size_addendum = self.op_bytes.len() as u64 + rex_prefix_size;
@@ -327,6 +364,37 @@ impl<'builder> Template<'builder> {
 
         (self.recipe.build(), bits)
     }
+
+    fn build_vex(mut self, bits: u16) -> (EncodingRecipe, u16) {
+        let enc = EncodingBits::from(bits);
+        assert!(
+            enc.mm() != 0,
+            "VEX encodings need a 0F, 0F 38 or 0F 3A opcode map."
+        );
+        assert!(
+            self.inferred_rex_compute_size.is_some(),
+            "VEX recipe '{}' needs an inferred_rex_compute_size function.",
+            &self.recipe.name
+        );
+        self.recipe.compute_size = self.inferred_rex_compute_size;
+
+        // The minimal size has a two-byte VEX prefix followed by the opcode byte.
+        let size_addendum = 2 + 1;
+        self.recipe.base_size += size_addendum;
+        if let Some(range) = self.recipe.branch_range.as_mut() {
+            range.inst_size += size_addendum;
+        }
+
+        let recipe_name = if self.vex_l_bit == 1 {
+            "Vex256"
+        } else {
+            "Vex128"
+        };
+        self.recipe.emit = replace_put_op(self.recipe.emit, recipe_name);
+        self.recipe.name = recipe_name.to_string() + &self.recipe.name;
+
+        (self.recipe.build(), bits)
+    }
 }
 
 /// Returns a predicate checking that the "cond" field of the instruction contains one of the
@@ -383,6 +451,8 @@ pub(crate) fn define<'shared>(
     let reg_rax = Register::new(gpr, regs.regunit_by_name(gpr, "rax"));
     let reg_rcx = Register::new(gpr, regs.regunit_by_name(gpr, "rcx"));
     let reg_rdx = Register::new(gpr, regs.regunit_by_name(gpr, "rdx"));
+    let reg_rsi = Register::new(gpr, regs.regunit_by_name(gpr, "rsi"));
+    let reg_r11 = Register::new(gpr, regs.regunit_by_name(gpr, "r11"));
     let reg_r15 = Register::new(gpr, regs.regunit_by_name(gpr, "r15"));
 
     // Stack operand with a 32-bit signed displacement from either RBP or RSP.
@@ -423,6 +493,14 @@ pub(crate) fn define<'shared>(
             .operands_out(vec![reg_r15])
             .emit(""),
     );
+
+    // The unwinder delivers the exception pointer and selector in rax and rdx, following the
+    // Itanium C++ ABI.
+    recipes.add_recipe(
+        EncodingRecipeBuilder::new("landing_pad", &formats.nullary, 0)
+            .operands_out(vec![reg_rax, reg_rdx])
+            .emit(""),
+    );
     // umr with a fixed register output that's r15.
     recipes.add_template_recipe(
         EncodingRecipeBuilder::new("set_pinned_reg", &formats.unary, 1)
@@ -457,6 +535,19 @@ pub(crate) fn define<'shared>(
         EncodingRecipeBuilder::new("debugtrap", &formats.nullary, 1).emit("sink.put1(0xcc);"),
     );
 
+    // Indirect branch landing pads: the `endbr` opcode with the ModR/M byte selecting the mode.
+    for &(name, modrm) in &[("endbr32", 0xfb), ("endbr64", 0xfa)] {
+        recipes.add_template_recipe(EncodingRecipeBuilder::new(name, &formats.nullary, 1).emit(
+            format!(
+                r#"
+                    {{{{PUT_OP}}}}(bits, BASE_REX, sink);
+                    sink.put1(0x{:x});
+                "#,
+                modrm
+            ),
+        ));
+    }
+
     // XX opcode, no ModR/M.
     recipes.add_template_recipe(EncodingRecipeBuilder::new("trap", &formats.trap, 0).emit(
         r#"
@@ -538,6 +629,24 @@ pub(crate) fn define<'shared>(
         .inferred_rex_compute_size("size_with_inferred_rex_for_inreg0_inreg1"),
     );
 
+    // VEX.NDS XX /r with GPR ins and outs. The first input is in r/m and the second in VEX.vvvv.
+    recipes.add_template(
+        Template::new(
+            EncodingRecipeBuilder::new("vrr", &formats.binary, 1)
+                .operands_in(vec![gpr, gpr])
+                .operands_out(vec![gpr])
+                .emit(
+                    r#"
+                        {{PUT_OP}}(bits, rex2(in_reg0, out_reg0), Some(in_reg1), sink);
+                        modrm_rr(in_reg0, out_reg0, sink);
+                    "#,
+                ),
+            regs,
+        )
+        .rex_kind(RexRecipeKind::Vex)
+        .inferred_rex_compute_size("size_with_inferred_vex_for_inreg0"),
+    );
+
     // XX /r with FPR ins and outs. A form.
     recipes.add_template_recipe(
         EncodingRecipeBuilder::new("fa", &formats.binary, 1)
@@ -564,6 +673,42 @@ pub(crate) fn define<'shared>(
             ),
     );
 
+    // VEX.NDS XX /r with FPR ins and outs. Non-destructive three-operand form of `fa`.
+    recipes.add_template(
+        Template::new(
+            EncodingRecipeBuilder::new("vfa", &formats.binary, 1)
+                .operands_in(vec![fpr, fpr])
+                .operands_out(vec![fpr])
+                .emit(
+                    r#"
+                        {{PUT_OP}}(bits, rex2(in_reg1, out_reg0), Some(in_reg0), sink);
+                        modrm_rr(in_reg1, out_reg0, sink);
+                    "#,
+                ),
+            regs,
+        )
+        .rex_kind(RexRecipeKind::Vex)
+        .inferred_rex_compute_size("size_with_inferred_vex_for_inreg1"),
+    );
+
+    // VEX.NDS XX /r with FPR ins and outs. Three-operand form of `fax`.
+    recipes.add_template(
+        Template::new(
+            EncodingRecipeBuilder::new("vfax", &formats.binary, 1)
+                .operands_in(vec![fpr, fpr])
+                .operands_out(vec![fpr])
+                .emit(
+                    r#"
+                        {{PUT_OP}}(bits, rex2(in_reg0, out_reg0), Some(in_reg1), sink);
+                        modrm_rr(in_reg0, out_reg0, sink);
+                    "#,
+                ),
+            regs,
+        )
+        .rex_kind(RexRecipeKind::Vex)
+        .inferred_rex_compute_size("size_with_inferred_vex_for_inreg0"),
+    );
+
     // XX /r with FPR ins and outs. A form with a byte immediate.
     {
         recipes.add_template_recipe(
@@ -604,6 +749,24 @@ pub(crate) fn define<'shared>(
         .inferred_rex_compute_size("size_with_inferred_rex_for_inreg0"),
     );
 
+    // VEX.NDD XX /n for a unary operation with GPR ins and outs; the output goes in VEX.vvvv.
+    recipes.add_template(
+        Template::new(
+            EncodingRecipeBuilder::new("vur", &formats.unary, 1)
+                .operands_in(vec![gpr])
+                .operands_out(vec![gpr])
+                .emit(
+                    r#"
+                        {{PUT_OP}}(bits, rex1(in_reg0), Some(out_reg0), sink);
+                        modrm_r_bits(in_reg0, bits, sink);
+                    "#,
+                ),
+            regs,
+        )
+        .rex_kind(RexRecipeKind::Vex)
+        .inferred_rex_compute_size("size_with_inferred_vex_for_inreg0"),
+    );
+
     // XX /r, but for a unary operator with separate input/output register, like
     // copies. MR form, preserving flags.
     recipes.add_template(
@@ -711,6 +874,45 @@ pub(crate) fn define<'shared>(
             ),
     );
 
+    // VEX XX /r, RM form, FPR -> FPR, with an unused VEX.vvvv.
+    recipes.add_template(
+        Template::new(
+            EncodingRecipeBuilder::new("vfurm", &formats.unary, 1)
+                .operands_in(vec![fpr])
+                .operands_out(vec![fpr])
+                .clobbers_flags(false)
+                .emit(
+                    r#"
+                        {{PUT_OP}}(bits, rex2(in_reg0, out_reg0), None, sink);
+                        modrm_rr(in_reg0, out_reg0, sink);
+                    "#,
+                ),
+            regs,
+        )
+        .rex_kind(RexRecipeKind::Vex)
+        .inferred_rex_compute_size("size_with_inferred_vex_for_inreg0"),
+    );
+
+    // VEX.NDS XX /r, RM form, FPR -> FPR, for scalar operations. The upper bits of the result are
+    // copied from the source as well, so they don't depend on the old value of the output.
+    recipes.add_template(
+        Template::new(
+            EncodingRecipeBuilder::new("vfurm_nds", &formats.unary, 1)
+                .operands_in(vec![fpr])
+                .operands_out(vec![fpr])
+                .clobbers_flags(false)
+                .emit(
+                    r#"
+                        {{PUT_OP}}(bits, rex2(in_reg0, out_reg0), Some(in_reg0), sink);
+                        modrm_rr(in_reg0, out_reg0, sink);
+                    "#,
+                ),
+            regs,
+        )
+        .rex_kind(RexRecipeKind::Vex)
+        .inferred_rex_compute_size("size_with_inferred_vex_for_inreg0"),
+    );
+
     // Same as furm, but with the source register specified directly.
     recipes.add_template_recipe(
         EncodingRecipeBuilder::new("furm_reg_to_ssa", &formats.copy_to_ssa, 1)
@@ -804,6 +1006,24 @@ pub(crate) fn define<'shared>(
             ),
     );
 
+    // VEX XX /r, for regmove instructions (FPR version, RM encoded).
+    recipes.add_template(
+        Template::new(
+            EncodingRecipeBuilder::new("vfrmov", &formats.reg_move, 1)
+                .operands_in(vec![fpr])
+                .clobbers_flags(false)
+                .emit(
+                    r#"
+                        {{PUT_OP}}(bits, rex2(src, dst), None, sink);
+                        modrm_rr(src, dst, sink);
+                    "#,
+                ),
+            regs,
+        )
+        .rex_kind(RexRecipeKind::Vex)
+        .inferred_rex_compute_size("size_with_inferred_vex_for_regmove"),
+    );
+
     // XX /n with one arg in %rcx, for shifts.
     recipes.add_template_recipe(
         EncodingRecipeBuilder::new("rc", &formats.binary, 1)
@@ -865,6 +1085,29 @@ pub(crate) fn define<'shared>(
         .inferred_rex_compute_size("size_with_inferred_rex_for_inreg1"),
     );
 
+    // VEX.NDD XX /r for mulx: inputs in %rdx, r. The high half of the result goes in ModR/M.reg
+    // and the low half in VEX.vvvv, without affecting flags.
+    recipes.add_template(
+        Template::new(
+            EncodingRecipeBuilder::new("vmulx", &formats.binary, 1)
+                .operands_in(vec![
+                    OperandConstraint::FixedReg(reg_rdx),
+                    OperandConstraint::RegClass(gpr),
+                ])
+                .operands_out(vec![gpr, gpr])
+                .clobbers_flags(false)
+                .emit(
+                    r#"
+                        {{PUT_OP}}(bits, rex2(in_reg1, out_reg1), Some(out_reg0), sink);
+                        modrm_rr(in_reg1, out_reg1, sink);
+                    "#,
+                ),
+            regs,
+        )
+        .rex_kind(RexRecipeKind::Vex)
+        .inferred_rex_compute_size("size_with_inferred_vex_for_inreg1"),
+    );
+
     // XX /n ib with 8-bit immediate sign-extended.
     {
         recipes.add_template(
@@ -911,6 +1154,88 @@ pub(crate) fn define<'shared>(
                 ),
         );
 
+        // VEX.NDD XX /n ib with an FPR input and output; the output goes in VEX.vvvv.
+        recipes.add_template(
+            Template::new(
+                EncodingRecipeBuilder::new("vf_ib", &formats.binary_imm, 2)
+                    .operands_in(vec![fpr])
+                    .operands_out(vec![fpr])
+                    .inst_predicate(InstructionPredicate::new_is_signed_int(
+                        &*formats.binary_imm,
+                        "imm",
+                        8,
+                        0,
+                    ))
+                    .emit(
+                        r#"
+                            {{PUT_OP}}(bits, rex1(in_reg0), Some(out_reg0), sink);
+                            modrm_r_bits(in_reg0, bits, sink);
+                            let imm: i64 = imm.into();
+                            sink.put1(imm as u8);
+                        "#,
+                    ),
+                regs,
+            )
+            .rex_kind(RexRecipeKind::Vex)
+            .inferred_rex_compute_size("size_with_inferred_vex_for_inreg0"),
+        );
+
+        // VEX XX /r ib with GPR ins and outs, without affecting flags.
+        recipes.add_template(
+            Template::new(
+                EncodingRecipeBuilder::new("vr_ib", &formats.binary_imm, 2)
+                    .operands_in(vec![gpr])
+                    .operands_out(vec![gpr])
+                    .inst_predicate(InstructionPredicate::new_is_signed_int(
+                        &*formats.binary_imm,
+                        "imm",
+                        8,
+                        0,
+                    ))
+                    .clobbers_flags(false)
+                    .emit(
+                        r#"
+                            {{PUT_OP}}(bits, rex2(in_reg0, out_reg0), None, sink);
+                            modrm_rr(in_reg0, out_reg0, sink);
+                            let imm: i64 = imm.into();
+                            sink.put1(imm as u8);
+                        "#,
+                    ),
+                regs,
+            )
+            .rex_kind(RexRecipeKind::Vex)
+            .inferred_rex_compute_size("size_with_inferred_vex_for_inreg0"),
+        );
+
+        // Same as `vr_ib`, but the immediate is the complementary rotation count. This encodes left
+        // rotations as right rotations.
+        recipes.add_template(
+            Template::new(
+                EncodingRecipeBuilder::new("vr_ib_neg", &formats.binary_imm, 2)
+                    .operands_in(vec![gpr])
+                    .operands_out(vec![gpr])
+                    .inst_predicate(InstructionPredicate::new_is_signed_int(
+                        &*formats.binary_imm,
+                        "imm",
+                        8,
+                        0,
+                    ))
+                    .clobbers_flags(false)
+                    .emit(
+                        r#"
+                            {{PUT_OP}}(bits, rex2(in_reg0, out_reg0), None, sink);
+                            modrm_rr(in_reg0, out_reg0, sink);
+                            let imm: i64 = imm.into();
+                            let width = if EncodingBits::from(bits).rex_w() == 1 { 64 } else { 32 };
+                            sink.put1(((width - imm) & (width - 1)) as u8);
+                        "#,
+                    ),
+                regs,
+            )
+            .rex_kind(RexRecipeKind::Vex)
+            .inferred_rex_compute_size("size_with_inferred_vex_for_inreg0"),
+        );
+
         // XX /n id with 32-bit immediate sign-extended.
         recipes.add_template(
             Template::new(
@@ -1151,6 +1476,53 @@ pub(crate) fn define<'shared>(
             ),
     );
 
+    // XX /r with an `[esp]` operand, between a `sub esp, 8` and an `add esp, 8`. The SSE move is
+    // paired with an x87 load or store, for floats returned in `st(0)` in 32-bit mode.
+    recipes.add_template_recipe(
+        EncodingRecipeBuilder::new("fld_st0", &formats.unary, 11)
+            .operands_in(vec![fpr])
+            .emit(
+                r#"
+                    sink.put1(0x83);
+                    modrm_rr(RU::rsp.into(), 5, sink);
+                    sink.put1(8);
+                    {{PUT_OP}}(bits, rex2(RU::rsp.into(), in_reg0), sink);
+                    modrm_sib(in_reg0, sink);
+                    sib_noindex(RU::rsp.into(), sink);
+                    // fld dword/qword [esp]
+                    sink.put1(if func.dfg.value_type(arg).bits() == 64 { 0xdd } else { 0xd9 });
+                    modrm_sib(0, sink);
+                    sib_noindex(RU::rsp.into(), sink);
+                    sink.put1(0x83);
+                    modrm_rr(RU::rsp.into(), 0, sink);
+                    sink.put1(8);
+                "#,
+            ),
+    );
+
+    recipes.add_template_recipe(
+        EncodingRecipeBuilder::new("fstp_st0", &formats.nullary, 11)
+            .operands_out(vec![fpr])
+            .emit(
+                r#"
+                    sink.put1(0x83);
+                    modrm_rr(RU::rsp.into(), 5, sink);
+                    sink.put1(8);
+                    // fstp dword/qword [esp]
+                    let ty = func.dfg.value_type(func.dfg.first_result(inst));
+                    sink.put1(if ty.bits() == 64 { 0xdd } else { 0xd9 });
+                    modrm_sib(3, sink);
+                    sib_noindex(RU::rsp.into(), sink);
+                    {{PUT_OP}}(bits, rex2(RU::rsp.into(), out_reg0), sink);
+                    modrm_sib(out_reg0, sink);
+                    sib_noindex(RU::rsp.into(), sink);
+                    sink.put1(0x83);
+                    modrm_rr(RU::rsp.into(), 0, sink);
+                    sink.put1(8);
+                "#,
+            ),
+    );
+
     // XX /r, for regmove instructions.
     recipes.add_template_recipe(
         EncodingRecipeBuilder::new("copysp", &formats.copy_special, 1)
@@ -1174,6 +1546,67 @@ pub(crate) fn define<'shared>(
             ),
     );
 
+    // Stack probe loop: copy the size in %rax to the output, then move SP down a page at a time,
+    // touching each page, while counting the output down, and move SP back up by the size. The
+    // output register differs between x86-32 and x86-64, since it must be free in the prologue.
+    for &(name, scratch, scratch_name) in &[
+        ("probestack", reg_rsi, "rsi"),
+        ("probestack_r11", reg_r11, "r11"),
+    ] {
+        recipes.add_template_recipe(
+            EncodingRecipeBuilder::new(name, &formats.binary_imm, 1)
+                .operands_in(vec![reg_rax])
+                .operands_out(vec![scratch])
+                .clobbers_flags(true)
+                .compute_size("size_probe_stack_loop")
+                .emit(format!(
+                    r#"
+                        {{{{PUT_OP}}}}(bits, rex2(RU::{0}.into(), RU::rax.into()), sink);
+                        modrm_rr(RU::{0}.into(), RU::rax.into(), sink);
+                        probe_stack_loop(bits, RU::rax.into(), RU::{0}.into(), imm, sink);
+                    "#,
+                    scratch_name
+                )),
+        );
+    }
+
+    // Touch the stack at [SP + disp32] with an `or` of 0.
+    recipes.add_template_recipe(
+        EncodingRecipeBuilder::new("probe_page", &formats.unary_imm, 7)
+            .inst_predicate(InstructionPredicate::new_is_signed_int(
+                &*formats.unary_imm,
+                "imm",
+                32,
+                0,
+            ))
+            .clobbers_flags(true)
+            .emit(
+                r#"
+                    {{PUT_OP}}(bits, rex1(RU::rsp.into()), sink);
+                    modrm_sib_disp32(((bits >> 12) & 7) as RegUnit, sink);
+                    sib_noindex(RU::rsp.into(), sink);
+                    let imm: i64 = imm.into();
+                    sink.put4(imm as u32);
+                    sink.put1(0);
+                "#,
+            ),
+    );
+
+    // Dynamic stack allocation: subtract the size from SP, realign SP, copy it to the output
+    // and re-reserve the outgoing argument area below the allocation.
+    recipes.add_template_recipe(
+        EncodingRecipeBuilder::new("dynstackalloc", &formats.binary_imm, 1)
+            .operands_in(vec![gpr])
+            .operands_out(vec![gpr])
+            .clobbers_flags(true)
+            .compute_size("size_dynamic_stack_alloc")
+            .emit(
+                r#"
+                    dynamic_stack_alloc(bits, in_reg0, out_reg0, imm, func, sink);
+                "#,
+            ),
+    );
+
     {
         recipes.add_template_recipe(
             EncodingRecipeBuilder::new("adjustsp_ib", &formats.unary_imm, 2)
@@ -1378,20 +1811,166 @@ pub(crate) fn define<'shared>(
             ),
     );
 
-    // Stack addresses.
-    //
-    // TODO Alternative forms for 8-bit immediates, when applicable.
-
+    // 32-bit position-independent code addresses the GOT relative to its base address, which is
+    // kept in `%ebx`. Load a function's address out of its GOT entry, or compute the address of a
+    // colocated function from its offset to the GOT.
     recipes.add_template_recipe(
-        EncodingRecipeBuilder::new("spaddr4_id", &formats.stack_load, 6)
+        EncodingRecipeBuilder::new("got_fnaddr4", &formats.func_addr, 5)
             .operands_out(vec![gpr])
             .emit(
                 r#"
-                    let sp = StackRef::sp(stack_slot, &func.stack_slots);
-                    let base = stk_base(sp.base);
-                    {{PUT_OP}}(bits, rex2(out_reg0, base), sink);
-                    modrm_sib_disp8(out_reg0, sink);
-                    sib_noindex(base, sink);
+                    {{PUT_OP}}(bits, rex2(RU::rbx.into(), out_reg0), sink);
+                    modrm_disp32(RU::rbx.into(), out_reg0, sink);
+                    sink.reloc_external(Reloc::X86GOT4,
+                                        &func.dfg.ext_funcs[func_ref].name,
+                                        0);
+                    sink.put4(0);
+                "#,
+            ),
+    );
+
+    recipes.add_template_recipe(
+        EncodingRecipeBuilder::new("gotoff_fnaddr4", &formats.func_addr, 5)
+            .operands_out(vec![gpr])
+            .emit(
+                r#"
+                    {{PUT_OP}}(bits, rex2(RU::rbx.into(), out_reg0), sink);
+                    modrm_disp32(RU::rbx.into(), out_reg0, sink);
+                    sink.reloc_external(Reloc::X86GOTOff4,
+                                        &func.dfg.ext_funcs[func_ref].name,
+                                        0);
+                    sink.put4(0);
+                "#,
+            ),
+    );
+
+    recipes.add_template_recipe(
+        EncodingRecipeBuilder::new("got_gvaddr4", &formats.unary_global_value, 5)
+            .operands_out(vec![gpr])
+            .emit(
+                r#"
+                    {{PUT_OP}}(bits, rex2(RU::rbx.into(), out_reg0), sink);
+                    modrm_disp32(RU::rbx.into(), out_reg0, sink);
+                    sink.reloc_external(Reloc::X86GOT4,
+                                        &func.global_values[global_value].symbol_name(),
+                                        0);
+                    sink.put4(0);
+                "#,
+            ),
+    );
+
+    recipes.add_template_recipe(
+        EncodingRecipeBuilder::new("gotoff_gvaddr4", &formats.unary_global_value, 5)
+            .operands_out(vec![gpr])
+            .emit(
+                r#"
+                    {{PUT_OP}}(bits, rex2(RU::rbx.into(), out_reg0), sink);
+                    modrm_disp32(RU::rbx.into(), out_reg0, sink);
+                    sink.reloc_external(Reloc::X86GOTOff4,
+                                        &func.global_values[global_value].symbol_name(),
+                                        0);
+                    sink.put4(0);
+                "#,
+            ),
+    );
+
+    // Compute the GOT base address in 32-bit mode: `call` the next instruction, `pop` the return
+    // address and add its distance to the GOT.
+    recipes.add_recipe(
+        EncodingRecipeBuilder::new("got_base", &formats.nullary, 12)
+            .operands_out(vec![gpr])
+            .emit(
+                r#"
+                    sink.put1(0xe8); // call .+5
+                    sink.put4(0);
+                    sink.put1(0x58 | (out_reg0 & 7) as u8); // pop
+                    sink.put1(0x81); // add
+                    modrm_rr(out_reg0, 0, sink);
+                    // The addend is the distance from the popped return address to the
+                    // immediate field.
+                    sink.reloc_external(Reloc::X86GOTPC4,
+                                        &ExternalName::LibCall(LibCall::ElfGlobalOffsetTable),
+                                        3);
+                    sink.put4(0);
+                "#,
+            ),
+    );
+
+    // ELF general-dynamic TLS access. The linker pattern-matches this exact 16 byte sequence, so
+    // it may rewrite it into a cheaper access model.
+    recipes.add_recipe(
+        EncodingRecipeBuilder::new("elf_tls_gd_addr", &formats.unary_global_value, 16)
+            // `__tls_get_addr` returns the address in %rax.
+            .operands_out(vec![reg_rax])
+            .clobbers_flags(true)
+            .emit(
+                r#"
+                    // data16 lea gv@tlsgd(%rip),%rdi
+                    sink.put1(0x66); // data16
+                    sink.put1(0b01001000); // rex.w
+                    const LEA: u8 = 0x8d;
+                    sink.put1(LEA); // lea
+                    modrm_riprel(0b111/*out_reg0*/, sink); // 0x3d
+                    sink.reloc_external(Reloc::ElfX86_64TlsGd,
+                                        &func.global_values[global_value].symbol_name(),
+                                        -4);
+                    sink.put4(0);
+
+                    // data16 data16 callq __tls_get_addr-4
+                    sink.put1(0x66); // data16
+                    sink.put1(0x66); // data16
+                    sink.put1(0b01001000); // rex.w
+                    sink.put1(0xe8); // call
+                    sink.reloc_external(Reloc::X86CallPLTRel4,
+                                        &ExternalName::LibCall(LibCall::ElfTlsGetAddr),
+                                        -4);
+                    sink.put4(0);
+                "#,
+            ),
+    );
+
+    // ELF initial-exec TLS access: load the thread pointer, then add the variable's offset from
+    // it, which is read from the GOT.
+    recipes.add_recipe(
+        EncodingRecipeBuilder::new("elf_tls_ie_addr", &formats.unary_global_value, 16)
+            .operands_out(vec![gpr])
+            .clobbers_flags(true)
+            .emit(
+                r#"
+                    // mov %fs:0,%out_reg0
+                    sink.put1(0x64); // %fs:
+                    sink.put1(rex2(0, out_reg0) | 0b1000); // rex.w
+                    sink.put1(0x8b); // mov
+                    modrm_sib(out_reg0, sink);
+                    sib_noindex(0b101, sink); // no base, disp32
+                    sink.put4(0);
+
+                    // add gv@gottpoff(%rip),%out_reg0
+                    sink.put1(rex2(0, out_reg0) | 0b1000); // rex.w
+                    sink.put1(0x03); // add
+                    modrm_riprel(out_reg0, sink);
+                    sink.reloc_external(Reloc::ElfX86_64GotTpOff,
+                                        &func.global_values[global_value].symbol_name(),
+                                        -4);
+                    sink.put4(0);
+                "#,
+            ),
+    );
+
+    // Stack addresses.
+    //
+    // TODO Alternative forms for 8-bit immediates, when applicable.
+
+    recipes.add_template_recipe(
+        EncodingRecipeBuilder::new("spaddr4_id", &formats.stack_load, 6)
+            .operands_out(vec![gpr])
+            .emit(
+                r#"
+                    let sp = stk_ref(stack_slot, func);
+                    let base = stk_base(sp.base);
+                    {{PUT_OP}}(bits, rex2(out_reg0, base), sink);
+                    modrm_sib_disp8(out_reg0, sink);
+                    sib_noindex(base, sink);
                     let imm : i32 = offset.into();
                     sink.put4(sp.offset.checked_add(imm).unwrap() as u32);
                 "#,
@@ -1403,7 +1982,7 @@ pub(crate) fn define<'shared>(
             .operands_out(vec![gpr])
             .emit(
                 r#"
-                    let sp = StackRef::sp(stack_slot, &func.stack_slots);
+                    let sp = stk_ref(stack_slot, func);
                     let base = stk_base(sp.base);
                     {{PUT_OP}}(bits, rex2(base, out_reg0), sink);
                     modrm_sib_disp32(out_reg0, sink);
@@ -1484,7 +2063,7 @@ pub(crate) fn define<'shared>(
         recipes.add_template_recipe(
             EncodingRecipeBuilder::new("fst", &formats.store, 1)
                 .operands_in(vec![fpr, gpr])
-                .inst_predicate(has_no_offset)
+                .inst_predicate(has_no_offset.clone())
                 .clobbers_flags(false)
                 .compute_size("size_plus_maybe_sib_or_offset_for_inreg_1")
                 .emit(
@@ -1506,6 +2085,38 @@ pub(crate) fn define<'shared>(
                 ),
         );
 
+        // VEX XX /r register-indirect store of FPR with no offset.
+        recipes.add_template(
+            Template::new(
+                EncodingRecipeBuilder::new("vfst", &formats.store, 1)
+                    .operands_in(vec![fpr, gpr])
+                    .inst_predicate(has_no_offset)
+                    .clobbers_flags(false)
+                    .emit(
+                        r#"
+                            if !flags.notrap() {
+                                sink.trap(TrapCode::HeapOutOfBounds, func.srclocs[inst]);
+                            }
+                            {{PUT_OP}}(bits, rex2(in_reg1, in_reg0), None, sink);
+                            if needs_sib_byte(in_reg1) {
+                                modrm_sib(in_reg0, sink);
+                                sib_noindex(in_reg1, sink);
+                            } else if needs_offset(in_reg1) {
+                                modrm_disp8(in_reg1, in_reg0, sink);
+                                sink.put1(0);
+                            } else {
+                                modrm_rm(in_reg1, in_reg0, sink);
+                            }
+                        "#,
+                    ),
+                regs,
+            )
+            .rex_kind(RexRecipeKind::Vex)
+            .inferred_rex_compute_size(
+                "size_with_inferred_vex_plus_maybe_sib_or_offset_for_inreg_1",
+            ),
+        );
+
         let has_small_offset =
             InstructionPredicate::new_is_signed_int(&*formats.store, "offset", 8, 0);
 
@@ -1568,7 +2179,7 @@ pub(crate) fn define<'shared>(
         recipes.add_template_recipe(
             EncodingRecipeBuilder::new("fstDisp8", &formats.store, 2)
                 .operands_in(vec![fpr, gpr])
-                .inst_predicate(has_small_offset)
+                .inst_predicate(has_small_offset.clone())
                 .clobbers_flags(false)
                 .compute_size("size_plus_maybe_sib_for_inreg_1")
                 .emit(
@@ -1589,6 +2200,35 @@ pub(crate) fn define<'shared>(
                 ),
         );
 
+        // VEX XX /r register-indirect store with 8-bit offset of FPR.
+        recipes.add_template(
+            Template::new(
+                EncodingRecipeBuilder::new("vfstDisp8", &formats.store, 2)
+                    .operands_in(vec![fpr, gpr])
+                    .inst_predicate(has_small_offset)
+                    .clobbers_flags(false)
+                    .emit(
+                        r#"
+                            if !flags.notrap() {
+                                sink.trap(TrapCode::HeapOutOfBounds, func.srclocs[inst]);
+                            }
+                            {{PUT_OP}}(bits, rex2(in_reg1, in_reg0), None, sink);
+                            if needs_sib_byte(in_reg1) {
+                                modrm_sib_disp8(in_reg0, sink);
+                                sib_noindex(in_reg1, sink);
+                            } else {
+                                modrm_disp8(in_reg1, in_reg0, sink);
+                            }
+                            let offset: i32 = offset.into();
+                            sink.put1(offset as u8);
+                        "#,
+                    ),
+                regs,
+            )
+            .rex_kind(RexRecipeKind::Vex)
+            .inferred_rex_compute_size("size_with_inferred_vex_plus_maybe_sib_for_inreg_1"),
+        );
+
         // XX /r register-indirect store with 32-bit offset.
         let st_disp32 = recipes.add_template_recipe(
             EncodingRecipeBuilder::new("stDisp32", &formats.store, 5)
@@ -1665,6 +2305,34 @@ pub(crate) fn define<'shared>(
                     "#,
                 ),
         );
+
+        // VEX XX /r register-indirect store with 32-bit offset of FPR.
+        recipes.add_template(
+            Template::new(
+                EncodingRecipeBuilder::new("vfstDisp32", &formats.store, 5)
+                    .operands_in(vec![fpr, gpr])
+                    .clobbers_flags(false)
+                    .emit(
+                        r#"
+                            if !flags.notrap() {
+                                sink.trap(TrapCode::HeapOutOfBounds, func.srclocs[inst]);
+                            }
+                            {{PUT_OP}}(bits, rex2(in_reg1, in_reg0), None, sink);
+                            if needs_sib_byte(in_reg1) {
+                                modrm_sib_disp32(in_reg0, sink);
+                                sib_noindex(in_reg1, sink);
+                            } else {
+                                modrm_disp32(in_reg1, in_reg0, sink);
+                            }
+                            let offset: i32 = offset.into();
+                            sink.put4(offset as u32);
+                        "#,
+                    ),
+                regs,
+            )
+            .rex_kind(RexRecipeKind::Vex)
+            .inferred_rex_compute_size("size_with_inferred_vex_plus_maybe_sib_for_inreg_1"),
+        );
     }
 
     {
@@ -1918,6 +2586,29 @@ pub(crate) fn define<'shared>(
             ),
     );
 
+    // Like fspillSib32, but with a VEX prefix.
+    recipes.add_template(
+        Template::new(
+            EncodingRecipeBuilder::new("vfspillSib32", &formats.unary, 6)
+                .operands_in(vec![fpr])
+                .operands_out(vec![stack_fpr32])
+                .clobbers_flags(false)
+                .emit(
+                    r#"
+                        sink.trap(TrapCode::StackOverflow, func.srclocs[inst]);
+                        let base = stk_base(out_stk0.base);
+                        {{PUT_OP}}(bits, rex2(base, in_reg0), None, sink);
+                        modrm_sib_disp32(in_reg0, sink);
+                        sib_noindex(base, sink);
+                        sink.put4(out_stk0.offset as u32);
+                    "#,
+                ),
+            regs,
+        )
+        .rex_kind(RexRecipeKind::Vex)
+        .inferred_rex_compute_size("size_with_inferred_vex_for_stack"),
+    );
+
     // Regspill using RSP-relative addressing.
     recipes.add_template_recipe(
         EncodingRecipeBuilder::new("regspill32", &formats.reg_spill, 6)
@@ -1926,7 +2617,7 @@ pub(crate) fn define<'shared>(
             .emit(
                 r#"
                     sink.trap(TrapCode::StackOverflow, func.srclocs[inst]);
-                    let dst = StackRef::sp(dst, &func.stack_slots);
+                    let dst = stk_ref(dst, func);
                     let base = stk_base(dst.base);
                     {{PUT_OP}}(bits, rex2(base, src), sink);
                     modrm_sib_disp32(src, sink);
@@ -1944,7 +2635,7 @@ pub(crate) fn define<'shared>(
             .emit(
                 r#"
                     sink.trap(TrapCode::StackOverflow, func.srclocs[inst]);
-                    let dst = StackRef::sp(dst, &func.stack_slots);
+                    let dst = stk_ref(dst, func);
                     let base = stk_base(dst.base);
                     {{PUT_OP}}(bits, rex2(base, src), sink);
                     modrm_sib_disp32(src, sink);
@@ -1954,6 +2645,29 @@ pub(crate) fn define<'shared>(
             ),
     );
 
+    // Like fregspill32, but with a VEX prefix.
+    recipes.add_template(
+        Template::new(
+            EncodingRecipeBuilder::new("vfregspill32", &formats.reg_spill, 6)
+                .operands_in(vec![fpr])
+                .clobbers_flags(false)
+                .emit(
+                    r#"
+                        sink.trap(TrapCode::StackOverflow, func.srclocs[inst]);
+                        let dst = stk_ref(dst, func);
+                        let base = stk_base(dst.base);
+                        {{PUT_OP}}(bits, rex2(base, src), None, sink);
+                        modrm_sib_disp32(src, sink);
+                        sib_noindex(base, sink);
+                        sink.put4(dst.offset as u32);
+                    "#,
+                ),
+            regs,
+        )
+        .rex_kind(RexRecipeKind::Vex)
+        .inferred_rex_compute_size("size_with_inferred_vex_for_stack"),
+    );
+
     // Load recipes.
 
     {
@@ -1995,7 +2709,7 @@ pub(crate) fn define<'shared>(
             EncodingRecipeBuilder::new("fld", &formats.load, 1)
                 .operands_in(vec![gpr])
                 .operands_out(vec![fpr])
-                .inst_predicate(has_no_offset)
+                .inst_predicate(has_no_offset.clone())
                 .clobbers_flags(false)
                 .compute_size("size_plus_maybe_sib_or_offset_for_inreg_0")
                 .emit(
@@ -2017,6 +2731,39 @@ pub(crate) fn define<'shared>(
                 ),
         );
 
+        // VEX XX /r float load with no offset.
+        recipes.add_template(
+            Template::new(
+                EncodingRecipeBuilder::new("vfld", &formats.load, 1)
+                    .operands_in(vec![gpr])
+                    .operands_out(vec![fpr])
+                    .inst_predicate(has_no_offset)
+                    .clobbers_flags(false)
+                    .emit(
+                        r#"
+                            if !flags.notrap() {
+                                sink.trap(TrapCode::HeapOutOfBounds, func.srclocs[inst]);
+                            }
+                            {{PUT_OP}}(bits, rex2(in_reg0, out_reg0), None, sink);
+                            if needs_sib_byte(in_reg0) {
+                                modrm_sib(out_reg0, sink);
+                                sib_noindex(in_reg0, sink);
+                            } else if needs_offset(in_reg0) {
+                                modrm_disp8(in_reg0, out_reg0, sink);
+                                sink.put1(0);
+                            } else {
+                                modrm_rm(in_reg0, out_reg0, sink);
+                            }
+                        "#,
+                    ),
+                regs,
+            )
+            .rex_kind(RexRecipeKind::Vex)
+            .inferred_rex_compute_size(
+                "size_with_inferred_vex_plus_maybe_sib_or_offset_for_inreg_0",
+            ),
+        );
+
         let has_small_offset =
             InstructionPredicate::new_is_signed_int(&*formats.load, "offset", 8, 0);
 
@@ -2051,7 +2798,7 @@ pub(crate) fn define<'shared>(
             EncodingRecipeBuilder::new("fldDisp8", &formats.load, 2)
                 .operands_in(vec![gpr])
                 .operands_out(vec![fpr])
-                .inst_predicate(has_small_offset)
+                .inst_predicate(has_small_offset.clone())
                 .clobbers_flags(false)
                 .compute_size("size_plus_maybe_sib_for_inreg_0")
                 .emit(
@@ -2072,6 +2819,36 @@ pub(crate) fn define<'shared>(
                 ),
         );
 
+        // VEX XX /r float load with 8-bit offset.
+        recipes.add_template(
+            Template::new(
+                EncodingRecipeBuilder::new("vfldDisp8", &formats.load, 2)
+                    .operands_in(vec![gpr])
+                    .operands_out(vec![fpr])
+                    .inst_predicate(has_small_offset)
+                    .clobbers_flags(false)
+                    .emit(
+                        r#"
+                            if !flags.notrap() {
+                                sink.trap(TrapCode::HeapOutOfBounds, func.srclocs[inst]);
+                            }
+                            {{PUT_OP}}(bits, rex2(in_reg0, out_reg0), None, sink);
+                            if needs_sib_byte(in_reg0) {
+                                modrm_sib_disp8(out_reg0, sink);
+                                sib_noindex(in_reg0, sink);
+                            } else {
+                                modrm_disp8(in_reg0, out_reg0, sink);
+                            }
+                            let offset: i32 = offset.into();
+                            sink.put1(offset as u8);
+                        "#,
+                    ),
+                regs,
+            )
+            .rex_kind(RexRecipeKind::Vex)
+            .inferred_rex_compute_size("size_with_inferred_vex_plus_maybe_sib_for_inreg_0"),
+        );
+
         let has_big_offset =
             InstructionPredicate::new_is_signed_int(&*formats.load, "offset", 32, 0);
 
@@ -2126,6 +2903,35 @@ pub(crate) fn define<'shared>(
                     "#,
                 ),
         );
+
+        // VEX XX /r float load with 32-bit offset.
+        recipes.add_template(
+            Template::new(
+                EncodingRecipeBuilder::new("vfldDisp32", &formats.load, 5)
+                    .operands_in(vec![gpr])
+                    .operands_out(vec![fpr])
+                    .clobbers_flags(false)
+                    .emit(
+                        r#"
+                            if !flags.notrap() {
+                                sink.trap(TrapCode::HeapOutOfBounds, func.srclocs[inst]);
+                            }
+                            {{PUT_OP}}(bits, rex2(in_reg0, out_reg0), None, sink);
+                            if needs_sib_byte(in_reg0) {
+                                modrm_sib_disp32(out_reg0, sink);
+                                sib_noindex(in_reg0, sink);
+                            } else {
+                                modrm_disp32(in_reg0, out_reg0, sink);
+                            }
+                            let offset: i32 = offset.into();
+                            sink.put4(offset as u32);
+                        "#,
+                    ),
+                regs,
+            )
+            .rex_kind(RexRecipeKind::Vex)
+            .inferred_rex_compute_size("size_with_inferred_vex_plus_maybe_sib_for_inreg_0"),
+        );
     }
 
     {
@@ -2280,40 +3086,283 @@ pub(crate) fn define<'shared>(
         );
     }
 
-    // Unary fill with SIB and 32-bit displacement.
-    recipes.add_template_recipe(
-        EncodingRecipeBuilder::new("fillSib32", &formats.unary, 6)
-            .operands_in(vec![stack_gpr32])
-            .operands_out(vec![gpr])
-            .clobbers_flags(false)
-            .emit(
-                r#"
-                    let base = stk_base(in_stk0.base);
-                    {{PUT_OP}}(bits, rex2(base, out_reg0), sink);
-                    modrm_sib_disp32(out_reg0, sink);
-                    sib_noindex(base, sink);
-                    sink.put4(in_stk0.offset as u32);
-                "#,
-            ),
-    );
+    // Atomic memory accesses.
+    //
+    // These only support register-indirect addressing; the x86 legalizer folds any offset into
+    // the address first. The memory operand is emitted like the `ld` and `st` recipes do.
+    {
+        let ordering = &shared_defs.imm.atomic_ordering;
+        let is_seq_cst = |format: &InstructionFormat| {
+            InstructionPredicate::new_is_field_equal(
+                format,
+                "ordering",
+                Literal::enumerator_for(ordering, "seq_cst").to_rust_code(),
+            )
+        };
+        let has_no_offset = |format: &InstructionFormat| {
+            InstructionPredicate::new_is_field_equal(format, "offset", "0".into())
+        };
 
-    // Like fillSib32, but targeting an FPR rather than a GPR.
-    recipes.add_template_recipe(
-        EncodingRecipeBuilder::new("ffillSib32", &formats.unary, 6)
-            .operands_in(vec![stack_fpr32])
-            .operands_out(vec![fpr])
-            .clobbers_flags(false)
-            .emit(
+        // XX /r atomic load with no offset.
+        recipes.add_template_recipe(
+            EncodingRecipeBuilder::new("atomic_ld", &formats.atomic_load, 1)
+                .operands_in(vec![gpr])
+                .operands_out(vec![gpr])
+                .inst_predicate(has_no_offset(&*formats.atomic_load))
+                .clobbers_flags(false)
+                .compute_size("size_plus_maybe_sib_or_offset_for_inreg_0")
+                .emit(
+                    r#"
+                        if !flags.notrap() {
+                            sink.trap(TrapCode::HeapOutOfBounds, func.srclocs[inst]);
+                        }
+                        {{PUT_OP}}(bits, rex2(in_reg0, out_reg0), sink);
+                        if needs_sib_byte(in_reg0) {
+                            modrm_sib(out_reg0, sink);
+                            sib_noindex(in_reg0, sink);
+                        } else if needs_offset(in_reg0) {
+                            modrm_disp8(in_reg0, out_reg0, sink);
+                            sink.put1(0);
+                        } else {
+                            modrm_rm(in_reg0, out_reg0, sink);
+                        }
+                    "#,
+                ),
+        );
+
+        // XX /r atomic store with no offset. A sequentially consistent store is followed by an
+        // `mfence` so it can't be reordered with later loads.
+        //
+        // The `_abcd` variants only allow ABCD for the stored value. They are used for byte
+        // stores with no REX prefix.
+        for &(name, size, seq_cst) in &[("atomic_st", 1, false), ("atomic_st_mfence", 4, true)] {
+            let mut predicate =
+                InstructionPredicate::new().and(has_no_offset(&*formats.atomic_store));
+            if seq_cst {
+                predicate = predicate.and(is_seq_cst(&*formats.atomic_store));
+            }
+            let emit = format!(
                 r#"
-                    let base = stk_base(in_stk0.base);
-                    {{PUT_OP}}(bits, rex2(base, out_reg0), sink);
-                    modrm_sib_disp32(out_reg0, sink);
-                    sib_noindex(base, sink);
-                    sink.put4(in_stk0.offset as u32);
+                        if !flags.notrap() {{
+                            sink.trap(TrapCode::HeapOutOfBounds, func.srclocs[inst]);
+                        }}
+                        {{{{PUT_OP}}}}(bits, rex2(in_reg1, in_reg0), sink);
+                        if needs_sib_byte(in_reg1) {{
+                            modrm_sib(in_reg0, sink);
+                            sib_noindex(in_reg1, sink);
+                        }} else if needs_offset(in_reg1) {{
+                            modrm_disp8(in_reg1, in_reg0, sink);
+                            sink.put1(0);
+                        }} else {{
+                            modrm_rm(in_reg1, in_reg0, sink);
+                        }}
+                        {}
+                    "#,
+                if seq_cst {
+                    "sink.put1(0x0f); sink.put1(0xae); sink.put1(0xf0); // mfence"
+                } else {
+                    ""
+                }
+            );
+
+            let st = recipes.add_template_recipe(
+                EncodingRecipeBuilder::new(name, &formats.atomic_store, size)
+                    .operands_in(vec![gpr, gpr])
+                    .inst_predicate(predicate.clone())
+                    .clobbers_flags(false)
+                    .compute_size("size_plus_maybe_sib_or_offset_for_inreg_1")
+                    .emit(emit.clone()),
+            );
+            recipes.add_template(
+                Template::new(
+                    EncodingRecipeBuilder::new(
+                        format!("{}_abcd", name),
+                        &formats.atomic_store,
+                        size,
+                    )
+                    .operands_in(vec![abcd, gpr])
+                    .inst_predicate(predicate)
+                    .clobbers_flags(false)
+                    .compute_size("size_plus_maybe_sib_or_offset_for_inreg_1")
+                    .emit(emit),
+                    regs,
+                )
+                .when_prefixed(st),
+            );
+        }
+
+        // XX /r atomic read-modify-write, where the register operand is both the
+        // input `x` and the old value loaded from memory. This is used by `xchg`, which locks the
+        // bus implicitly, and by `lock xadd`.
+        for &(name, size, lock) in &[("atomic_rmw", 1, false), ("atomic_rmw_lock", 2, true)] {
+            let emit = format!(
+                r#"
+                        if !flags.notrap() {{
+                            sink.trap(TrapCode::HeapOutOfBounds, func.srclocs[inst]);
+                        }}
+                        {}
+                        {{{{PUT_OP}}}}(bits, rex2(in_reg1, in_reg0), sink);
+                        if needs_sib_byte(in_reg1) {{
+                            modrm_sib(in_reg0, sink);
+                            sib_noindex(in_reg1, sink);
+                        }} else if needs_offset(in_reg1) {{
+                            modrm_disp8(in_reg1, in_reg0, sink);
+                            sink.put1(0);
+                        }} else {{
+                            modrm_rm(in_reg1, in_reg0, sink);
+                        }}
+                    "#,
+                if lock {
+                    "sink.put1(0xf0); // LOCK prefix"
+                } else {
+                    ""
+                }
+            );
+
+            let rmw = recipes.add_template_recipe(
+                EncodingRecipeBuilder::new(name, &formats.atomic_rmw, size)
+                    .operands_in(vec![gpr, gpr])
+                    .operands_out(vec![0])
+                    .clobbers_flags(lock)
+                    .compute_size("size_plus_maybe_sib_or_offset_for_inreg_1")
+                    .emit(emit.clone()),
+            );
+            recipes.add_template(
+                Template::new(
+                    EncodingRecipeBuilder::new(format!("{}_abcd", name), &formats.atomic_rmw, size)
+                        .operands_in(vec![abcd, gpr])
+                        .operands_out(vec![0])
+                        .clobbers_flags(lock)
+                        .compute_size("size_plus_maybe_sib_or_offset_for_inreg_1")
+                        .emit(emit),
+                    regs,
+                )
+                .when_prefixed(rmw),
+            );
+        }
+
+        // lock XX /r compare and swap. The expected value and the result live in
+        // rax.
+        let cas_emit = r#"
+                        if !flags.notrap() {
+                            sink.trap(TrapCode::HeapOutOfBounds, func.srclocs[inst]);
+                        }
+                        sink.put1(0xf0); // LOCK prefix
+                        {{PUT_OP}}(bits, rex2(in_reg2, in_reg1), sink);
+                        if needs_sib_byte(in_reg2) {
+                            modrm_sib(in_reg1, sink);
+                            sib_noindex(in_reg2, sink);
+                        } else if needs_offset(in_reg2) {
+                            modrm_disp8(in_reg2, in_reg1, sink);
+                            sink.put1(0);
+                        } else {
+                            modrm_rm(in_reg2, in_reg1, sink);
+                        }
+                    "#;
+        let cas = recipes.add_template_recipe(
+            EncodingRecipeBuilder::new("atomic_cas", &formats.atomic_cas, 2)
+                .operands_in(vec![
+                    OperandConstraint::FixedReg(reg_rax),
+                    OperandConstraint::RegClass(gpr),
+                    OperandConstraint::RegClass(gpr),
+                ])
+                .operands_out(vec![reg_rax])
+                .compute_size("size_plus_maybe_sib_or_offset_for_inreg_2")
+                .emit(cas_emit),
+        );
+        recipes.add_template(
+            Template::new(
+                EncodingRecipeBuilder::new("atomic_cas_abcd", &formats.atomic_cas, 2)
+                    .operands_in(vec![
+                        OperandConstraint::FixedReg(reg_rax),
+                        OperandConstraint::RegClass(abcd),
+                        OperandConstraint::RegClass(gpr),
+                    ])
+                    .operands_out(vec![reg_rax])
+                    .compute_size("size_plus_maybe_sib_or_offset_for_inreg_2")
+                    .emit(cas_emit),
+                regs,
+            )
+            .when_prefixed(cas),
+        );
+
+        // Only a sequentially consistent fence needs an instruction: x86 doesn't reorder loads
+        // with older loads or stores with older accesses, so weaker fences are free.
+        recipes.add_recipe(
+            EncodingRecipeBuilder::new("mfence", &formats.fence, 3)
+                .inst_predicate(is_seq_cst(&*formats.fence))
+                .clobbers_flags(false)
+                .emit(
+                    r#"
+                        sink.put1(0x0f);
+                        sink.put1(0xae);
+                        sink.put1(0xf0);
+                    "#,
+                ),
+        );
+        recipes.add_recipe(
+            EncodingRecipeBuilder::new("fence_null", &formats.fence, 0)
+                .clobbers_flags(false)
+                .emit(""),
+        );
+    }
+
+    // Unary fill with SIB and 32-bit displacement.
+    recipes.add_template_recipe(
+        EncodingRecipeBuilder::new("fillSib32", &formats.unary, 6)
+            .operands_in(vec![stack_gpr32])
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .emit(
+                r#"
+                    let base = stk_base(in_stk0.base);
+                    {{PUT_OP}}(bits, rex2(base, out_reg0), sink);
+                    modrm_sib_disp32(out_reg0, sink);
+                    sib_noindex(base, sink);
+                    sink.put4(in_stk0.offset as u32);
+                "#,
+            ),
+    );
+
+    // Like fillSib32, but targeting an FPR rather than a GPR.
+    recipes.add_template_recipe(
+        EncodingRecipeBuilder::new("ffillSib32", &formats.unary, 6)
+            .operands_in(vec![stack_fpr32])
+            .operands_out(vec![fpr])
+            .clobbers_flags(false)
+            .emit(
+                r#"
+                    let base = stk_base(in_stk0.base);
+                    {{PUT_OP}}(bits, rex2(base, out_reg0), sink);
+                    modrm_sib_disp32(out_reg0, sink);
+                    sib_noindex(base, sink);
+                    sink.put4(in_stk0.offset as u32);
                 "#,
             ),
     );
 
+    // Like ffillSib32, but with a VEX prefix.
+    recipes.add_template(
+        Template::new(
+            EncodingRecipeBuilder::new("vffillSib32", &formats.unary, 6)
+                .operands_in(vec![stack_fpr32])
+                .operands_out(vec![fpr])
+                .clobbers_flags(false)
+                .emit(
+                    r#"
+                        let base = stk_base(in_stk0.base);
+                        {{PUT_OP}}(bits, rex2(base, out_reg0), None, sink);
+                        modrm_sib_disp32(out_reg0, sink);
+                        sib_noindex(base, sink);
+                        sink.put4(in_stk0.offset as u32);
+                    "#,
+                ),
+            regs,
+        )
+        .rex_kind(RexRecipeKind::Vex)
+        .inferred_rex_compute_size("size_with_inferred_vex_for_stack"),
+    );
+
     // Regfill with RSP-relative 32-bit displacement.
     recipes.add_template_recipe(
         EncodingRecipeBuilder::new("regfill32", &formats.reg_fill, 6)
@@ -2321,7 +3370,7 @@ pub(crate) fn define<'shared>(
             .clobbers_flags(false)
             .emit(
                 r#"
-                    let src = StackRef::sp(src, &func.stack_slots);
+                    let src = stk_ref(src, func);
                     let base = stk_base(src.base);
                     {{PUT_OP}}(bits, rex2(base, dst), sink);
                     modrm_sib_disp32(dst, sink);
@@ -2338,7 +3387,7 @@ pub(crate) fn define<'shared>(
             .clobbers_flags(false)
             .emit(
                 r#"
-                    let src = StackRef::sp(src, &func.stack_slots);
+                    let src = stk_ref(src, func);
                     let base = stk_base(src.base);
                     {{PUT_OP}}(bits, rex2(base, dst), sink);
                     modrm_sib_disp32(dst, sink);
@@ -2348,51 +3397,331 @@ pub(crate) fn define<'shared>(
             ),
     );
 
+    // Like fregfill32, but with a VEX prefix.
+    recipes.add_template(
+        Template::new(
+            EncodingRecipeBuilder::new("vfregfill32", &formats.reg_fill, 6)
+                .operands_in(vec![stack_fpr32])
+                .clobbers_flags(false)
+                .emit(
+                    r#"
+                        let src = stk_ref(src, func);
+                        let base = stk_base(src.base);
+                        {{PUT_OP}}(bits, rex2(base, dst), None, sink);
+                        modrm_sib_disp32(dst, sink);
+                        sib_noindex(base, sink);
+                        sink.put4(src.offset as u32);
+                    "#,
+                ),
+            regs,
+        )
+        .rex_kind(RexRecipeKind::Vex)
+        .inferred_rex_compute_size("size_with_inferred_vex_for_stack"),
+    );
+
     // Call/return.
 
     recipes.add_template_recipe(
-        EncodingRecipeBuilder::new("call_id", &formats.call, 4).emit(
-            r#"
-            sink.trap(TrapCode::StackOverflow, func.srclocs[inst]);
-            {{PUT_OP}}(bits, BASE_REX, sink);
-            // The addend adjusts for the difference between the end of the
-            // instruction and the beginning of the immediate field.
-            sink.reloc_external(Reloc::X86CallPCRel4,
-                                &func.dfg.ext_funcs[func_ref].name,
-                                -4);
-            sink.put4(0);
-        "#,
-        ),
+        EncodingRecipeBuilder::new("call_id", &formats.call, 4)
+            .compute_size("size_plus_callee_popped_args")
+            .emit(
+                r#"
+                    sink.trap(TrapCode::StackOverflow, func.srclocs[inst]);
+                    {{PUT_OP}}(bits, BASE_REX, sink);
+                    // The addend adjusts for the difference between the end of the
+                    // instruction and the beginning of the immediate field.
+                    sink.reloc_external(Reloc::X86CallPCRel4,
+                                        &func.dfg.ext_funcs[func_ref].name,
+                                        -4);
+                    sink.put4(0);
+                    realloc_callee_popped_args(func, func.dfg.ext_funcs[func_ref].signature, sink);
+                "#,
+            ),
     );
 
     recipes.add_template_recipe(
-        EncodingRecipeBuilder::new("call_plt_id", &formats.call, 4).emit(
-            r#"
-            sink.trap(TrapCode::StackOverflow, func.srclocs[inst]);
-            {{PUT_OP}}(bits, BASE_REX, sink);
-            sink.reloc_external(Reloc::X86CallPLTRel4,
-                                &func.dfg.ext_funcs[func_ref].name,
-                                -4);
-            sink.put4(0);
-        "#,
-        ),
+        EncodingRecipeBuilder::new("call_plt_id", &formats.call, 4)
+            .compute_size("size_plus_callee_popped_args")
+            .emit(
+                r#"
+                    sink.trap(TrapCode::StackOverflow, func.srclocs[inst]);
+                    {{PUT_OP}}(bits, BASE_REX, sink);
+                    sink.reloc_external(Reloc::X86CallPLTRel4,
+                                        &func.dfg.ext_funcs[func_ref].name,
+                                        -4);
+                    sink.put4(0);
+                    realloc_callee_popped_args(func, func.dfg.ext_funcs[func_ref].signature, sink);
+                "#,
+            ),
     );
 
     recipes.add_template_recipe(
         EncodingRecipeBuilder::new("call_r", &formats.call_indirect, 1)
             .operands_in(vec![gpr])
+            .compute_size("size_plus_callee_popped_args")
             .emit(
                 r#"
                     sink.trap(TrapCode::StackOverflow, func.srclocs[inst]);
                     {{PUT_OP}}(bits, rex1(in_reg0), sink);
                     modrm_r_bits(in_reg0, bits, sink);
+                    realloc_callee_popped_args(func, sig_ref, sink);
+                "#,
+            ),
+    );
+
+    // Retpoline for an indirect call: the call at the end pushes the return address and goes
+    // back to an inner call, whose own return address is overwritten with the callee by a
+    // `mov reg, (%rsp)` before returning to it. A mispredicted `ret` is captured in a loop.
+    recipes.add_template_recipe(
+        EncodingRecipeBuilder::new("retpoline_call", &formats.call_indirect, 22)
+            .operands_in(vec![gpr])
+            .compute_size("size_plus_callee_popped_args")
+            .emit(
+                r#"
+                    let srcloc = func.srclocs[inst];
+                    let mov_size = retpoline_mov_size(bits);
+                    retpoline_call_head(mov_size, srcloc, sink);
+                    {{PUT_OP}}(bits, rex2(RU::rsp.into(), in_reg0), sink);
+                    modrm_sib(in_reg0, sink);
+                    sib_noindex(RU::rsp.into(), sink);
+                    retpoline_call_tail(mov_size, srcloc, sink);
+                    realloc_callee_popped_args(func, sig_ref, sink);
                 "#,
             ),
     );
 
+    // Calls through the module's shared retpoline thunk, which takes the callee in a fixed
+    // register that differs between x86-32 and x86-64.
+    for &(name, target, thunk) in &[
+        ("call_thunk", reg_rax, "RetpolineThunkEax"),
+        ("call_thunk_r11", reg_r11, "RetpolineThunkR11"),
+    ] {
+        recipes.add_template_recipe(
+            EncodingRecipeBuilder::new(name, &formats.call_indirect, 4)
+                .operands_in(vec![target])
+                .compute_size("size_plus_callee_popped_args")
+                .emit(format!(
+                    r#"
+                        sink.trap(TrapCode::StackOverflow, func.srclocs[inst]);
+                        {{{{PUT_OP}}}}(bits, BASE_REX, sink);
+                        sink.reloc_external(Reloc::X86CallPCRel4,
+                                            &ExternalName::LibCall(LibCall::{}),
+                                            -4);
+                        sink.put4(0);
+                        realloc_callee_popped_args(func, sig_ref, sink);
+                    "#,
+                    thunk
+                )),
+        );
+    }
+
+    // Invokes are calls that also record their landing pad at the return address.
+
+    recipes.add_template_recipe(
+        EncodingRecipeBuilder::new("invoke_id", &formats.invoke, 4)
+            .compute_size("size_plus_callee_popped_args")
+            .emit(
+                r#"
+                    sink.trap(TrapCode::StackOverflow, func.srclocs[inst]);
+                    {{PUT_OP}}(bits, BASE_REX, sink);
+                    sink.reloc_external(Reloc::X86CallPCRel4,
+                                        &func.dfg.ext_funcs[func_ref].name,
+                                        -4);
+                    sink.put4(0);
+                    sink.add_call_site(func.offsets[destination], func.srclocs[inst]);
+                    realloc_callee_popped_args(func, func.dfg.ext_funcs[func_ref].signature, sink);
+                "#,
+            ),
+    );
+
+    recipes.add_template_recipe(
+        EncodingRecipeBuilder::new("invoke_plt_id", &formats.invoke, 4)
+            .compute_size("size_plus_callee_popped_args")
+            .emit(
+                r#"
+                    sink.trap(TrapCode::StackOverflow, func.srclocs[inst]);
+                    {{PUT_OP}}(bits, BASE_REX, sink);
+                    sink.reloc_external(Reloc::X86CallPLTRel4,
+                                        &func.dfg.ext_funcs[func_ref].name,
+                                        -4);
+                    sink.put4(0);
+                    sink.add_call_site(func.offsets[destination], func.srclocs[inst]);
+                    realloc_callee_popped_args(func, func.dfg.ext_funcs[func_ref].signature, sink);
+                "#,
+            ),
+    );
+
+    recipes.add_template_recipe(
+        EncodingRecipeBuilder::new("invoke_r", &formats.invoke_indirect, 1)
+            .operands_in(vec![gpr])
+            .compute_size("size_plus_callee_popped_args")
+            .emit(
+                r#"
+                    sink.trap(TrapCode::StackOverflow, func.srclocs[inst]);
+                    {{PUT_OP}}(bits, rex1(in_reg0), sink);
+                    modrm_r_bits(in_reg0, bits, sink);
+                    sink.add_call_site(func.offsets[destination], func.srclocs[inst]);
+                    realloc_callee_popped_args(func, sig_ref, sink);
+                "#,
+            ),
+    );
+
+    // The retpoline for an indirect invoke. The callee returns after the final `call 1b`, so
+    // that is where the landing pad is recorded.
+    recipes.add_template_recipe(
+        EncodingRecipeBuilder::new("retpoline_invoke", &formats.invoke_indirect, 22)
+            .operands_in(vec![gpr])
+            .compute_size("size_plus_callee_popped_args")
+            .emit(
+                r#"
+                    let srcloc = func.srclocs[inst];
+                    let mov_size = retpoline_mov_size(bits);
+                    retpoline_call_head(mov_size, srcloc, sink);
+                    {{PUT_OP}}(bits, rex2(RU::rsp.into(), in_reg0), sink);
+                    modrm_sib(in_reg0, sink);
+                    sib_noindex(RU::rsp.into(), sink);
+                    retpoline_call_tail(mov_size, srcloc, sink);
+                    sink.add_call_site(func.offsets[destination], srcloc);
+                    realloc_callee_popped_args(func, sig_ref, sink);
+                "#,
+            ),
+    );
+
+    for &(name, target, thunk) in &[
+        ("invoke_thunk", reg_rax, "RetpolineThunkEax"),
+        ("invoke_thunk_r11", reg_r11, "RetpolineThunkR11"),
+    ] {
+        recipes.add_template_recipe(
+            EncodingRecipeBuilder::new(name, &formats.invoke_indirect, 4)
+                .operands_in(vec![target])
+                .compute_size("size_plus_callee_popped_args")
+                .emit(format!(
+                    r#"
+                        sink.trap(TrapCode::StackOverflow, func.srclocs[inst]);
+                        {{{{PUT_OP}}}}(bits, BASE_REX, sink);
+                        sink.reloc_external(Reloc::X86CallPCRel4,
+                                            &ExternalName::LibCall(LibCall::{}),
+                                            -4);
+                        sink.put4(0);
+                        sink.add_call_site(func.offsets[destination], func.srclocs[inst]);
+                        realloc_callee_popped_args(func, sig_ref, sink);
+                    "#,
+                    thunk
+                )),
+        );
+    }
+
     recipes.add_template_recipe(
         EncodingRecipeBuilder::new("ret", &formats.multiary, 0)
-            .emit("{{PUT_OP}}(bits, BASE_REX, sink);"),
+            .compute_size("size_plus_popped_args")
+            .emit(
+                r#"
+                    let popped = callee_popped_bytes(&func.signature);
+                    if popped == 0 {
+                        {{PUT_OP}}(bits, BASE_REX, sink);
+                    } else {
+                        // ret imm16
+                        debug_assert!(popped <= 0xffff, "too many stack arguments to pop");
+                        sink.put1(0xc2);
+                        sink.put2(popped as u16);
+                    }
+                "#,
+            ),
+    );
+
+    // The 32-bit `windows_fastcall` convention makes the callee pop its stack arguments.
+    recipes.add_template_recipe(
+        EncodingRecipeBuilder::new("ret_iw", &formats.multiary, 2).emit(
+            r#"
+                    {{PUT_OP}}(bits, BASE_REX, sink);
+                    let popped = (func.signature.stack_args_size() + 3) & !3;
+                    debug_assert!(popped <= 0xffff, "too many stack arguments to pop");
+                    sink.put2(popped as u16);
+                "#,
+        ),
+    );
+
+    // Tail calls tear down the current frame themselves, since there is no `return` in front of
+    // which the prologue/epilogue insertion could put an epilogue.
+
+    recipes.add_template_recipe(
+        EncodingRecipeBuilder::new("tail_call_id", &formats.call, 4)
+            .compute_size("size_plus_tail_call_epilogue")
+            .emit(
+                r#"
+                    tail_call_epilogue(func, func.dfg.ext_funcs[func_ref].signature, sink);
+                    {{PUT_OP}}(bits, BASE_REX, sink);
+                    sink.reloc_external(Reloc::X86CallPCRel4,
+                                        &func.dfg.ext_funcs[func_ref].name,
+                                        -4);
+                    sink.put4(0);
+                "#,
+            ),
+    );
+
+    recipes.add_template_recipe(
+        EncodingRecipeBuilder::new("tail_call_plt_id", &formats.call, 4)
+            .compute_size("size_plus_tail_call_epilogue")
+            .emit(
+                r#"
+                    tail_call_epilogue(func, func.dfg.ext_funcs[func_ref].signature, sink);
+                    {{PUT_OP}}(bits, BASE_REX, sink);
+                    sink.reloc_external(Reloc::X86CallPLTRel4,
+                                        &func.dfg.ext_funcs[func_ref].name,
+                                        -4);
+                    sink.put4(0);
+                "#,
+            ),
+    );
+
+    // The callee address is pinned to r11, which neither passes arguments nor is callee-saved,
+    // so it survives the frame teardown.
+    recipes.add_template_recipe(
+        EncodingRecipeBuilder::new("tail_call_r", &formats.call_indirect, 1)
+            .operands_in(vec![reg_r11])
+            .compute_size("size_plus_tail_call_epilogue")
+            .emit(
+                r#"
+                    tail_call_epilogue(func, sig_ref, sink);
+                    let r11 = RU::r11 as RegUnit;
+                    {{PUT_OP}}(bits, rex1(r11), sink);
+                    modrm_r_bits(r11, bits, sink);
+                "#,
+            ),
+    );
+
+    // Indirect tail calls through an inline retpoline, or through the shared r11 thunk.
+    recipes.add_template_recipe(
+        EncodingRecipeBuilder::new("tail_call_retpoline", &formats.call_indirect, 15)
+            .operands_in(vec![reg_r11])
+            .compute_size("size_plus_tail_call_epilogue")
+            .emit(
+                r#"
+                    tail_call_epilogue(func, sig_ref, sink);
+                    let r11 = RU::r11 as RegUnit;
+                    retpoline_jump_head(func.srclocs[inst], sink);
+                    {{PUT_OP}}(bits, rex2(RU::rsp.into(), r11), sink);
+                    modrm_sib(r11, sink);
+                    sib_noindex(RU::rsp.into(), sink);
+                    sink.put1(0xc3);
+                "#,
+            ),
+    );
+
+    recipes.add_template_recipe(
+        EncodingRecipeBuilder::new("tail_call_thunk_r11", &formats.call_indirect, 4)
+            .operands_in(vec![reg_r11])
+            .compute_size("size_plus_tail_call_epilogue")
+            .emit(
+                r#"
+                    tail_call_epilogue(func, sig_ref, sink);
+                    {{PUT_OP}}(bits, BASE_REX, sink);
+                    sink.reloc_external(Reloc::X86CallPCRel4,
+                                        &ExternalName::LibCall(LibCall::RetpolineThunkR11),
+                                        -4);
+                    sink.put4(0);
+                "#,
+            ),
     );
 
     // Branches.
@@ -2493,6 +3822,44 @@ pub(crate) fn define<'shared>(
             ),
     );
 
+    // Retpoline for an indirect jump: like the one for calls, without the outer call.
+    recipes.add_template_recipe(
+        EncodingRecipeBuilder::new("retpoline_jmp", &formats.indirect_jump, 15)
+            .operands_in(vec![gpr])
+            .clobbers_flags(false)
+            .emit(
+                r#"
+                    retpoline_jump_head(func.srclocs[inst], sink);
+                    {{PUT_OP}}(bits, rex2(RU::rsp.into(), in_reg0), sink);
+                    modrm_sib(in_reg0, sink);
+                    sib_noindex(RU::rsp.into(), sink);
+                    sink.put1(0xc3);
+                "#,
+            ),
+    );
+
+    // Jumps through the module's shared retpoline thunk.
+    for &(name, target, thunk) in &[
+        ("jmp_thunk", reg_rax, "RetpolineThunkEax"),
+        ("jmp_thunk_r11", reg_r11, "RetpolineThunkR11"),
+    ] {
+        recipes.add_template_recipe(
+            EncodingRecipeBuilder::new(name, &formats.indirect_jump, 4)
+                .operands_in(vec![target])
+                .clobbers_flags(false)
+                .emit(format!(
+                    r#"
+                        {{{{PUT_OP}}}}(bits, BASE_REX, sink);
+                        sink.reloc_external(Reloc::X86CallPCRel4,
+                                            &ExternalName::LibCall(LibCall::{}),
+                                            -4);
+                        sink.put4(0);
+                    "#,
+                    thunk
+                )),
+        );
+    }
+
     recipes.add_template_recipe(
         EncodingRecipeBuilder::new("jt_entry", &formats.branch_table_entry, 2)
             .operands_in(vec![gpr, gpr])
@@ -2556,6 +3923,59 @@ pub(crate) fn define<'shared>(
             ),
     );
 
+    // 32-bit code can't address the trailing constants and jump tables relative to the
+    // instruction pointer. Without a REX prefix, the `modrm_riprel` form is an absolute address.
+    recipes.add_template_recipe(
+        EncodingRecipeBuilder::new("vconst_abs4", &formats.unary_const, 5)
+            .operands_out(vec![fpr])
+            .clobbers_flags(false)
+            .emit(
+                r#"
+                    {{PUT_OP}}(bits, rex2(0, out_reg0), sink);
+                    modrm_riprel(out_reg0, sink);
+                    const_ref4(Reloc::Abs4, constant_handle, func, sink);
+                "#,
+            ),
+    );
+
+    recipes.add_template_recipe(
+        EncodingRecipeBuilder::new("vconst_gotoff4", &formats.unary_const, 5)
+            .operands_out(vec![fpr])
+            .clobbers_flags(false)
+            .emit(
+                r#"
+                    {{PUT_OP}}(bits, rex2(RU::rbx.into(), out_reg0), sink);
+                    modrm_disp32(RU::rbx.into(), out_reg0, sink);
+                    const_ref4(Reloc::X86GOTOff4, constant_handle, func, sink);
+                "#,
+            ),
+    );
+
+    recipes.add_template_recipe(
+        EncodingRecipeBuilder::new("jt_base_abs4", &formats.branch_table_base, 4)
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .emit(
+                r#"
+                    {{PUT_OP}}(bits | (out_reg0 & 7), rex1(out_reg0), sink);
+                    jt_ref4(Reloc::Abs4, table, func, sink);
+                "#,
+            ),
+    );
+
+    recipes.add_template_recipe(
+        EncodingRecipeBuilder::new("jt_base_gotoff4", &formats.branch_table_base, 5)
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .emit(
+                r#"
+                    {{PUT_OP}}(bits, rex2(RU::rbx.into(), out_reg0), sink);
+                    modrm_disp32(RU::rbx.into(), out_reg0, sink);
+                    jt_ref4(Reloc::X86GOTOff4, table, func, sink);
+                "#,
+            ),
+    );
+
     // Test flags and set a register.
     //
     // These setCC instructions only set the low 8 bits, and they can only write ABCD registers
@@ -2699,6 +4119,85 @@ pub(crate) fn define<'shared>(
         .inferred_rex_compute_size("size_with_inferred_rex_for_inreg0_inreg1"),
     );
 
+    // XX /r, RM form. Multiply two GPR registers and set the overflow flag.
+    recipes.add_template(
+        Template::new(
+            EncodingRecipeBuilder::new("rrxout", &formats.binary, 1)
+                .operands_in(vec![gpr, gpr])
+                .operands_out(vec![
+                    OperandConstraint::TiedInput(0),
+                    OperandConstraint::FixedReg(reg_rflags),
+                ])
+                .clobbers_flags(true)
+                .emit(
+                    r#"
+                        {{PUT_OP}}(bits, rex2(in_reg1, in_reg0), sink);
+                        modrm_rr(in_reg1, in_reg0, sink);
+                    "#,
+                ),
+            regs,
+        )
+        .inferred_rex_compute_size("size_with_inferred_rex_for_inreg0_inreg1"),
+    );
+
+    // XX /n ib with an 8-bit sign-extended immediate, setting the flags.
+    recipes.add_template(
+        Template::new(
+            EncodingRecipeBuilder::new("r_ib_out", &formats.binary_imm, 2)
+                .operands_in(vec![gpr])
+                .operands_out(vec![
+                    OperandConstraint::TiedInput(0),
+                    OperandConstraint::FixedReg(reg_rflags),
+                ])
+                .inst_predicate(InstructionPredicate::new_is_signed_int(
+                    &*formats.binary_imm,
+                    "imm",
+                    8,
+                    0,
+                ))
+                .clobbers_flags(true)
+                .emit(
+                    r#"
+                        {{PUT_OP}}(bits, rex1(in_reg0), sink);
+                        modrm_r_bits(in_reg0, bits, sink);
+                        let imm: i64 = imm.into();
+                        sink.put1(imm as u8);
+                    "#,
+                ),
+            regs,
+        )
+        .inferred_rex_compute_size("size_with_inferred_rex_for_inreg0"),
+    );
+
+    // XX /n id with a 32-bit sign-extended immediate, setting the flags.
+    recipes.add_template(
+        Template::new(
+            EncodingRecipeBuilder::new("r_id_out", &formats.binary_imm, 5)
+                .operands_in(vec![gpr])
+                .operands_out(vec![
+                    OperandConstraint::TiedInput(0),
+                    OperandConstraint::FixedReg(reg_rflags),
+                ])
+                .inst_predicate(InstructionPredicate::new_is_signed_int(
+                    &*formats.binary_imm,
+                    "imm",
+                    32,
+                    0,
+                ))
+                .clobbers_flags(true)
+                .emit(
+                    r#"
+                        {{PUT_OP}}(bits, rex1(in_reg0), sink);
+                        modrm_r_bits(in_reg0, bits, sink);
+                        let imm: i64 = imm.into();
+                        sink.put4(imm as u32);
+                    "#,
+                ),
+            regs,
+        )
+        .inferred_rex_compute_size("size_with_inferred_rex_for_inreg0"),
+    );
+
     // XX /r, MR form. Add two GPR registers and get carry flag.
     recipes.add_template(
         Template::new(
@@ -3056,6 +4555,25 @@ pub(crate) fn define<'shared>(
             ),
     );
 
+    // VEX.NDS form of icscc_fpr, which doesn't overwrite its first input.
+    recipes.add_template(
+        Template::new(
+            EncodingRecipeBuilder::new("vicscc_fpr", &formats.int_compare, 1)
+                .operands_in(vec![fpr, fpr])
+                .operands_out(vec![fpr])
+                .emit(
+                    r#"
+                        // Comparison instruction.
+                        {{PUT_OP}}(bits, rex2(in_reg1, out_reg0), Some(in_reg0), sink);
+                        modrm_rr(in_reg1, out_reg0, sink);
+                    "#,
+                ),
+            regs,
+        )
+        .rex_kind(RexRecipeKind::Vex)
+        .inferred_rex_compute_size("size_with_inferred_vex_for_inreg1"),
+    );
+
     {
         let is_small_imm =
             InstructionPredicate::new_is_signed_int(&*formats.int_compare_imm, "imm", 8, 0);
@@ -3203,6 +4721,43 @@ pub(crate) fn define<'shared>(
                 "#,
                 ),
         );
+
+        // VEX.NDS form of pfcmp, which doesn't overwrite its first input.
+        recipes.add_template(
+            Template::new(
+                EncodingRecipeBuilder::new("vpfcmp", &formats.float_compare, 2)
+                    .operands_in(vec![fpr, fpr])
+                    .operands_out(vec![fpr])
+                    .inst_predicate(supported_floatccs_predicate(
+                        &supported_floatccs[..],
+                        &*formats.float_compare,
+                    ))
+                    .emit(
+                        r#"
+                        // Comparison instruction.
+                        {{PUT_OP}}(bits, rex2(in_reg1, out_reg0), Some(in_reg0), sink);
+                        modrm_rr(in_reg1, out_reg0, sink);
+                        // Add immediate byte indicating what type of comparison.
+                        use crate::ir::condcodes::FloatCC::*;
+                        let imm = match cond {
+                            Equal                      => 0x00,
+                            LessThan                   => 0x01,
+                            LessThanOrEqual            => 0x02,
+                            Unordered                  => 0x03,
+                            NotEqual                   => 0x04,
+                            UnorderedOrGreaterThanOrEqual => 0x05,
+                            UnorderedOrGreaterThan => 0x06,
+                            Ordered                    => 0x07,
+                            _ => panic!("{} not supported by vpfcmp", cond),
+                        };
+                        sink.put1(imm);
+                    "#,
+                    ),
+                regs,
+            )
+            .rex_kind(RexRecipeKind::Vex)
+            .inferred_rex_compute_size("size_with_inferred_vex_for_inreg1"),
+        );
     }
 
     recipes.add_template_recipe(
@@ -9,9 +9,14 @@ pub(crate) fn define(shared: &SettingGroup) -> SettingGroup {
     let has_sse41 = settings.add_bool("has_sse41", "SSE4.1: CPUID.01H:ECX.SSE4_1[bit 19]", false);
     let has_sse42 = settings.add_bool("has_sse42", "SSE4.2: CPUID.01H:ECX.SSE4_2[bit 20]", false);
     let has_popcnt = settings.add_bool("has_popcnt", "POPCNT: CPUID.01H:ECX.POPCNT[bit 23]", false);
-    settings.add_bool("has_avx", "AVX: CPUID.01H:ECX.AVX[bit 28]", false);
+    let has_avx = settings.add_bool("has_avx", "AVX: CPUID.01H:ECX.AVX[bit 28]", false);
 
     // CPUID.(EAX=07H, ECX=0H):EBX
+    let has_avx2 = settings.add_bool(
+        "has_avx2",
+        "AVX2: CPUID.(EAX=07H, ECX=0H):EBX.AVX2[bit 5]",
+        false,
+    );
     let has_bmi1 = settings.add_bool(
         "has_bmi1",
         "BMI1: CPUID.(EAX=07H, ECX=0H):EBX.BMI1[bit 3]",
@@ -30,6 +35,38 @@ pub(crate) fn define(shared: &SettingGroup) -> SettingGroup {
         false,
     );
 
+    settings.add_bool(
+        "enable_ibt",
+        r#"
+        Emit `endbr` landing pads for Intel CET indirect branch tracking.
+
+        This places an `endbr32` or `endbr64` at the entry of every function that
+        may be called indirectly, and at every jump table target.
+        "#,
+        false,
+    );
+
+    let enable_retpoline = settings.add_bool(
+        "enable_retpoline",
+        r#"
+        Lower indirect calls, indirect tail calls and jumps through jump tables to
+        retpolines, which keep the indirect branch predictor from steering speculative
+        execution (Spectre variant 2).
+        "#,
+        false,
+    );
+    let retpoline_shared_thunks = settings.add_bool(
+        "retpoline_shared_thunks",
+        r#"
+        Call or jump to a retpoline thunk shared by the whole module, with the target
+        in `%r11` (`%eax` in 32-bit mode), instead of expanding a retpoline inline at
+        every indirect branch.
+
+        The module backend defines the thunk once per module.
+        "#,
+        false,
+    );
+
     let shared_enable_simd = shared.get_bool("enable_simd");
 
     settings.add_predicate("use_ssse3", predicate!(has_ssse3));
@@ -49,10 +86,28 @@ pub(crate) fn define(shared: &SettingGroup) -> SettingGroup {
         predicate!(shared_enable_simd && has_sse41 && has_sse42),
     );
 
+    settings.add_predicate("use_avx", predicate!(has_avx));
+    settings.add_predicate("use_avx_simd", predicate!(shared_enable_simd && has_avx));
+    settings.add_predicate(
+        "use_avx2_simd",
+        predicate!(shared_enable_simd && has_avx && has_avx2),
+    );
+
     settings.add_predicate("use_popcnt", predicate!(has_popcnt && has_sse42));
     settings.add_predicate("use_bmi1", predicate!(has_bmi1));
+    settings.add_predicate("use_bmi2", predicate!(has_bmi2));
     settings.add_predicate("use_lzcnt", predicate!(has_lzcnt));
 
+    settings.add_predicate(
+        "use_retpoline_inline",
+        predicate!(enable_retpoline && !retpoline_shared_thunks),
+    );
+    settings.add_predicate(
+        "use_retpoline_thunks",
+        predicate!(enable_retpoline && retpoline_shared_thunks),
+    );
+    settings.add_predicate("not_enable_retpoline", predicate!(!enable_retpoline));
+
     // Some shared boolean values are used in x86 instruction predicates, so we need to group them
     // in the same TargetIsa, for compabitibity with code generated by meta-python.
     // TODO Once all the meta generation code has been migrated from Python to Rust, we can put it
@@ -71,6 +71,15 @@ pub(crate) struct Immediates {
     ///
     /// The Rust enum type also has a `User(u16)` variant for user-provided trap codes.
     pub trapcode: OperandKind,
+
+    /// A memory ordering for atomic instructions.
+    ///
+    /// This enumerated operand kind is used by the `atomic_*` and `fence` instructions and
+    /// corresponds to the `ir::AtomicOrdering` Rust type.
+    pub atomic_ordering: OperandKind,
+
+    /// The operation performed by an `atomic_rmw` instruction.
+    pub atomic_rmw_op: OperandKind,
 }
 
 fn new_imm(format_field_name: &'static str, rust_type: &'static str) -> OperandKind {
@@ -156,6 +165,31 @@ impl Immediates {
                 trapcode_values.insert("int_divz", "IntegerDivisionByZero");
                 new_enum("code", "ir::TrapCode", trapcode_values).with_doc("A trap reason code.")
             },
+            atomic_ordering: {
+                let mut ordering_values = HashMap::new();
+                ordering_values.insert("relaxed", "Relaxed");
+                ordering_values.insert("acquire", "Acquire");
+                ordering_values.insert("release", "Release");
+                ordering_values.insert("acq_rel", "AcqRel");
+                ordering_values.insert("seq_cst", "SeqCst");
+                new_enum("ordering", "ir::AtomicOrdering", ordering_values)
+                    .with_doc("An atomic memory ordering.")
+            },
+            atomic_rmw_op: {
+                let mut rmw_values = HashMap::new();
+                rmw_values.insert("add", "Add");
+                rmw_values.insert("sub", "Sub");
+                rmw_values.insert("and", "And");
+                rmw_values.insert("or", "Or");
+                rmw_values.insert("xor", "Xor");
+                rmw_values.insert("xchg", "Xchg");
+                rmw_values.insert("umin", "Umin");
+                rmw_values.insert("umax", "Umax");
+                rmw_values.insert("smin", "Smin");
+                rmw_values.insert("smax", "Smax");
+                new_enum("op", "ir::AtomicRmwOp", rmw_values)
+                    .with_doc("An atomic read-modify-write operation.")
+            },
         }
     }
 }
@@ -459,6 +459,120 @@ fn define_control_flow(
         .is_call(true),
     );
 
+    let FN = &Operand::new("FN", &entities.func_ref)
+        .with_doc("function to call, declared by `function`");
+    let args = &Operand::new("args", &entities.varargs).with_doc("call arguments");
+    ig.push(
+        Inst::new(
+            "return_call",
+            r#"
+        Direct tail call.
+
+        Tail call a function which has been declared in the preamble. The
+        argument types must match the function's signature, the caller and
+        callee must use the same calling convention, and the callee's return
+        types must match the caller's.
+
+        The current function's frame is torn down before control is
+        transferred to the callee, so the callee returns directly to the
+        current function's caller.
+        "#,
+            &formats.call,
+        )
+        .operands_in(vec![FN, args])
+        .is_call(true)
+        .is_terminator(true),
+    );
+
+    let SIG = &Operand::new("SIG", &entities.sig_ref).with_doc("function signature");
+    let callee = &Operand::new("callee", iAddr).with_doc("address of function to call");
+    let args = &Operand::new("args", &entities.varargs).with_doc("call arguments");
+    ig.push(
+        Inst::new(
+            "return_call_indirect",
+            r#"
+        Indirect tail call.
+
+        Tail call the function pointed to by `callee` with the given arguments.
+        The same restrictions as for `return_call` apply.
+        "#,
+            &formats.call_indirect,
+        )
+        .operands_in(vec![SIG, callee, args])
+        .is_call(true)
+        .is_terminator(true),
+    );
+
+    let FN = &Operand::new("FN", &entities.func_ref)
+        .with_doc("function to call, declared by `function`");
+    let EBB = &Operand::new("EBB", &entities.ebb).with_doc("landing pad to unwind to");
+    let args = &Operand::new("args", &entities.varargs).with_doc("call arguments");
+    let rvals = &Operand::new("rvals", &entities.varargs).with_doc("return values");
+    ig.push(
+        Inst::new(
+            "invoke",
+            r#"
+        Direct function call with a landing pad.
+
+        Call a function which has been declared in the preamble, like `call`.
+        If the callee returns normally, execution continues with the next
+        instruction. If an exception unwinds through the call, control is
+        transferred to the landing pad `EBB` instead, and the return values
+        are not defined there.
+
+        The landing pad can't take any EBB arguments. Use `landing_pad` to
+        get the values passed along by the unwinder.
+        "#,
+            &formats.invoke,
+        )
+        .operands_in(vec![FN, EBB, args])
+        .operands_out(vec![rvals])
+        .is_call(true)
+        .is_branch(true)
+        .can_unwind(true),
+    );
+
+    let SIG = &Operand::new("SIG", &entities.sig_ref).with_doc("function signature");
+    let callee = &Operand::new("callee", iAddr).with_doc("address of function to call");
+    let args = &Operand::new("args", &entities.varargs).with_doc("call arguments");
+    let rvals = &Operand::new("rvals", &entities.varargs).with_doc("return values");
+    ig.push(
+        Inst::new(
+            "invoke_indirect",
+            r#"
+        Indirect function call with a landing pad.
+
+        Call the function pointed to by `callee` like `call_indirect`, and
+        unwind to the landing pad `EBB` like `invoke`.
+        "#,
+            &formats.invoke_indirect,
+        )
+        .operands_in(vec![SIG, EBB, callee, args])
+        .operands_out(vec![rvals])
+        .is_call(true)
+        .is_branch(true)
+        .can_unwind(true),
+    );
+
+    let exn = &Operand::new("exn", iAddr).with_doc("exception pointer");
+    let selector = &Operand::new("selector", iAddr).with_doc("handler selector");
+    ig.push(
+        Inst::new(
+            "landing_pad",
+            r#"
+        Get the values passed to a landing pad by the unwinder.
+
+        This must be the first instruction in an EBB that is only reached as
+        the landing pad of `invoke` instructions. The meaning of the two
+        values depends on the personality routine; for the Itanium C++ ABI
+        they are the exception object pointer and the handler selector.
+        "#,
+            &formats.nullary,
+        )
+        .operands_out(vec![exn, selector])
+        .other_side_effects(true),
+    );
+
     let FN = &Operand::new("FN", &entities.func_ref)
         .with_doc("function to call, declared by `function`");
     let addr = &Operand::new("addr", iAddr);
@@ -945,6 +1059,113 @@ pub(crate) fn define(
         .can_store(true),
     );
 
+    let AtomicMem = &TypeVar::new(
+        "AtomicMem",
+        "An integer type that can be accessed atomically",
+        TypeSetBuilder::new().ints(8..64).build(),
+    );
+    let Ordering = &Operand::new("Ordering", &imm.atomic_ordering);
+    let RmwOp = &Operand::new("RmwOp", &imm.atomic_rmw_op);
+    let x = &Operand::new("x", AtomicMem).with_doc("Value to be stored or combined");
+    let a = &Operand::new("a", AtomicMem).with_doc("Value loaded");
+    let expected = &Operand::new("expected", AtomicMem).with_doc("Value expected in memory");
+    let replacement = &Operand::new("replacement", AtomicMem).with_doc("Value to store on a match");
+
+    ig.push(
+        Inst::new(
+            "atomic_load",
+            r#"
+        Atomically load from memory at ``p + Offset``.
+
+        The address must be naturally aligned for the loaded type. The
+        ``Ordering`` can't be ``release`` or ``acq_rel``.
+        "#,
+            &formats.atomic_load,
+        )
+        .operands_in(vec![Ordering, MemFlags, p, Offset])
+        .operands_out(vec![a])
+        .can_load(true)
+        .other_side_effects(true),
+    );
+
+    ig.push(
+        Inst::new(
+            "atomic_store",
+            r#"
+        Atomically store ``x`` to memory at ``p + Offset``.
+
+        The address must be naturally aligned for the stored type. The
+        ``Ordering`` can't be ``acquire`` or ``acq_rel``.
+        "#,
+            &formats.atomic_store,
+        )
+        .operands_in(vec![Ordering, MemFlags, x, p, Offset])
+        .can_store(true)
+        .other_side_effects(true),
+    );
+
+    ig.push(
+        Inst::new(
+            "atomic_rmw",
+            r#"
+        Atomically read-modify-write memory at ``p``.
+
+        Load the old value from memory, combine it with ``x`` using ``RmwOp``
+        and store the result back as one atomic operation. The result ``a`` is
+        the old value.
+
+        There is no immediate offset. The address must be naturally aligned
+        for the accessed type.
+        "#,
+            &formats.atomic_rmw,
+        )
+        .operands_in(vec![Ordering, MemFlags, RmwOp, x, p])
+        .operands_out(vec![a])
+        .can_load(true)
+        .can_store(true)
+        .other_side_effects(true),
+    );
+
+    ig.push(
+        Inst::new(
+            "atomic_cas",
+            r#"
+        Atomically compare and swap memory at ``p``.
+
+        If the value in memory equals ``expected``, replace it with
+        ``replacement``. In either case, the result ``a`` is the value that
+        was in memory before the operation, so the swap succeeded exactly when
+        ``a == expected``.
+
+        There is no immediate offset. The address must be naturally aligned
+        for the accessed type.
+        "#,
+            &formats.atomic_cas,
+        )
+        .operands_in(vec![Ordering, MemFlags, expected, replacement, p])
+        .operands_out(vec![a])
+        .can_load(true)
+        .can_store(true)
+        .other_side_effects(true),
+    );
+
+    ig.push(
+        Inst::new(
+            "fence",
+            r#"
+        A memory fence.
+
+        Prevent memory accesses from being reordered across the fence as
+        described by ``Ordering``, which can't be ``relaxed``.
+        "#,
+            &formats.fence,
+        )
+        .operands_in(vec![Ordering])
+        .can_load(true)
+        .can_store(true)
+        .other_side_effects(true),
+    );
+
     let x = &Operand::new("x", Mem).with_doc("Value to be stored");
     let a = &Operand::new("a", Mem).with_doc("Value loaded");
     let Offset =
@@ -1005,6 +1226,31 @@ pub(crate) fn define(
         .operands_out(vec![addr]),
     );
 
+    let size = &Operand::new("size", iAddr).with_doc("Number of bytes to allocate");
+    let Align = &Operand::new("Align", &imm.imm64).with_doc("Alignment of the allocated region");
+
+    ig.push(
+        Inst::new(
+            "dynamic_stack_alloc",
+            r#"
+        Allocate a dynamically sized region on the stack.
+
+        Allocate ``size`` bytes in the current stack frame and return the
+        address of the lowest byte. The address is a multiple of ``Align``,
+        which must be a power of two no larger than 2^31. The region is freed
+        when the function returns.
+
+        The stack pointer isn't fixed after the prologue in functions that use
+        this instruction, so their stack slots are addressed relative to the
+        frame pointer instead.
+        "#,
+            &formats.binary_imm,
+        )
+        .operands_in(vec![size, Align])
+        .operands_out(vec![addr])
+        .other_side_effects(true),
+    );
+
     let GV = &Operand::new("GV", &entities.global_value);
 
     ig.push(
@@ -1031,6 +1277,21 @@ pub(crate) fn define(
         .operands_out(vec![a]),
     );
 
+    ig.push(
+        Inst::new(
+            "tls_value",
+            r#"
+        Compute the value of global GV, which is a TLS (thread local storage) value.
+
+        The result is the address of the current thread's instance of the variable. How it is
+        computed depends on the ``tls_model`` setting.
+        "#,
+            &formats.unary_global_value,
+        )
+        .operands_in(vec![GV])
+        .operands_out(vec![a]),
+    );
+
     let HeapOffset = &TypeVar::new(
         "HeapOffset",
         "An unsigned heap offset",
@@ -2457,6 +2718,146 @@ pub(crate) fn define(
         .operands_out(vec![a, b_if_out]),
     );
 
+    let of = &Operand::new("of", b1).with_doc("Output overflow flag");
+
+    ig.push(
+        Inst::new(
+            "uadd_overflow",
+            r#"
+        Add unsigned integers with overflow out.
+
+        Same as `iadd` with an additional overflow output that is set when the
+        sum doesn't fit in an unsigned `B`-bit integer.
+
+        ```text
+            a &= x + y \pmod 2^B \\
+            of &= x + y >= 2^B
+        ```
+
+        Polymorphic over all scalar integer types, but does not support vector
+        types.
+        "#,
+            &formats.binary,
+        )
+        .operands_in(vec![x, y])
+        .operands_out(vec![a, of]),
+    );
+
+    ig.push(
+        Inst::new(
+            "sadd_overflow",
+            r#"
+        Add signed integers with overflow out.
+
+        Same as `iadd` with an additional overflow output that is set when the
+        sum doesn't fit in a signed `B`-bit integer.
+
+        ```text
+            a &= x + y \pmod 2^B \\
+            of &= x + y \notin [-2^{B-1}, 2^{B-1})
+        ```
+
+        Polymorphic over all scalar integer types, but does not support vector
+        types.
+        "#,
+            &formats.binary,
+        )
+        .operands_in(vec![x, y])
+        .operands_out(vec![a, of]),
+    );
+
+    ig.push(
+        Inst::new(
+            "usub_overflow",
+            r#"
+        Subtract unsigned integers with overflow out.
+
+        Same as `isub` with an additional overflow output that is set when the
+        difference is negative.
+
+        ```text
+            a &= x - y \pmod 2^B \\
+            of &= x < y
+        ```
+
+        Polymorphic over all scalar integer types, but does not support vector
+        types.
+        "#,
+            &formats.binary,
+        )
+        .operands_in(vec![x, y])
+        .operands_out(vec![a, of]),
+    );
+
+    ig.push(
+        Inst::new(
+            "ssub_overflow",
+            r#"
+        Subtract signed integers with overflow out.
+
+        Same as `isub` with an additional overflow output that is set when the
+        difference doesn't fit in a signed `B`-bit integer.
+
+        ```text
+            a &= x - y \pmod 2^B \\
+            of &= x - y \notin [-2^{B-1}, 2^{B-1})
+        ```
+
+        Polymorphic over all scalar integer types, but does not support vector
+        types.
+        "#,
+            &formats.binary,
+        )
+        .operands_in(vec![x, y])
+        .operands_out(vec![a, of]),
+    );
+
+    ig.push(
+        Inst::new(
+            "umul_overflow",
+            r#"
+        Multiply unsigned integers with overflow out.
+
+        Same as `imul` with an additional overflow output that is set when the
+        product doesn't fit in an unsigned `B`-bit integer.
+
+        ```text
+            a &= x y \pmod 2^B \\
+            of &= x y >= 2^B
+        ```
+
+        Polymorphic over all scalar integer types, but does not support vector
+        types.
+        "#,
+            &formats.binary,
+        )
+        .operands_in(vec![x, y])
+        .operands_out(vec![a, of]),
+    );
+
+    ig.push(
+        Inst::new(
+            "smul_overflow",
+            r#"
+        Multiply signed integers with overflow out.
+
+        Same as `imul` with an additional overflow output that is set when the
+        product doesn't fit in a signed `B`-bit integer.
+
+        ```text
+            a &= x y \pmod 2^B \\
+            of &= x y \notin [-2^{B-1}, 2^{B-1})
+        ```
+
+        Polymorphic over all scalar integer types, but does not support vector
+        types.
+        "#,
+            &formats.binary,
+        )
+        .operands_in(vec![x, y])
+        .operands_out(vec![a, of]),
+    );
+
     let bits = &TypeVar::new(
         "bits",
         "Any integer, float, or boolean scalar or vector type",
@@ -5,7 +5,7 @@ use crate::cdsl::xform::{TransformGroupBuilder, TransformGroups};
 use crate::shared::immediates::Immediates;
 use crate::shared::types::Float::{F32, F64};
 use crate::shared::types::Int::{I128, I16, I32, I64, I8};
-use cranelift_codegen_shared::condcodes::{CondCode, IntCC};
+use cranelift_codegen_shared::condcodes::IntCC;
 
 #[allow(clippy::many_single_char_names, clippy::cognitive_complexity)]
 pub(crate) fn define(insts: &InstructionGroup, imm: &Immediates) -> TransformGroups {
@@ -89,6 +89,7 @@ pub(crate) fn define(insts: &InstructionGroup, imm: &Immediates) -> TransformGro
     let isplit = insts.by_name("isplit");
     let istore8 = insts.by_name("istore8");
     let istore16 = insts.by_name("istore16");
+    let istore32 = insts.by_name("istore32");
     let isub = insts.by_name("isub");
     let isub_bin = insts.by_name("isub_bin");
     let isub_bout = insts.by_name("isub_bout");
@@ -102,21 +103,32 @@ pub(crate) fn define(insts: &InstructionGroup, imm: &Immediates) -> TransformGro
     let rotl_imm = insts.by_name("rotl_imm");
     let rotr = insts.by_name("rotr");
     let rotr_imm = insts.by_name("rotr_imm");
+    let sadd_overflow = insts.by_name("sadd_overflow");
     let sdiv = insts.by_name("sdiv");
     let sdiv_imm = insts.by_name("sdiv_imm");
     let select = insts.by_name("select");
+    let sload8 = insts.by_name("sload8");
+    let sload16 = insts.by_name("sload16");
+    let sload32 = insts.by_name("sload32");
     let sextend = insts.by_name("sextend");
+    let smul_overflow = insts.by_name("smul_overflow");
+    let smulhi = insts.by_name("smulhi");
     let sshr = insts.by_name("sshr");
     let sshr_imm = insts.by_name("sshr_imm");
     let srem = insts.by_name("srem");
     let srem_imm = insts.by_name("srem_imm");
+    let ssub_overflow = insts.by_name("ssub_overflow");
     let store = insts.by_name("store");
+    let uadd_overflow = insts.by_name("uadd_overflow");
     let udiv = insts.by_name("udiv");
     let udiv_imm = insts.by_name("udiv_imm");
     let uextend = insts.by_name("uextend");
     let uload8 = insts.by_name("uload8");
     let uload16 = insts.by_name("uload16");
+    let uload32 = insts.by_name("uload32");
     let umulhi = insts.by_name("umulhi");
+    let umul_overflow = insts.by_name("umul_overflow");
+    let usub_overflow = insts.by_name("usub_overflow");
     let ushr = insts.by_name("ushr");
     let ushr_imm = insts.by_name("ushr_imm");
     let urem = insts.by_name("urem");
@@ -130,8 +142,17 @@ pub(crate) fn define(insts: &InstructionGroup, imm: &Immediates) -> TransformGro
     expand.custom_legalize(insts.by_name("heap_addr"), "expand_heap_addr");
     expand.custom_legalize(insts.by_name("table_addr"), "expand_table_addr");
 
+    // Custom expansions for atomic memory accesses. Small integer types are accessed directly,
+    // not widened, so they need the same expansions.
+    for name in &["atomic_load", "atomic_store", "atomic_rmw", "atomic_cas"] {
+        expand.custom_legalize(insts.by_name(name), "expand_atomic");
+        widen.custom_legalize(insts.by_name(name), "expand_atomic");
+    }
+
     // Custom expansions for calls.
     expand.custom_legalize(insts.by_name("call"), "expand_call");
+    expand.custom_legalize(insts.by_name("return_call"), "expand_call");
+    expand.custom_legalize(insts.by_name("invoke"), "expand_call");
 
     // Custom expansions that need to change the CFG.
     // TODO: Add sufficient XForm syntax that we don't need to hand-code these.
@@ -154,6 +175,10 @@ pub(crate) fn define(insts: &InstructionGroup, imm: &Immediates) -> TransformGro
     widen.custom_legalize(insts.by_name("stack_load"), "expand_stack_load");
     widen.custom_legalize(insts.by_name("stack_store"), "expand_stack_store");
 
+    // Wide stack memory accesses are narrowed once they have been turned into loads and stores.
+    narrow.custom_legalize(insts.by_name("stack_load"), "expand_stack_load");
+    narrow.custom_legalize(insts.by_name("stack_store"), "expand_stack_store");
+
     // List of variables to reuse in patterns.
     let x = var("x");
     let y = var("y");
@@ -187,6 +212,9 @@ pub(crate) fn define(insts: &InstructionGroup, imm: &Immediates) -> TransformGro
     let e2 = var("e2");
     let e3 = var("e3");
     let e4 = var("e4");
+    let e5 = var("e5");
+    let e6 = var("e6");
+    let e7 = var("e7");
     let f = var("f");
     let f1 = var("f1");
     let f2 = var("f2");
@@ -212,8 +240,12 @@ pub(crate) fn define(insts: &InstructionGroup, imm: &Immediates) -> TransformGro
     // embedded as part of arguments), so use a custom legalization for now.
     narrow.custom_legalize(iconst, "narrow_iconst");
 
-    {
-        let inst = uextend.bind(I128).bind(I64);
+    // Integers are narrowed into halves of the next smaller type, so both the i64 and the i128
+    // instructions below are expressed in terms of their halves.
+    let halves = [(I64, I32, 64), (I128, I64, 128)];
+
+    for &(ty, half_ty, bits) in &halves {
+        let inst = uextend.bind(ty).bind(half_ty);
         narrow.legalize(
             def!(a = inst(x)),
             vec![
@@ -221,19 +253,65 @@ pub(crate) fn define(insts: &InstructionGroup, imm: &Immediates) -> TransformGro
                 def!(a = iconcat(x, ah)),
             ],
         );
-    }
 
-    {
-        let inst = sextend.bind(I128).bind(I64);
+        let inst = sextend.bind(ty).bind(half_ty);
         narrow.legalize(
             def!(a = inst(x)),
             vec![
-                def!(ah = sshr_imm(x, Literal::constant(&imm.imm64, 63))), // splat sign bit to whole number
+                def!(ah = sshr_imm(x, Literal::constant(&imm.imm64, bits / 2 - 1))), // splat sign bit to whole number
                 def!(a = iconcat(x, ah)),
             ],
         );
     }
 
+    // Narrower integers are extended to the half type first.
+    for &extend_op in &[uextend, sextend] {
+        for &int_ty in &[I8, I16] {
+            let inst = extend_op.bind(I64).bind(int_ty);
+            narrow.legalize(
+                def!(a = inst(x)),
+                vec![def!(a1 = extend_op.I32(x)), def!(a = extend_op(a1))],
+            );
+        }
+        for &int_ty in &[I8, I16, I32] {
+            let inst = extend_op.bind(I128).bind(int_ty);
+            narrow.legalize(
+                def!(a = inst(x)),
+                vec![def!(a1 = extend_op.I64(x)), def!(a = extend_op(a1))],
+            );
+        }
+    }
+
+    for &(ty, half_ty, _) in &halves {
+        narrow.legalize(
+            def!(a = bint.ty(b)),
+            vec![def!(a1 = bint.half_ty(b)), def!(a = uextend(a1))],
+        );
+    }
+
+    // The controlling type of `ireduce` is the narrow result, so reducing a split integer is
+    // handled by the groups that legalize its result type.
+    for &(ty, half_ty, _) in &halves {
+        for &int_ty in &[I8, I16] {
+            let inst = ireduce.bind(int_ty).bind(ty);
+            widen.legalize(
+                def!(a = inst(x)),
+                vec![def!((xl, xh) = isplit(x)), def!(a = ireduce(xl))],
+            );
+        }
+
+        let inst = ireduce.bind(half_ty).bind(ty);
+        expand.legalize(def!(a = inst(x)), vec![def!((a, xh) = isplit(x))]);
+    }
+
+    {
+        let inst = ireduce.bind(I32).bind(I128);
+        expand.legalize(
+            def!(a = inst(x)),
+            vec![def!((xl, xh) = isplit(x)), def!(a = ireduce(xl))],
+        );
+    }
+
     for &bin_op in &[band, bor, bxor, band_not, bor_not, bxor_not] {
         narrow.legalize(
             def!(a = bin_op(x, y)),
@@ -268,50 +346,53 @@ pub(crate) fn define(insts: &InstructionGroup, imm: &Immediates) -> TransformGro
         ],
     );
 
-    narrow.legalize(
-        def!(brz.I128(x, ebb, vararg)),
-        vec![
-            def!((xl, xh) = isplit(x)),
-            def!(
-                a = icmp_imm(
-                    Literal::enumerator_for(&imm.intcc, "eq"),
-                    xl,
-                    Literal::constant(&imm.imm64, 0)
-                )
-            ),
-            def!(
-                b = icmp_imm(
-                    Literal::enumerator_for(&imm.intcc, "eq"),
-                    xh,
-                    Literal::constant(&imm.imm64, 0)
-                )
-            ),
-            def!(c = band(a, b)),
-            def!(brnz(c, ebb, vararg)),
-        ],
-    );
+    for &(ty, _, _) in &halves {
+        let ebb2 = ebb2.clone();
+        narrow.legalize(
+            def!(brz.ty(x, ebb, vararg)),
+            vec![
+                def!((xl, xh) = isplit(x)),
+                def!(
+                    a = icmp_imm(
+                        Literal::enumerator_for(&imm.intcc, "eq"),
+                        xl,
+                        Literal::constant(&imm.imm64, 0)
+                    )
+                ),
+                def!(
+                    b = icmp_imm(
+                        Literal::enumerator_for(&imm.intcc, "eq"),
+                        xh,
+                        Literal::constant(&imm.imm64, 0)
+                    )
+                ),
+                def!(c = band(a, b)),
+                def!(brnz(c, ebb, vararg)),
+            ],
+        );
 
-    narrow.legalize(
-        def!(brnz.I128(x, ebb1, vararg)),
-        vec![
-            def!((xl, xh) = isplit(x)),
-            def!(brnz(xl, ebb1, vararg)),
-            def!(jump(ebb2, Literal::empty_vararg())),
-            ebb!(ebb2),
-            def!(brnz(xh, ebb1, vararg)),
-        ],
-    );
+        narrow.legalize(
+            def!(brnz.ty(x, ebb1, vararg)),
+            vec![
+                def!((xl, xh) = isplit(x)),
+                def!(brnz(xl, ebb1, vararg)),
+                def!(jump(ebb2, Literal::empty_vararg())),
+                ebb!(ebb2),
+                def!(brnz(xh, ebb1, vararg)),
+            ],
+        );
 
-    narrow.legalize(
-        def!(a = popcnt.I128(x)),
-        vec![
-            def!((xl, xh) = isplit(x)),
-            def!(e1 = popcnt(xl)),
-            def!(e2 = popcnt(xh)),
-            def!(e3 = iadd(e1, e2)),
-            def!(a = uextend(e3)),
-        ],
-    );
+        narrow.legalize(
+            def!(a = popcnt.ty(x)),
+            vec![
+                def!((xl, xh) = isplit(x)),
+                def!(e1 = popcnt(xl)),
+                def!(e2 = popcnt(xh)),
+                def!(e3 = iadd(e1, e2)),
+                def!(a = uextend(e3)),
+            ],
+        );
+    }
 
     // TODO(ryzokuken): benchmark this and decide if branching is a faster
     // approach than evaluating boolean expressions.
@@ -356,21 +437,18 @@ pub(crate) fn define(insts: &InstructionGroup, imm: &Immediates) -> TransformGro
         ] {
             let intcc_cc = Literal::enumerator_for(&imm.intcc, cc.to_static_str());
             let cc1 = Literal::enumerator_for(&imm.intcc, cc.without_equal().to_static_str());
-            let cc2 =
-                Literal::enumerator_for(&imm.intcc, cc.inverse().without_equal().to_static_str());
             let cc3 = Literal::enumerator_for(&imm.intcc, cc.unsigned().to_static_str());
             narrow.legalize(
                 def!(b = icmp.int_ty(intcc_cc, x, y)),
                 vec![
                     def!((xl, xh) = isplit(x)),
                     def!((yl, yh) = isplit(y)),
-                    // X = cc1 || (!cc2 && cc3)
+                    // X = cc1 || (eq && cc3)
                     def!(b1 = icmp.int_ty_half(cc1, xh, yh)),
-                    def!(b2 = icmp.int_ty_half(cc2, xh, yh)),
+                    def!(b2 = icmp.int_ty_half(intcc_eq, xh, yh)),
                     def!(b3 = icmp.int_ty_half(cc3, xl, yl)),
-                    def!(c1 = bnot(b2)),
-                    def!(c2 = band(c1, b3)),
-                    def!(b = bor(b1, c2)),
+                    def!(c1 = band(b2, b3)),
+                    def!(b = bor(b1, c1)),
                 ],
             );
         }
@@ -395,6 +473,299 @@ pub(crate) fn define(insts: &InstructionGroup, imm: &Immediates) -> TransformGro
         );
     }
 
+    let imm64_0 = Literal::constant(&imm.imm64, 0);
+    for &(ty, half_ty, bits) in &halves {
+        // Immediate operands are materialized as narrowed constants.
+        for &(inst_imm, inst) in &[
+            (iadd_imm, iadd),
+            (imul_imm, imul),
+            (sdiv_imm, sdiv),
+            (udiv_imm, udiv),
+            (srem_imm, srem),
+            (urem_imm, urem),
+            (band_imm, band),
+            (bor_imm, bor),
+            (bxor_imm, bxor),
+        ] {
+            narrow.legalize(
+                def!(a = inst_imm.ty(x, y)),
+                vec![def!(a1 = iconst.ty(y)), def!(a = inst(x, a1))],
+            );
+        }
+
+        narrow.legalize(
+            def!(a = irsub_imm.ty(y, x)),
+            vec![def!(a1 = iconst.ty(x)), def!(a = isub(a1, y))],
+        );
+
+        for &(inst_imm, inst) in &[
+            (rotl_imm, rotl),
+            (rotr_imm, rotr),
+            (ishl_imm, ishl),
+            (sshr_imm, sshr),
+            (ushr_imm, ushr),
+        ] {
+            narrow.legalize(
+                def!(a = inst_imm.ty(x, y)),
+                vec![def!(a1 = iconst.I32(y)), def!(a = inst(x, a1))],
+            );
+        }
+
+        // Only the low bits of a split shift amount matter.
+        for &op in &[ishl, ushr, sshr, rotl, rotr] {
+            let inst = op.bind(ty).bind(ty);
+            narrow.legalize(
+                def!(a = inst(x, y)),
+                vec![def!((yl, yh) = isplit(y)), def!(a = op(x, yl))],
+            );
+        }
+
+        // Shifts by a variable amount. The shifts of the halves already take the amount modulo
+        // their width, and the next bit of the amount selects whether the result is moved
+        // across halves. The bits crossing between halves are shifted in two steps so that a
+        // zero amount doesn't turn into a shift by the full width of a half.
+        let imm64_1 = Literal::constant(&imm.imm64, 1);
+        let imm64_half = Literal::constant(&imm.imm64, bits / 2);
+        let imm64_half_m1 = Literal::constant(&imm.imm64, bits / 2 - 1);
+        let imm64_bits = Literal::constant(&imm.imm64, bits);
+
+        narrow.legalize(
+            def!(a = ishl.ty(x, y)),
+            vec![
+                def!((xl, xh) = isplit(x)),
+                def!(e1 = ishl(xl, y)),
+                def!(e2 = ishl(xh, y)),
+                def!(e3 = ushr_imm(xl, imm64_1)),
+                def!(e4 = bnot(y)),
+                def!(e5 = ushr(e3, e4)),
+                def!(e6 = bor(e2, e5)),
+                def!(e7 = band_imm(y, imm64_half)),
+                def!(z = iconst.half_ty(imm64_0)),
+                def!(al = select(e7, z, e1)),
+                def!(ah = select(e7, e1, e6)),
+                def!(a = iconcat(al, ah)),
+            ],
+        );
+
+        narrow.legalize(
+            def!(a = ushr.ty(x, y)),
+            vec![
+                def!((xl, xh) = isplit(x)),
+                def!(e1 = ushr(xh, y)),
+                def!(e2 = ushr(xl, y)),
+                def!(e3 = ishl_imm(xh, imm64_1)),
+                def!(e4 = bnot(y)),
+                def!(e5 = ishl(e3, e4)),
+                def!(e6 = bor(e2, e5)),
+                def!(e7 = band_imm(y, imm64_half)),
+                def!(z = iconst.half_ty(imm64_0)),
+                def!(al = select(e7, e1, e6)),
+                def!(ah = select(e7, z, e1)),
+                def!(a = iconcat(al, ah)),
+            ],
+        );
+
+        narrow.legalize(
+            def!(a = sshr.ty(x, y)),
+            vec![
+                def!((xl, xh) = isplit(x)),
+                def!(e1 = sshr(xh, y)),
+                def!(e2 = ushr(xl, y)),
+                def!(e3 = ishl_imm(xh, imm64_1)),
+                def!(e4 = bnot(y)),
+                def!(e5 = ishl(e3, e4)),
+                def!(e6 = bor(e2, e5)),
+                def!(e7 = band_imm(y, imm64_half)),
+                def!(z = sshr_imm(xh, imm64_half_m1)),
+                def!(al = select(e7, e1, e6)),
+                def!(ah = select(e7, z, e1)),
+                def!(a = iconcat(al, ah)),
+            ],
+        );
+
+        // Rotating by `bits - y` is the same as rotating the other way by `y`, and the shifts
+        // above take the amount modulo `bits`.
+        for &(rot, shift, shift_back) in &[(rotl, ishl, ushr), (rotr, ushr, ishl)] {
+            narrow.legalize(
+                def!(a = rot.ty(x, y)),
+                vec![
+                    def!(e1 = shift(x, y)),
+                    def!(e2 = irsub_imm(y, imm64_bits)),
+                    def!(e3 = shift_back(x, e2)),
+                    def!(a = bor(e1, e3)),
+                ],
+            );
+        }
+
+        // Bit counting continues into the other half when the first half is exhausted.
+        narrow.legalize(
+            def!(a = clz.ty(x)),
+            vec![
+                def!((xl, xh) = isplit(x)),
+                def!(e1 = clz(xh)),
+                def!(e2 = clz(xl)),
+                def!(e3 = iadd_imm(e2, imm64_half)),
+                def!(c = icmp_imm(intcc_eq, xh, imm64_0)),
+                def!(e4 = select(c, e3, e1)),
+                def!(a = uextend(e4)),
+            ],
+        );
+
+        narrow.legalize(
+            def!(a = ctz.ty(x)),
+            vec![
+                def!((xl, xh) = isplit(x)),
+                def!(e1 = ctz(xl)),
+                def!(e2 = ctz(xh)),
+                def!(e3 = iadd_imm(e2, imm64_half)),
+                def!(c = icmp_imm(intcc_eq, xl, imm64_0)),
+                def!(e4 = select(c, e3, e1)),
+                def!(a = uextend(e4)),
+            ],
+        );
+
+        narrow.legalize(
+            def!(a = cls.ty(x)),
+            vec![
+                def!((xl, xh) = isplit(x)),
+                def!(e1 = cls(xh)),
+                def!(e2 = sshr_imm(xh, imm64_half_m1)),
+                def!(e3 = bxor(xl, e2)),
+                def!(e4 = clz(e3)),
+                def!(e5 = iadd_imm(e4, imm64_half_m1)),
+                def!(c = icmp_imm(intcc_eq, e1, imm64_half_m1)),
+                def!(e6 = select(c, e5, e1)),
+                def!(a = uextend(e6)),
+            ],
+        );
+
+        narrow.legalize(
+            def!(a = bitrev.ty(x)),
+            vec![
+                def!((xl, xh) = isplit(x)),
+                def!(ah = bitrev(xl)),
+                def!(al = bitrev(xh)),
+                def!(a = iconcat(al, ah)),
+            ],
+        );
+
+        // The high half of the full product is the sum of the partial products of the halves,
+        // shifted into place. Each of the partial sums below fits in the wide type.
+        let pl = var("pl");
+        let ph = var("ph");
+        let ql = var("ql");
+        let qh = var("qh");
+        let rl = var("rl");
+        let rh = var("rh");
+        let t1 = var("t1");
+        let t1l = var("t1l");
+        let t1h = var("t1h");
+        let t2 = var("t2");
+        let t2l = var("t2l");
+        let t2h = var("t2h");
+        narrow.legalize(
+            def!(a = umulhi.ty(x, y)),
+            vec![
+                def!((xl, xh) = isplit(x)),
+                def!((yl, yh) = isplit(y)),
+                def!(e1 = umulhi(xl, yl)),
+                def!(pl = imul(xh, yl)),
+                def!(ph = umulhi(xh, yl)),
+                def!(ql = imul(xl, yh)),
+                def!(qh = umulhi(xl, yh)),
+                def!(rl = imul(xh, yh)),
+                def!(rh = umulhi(xh, yh)),
+                def!(e2 = uextend.ty(e1)),
+                def!(e3 = iconcat(pl, ph)),
+                def!(t1 = iadd(e3, e2)),
+                def!((t1l, t1h) = isplit(t1)),
+                def!(e4 = uextend.ty(t1l)),
+                def!(e5 = iconcat(ql, qh)),
+                def!(t2 = iadd(e5, e4)),
+                def!((t2l, t2h) = isplit(t2)),
+                def!(e6 = uextend.ty(t1h)),
+                def!(e7 = uextend.ty(t2h)),
+                def!(a1 = iconcat(rl, rh)),
+                def!(a2 = iadd(a1, e6)),
+                def!(a = iadd(a2, e7)),
+            ],
+        );
+
+        // A negative operand contributes the other operand times `2^bits` to the unsigned
+        // product, which is subtracted back from the high half.
+        let imm64_bits_m1 = Literal::constant(&imm.imm64, bits - 1);
+        narrow.legalize(
+            def!(a = smulhi.ty(x, y)),
+            vec![
+                def!(a1 = umulhi(x, y)),
+                def!(e1 = sshr_imm(x, imm64_bits_m1)),
+                def!(e2 = band(e1, y)),
+                def!(e3 = sshr_imm(y, imm64_bits_m1)),
+                def!(e4 = band(e3, x)),
+                def!(a2 = isub(a1, e2)),
+                def!(a = isub(a2, e4)),
+            ],
+        );
+    }
+
+    // Extending loads and truncating stores access the low half, which comes first in memory.
+    // The 32-bit variants only exist for `i64`, so they aren't polymorphic. The loads have no
+    // controlling type variable and are expanded instead of narrowed.
+    let imm64_31 = Literal::constant(&imm.imm64, 31);
+    for &load_op in &[uload8, uload16] {
+        narrow.legalize(
+            def!(a = load_op.I64(flags, ptr, offset)),
+            vec![
+                def!(al = load_op.I32(flags, ptr, offset)),
+                def!(ah = iconst.I32(imm64_0)),
+                def!(a = iconcat(al, ah)),
+            ],
+        );
+    }
+    for &load_op in &[sload8, sload16] {
+        narrow.legalize(
+            def!(a = load_op.I64(flags, ptr, offset)),
+            vec![
+                def!(al = load_op.I32(flags, ptr, offset)),
+                def!(ah = sshr_imm(al, imm64_31)),
+                def!(a = iconcat(al, ah)),
+            ],
+        );
+    }
+    for &store_op in &[istore8, istore16] {
+        narrow.legalize(
+            def!(store_op.I64(flags, x, ptr, offset)),
+            vec![
+                def!((xl, xh) = isplit(x)),
+                def!(store_op(flags, xl, ptr, offset)),
+            ],
+        );
+    }
+
+    expand.legalize(
+        def!(a = uload32(flags, ptr, offset)),
+        vec![
+            def!(al = load.I32(flags, ptr, offset)),
+            def!(ah = iconst.I32(imm64_0)),
+            def!(a = iconcat(al, ah)),
+        ],
+    );
+    expand.legalize(
+        def!(a = sload32(flags, ptr, offset)),
+        vec![
+            def!(al = load.I32(flags, ptr, offset)),
+            def!(ah = sshr_imm(al, imm64_31)),
+            def!(a = iconcat(al, ah)),
+        ],
+    );
+    narrow.legalize(
+        def!(istore32(flags, x, ptr, offset)),
+        vec![
+            def!((xl, xh) = isplit(x)),
+            def!(store(flags, xl, ptr, offset)),
+        ],
+    );
+
     // Widen instructions with one input operand.
     for &op in &[bnot, popcnt] {
         for &int_ty in &[I8, I16] {
@@ -629,57 +1000,145 @@ pub(crate) fn define(insts: &InstructionGroup, imm: &Immediates) -> TransformGro
         );
     }
 
-    // Expand integer operations with carry for RISC architectures that don't have
-    // the flags.
+    // Expand integer operations with carry and the overflow-reporting arithmetic for RISC
+    // architectures that don't have the flags. The same expansions narrow them, since the wide
+    // operations they are expressed in can be narrowed.
     let intcc_ult = Literal::enumerator_for(&imm.intcc, "ult");
-    expand.legalize(
-        def!((a, c) = iadd_cout(x, y)),
-        vec![def!(a = iadd(x, y)), def!(c = icmp(intcc_ult, a, x))],
-    );
-
     let intcc_ugt = Literal::enumerator_for(&imm.intcc, "ugt");
-    expand.legalize(
-        def!((a, b) = isub_bout(x, y)),
-        vec![def!(a = isub(x, y)), def!(b = icmp(intcc_ugt, a, x))],
-    );
+    let intcc_slt = Literal::enumerator_for(&imm.intcc, "slt");
+    for group in &mut [&mut expand, &mut narrow] {
+        group.legalize(
+            def!((a, c) = iadd_cout(x, y)),
+            vec![def!(a = iadd(x, y)), def!(c = icmp(intcc_ult, a, x))],
+        );
 
-    expand.legalize(
-        def!(a = iadd_cin(x, y, c)),
-        vec![
-            def!(a1 = iadd(x, y)),
-            def!(c_int = bint(c)),
-            def!(a = iadd(a1, c_int)),
-        ],
-    );
+        group.legalize(
+            def!((a, b) = isub_bout(x, y)),
+            vec![def!(a = isub(x, y)), def!(b = icmp(intcc_ugt, a, x))],
+        );
 
-    expand.legalize(
-        def!(a = isub_bin(x, y, b)),
-        vec![
-            def!(a1 = isub(x, y)),
-            def!(b_int = bint(b)),
-            def!(a = isub(a1, b_int)),
-        ],
-    );
+        group.legalize(
+            def!(a = iadd_cin(x, y, c)),
+            vec![
+                def!(a1 = iadd(x, y)),
+                def!(c_int = bint(c)),
+                def!(a = iadd(a1, c_int)),
+            ],
+        );
 
-    expand.legalize(
-        def!((a, c) = iadd_carry(x, y, c_in)),
-        vec![
-            def!((a1, c1) = iadd_cout(x, y)),
-            def!(c_int = bint(c_in)),
-            def!((a, c2) = iadd_cout(a1, c_int)),
-            def!(c = bor(c1, c2)),
-        ],
-    );
+        group.legalize(
+            def!(a = isub_bin(x, y, b)),
+            vec![
+                def!(a1 = isub(x, y)),
+                def!(b_int = bint(b)),
+                def!(a = isub(a1, b_int)),
+            ],
+        );
 
-    expand.legalize(
-        def!((a, b) = isub_borrow(x, y, b_in)),
-        vec![
-            def!((a1, b1) = isub_bout(x, y)),
-            def!(b_int = bint(b_in)),
-            def!((a, b2) = isub_bout(a1, b_int)),
-            def!(b = bor(b1, b2)),
-        ],
-    );
+        group.legalize(
+            def!((a, c) = iadd_carry(x, y, c_in)),
+            vec![
+                def!((a1, c1) = iadd_cout(x, y)),
+                def!(c_int = bint(c_in)),
+                def!((a, c2) = iadd_cout(a1, c_int)),
+                def!(c = bor(c1, c2)),
+            ],
+        );
+
+        group.legalize(
+            def!((a, b) = isub_borrow(x, y, b_in)),
+            vec![
+                def!((a1, b1) = isub_bout(x, y)),
+                def!(b_int = bint(b_in)),
+                def!((a, b2) = isub_bout(a1, b_int)),
+                def!(b = bor(b1, b2)),
+            ],
+        );
+
+        // The overflow-reporting arithmetic.
+        group.legalize(
+            def!((a, b) = uadd_overflow(x, y)),
+            vec![def!(a = iadd(x, y)), def!(b = icmp(intcc_ult, a, x))],
+        );
+
+        group.legalize(
+            def!((a, b) = usub_overflow(x, y)),
+            vec![def!(a = isub(x, y)), def!(b = icmp(intcc_ult, x, y))],
+        );
+
+        // A signed sum overflows when its sign differs from the signs of both operands.
+        group.legalize(
+            def!((a, b) = sadd_overflow(x, y)),
+            vec![
+                def!(a = iadd(x, y)),
+                def!(a1 = bxor(a, x)),
+                def!(a2 = bxor(a, y)),
+                def!(a3 = band(a1, a2)),
+                def!(b = icmp_imm(intcc_slt, a3, imm64_0)),
+            ],
+        );
+
+        // A signed difference overflows when the operands have different signs and its sign differs
+        // from the sign of `x`.
+        group.legalize(
+            def!((a, b) = ssub_overflow(x, y)),
+            vec![
+                def!(a = isub(x, y)),
+                def!(a1 = bxor(x, y)),
+                def!(a2 = bxor(a, x)),
+                def!(a3 = band(a1, a2)),
+                def!(b = icmp_imm(intcc_slt, a3, imm64_0)),
+            ],
+        );
+
+        group.legalize(
+            def!((a, b) = umul_overflow(x, y)),
+            vec![
+                def!(a = imul(x, y)),
+                def!(a1 = umulhi(x, y)),
+                def!(b = icmp_imm(intcc_ne, a1, imm64_0)),
+            ],
+        );
+
+        // A signed product fits when the high half is just the sign extension of the low half.
+        for &(ty, sign_bit) in &[(I32, 31), (I64, 63)] {
+            let sign_bit = Literal::constant(&imm.imm64, sign_bit);
+            group.legalize(
+                def!((a, b) = smul_overflow.ty(x, y)),
+                vec![
+                    def!(a = imul(x, y)),
+                    def!(a1 = smulhi(x, y)),
+                    def!(a2 = sshr_imm(a, sign_bit)),
+                    def!(b = icmp(intcc_ne, a1, a2)),
+                ],
+            );
+        }
+    }
+
+    // Small integers can't overflow when computed in 32 bits, so check that the result survives
+    // the round trip through the narrow type.
+    for &int_ty in &[I8, I16] {
+        for &(op, wide_op, ext) in &[
+            (uadd_overflow, iadd, uextend),
+            (usub_overflow, isub, uextend),
+            (umul_overflow, imul, uextend),
+            (sadd_overflow, iadd, sextend),
+            (ssub_overflow, isub, sextend),
+            (smul_overflow, imul, sextend),
+        ] {
+            widen.legalize(
+                def!((a, b) = op.int_ty(x, y)),
+                vec![
+                    def!(c1 = ext.I32(x)),
+                    def!(c2 = ext.I32(y)),
+                    def!(c3 = wide_op(c1, c2)),
+                    def!(a = ireduce.int_ty(c3)),
+                    def!(c4 = ext.I32(a)),
+                    def!(b = icmp(intcc_ne, c4, c3)),
+                ],
+            );
+        }
+    }
 
     // Expansion for fcvt_from_sint for smaller integer types.
     // This uses expand and not widen because the controlling type variable for
@@ -935,6 +1394,7 @@ pub(crate) fn define(insts: &InstructionGroup, imm: &Immediates) -> TransformGro
     }
 
     expand.custom_legalize(br_icmp, "expand_br_icmp");
+    narrow.custom_legalize(br_icmp, "expand_br_icmp");
 
     let mut groups = TransformGroups::new();
 
@@ -1,6 +1,6 @@
 //! Shared definitions for the Cranelift intermediate language.
 
-mod entities;
+pub mod entities;
 pub mod formats;
 pub mod immediates;
 pub mod instructions;
@@ -26,6 +26,7 @@ pub(crate) struct Definitions {
     pub all_instructions: AllInstructions,
     pub instructions: InstructionGroup,
     pub imm: Immediates,
+    pub entities: EntityRefs,
     pub formats: Formats,
     pub transform_groups: TransformGroups,
 }
@@ -45,6 +46,7 @@ pub(crate) fn define() -> Definitions {
         all_instructions,
         instructions,
         imm: immediates,
+        entities,
         formats,
         transform_groups,
     }
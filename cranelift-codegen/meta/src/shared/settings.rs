@@ -111,6 +111,21 @@ pub(crate) fn define() -> SettingGroup {
         false,
     );
 
+    settings.add_bool(
+        "enable_heap_access_spectre_mitigation",
+        r#"Enable Spectre mitigation on heap and table bounds checks.
+
+        This is a no-op for any heap or table that needs no bounds check, e.g. a static heap whose
+        guard region covers the whole offset range.
+
+        When enabled, the address computed by a legalized `heap_addr` or `table_addr` is selected
+        with a conditional move that yields a null address whenever the bounds check fails, so
+        that code speculatively executed past the trap cannot access out-of-bounds memory. This
+        requires an ISA with `selectif` encodings.
+        "#,
+        false,
+    );
+
     settings.add_bool("enable_simd", "Enable the use of SIMD instructions.", false);
 
     settings.add_bool(
@@ -131,6 +146,20 @@ pub(crate) fn define() -> SettingGroup {
         false,
     );
 
+    settings.add_num(
+        "unroll_threshold",
+        r#"
+            The maximum number of instructions in a loop after unrolling.
+
+            Counted loops with a constant trip count are fully unrolled when the
+            whole unrolled loop stays within this size, and otherwise unrolled a
+            few times, after peeling the iterations left over. Setting this to 0
+            disables loop unrolling. It only applies when `opt_level` is not
+            "none".
+            "#,
+        32,
+    );
+
     // Settings specific to the `baldrdash` calling convention.
 
     settings.add_enum(
@@ -150,6 +179,7 @@ pub(crate) fn define() -> SettingGroup {
             "fast",
             "cold",
             "system_v",
+            "tail",
             "windows_fastcall",
             "baldrdash_system_v",
             "baldrdash_windows",
@@ -157,6 +187,21 @@ pub(crate) fn define() -> SettingGroup {
         ],
     );
 
+    settings.add_enum(
+        "tls_model",
+        r#"
+            Defines the model used to perform TLS accesses.
+
+            - none: TLS accesses are not supported.
+            - elf_gd: The ELF general-dynamic model, which calls `__tls_get_addr`
+              and works for any thread-local symbol.
+            - elf_ie: The ELF initial-exec model, which loads the offset from the
+              thread pointer from the GOT. It only works for symbols defined in
+              the executable or in libraries loaded at startup.
+        "#,
+        vec!["none", "elf_gd", "elf_ie"],
+    );
+
     settings.add_num(
         "baldrdash_prologue_words",
         r#"
@@ -208,13 +253,28 @@ pub(crate) fn define() -> SettingGroup {
             The log2 of the size of the stack guard region.
 
             Stack frames larger than this size will have stack overflow checked
-            by calling the probestack function.
+            by probing the stack, as chosen by `probestack_strategy`.
 
             The default is 12, which translates to a size of 4096.
             "#,
         12,
     );
 
+    settings.add_enum(
+        "probestack_strategy",
+        r#"
+            The method used to probe the stack of large frames.
+
+            - outline: Call the probestack function, which the embedder provides
+              as the `Probestack` libcall.
+            - inline: Touch the pages of the frame with code in the function
+              itself: one probe per page for frames of a few pages, and a loop
+              for larger ones. This needs no libcall, and ignores
+              `probestack_func_adjusts_sp`.
+        "#,
+        vec!["outline", "inline"],
+    );
+
     // Jump table options.
 
     settings.add_bool(
@@ -9,8 +9,8 @@ use std::ops::RangeInclusive;
 /// styles of opcodes and prefixes. The opcode format is indicated by the
 /// recipe name prefix.
 ///
-/// VEX/XOP and EVEX prefixes are not yet supported.
-/// Encodings using any of these prefixes are represented by separate recipes.
+/// VEX-prefixed encodings are represented by separate recipes, which build the
+/// VEX prefix from the pp, mm and W bits. XOP and EVEX prefixes are not yet supported.
 ///
 /// The encoding bits are:
 ///
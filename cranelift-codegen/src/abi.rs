@@ -3,7 +3,7 @@
 //! This module provides functions and data structures that are useful for implementing the
 //! `TargetIsa::legalize_signature()` method.
 
-use crate::ir::{AbiParam, ArgumentExtension, ArgumentLoc, Type};
+use crate::ir::{AbiParam, ArgumentExtension, ArgumentLoc, Signature, Type};
 use alloc::borrow::Cow;
 use alloc::vec::Vec;
 use core::cmp::Ordering;
@@ -13,7 +13,7 @@ use core::cmp::Ordering;
 ///
 /// An argument may go through a sequence of legalization steps before it reaches the final
 /// `Assign` action.
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Debug)]
 pub enum ArgAction {
     /// Assign the argument to the given location.
     Assign(ArgumentLoc),
@@ -23,6 +23,12 @@ pub enum ArgAction {
     /// This action can split an integer type into two smaller integer arguments, or it can split a
     /// SIMD vector into halves.
     Convert(ValueConversion),
+
+    /// Replace the argument with the given parts, then call again for each part that doesn't
+    /// have a location yet.
+    ///
+    /// This is used to pass a struct argument by value as a sequence of smaller arguments.
+    Expand(Vec<AbiParam>),
 }
 
 impl From<ArgumentLoc> for ArgAction {
@@ -117,6 +123,10 @@ pub fn legalize_args<AA: ArgAssigner>(args: &[AbiParam], aa: &mut AA) -> Option<
                     args.to_mut().insert(argno + 1, new_arg);
                 }
             }
+            // Replace this argument with its parts. Then revisit the parts.
+            ArgAction::Expand(parts) => {
+                args.to_mut().splice(argno..=argno, parts);
+            }
         }
     }
 
@@ -126,6 +136,37 @@ pub fn legalize_args<AA: ArgAssigner>(args: &[AbiParam], aa: &mut AA) -> Option<
     }
 }
 
+/// Legalize the parameters of `sig` using the given argument assigner.
+///
+/// This works like `legalize_args`, but it also keeps the variadic marker of `sig` in front of the
+/// first variadic argument when the fixed parameters are split.
+pub fn legalize_params<AA: ArgAssigner>(sig: &mut Cow<Signature>, aa: &mut AA) {
+    let start = match sig.variadic {
+        Some(start) => start,
+        None => {
+            if let Some(new_params) = legalize_args(&sig.params, aa) {
+                sig.to_mut().params = new_params;
+            }
+            return;
+        }
+    };
+
+    // The assigner carries over from the fixed parameters to the variadic arguments, so this
+    // assigns the same locations as legalizing the whole list at once.
+    let fixed = legalize_args(&sig.params[..start], aa);
+    let varargs = legalize_args(&sig.params[start..], aa);
+    if fixed.is_none() && varargs.is_none() {
+        return;
+    }
+
+    let mut params = fixed.unwrap_or_else(|| sig.params[..start].to_vec());
+    let new_start = params.len();
+    params.extend(varargs.unwrap_or_else(|| sig.params[start..].to_vec()));
+    let sig = sig.to_mut();
+    sig.params = params;
+    sig.variadic = Some(new_start);
+}
+
 /// Determine the right action to take when passing a `have` value type to a call signature where
 /// the next argument is `arg` which has a different value type.
 ///
@@ -167,10 +208,16 @@ pub fn legalize_abi_value(have: Type, arg: &AbiParam) -> ValueConversion {
         }
         // We have the same number of bits as the argument.
         Ordering::Equal => {
-            // This must be an integer vector that is split and then extended.
             debug_assert!(arg.value_type.is_int());
-            debug_assert!(have.is_vector(), "expected vector type, got {}", have);
-            ValueConversion::VectorSplit
+            if have.is_float() {
+                // A float passed as an integer of the same size.
+                // The ARM soft-float ABI does this to pass an `f32` in a register.
+                ValueConversion::IntBits
+            } else {
+                // This must be an integer vector that is split and then extended.
+                debug_assert!(have.is_vector(), "expected vector type, got {}", have);
+                ValueConversion::VectorSplit
+            }
         }
         // We have more bits than the argument.
         Ordering::Greater => {
@@ -226,5 +273,11 @@ mod tests {
             legalize_abi_value(types::F64, &arg),
             ValueConversion::IntBits
         );
+
+        // 32-bit float is passed as an integer of the same size.
+        assert_eq!(
+            legalize_abi_value(types::F32, &arg),
+            ValueConversion::IntBits
+        );
     }
 }
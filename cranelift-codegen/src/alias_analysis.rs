@@ -0,0 +1,422 @@
+//! Alias analysis for memory accesses.
+//!
+//! Every address is decomposed into a base and a constant byte offset from it, looking through
+//! `iadd_imm` and additions of constants. The base is the memory object the address points into
+//! when that can be told: an explicit stack slot, a heap indexed by a given SSA value, or a
+//! global value like the VM context or a symbol. Other addresses are based on the SSA value
+//! computing them.
+//!
+//! Two accesses can then be proven not to alias when:
+//!
+//! - their `MemFlags` put them in different alias regions,
+//! - they are into different stack slots, or into a stack slot whose address never escapes and
+//!   some other memory,
+//! - they are into different heaps, or different symbols,
+//! - they have the same base and their constant offsets give disjoint byte ranges.
+//!
+//! Accesses with the same base, offset and size are to the same memory.
+
+use crate::entity::EntitySet;
+use crate::ir::{
+    ArgumentPurpose, DataFlowGraph, Function, GlobalValue, GlobalValueData, Heap, Inst,
+    InstructionData, MemFlags, Opcode, StackSlot, Type, Value, ValueDef,
+};
+
+/// The memory object an address points into.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Base {
+    /// An explicit stack slot.
+    StackSlot(StackSlot),
+    /// A heap, at the index given by the value.
+    Heap(Heap, Value),
+    /// The address of a global value: the VM context, a symbol, or a pointer loaded from
+    /// `readonly` memory.
+    GlobalValue(GlobalValue),
+    /// The address computed by a value.
+    Value(Value),
+    /// Nothing is known about the address.
+    Unknown,
+}
+
+/// A memory access made by an instruction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Access {
+    /// The memory object accessed.
+    pub base: Base,
+    /// The offset of the access from `base`, wrapped to the width of the address.
+    pub offset: i64,
+    /// The number of bytes accessed.
+    pub size: u32,
+    /// The width of the address in bits.
+    pub addr_bits: u16,
+    /// The flags of the access.
+    pub flags: MemFlags,
+}
+
+impl Access {
+    /// Do `self` and `other` access exactly the same bytes?
+    pub fn same_location(&self, other: &Self) -> bool {
+        self.base == other.base
+            && self.base != Base::Unknown
+            && self.offset == other.offset
+            && self.size == other.size
+    }
+
+    /// Are the bytes accessed by `self` and `other` disjoint, given that they have the same base?
+    ///
+    /// Offsets are compared modulo the width of the address, since address arithmetic wraps.
+    fn disjoint_from(&self, other: &Self) -> bool {
+        let modulus = 1i128 << self.addr_bits.max(other.addr_bits).min(64);
+        let distance = (i128::from(other.offset) - i128::from(self.offset)).rem_euclid(modulus);
+        i128::from(self.size) <= distance && distance + i128::from(other.size) <= modulus
+    }
+}
+
+/// Alias analysis results for a function.
+pub struct AliasAnalysis {
+    /// Stack slots whose address is used for something else than accessing them.
+    escaped: EntitySet<StackSlot>,
+    /// The global values that are the address of a symbol.
+    symbols: EntitySet<GlobalValue>,
+    /// The first global value standing for the VM context, if any.
+    vmctx: Option<GlobalValue>,
+    /// The VM context parameter, if any.
+    vmctx_param: Option<Value>,
+}
+
+impl AliasAnalysis {
+    /// Analyze `func`.
+    pub fn new(func: &Function) -> Self {
+        let vmctx = func
+            .global_values
+            .iter()
+            .find(|&(_, data)| match *data {
+                GlobalValueData::VMContext => true,
+                _ => false,
+            })
+            .map(|(gv, _)| gv);
+        let vmctx_param = match func.layout.entry_block() {
+            Some(_) => func.special_param(ArgumentPurpose::VMContext),
+            None => None,
+        };
+        let mut symbols = EntitySet::new();
+        for (gv, data) in func.global_values.iter() {
+            if let GlobalValueData::Symbol { .. } = *data {
+                symbols.insert(gv);
+            }
+        }
+        let mut aa = Self {
+            escaped: EntitySet::new(),
+            symbols,
+            vmctx,
+            vmctx_param,
+        };
+        aa.compute_escaped(func);
+        aa
+    }
+
+    /// Find the stack slots whose address escapes: it is used for anything else than as the
+    /// address of a plain load or store, or to compute another address into the same slot.
+    fn compute_escaped(&mut self, func: &Function) {
+        for ebb in func.layout.ebbs() {
+            for inst in func.layout.ebb_insts(ebb) {
+                let address_index = match func.dfg[inst] {
+                    InstructionData::Load { .. } => Some(0),
+                    InstructionData::Store { .. } => Some(1),
+                    _ => None,
+                };
+                let derived = match func.dfg.inst_results(inst) {
+                    [result] => match self.address(func, *result, 0).0 {
+                        Base::StackSlot(ss) => Some(ss),
+                        _ => None,
+                    },
+                    _ => None,
+                };
+                for (i, &arg) in func.dfg.inst_args(inst).iter().enumerate() {
+                    if let Base::StackSlot(ss) = self.address(func, arg, 0).0 {
+                        if Some(i) != address_index && Some(ss) != derived {
+                            self.escaped.insert(ss);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Decompose the address `addr + offset` into a base and a constant offset.
+    pub fn address(&self, func: &Function, addr: Value, offset: i64) -> (Base, i64) {
+        let dfg = &func.dfg;
+        let mut value = dfg.resolve_aliases(addr);
+        let mut offset = offset;
+        loop {
+            let inst = match dfg.value_def(value) {
+                ValueDef::Result(inst, _) => inst,
+                ValueDef::Param(_, _) => {
+                    return match self.vmctx {
+                        Some(gv) if self.vmctx_param == Some(value) => {
+                            (Base::GlobalValue(gv), offset)
+                        }
+                        _ => (Base::Value(value), offset),
+                    };
+                }
+            };
+            match dfg[inst] {
+                InstructionData::StackLoad {
+                    opcode: Opcode::StackAddr,
+                    stack_slot,
+                    offset: slot_offset,
+                } => {
+                    let slot_offset: i32 = slot_offset.into();
+                    return (
+                        Base::StackSlot(stack_slot),
+                        offset.wrapping_add(slot_offset.into()),
+                    );
+                }
+                InstructionData::HeapAddr { heap, arg, .. }
+                    if self.global_is_fixed(func, func.heaps[heap].base) =>
+                {
+                    return (Base::Heap(heap, dfg.resolve_aliases(arg)), offset);
+                }
+                InstructionData::UnaryGlobalValue {
+                    opcode: Opcode::GlobalValue,
+                    global_value,
+                }
+                | InstructionData::UnaryGlobalValue {
+                    opcode: Opcode::SymbolValue,
+                    global_value,
+                } => {
+                    return match self.global_address(func, global_value, offset) {
+                        Some(address) => address,
+                        None => (Base::Value(value), offset),
+                    };
+                }
+                InstructionData::BinaryImm {
+                    opcode: Opcode::IaddImm,
+                    arg,
+                    imm,
+                } => {
+                    let imm: i64 = imm.into();
+                    offset = offset.wrapping_add(imm);
+                    value = dfg.resolve_aliases(arg);
+                }
+                InstructionData::Binary {
+                    opcode: Opcode::Iadd,
+                    args,
+                } => match (iconst_value(dfg, args[0]), iconst_value(dfg, args[1])) {
+                    (_, Some(imm)) => {
+                        offset = offset.wrapping_add(imm);
+                        value = dfg.resolve_aliases(args[0]);
+                    }
+                    (Some(imm), None) => {
+                        offset = offset.wrapping_add(imm);
+                        value = dfg.resolve_aliases(args[1]);
+                    }
+                    (None, None) => return (Base::Value(value), offset),
+                },
+                _ => return (Base::Value(value), offset),
+            }
+        }
+    }
+
+    /// Decompose the address of the global value `gv`, plus `offset`.
+    ///
+    /// Returns `None` if `gv` is loaded from memory that may change, so its address isn't fixed.
+    fn global_address(&self, func: &Function, gv: GlobalValue, offset: i64) -> Option<(Base, i64)> {
+        match func.global_values[gv] {
+            GlobalValueData::VMContext => {
+                let gv = self.vmctx.unwrap_or(gv);
+                Some((Base::GlobalValue(gv), offset))
+            }
+            GlobalValueData::Load { readonly: true, .. } => Some((Base::GlobalValue(gv), offset)),
+            GlobalValueData::Load { .. } => None,
+            GlobalValueData::IAddImm {
+                base, offset: imm, ..
+            } => {
+                let imm: i64 = imm.into();
+                self.global_address(func, base, offset.wrapping_add(imm))
+            }
+            GlobalValueData::Symbol {
+                ref name,
+                offset: imm,
+                tls,
+                ..
+            } => {
+                // Canonicalize to the first global value naming the same symbol.
+                let first = func
+                    .global_values
+                    .iter()
+                    .find(|&(_, data)| match *data {
+                        GlobalValueData::Symbol {
+                            name: ref other_name,
+                            tls: other_tls,
+                            ..
+                        } => other_name == name && other_tls == tls,
+                        _ => false,
+                    })
+                    .map_or(gv, |(first, _)| first);
+                let imm: i64 = imm.into();
+                Some((Base::GlobalValue(first), offset.wrapping_add(imm)))
+            }
+        }
+    }
+
+    /// Is the address of `gv` the same for the whole function?
+    fn global_is_fixed(&self, func: &Function, gv: GlobalValue) -> bool {
+        self.global_address(func, gv, 0).is_some()
+    }
+
+    /// Get the memory access made by `inst`, if it is a load or a store of a single value at an
+    /// address this analysis understands.
+    ///
+    /// This covers `load`, `store`, their extending and truncating forms, `stack_load`,
+    /// `stack_store` and `global_value` of a global value loaded from memory.
+    pub fn access(&self, func: &Function, inst: Inst) -> Option<Access> {
+        let dfg = &func.dfg;
+        let (addr, offset, size, flags) = match dfg[inst] {
+            InstructionData::Load {
+                opcode,
+                arg,
+                flags,
+                offset,
+            } => {
+                let ty = dfg.value_type(dfg.first_result(inst));
+                (arg, offset, access_size(opcode, ty), flags)
+            }
+            InstructionData::Store {
+                opcode,
+                args,
+                flags,
+                offset,
+            } => {
+                let ty = dfg.value_type(args[0]);
+                (args[1], offset, access_size(opcode, ty), flags)
+            }
+            InstructionData::StackLoad {
+                opcode: Opcode::StackLoad,
+                stack_slot,
+                offset,
+            } => {
+                let ty = dfg.value_type(dfg.first_result(inst));
+                let offset: i32 = offset.into();
+                return Some(Access {
+                    base: Base::StackSlot(stack_slot),
+                    offset: offset.into(),
+                    size: ty.bytes(),
+                    addr_bits: 64,
+                    flags: MemFlags::trusted(),
+                });
+            }
+            InstructionData::StackStore {
+                arg,
+                stack_slot,
+                offset,
+                ..
+            } => {
+                let ty = dfg.value_type(arg);
+                let offset: i32 = offset.into();
+                return Some(Access {
+                    base: Base::StackSlot(stack_slot),
+                    offset: offset.into(),
+                    size: ty.bytes(),
+                    addr_bits: 64,
+                    flags: MemFlags::trusted(),
+                });
+            }
+            InstructionData::UnaryGlobalValue {
+                opcode: Opcode::GlobalValue,
+                global_value,
+            } => match func.global_values[global_value] {
+                GlobalValueData::Load {
+                    base,
+                    offset,
+                    global_type,
+                    readonly,
+                } => {
+                    let mut flags = MemFlags::trusted();
+                    if readonly {
+                        flags.set_readonly();
+                    }
+                    let offset: i32 = offset.into();
+                    let (base, offset) = self
+                        .global_address(func, base, offset.into())
+                        .unwrap_or((Base::Unknown, 0));
+                    return Some(Access {
+                        base,
+                        offset,
+                        size: global_type.bytes(),
+                        addr_bits: 64,
+                        flags,
+                    });
+                }
+                _ => return None,
+            },
+            _ => return None,
+        };
+        let addr_bits = dfg.value_type(addr).bits();
+        let offset: i32 = offset.into();
+        let (base, offset) = self.address(func, addr, offset.into());
+        Some(Access {
+            base,
+            offset: wrap_offset(offset, addr_bits),
+            size,
+            addr_bits,
+            flags,
+        })
+    }
+
+    /// May the accesses `a` and `b` touch the same memory?
+    pub fn may_alias(&self, a: &Access, b: &Access) -> bool {
+        if let (Some(ra), Some(rb)) = (a.flags.alias_region(), b.flags.alias_region()) {
+            if ra != rb {
+                return false;
+            }
+        }
+        match (a.base, b.base) {
+            (Base::StackSlot(sa), Base::StackSlot(sb)) => sa == sb && !a.disjoint_from(b),
+            (Base::StackSlot(ss), _) | (_, Base::StackSlot(ss)) => self.escaped.contains(ss),
+            (Base::Heap(ha, ia), Base::Heap(hb, ib)) => {
+                ha == hb && (ia != ib || !a.disjoint_from(b))
+            }
+            (Base::GlobalValue(ga), Base::GlobalValue(gb)) => {
+                ga == gb && !a.disjoint_from(b)
+                    || ga != gb && !(self.symbols.contains(ga) && self.symbols.contains(gb))
+            }
+            (Base::Value(va), Base::Value(vb)) if va == vb => !a.disjoint_from(b),
+            _ => true,
+        }
+    }
+}
+
+/// Get the value of `value` if it is defined by an `iconst`.
+fn iconst_value(dfg: &DataFlowGraph, value: Value) -> Option<i64> {
+    match dfg.value_def(dfg.resolve_aliases(value)) {
+        ValueDef::Result(inst, _) => match dfg[inst] {
+            InstructionData::UnaryImm {
+                opcode: Opcode::Iconst,
+                imm,
+            } => Some(imm.into()),
+            _ => None,
+        },
+        ValueDef::Param(_, _) => None,
+    }
+}
+
+/// Get the number of bytes accessed by a load or store `opcode` of a value of type `ty`.
+fn access_size(opcode: Opcode, ty: Type) -> u32 {
+    match opcode {
+        Opcode::Uload8 | Opcode::Sload8 | Opcode::Istore8 => 1,
+        Opcode::Uload16 | Opcode::Sload16 | Opcode::Istore16 => 2,
+        Opcode::Uload32 | Opcode::Sload32 | Opcode::Istore32 => 4,
+        _ => ty.bytes(),
+    }
+}
+
+/// Wrap `offset` to an address of `bits` bits, sign-extending it back.
+fn wrap_offset(offset: i64, bits: u16) -> i64 {
+    if bits >= 64 {
+        offset
+    } else {
+        let shift = 64 - u32::from(bits);
+        offset.wrapping_shl(shift).wrapping_shr(shift)
+    }
+}
@@ -39,6 +39,7 @@ pub struct MemoryCodeSink<'a> {
     relocs: &'a mut dyn RelocSink,
     traps: &'a mut dyn TrapSink,
     stackmaps: &'a mut dyn StackmapSink,
+    call_sites: &'a mut dyn CallSiteSink,
     /// Information about the generated code and read-only data.
     pub info: CodeInfo,
 }
@@ -55,6 +56,7 @@ impl<'a> MemoryCodeSink<'a> {
         relocs: &'a mut dyn RelocSink,
         traps: &'a mut dyn TrapSink,
         stackmaps: &'a mut dyn StackmapSink,
+        call_sites: &'a mut dyn CallSiteSink,
     ) -> Self {
         Self {
             data,
@@ -68,6 +70,7 @@ impl<'a> MemoryCodeSink<'a> {
             relocs,
             traps,
             stackmaps,
+            call_sites,
         }
     }
 }
@@ -152,6 +155,11 @@ impl<'a> CodeSink for MemoryCodeSink<'a> {
         self.traps.trap(ofs, srcloc, code);
     }
 
+    fn add_call_site(&mut self, landing_pad: CodeOffset, srcloc: SourceLoc) {
+        let ofs = self.offset();
+        self.call_sites.add_call_site(ofs, landing_pad, srcloc);
+    }
+
     fn begin_jumptables(&mut self) {
         self.info.code_size = self.offset();
     }
@@ -203,3 +211,21 @@ pub struct NullStackmapSink {}
 impl StackmapSink for NullStackmapSink {
     fn add_stackmap(&mut self, _: CodeOffset, _: Stackmap) {}
 }
+
+/// A trait for receiving the landing pads of call sites that can unwind.
+///
+/// Each call site is identified by its return address, which is what an unwinder sees when it
+/// walks the stack. Together, the call sites form the table a personality routine needs to find
+/// the landing pad to resume at.
+pub trait CallSiteSink {
+    /// Add a call site returning to the first offset, unwinding to the landing pad at the second.
+    fn add_call_site(&mut self, _: CodeOffset, _: CodeOffset, _: SourceLoc);
+}
+
+/// A `CallSiteSink` implementation that does nothing, which is convenient when
+/// compiling code that does not use `invoke`.
+pub struct NullCallSiteSink {}
+
+impl CallSiteSink for NullCallSiteSink {
+    fn add_call_site(&mut self, _: CodeOffset, _: CodeOffset, _: SourceLoc) {}
+}
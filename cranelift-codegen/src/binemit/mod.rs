@@ -9,8 +9,8 @@ mod shrink;
 mod stackmap;
 
 pub use self::memorysink::{
-    MemoryCodeSink, NullRelocSink, NullStackmapSink, NullTrapSink, RelocSink, StackmapSink,
-    TrapSink,
+    CallSiteSink, MemoryCodeSink, NullCallSiteSink, NullRelocSink, NullStackmapSink, NullTrapSink,
+    RelocSink, StackmapSink, TrapSink,
 };
 pub use self::relaxation::relax_branches;
 pub use self::shrink::shrink_instructions;
@@ -50,12 +50,43 @@ pub enum Reloc {
     X86CallPLTRel4,
     /// x86 GOT PC-relative 4-byte
     X86GOTPCRel4,
+    /// x86 PC-relative 4-byte offset to the GOT itself
+    X86GOTPC4,
+    /// x86 4-byte offset of a symbol's GOT entry from the GOT
+    X86GOT4,
+    /// x86 4-byte offset of a symbol from the GOT
+    X86GOTOff4,
     /// Arm32 call target
     Arm32Call,
+    /// Arm32 `movw` of the low 16 bits of a symbol address
+    Arm32MovwAbsNc,
+    /// Arm32 `movt` of the high 16 bits of a symbol address
+    Arm32MovtAbs,
+    /// Thumb-2 call target
+    Arm32ThmCall,
+    /// Thumb-2 `movw` of the low 16 bits of a symbol address
+    Arm32ThmMovwAbsNc,
+    /// Thumb-2 `movt` of the high 16 bits of a symbol address
+    Arm32ThmMovtAbs,
     /// Arm64 call target
     Arm64Call,
+    /// Arm64 `adrp` of the 4 KiB page containing a symbol
+    Arm64AdrPrelPgHi21,
+    /// Arm64 `add` of the low 12 bits of a symbol address
+    Arm64AddAbsLo12Nc,
+    /// Arm64 `adrp` of the 4 KiB page containing the GOT entry of a symbol
+    Arm64AdrGotPage21,
+    /// Arm64 `ldr` of the low 12 bits of the address of the GOT entry of a symbol
+    Arm64Ld64GotLo12Nc,
     /// RISC-V call target
     RiscvCall,
+
+    /// Elf x86_64 32 bit signed PC relative offset to two GOT entries for GD symbol.
+    ElfX86_64TlsGd,
+
+    /// Elf x86_64 32 bit signed PC relative offset to the GOT entry holding the offset of an IE
+    /// symbol from the thread pointer.
+    ElfX86_64GotTpOff,
 }
 
 impl fmt::Display for Reloc {
@@ -70,7 +101,21 @@ impl fmt::Display for Reloc {
             Self::X86CallPCRel4 => write!(f, "CallPCRel4"),
             Self::X86CallPLTRel4 => write!(f, "CallPLTRel4"),
             Self::X86GOTPCRel4 => write!(f, "GOTPCRel4"),
-            Self::Arm32Call | Self::Arm64Call | Self::RiscvCall => write!(f, "Call"),
+            Self::X86GOTPC4 => write!(f, "GOTPC4"),
+            Self::X86GOT4 => write!(f, "GOT4"),
+            Self::X86GOTOff4 => write!(f, "GOTOff4"),
+            Self::Arm32Call | Self::Arm32ThmCall | Self::Arm64Call | Self::RiscvCall => {
+                write!(f, "Call")
+            }
+            Self::Arm32MovwAbsNc | Self::Arm32ThmMovwAbsNc => write!(f, "MovwAbsNc"),
+            Self::Arm32MovtAbs | Self::Arm32ThmMovtAbs => write!(f, "MovtAbs"),
+            Self::Arm64AdrPrelPgHi21 => write!(f, "AdrPrelPgHi21"),
+            Self::Arm64AddAbsLo12Nc => write!(f, "AddAbsLo12Nc"),
+            Self::Arm64AdrGotPage21 => write!(f, "AdrGotPage21"),
+            Self::Arm64Ld64GotLo12Nc => write!(f, "Ld64GotLo12Nc"),
+
+            Self::ElfX86_64TlsGd => write!(f, "ElfX86_64TlsGd"),
+            Self::ElfX86_64GotTpOff => write!(f, "ElfX86_64GotTpOff"),
         }
     }
 }
@@ -142,6 +187,10 @@ pub trait CodeSink {
     /// Add trap information for the current offset.
     fn trap(&mut self, _: TrapCode, _: SourceLoc);
 
+    /// Add a call site returning to the current offset that unwinds to the landing pad at the
+    /// given offset.
+    fn add_call_site(&mut self, _: CodeOffset, _: SourceLoc);
+
     /// Machine code output is complete, jump table data may follow.
     fn begin_jumptables(&mut self);
 
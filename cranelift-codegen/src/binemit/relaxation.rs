@@ -168,6 +168,11 @@ fn try_fold_redundant_jump(
         }
     };
 
+    // The landing pad of an invoke is not a jump target.
+    if func.dfg[first_inst].opcode().can_unwind() {
+        return false;
+    }
+
     // For the moment, only attempt to fold a branch to an ebb that is parameterless.
     // These blocks are mainly produced by critical edge splitting.
     //
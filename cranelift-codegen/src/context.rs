@@ -10,8 +10,8 @@
 //! single ISA instance.
 
 use crate::binemit::{
-    relax_branches, shrink_instructions, CodeInfo, MemoryCodeSink, RelocSink, StackmapSink,
-    TrapSink,
+    relax_branches, shrink_instructions, CallSiteSink, CodeInfo, MemoryCodeSink, RelocSink,
+    StackmapSink, TrapSink,
 };
 use crate::dce::do_dce;
 use crate::dominator_tree::DominatorTree;
@@ -26,11 +26,14 @@ use crate::postopt::do_postopt;
 use crate::redundant_reload_remover::RedundantReloadRemover;
 use crate::regalloc;
 use crate::result::CodegenResult;
+use crate::rle::do_rle;
+use crate::sccp::do_sccp;
 use crate::settings::{FlagsOrIsa, OptLevel};
 use crate::simple_gvn::do_simple_gvn;
 use crate::simple_preopt::do_preopt;
 use crate::timing;
 use crate::unreachable_code::eliminate_unreachable_code;
+use crate::unroll::do_unroll;
 use crate::value_label::{build_value_labels_ranges, ComparableSourceLoc, ValueLabelsRanges};
 use crate::verifier::{verify_context, verify_locations, VerifierErrors, VerifierResult};
 use alloc::vec::Vec;
@@ -109,12 +112,20 @@ impl Context {
         relocs: &mut dyn RelocSink,
         traps: &mut dyn TrapSink,
         stackmaps: &mut dyn StackmapSink,
+        call_sites: &mut dyn CallSiteSink,
     ) -> CodegenResult<CodeInfo> {
         let info = self.compile(isa)?;
         let old_len = mem.len();
         mem.resize(old_len + info.total_size as usize, 0);
         let new_info = unsafe {
-            self.emit_to_memory(isa, mem.as_mut_ptr().add(old_len), relocs, traps, stackmaps)
+            self.emit_to_memory(
+                isa,
+                mem.as_mut_ptr().add(old_len),
+                relocs,
+                traps,
+                stackmaps,
+                call_sites,
+            )
         };
         debug_assert!(new_info == info);
         Ok(info)
@@ -137,6 +148,12 @@ impl Context {
         self.compute_cfg();
         if opt_level != OptLevel::None {
             self.preopt(isa)?;
+            self.compute_domtree();
+            self.compute_loop_analysis();
+            self.unroll(isa)?;
+            self.sccp(isa)?;
+            self.compute_domtree();
+            self.rle(isa)?;
         }
         if isa.flags().enable_nan_canonicalization() {
             self.canonicalize_nans(isa)?;
@@ -174,6 +191,7 @@ impl Context {
     /// code is returned by `compile` above.
     ///
     /// The machine code is not relocated. Instead, any relocations are emitted into `relocs`.
+    /// The landing pads of `invoke` instructions are reported to `call_sites`.
     ///
     /// # Safety
     ///
@@ -188,9 +206,10 @@ impl Context {
         relocs: &mut dyn RelocSink,
         traps: &mut dyn TrapSink,
         stackmaps: &mut dyn StackmapSink,
+        call_sites: &mut dyn CallSiteSink,
     ) -> CodeInfo {
         let _tt = timing::binemit();
-        let mut sink = MemoryCodeSink::new(mem, relocs, traps, stackmaps);
+        let mut sink = MemoryCodeSink::new(mem, relocs, traps, stackmaps, call_sites);
         isa.emit_function_to_memory(&self.func, &mut sink);
         sink.info
     }
@@ -262,6 +281,23 @@ impl Context {
         Ok(())
     }
 
+    /// Perform sparse conditional constant propagation on the function.
+    ///
+    /// This needs a valid dominator tree, and invalidates it since branches may be folded.
+    pub fn sccp<'a, FOI: Into<FlagsOrIsa<'a>>>(&mut self, fisa: FOI) -> CodegenResult<()> {
+        do_sccp(&mut self.func, &mut self.cfg, &self.domtree);
+        self.domtree.clear();
+        self.verify_if(fisa)
+    }
+
+    /// Eliminate redundant loads and forward stored values to loads on the function.
+    ///
+    /// This needs a valid control flow graph and dominator tree.
+    pub fn rle<'a, FOI: Into<FlagsOrIsa<'a>>>(&mut self, fisa: FOI) -> CodegenResult<()> {
+        do_rle(&mut self.func, &self.cfg, &self.domtree);
+        self.verify_if(fisa)
+    }
+
     /// Perform NaN canonicalizing rewrites on the function.
     pub fn canonicalize_nans(&mut self, isa: &dyn TargetIsa) -> CodegenResult<()> {
         do_nan_canonicalization(&mut self.func);
@@ -326,6 +362,20 @@ impl Context {
         self.verify_if(isa)
     }
 
+    /// Unroll and peel the counted loops of the function, as allowed by the `unroll_threshold`
+    /// setting.
+    pub fn unroll<'a, FOI: Into<FlagsOrIsa<'a>>>(&mut self, fisa: FOI) -> CodegenResult<()> {
+        let fisa = fisa.into();
+        do_unroll(
+            &mut self.func,
+            &mut self.cfg,
+            &mut self.domtree,
+            &mut self.loop_analysis,
+            usize::from(fisa.flags.unroll_threshold()),
+        );
+        self.verify_if(fisa)
+    }
+
     /// Perform unreachable code elimination.
     pub fn eliminate_unreachable_code<'a, FOI>(&mut self, fisa: FOI) -> CodegenResult<()>
     where
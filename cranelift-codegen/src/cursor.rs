@@ -647,6 +647,7 @@ impl<'c, 'f> ir::InstInserterBase<'c> for &'c mut FuncCursor<'f> {
                         let curr_op = self.data_flow_graph()[curr].opcode();
                         if prev_op.is_branch()
                             && !prev_op.is_terminator()
+                            && !prev_op.can_unwind()
                             && !inst_op.is_terminator()
                         {
                             panic!(
@@ -776,6 +777,7 @@ impl<'c, 'f> ir::InstInserterBase<'c> for &'c mut EncCursor<'f> {
                         let inst_op = self.data_flow_graph()[inst].opcode();
                         if prev_op.is_branch()
                             && !prev_op.is_terminator()
+                            && !prev_op.can_unwind()
                             && !inst_op.is_terminator()
                         {
                             panic!(
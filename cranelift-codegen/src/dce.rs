@@ -28,14 +28,14 @@ fn any_inst_results_used(inst: Inst, live: &[bool], dfg: &DataFlowGraph) -> bool
 
 /// Load instructions without the `notrap` flag are defined to trap when
 /// operating on inaccessible memory, so we can't DCE them even if the
-/// loaded value is unused.
+/// loaded value is unused. Volatile loads are kept for their side effects.
 fn is_load_with_defined_trapping(opcode: Opcode, data: &InstructionData) -> bool {
     if !opcode.can_load() {
         return false;
     }
     match *data {
         InstructionData::StackLoad { .. } => false,
-        InstructionData::Load { flags, .. } => !flags.notrap(),
+        InstructionData::Load { flags, .. } => !flags.notrap() || flags.volatile(),
         _ => true,
     }
 }
@@ -22,6 +22,9 @@
 //!
 //! Here `Ebb1` and `Ebb2` would each have a single predecessor denoted as `(Ebb0, brz)`
 //! and `(Ebb0, jmp Ebb2)` respectively.
+//!
+//! An `invoke` also ends a basic block: its landing pad has the predecessor `(Ebb0, invoke)`,
+//! which is the exceptional edge taken when the callee unwinds.
 
 use crate::bforest;
 use crate::entity::SecondaryMap;
@@ -213,7 +216,8 @@ pub type SuccIter<'a> = bforest::SetIter<'a, Ebb>;
 mod tests {
     use super::*;
     use crate::cursor::{Cursor, FuncCursor};
-    use crate::ir::{types, Function, InstBuilder};
+    use crate::ir::{types, ExtFuncData, ExternalName, Function, InstBuilder, Signature};
+    use crate::isa::CallConv;
     use alloc::vec::Vec;
 
     #[test]
@@ -347,4 +351,48 @@ mod tests {
             assert_eq!(ebb2_successors.collect::<Vec<_>>(), []);
         }
     }
+
+    #[test]
+    fn invoke_edges() {
+        let mut func = Function::new();
+        let ebb0 = func.dfg.make_ebb();
+        let ebb1 = func.dfg.make_ebb();
+        let ebb2 = func.dfg.make_ebb();
+
+        let sig = func.import_signature(Signature::new(CallConv::SystemV));
+        let callee = func.import_function(ExtFuncData {
+            name: ExternalName::testcase("callee"),
+            signature: sig,
+            colocated: false,
+        });
+
+        let invoke_ebb0_ebb2;
+        let jmp_ebb0_ebb1;
+
+        {
+            let mut cur = FuncCursor::new(&mut func);
+
+            cur.insert_ebb(ebb0);
+            invoke_ebb0_ebb2 = cur.ins().invoke(callee, ebb2, &[]);
+            jmp_ebb0_ebb1 = cur.ins().jump(ebb1, &[]);
+
+            cur.insert_ebb(ebb1);
+            cur.ins().return_(&[]);
+
+            cur.insert_ebb(ebb2);
+            cur.ins().return_(&[]);
+        }
+
+        let cfg = ControlFlowGraph::with_function(&func);
+
+        assert_eq!(cfg.succ_iter(ebb0).collect::<Vec<_>>(), [ebb1, ebb2]);
+        assert_eq!(
+            cfg.pred_iter(ebb1).collect::<Vec<_>>(),
+            [BasicBlock::new(ebb0, jmp_ebb0_ebb1)]
+        );
+        assert_eq!(
+            cfg.pred_iter(ebb2).collect::<Vec<_>>(),
+            [BasicBlock::new(ebb0, invoke_ebb0_ebb2)]
+        );
+    }
 }
@@ -0,0 +1,114 @@
+//! Memory orderings for atomic instructions.
+
+use core::fmt::{self, Display, Formatter};
+use core::str::FromStr;
+#[cfg(feature = "enable-serde")]
+use serde::{Deserialize, Serialize};
+
+/// The memory ordering constraint imposed by an atomic instruction.
+///
+/// These follow the C++11 memory model. Every atomic memory access and every `fence` carries an
+/// explicit ordering.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+#[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
+pub enum AtomicOrdering {
+    /// The access is atomic, but imposes no ordering on other memory accesses.
+    Relaxed,
+
+    /// No later memory access can be reordered before this one.
+    Acquire,
+
+    /// No earlier memory access can be reordered after this one.
+    Release,
+
+    /// Both `Acquire` and `Release`.
+    AcqRel,
+
+    /// `AcqRel`, and additionally a single total order exists for all `SeqCst` operations.
+    SeqCst,
+}
+
+impl AtomicOrdering {
+    /// Can this ordering be used on an atomic load?
+    pub fn is_valid_for_load(self) -> bool {
+        match self {
+            AtomicOrdering::Release | AtomicOrdering::AcqRel => false,
+            _ => true,
+        }
+    }
+
+    /// Can this ordering be used on an atomic store?
+    pub fn is_valid_for_store(self) -> bool {
+        match self {
+            AtomicOrdering::Acquire | AtomicOrdering::AcqRel => false,
+            _ => true,
+        }
+    }
+
+    /// Can this ordering be used on a `fence`?
+    pub fn is_valid_for_fence(self) -> bool {
+        self != AtomicOrdering::Relaxed
+    }
+}
+
+impl Display for AtomicOrdering {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        use self::AtomicOrdering::*;
+        f.write_str(match *self {
+            Relaxed => "relaxed",
+            Acquire => "acquire",
+            Release => "release",
+            AcqRel => "acq_rel",
+            SeqCst => "seq_cst",
+        })
+    }
+}
+
+impl FromStr for AtomicOrdering {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        use self::AtomicOrdering::*;
+        match s {
+            "relaxed" => Ok(Relaxed),
+            "acquire" => Ok(Acquire),
+            "release" => Ok(Release),
+            "acq_rel" => Ok(AcqRel),
+            "seq_cst" => Ok(SeqCst),
+            _ => Err(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+
+    const ORDERINGS: [AtomicOrdering; 5] = [
+        AtomicOrdering::Relaxed,
+        AtomicOrdering::Acquire,
+        AtomicOrdering::Release,
+        AtomicOrdering::AcqRel,
+        AtomicOrdering::SeqCst,
+    ];
+
+    #[test]
+    fn display() {
+        for r in &ORDERINGS {
+            let o = *r;
+            assert_eq!(o.to_string().parse(), Ok(o));
+        }
+        assert_eq!("bogus".parse::<AtomicOrdering>(), Err(()));
+    }
+
+    #[test]
+    fn validity() {
+        assert!(AtomicOrdering::Acquire.is_valid_for_load());
+        assert!(!AtomicOrdering::Release.is_valid_for_load());
+        assert!(AtomicOrdering::Release.is_valid_for_store());
+        assert!(!AtomicOrdering::AcqRel.is_valid_for_store());
+        assert!(!AtomicOrdering::Relaxed.is_valid_for_fence());
+        assert!(AtomicOrdering::SeqCst.is_valid_for_fence());
+    }
+}
@@ -0,0 +1,102 @@
+//! Operations performed by the `atomic_rmw` instruction.
+
+use core::fmt::{self, Display, Formatter};
+use core::str::FromStr;
+#[cfg(feature = "enable-serde")]
+use serde::{Deserialize, Serialize};
+
+/// The read-modify-write operation applied by an `atomic_rmw` instruction.
+///
+/// The instruction atomically loads the old value from memory, combines it with its operand
+/// using this operation, and stores the result back.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+#[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
+pub enum AtomicRmwOp {
+    /// Wrapping addition.
+    Add,
+    /// Wrapping subtraction.
+    Sub,
+    /// Bitwise and.
+    And,
+    /// Bitwise or.
+    Or,
+    /// Bitwise exclusive or.
+    Xor,
+    /// Replace the old value.
+    Xchg,
+    /// Unsigned minimum.
+    Umin,
+    /// Unsigned maximum.
+    Umax,
+    /// Signed minimum.
+    Smin,
+    /// Signed maximum.
+    Smax,
+}
+
+impl Display for AtomicRmwOp {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        use self::AtomicRmwOp::*;
+        f.write_str(match *self {
+            Add => "add",
+            Sub => "sub",
+            And => "and",
+            Or => "or",
+            Xor => "xor",
+            Xchg => "xchg",
+            Umin => "umin",
+            Umax => "umax",
+            Smin => "smin",
+            Smax => "smax",
+        })
+    }
+}
+
+impl FromStr for AtomicRmwOp {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        use self::AtomicRmwOp::*;
+        match s {
+            "add" => Ok(Add),
+            "sub" => Ok(Sub),
+            "and" => Ok(And),
+            "or" => Ok(Or),
+            "xor" => Ok(Xor),
+            "xchg" => Ok(Xchg),
+            "umin" => Ok(Umin),
+            "umax" => Ok(Umax),
+            "smin" => Ok(Smin),
+            "smax" => Ok(Smax),
+            _ => Err(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+
+    const OPS: [AtomicRmwOp; 10] = [
+        AtomicRmwOp::Add,
+        AtomicRmwOp::Sub,
+        AtomicRmwOp::And,
+        AtomicRmwOp::Or,
+        AtomicRmwOp::Xor,
+        AtomicRmwOp::Xchg,
+        AtomicRmwOp::Umin,
+        AtomicRmwOp::Umax,
+        AtomicRmwOp::Smin,
+        AtomicRmwOp::Smax,
+    ];
+
+    #[test]
+    fn display() {
+        for r in &OPS {
+            let op = *r;
+            assert_eq!(op.to_string().parse(), Ok(op));
+        }
+        assert_eq!("nand".parse::<AtomicRmwOp>(), Err(()));
+    }
+}
@@ -531,7 +531,7 @@ impl DataFlowGraph {
         self.results[inst].clear(&mut self.value_lists);
 
         // Get the call signature if this is a function call.
-        if let Some(sig) = self.call_signature(inst) {
+        if let Some(sig) = self.non_tail_call_signature(inst) {
             // Create result values corresponding to the call return types.
             debug_assert_eq!(
                 self.insts[inst].opcode().constraints().num_fixed_results(),
@@ -690,6 +690,17 @@ impl DataFlowGraph {
         }
     }
 
+    /// Like `call_signature` but returns `None` for tail calls.
+    ///
+    /// Tail calls don't return to the current function, so unlike other calls they don't produce
+    /// the callee's return values as results.
+    pub fn non_tail_call_signature(&self, inst: Inst) -> Option<SigRef> {
+        if self.insts[inst].opcode().is_terminator() {
+            return None;
+        }
+        self.call_signature(inst)
+    }
+
     /// Check if `inst` is a branch.
     pub fn analyze_branch(&self, inst: Inst) -> BranchInfo {
         self.insts[inst].analyze_branch(&self.value_lists)
@@ -716,7 +727,7 @@ impl DataFlowGraph {
         }
 
         // Not a fixed result, try to extract a return type from the call signature.
-        self.call_signature(inst).and_then(|sigref| {
+        self.non_tail_call_signature(inst).and_then(|sigref| {
             self.signatures[sigref]
                 .returns
                 .get(result_idx - num_fixed_results)
@@ -988,7 +999,7 @@ impl DataFlowGraph {
         reuse: &[Value],
     ) -> usize {
         // Get the call signature if this is a function call.
-        if let Some(sig) = self.call_signature(inst) {
+        if let Some(sig) = self.non_tail_call_signature(inst) {
             assert_eq!(
                 self.insts[inst].opcode().constraints().num_fixed_results(),
                 0
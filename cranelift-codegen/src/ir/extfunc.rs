@@ -27,6 +27,13 @@ pub struct Signature {
 
     /// Calling convention.
     pub call_conv: CallConv,
+
+    /// For a variadic function, the index into `params` of the first variadic argument.
+    ///
+    /// The parameters before this index are the fixed parameters of the function, and the
+    /// remaining ones describe the variadic arguments passed by a particular call. Calls that pass
+    /// different variadic arguments need different signatures.
+    pub variadic: Option<usize>,
 }
 
 impl Signature {
@@ -36,6 +43,7 @@ impl Signature {
             params: Vec::new(),
             returns: Vec::new(),
             call_conv,
+            variadic: None,
         }
     }
 
@@ -44,6 +52,12 @@ impl Signature {
         self.params.clear();
         self.returns.clear();
         self.call_conv = call_conv;
+        self.variadic = None;
+    }
+
+    /// Is this the signature of a variadic function?
+    pub fn is_variadic(&self) -> bool {
+        self.variadic.is_some()
     }
 
     /// Return an object that can display `self` with correct register names.
@@ -103,6 +117,11 @@ impl Signature {
         self.uses_special_param(ArgumentPurpose::StructReturn)
     }
 
+    /// Does this signature pass structs by value?
+    pub fn uses_struct_arguments(&self) -> bool {
+        self.params.iter().any(|p| p.purpose.is_struct_argument())
+    }
+
     /// Does this return more than one normal value? (Pre-struct return
     /// legalization)
     pub fn is_multi_return(&self) -> bool {
@@ -113,6 +132,28 @@ impl Signature {
             > 1
     }
 
+    /// Have all the parameters of this signature been assigned locations?
+    ///
+    /// This is the case once the signature has been legalized.
+    pub fn params_assigned(&self) -> bool {
+        self.params.iter().all(|arg| arg.location.is_assigned())
+    }
+
+    /// Get the number of bytes needed for the parameters passed on the stack.
+    ///
+    /// This is only meaningful once the signature has been legalized and its parameters have
+    /// been assigned locations; an unlegalized signature always reports 0.
+    pub fn stack_args_size(&self) -> u32 {
+        self.params
+            .iter()
+            .filter_map(|arg| match arg.location {
+                ArgumentLoc::Stack(offset) => Some(offset as u32 + arg.value_type.bytes()),
+                _ => None,
+            })
+            .max()
+            .unwrap_or(0)
+    }
+
     /// Collect the normal parameter types of the signature; see `[ArgumentPurpose::Normal]`.
     pub fn param_types(&self) -> Vec<Type> {
         self.params
@@ -151,7 +192,22 @@ fn write_list(f: &mut fmt::Formatter, args: &[AbiParam], regs: Option<&RegInfo>)
 impl<'a> fmt::Display for DisplaySignature<'a> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "(")?;
-        write_list(f, &self.0.params, self.1)?;
+        match self.0.variadic {
+            // The variadic arguments follow a `...` marker.
+            Some(start) => {
+                let (fixed, varargs) = self.0.params.split_at(start);
+                write_list(f, fixed, self.1)?;
+                if !fixed.is_empty() {
+                    write!(f, ", ")?;
+                }
+                write!(f, "...")?;
+                if !varargs.is_empty() {
+                    write!(f, ", ")?;
+                    write_list(f, varargs, self.1)?;
+                }
+            }
+            None => write_list(f, &self.0.params, self.1)?,
+        }
         write!(f, ")")?;
         if !self.0.returns.is_empty() {
             write!(f, " -> ")?;
@@ -345,23 +401,62 @@ pub enum ArgumentPurpose {
     /// This is a pointer to a stack limit. It is used to check the current stack pointer
     /// against. Can only appear once in a signature.
     StackLimit,
+
+    /// The number of vector registers used by the arguments of a variadic call.
+    ///
+    /// The System V x86-64 ABI passes this count in `%al` so that the callee knows which vector
+    /// registers to spill in its prologue. It is added by `legalize_signature()`, and the
+    /// legalizer computes its value at each call.
+    VarargVectors,
+
+    /// A struct passed by value.
+    ///
+    /// The argument value is a pointer to the struct, and the callee gets a pointer to its own
+    /// copy. `legalize_signature()` lowers it the way the native C ABI passes a struct with this
+    /// layout, which is either a sequence of `StructArgumentPart` arguments or a pointer to a copy
+    /// made by the caller. ISAs that don't know about struct arguments always do the latter.
+    StructArgument(StructLayout),
+
+    /// A piece of a struct passed by value, at the given byte offset into the struct.
+    ///
+    /// The pieces of a struct appear in order, starting at offset 0. They only appear in
+    /// legalized signatures.
+    StructArgumentPart(u32),
 }
 
-/// Text format names of the `ArgumentPurpose` variants.
-static PURPOSE_NAMES: [&str; 8] = [
-    "normal",
-    "sret",
-    "link",
-    "fp",
-    "csr",
-    "vmctx",
-    "sigid",
-    "stack_limit",
-];
+impl ArgumentPurpose {
+    /// Is this a struct passed by value, or a part of one?
+    pub fn is_struct_argument(self) -> bool {
+        match self {
+            Self::StructArgument(_) | Self::StructArgumentPart(_) => true,
+            Self::Normal
+            | Self::StructReturn
+            | Self::Link
+            | Self::FramePointer
+            | Self::CalleeSaved
+            | Self::VMContext
+            | Self::SignatureId
+            | Self::StackLimit
+            | Self::VarargVectors => false,
+        }
+    }
+}
 
 impl fmt::Display for ArgumentPurpose {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        f.write_str(PURPOSE_NAMES[*self as usize])
+        f.write_str(match *self {
+            Self::Normal => "normal",
+            Self::StructReturn => "sret",
+            Self::Link => "link",
+            Self::FramePointer => "fp",
+            Self::CalleeSaved => "csr",
+            Self::VMContext => "vmctx",
+            Self::SignatureId => "sigid",
+            Self::StackLimit => "stack_limit",
+            Self::VarargVectors => "vararg_vectors",
+            Self::StructArgument(layout) => return write!(f, "sarg({})", layout),
+            Self::StructArgumentPart(offset) => return write!(f, "sarg_part({})", offset),
+        })
     }
 }
 
@@ -377,11 +472,92 @@ impl FromStr for ArgumentPurpose {
             "vmctx" => Ok(Self::VMContext),
             "sigid" => Ok(Self::SignatureId),
             "stack_limit" => Ok(Self::StackLimit),
+            "vararg_vectors" => Ok(Self::VarargVectors),
+            _ => Err(()),
+        }
+    }
+}
+
+/// The class of an eightbyte of a struct passed by value.
+///
+/// This follows the classification of the System V x86-64 ABI, where each eightbyte of a small
+/// struct is passed in a general-purpose or a vector register depending on the fields it contains.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum StructClass {
+    /// The eightbyte contains integer or pointer fields, possibly mixed with floating point fields.
+    Integer,
+    /// The eightbyte only contains `f32` or `f64` fields.
+    Float,
+    /// The eightbyte contains unaligned fields, so the whole struct must be passed in memory.
+    Memory,
+}
+
+impl fmt::Display for StructClass {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            Self::Integer => "int",
+            Self::Float => "float",
+            Self::Memory => "mem",
+        })
+    }
+}
+
+impl FromStr for StructClass {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, ()> {
+        match s {
+            "int" => Ok(Self::Integer),
+            "float" => Ok(Self::Float),
+            "mem" => Ok(Self::Memory),
             _ => Err(()),
         }
     }
 }
 
+/// The layout of a struct passed by value, as far as the native C ABI is concerned.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct StructLayout {
+    /// Size of the struct in bytes.
+    pub size: u32,
+    /// Alignment of the struct in bytes.
+    pub align: u32,
+    /// Classes of the eightbytes of the struct.
+    ///
+    /// Only the first `num_classes()` entries are meaningful. Larger structs are never passed in
+    /// registers, so they don't need classifying.
+    pub classes: [StructClass; 2],
+}
+
+impl StructLayout {
+    /// Create a layout for a struct of integer fields.
+    pub fn new(size: u32, align: u32) -> Self {
+        Self {
+            size,
+            align,
+            classes: [StructClass::Integer; 2],
+        }
+    }
+
+    /// Get the number of eightbytes that have a class.
+    pub fn num_classes(&self) -> usize {
+        match self.size {
+            1..=8 => 1,
+            9..=16 => 2,
+            _ => 0,
+        }
+    }
+}
+
+impl fmt::Display for StructLayout {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}, {}", self.size, self.align)?;
+        for class in &self.classes[..self.num_classes()] {
+            write!(f, ", {}", class)?;
+        }
+        Ok(())
+    }
+}
+
 /// An external function.
 ///
 /// Information about a function that can be called directly with a direct `call` instruction.
@@ -426,27 +602,44 @@ mod tests {
     #[test]
     fn argument_purpose() {
         let all_purpose = [
-            ArgumentPurpose::Normal,
-            ArgumentPurpose::StructReturn,
-            ArgumentPurpose::Link,
-            ArgumentPurpose::FramePointer,
-            ArgumentPurpose::CalleeSaved,
-            ArgumentPurpose::VMContext,
-            ArgumentPurpose::SignatureId,
-            ArgumentPurpose::StackLimit,
+            (ArgumentPurpose::Normal, "normal"),
+            (ArgumentPurpose::StructReturn, "sret"),
+            (ArgumentPurpose::Link, "link"),
+            (ArgumentPurpose::FramePointer, "fp"),
+            (ArgumentPurpose::CalleeSaved, "csr"),
+            (ArgumentPurpose::VMContext, "vmctx"),
+            (ArgumentPurpose::SignatureId, "sigid"),
+            (ArgumentPurpose::StackLimit, "stack_limit"),
+            (ArgumentPurpose::VarargVectors, "vararg_vectors"),
         ];
-        for (&e, &n) in all_purpose.iter().zip(PURPOSE_NAMES.iter()) {
+        for &(e, n) in all_purpose.iter() {
             assert_eq!(e.to_string(), n);
             assert_eq!(Ok(e), n.parse());
         }
     }
 
+    #[test]
+    fn struct_arguments() {
+        let mut layout = StructLayout::new(12, 4);
+        layout.classes[1] = StructClass::Float;
+        let t = AbiParam::special(I32, ArgumentPurpose::StructArgument(layout));
+        assert_eq!(t.to_string(), "i32 sarg(12, 4, int, float)");
+        let t = AbiParam::special(
+            I32,
+            ArgumentPurpose::StructArgument(StructLayout::new(24, 8)),
+        );
+        assert_eq!(t.to_string(), "i32 sarg(24, 8)");
+        let t = AbiParam::special(F32, ArgumentPurpose::StructArgumentPart(8));
+        assert_eq!(t.to_string(), "f32 sarg_part(8)");
+    }
+
     #[test]
     fn call_conv() {
         for &cc in &[
             CallConv::Fast,
             CallConv::Cold,
             CallConv::SystemV,
+            CallConv::Tail,
             CallConv::WindowsFastcall,
             CallConv::BaldrdashSystemV,
             CallConv::BaldrdashWindows,
@@ -471,9 +664,12 @@ mod tests {
             "(i32, i32x4) -> f32, b8 baldrdash_system_v"
         );
 
+        assert_eq!(sig.stack_args_size(), 0);
+
         // Order does not matter.
         sig.params[0].location = ArgumentLoc::Stack(24);
         sig.params[1].location = ArgumentLoc::Stack(8);
+        assert_eq!(sig.stack_args_size(), 28);
 
         // Writing ABI-annotated signatures.
         assert_eq!(
@@ -481,4 +677,17 @@ mod tests {
             "(i32 [24], i32x4 [8]) -> f32, b8 baldrdash_system_v"
         );
     }
+    #[test]
+    fn variadic_signatures() {
+        let mut sig = Signature::new(CallConv::SystemV);
+        sig.variadic = Some(0);
+        assert_eq!(sig.to_string(), "(...) system_v");
+        sig.params.push(AbiParam::new(I32));
+        assert_eq!(sig.to_string(), "(..., i32) system_v");
+        sig.variadic = Some(1);
+        assert_eq!(sig.to_string(), "(i32, ...) system_v");
+        sig.params.push(AbiParam::new(F32));
+        sig.params.push(AbiParam::new(I32));
+        assert_eq!(sig.to_string(), "(i32, ..., f32, i32) system_v");
+    }
 }
@@ -273,8 +273,12 @@ impl Function {
         let dfg = &self.dfg;
         let inst_iter = self.layout.ebb_insts(ebb);
 
-        // Ignore all instructions prior to the first branch.
-        let mut inst_iter = inst_iter.skip_while(|&inst| !dfg[inst].opcode().is_branch());
+        // Ignore all instructions prior to the first branch. Invokes continue with the next
+        // instruction when the callee returns normally, so they don't end a basic block.
+        let mut inst_iter = inst_iter.skip_while(|&inst| {
+            let opcode = dfg[inst].opcode();
+            !opcode.is_branch() || opcode.can_unwind()
+        });
 
         // A conditional branch is permitted in a basic block only when followed
         // by a terminal jump or fallthrough instruction.
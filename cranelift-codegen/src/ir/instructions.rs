@@ -193,6 +193,11 @@ impl InstructionData {
                 ref args,
                 ..
             } => BranchInfo::SingleDest(destination, &args.as_slice(pool)[2..]),
+            // The landing pad of an invoke doesn't take any arguments; all of the values are
+            // call arguments.
+            Self::Invoke { destination, .. } | Self::InvokeIndirect { destination, .. } => {
+                BranchInfo::SingleDest(destination, &[])
+            }
             Self::BranchTable {
                 table, destination, ..
             } => BranchInfo::Table(table, Some(destination)),
@@ -214,7 +219,9 @@ impl InstructionData {
             | Self::Branch { destination, .. }
             | Self::BranchInt { destination, .. }
             | Self::BranchFloat { destination, .. }
-            | Self::BranchIcmp { destination, .. } => Some(destination),
+            | Self::BranchIcmp { destination, .. }
+            | Self::Invoke { destination, .. }
+            | Self::InvokeIndirect { destination, .. } => Some(destination),
             Self::BranchTable { .. } | Self::IndirectJump { .. } => None,
             _ => {
                 debug_assert!(!self.opcode().is_branch());
@@ -248,6 +255,14 @@ impl InstructionData {
             | Self::BranchIcmp {
                 ref mut destination,
                 ..
+            }
+            | Self::Invoke {
+                ref mut destination,
+                ..
+            }
+            | Self::InvokeIndirect {
+                ref mut destination,
+                ..
             } => Some(destination),
             Self::BranchTable { .. } => None,
             _ => {
@@ -257,6 +272,23 @@ impl InstructionData {
         }
     }
 
+    /// Get the memory flags of a load or store instruction.
+    ///
+    /// Returns `None` for instructions that don't carry memory flags.
+    pub fn memflags(&self) -> Option<ir::MemFlags> {
+        match *self {
+            Self::Load { flags, .. }
+            | Self::LoadComplex { flags, .. }
+            | Self::Store { flags, .. }
+            | Self::StoreComplex { flags, .. }
+            | Self::AtomicLoad { flags, .. }
+            | Self::AtomicStore { flags, .. }
+            | Self::AtomicRmw { flags, .. }
+            | Self::AtomicCas { flags, .. } => Some(flags),
+            _ => None,
+        }
+    }
+
     /// Return information about a call instruction.
     ///
     /// Any instruction that can call another function reveals its call signature here.
@@ -264,9 +296,15 @@ impl InstructionData {
         match *self {
             Self::Call {
                 func_ref, ref args, ..
+            }
+            | Self::Invoke {
+                func_ref, ref args, ..
             } => CallInfo::Direct(func_ref, args.as_slice(pool)),
             Self::CallIndirect {
                 sig_ref, ref args, ..
+            }
+            | Self::InvokeIndirect {
+                sig_ref, ref args, ..
             } => CallInfo::Indirect(sig_ref, &args.as_slice(pool)[1..]),
             _ => {
                 debug_assert!(!self.opcode().is_call());
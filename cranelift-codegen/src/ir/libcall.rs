@@ -22,7 +22,8 @@ use serde::{Deserialize, Serialize};
 #[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
 pub enum LibCall {
     /// probe for stack overflow. These are emitted for functions which need
-    /// when the `probestack_enabled` setting is true.
+    /// when the `probestack_enabled` setting is true, unless the
+    /// `probestack_strategy` setting is `inline`.
     Probestack,
     /// ceil.f32
     CeilF32,
@@ -40,12 +41,62 @@ pub enum LibCall {
     NearestF32,
     /// nearest.f64
     NearestF64,
+    /// udiv.i32
+    UdivI32,
+    /// sdiv.i32
+    SdivI32,
+    /// urem.i32
+    UremI32,
+    /// srem.i32
+    SremI32,
+    /// udiv.i64
+    UdivI64,
+    /// sdiv.i64
+    SdivI64,
+    /// urem.i64
+    UremI64,
+    /// srem.i64
+    SremI64,
+    /// udiv.i128
+    UdivI128,
+    /// sdiv.i128
+    SdivI128,
+    /// urem.i128
+    UremI128,
+    /// srem.i128
+    SremI128,
+    /// fcvt_from_sint.f32 of an i64
+    FcvtFromSintI64F32,
+    /// fcvt_from_sint.f64 of an i64
+    FcvtFromSintI64F64,
+    /// fcvt_from_uint.f32 of an i64
+    FcvtFromUintI64F32,
+    /// fcvt_from_uint.f64 of an i64
+    FcvtFromUintI64F64,
+    /// fcvt_to_sint.i64 of an f32, without the trapping checks
+    FcvtToSintF32I64,
+    /// fcvt_to_sint.i64 of an f64, without the trapping checks
+    FcvtToSintF64I64,
+    /// fcvt_to_uint.i64 of an f32, without the trapping checks
+    FcvtToUintF32I64,
+    /// fcvt_to_uint.i64 of an f64, without the trapping checks
+    FcvtToUintF64I64,
     /// libc.memcpy
     Memcpy,
     /// libc.memset
     Memset,
     /// libc.memmove
     Memmove,
+
+    /// Elf __tls_get_addr
+    ElfTlsGetAddr,
+    /// Elf _GLOBAL_OFFSET_TABLE_, the symbol of 32-bit x86 GOT base relocations
+    ElfGlobalOffsetTable,
+
+    /// x86-64 retpoline thunk, branching to the address in %r11
+    RetpolineThunkR11,
+    /// 32-bit x86 retpoline thunk, branching to the address in %eax
+    RetpolineThunkEax,
 }
 
 impl fmt::Display for LibCall {
@@ -68,9 +119,35 @@ impl FromStr for LibCall {
             "TruncF64" => Ok(Self::TruncF64),
             "NearestF32" => Ok(Self::NearestF32),
             "NearestF64" => Ok(Self::NearestF64),
+            "UdivI32" => Ok(Self::UdivI32),
+            "SdivI32" => Ok(Self::SdivI32),
+            "UremI32" => Ok(Self::UremI32),
+            "SremI32" => Ok(Self::SremI32),
+            "UdivI64" => Ok(Self::UdivI64),
+            "SdivI64" => Ok(Self::SdivI64),
+            "UremI64" => Ok(Self::UremI64),
+            "SremI64" => Ok(Self::SremI64),
+            "UdivI128" => Ok(Self::UdivI128),
+            "SdivI128" => Ok(Self::SdivI128),
+            "UremI128" => Ok(Self::UremI128),
+            "SremI128" => Ok(Self::SremI128),
+            "FcvtFromSintI64F32" => Ok(Self::FcvtFromSintI64F32),
+            "FcvtFromSintI64F64" => Ok(Self::FcvtFromSintI64F64),
+            "FcvtFromUintI64F32" => Ok(Self::FcvtFromUintI64F32),
+            "FcvtFromUintI64F64" => Ok(Self::FcvtFromUintI64F64),
+            "FcvtToSintF32I64" => Ok(Self::FcvtToSintF32I64),
+            "FcvtToSintF64I64" => Ok(Self::FcvtToSintF64I64),
+            "FcvtToUintF32I64" => Ok(Self::FcvtToUintF32I64),
+            "FcvtToUintF64I64" => Ok(Self::FcvtToUintF64I64),
             "Memcpy" => Ok(Self::Memcpy),
             "Memset" => Ok(Self::Memset),
             "Memmove" => Ok(Self::Memmove),
+
+            "ElfTlsGetAddr" => Ok(Self::ElfTlsGetAddr),
+            "ElfGlobalOffsetTable" => Ok(Self::ElfGlobalOffsetTable),
+
+            "RetpolineThunkR11" => Ok(Self::RetpolineThunkR11),
+            "RetpolineThunkEax" => Ok(Self::RetpolineThunkEax),
             _ => Err(()),
         }
     }
@@ -97,6 +174,41 @@ impl LibCall {
                 Opcode::Nearest => Self::NearestF64,
                 _ => return None,
             },
+            types::I32 => match opcode {
+                Opcode::Udiv => Self::UdivI32,
+                Opcode::Sdiv => Self::SdivI32,
+                Opcode::Urem => Self::UremI32,
+                Opcode::Srem => Self::SremI32,
+                _ => return None,
+            },
+            types::I64 => match opcode {
+                Opcode::Udiv => Self::UdivI64,
+                Opcode::Sdiv => Self::SdivI64,
+                Opcode::Urem => Self::UremI64,
+                Opcode::Srem => Self::SremI64,
+                _ => return None,
+            },
+            types::I128 => match opcode {
+                Opcode::Udiv => Self::UdivI128,
+                Opcode::Sdiv => Self::SdivI128,
+                Opcode::Urem => Self::UremI128,
+                Opcode::Srem => Self::SremI128,
+                _ => return None,
+            },
+            _ => return None,
+        })
+    }
+
+    /// Get the well-known library call name to use as a replacement for a conversion instruction
+    /// with the given opcode, argument type and result type.
+    ///
+    /// Returns `None` if no well-known library routine name exists for that conversion.
+    pub fn for_conversion(opcode: Opcode, from: Type, to: Type) -> Option<Self> {
+        Some(match (opcode, from, to) {
+            (Opcode::FcvtFromSint, types::I64, types::F32) => Self::FcvtFromSintI64F32,
+            (Opcode::FcvtFromSint, types::I64, types::F64) => Self::FcvtFromSintI64F64,
+            (Opcode::FcvtFromUint, types::I64, types::F32) => Self::FcvtFromUintI64F32,
+            (Opcode::FcvtFromUint, types::I64, types::F64) => Self::FcvtFromUintI64F64,
             _ => return None,
         })
     }
@@ -220,5 +332,7 @@ mod tests {
     #[test]
     fn parsing() {
         assert_eq!("FloorF32".parse(), Ok(LibCall::FloorF32));
+        assert_eq!("UdivI128".parse(), Ok(LibCall::UdivI128));
+        assert_eq!("FcvtToSintF64I64".parse(), Ok(LibCall::FcvtToSintF64I64));
     }
 }
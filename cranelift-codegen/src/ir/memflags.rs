@@ -6,9 +6,43 @@ enum FlagBit {
     Notrap,
     Aligned,
     Readonly,
+    Volatile,
+    Heap,
+    Table,
+    Vmctx,
 }
 
-const NAMES: [&str; 3] = ["notrap", "aligned", "readonly"];
+const NAMES: [&str; 7] = [
+    "notrap", "aligned", "readonly", "volatile", "heap", "table", "vmctx",
+];
+
+/// The flag bits that encode an alias region. At most one of them is set.
+const ALIAS_REGION_MASK: u8 =
+    1 << FlagBit::Heap as usize | 1 << FlagBit::Table as usize | 1 << FlagBit::Vmctx as usize;
+
+/// A disjoint region of memory that a memory operation is known to access.
+///
+/// Two memory operations tagged with different alias regions never access the same memory, which
+/// lets passes reason about them independently. An operation without a region may alias anything.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub enum AliasRegion {
+    /// A WebAssembly-style linear memory heap.
+    Heap,
+    /// A table of function references or other opaque values.
+    Table,
+    /// The VM context and the runtime data structures reachable from it.
+    Vmctx,
+}
+
+impl AliasRegion {
+    fn bit(self) -> FlagBit {
+        match self {
+            Self::Heap => FlagBit::Heap,
+            Self::Table => FlagBit::Table,
+            Self::Vmctx => FlagBit::Vmctx,
+        }
+    }
+}
 
 /// Flags for memory operations like load/store.
 ///
@@ -47,11 +81,16 @@ impl MemFlags {
 
     /// Set a flag bit by name.
     ///
-    /// Returns true if the flag was found and set, false for an unknown flag name.
+    /// Returns true if the flag was found and set, false for an unknown flag name or for an alias
+    /// region when a different region is already set.
     pub fn set_by_name(&mut self, name: &str) -> bool {
         match NAMES.iter().position(|&s| s == name) {
             Some(bit) => {
-                self.bits |= 1 << bit;
+                let mask = 1 << bit;
+                if mask & ALIAS_REGION_MASK != 0 && self.bits & ALIAS_REGION_MASK & !mask != 0 {
+                    return false;
+                }
+                self.bits |= mask;
                 true
             }
             None => false,
@@ -103,6 +142,43 @@ impl MemFlags {
     pub fn set_readonly(&mut self) {
         self.set(FlagBit::Readonly)
     }
+
+    /// Test if the `volatile` flag is set.
+    ///
+    /// A volatile access has side effects that Cranelift can't see, as with memory-mapped I/O.
+    /// Volatile loads and stores are never removed, merged, hoisted or reordered with respect to
+    /// each other, even when other flags like `readonly` or `notrap` would otherwise allow it.
+    pub fn volatile(self) -> bool {
+        self.read(FlagBit::Volatile)
+    }
+
+    /// Set the `volatile` flag.
+    pub fn set_volatile(&mut self) {
+        self.set(FlagBit::Volatile)
+    }
+
+    /// Get the alias region of the access, if any.
+    ///
+    /// It is undefined behavior for an access tagged with a region to touch memory outside it.
+    pub fn alias_region(self) -> Option<AliasRegion> {
+        if self.read(FlagBit::Heap) {
+            Some(AliasRegion::Heap)
+        } else if self.read(FlagBit::Table) {
+            Some(AliasRegion::Table)
+        } else if self.read(FlagBit::Vmctx) {
+            Some(AliasRegion::Vmctx)
+        } else {
+            None
+        }
+    }
+
+    /// Set the alias region of the access, replacing any previous one.
+    pub fn set_alias_region(&mut self, region: Option<AliasRegion>) {
+        self.bits &= !ALIAS_REGION_MASK;
+        if let Some(region) = region {
+            self.set(region.bit());
+        }
+    }
 }
 
 impl fmt::Display for MemFlags {
@@ -115,3 +191,35 @@ impl fmt::Display for MemFlags {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+
+    #[test]
+    fn alias_region() {
+        let mut flags = MemFlags::new();
+        assert_eq!(flags.alias_region(), None);
+        flags.set_alias_region(Some(AliasRegion::Table));
+        assert_eq!(flags.alias_region(), Some(AliasRegion::Table));
+        flags.set_alias_region(Some(AliasRegion::Vmctx));
+        assert_eq!(flags.alias_region(), Some(AliasRegion::Vmctx));
+        assert_eq!(flags.to_string(), " vmctx");
+        flags.set_alias_region(None);
+        assert_eq!(flags.alias_region(), None);
+    }
+
+    #[test]
+    fn set_by_name() {
+        let mut flags = MemFlags::new();
+        assert!(flags.set_by_name("volatile"));
+        assert!(flags.set_by_name("heap"));
+        assert!(flags.set_by_name("heap"));
+        assert!(!flags.set_by_name("table"));
+        assert!(!flags.set_by_name("bogus"));
+        assert!(flags.volatile());
+        assert_eq!(flags.alias_region(), Some(AliasRegion::Heap));
+        assert_eq!(flags.to_string(), " volatile heap");
+    }
+}
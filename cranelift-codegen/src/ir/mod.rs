@@ -1,5 +1,7 @@
 //! Representation of Cranelift IR functions.
 
+mod atomicordering;
+mod atomicrmwop;
 mod builder;
 pub mod constant;
 pub mod dfg;
@@ -27,6 +29,8 @@ mod valueloc;
 #[cfg(feature = "enable-serde")]
 use serde::{Deserialize, Serialize};
 
+pub use crate::ir::atomicordering::AtomicOrdering;
+pub use crate::ir::atomicrmwop::AtomicRmwOp;
 pub use crate::ir::builder::{
     InsertBuilder, InstBuilder, InstBuilderBase, InstInserterBase, ReplaceBuilder,
 };
@@ -37,7 +41,7 @@ pub use crate::ir::entities::{
     Table, Value,
 };
 pub use crate::ir::extfunc::{
-    AbiParam, ArgumentExtension, ArgumentPurpose, ExtFuncData, Signature,
+    AbiParam, ArgumentExtension, ArgumentPurpose, ExtFuncData, Signature, StructClass, StructLayout,
 };
 pub use crate::ir::extname::ExternalName;
 pub use crate::ir::framelayout::{FrameLayout, FrameLayoutChange, FrameLayoutChanges};
@@ -50,7 +54,7 @@ pub use crate::ir::instructions::{
 pub use crate::ir::jumptable::JumpTableData;
 pub use crate::ir::layout::Layout;
 pub use crate::ir::libcall::{get_libcall_funcref, get_probestack_funcref, LibCall};
-pub use crate::ir::memflags::MemFlags;
+pub use crate::ir::memflags::{AliasRegion, MemFlags};
 pub use crate::ir::progpoint::{ExpandedProgramPoint, ProgramOrder, ProgramPoint};
 pub use crate::ir::sourceloc::SourceLoc;
 pub use crate::ir::stackslot::{StackSlotData, StackSlotKind, StackSlots};
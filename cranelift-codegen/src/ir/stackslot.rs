@@ -185,6 +185,14 @@ pub struct StackSlots {
     ///
     /// This is computed by the `layout()` method.
     pub frame_size: Option<StackSize>,
+
+    /// The offset of the frame pointer from the stack pointer in the calling function, when stack
+    /// slots must be addressed relative to the frame pointer.
+    ///
+    /// The ABI code sets this for functions whose stack pointer moves after the prologue, like
+    /// those containing `dynamic_stack_alloc`. Outgoing arguments are still addressed relative to
+    /// the stack pointer.
+    pub frame_pointer_offset: Option<StackOffset>,
 }
 
 /// Stack slot manager functions that behave mostly like an entity map.
@@ -196,6 +204,7 @@ impl StackSlots {
             outgoing: Vec::new(),
             emergency: Vec::new(),
             frame_size: None,
+            frame_pointer_offset: None,
         }
     }
 
@@ -205,6 +214,7 @@ impl StackSlots {
         self.outgoing.clear();
         self.emergency.clear();
         self.frame_size = None;
+        self.frame_pointer_offset = None;
     }
 
     /// Allocate a new stack slot.
@@ -1,96 +1,252 @@
-//! ARM ABI implementation.
-//! This is from the RISC-V target and will need to be updated for ARM32.
+//! ARM 32-bit ABI implementation.
+//!
+//! This follows the procedure call standard for the ARM architecture (AAPCS), with the base
+//! variant passing floating point values in the general purpose registers and the hard-float
+//! variant of the `*hf` targets passing them in the VFP registers.
 
-use super::registers::{D, GPR, Q, S};
-use crate::abi::{legalize_args, ArgAction, ArgAssigner, ValueConversion};
-use crate::ir::{self, AbiParam, ArgumentExtension, ArgumentLoc, Type};
-use crate::isa::RegClass;
+use super::registers::{D, GPR, Q, RU, S};
+use super::settings;
+use crate::abi::{legalize_args, legalize_params, ArgAction, ArgAssigner, ValueConversion};
+use crate::cursor::{Cursor, CursorPosition, EncCursor};
+use crate::ir;
+use crate::ir::immediates::Imm64;
+use crate::ir::stackslot::StackOffset;
+use crate::ir::{
+    AbiParam, ArgumentExtension, ArgumentLoc, ArgumentPurpose, InstBuilder, StructLayout, Type,
+    ValueLoc,
+};
+use crate::isa::{CallConv, RegClass, RegUnit, TargetIsa};
 use crate::regalloc::RegisterSet;
+use crate::result::{CodegenError, CodegenResult};
+use crate::stack_layout::layout_stack;
 use alloc::borrow::Cow;
+use alloc::string::ToString;
+use alloc::vec::Vec;
 use core::i32;
-use target_lexicon::Triple;
+use target_lexicon::{Architecture, Environment, Triple};
 
+/// Number of general purpose registers used for arguments and return values.
+const ARG_GPRS: usize = 4;
+
+/// Number of single precision registers used for arguments and return values by the hard-float
+/// variant.
+const ARG_SREGS: usize = 16;
+
+/// The stack pointer is always 8-byte aligned at public interfaces.
+const STACK_ALIGNMENT: u32 = 8;
+
+/// Size of the frame record: the frame pointer and the link register.
+const FRAME_RECORD_SIZE: u32 = 8;
+
+/// Does `triple` use the hard-float variant of the procedure call standard?
+fn is_hard_float(triple: &Triple) -> bool {
+    match triple.environment {
+        Environment::Eabihf | Environment::Gnueabihf | Environment::Musleabihf => true,
+        _ => false,
+    }
+}
+
+/// Does `triple` select Thumb-2 code?
+pub fn is_thumb(triple: &Triple) -> bool {
+    match triple.architecture {
+        Architecture::Arm(arm) => arm.is_thumb(),
+        _ => false,
+    }
+}
+
+/// The frame pointer: `r7` in Thumb-2 code, where it is one of the low registers that 16-bit
+/// instructions can access, and `r11` otherwise.
+fn frame_pointer(thumb: bool) -> RU {
+    if thumb {
+        RU::r7
+    } else {
+        RU::r11
+    }
+}
+
+#[derive(Clone)]
 struct Args {
-    pointer_bits: u8,
-    pointer_bytes: u8,
-    pointer_type: Type,
-    regs: u32,
-    reg_limit: u32,
+    gpr_used: usize,
+    /// The single precision registers used by the hard-float variant, which back-fills the
+    /// registers skipped when aligning doubles.
+    sregs_used: u16,
+    /// Set once a floating point argument has gone on the stack. No more floating point
+    /// arguments are passed in registers after that.
+    sregs_exhausted: bool,
     offset: u32,
+    hard_float: bool,
 }
 
 impl Args {
-    fn new(bits: u8) -> Self {
+    fn new(hard_float: bool) -> Self {
         Self {
-            pointer_bits: bits,
-            pointer_bytes: bits / 8,
-            pointer_type: Type::int(u16::from(bits)).unwrap(),
-            regs: 0,
-            reg_limit: 8,
+            gpr_used: 0,
+            sregs_used: 0,
+            sregs_exhausted: !hard_float,
             offset: 0,
+            hard_float,
+        }
+    }
+
+    /// Allocate `size` bytes of the stack argument area with `align` alignment.
+    fn stack_slot(&mut self, size: u32, align: u32) -> ArgumentLoc {
+        self.offset = (self.offset + align - 1) & !(align - 1);
+        let loc = ArgumentLoc::Stack(self.offset as i32);
+        self.offset += size;
+        debug_assert!(self.offset <= i32::MAX as u32);
+        loc
+    }
+
+    /// Find the first free single precision register, or even pair of them for a double.
+    fn vfp_reg(&mut self, ty: Type) -> Option<RegUnit> {
+        if self.sregs_exhausted {
+            return None;
+        }
+        let count = if ty == ir::types::F64 { 2 } else { 1 };
+        let mask = (1u16 << count) - 1;
+        match (0..ARG_SREGS)
+            .step_by(count)
+            .find(|&reg| self.sregs_used & (mask << reg) == 0)
+        {
+            Some(reg) => {
+                self.sregs_used |= mask << reg;
+                Some(if count == 2 {
+                    D.unit(reg / 2)
+                } else {
+                    S.unit(reg)
+                })
+            }
+            None => {
+                self.sregs_exhausted = true;
+                None
+            }
         }
     }
+
+    /// Get the parts that a struct argument with `layout` is passed in.
+    ///
+    /// Structs are always passed by value, in words that go in the general purpose registers
+    /// while some are left and on the stack after that. A struct is only split between registers
+    /// and the stack if nothing was passed on the stack yet. Homogeneous floating point
+    /// aggregates would go in floating point registers with the hard-float variant, but the
+    /// layout doesn't describe the individual members, so they are passed like any other struct.
+    fn struct_parts(&mut self, layout: &StructLayout) -> Vec<AbiParam> {
+        let words = (layout.size + 3) / 4;
+        if layout.align >= 8 {
+            self.gpr_used = (self.gpr_used + 1) & !1;
+            self.offset = (self.offset + 7) & !7;
+        }
+        if self.gpr_used + words as usize > ARG_GPRS && self.offset != 0 {
+            self.gpr_used = ARG_GPRS;
+        }
+        (0..words)
+            .map(|word| {
+                AbiParam::special(
+                    ir::types::I32,
+                    ArgumentPurpose::StructArgumentPart(word * 4),
+                )
+            })
+            .collect()
+    }
 }
 
 impl ArgAssigner for Args {
     fn assign(&mut self, arg: &AbiParam) -> ArgAction {
-        fn align(value: u32, to: u32) -> u32 {
-            (value + to - 1) & !(to - 1)
-        }
-
         let ty = arg.value_type;
 
-        // Check for a legal type.
-        // SIMD instructions are currently no implemented, so break down vectors
+        if let ArgumentPurpose::StructArgument(layout) = arg.purpose {
+            return ArgAction::Expand(self.struct_parts(&layout));
+        }
+
+        // There are no vector registers without NEON.
         if ty.is_vector() {
             return ValueConversion::VectorSplit.into();
         }
 
-        // Large integers and booleans are broken down to fit in a register.
-        if !ty.is_float() && ty.bits() > u16::from(self.pointer_bits) {
-            // Align registers and stack to a multiple of two pointers.
-            self.regs = align(self.regs, 2);
-            self.offset = align(self.offset, 2 * u32::from(self.pointer_bytes));
+        if ty.is_float() {
+            if let Some(reg) = self.vfp_reg(ty) {
+                return ArgumentLoc::Reg(reg).into();
+            }
+            if !self.hard_float {
+                // The base variant passes floating point values like integers of the same size.
+                return ValueConversion::IntBits.into();
+            }
+            let size = ty.bytes();
+            return self.stack_slot(size, size).into();
+        }
+
+        // Doublewords go in an even register pair, or at an 8-byte aligned stack offset.
+        if ty.bits() > 32 {
+            self.gpr_used = (self.gpr_used + 1) & !1;
+            self.offset = (self.offset + 7) & !7;
             return ValueConversion::IntSplit.into();
         }
 
-        // Small integers are extended to the size of a pointer register.
-        if ty.is_int() && ty.bits() < u16::from(self.pointer_bits) {
+        // Small integers are extended to the size of a register.
+        if ty.is_int() && ty.bits() < 32 {
             match arg.extension {
                 ArgumentExtension::None => {}
-                ArgumentExtension::Uext => return ValueConversion::Uext(self.pointer_type).into(),
-                ArgumentExtension::Sext => return ValueConversion::Sext(self.pointer_type).into(),
+                ArgumentExtension::Uext => return ValueConversion::Uext(ir::types::I32).into(),
+                ArgumentExtension::Sext => return ValueConversion::Sext(ir::types::I32).into(),
             }
         }
 
-        if self.regs < self.reg_limit {
-            // Assign to a register.
-            let reg = GPR.unit(10 + self.regs as usize);
-            self.regs += 1;
-            ArgumentLoc::Reg(reg).into()
-        } else {
-            // Assign a stack location.
-            let loc = ArgumentLoc::Stack(self.offset as i32);
-            self.offset += u32::from(self.pointer_bytes);
-            debug_assert!(self.offset <= i32::MAX as u32);
-            loc.into()
+        if self.gpr_used < ARG_GPRS {
+            let reg = GPR.unit(self.gpr_used);
+            self.gpr_used += 1;
+            return ArgumentLoc::Reg(reg).into();
         }
+
+        self.stack_slot(4, 4).into()
     }
 }
 
 /// Legalize `sig`.
 pub fn legalize_signature(sig: &mut Cow<ir::Signature>, triple: &Triple, _current: bool) {
-    let bits = triple.pointer_width().unwrap().bits();
+    // Variadic functions always use the base variant.
+    let hard_float = is_hard_float(triple) && !sig.is_variadic();
+    let mut args = Args::new(hard_float);
+    let mut rets = Args::new(hard_float);
 
-    let mut args = Args::new(bits);
-    if let Some(new_params) = legalize_args(&sig.params, &mut args) {
-        sig.to_mut().params = new_params;
+    // Keep a copy of the return assigner, in case the return values have to be returned in
+    // memory instead.
+    let backup_rets = rets.clone();
+
+    if let Some(new_returns) = legalize_args(&sig.returns, &mut rets) {
+        if new_returns
+            .iter()
+            .filter(|r| r.purpose == ArgumentPurpose::Normal)
+            .any(|r| !r.location.is_reg())
+        {
+            // The caller passes the address of the memory for the return values as a hidden
+            // first argument in `r0`, and we return the same address in `r0`.
+            debug_assert!(!sig.uses_struct_return_param());
+            let mut ret_ptr = AbiParam::special(ir::types::I32, ArgumentPurpose::StructReturn);
+            match args.assign(&ret_ptr) {
+                ArgAction::Assign(loc @ ArgumentLoc::Reg(_)) => ret_ptr.location = loc,
+                _ => unreachable!("return pointer should always get a register assignment"),
+            }
+            sig.to_mut().params.push(ret_ptr);
+
+            let mut rets = backup_rets;
+            match rets.assign(&ret_ptr) {
+                ArgAction::Assign(loc @ ArgumentLoc::Reg(_)) => ret_ptr.location = loc,
+                _ => unreachable!("return pointer should always get a register assignment"),
+            }
+            let sig = sig.to_mut();
+            sig.returns.push(ret_ptr);
+            sig.returns.retain(|ret| ret.location.is_assigned());
+        } else {
+            sig.to_mut().returns = new_returns;
+        }
     }
+
+    legalize_params(sig, &mut args);
 }
 
 /// Get register class for a type appearing in a legalized signature.
 pub fn regclass_for_abi_type(ty: ir::Type) -> RegClass {
-    if ty.is_int() {
+    if ty.is_int() || ty.is_bool() {
         GPR
     } else {
         match ty.bits() {
@@ -103,6 +259,224 @@ pub fn regclass_for_abi_type(ty: ir::Type) -> RegClass {
 }
 
 /// Get the set of allocatable registers for `func`.
-pub fn allocatable_registers(_func: &ir::Function) -> RegisterSet {
-    unimplemented!()
+pub fn allocatable_registers(
+    _func: &ir::Function,
+    isa_flags: &settings::Flags,
+    thumb: bool,
+) -> RegisterSet {
+    let mut regs = RegisterSet::new();
+
+    // `r12` is the scratch register for the code we emit and for veneers inserted by the linker.
+    // Then come the stack pointer, the link register and the program counter.
+    regs.take(GPR, RU::r12 as RegUnit);
+    regs.take(GPR, RU::r13 as RegUnit);
+    regs.take(GPR, RU::r14 as RegUnit);
+    regs.take(GPR, RU::r15 as RegUnit);
+    regs.take(GPR, frame_pointer(thumb) as RegUnit);
+
+    // Most VFP units only have 16 double precision registers.
+    if !isa_flags.has_d32() {
+        for reg in 16..32 {
+            regs.take(D, D.unit(reg));
+        }
+    }
+
+    regs
+}
+
+/// Get the callee-saved registers of `class` that are used in `func`. Only `d8`-`d15` are
+/// callee-saved among the VFP registers, which covers `s16`-`s31`.
+fn callee_saved_regs_used(func: &ir::Function, thumb: bool) -> (Vec<RegUnit>, Vec<RegUnit>) {
+    let mut all_callee_saved = RegisterSet::empty();
+    for reg in 4..12 {
+        all_callee_saved.free(GPR, GPR.unit(reg));
+    }
+    all_callee_saved.take(GPR, frame_pointer(thumb) as RegUnit);
+    for reg in 8..16 {
+        all_callee_saved.free(D, D.unit(reg));
+    }
+
+    let mut used = RegisterSet::empty();
+    let mut mark_used = |ru: RegUnit| {
+        let (rc, ru) = if GPR.contains(ru) {
+            (GPR, ru)
+        } else if S.contains(ru) || D.contains(ru) {
+            // Single precision registers are saved with the double they are part of.
+            (D, ru & !1)
+        } else {
+            return;
+        };
+        if !used.is_avail(rc, ru) {
+            used.free(rc, ru);
+        }
+    };
+    for value_loc in func.locations.values() {
+        if let ValueLoc::Reg(ru) = *value_loc {
+            mark_used(ru);
+        }
+    }
+
+    // Values diverted by `regmove` and `regfill` aren't reflected in `func.locations`.
+    for ebb in &func.layout {
+        for inst in func.layout.ebb_insts(ebb) {
+            match func.dfg[inst] {
+                ir::InstructionData::RegMove { dst, .. }
+                | ir::InstructionData::RegFill { dst, .. } => mark_used(dst),
+                _ => (),
+            }
+        }
+    }
+
+    used.intersect(&all_callee_saved);
+    (used.iter(GPR).collect(), used.iter(D).collect())
+}
+
+/// Does `func` contain an instruction that the frame layout can't handle?
+fn unsupported_inst(func: &ir::Function) -> Option<&'static str> {
+    func.layout.ebbs().find_map(|ebb| {
+        func.layout.ebb_insts(ebb).find_map(|inst| {
+            let opcode = func.dfg[inst].opcode();
+            if opcode == ir::Opcode::DynamicStackAlloc {
+                Some("dynamic stack allocations on arm32")
+            } else if opcode.is_call() && opcode.is_terminator() {
+                Some("tail calls on arm32")
+            } else {
+                None
+            }
+        })
+    })
+}
+
+/// Insert the prologue and epilogues of `func`.
+///
+/// The prologue pushes the frame record and makes the frame pointer point to it, then pushes the
+/// callee-saved registers and finally allocates the rest of the frame.
+pub fn prologue_epilogue(func: &mut ir::Function, isa: &dyn TargetIsa) -> CodegenResult<()> {
+    if let Some(what) = unsupported_inst(func) {
+        return Err(CodegenError::Unsupported(what.to_string()));
+    }
+    match func.signature.call_conv {
+        CallConv::Tail | CallConv::WindowsFastcall | CallConv::Probestack => {
+            return Err(CodegenError::Unsupported(format!(
+                "the {} calling convention on arm32",
+                func.signature.call_conv
+            )));
+        }
+        _ => {}
+    }
+
+    let thumb = is_thumb(isa.triple());
+    let (gprs, dregs) = callee_saved_regs_used(func, thumb);
+
+    let reserved_size = FRAME_RECORD_SIZE + 4 * gprs.len() as u32 + 8 * dregs.len() as u32;
+    func.create_stack_slot(ir::StackSlotData {
+        kind: ir::StackSlotKind::IncomingArg,
+        size: reserved_size,
+        offset: Some(-(reserved_size as StackOffset)),
+    });
+
+    let is_leaf = func.is_leaf();
+    let total_size = layout_stack(&mut func.stack_slots, is_leaf, STACK_ALIGNMENT)?;
+    let local_size = i64::from(total_size - reserved_size);
+
+    // The saved registers are passed to the function and returned from it like the frame pointer
+    // and link register.
+    let mut saved = vec![
+        (ir::types::I32, ArgumentPurpose::Link, RU::r14 as RegUnit),
+        (
+            ir::types::I32,
+            ArgumentPurpose::FramePointer,
+            frame_pointer(thumb) as RegUnit,
+        ),
+    ];
+    saved.extend(
+        gprs.iter()
+            .map(|&reg| (ir::types::I32, ArgumentPurpose::CalleeSaved, reg)),
+    );
+    saved.extend(
+        dregs
+            .iter()
+            .map(|&reg| (ir::types::F64, ArgumentPurpose::CalleeSaved, reg)),
+    );
+    for &(ty, purpose, reg) in &saved {
+        let arg = AbiParam::special_reg(ty, purpose, reg);
+        func.signature.params.push(arg);
+        func.signature.returns.push(arg);
+    }
+
+    let entry_ebb = func.layout.entry_block().expect("missing entry block");
+    let mut pos = EncCursor::new(func, isa).at_first_insertion_point(entry_ebb);
+    insert_prologue(&mut pos, local_size, &saved);
+
+    let mut pos = pos.at_position(CursorPosition::Nowhere);
+    while let Some(ebb) = pos.next_ebb() {
+        pos.goto_last_inst(ebb);
+        if let Some(inst) = pos.current_inst() {
+            if pos.func.dfg[inst].opcode().is_return() {
+                insert_epilogue(inst, local_size, &mut pos, &saved);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Insert the prologue at the cursor, given the frame size below the saved registers and the
+/// registers in `saved`, starting with the frame record.
+fn insert_prologue(
+    pos: &mut EncCursor,
+    local_size: i64,
+    saved: &[(ir::Type, ArgumentPurpose, RegUnit)],
+) {
+    let ebb = pos.current_ebb().expect("missing ebb under cursor");
+    let values: Vec<_> = saved
+        .iter()
+        .map(|&(ty, _, reg)| {
+            let value = pos.func.dfg.append_ebb_param(ebb, ty);
+            pos.func.locations[value] = ValueLoc::Reg(reg);
+            value
+        })
+        .collect();
+
+    let (record, regs) = values.split_at(2);
+    pos.ins().arm32_push(record[0]);
+    pos.ins().arm32_push(record[1]);
+    pos.ins().copy_special(RU::r13 as RegUnit, saved[1].2);
+
+    for &value in regs {
+        pos.ins().arm32_push(value);
+    }
+
+    if local_size > 0 {
+        pos.func.prologue_end = Some(pos.ins().adjust_sp_down_imm(Imm64::new(local_size)));
+    }
+}
+
+/// Insert an epilogue before the return instruction `inst`, restoring the registers in `saved`
+/// and passing them to the return.
+fn insert_epilogue(
+    inst: ir::Inst,
+    local_size: i64,
+    pos: &mut EncCursor,
+    saved: &[(ir::Type, ArgumentPurpose, RegUnit)],
+) {
+    if local_size > 0 {
+        pos.ins().adjust_sp_up_imm(Imm64::new(local_size));
+    }
+
+    // Pop the registers in the reverse order of the pushes, but pass them to the return in the
+    // order of the signature.
+    let mut values: Vec<_> = saved
+        .iter()
+        .rev()
+        .map(|&(ty, _, reg)| {
+            let value = pos.ins().arm32_pop(ty);
+            pos.func.locations[value] = ValueLoc::Reg(reg);
+            value
+        })
+        .collect();
+    values.reverse();
+    for value in values {
+        pos.func.dfg.append_inst_arg(inst, value);
+    }
 }
@@ -1,8 +1,1321 @@
 //! Emitting binary ARM32 machine code.
+//!
+//! The recipes of the A32 and Thumb-2 modes share their emit code, which calls the helpers of
+//! either the `a32` or the `t32` module below. Both modules provide the same helpers, with the
+//! same names and signatures, which emit the instructions of their mode.
 
-use crate::binemit::{bad_encoding, CodeSink};
-use crate::ir::{Function, Inst};
-use crate::isa::TargetIsa;
+use super::registers::RU;
+use crate::binemit::{bad_encoding, CodeOffset, CodeSink, Reloc};
+use crate::ir::condcodes::{FloatCC, IntCC};
+use crate::ir::immediates::Imm64;
+use crate::ir::{ExternalName, Function, Inst, InstructionData, SourceLoc, StackSlot, TrapCode};
+use crate::isa::{RegUnit, StackBase, StackBaseMask, StackRef, TargetIsa};
 use crate::regalloc::RegDiversions;
 
 include!(concat!(env!("OUT_DIR"), "/binemit-arm32.rs"));
+
+/// The `eq` condition code.
+const COND_EQ: u32 = 0b0000;
+
+/// The `ne` condition code.
+const COND_NE: u32 = 0b0001;
+
+/// The `al` condition code, for unconditional A32 instructions.
+const COND_AL: u32 = 0b1110;
+
+/// A VFP register, as its 4-bit register field and the extra bit that goes in the `D`, `N` or
+/// `M` bit of the instruction. Single precision registers keep their low bit in the extra bit,
+/// and double precision registers their high bit.
+type VReg = (u32, u32);
+
+/// The register field of an instruction that doesn't use it.
+const NO_VREG: VReg = (0, 0);
+
+// The scratch register used to materialize large offsets and immediates.
+fn scratch() -> RegUnit {
+    RU::r12.into()
+}
+
+// Get a reference to the stack slot `ss`, relative to the frame pointer if the stack pointer of
+// `func` isn't fixed.
+fn stk_ref(ss: StackSlot, func: &Function) -> StackRef {
+    StackRef::masked(ss, StackBaseMask(3), &func.stack_slots).unwrap()
+}
+
+// The displacement from the current position of `sink` to `dest`.
+fn branch_disp<CS: CodeSink + ?Sized>(dest: CodeOffset, sink: &CS) -> i64 {
+    i64::from(dest) - i64::from(sink.offset())
+}
+
+// Register number as encoded in an instruction.
+fn rn(reg: RegUnit) -> u32 {
+    u32::from(reg) & 0xf
+}
+
+/// A single precision register of the `S` class.
+fn sreg(reg: RegUnit) -> VReg {
+    let n = u32::from(reg);
+    (n >> 1, n & 1)
+}
+
+/// A double precision register of the `D` class, which spans two register units.
+fn dreg(reg: RegUnit) -> VReg {
+    let n = u32::from(reg) / 2;
+    (n & 0xf, n >> 4)
+}
+
+/// Convert an integer condition code to the ARM condition, assuming the flags were set by a
+/// subtraction.
+fn icc2cond(cond: IntCC) -> u32 {
+    use crate::ir::condcodes::IntCC::*;
+    match cond {
+        Equal => 0b0000,
+        NotEqual => 0b0001,
+        UnsignedGreaterThanOrEqual => 0b0010,
+        UnsignedLessThan => 0b0011,
+        Overflow => 0b0110,
+        NotOverflow => 0b0111,
+        UnsignedGreaterThan => 0b1000,
+        UnsignedLessThanOrEqual => 0b1001,
+        SignedGreaterThanOrEqual => 0b1010,
+        SignedLessThan => 0b1011,
+        SignedGreaterThan => 0b1100,
+        SignedLessThanOrEqual => 0b1101,
+    }
+}
+
+/// Convert a floating point condition code to a pair of ARM conditions, assuming the flags were
+/// set by `vcmp` and copied by `vmrs`. The condition holds if either of the pair holds. Only
+/// `one` and `ueq` need both; the others repeat the first condition.
+fn fcc2cond(cond: FloatCC) -> (u32, u32) {
+    use crate::ir::condcodes::FloatCC::*;
+    let single = |c| (c, c);
+    match cond {
+        Equal => single(0b0000),
+        NotEqual => single(0b0001),
+        LessThan => single(0b0100),
+        LessThanOrEqual => single(0b1001),
+        GreaterThan => single(0b1100),
+        GreaterThanOrEqual => single(0b1010),
+        Ordered => single(0b0111),
+        Unordered => single(0b0110),
+        UnorderedOrLessThan => single(0b1011),
+        UnorderedOrLessThanOrEqual => single(0b1101),
+        UnorderedOrGreaterThan => single(0b1000),
+        UnorderedOrGreaterThanOrEqual => single(0b0101),
+        OrderedNotEqual => (0b0100, 0b1100),
+        UnorderedOrEqual => (0b0000, 0b0110),
+    }
+}
+
+/// The shift amount of an immediate shift or rotate, modulo 32.
+fn imm_shift_amount(imm: Imm64) -> u32 {
+    let imm: i64 = imm.into();
+    (imm as u32) & 31
+}
+
+/// The size in bytes of `put_iconst`, which is the same in both modes.
+pub fn iconst_size(imm: u32) -> u8 {
+    if imm >> 16 == 0 || !imm <= 0xff {
+        4
+    } else {
+        8
+    }
+}
+
+/// The size in bytes of `put_add_imm`, which is the same in both modes.
+pub fn add_imm_size(imm: i64) -> u8 {
+    if imm.wrapping_abs() as u64 <= 0xff {
+        4
+    } else {
+        iconst_size(imm.wrapping_abs() as u32) + 4
+    }
+}
+
+/// Whether a VFP load or store can encode `offset`: a multiple of 4 within 1020 bytes.
+fn vldst_fits(offset: i64) -> bool {
+    offset % 4 == 0 && offset >= -1020 && offset <= 1020
+}
+
+/// The size in bytes of a VFP load or store with an immediate `offset`, which is the same in
+/// both modes.
+pub fn vldst_size(offset: i64) -> u8 {
+    if vldst_fits(offset) {
+        4
+    } else {
+        add_imm_size(offset) + 4
+    }
+}
+
+/// VFP data processing, which is the same word in both modes.
+///
+/// Encoding bits: `word[23:16] | word[8:6]`.
+fn vfp_word(bits: u16, vd: VReg, vn: VReg, vm: VReg) -> u32 {
+    let bits = u32::from(bits);
+    0xee00_0a00
+        | (bits >> 3) << 16
+        | (bits & 0x7) << 6
+        | vd.1 << 22
+        | vn.0 << 16
+        | vd.0 << 12
+        | vn.1 << 7
+        | vm.1 << 5
+        | vm.0
+}
+
+/// VFP loads and stores with an offset that `vldst_fits`.
+///
+/// Encoding bits: `word[27:20] | word[11:8]`.
+fn vldst_word(bits: u16, rn_: RegUnit, offset: i64, vd: VReg) -> u32 {
+    let bits = u32::from(bits);
+    let mut word = COND_AL << 28 | (bits >> 4) << 20 | (bits & 0xf) << 8;
+    if offset < 0 {
+        word &= !(1 << 23);
+    }
+    let imm8 = (offset.wrapping_abs() / 4) as u32;
+    word | vd.1 << 22 | rn(rn_) << 16 | vd.0 << 12 | imm8
+}
+
+/// Emitting A32 instructions: 32-bit words with a condition field.
+pub mod a32 {
+    use super::*;
+
+    /// Encoding bits of `mov`.
+    pub const MOV: u16 = 0x1a;
+
+    /// Encoding bits of `and` with an immediate.
+    pub const AND_IMM: u16 = 0x20;
+
+    /// Encoding bits of `cmp` with an immediate.
+    pub const CMP_IMM: u16 = 0x35;
+
+    /// Encoding bits of `clz` and `rbit`.
+    pub const CLZ: u16 = 0x161;
+    pub const RBIT: u16 = 0x6f3;
+
+    /// Encoding bits of `add` with a register and with an immediate, and of `sub` with a
+    /// register.
+    const ADD: u16 = 0x08;
+    const ADD_IMM: u16 = 0x28;
+    const SUB: u16 = 0x04;
+
+    /// `udf #0`, the permanently undefined instruction.
+    const UDF: u32 = 0xe7f0_00f0;
+
+    /// `bkpt #0`.
+    const BKPT: u32 = 0xe120_0070;
+
+    // Convert a stack base to the corresponding register.
+    pub fn stk_base(base: StackBase) -> RegUnit {
+        let ru = match base {
+            StackBase::SP => RU::r13,
+            StackBase::FP => RU::r11,
+            StackBase::Zone => unimplemented!(),
+        };
+        ru as RegUnit
+    }
+
+    /// Encode a modified immediate: an 8-bit value rotated right by an even amount.
+    fn encode_imm(value: u32) -> u32 {
+        (0..16)
+            .find_map(|rot| {
+                let imm8 = value.rotate_left(2 * rot);
+                if imm8 <= 0xff {
+                    Some(rot << 8 | imm8)
+                } else {
+                    None
+                }
+            })
+            .expect("immediate can't be encoded")
+    }
+
+    /// Data processing with three registers.
+    ///
+    /// Encoding bits: `word[27:20]`.
+    pub fn put_dp<CS: CodeSink + ?Sized>(
+        bits: u16,
+        rn_: RegUnit,
+        rm: RegUnit,
+        rd: RegUnit,
+        sink: &mut CS,
+    ) {
+        let bits = u32::from(bits);
+        sink.put4(COND_AL << 28 | bits << 20 | rn(rn_) << 16 | rn(rd) << 12 | rn(rm));
+    }
+
+    /// Data processing with a modified immediate.
+    ///
+    /// Encoding bits: `word[27:20]`.
+    pub fn put_dp_imm<CS: CodeSink + ?Sized>(
+        bits: u16,
+        rn_: RegUnit,
+        imm: u32,
+        rd: RegUnit,
+        sink: &mut CS,
+    ) {
+        let bits = u32::from(bits);
+        sink.put4(COND_AL << 28 | bits << 20 | rn(rn_) << 16 | rn(rd) << 12 | encode_imm(imm));
+    }
+
+    /// `add` and `sub` with an immediate. Negative immediates flip the operation.
+    pub fn put_addsub_imm<CS: CodeSink + ?Sized>(
+        bits: u16,
+        rn_: RegUnit,
+        imm: i64,
+        rd: RegUnit,
+        sink: &mut CS,
+    ) {
+        let bits = if imm < 0 { bits ^ 0x0c } else { bits };
+        put_dp_imm(bits, rn_, imm.wrapping_abs() as u32, rd, sink);
+    }
+
+    /// `cmp` with a register.
+    pub fn put_cmp<CS: CodeSink + ?Sized>(bits: u16, rn_: RegUnit, rm: RegUnit, sink: &mut CS) {
+        put_dp(bits, rn_, rm, 0, sink);
+    }
+
+    /// `cmp` with an immediate. Negative immediates use `cmn`.
+    pub fn put_cmp_imm<CS: CodeSink + ?Sized>(bits: u16, rn_: RegUnit, imm: i64, sink: &mut CS) {
+        let bits = if imm < 0 { bits ^ 0x02 } else { bits };
+        put_dp_imm(bits, rn_, imm.wrapping_abs() as u32, 0, sink);
+    }
+
+    /// Shift `rm` by the amount in `rs`.
+    ///
+    /// Encoding bits: the shift type.
+    pub fn put_shift<CS: CodeSink + ?Sized>(
+        bits: u16,
+        rm: RegUnit,
+        rs: RegUnit,
+        rd: RegUnit,
+        sink: &mut CS,
+    ) {
+        let ty = u32::from(bits);
+        sink.put4(0xe1a0_0010 | rn(rd) << 12 | rn(rs) << 8 | ty << 5 | rn(rm));
+    }
+
+    /// Shift `rm` by an immediate amount. A shift by 0 is a `mov`.
+    ///
+    /// Encoding bits: the shift type.
+    pub fn put_shift_imm<CS: CodeSink + ?Sized>(
+        bits: u16,
+        rm: RegUnit,
+        amount: u32,
+        rd: RegUnit,
+        sink: &mut CS,
+    ) {
+        let ty = if amount == 0 { 0 } else { u32::from(bits) };
+        sink.put4(0xe1a0_0000 | rn(rd) << 12 | amount << 7 | ty << 5 | rn(rm));
+    }
+
+    /// `mul rd, rn, rm`.
+    pub fn put_mul<CS: CodeSink + ?Sized>(rn_: RegUnit, rm: RegUnit, rd: RegUnit, sink: &mut CS) {
+        sink.put4(0xe000_0090 | rn(rd) << 16 | rn(rm) << 8 | rn(rn_));
+    }
+
+    /// `mls rd, rn, rm, ra`, computing `ra - rn * rm`.
+    pub fn put_mls<CS: CodeSink + ?Sized>(
+        rn_: RegUnit,
+        rm: RegUnit,
+        ra: RegUnit,
+        rd: RegUnit,
+        sink: &mut CS,
+    ) {
+        sink.put4(0xe060_0090 | rn(rd) << 16 | rn(ra) << 12 | rn(rm) << 8 | rn(rn_));
+    }
+
+    /// `umull` and `smull`.
+    ///
+    /// Encoding bits: `word[27:20]`.
+    pub fn put_mull<CS: CodeSink + ?Sized>(
+        bits: u16,
+        rn_: RegUnit,
+        rm: RegUnit,
+        rdlo: RegUnit,
+        rdhi: RegUnit,
+        sink: &mut CS,
+    ) {
+        let bits = u32::from(bits);
+        sink.put4(
+            COND_AL << 28
+                | bits << 20
+                | rn(rdhi) << 16
+                | rn(rdlo) << 12
+                | rn(rm) << 8
+                | 0x90
+                | rn(rn_),
+        );
+    }
+
+    /// `udiv` and `sdiv`.
+    ///
+    /// Encoding bits: `word[27:20]`.
+    pub fn put_div<CS: CodeSink + ?Sized>(
+        bits: u16,
+        rn_: RegUnit,
+        rm: RegUnit,
+        rd: RegUnit,
+        sink: &mut CS,
+    ) {
+        let bits = u32::from(bits);
+        sink.put4(COND_AL << 28 | bits << 20 | rn(rd) << 16 | 0xf010 | rn(rm) << 8 | rn(rn_));
+    }
+
+    /// Trap with `IntegerDivisionByZero` unless `rt` is non-zero.
+    pub fn put_trap_if_zero<CS: CodeSink + ?Sized>(rt: RegUnit, srcloc: SourceLoc, sink: &mut CS) {
+        put_cmp_imm(CMP_IMM, rt, 0, sink);
+        put_trap_unless(COND_NE, TrapCode::IntegerDivisionByZero, srcloc, sink);
+    }
+
+    /// Trap with `IntegerOverflow` if `x / y` overflows, which happens when dividing the smallest
+    /// integer by -1.
+    pub fn put_sdiv_overflow_check<CS: CodeSink + ?Sized>(
+        x: RegUnit,
+        y: RegUnit,
+        srcloc: SourceLoc,
+        sink: &mut CS,
+    ) {
+        // cmn y, #1
+        put_cmp_imm(CMP_IMM, y, -1, sink);
+        // cmpeq x, #0x80000000
+        let cmp = u32::from(CMP_IMM);
+        sink.put4(COND_EQ << 28 | cmp << 20 | rn(x) << 16 | encode_imm(0x8000_0000));
+        put_trap_unless(COND_NE, TrapCode::IntegerOverflow, srcloc, sink);
+    }
+
+    /// `clz` and `rbit`.
+    ///
+    /// Encoding bits: `word[27:20] | word[7:4]`.
+    pub fn put_bitop<CS: CodeSink + ?Sized>(bits: u16, rm: RegUnit, rd: RegUnit, sink: &mut CS) {
+        let bits = u32::from(bits);
+        sink.put4(
+            COND_AL << 28
+                | (bits >> 4) << 20
+                | 0xf_0f00
+                | rn(rd) << 12
+                | (bits & 0xf) << 4
+                | rn(rm),
+        );
+    }
+
+    /// Sign and zero extensions.
+    ///
+    /// Encoding bits: `word[27:20]`.
+    pub fn put_extend<CS: CodeSink + ?Sized>(bits: u16, rm: RegUnit, rd: RegUnit, sink: &mut CS) {
+        let bits = u32::from(bits);
+        sink.put4(COND_AL << 28 | bits << 20 | 0xf_0070 | rn(rd) << 12 | rn(rm));
+    }
+
+    /// Materialize an immediate with `movw` followed by `movt`, or with `mvn`.
+    pub fn put_iconst<CS: CodeSink + ?Sized>(imm: u32, rd: RegUnit, sink: &mut CS) {
+        if imm >> 16 != 0 && !imm <= 0xff {
+            // mvn rd, #imm
+            sink.put4(0xe3e0_0000 | rn(rd) << 12 | !imm);
+            return;
+        }
+        let movw = |imm: u32| (imm >> 12) << 16 | (imm & 0xfff);
+        sink.put4(0xe300_0000 | rn(rd) << 12 | movw(imm & 0xffff));
+        if imm >> 16 != 0 {
+            sink.put4(0xe340_0000 | rn(rd) << 12 | movw(imm >> 16));
+        }
+    }
+
+    /// Add an immediate to `rn`, where `rn` and `rd` may be the stack pointer.
+    pub fn put_add_imm<CS: CodeSink + ?Sized>(rn_: RegUnit, imm: i64, rd: RegUnit, sink: &mut CS) {
+        if imm.wrapping_abs() as u64 <= 0xff {
+            put_addsub_imm(ADD_IMM, rn_, imm, rd, sink);
+        } else {
+            put_iconst(imm.wrapping_abs() as u32, scratch(), sink);
+            put_dp(if imm < 0 { SUB } else { ADD }, rn_, scratch(), rd, sink);
+        }
+    }
+
+    /// `mov<cond> rd, rm`.
+    pub fn put_mov_cond<CS: CodeSink + ?Sized>(cond: u32, rm: RegUnit, rd: RegUnit, sink: &mut CS) {
+        sink.put4(cond << 28 | 0x01a0_0000 | rn(rd) << 12 | rn(rm));
+    }
+
+    /// `mov<cond> rd, #imm8`.
+    fn put_movi_cond<CS: CodeSink + ?Sized>(cond: u32, imm8: u32, rd: RegUnit, sink: &mut CS) {
+        sink.put4(cond << 28 | 0x03a0_0000 | rn(rd) << 12 | imm8);
+    }
+
+    /// Set `rd` to 1 if `cond` holds, and to 0 otherwise.
+    pub fn put_cset<CS: CodeSink + ?Sized>(cond: u32, rd: RegUnit, sink: &mut CS) {
+        put_movi_cond(cond ^ 1, 0, rd, sink);
+        put_movi_cond(cond, 1, rd, sink);
+    }
+
+    /// Set `rd` to 1 if either condition holds, and to 0 otherwise.
+    pub fn put_cset2<CS: CodeSink + ?Sized>(cond1: u32, cond2: u32, rd: RegUnit, sink: &mut CS) {
+        put_cset(cond1, rd, sink);
+        put_movi_cond(cond2, 1, rd, sink);
+    }
+
+    /// `tst rn, #1`, testing a boolean.
+    pub fn put_tst_bool<CS: CodeSink + ?Sized>(rn_: RegUnit, sink: &mut CS) {
+        sink.put4(0xe310_0001 | rn(rn_) << 16);
+    }
+
+    /// Loads and stores can encode offsets within 4095 bytes, or 255 bytes for the halfword and
+    /// signed byte accesses, which have an opcode in `word[7:4]`.
+    fn ldst_fits(bits: u16, offset: i64) -> bool {
+        let max = if bits & 0xf != 0 { 0xff } else { 0xfff };
+        offset >= -max && offset <= max
+    }
+
+    /// The size in bytes of a load or store with an immediate `offset`.
+    pub fn ldst_size(bits: u16, offset: i64) -> u8 {
+        if ldst_fits(bits, offset) {
+            4
+        } else {
+            iconst_size(offset as u32) + 4
+        }
+    }
+
+    /// Prepare the offset of a load or store: returns the immediate offset if it fits in the
+    /// instruction, or materializes it in the scratch register.
+    pub fn ldst_prepare<CS: CodeSink + ?Sized>(
+        bits: u16,
+        offset: i64,
+        sink: &mut CS,
+    ) -> Option<i64> {
+        if ldst_fits(bits, offset) {
+            Some(offset)
+        } else {
+            put_iconst(offset as u32, scratch(), sink);
+            None
+        }
+    }
+
+    /// A load or store with the offset prepared by `ldst_prepare`.
+    ///
+    /// Encoding bits: `word[27:20] | word[7:4]` of the immediate offset form.
+    pub fn put_ldst<CS: CodeSink + ?Sized>(
+        bits: u16,
+        rn_: RegUnit,
+        offset: Option<i64>,
+        rt: RegUnit,
+        sink: &mut CS,
+    ) {
+        let extra = bits & 0xf != 0;
+        let bits = u32::from(bits);
+        let mut word =
+            COND_AL << 28 | (bits >> 4) << 20 | (bits & 0xf) << 4 | rn(rn_) << 16 | rn(rt) << 12;
+        match offset {
+            Some(offset) => {
+                if offset < 0 {
+                    word &= !(1 << 23);
+                }
+                let imm = offset.wrapping_abs() as u32;
+                if extra {
+                    word |= (imm >> 4) << 8 | (imm & 0xf);
+                } else {
+                    word |= imm;
+                }
+            }
+            None => {
+                if extra {
+                    word &= !(1 << 22);
+                } else {
+                    word |= 1 << 25;
+                }
+                word |= rn(scratch());
+            }
+        }
+        sink.put4(word);
+    }
+
+    /// `ldr rd, [rn, rm, lsl #2]`.
+    pub fn put_ldr_scaled<CS: CodeSink + ?Sized>(
+        rn_: RegUnit,
+        rm: RegUnit,
+        rd: RegUnit,
+        sink: &mut CS,
+    ) {
+        sink.put4(0xe790_0100 | rn(rn_) << 16 | rn(rd) << 12 | rn(rm));
+    }
+
+    /// Prepare the base and offset of a VFP load or store, computing the address in the scratch
+    /// register if the offset doesn't fit in the instruction.
+    pub fn vldst_prepare<CS: CodeSink + ?Sized>(
+        rn_: RegUnit,
+        offset: i64,
+        sink: &mut CS,
+    ) -> (RegUnit, i64) {
+        if vldst_fits(offset) {
+            (rn_, offset)
+        } else {
+            put_add_imm(rn_, offset, scratch(), sink);
+            (scratch(), 0)
+        }
+    }
+
+    /// `vldr` and `vstr`.
+    pub fn put_vldst<CS: CodeSink + ?Sized>(
+        bits: u16,
+        rn_: RegUnit,
+        offset: i64,
+        vd: VReg,
+        sink: &mut CS,
+    ) {
+        sink.put4(vldst_word(bits, rn_, offset, vd));
+    }
+
+    /// `str rt, [sp, #-4]!`.
+    pub fn put_push<CS: CodeSink + ?Sized>(rt: RegUnit, sink: &mut CS) {
+        sink.put4(0xe52d_0004 | rn(rt) << 12);
+    }
+
+    /// `ldr rt, [sp], #4`.
+    pub fn put_pop<CS: CodeSink + ?Sized>(rt: RegUnit, sink: &mut CS) {
+        sink.put4(0xe49d_0004 | rn(rt) << 12);
+    }
+
+    /// `vpush {dd}`.
+    pub fn put_vpush<CS: CodeSink + ?Sized>(dd: VReg, sink: &mut CS) {
+        sink.put4(0xed2d_0b02 | dd.1 << 22 | dd.0 << 12);
+    }
+
+    /// `vpop {dd}`.
+    pub fn put_vpop<CS: CodeSink + ?Sized>(dd: VReg, sink: &mut CS) {
+        sink.put4(0xecbd_0b02 | dd.1 << 22 | dd.0 << 12);
+    }
+
+    /// `b` to a displacement from the start of the instruction.
+    pub fn put_b<CS: CodeSink + ?Sized>(disp: i64, sink: &mut CS) {
+        put_b_cond(COND_AL, disp, sink);
+    }
+
+    /// `b<cond>` to a displacement from the start of the instruction. The PC reads 8 bytes
+    /// ahead.
+    pub fn put_b_cond<CS: CodeSink + ?Sized>(cond: u32, disp: i64, sink: &mut CS) {
+        sink.put4(cond << 28 | 0x0a00_0000 | (((disp - 8) >> 2) as u32) & 0xff_ffff);
+    }
+
+    /// `bl` to a function, with the addend of a `bl .` so the relocation works with both `REL`
+    /// and `RELA` object files.
+    pub fn put_bl<CS: CodeSink + ?Sized>(name: &ExternalName, sink: &mut CS) {
+        sink.reloc_external(Reloc::Arm32Call, name, -8);
+        sink.put4(0xebff_fffe);
+    }
+
+    /// `blx rm`.
+    pub fn put_blx<CS: CodeSink + ?Sized>(rm: RegUnit, sink: &mut CS) {
+        sink.put4(0xe12f_ff30 | rn(rm));
+    }
+
+    /// `bx rm`.
+    pub fn put_bx<CS: CodeSink + ?Sized>(rm: RegUnit, sink: &mut CS) {
+        sink.put4(0xe12f_ff10 | rn(rm));
+    }
+
+    /// Jump to the address in `rm`.
+    pub fn put_indirect_jump<CS: CodeSink + ?Sized>(rm: RegUnit, sink: &mut CS) {
+        put_bx(rm, sink);
+    }
+
+    /// `movw` and `movt` of the absolute address of a symbol.
+    pub fn put_movw_movt<CS: CodeSink + ?Sized>(name: &ExternalName, rd: RegUnit, sink: &mut CS) {
+        sink.reloc_external(Reloc::Arm32MovwAbsNc, name, 0);
+        sink.put4(0xe300_0000 | rn(rd) << 12);
+        sink.reloc_external(Reloc::Arm32MovtAbs, name, 0);
+        sink.put4(0xe340_0000 | rn(rd) << 12);
+    }
+
+    /// Compute the address at a displacement from the start of the sequence: `movw` and `movt`
+    /// of the distance from the PC, followed by `add rd, pc, rd`.
+    pub fn put_pc_relative<CS: CodeSink + ?Sized>(disp: i64, rd: RegUnit, sink: &mut CS) {
+        let offset = (disp - 16) as u32;
+        let movw = |imm: u32| (imm >> 12) << 16 | (imm & 0xfff);
+        sink.put4(0xe300_0000 | rn(rd) << 12 | movw(offset & 0xffff));
+        sink.put4(0xe340_0000 | rn(rd) << 12 | movw(offset >> 16));
+        sink.put4(0xe08f_0000 | rn(rd) << 12 | rn(rd));
+    }
+
+    /// `udf #0`.
+    pub fn put_udf<CS: CodeSink + ?Sized>(sink: &mut CS) {
+        sink.put4(UDF);
+    }
+
+    /// `bkpt #0`.
+    pub fn put_bkpt<CS: CodeSink + ?Sized>(sink: &mut CS) {
+        sink.put4(BKPT);
+    }
+
+    /// Trap with `code` unless `cond` holds, by branching over an `udf`.
+    pub fn put_trap_unless<CS: CodeSink + ?Sized>(
+        cond: u32,
+        code: TrapCode,
+        srcloc: SourceLoc,
+        sink: &mut CS,
+    ) {
+        put_b_cond(cond, 8, sink);
+        sink.trap(code, srcloc);
+        put_udf(sink);
+    }
+
+    /// Trap with `code` if either condition holds.
+    pub fn put_trap_if_either<CS: CodeSink + ?Sized>(
+        cond1: u32,
+        cond2: u32,
+        code: TrapCode,
+        srcloc: SourceLoc,
+        sink: &mut CS,
+    ) {
+        put_b_cond(cond1, 8, sink);
+        put_b_cond(cond2 ^ 1, 8, sink);
+        sink.trap(code, srcloc);
+        put_udf(sink);
+    }
+
+    /// VFP data processing.
+    pub fn put_vfp<CS: CodeSink + ?Sized>(bits: u16, vd: VReg, vn: VReg, vm: VReg, sink: &mut CS) {
+        sink.put4(vfp_word(bits, vd, vn, vm));
+    }
+
+    /// VFP data processing with one source, only executed if `cond` holds.
+    pub fn put_vfp_cond<CS: CodeSink + ?Sized>(
+        bits: u16,
+        cond: u32,
+        vd: VReg,
+        vm: VReg,
+        sink: &mut CS,
+    ) {
+        sink.put4(cond << 28 | vfp_word(bits, vd, NO_VREG, vm) & 0x0fff_ffff);
+    }
+
+    /// `vmrs APSR_nzcv, fpscr`, copying the floating point flags.
+    pub fn put_vmrs<CS: CodeSink + ?Sized>(sink: &mut CS) {
+        sink.put4(0xeef1_fa10);
+    }
+
+    /// `vmov` between a core register and a single precision register.
+    ///
+    /// Encoding bits: `word[20]`, set for moves to the core register.
+    pub fn put_vmov_core<CS: CodeSink + ?Sized>(bits: u16, rt: RegUnit, sn: VReg, sink: &mut CS) {
+        let op = u32::from(bits);
+        sink.put4(0xee00_0a10 | op << 20 | sn.0 << 16 | rn(rt) << 12 | sn.1 << 7);
+    }
+
+    /// `vmov` between two core registers and a double precision register.
+    ///
+    /// Encoding bits: `word[20]`, set for moves to the core registers.
+    pub fn put_vmov_pair<CS: CodeSink + ?Sized>(
+        bits: u16,
+        rt: RegUnit,
+        rt2: RegUnit,
+        dm: VReg,
+        sink: &mut CS,
+    ) {
+        let op = u32::from(bits);
+        sink.put4(0xec40_0b10 | op << 20 | rn(rt2) << 16 | rn(rt) << 12 | dm.1 << 5 | dm.0);
+    }
+}
+
+/// Emitting Thumb-2 instructions: 16-bit halfwords, and 32-bit instructions emitted as two
+/// halfwords with the first one holding the opcode. Conditional instructions other than branches
+/// are preceded by an `it` instruction.
+pub mod t32 {
+    use super::*;
+
+    /// Encoding bits of `mov.w`.
+    pub const MOV: u16 = 0xea4f;
+
+    /// Encoding bits of `and` with an immediate.
+    pub const AND_IMM: u16 = 0xf000;
+
+    /// Encoding bits of `cmp.w` with an immediate.
+    pub const CMP_IMM: u16 = 0xf1b0;
+
+    /// Encoding bits of `clz` and `rbit`, with the opcode from the second halfword.
+    pub const CLZ: u16 = 0xfab8;
+    pub const RBIT: u16 = 0xfa9a;
+
+    /// Encoding bits of `add.w` with a register and with an immediate, and of `sub.w` with a
+    /// register.
+    const ADD: u16 = 0xeb00;
+    const ADD_IMM: u16 = 0xf100;
+    const SUB: u16 = 0xeba0;
+
+    /// `udf #0`, the permanently undefined instruction.
+    const UDF: u16 = 0xde00;
+
+    /// `bkpt #0`.
+    const BKPT: u16 = 0xbe00;
+
+    // Convert a stack base to the corresponding register.
+    pub fn stk_base(base: StackBase) -> RegUnit {
+        let ru = match base {
+            StackBase::SP => RU::r13,
+            StackBase::FP => RU::r7,
+            StackBase::Zone => unimplemented!(),
+        };
+        ru as RegUnit
+    }
+
+    /// Emit a 32-bit instruction, first halfword first.
+    fn put32<CS: CodeSink + ?Sized>(word: u32, sink: &mut CS) {
+        sink.put2((word >> 16) as u16);
+        sink.put2(word as u16);
+    }
+
+    /// `it<cond>`, making the next instruction conditional.
+    fn put_it<CS: CodeSink + ?Sized>(cond: u32, sink: &mut CS) {
+        sink.put2(0xbf08 | (cond << 4) as u16);
+    }
+
+    /// `ite<cond>`, making the next instruction conditional on `cond`, and the one after it on
+    /// the inverse condition.
+    fn put_ite<CS: CodeSink + ?Sized>(cond: u32, sink: &mut CS) {
+        let mask = (!cond & 1) << 3 | 0b100;
+        sink.put2(0xbf00 | (cond << 4 | mask) as u16);
+    }
+
+    /// Encode a modified immediate as `i:imm3:imm8`: an 8-bit value, a byte repeated in some of
+    /// the bytes of the word, or a 7-bit value with an implicit top bit rotated right.
+    fn encode_imm(value: u32) -> u32 {
+        let byte = value & 0xff;
+        if value <= 0xff {
+            value
+        } else if value == byte * 0x0001_0001 {
+            0x100 | byte
+        } else if value == (value >> 8 & 0xff) * 0x0100_0100 {
+            0x200 | value >> 8 & 0xff
+        } else if value == byte * 0x0101_0101 {
+            0x300 | byte
+        } else {
+            (8..32)
+                .find_map(|rot| {
+                    let imm8 = value.rotate_left(rot);
+                    if imm8 <= 0xff && imm8 & 0x80 != 0 {
+                        Some(rot << 7 | (imm8 & 0x7f))
+                    } else {
+                        None
+                    }
+                })
+                .expect("immediate can't be encoded")
+        }
+    }
+
+    /// Place an `i:imm3:imm8` immediate in its instruction fields.
+    fn imm12_fields(imm12: u32) -> u32 {
+        (imm12 >> 11) << 26 | ((imm12 >> 8) & 0x7) << 12 | (imm12 & 0xff)
+    }
+
+    /// Place a 16-bit immediate in the fields of `movw` and `movt`.
+    fn imm16_fields(imm: u32) -> u32 {
+        (imm >> 12) << 16 | imm12_fields(imm & 0xfff)
+    }
+
+    /// Data processing with three registers.
+    ///
+    /// Encoding bits: the first halfword.
+    pub fn put_dp<CS: CodeSink + ?Sized>(
+        bits: u16,
+        rn_: RegUnit,
+        rm: RegUnit,
+        rd: RegUnit,
+        sink: &mut CS,
+    ) {
+        let bits = u32::from(bits);
+        put32(bits << 16 | rn(rn_) << 16 | rn(rd) << 8 | rn(rm), sink);
+    }
+
+    /// Data processing with a modified immediate.
+    ///
+    /// Encoding bits: the first halfword.
+    pub fn put_dp_imm<CS: CodeSink + ?Sized>(
+        bits: u16,
+        rn_: RegUnit,
+        imm: u32,
+        rd: RegUnit,
+        sink: &mut CS,
+    ) {
+        let bits = u32::from(bits);
+        put32(
+            bits << 16 | rn(rn_) << 16 | rn(rd) << 8 | imm12_fields(encode_imm(imm)),
+            sink,
+        );
+    }
+
+    /// `add` and `sub` with an immediate. Negative immediates flip the operation.
+    pub fn put_addsub_imm<CS: CodeSink + ?Sized>(
+        bits: u16,
+        rn_: RegUnit,
+        imm: i64,
+        rd: RegUnit,
+        sink: &mut CS,
+    ) {
+        let bits = if imm < 0 { bits ^ 0xa0 } else { bits };
+        put_dp_imm(bits, rn_, imm.wrapping_abs() as u32, rd, sink);
+    }
+
+    /// `cmp.w` with a register, which has the PC as its destination.
+    pub fn put_cmp<CS: CodeSink + ?Sized>(bits: u16, rn_: RegUnit, rm: RegUnit, sink: &mut CS) {
+        put_dp(bits, rn_, rm, RU::r15.into(), sink);
+    }
+
+    /// `cmp.w` with an immediate. Negative immediates use `cmn.w`.
+    pub fn put_cmp_imm<CS: CodeSink + ?Sized>(bits: u16, rn_: RegUnit, imm: i64, sink: &mut CS) {
+        put_addsub_imm(bits, rn_, imm, RU::r15.into(), sink);
+    }
+
+    /// Shift `rn` by the amount in `rm`.
+    ///
+    /// Encoding bits: the shift type.
+    pub fn put_shift<CS: CodeSink + ?Sized>(
+        bits: u16,
+        rn_: RegUnit,
+        rm: RegUnit,
+        rd: RegUnit,
+        sink: &mut CS,
+    ) {
+        let ty = u32::from(bits);
+        put32(
+            0xfa00_f000 | ty << 21 | rn(rn_) << 16 | rn(rd) << 8 | rn(rm),
+            sink,
+        );
+    }
+
+    /// Shift `rm` by an immediate amount. A shift by 0 is a `mov.w`.
+    ///
+    /// Encoding bits: the shift type.
+    pub fn put_shift_imm<CS: CodeSink + ?Sized>(
+        bits: u16,
+        rm: RegUnit,
+        amount: u32,
+        rd: RegUnit,
+        sink: &mut CS,
+    ) {
+        let ty = if amount == 0 { 0 } else { u32::from(bits) };
+        put32(
+            0xea4f_0000 | (amount >> 2) << 12 | rn(rd) << 8 | (amount & 3) << 6 | ty << 4 | rn(rm),
+            sink,
+        );
+    }
+
+    /// `mul rd, rn, rm`.
+    pub fn put_mul<CS: CodeSink + ?Sized>(rn_: RegUnit, rm: RegUnit, rd: RegUnit, sink: &mut CS) {
+        put32(0xfb00_f000 | rn(rn_) << 16 | rn(rd) << 8 | rn(rm), sink);
+    }
+
+    /// `mls rd, rn, rm, ra`, computing `ra - rn * rm`.
+    pub fn put_mls<CS: CodeSink + ?Sized>(
+        rn_: RegUnit,
+        rm: RegUnit,
+        ra: RegUnit,
+        rd: RegUnit,
+        sink: &mut CS,
+    ) {
+        put32(
+            0xfb00_0010 | rn(rn_) << 16 | rn(ra) << 12 | rn(rd) << 8 | rn(rm),
+            sink,
+        );
+    }
+
+    /// `umull` and `smull`.
+    ///
+    /// Encoding bits: the first halfword.
+    pub fn put_mull<CS: CodeSink + ?Sized>(
+        bits: u16,
+        rn_: RegUnit,
+        rm: RegUnit,
+        rdlo: RegUnit,
+        rdhi: RegUnit,
+        sink: &mut CS,
+    ) {
+        let bits = u32::from(bits);
+        put32(
+            bits << 16 | rn(rn_) << 16 | rn(rdlo) << 12 | rn(rdhi) << 8 | rn(rm),
+            sink,
+        );
+    }
+
+    /// `udiv` and `sdiv`.
+    ///
+    /// Encoding bits: the first halfword.
+    pub fn put_div<CS: CodeSink + ?Sized>(
+        bits: u16,
+        rn_: RegUnit,
+        rm: RegUnit,
+        rd: RegUnit,
+        sink: &mut CS,
+    ) {
+        let bits = u32::from(bits);
+        put32(
+            bits << 16 | rn(rn_) << 16 | 0xf0f0 | rn(rd) << 8 | rn(rm),
+            sink,
+        );
+    }
+
+    /// Trap with `IntegerDivisionByZero` unless `rt` is non-zero.
+    pub fn put_trap_if_zero<CS: CodeSink + ?Sized>(rt: RegUnit, srcloc: SourceLoc, sink: &mut CS) {
+        put_cmp_imm(CMP_IMM, rt, 0, sink);
+        put_trap_unless(COND_NE, TrapCode::IntegerDivisionByZero, srcloc, sink);
+    }
+
+    /// Trap with `IntegerOverflow` if `x / y` overflows, which happens when dividing the smallest
+    /// integer by -1.
+    pub fn put_sdiv_overflow_check<CS: CodeSink + ?Sized>(
+        x: RegUnit,
+        y: RegUnit,
+        srcloc: SourceLoc,
+        sink: &mut CS,
+    ) {
+        // cmn.w y, #1
+        put_cmp_imm(CMP_IMM, y, -1, sink);
+        // it eq; cmpeq.w x, #0x80000000
+        put_it(COND_EQ, sink);
+        put_dp_imm(CMP_IMM, x, 0x8000_0000, RU::r15.into(), sink);
+        put_trap_unless(COND_NE, TrapCode::IntegerOverflow, srcloc, sink);
+    }
+
+    /// `clz` and `rbit`.
+    ///
+    /// Encoding bits: the first halfword, with the opcode of the second halfword in place of
+    /// the register.
+    pub fn put_bitop<CS: CodeSink + ?Sized>(bits: u16, rm: RegUnit, rd: RegUnit, sink: &mut CS) {
+        let bits = u32::from(bits);
+        put32(
+            (bits & 0xfff0 | rn(rm)) << 16 | 0xf000 | rn(rd) << 8 | (bits & 0xf) << 4 | rn(rm),
+            sink,
+        );
+    }
+
+    /// Sign and zero extensions.
+    ///
+    /// Encoding bits: the first halfword.
+    pub fn put_extend<CS: CodeSink + ?Sized>(bits: u16, rm: RegUnit, rd: RegUnit, sink: &mut CS) {
+        let bits = u32::from(bits);
+        put32(bits << 16 | 0xf080 | rn(rd) << 8 | rn(rm), sink);
+    }
+
+    /// Materialize an immediate with `movw` followed by `movt`, or with `mvn`.
+    pub fn put_iconst<CS: CodeSink + ?Sized>(imm: u32, rd: RegUnit, sink: &mut CS) {
+        if imm >> 16 != 0 && !imm <= 0xff {
+            // mvn rd, #imm
+            put32(0xf06f_0000 | rn(rd) << 8 | !imm, sink);
+            return;
+        }
+        put32(0xf240_0000 | rn(rd) << 8 | imm16_fields(imm & 0xffff), sink);
+        if imm >> 16 != 0 {
+            put32(0xf2c0_0000 | rn(rd) << 8 | imm16_fields(imm >> 16), sink);
+        }
+    }
+
+    /// Add an immediate to `rn`, where `rn` and `rd` may be the stack pointer.
+    pub fn put_add_imm<CS: CodeSink + ?Sized>(rn_: RegUnit, imm: i64, rd: RegUnit, sink: &mut CS) {
+        if imm.wrapping_abs() as u64 <= 0xff {
+            put_addsub_imm(ADD_IMM, rn_, imm, rd, sink);
+        } else {
+            put_iconst(imm.wrapping_abs() as u32, scratch(), sink);
+            put_dp(if imm < 0 { SUB } else { ADD }, rn_, scratch(), rd, sink);
+        }
+    }
+
+    /// `mov<cond> rd, rm`, with the 16-bit `mov` that accepts all registers.
+    pub fn put_mov_cond<CS: CodeSink + ?Sized>(cond: u32, rm: RegUnit, rd: RegUnit, sink: &mut CS) {
+        put_it(cond, sink);
+        sink.put2((0x4600 | (rn(rd) & 8) << 4 | rn(rm) << 3 | rn(rd) & 7) as u16);
+    }
+
+    /// `mov.w rd, #imm8`.
+    fn put_movi<CS: CodeSink + ?Sized>(imm8: u32, rd: RegUnit, sink: &mut CS) {
+        put32(0xf04f_0000 | rn(rd) << 8 | imm8, sink);
+    }
+
+    /// Set `rd` to 1 if `cond` holds, and to 0 otherwise.
+    pub fn put_cset<CS: CodeSink + ?Sized>(cond: u32, rd: RegUnit, sink: &mut CS) {
+        put_ite(cond, sink);
+        put_movi(1, rd, sink);
+        put_movi(0, rd, sink);
+    }
+
+    /// Set `rd` to 1 if either condition holds, and to 0 otherwise.
+    pub fn put_cset2<CS: CodeSink + ?Sized>(cond1: u32, cond2: u32, rd: RegUnit, sink: &mut CS) {
+        put_cset(cond1, rd, sink);
+        put_it(cond2, sink);
+        put_movi(1, rd, sink);
+    }
+
+    /// `tst.w rn, #1`, testing a boolean.
+    pub fn put_tst_bool<CS: CodeSink + ?Sized>(rn_: RegUnit, sink: &mut CS) {
+        put32(0xf010_0f01 | rn(rn_) << 16, sink);
+    }
+
+    /// Loads and stores can encode positive offsets within 4095 bytes, and negative offsets
+    /// within 255 bytes.
+    fn ldst_fits(offset: i64) -> bool {
+        offset >= -0xff && offset <= 0xfff
+    }
+
+    /// The size in bytes of a load or store with an immediate `offset`.
+    pub fn ldst_size(_bits: u16, offset: i64) -> u8 {
+        if ldst_fits(offset) {
+            4
+        } else {
+            iconst_size(offset as u32) + 4
+        }
+    }
+
+    /// Prepare the offset of a load or store: returns the immediate offset if it fits in the
+    /// instruction, or materializes it in the scratch register.
+    pub fn ldst_prepare<CS: CodeSink + ?Sized>(
+        _bits: u16,
+        offset: i64,
+        sink: &mut CS,
+    ) -> Option<i64> {
+        if ldst_fits(offset) {
+            Some(offset)
+        } else {
+            put_iconst(offset as u32, scratch(), sink);
+            None
+        }
+    }
+
+    /// A load or store with the offset prepared by `ldst_prepare`.
+    ///
+    /// Encoding bits: the first halfword of the 12-bit immediate offset form.
+    pub fn put_ldst<CS: CodeSink + ?Sized>(
+        bits: u16,
+        rn_: RegUnit,
+        offset: Option<i64>,
+        rt: RegUnit,
+        sink: &mut CS,
+    ) {
+        let bits = u32::from(bits);
+        let word = match offset {
+            Some(offset) if offset >= 0 => bits << 16 | offset as u32,
+            // The 8-bit negative offset form.
+            Some(offset) => (bits & !0x80) << 16 | 0xc00 | (-offset) as u32,
+            // The register offset form.
+            None => (bits & !0x80) << 16 | rn(scratch()),
+        };
+        put32(word | rn(rn_) << 16 | rn(rt) << 12, sink);
+    }
+
+    /// `ldr.w rd, [rn, rm, lsl #2]`.
+    pub fn put_ldr_scaled<CS: CodeSink + ?Sized>(
+        rn_: RegUnit,
+        rm: RegUnit,
+        rd: RegUnit,
+        sink: &mut CS,
+    ) {
+        put32(0xf850_0020 | rn(rn_) << 16 | rn(rd) << 12 | rn(rm), sink);
+    }
+
+    /// Prepare the base and offset of a VFP load or store, computing the address in the scratch
+    /// register if the offset doesn't fit in the instruction.
+    pub fn vldst_prepare<CS: CodeSink + ?Sized>(
+        rn_: RegUnit,
+        offset: i64,
+        sink: &mut CS,
+    ) -> (RegUnit, i64) {
+        if vldst_fits(offset) {
+            (rn_, offset)
+        } else {
+            put_add_imm(rn_, offset, scratch(), sink);
+            (scratch(), 0)
+        }
+    }
+
+    /// `vldr` and `vstr`.
+    pub fn put_vldst<CS: CodeSink + ?Sized>(
+        bits: u16,
+        rn_: RegUnit,
+        offset: i64,
+        vd: VReg,
+        sink: &mut CS,
+    ) {
+        put32(vldst_word(bits, rn_, offset, vd), sink);
+    }
+
+    /// `str.w rt, [sp, #-4]!`.
+    pub fn put_push<CS: CodeSink + ?Sized>(rt: RegUnit, sink: &mut CS) {
+        put32(0xf84d_0d04 | rn(rt) << 12, sink);
+    }
+
+    /// `ldr.w rt, [sp], #4`.
+    pub fn put_pop<CS: CodeSink + ?Sized>(rt: RegUnit, sink: &mut CS) {
+        put32(0xf85d_0b04 | rn(rt) << 12, sink);
+    }
+
+    /// `vpush {dd}`.
+    pub fn put_vpush<CS: CodeSink + ?Sized>(dd: VReg, sink: &mut CS) {
+        put32(0xed2d_0b02 | dd.1 << 22 | dd.0 << 12, sink);
+    }
+
+    /// `vpop {dd}`.
+    pub fn put_vpop<CS: CodeSink + ?Sized>(dd: VReg, sink: &mut CS) {
+        put32(0xecbd_0b02 | dd.1 << 22 | dd.0 << 12, sink);
+    }
+
+    /// The `S:imm10` and `J1:J2:imm11` fields of `b.w` and `bl` for a displacement from the
+    /// start of the instruction. The PC reads 4 bytes ahead.
+    fn branch24_fields(disp: i64) -> u32 {
+        let imm = ((disp - 4) >> 1) as u32;
+        let s = (imm >> 23) & 1;
+        let j1 = !((imm >> 22) ^ s) & 1;
+        let j2 = !((imm >> 21) ^ s) & 1;
+        s << 26 | ((imm >> 11) & 0x3ff) << 16 | j1 << 13 | j2 << 11 | (imm & 0x7ff)
+    }
+
+    /// `b.w` to a displacement from the start of the instruction.
+    pub fn put_b<CS: CodeSink + ?Sized>(disp: i64, sink: &mut CS) {
+        put32(0xf000_9000 | branch24_fields(disp), sink);
+    }
+
+    /// `b<cond>.w` to a displacement from the start of the instruction.
+    pub fn put_b_cond<CS: CodeSink + ?Sized>(cond: u32, disp: i64, sink: &mut CS) {
+        let imm = ((disp - 4) >> 1) as u32;
+        let s = (imm >> 19) & 1;
+        let j2 = (imm >> 18) & 1;
+        let j1 = (imm >> 17) & 1;
+        put32(
+            0xf000_8000
+                | s << 26
+                | cond << 22
+                | ((imm >> 11) & 0x3f) << 16
+                | j1 << 13
+                | j2 << 11
+                | (imm & 0x7ff),
+            sink,
+        );
+    }
+
+    /// `b<cond>.n` to a short displacement from the start of the instruction.
+    pub fn put_b_cond_narrow<CS: CodeSink + ?Sized>(cond: u32, disp: i64, sink: &mut CS) {
+        let imm8 = (((disp - 4) >> 1) as u32) & 0xff;
+        sink.put2((0xd000 | cond << 8 | imm8) as u16);
+    }
+
+    /// `bl` to a function, with the addend of a `bl .` so the relocation works with both `REL`
+    /// and `RELA` object files.
+    pub fn put_bl<CS: CodeSink + ?Sized>(name: &ExternalName, sink: &mut CS) {
+        sink.reloc_external(Reloc::Arm32ThmCall, name, -4);
+        put32(0xf000_d000 | branch24_fields(0), sink);
+    }
+
+    /// `blx rm`.
+    pub fn put_blx<CS: CodeSink + ?Sized>(rm: RegUnit, sink: &mut CS) {
+        sink.put2((0x4780 | rn(rm) << 3) as u16);
+    }
+
+    /// `bx rm`.
+    pub fn put_bx<CS: CodeSink + ?Sized>(rm: RegUnit, sink: &mut CS) {
+        sink.put2((0x4700 | rn(rm) << 3) as u16);
+    }
+
+    /// Jump to the address in `rm` with `mov pc, rm`, which stays in Thumb state as opposed to
+    /// `bx`.
+    pub fn put_indirect_jump<CS: CodeSink + ?Sized>(rm: RegUnit, sink: &mut CS) {
+        sink.put2((0x4687 | rn(rm) << 3) as u16);
+    }
+
+    /// `movw` and `movt` of the absolute address of a symbol.
+    pub fn put_movw_movt<CS: CodeSink + ?Sized>(name: &ExternalName, rd: RegUnit, sink: &mut CS) {
+        sink.reloc_external(Reloc::Arm32ThmMovwAbsNc, name, 0);
+        put32(0xf240_0000 | rn(rd) << 8, sink);
+        sink.reloc_external(Reloc::Arm32ThmMovtAbs, name, 0);
+        put32(0xf2c0_0000 | rn(rd) << 8, sink);
+    }
+
+    /// Compute the address at a displacement from the start of the sequence: `movw` and `movt`
+    /// of the distance from the PC, followed by `add rd, pc`.
+    pub fn put_pc_relative<CS: CodeSink + ?Sized>(disp: i64, rd: RegUnit, sink: &mut CS) {
+        let offset = (disp - 12) as u32;
+        put32(
+            0xf240_0000 | rn(rd) << 8 | imm16_fields(offset & 0xffff),
+            sink,
+        );
+        put32(0xf2c0_0000 | rn(rd) << 8 | imm16_fields(offset >> 16), sink);
+        sink.put2((0x4478 | (rn(rd) & 8) << 4 | rn(rd) & 7) as u16);
+    }
+
+    /// `udf #0`.
+    pub fn put_udf<CS: CodeSink + ?Sized>(sink: &mut CS) {
+        sink.put2(UDF);
+    }
+
+    /// `bkpt #0`.
+    pub fn put_bkpt<CS: CodeSink + ?Sized>(sink: &mut CS) {
+        sink.put2(BKPT);
+    }
+
+    /// Trap with `code` unless `cond` holds, by branching over an `udf`.
+    pub fn put_trap_unless<CS: CodeSink + ?Sized>(
+        cond: u32,
+        code: TrapCode,
+        srcloc: SourceLoc,
+        sink: &mut CS,
+    ) {
+        put_b_cond_narrow(cond, 4, sink);
+        sink.trap(code, srcloc);
+        put_udf(sink);
+    }
+
+    /// Trap with `code` if either condition holds.
+    pub fn put_trap_if_either<CS: CodeSink + ?Sized>(
+        cond1: u32,
+        cond2: u32,
+        code: TrapCode,
+        srcloc: SourceLoc,
+        sink: &mut CS,
+    ) {
+        put_b_cond_narrow(cond1, 4, sink);
+        put_b_cond_narrow(cond2 ^ 1, 4, sink);
+        sink.trap(code, srcloc);
+        put_udf(sink);
+    }
+
+    /// VFP data processing.
+    pub fn put_vfp<CS: CodeSink + ?Sized>(bits: u16, vd: VReg, vn: VReg, vm: VReg, sink: &mut CS) {
+        put32(vfp_word(bits, vd, vn, vm), sink);
+    }
+
+    /// VFP data processing with one source, only executed if `cond` holds.
+    pub fn put_vfp_cond<CS: CodeSink + ?Sized>(
+        bits: u16,
+        cond: u32,
+        vd: VReg,
+        vm: VReg,
+        sink: &mut CS,
+    ) {
+        put_it(cond, sink);
+        put32(vfp_word(bits, vd, NO_VREG, vm), sink);
+    }
+
+    /// `vmrs APSR_nzcv, fpscr`, copying the floating point flags.
+    pub fn put_vmrs<CS: CodeSink + ?Sized>(sink: &mut CS) {
+        put32(0xeef1_fa10, sink);
+    }
+
+    /// `vmov` between a core register and a single precision register.
+    ///
+    /// Encoding bits: `word[20]`, set for moves to the core register.
+    pub fn put_vmov_core<CS: CodeSink + ?Sized>(bits: u16, rt: RegUnit, sn: VReg, sink: &mut CS) {
+        let op = u32::from(bits);
+        put32(
+            0xee00_0a10 | op << 20 | sn.0 << 16 | rn(rt) << 12 | sn.1 << 7,
+            sink,
+        );
+    }
+
+    /// `vmov` between two core registers and a double precision register.
+    ///
+    /// Encoding bits: `word[20]`, set for moves to the core registers.
+    pub fn put_vmov_pair<CS: CodeSink + ?Sized>(
+        bits: u16,
+        rt: RegUnit,
+        rt2: RegUnit,
+        dm: VReg,
+        sink: &mut CS,
+    ) {
+        let op = u32::from(bits);
+        put32(
+            0xec40_0b10 | op << 20 | rn(rt2) << 16 | rn(rt) << 12 | dm.1 << 5 | dm.0,
+            sink,
+        );
+    }
+}
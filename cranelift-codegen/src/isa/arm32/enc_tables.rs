@@ -1,10 +1,356 @@
 //! Encoding tables for ARM32 ISA.
 
-use crate::ir;
+use super::binemit::{a32, add_imm_size, iconst_size, t32, vldst_size};
+use super::registers::*;
+use crate::bitset::BitSet;
+use crate::cursor::{Cursor, FuncCursor};
+use crate::flowgraph::ControlFlowGraph;
+use crate::ir::condcodes::{FloatCC, IntCC};
+use crate::ir::{self, Function, Inst, InstBuilder, InstructionData, StackSlot, ValueLoc};
 use crate::isa;
 use crate::isa::constraints::*;
 use crate::isa::enc_tables::*;
-use crate::isa::encoding::RecipeSizing;
+use crate::isa::encoding::base_size;
+use crate::isa::encoding::{Encoding, RecipeSizing};
+use crate::isa::{StackBaseMask, StackRef, TargetIsa};
+use crate::legalizer::{expand_as_libcall, split};
+use crate::predicates;
+use crate::regalloc::RegDiversions;
 
 include!(concat!(env!("OUT_DIR"), "/encoding-arm32.rs"));
 include!(concat!(env!("OUT_DIR"), "/legalize-arm32.rs"));
+
+/// Accounts for the `movt` instruction needed by most immediates.
+fn size_iconst(
+    _sizing: &RecipeSizing,
+    _enc: Encoding,
+    inst: Inst,
+    _divert: &RegDiversions,
+    func: &Function,
+) -> u8 {
+    match func.dfg[inst] {
+        InstructionData::UnaryImm { imm, .. } => {
+            let imm: i64 = imm.into();
+            iconst_size(imm as u32)
+        }
+        _ => panic!("Expected UnaryImm: {}", func.dfg.display_inst(inst, None)),
+    }
+}
+
+/// Accounts for materializing a comparison immediate in the scratch register.
+fn size_ifcmp_imm_ip(
+    _sizing: &RecipeSizing,
+    _enc: Encoding,
+    inst: Inst,
+    _divert: &RegDiversions,
+    func: &Function,
+) -> u8 {
+    match func.dfg[inst] {
+        InstructionData::BinaryImm { imm, .. } => {
+            let imm: i64 = imm.into();
+            iconst_size(imm as u32) + 4
+        }
+        _ => panic!("Expected BinaryImm: {}", func.dfg.display_inst(inst, None)),
+    }
+}
+
+/// Get the immediate offset of a load or store.
+fn ldst_offset(inst: Inst, func: &Function) -> i64 {
+    match func.dfg[inst] {
+        InstructionData::Load { offset, .. } | InstructionData::Store { offset, .. } => {
+            let offset: i32 = offset.into();
+            offset.into()
+        }
+        _ => panic!(
+            "Expected Load or Store: {}",
+            func.dfg.display_inst(inst, None)
+        ),
+    }
+}
+
+/// Accounts for A32 loads and stores with an offset that doesn't fit in the instruction.
+fn size_ldst(
+    _sizing: &RecipeSizing,
+    enc: Encoding,
+    inst: Inst,
+    _divert: &RegDiversions,
+    func: &Function,
+) -> u8 {
+    a32::ldst_size(enc.bits(), ldst_offset(inst, func))
+}
+
+/// Accounts for Thumb-2 loads and stores with an offset that doesn't fit in the instruction.
+fn t32_size_ldst(
+    _sizing: &RecipeSizing,
+    enc: Encoding,
+    inst: Inst,
+    _divert: &RegDiversions,
+    func: &Function,
+) -> u8 {
+    t32::ldst_size(enc.bits(), ldst_offset(inst, func))
+}
+
+/// Accounts for floating point loads and stores with an offset that doesn't fit in the
+/// instruction.
+fn size_vldst(
+    _sizing: &RecipeSizing,
+    _enc: Encoding,
+    inst: Inst,
+    _divert: &RegDiversions,
+    func: &Function,
+) -> u8 {
+    vldst_size(ldst_offset(inst, func))
+}
+
+/// Get the offset of the stack slot accessed by a spill or a fill, or `None` if the frame hasn't
+/// been laid out yet.
+fn stack_ldst_offset(inst: Inst, divert: &RegDiversions, func: &Function) -> Option<i64> {
+    let slot: Option<StackSlot> = match func.dfg[inst] {
+        InstructionData::RegSpill { dst, .. } => Some(dst),
+        InstructionData::RegFill { src, .. } => Some(src),
+        _ => {
+            let value = match func.dfg.inst_results(inst).first() {
+                Some(&result) => result,
+                None => func.dfg.inst_args(inst)[0],
+            };
+            let value = match divert.get(value, &func.locations) {
+                ValueLoc::Stack(_) => value,
+                _ => func.dfg.inst_args(inst)[0],
+            };
+            match divert.get(value, &func.locations) {
+                ValueLoc::Stack(ss) => Some(ss),
+                _ => None,
+            }
+        }
+    };
+    slot.and_then(|ss| StackRef::masked(ss, StackBaseMask(3), &func.stack_slots))
+        .map(|stk| stk.offset.into())
+}
+
+/// Accounts for A32 spills and fills to stack slots that are too far from the stack pointer.
+fn size_stack_ldst(
+    _sizing: &RecipeSizing,
+    enc: Encoding,
+    inst: Inst,
+    divert: &RegDiversions,
+    func: &Function,
+) -> u8 {
+    // Until the frame is laid out, assume the worst.
+    let offset = stack_ldst_offset(inst, divert, func).unwrap_or(core::i32::MAX.into());
+    a32::ldst_size(enc.bits(), offset)
+}
+
+/// Accounts for Thumb-2 spills and fills to stack slots that are too far from the stack pointer.
+fn t32_size_stack_ldst(
+    _sizing: &RecipeSizing,
+    enc: Encoding,
+    inst: Inst,
+    divert: &RegDiversions,
+    func: &Function,
+) -> u8 {
+    let offset = stack_ldst_offset(inst, divert, func).unwrap_or(core::i32::MAX.into());
+    t32::ldst_size(enc.bits(), offset)
+}
+
+/// Accounts for floating point spills and fills to stack slots that are too far from the stack
+/// pointer.
+fn size_stack_vldst(
+    _sizing: &RecipeSizing,
+    _enc: Encoding,
+    inst: Inst,
+    divert: &RegDiversions,
+    func: &Function,
+) -> u8 {
+    let offset = stack_ldst_offset(inst, divert, func).unwrap_or(core::i32::MAX.into());
+    vldst_size(offset)
+}
+
+/// Accounts for stack slot addresses that don't fit in an `add` immediate.
+fn size_stack_addr(
+    _sizing: &RecipeSizing,
+    _enc: Encoding,
+    inst: Inst,
+    _divert: &RegDiversions,
+    func: &Function,
+) -> u8 {
+    match func.dfg[inst] {
+        InstructionData::StackLoad {
+            stack_slot, offset, ..
+        } => match StackRef::masked(stack_slot, StackBaseMask(3), &func.stack_slots) {
+            Some(stk) => {
+                let offset: i64 = offset.into();
+                add_imm_size(i64::from(stk.offset) + offset)
+            }
+            None => add_imm_size(i64::from(core::i32::MAX)),
+        },
+        _ => panic!("Expected StackLoad: {}", func.dfg.display_inst(inst, None)),
+    }
+}
+
+/// Accounts for stack adjustments that don't fit in an `add` immediate.
+fn size_adjust_sp(
+    _sizing: &RecipeSizing,
+    _enc: Encoding,
+    inst: Inst,
+    _divert: &RegDiversions,
+    func: &Function,
+) -> u8 {
+    match func.dfg[inst] {
+        InstructionData::UnaryImm { imm, .. } => add_imm_size(imm.into()),
+        _ => panic!("Expected UnaryImm: {}", func.dfg.display_inst(inst, None)),
+    }
+}
+
+/// Expand a division that has no instruction into a library call, guarded by the traps that the
+/// library routines don't have.
+fn expand_div_libcall(
+    inst: ir::Inst,
+    func: &mut ir::Function,
+    _cfg: &mut ControlFlowGraph,
+    isa: &dyn TargetIsa,
+) {
+    let (opcode, x, y) = match func.dfg[inst] {
+        ir::InstructionData::Binary { opcode, args } => (opcode, args[0], args[1]),
+        _ => panic!("Need a division: {}", func.dfg.display_inst(inst, None)),
+    };
+    let ty = func.dfg.ctrl_typevar(inst);
+
+    let mut pos = FuncCursor::new(func).at_inst(inst);
+    pos.use_srcloc(inst);
+
+    // Wider divisors are tested for zero one register at a time.
+    let divisor = if ty.bits() > 32 {
+        let (lo, hi) = pos.ins().isplit(y);
+        pos.ins().bor(lo, hi)
+    } else {
+        y
+    };
+    pos.ins()
+        .trapz(divisor, ir::TrapCode::IntegerDivisionByZero);
+
+    // Dividing the smallest integer by -1 overflows. The remainder is 0.
+    if opcode == ir::Opcode::Sdiv {
+        let min = -1i64 << (ty.bits() - 1);
+        let is_neg_one = pos.ins().icmp_imm(IntCC::Equal, y, -1);
+        let is_min = pos.ins().icmp_imm(IntCC::Equal, x, min);
+        let overflow = pos.ins().band(is_neg_one, is_min);
+        pos.ins().trapnz(overflow, ir::TrapCode::IntegerOverflow);
+    }
+
+    let converted = expand_as_libcall(inst, pos.func, isa);
+    debug_assert!(converted, "no library call for {}", opcode);
+}
+
+/// Expand `fcvt_to_sint` into a saturating conversion guarded by traps for NaN and out of range
+/// inputs.
+fn expand_fcvt_to_sint(
+    inst: ir::Inst,
+    func: &mut ir::Function,
+    _cfg: &mut ControlFlowGraph,
+    _isa: &dyn TargetIsa,
+) {
+    use crate::ir::immediates::{Ieee32, Ieee64};
+
+    let x = match func.dfg[inst] {
+        ir::InstructionData::Unary {
+            opcode: ir::Opcode::FcvtToSint,
+            arg,
+        } => arg,
+        _ => panic!("Need fcvt_to_sint: {}", func.dfg.display_inst(inst, None)),
+    };
+    let xty = func.dfg.value_type(x);
+    let ty = func.dfg.ctrl_typevar(inst);
+    let output_bits = ty.lane_bits();
+
+    let mut pos = FuncCursor::new(func).at_inst(inst);
+    pos.use_srcloc(inst);
+
+    let is_nan = pos.ins().fcmp(FloatCC::Unordered, x, x);
+    pos.ins()
+        .trapnz(is_nan, ir::TrapCode::BadConversionToInteger);
+
+    // When the input type can represent `INT_MIN - 1` exactly, anything above it converts
+    // correctly to `INT_MIN`.
+    let mut overflow_cc = FloatCC::LessThan;
+    let (flimit, fupper) = match xty {
+        ir::types::F32 => {
+            let flimit = if output_bits < 32 {
+                overflow_cc = FloatCC::LessThanOrEqual;
+                Ieee32::fcvt_to_sint_negative_overflow(output_bits)
+            } else {
+                Ieee32::pow2(output_bits - 1).neg()
+            };
+            (
+                pos.ins().f32const(flimit),
+                pos.ins().f32const(Ieee32::pow2(output_bits - 1)),
+            )
+        }
+        ir::types::F64 => {
+            let flimit = if output_bits < 64 {
+                overflow_cc = FloatCC::LessThanOrEqual;
+                Ieee64::fcvt_to_sint_negative_overflow(output_bits)
+            } else {
+                Ieee64::pow2(output_bits - 1).neg()
+            };
+            (
+                pos.ins().f64const(flimit),
+                pos.ins().f64const(Ieee64::pow2(output_bits - 1)),
+            )
+        }
+        _ => panic!("Can't convert {}", xty),
+    };
+    let overflow = pos.ins().fcmp(overflow_cc, x, flimit);
+    pos.ins().trapnz(overflow, ir::TrapCode::IntegerOverflow);
+    let overflow = pos.ins().fcmp(FloatCC::GreaterThanOrEqual, x, fupper);
+    pos.ins().trapnz(overflow, ir::TrapCode::IntegerOverflow);
+
+    pos.func.dfg.replace(inst).fcvt_to_sint_sat(ty, x);
+}
+
+/// Expand `fcvt_to_uint` into a saturating conversion guarded by traps for NaN and out of range
+/// inputs.
+fn expand_fcvt_to_uint(
+    inst: ir::Inst,
+    func: &mut ir::Function,
+    _cfg: &mut ControlFlowGraph,
+    _isa: &dyn TargetIsa,
+) {
+    use crate::ir::immediates::{Ieee32, Ieee64};
+
+    let x = match func.dfg[inst] {
+        ir::InstructionData::Unary {
+            opcode: ir::Opcode::FcvtToUint,
+            arg,
+        } => arg,
+        _ => panic!("Need fcvt_to_uint: {}", func.dfg.display_inst(inst, None)),
+    };
+    let xty = func.dfg.value_type(x);
+    let ty = func.dfg.ctrl_typevar(inst);
+    let output_bits = ty.lane_bits();
+
+    let mut pos = FuncCursor::new(func).at_inst(inst);
+    pos.use_srcloc(inst);
+
+    let is_nan = pos.ins().fcmp(FloatCC::Unordered, x, x);
+    pos.ins()
+        .trapnz(is_nan, ir::TrapCode::BadConversionToInteger);
+
+    // Anything above -1.0 truncates to a value in range.
+    let (flimit, fupper) = match xty {
+        ir::types::F32 => (
+            pos.ins().f32const(Ieee32::with_float(-1.0)),
+            pos.ins().f32const(Ieee32::pow2(output_bits)),
+        ),
+        ir::types::F64 => (
+            pos.ins().f64const(Ieee64::with_float(-1.0)),
+            pos.ins().f64const(Ieee64::pow2(output_bits)),
+        ),
+        _ => panic!("Can't convert {}", xty),
+    };
+    let overflow = pos.ins().fcmp(FloatCC::LessThanOrEqual, x, flimit);
+    pos.ins().trapnz(overflow, ir::TrapCode::IntegerOverflow);
+    let overflow = pos.ins().fcmp(FloatCC::GreaterThanOrEqual, x, fupper);
+    pos.ins().trapnz(overflow, ir::TrapCode::IntegerOverflow);
+
+    pos.func.dfg.replace(inst).fcvt_to_uint_sat(ty, x);
+}
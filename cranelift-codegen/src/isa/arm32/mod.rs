@@ -15,6 +15,8 @@ use crate::isa::enc_tables::{self as shared_enc_tables, lookup_enclist, Encoding
 use crate::isa::Builder as IsaBuilder;
 use crate::isa::{EncInfo, RegClass, RegInfo, TargetIsa};
 use crate::regalloc;
+use crate::result::CodegenResult;
+use crate::timing;
 use alloc::borrow::Cow;
 use alloc::boxed::Box;
 use core::fmt;
@@ -69,6 +71,10 @@ impl TargetIsa for Isa {
         &self.triple
     }
 
+    fn uses_cpu_flags(&self) -> bool {
+        true
+    }
+
     fn flags(&self) -> &shared_settings::Flags {
         &self.shared_flags
     }
@@ -110,7 +116,7 @@ impl TargetIsa for Isa {
     }
 
     fn allocatable_registers(&self, func: &ir::Function) -> regalloc::RegisterSet {
-        abi::allocatable_registers(func)
+        abi::allocatable_registers(func, &self.isa_flags, abi::is_thumb(&self.triple))
     }
 
     #[cfg(feature = "testing_hooks")]
@@ -128,12 +134,17 @@ impl TargetIsa for Isa {
         emit_function(func, binemit::emit_inst, sink, self)
     }
 
+    fn prologue_epilogue(&self, func: &mut ir::Function) -> CodegenResult<()> {
+        let _tt = timing::prologue_epilogue();
+        abi::prologue_epilogue(func, self)
+    }
+
     fn unsigned_add_overflow_condition(&self) -> ir::condcodes::IntCC {
-        ir::condcodes::IntCC::UnsignedLessThan
+        ir::condcodes::IntCC::UnsignedGreaterThanOrEqual
     }
 
     fn unsigned_sub_overflow_condition(&self) -> ir::condcodes::IntCC {
-        ir::condcodes::IntCC::UnsignedGreaterThanOrEqual
+        ir::condcodes::IntCC::UnsignedLessThan
     }
 }
 
@@ -1,24 +1,209 @@
 //! ARM 64 ABI implementation.
+//!
+//! This follows the procedure call standard for the ARM 64-bit architecture (AAPCS64).
 
-use super::registers::{FPR, GPR};
+use super::registers::{FPR, GPR, RU};
+use crate::abi::{legalize_args, legalize_params, ArgAction, ArgAssigner, ValueConversion};
+use crate::cursor::{Cursor, CursorPosition, EncCursor};
 use crate::ir;
-use crate::isa::RegClass;
+use crate::ir::immediates::Imm64;
+use crate::ir::stackslot::StackOffset;
+use crate::ir::{
+    AbiParam, ArgumentExtension, ArgumentLoc, ArgumentPurpose, InstBuilder, StructLayout, ValueLoc,
+};
+use crate::isa::{CallConv, RegClass, RegUnit, TargetIsa};
 use crate::regalloc::RegisterSet;
+use crate::result::{CodegenError, CodegenResult};
 use crate::settings as shared_settings;
+use crate::stack_layout::layout_stack;
 use alloc::borrow::Cow;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use core::i32;
+
+/// Number of registers used for arguments and return values in each register bank.
+const ARG_REGS: usize = 8;
+
+/// The indirect result location register, holding the address of the memory for return values
+/// that don't fit in registers.
+const STRUCT_RETURN_REG: RU = RU::x8;
+
+/// The stack pointer is always 16-byte aligned.
+const STACK_ALIGNMENT: u32 = 16;
+
+/// Size of the frame record: the frame pointer and the link register.
+const FRAME_RECORD_SIZE: u32 = 16;
+
+#[derive(Clone)]
+struct Args {
+    gpr_used: usize,
+    fpr_used: usize,
+    offset: u32,
+    shared_flags: shared_settings::Flags,
+}
+
+impl Args {
+    fn new(shared_flags: &shared_settings::Flags) -> Self {
+        Self {
+            gpr_used: 0,
+            fpr_used: 0,
+            offset: 0,
+            shared_flags: shared_flags.clone(),
+        }
+    }
+
+    /// Allocate `size` bytes of the stack argument area with `align` alignment.
+    fn stack_slot(&mut self, size: u32, align: u32) -> ArgumentLoc {
+        self.offset = (self.offset + align - 1) & !(align - 1);
+        let loc = ArgumentLoc::Stack(self.offset as i32);
+        self.offset += size;
+        debug_assert!(self.offset <= i32::MAX as u32);
+        loc
+    }
+
+    /// Get the parts that a struct argument with `layout` is passed in by value, or `None` if it
+    /// is passed as a pointer to a copy.
+    ///
+    /// Structs of up to 16 bytes are passed in consecutive general purpose registers, or on the
+    /// stack if there aren't enough registers left. Homogeneous floating point aggregates would
+    /// go in floating point registers, but the layout doesn't describe the individual members,
+    /// so they are passed like any other struct.
+    fn struct_parts(&mut self, layout: &StructLayout) -> Option<Vec<AbiParam>> {
+        if layout.size == 0 || layout.size > 16 {
+            return None;
+        }
+
+        let words = (layout.size + 7) / 8;
+        let in_regs = self.gpr_used + words as usize <= ARG_REGS;
+        if !in_regs {
+            // Once a struct goes on the stack, no more arguments are passed in the general
+            // purpose registers.
+            self.gpr_used = ARG_REGS;
+        }
+        let base = if in_regs {
+            None
+        } else {
+            Some(self.stack_slot(words * 8, layout.align.max(8)))
+        };
+        let parts = (0..words)
+            .map(|word| {
+                let offset = word * 8;
+                let mut part =
+                    AbiParam::special(ir::types::I64, ArgumentPurpose::StructArgumentPart(offset));
+                if let Some(ArgumentLoc::Stack(base)) = base {
+                    part.location = ArgumentLoc::Stack(base + offset as i32);
+                }
+                part
+            })
+            .collect();
+        Some(parts)
+    }
+}
+
+impl ArgAssigner for Args {
+    fn assign(&mut self, arg: &AbiParam) -> ArgAction {
+        let ty = arg.value_type;
+
+        // Structs passed by value are either split into parts or passed by reference, which is
+        // handled like any other pointer argument.
+        if let ArgumentPurpose::StructArgument(layout) = arg.purpose {
+            if let Some(parts) = self.struct_parts(&layout) {
+                return ArgAction::Expand(parts);
+            }
+        }
+
+        // Vectors are passed in the SIMD registers, or split if SIMD is disabled.
+        if ty.is_vector() {
+            if !self.shared_flags.enable_simd() {
+                return ValueConversion::VectorSplit.into();
+            }
+            if self.fpr_used < ARG_REGS {
+                let reg = FPR.unit(self.fpr_used);
+                self.fpr_used += 1;
+                return ArgumentLoc::Reg(reg).into();
+            }
+            return self.stack_slot(16, 16).into();
+        }
+
+        // Large integers and booleans are broken down to fit in a register.
+        if !ty.is_float() && ty.bits() > 64 {
+            return ValueConversion::IntSplit.into();
+        }
+
+        // Small integers are extended to the size of a register.
+        if ty.is_int() && ty.bits() < 64 {
+            match arg.extension {
+                ArgumentExtension::None => {}
+                ArgumentExtension::Uext => return ValueConversion::Uext(ir::types::I64).into(),
+                ArgumentExtension::Sext => return ValueConversion::Sext(ir::types::I64).into(),
+            }
+        }
+
+        if ty.is_float() {
+            if self.fpr_used < ARG_REGS {
+                let reg = FPR.unit(self.fpr_used);
+                self.fpr_used += 1;
+                return ArgumentLoc::Reg(reg).into();
+            }
+        } else if self.gpr_used < ARG_REGS {
+            let reg = GPR.unit(self.gpr_used);
+            self.gpr_used += 1;
+            return ArgumentLoc::Reg(reg).into();
+        }
+
+        // Stack arguments take at least 8 bytes each.
+        self.stack_slot(8, 8).into()
+    }
+}
 
 /// Legalize `sig`.
 pub fn legalize_signature(
-    _sig: &mut Cow<ir::Signature>,
-    _flags: &shared_settings::Flags,
+    sig: &mut Cow<ir::Signature>,
+    flags: &shared_settings::Flags,
     _current: bool,
 ) {
-    unimplemented!()
+    let mut args = Args::new(flags);
+    let mut rets = Args::new(flags);
+
+    // Keep a copy of the return assigner, in case the return values have to be returned in
+    // memory instead.
+    let backup_rets = rets.clone();
+
+    if let Some(new_returns) = legalize_args(&sig.returns, &mut rets) {
+        if new_returns
+            .iter()
+            .filter(|r| r.purpose == ArgumentPurpose::Normal)
+            .any(|r| !r.location.is_reg())
+        {
+            // The caller passes the address of the memory for the return values in `x8`, and we
+            // return the same address in `x0`.
+            debug_assert!(!sig.uses_struct_return_param());
+            sig.to_mut().params.push(AbiParam::special_reg(
+                ir::types::I64,
+                ArgumentPurpose::StructReturn,
+                STRUCT_RETURN_REG as RegUnit,
+            ));
+
+            let mut rets = backup_rets;
+            let mut ret_ptr = AbiParam::special(ir::types::I64, ArgumentPurpose::StructReturn);
+            match rets.assign(&ret_ptr) {
+                ArgAction::Assign(loc @ ArgumentLoc::Reg(_)) => ret_ptr.location = loc,
+                _ => unreachable!("return pointer should always get a register assignment"),
+            }
+            let sig = sig.to_mut();
+            sig.returns.push(ret_ptr);
+            sig.returns.retain(|ret| ret.location.is_assigned());
+        } else {
+            sig.to_mut().returns = new_returns;
+        }
+    }
+
+    legalize_params(sig, &mut args);
 }
 
 /// Get register class for a type appearing in a legalized signature.
 pub fn regclass_for_abi_type(ty: ir::Type) -> RegClass {
-    if ty.is_int() {
+    if ty.is_int() || ty.is_bool() {
         GPR
     } else {
         FPR
@@ -26,6 +211,271 @@ pub fn regclass_for_abi_type(ty: ir::Type) -> RegClass {
 }
 
 /// Get the set of allocatable registers for `func`.
-pub fn allocatable_registers(_func: &ir::Function) -> RegisterSet {
-    unimplemented!()
+pub fn allocatable_registers(_func: &ir::Function, flags: &shared_settings::Flags) -> RegisterSet {
+    let mut regs = RegisterSet::new();
+
+    // `x16` and `x17` are scratch registers for the code we emit and for veneers inserted by the
+    // linker. `x18` is the platform register.
+    regs.take(GPR, RU::x16 as RegUnit);
+    regs.take(GPR, RU::x17 as RegUnit);
+    regs.take(GPR, RU::x18 as RegUnit);
+
+    // The frame pointer, the link register, and the stack pointer or zero register.
+    regs.take(GPR, RU::x29 as RegUnit);
+    regs.take(GPR, RU::x30 as RegUnit);
+    regs.take(GPR, RU::x31 as RegUnit);
+
+    // The pinned register is the last callee-saved register.
+    if flags.enable_pinned_reg() {
+        regs.take(GPR, RU::x28 as RegUnit);
+    }
+
+    regs
+}
+
+/// Get the callee-saved registers of `class` that are used in `func`. Only the low 64 bits of
+/// `v8`-`v15` are callee-saved, which is all a scalar floating point value occupies.
+fn callee_saved_regs_used(
+    func: &ir::Function,
+    isa: &dyn TargetIsa,
+) -> (Vec<RegUnit>, Vec<RegUnit>) {
+    let mut all_callee_saved = RegisterSet::empty();
+    for reg in 19..29 {
+        all_callee_saved.free(GPR, GPR.unit(reg));
+    }
+    if isa.flags().enable_pinned_reg() {
+        all_callee_saved.take(GPR, RU::x28 as RegUnit);
+    }
+    for reg in 8..16 {
+        all_callee_saved.free(FPR, FPR.unit(reg));
+    }
+
+    let mut used = RegisterSet::empty();
+    let mut mark_used = |ru: RegUnit| {
+        let rc = if GPR.contains(ru) {
+            GPR
+        } else if FPR.contains(ru) {
+            FPR
+        } else {
+            return;
+        };
+        if !used.is_avail(rc, ru) {
+            used.free(rc, ru);
+        }
+    };
+    for value_loc in func.locations.values() {
+        if let ValueLoc::Reg(ru) = *value_loc {
+            mark_used(ru);
+        }
+    }
+
+    // Values diverted by `regmove` and `regfill` aren't reflected in `func.locations`.
+    for ebb in &func.layout {
+        for inst in func.layout.ebb_insts(ebb) {
+            match func.dfg[inst] {
+                ir::InstructionData::RegMove { dst, .. }
+                | ir::InstructionData::RegFill { dst, .. } => mark_used(dst),
+                _ => (),
+            }
+        }
+    }
+
+    used.intersect(&all_callee_saved);
+    (used.iter(GPR).collect(), used.iter(FPR).collect())
+}
+
+/// Does `func` contain an instruction that the frame layout can't handle?
+fn unsupported_inst(func: &ir::Function) -> Option<&'static str> {
+    func.layout.ebbs().find_map(|ebb| {
+        func.layout.ebb_insts(ebb).find_map(|inst| {
+            let opcode = func.dfg[inst].opcode();
+            if opcode == ir::Opcode::DynamicStackAlloc {
+                Some("dynamic stack allocations on arm64")
+            } else if opcode.is_call() && opcode.is_terminator() {
+                Some("tail calls on arm64")
+            } else {
+                None
+            }
+        })
+    })
+}
+
+/// The size of the stack area needed to push `count` registers in pairs.
+fn pairs_size(count: usize) -> u32 {
+    ((count as u32 + 1) / 2) * 16
+}
+
+/// Insert the prologue and epilogues of `func`.
+///
+/// The prologue pushes the frame record and makes `x29` point to it, then pushes the callee-saved
+/// registers in pairs and finally allocates the rest of the frame.
+pub fn prologue_epilogue(func: &mut ir::Function, isa: &dyn TargetIsa) -> CodegenResult<()> {
+    if let Some(what) = unsupported_inst(func) {
+        return Err(CodegenError::Unsupported(what.to_string()));
+    }
+    match func.signature.call_conv {
+        CallConv::Tail | CallConv::WindowsFastcall | CallConv::Probestack => {
+            return Err(CodegenError::Unsupported(format!(
+                "the {} calling convention on arm64",
+                func.signature.call_conv
+            )));
+        }
+        _ => {}
+    }
+
+    let (gprs, fprs) = callee_saved_regs_used(func, isa);
+
+    let reserved_size = FRAME_RECORD_SIZE + pairs_size(gprs.len()) + pairs_size(fprs.len());
+    func.create_stack_slot(ir::StackSlotData {
+        kind: ir::StackSlotKind::IncomingArg,
+        size: reserved_size,
+        offset: Some(-(reserved_size as StackOffset)),
+    });
+
+    let is_leaf = func.is_leaf();
+    let total_size = layout_stack(&mut func.stack_slots, is_leaf, STACK_ALIGNMENT)?;
+    let local_size = i64::from(total_size - reserved_size);
+
+    // The saved registers are passed to the function and returned from it like the frame pointer
+    // and link register.
+    let mut saved = vec![
+        (
+            ir::types::I64,
+            ArgumentPurpose::FramePointer,
+            RU::x29 as RegUnit,
+        ),
+        (ir::types::I64, ArgumentPurpose::Link, RU::x30 as RegUnit),
+    ];
+    saved.extend(
+        gprs.iter()
+            .map(|&reg| (ir::types::I64, ArgumentPurpose::CalleeSaved, reg)),
+    );
+    saved.extend(
+        fprs.iter()
+            .map(|&reg| (ir::types::F64, ArgumentPurpose::CalleeSaved, reg)),
+    );
+    for &(ty, purpose, reg) in &saved {
+        let arg = AbiParam::special_reg(ty, purpose, reg);
+        func.signature.params.push(arg);
+        func.signature.returns.push(arg);
+    }
+
+    let entry_ebb = func.layout.entry_block().expect("missing entry block");
+    let mut pos = EncCursor::new(func, isa).at_first_insertion_point(entry_ebb);
+    insert_prologue(&mut pos, local_size, &saved);
+
+    let mut pos = pos.at_position(CursorPosition::Nowhere);
+    while let Some(ebb) = pos.next_ebb() {
+        pos.goto_last_inst(ebb);
+        if let Some(inst) = pos.current_inst() {
+            if pos.func.dfg[inst].opcode().is_return() {
+                insert_epilogue(inst, local_size, &mut pos, &saved);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Push `values` onto the stack in pairs.
+fn push_all(pos: &mut EncCursor, values: &[ir::Value]) {
+    for pair in values.chunks(2) {
+        match *pair {
+            [x, y] => pos.ins().arm64_push_pair(x, y),
+            [x] => pos.ins().arm64_push(x),
+            _ => unreachable!(),
+        };
+    }
+}
+
+/// Pop values of type `ty` into `regs`, undoing `push_all`.
+fn pop_all(pos: &mut EncCursor, ty: ir::Type, regs: &[RegUnit]) -> Vec<ir::Value> {
+    let mut values = Vec::with_capacity(regs.len());
+    for pair in regs.chunks(2).rev() {
+        match *pair {
+            [x, y] => {
+                let (vx, vy) = pos.ins().arm64_pop_pair(ty);
+                pos.func.locations[vx] = ValueLoc::Reg(x);
+                pos.func.locations[vy] = ValueLoc::Reg(y);
+                values.push(vy);
+                values.push(vx);
+            }
+            [x] => {
+                let vx = pos.ins().arm64_pop(ty);
+                pos.func.locations[vx] = ValueLoc::Reg(x);
+                values.push(vx);
+            }
+            _ => unreachable!(),
+        }
+    }
+    values.reverse();
+    values
+}
+
+/// Insert the prologue at the cursor, given the frame size below the saved registers and the
+/// registers in `saved`, starting with the frame record.
+fn insert_prologue(
+    pos: &mut EncCursor,
+    local_size: i64,
+    saved: &[(ir::Type, ArgumentPurpose, RegUnit)],
+) {
+    let ebb = pos.current_ebb().expect("missing ebb under cursor");
+    let values: Vec<_> = saved
+        .iter()
+        .map(|&(ty, _, reg)| {
+            let value = pos.func.dfg.append_ebb_param(ebb, ty);
+            pos.func.locations[value] = ValueLoc::Reg(reg);
+            value
+        })
+        .collect();
+
+    let (record, regs) = values.split_at(2);
+    pos.ins().arm64_push_pair(record[0], record[1]);
+    pos.ins()
+        .copy_special(RU::x31 as RegUnit, RU::x29 as RegUnit);
+
+    let num_gprs = saved
+        .iter()
+        .filter(|&&(ty, purpose, _)| purpose == ArgumentPurpose::CalleeSaved && ty.is_int())
+        .count();
+    let (gprs, fprs) = regs.split_at(num_gprs);
+    push_all(pos, gprs);
+    push_all(pos, fprs);
+
+    if local_size > 0 {
+        pos.func.prologue_end = Some(pos.ins().adjust_sp_down_imm(Imm64::new(local_size)));
+    }
+}
+
+/// Insert an epilogue before the return instruction `inst`, restoring the registers in `saved`
+/// and passing them to the return.
+fn insert_epilogue(
+    inst: ir::Inst,
+    local_size: i64,
+    pos: &mut EncCursor,
+    saved: &[(ir::Type, ArgumentPurpose, RegUnit)],
+) {
+    if local_size > 0 {
+        pos.ins().adjust_sp_up_imm(Imm64::new(local_size));
+    }
+
+    let (record, regs) = saved.split_at(2);
+    let (gprs, fprs): (Vec<_>, Vec<_>) = regs.iter().partition(|&&(ty, _, _)| ty.is_int());
+    let fprs: Vec<_> = fprs.iter().map(|&&(_, _, reg)| reg).collect();
+    let gprs: Vec<_> = gprs.iter().map(|&&(_, _, reg)| reg).collect();
+
+    let fpr_values = pop_all(pos, ir::types::F64, &fprs);
+    let gpr_values = pop_all(pos, ir::types::I64, &gprs);
+    let (fp, lr) = pos.ins().arm64_pop_pair(ir::types::I64);
+    pos.func.locations[fp] = ValueLoc::Reg(record[0].2);
+    pos.func.locations[lr] = ValueLoc::Reg(record[1].2);
+
+    // The return arguments follow the order of the signature.
+    for value in [fp, lr]
+        .iter()
+        .chain(gpr_values.iter())
+        .chain(fpr_values.iter())
+    {
+        pos.func.dfg.append_inst_arg(inst, *value);
+    }
 }
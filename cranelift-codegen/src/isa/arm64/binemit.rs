@@ -1,8 +1,742 @@
 //! Emitting binary ARM64 machine code.
 
-use crate::binemit::{bad_encoding, CodeSink};
-use crate::ir::{Function, Inst};
-use crate::isa::TargetIsa;
+use super::registers::RU;
+use crate::binemit::{bad_encoding, CodeOffset, CodeSink, Reloc};
+use crate::ir::condcodes::{FloatCC, IntCC};
+use crate::ir::immediates::Imm64;
+use crate::ir::{ExternalName, Function, Inst, InstructionData, SourceLoc, StackSlot, TrapCode};
+use crate::isa::{RegUnit, StackBase, StackBaseMask, StackRef, TargetIsa};
 use crate::regalloc::RegDiversions;
 
 include!(concat!(env!("OUT_DIR"), "/binemit-arm64.rs"));
+
+/// Encoding bits of the unconditional branch `b`.
+const B: u16 = 0x05;
+
+/// Bit distinguishing `cbnz` from `cbz`.
+const CB_NZ: u16 = 1;
+
+/// Opcode of `clz` in the data processing with one source encoding bits.
+const DP1_CLZ: u16 = 0b100;
+
+/// Bit distinguishing the unsigned multiplications from the signed ones in `op31`.
+const DP3_U: u16 = 1 << 3;
+
+/// `ubfm` and `sbfm` on 64-bit registers.
+const BF_UBFM64: u16 = 0x34d;
+const BF_SBFM64: u16 = 0x24d;
+
+/// The `ne` condition code.
+const COND_NE: u32 = 0b0001;
+
+/// The `vc` condition code.
+const COND_VC: u32 = 0b0111;
+
+/// `brk #0xf000`, which debuggers recognize as a breakpoint.
+const BRK_DEBUG: u32 = 0xd43e_0000;
+
+/// `udf #0`, the permanently undefined instruction.
+const UDF: u32 = 0;
+
+// Moves between vector lanes and registers, as `imm5` is filled in by `put_lane`.
+const DUP_GENERAL: u32 = 0x4e00_0c00;
+const DUP_ELEMENT: u32 = 0x4e00_0400;
+const UMOV: u32 = 0x0e00_3c00;
+const DUP_SCALAR: u32 = 0x5e00_0400;
+const INS_GENERAL: u32 = 0x4e00_1c00;
+const INS_ELEMENT: u32 = 0x6e00_0400;
+
+// The scratch register used to materialize large offsets and immediates.
+fn scratch() -> RegUnit {
+    RU::x16.into()
+}
+
+// Convert a stack base to the corresponding register.
+fn stk_base(base: StackBase) -> RegUnit {
+    let ru = match base {
+        StackBase::SP => RU::x31,
+        StackBase::FP => RU::x29,
+        StackBase::Zone => unimplemented!(),
+    };
+    ru as RegUnit
+}
+
+// Get a reference to the stack slot `ss`, relative to the frame pointer if the stack pointer of
+// `func` isn't fixed.
+fn stk_ref(ss: StackSlot, func: &Function) -> StackRef {
+    StackRef::masked(ss, StackBaseMask(3), &func.stack_slots).unwrap()
+}
+
+// The displacement from the current position of `sink` to `dest`.
+fn branch_disp<CS: CodeSink + ?Sized>(dest: CodeOffset, sink: &CS) -> i64 {
+    i64::from(dest) - i64::from(sink.offset())
+}
+
+// Register number as encoded in an instruction. Both register banks have 32 registers.
+fn rn(reg: RegUnit) -> u32 {
+    u32::from(reg) & 0x1f
+}
+
+/// Convert an integer condition code to the A64 condition, assuming the flags were set by a
+/// subtraction.
+fn icc2cond(cond: IntCC) -> u32 {
+    use crate::ir::condcodes::IntCC::*;
+    match cond {
+        Equal => 0b0000,
+        NotEqual => 0b0001,
+        UnsignedGreaterThanOrEqual => 0b0010,
+        UnsignedLessThan => 0b0011,
+        Overflow => 0b0110,
+        NotOverflow => 0b0111,
+        UnsignedGreaterThan => 0b1000,
+        UnsignedLessThanOrEqual => 0b1001,
+        SignedGreaterThanOrEqual => 0b1010,
+        SignedLessThan => 0b1011,
+        SignedGreaterThan => 0b1100,
+        SignedLessThanOrEqual => 0b1101,
+    }
+}
+
+/// Convert a floating point condition code to a pair of A64 conditions, assuming the flags were
+/// set by `fcmp`. The condition holds if either of the pair holds. Only `one` and `ueq` need
+/// both; the others repeat the first condition.
+fn fcc2cond(cond: FloatCC) -> (u32, u32) {
+    use crate::ir::condcodes::FloatCC::*;
+    let single = |c| (c, c);
+    match cond {
+        Equal => single(0b0000),
+        NotEqual => single(0b0001),
+        LessThan => single(0b0100),
+        LessThanOrEqual => single(0b1001),
+        GreaterThan => single(0b1100),
+        GreaterThanOrEqual => single(0b1010),
+        Ordered => single(0b0111),
+        Unordered => single(0b0110),
+        UnorderedOrLessThan => single(0b1011),
+        UnorderedOrLessThanOrEqual => single(0b1101),
+        UnorderedOrGreaterThan => single(0b1000),
+        UnorderedOrGreaterThanOrEqual => single(0b0101),
+        OrderedNotEqual => (0b0100, 0b1100),
+        UnorderedOrEqual => (0b0000, 0b0110),
+    }
+}
+
+/// Data processing with three registers: the shifted register forms of `add`, `sub` and the
+/// logical instructions, and `adc` and `sbc`.
+///
+///   31     20 15      9  4
+///   opcode rm 000000 rn rd
+///
+/// Encoding bits: `word[31:21]`.
+fn put_rrr<CS: CodeSink + ?Sized>(
+    bits: u16,
+    rn_: RegUnit,
+    rm: RegUnit,
+    rd: RegUnit,
+    sink: &mut CS,
+) {
+    sink.put4(u32::from(bits) << 21 | rn(rm) << 16 | rn(rn_) << 5 | rn(rd));
+}
+
+/// The `sf` bit of the encoding bits of `put_rrr`, in the format of `put_iconst`.
+pub fn rrr_sf(bits: u16) -> u16 {
+    (bits >> 10) & 1
+}
+
+/// Data processing with two sources: `udiv`, `sdiv` and the variable shifts.
+///
+/// Encoding bits: `sf | opcode`.
+fn put_dp2<CS: CodeSink + ?Sized>(
+    bits: u16,
+    rn_: RegUnit,
+    rm: RegUnit,
+    rd: RegUnit,
+    sink: &mut CS,
+) {
+    let bits = u32::from(bits);
+    let sf = bits >> 6;
+    let opcode = bits & 0x3f;
+    sink.put4(sf << 31 | 0x1ac0_0000 | rn(rm) << 16 | opcode << 10 | rn(rn_) << 5 | rn(rd));
+}
+
+/// Data processing with one source: `rbit`, `clz` and `cls`.
+///
+/// Encoding bits: `sf | opcode`.
+fn put_dp1<CS: CodeSink + ?Sized>(bits: u16, rn_: RegUnit, rd: RegUnit, sink: &mut CS) {
+    let bits = u32::from(bits);
+    let sf = bits >> 6;
+    let opcode = bits & 0x3f;
+    sink.put4(sf << 31 | 0x5ac0_0000 | opcode << 10 | rn(rn_) << 5 | rn(rd));
+}
+
+/// Data processing with three sources: the multiplications.
+///
+/// Encoding bits: `sf | op31 | o0`.
+fn put_dp3<CS: CodeSink + ?Sized>(
+    bits: u16,
+    rn_: RegUnit,
+    rm: RegUnit,
+    ra: RegUnit,
+    rd: RegUnit,
+    sink: &mut CS,
+) {
+    let bits = u32::from(bits);
+    let sf = bits >> 4;
+    let op31 = (bits >> 1) & 0x7;
+    let o0 = bits & 1;
+    sink.put4(
+        sf << 31
+            | 0x1b00_0000
+            | op31 << 21
+            | rn(rm) << 16
+            | o0 << 15
+            | rn(ra) << 10
+            | rn(rn_) << 5
+            | rn(rd),
+    );
+}
+
+/// `msub rd, rn, rm, ra`, computing `ra - rn * rm`. The width is taken from the `put_dp2` bits
+/// of the preceding division.
+fn put_msub<CS: CodeSink + ?Sized>(
+    dp2_bits: u16,
+    rn_: RegUnit,
+    rm: RegUnit,
+    ra: RegUnit,
+    rd: RegUnit,
+    sink: &mut CS,
+) {
+    let sf = u32::from(dp2_bits) >> 6;
+    sink.put4(sf << 31 | 0x1b00_8000 | rn(rm) << 16 | rn(ra) << 10 | rn(rn_) << 5 | rn(rd));
+}
+
+/// Trap with `IntegerDivisionByZero` unless `rt` is non-zero. The width is taken from the
+/// `put_dp2` bits of the division.
+fn put_trap_if_zero<CS: CodeSink + ?Sized>(
+    dp2_bits: u16,
+    rt: RegUnit,
+    srcloc: SourceLoc,
+    sink: &mut CS,
+) {
+    let sf = u32::from(dp2_bits) >> 6;
+    // cbnz rt, +8
+    sink.put4(sf << 31 | 0x3500_0000 | 2 << 5 | rn(rt));
+    sink.trap(TrapCode::IntegerDivisionByZero, srcloc);
+    put_udf(sink);
+}
+
+/// Trap with `IntegerOverflow` if `x / y` overflows, which happens when dividing the smallest
+/// integer by -1.
+fn put_sdiv_overflow_check<CS: CodeSink + ?Sized>(
+    dp2_bits: u16,
+    x: RegUnit,
+    y: RegUnit,
+    srcloc: SourceLoc,
+    sink: &mut CS,
+) {
+    let sf = u32::from(dp2_bits) >> 6;
+    // cmn y, #1
+    sink.put4(sf << 31 | 0x3100_0000 | 1 << 10 | rn(y) << 5 | 0x1f);
+    // ccmp x, #1, #0, eq: `x - 1` only overflows when `x` is the smallest integer.
+    sink.put4(sf << 31 | 0x7a40_0800 | 1 << 16 | rn(x) << 5);
+    put_b_cond(COND_VC, 8, sink);
+    sink.trap(TrapCode::IntegerOverflow, srcloc);
+    put_udf(sink);
+}
+
+/// Add and subtract with a 12-bit immediate, optionally shifted by 12. Negative immediates flip
+/// the operation.
+///
+/// Encoding bits: `word[31:23]`.
+fn put_addsub_imm<CS: CodeSink + ?Sized>(
+    bits: u16,
+    rn_: RegUnit,
+    imm: i64,
+    rd: RegUnit,
+    sink: &mut CS,
+) {
+    let mut word = u32::from(bits) << 23;
+    if imm < 0 {
+        word ^= 1 << 30;
+    }
+    let imm = imm.wrapping_abs() as u64;
+    let (imm12, sh) = if imm < 0x1000 {
+        (imm as u32, 0)
+    } else {
+        debug_assert_eq!(imm & 0xfff, 0, "immediate out of range");
+        ((imm >> 12) as u32, 1)
+    };
+    debug_assert!(imm12 < 0x1000, "immediate out of range");
+    sink.put4(word | sh << 22 | imm12 << 10 | rn(rn_) << 5 | rn(rd));
+}
+
+/// The size in bytes of `put_add_imm`.
+pub fn add_imm_size(imm: i64) -> u8 {
+    let abs = imm.wrapping_abs() as u64;
+    if abs < 0x1000 {
+        4
+    } else if abs < 0x100_0000 {
+        if abs & 0xfff == 0 {
+            4
+        } else {
+            8
+        }
+    } else {
+        iconst_size(1, imm) + 4
+    }
+}
+
+/// Add a 64-bit immediate to `rn`, where `rn` and `rd` may be the stack pointer.
+fn put_add_imm<CS: CodeSink + ?Sized>(rn_: RegUnit, imm: i64, rd: RegUnit, sink: &mut CS) {
+    const ADD_X_IMM: u16 = 0x122;
+    let abs = imm.wrapping_abs() as u64;
+    if abs < 0x100_0000 {
+        let sign = if imm < 0 { -1 } else { 1 };
+        let hi = (abs & 0xff_f000) as i64;
+        let lo = (abs & 0xfff) as i64;
+        if hi == 0 {
+            put_addsub_imm(ADD_X_IMM, rn_, sign * lo, rd, sink);
+        } else {
+            put_addsub_imm(ADD_X_IMM, rn_, sign * hi, rd, sink);
+            if lo != 0 {
+                put_addsub_imm(ADD_X_IMM, rd, sign * lo, rd, sink);
+            }
+        }
+    } else {
+        // add rd, rn, x16, uxtx
+        put_iconst(1, imm, scratch(), sink);
+        sink.put4(0x8b20_6000 | rn(scratch()) << 16 | rn(rn_) << 5 | rn(rd));
+    }
+}
+
+/// The 16-bit chunks of an immediate that `movz` or `movn` and `movk` need to set.
+fn iconst_chunks(sf: u16, imm: i64) -> (bool, impl Iterator<Item = (u32, u32)>) {
+    let (value, count) = if sf == 0 {
+        (u64::from(imm as u32), 2)
+    } else {
+        (imm as u64, 4)
+    };
+    let chunk = move |i: u32| ((value >> (i * 16)) & 0xffff) as u32;
+    let zeros = (0..count).filter(|&i| chunk(i) == 0).count();
+    let ones = (0..count).filter(|&i| chunk(i) == 0xffff).count();
+    let inverted = ones > zeros;
+    let skip = if inverted { 0xffff } else { 0 };
+    let mut chunks = (0..count)
+        .map(move |i| (i, chunk(i)))
+        .filter(move |&(_, c)| c != skip)
+        .peekable();
+    // An immediate consisting of skipped chunks only still needs one instruction.
+    let first = if chunks.peek().is_none() {
+        Some((0, skip))
+    } else {
+        None
+    };
+    (inverted, first.into_iter().chain(chunks))
+}
+
+/// The size in bytes of `put_iconst`.
+pub fn iconst_size(sf: u16, imm: i64) -> u8 {
+    4 * iconst_chunks(sf, imm).1.count() as u8
+}
+
+/// Materialize an immediate with `movz` or `movn` followed by `movk`.
+///
+/// Encoding bits: `sf`.
+fn put_iconst<CS: CodeSink + ?Sized>(bits: u16, imm: i64, rd: RegUnit, sink: &mut CS) {
+    let sf = u32::from(bits);
+    let (inverted, chunks) = iconst_chunks(bits, imm);
+    for (n, (hw, chunk)) in chunks.enumerate() {
+        let (opc, chunk) = match (n, inverted) {
+            (0, false) => (0x5280_0000, chunk),
+            (0, true) => (0x1280_0000, !chunk & 0xffff),
+            _ => (0x7280_0000, chunk),
+        };
+        sink.put4(sf << 31 | opc | hw << 21 | chunk << 5 | rn(rd));
+    }
+}
+
+/// Bitfield moves.
+///
+/// Encoding bits: `word[31:22]`, including the `N` bit.
+fn put_bitfield<CS: CodeSink + ?Sized>(
+    bits: u16,
+    immr: u32,
+    imms: u32,
+    rn_: RegUnit,
+    rd: RegUnit,
+    sink: &mut CS,
+) {
+    sink.put4(u32::from(bits) << 22 | immr << 16 | imms << 10 | rn(rn_) << 5 | rn(rd));
+}
+
+/// `extr`, extracting a register from the concatenation of `rn` and `rm`.
+///
+/// Encoding bits: `word[31:22]`.
+fn put_extr<CS: CodeSink + ?Sized>(
+    bits: u16,
+    rn_: RegUnit,
+    rm: RegUnit,
+    lsb: u32,
+    rd: RegUnit,
+    sink: &mut CS,
+) {
+    sink.put4(u32::from(bits) << 22 | rn(rm) << 16 | lsb << 10 | rn(rn_) << 5 | rn(rd));
+}
+
+/// The register width of a bitfield move or `extr`.
+fn bitfield_width(bits: u16) -> u32 {
+    if bits & (1 << 9) != 0 {
+        64
+    } else {
+        32
+    }
+}
+
+/// The shift amount of an immediate shift or rotate, modulo the register width.
+fn imm_shift_amount(imm: Imm64, width: u32) -> u32 {
+    let imm: i64 = imm.into();
+    (imm as u32) & (width - 1)
+}
+
+/// The access size of a load or store in bytes.
+fn ldst_scale(bits: u16) -> i64 {
+    let vector = (bits >> 4) & 1 != 0;
+    let opc = bits & 0b11;
+    if vector && opc & 0b10 != 0 {
+        16
+    } else {
+        1 << (bits >> 8)
+    }
+}
+
+/// The size in bytes of a load or store with an immediate `offset`.
+pub fn ldst_size(bits: u16, offset: i64) -> u8 {
+    let scale = ldst_scale(bits);
+    if (offset >= 0 && offset % scale == 0 && offset / scale < 0x1000)
+        || (offset >= -256 && offset < 256)
+    {
+        4
+    } else {
+        iconst_size(1, offset) + 4
+    }
+}
+
+/// Pick the form of a load or store for `offset`: the scaled unsigned offset, the unscaled
+/// signed offset, or a register offset in the scratch register, which is materialized here.
+/// Returns the instruction word without the registers.
+///
+/// Encoding bits: `word[31:22]` of the unsigned offset form.
+fn ldst_form<CS: CodeSink + ?Sized>(bits: u16, offset: i64, sink: &mut CS) -> u32 {
+    let word = u32::from(bits) << 22;
+    let scale = ldst_scale(bits);
+    if offset >= 0 && offset % scale == 0 && offset / scale < 0x1000 {
+        word | ((offset / scale) as u32) << 10
+    } else if offset >= -256 && offset < 256 {
+        (word & !(1 << 24)) | ((offset as u32) & 0x1ff) << 12
+    } else {
+        put_iconst(1, offset, scratch(), sink);
+        (word & !(1 << 24)) | 1 << 21 | rn(scratch()) << 16 | 0b011 << 13 | 0b10 << 10
+    }
+}
+
+/// Emit a load or store picked by `ldst_form`.
+fn put_ldst<CS: CodeSink + ?Sized>(word: u32, rn_: RegUnit, rt: RegUnit, sink: &mut CS) {
+    sink.put4(word | rn(rn_) << 5 | rn(rt));
+}
+
+/// A load or store with a register offset scaled by the access size.
+///
+/// Encoding bits: `word[31:22]` of the unsigned offset form.
+fn put_ldst_reg_scaled<CS: CodeSink + ?Sized>(
+    bits: u16,
+    rn_: RegUnit,
+    rm: RegUnit,
+    rt: RegUnit,
+    sink: &mut CS,
+) {
+    let word = (u32::from(bits) << 22) & !(1 << 24);
+    sink.put4(
+        word | 1 << 21 | rn(rm) << 16 | 0b011 << 13 | 1 << 12 | 0b10 << 10 | rn(rn_) << 5 | rn(rt),
+    );
+}
+
+/// `stp` with pre-indexing or `ldp` with post-indexing of the stack pointer.
+///
+/// Encoding bits: `word[31:22]`.
+fn put_ldst_pair<CS: CodeSink + ?Sized>(
+    bits: u16,
+    offset: i32,
+    rt: RegUnit,
+    rt2: RegUnit,
+    sink: &mut CS,
+) {
+    let imm7 = ((offset / 8) as u32) & 0x7f;
+    sink.put4(u32::from(bits) << 22 | imm7 << 15 | rn(rt2) << 10 | 0x1f << 5 | rn(rt));
+}
+
+/// `str` with pre-indexing or `ldr` with post-indexing of the stack pointer, depending on the
+/// sign of `offset`.
+///
+/// Encoding bits: `word[31:21]`.
+fn put_ldst_indexed<CS: CodeSink + ?Sized>(bits: u16, offset: i32, rt: RegUnit, sink: &mut CS) {
+    let imm9 = (offset as u32) & 0x1ff;
+    let mode = if offset < 0 { 0b11 } else { 0b01 };
+    sink.put4(u32::from(bits) << 21 | imm9 << 12 | mode << 10 | 0x1f << 5 | rn(rt));
+}
+
+/// `b` and `bl`.
+///
+/// Encoding bits: `word[31:26]`.
+fn put_b<CS: CodeSink + ?Sized>(bits: u16, disp: i64, sink: &mut CS) {
+    sink.put4(u32::from(bits) << 26 | ((disp >> 2) as u32) & 0x3ff_ffff);
+}
+
+/// `b.cond`.
+fn put_b_cond<CS: CodeSink + ?Sized>(cond: u32, disp: i64, sink: &mut CS) {
+    sink.put4(0x5400_0000 | (((disp >> 2) as u32) & 0x7_ffff) << 5 | cond);
+}
+
+/// `cbz` and `cbnz`.
+///
+/// Encoding bits: `word[31:24]`.
+fn put_cbz<CS: CodeSink + ?Sized>(bits: u16, rt: RegUnit, disp: i64, sink: &mut CS) {
+    sink.put4(u32::from(bits) << 24 | (((disp >> 2) as u32) & 0x7_ffff) << 5 | rn(rt));
+}
+
+/// `br`, `blr` and `ret`.
+///
+/// Encoding bits: `word[31:16]`.
+fn put_br<CS: CodeSink + ?Sized>(bits: u16, rn_: RegUnit, sink: &mut CS) {
+    sink.put4(u32::from(bits) << 16 | rn(rn_) << 5);
+}
+
+/// `adr`, computing a PC-relative address.
+fn put_adr<CS: CodeSink + ?Sized>(disp: i64, rd: RegUnit, sink: &mut CS) {
+    let immlo = (disp as u32) & 0b11;
+    let immhi = ((disp >> 2) as u32) & 0x7_ffff;
+    sink.put4(0x1000_0000 | immlo << 29 | immhi << 5 | rn(rd));
+}
+
+/// A PC-relative load of a literal.
+///
+/// Encoding bits: `word[31:24]`.
+fn put_ldr_literal<CS: CodeSink + ?Sized>(bits: u16, disp: i64, rt: RegUnit, sink: &mut CS) {
+    sink.put4(u32::from(bits) << 24 | (((disp >> 2) as u32) & 0x7_ffff) << 5 | rn(rt));
+}
+
+/// `adrp` and `add`, computing the address of a symbol within 4 GB.
+fn put_adrp_add<CS: CodeSink + ?Sized>(
+    name: &ExternalName,
+    addend: i64,
+    rd: RegUnit,
+    sink: &mut CS,
+) {
+    sink.reloc_external(Reloc::Arm64AdrPrelPgHi21, name, addend);
+    sink.put4(0x9000_0000 | rn(rd));
+    sink.reloc_external(Reloc::Arm64AddAbsLo12Nc, name, addend);
+    sink.put4(0x9100_0000 | rn(rd) << 5 | rn(rd));
+}
+
+/// `adrp` and `ldr`, loading the address of a symbol from the GOT.
+fn put_adrp_ldr_got<CS: CodeSink + ?Sized>(name: &ExternalName, rd: RegUnit, sink: &mut CS) {
+    sink.reloc_external(Reloc::Arm64AdrGotPage21, name, 0);
+    sink.put4(0x9000_0000 | rn(rd));
+    sink.reloc_external(Reloc::Arm64Ld64GotLo12Nc, name, 0);
+    sink.put4(0xf940_0000 | rn(rd) << 5 | rn(rd));
+}
+
+/// Load the absolute address of a symbol from a literal placed inline, and branch over it.
+fn put_abs_literal<CS: CodeSink + ?Sized>(name: &ExternalName, rd: RegUnit, sink: &mut CS) {
+    // ldr rd, #8
+    sink.put4(0x5800_0040 | rn(rd));
+    // b #12
+    sink.put4(0x1400_0003);
+    sink.reloc_external(Reloc::Abs8, name, 0);
+    sink.put8(0);
+}
+
+/// `udf #0`.
+fn put_udf<CS: CodeSink + ?Sized>(sink: &mut CS) {
+    sink.put4(UDF);
+}
+
+/// `cset rd, cond`.
+fn put_cset<CS: CodeSink + ?Sized>(cond: u32, rd: RegUnit, sink: &mut CS) {
+    sink.put4(0x1a9f_07e0 | (cond ^ 1) << 12 | rn(rd));
+}
+
+/// Set `rd` if either of the conditions of a floating point condition code holds.
+fn put_cset_fcc<CS: CodeSink + ?Sized>(cond: FloatCC, rd: RegUnit, sink: &mut CS) {
+    let (cond1, cond2) = fcc2cond(cond);
+    put_cset(cond1, rd, sink);
+    // csinc rd, rd, wzr, !cond2
+    sink.put4(0x1a80_0400 | 0x1f << 16 | (cond2 ^ 1) << 12 | rn(rd) << 5 | rn(rd));
+}
+
+/// `csel`.
+///
+/// Encoding bits: `sf`.
+fn put_csel<CS: CodeSink + ?Sized>(
+    bits: u16,
+    cond: u32,
+    rn_: RegUnit,
+    rm: RegUnit,
+    rd: RegUnit,
+    sink: &mut CS,
+) {
+    let sf = u32::from(bits);
+    sink.put4(sf << 31 | 0x1a80_0000 | rn(rm) << 16 | cond << 12 | rn(rn_) << 5 | rn(rd));
+}
+
+/// `fcsel`.
+///
+/// Encoding bits: `ftype`.
+fn put_fcsel<CS: CodeSink + ?Sized>(
+    bits: u16,
+    cond: u32,
+    rn_: RegUnit,
+    rm: RegUnit,
+    rd: RegUnit,
+    sink: &mut CS,
+) {
+    let ftype = u32::from(bits);
+    sink.put4(0x1e20_0c00 | ftype << 22 | rn(rm) << 16 | cond << 12 | rn(rn_) << 5 | rn(rd));
+}
+
+/// `tst rn, #1`, testing a boolean.
+fn put_tst_bool<CS: CodeSink + ?Sized>(rn_: RegUnit, sink: &mut CS) {
+    sink.put4(0x7200_0000 | rn(rn_) << 5 | 0x1f);
+}
+
+/// Floating point data processing with two sources.
+///
+/// Encoding bits: `ftype | opcode`.
+fn put_fp2<CS: CodeSink + ?Sized>(
+    bits: u16,
+    rn_: RegUnit,
+    rm: RegUnit,
+    rd: RegUnit,
+    sink: &mut CS,
+) {
+    let bits = u32::from(bits);
+    let ftype = bits >> 4;
+    let opcode = bits & 0xf;
+    sink.put4(0x1e20_0800 | ftype << 22 | rn(rm) << 16 | opcode << 12 | rn(rn_) << 5 | rn(rd));
+}
+
+/// Floating point data processing with one source.
+///
+/// Encoding bits: `ftype | opcode`.
+fn put_fp1<CS: CodeSink + ?Sized>(bits: u16, rn_: RegUnit, rd: RegUnit, sink: &mut CS) {
+    let bits = u32::from(bits);
+    let ftype = bits >> 6;
+    let opcode = bits & 0x3f;
+    sink.put4(0x1e20_4000 | ftype << 22 | opcode << 15 | rn(rn_) << 5 | rn(rd));
+}
+
+/// `fmadd`.
+///
+/// Encoding bits: `ftype`.
+fn put_fp3<CS: CodeSink + ?Sized>(
+    bits: u16,
+    rn_: RegUnit,
+    rm: RegUnit,
+    ra: RegUnit,
+    rd: RegUnit,
+    sink: &mut CS,
+) {
+    let ftype = u32::from(bits);
+    sink.put4(0x1f00_0000 | ftype << 22 | rn(rm) << 16 | rn(ra) << 10 | rn(rn_) << 5 | rn(rd));
+}
+
+/// `fcmp`.
+///
+/// Encoding bits: `ftype`.
+fn put_fcmp<CS: CodeSink + ?Sized>(bits: u16, rn_: RegUnit, rm: RegUnit, sink: &mut CS) {
+    let ftype = u32::from(bits);
+    sink.put4(0x1e20_2000 | ftype << 22 | rn(rm) << 16 | rn(rn_) << 5);
+}
+
+/// Conversions and moves between floating point and integer registers.
+///
+/// Encoding bits: `sf | ftype | rmode | opcode`.
+fn put_fpint<CS: CodeSink + ?Sized>(bits: u16, rn_: RegUnit, rd: RegUnit, sink: &mut CS) {
+    let bits = u32::from(bits);
+    let sf = bits >> 7;
+    let ftype = (bits >> 5) & 0b11;
+    let rmode = (bits >> 3) & 0b11;
+    let opcode = bits & 0b111;
+    sink.put4(
+        sf << 31 | 0x1e20_0000 | ftype << 22 | rmode << 19 | opcode << 16 | rn(rn_) << 5 | rn(rd),
+    );
+}
+
+/// SIMD three same.
+///
+/// Encoding bits: `Q | U | size | opcode`.
+fn put_vrrr<CS: CodeSink + ?Sized>(
+    bits: u16,
+    rn_: RegUnit,
+    rm: RegUnit,
+    rd: RegUnit,
+    sink: &mut CS,
+) {
+    let bits = u32::from(bits);
+    let q = bits >> 8;
+    let u = (bits >> 7) & 1;
+    let size = (bits >> 5) & 0b11;
+    let opcode = bits & 0x1f;
+    sink.put4(
+        0x0e20_0400
+            | q << 30
+            | u << 29
+            | size << 22
+            | rn(rm) << 16
+            | opcode << 11
+            | rn(rn_) << 5
+            | rn(rd),
+    );
+}
+
+/// SIMD two-register miscellaneous.
+///
+/// Encoding bits: `Q | U | size | opcode`.
+fn put_vmisc<CS: CodeSink + ?Sized>(bits: u16, rn_: RegUnit, rd: RegUnit, sink: &mut CS) {
+    let bits = u32::from(bits);
+    let q = bits >> 8;
+    let u = (bits >> 7) & 1;
+    let size = (bits >> 5) & 0b11;
+    let opcode = bits & 0x1f;
+    sink.put4(0x0e20_0800 | q << 30 | u << 29 | size << 22 | opcode << 12 | rn(rn_) << 5 | rn(rd));
+}
+
+/// Moves between vector lanes and registers. `kind` is one of the lane move instructions above.
+///
+/// Encoding bits: log2 of the lane size in bytes.
+fn put_lane<CS: CodeSink + ?Sized>(
+    kind: u32,
+    bits: u16,
+    lane: u8,
+    rn_: RegUnit,
+    rd: RegUnit,
+    sink: &mut CS,
+) {
+    let size = u32::from(bits);
+    let imm5 = ((u32::from(lane) << (size + 1)) | (1 << size)) & 0x1f;
+    let q = if kind == UMOV && size == 3 {
+        1 << 30
+    } else {
+        0
+    };
+    sink.put4(kind | q | imm5 << 16 | rn(rn_) << 5 | rn(rd));
+}
+
+/// `movi` with a byte mask immediate.
+///
+/// Encoding bits: `Q | imm8`.
+fn put_movi<CS: CodeSink + ?Sized>(bits: u16, rd: RegUnit, sink: &mut CS) {
+    let bits = u32::from(bits);
+    let q = bits >> 8;
+    let imm8 = bits & 0xff;
+    sink.put4(0x2f00_e400 | q << 30 | (imm8 >> 5) << 16 | (imm8 & 0x1f) << 5 | rn(rd));
+}
@@ -1,10 +1,249 @@
 //! Encoding tables for ARM64 ISA.
 
-use crate::ir;
+use super::binemit::{add_imm_size, iconst_size, ldst_size, rrr_sf};
+use super::registers::*;
+use crate::cursor::{Cursor, FuncCursor};
+use crate::flowgraph::ControlFlowGraph;
+use crate::ir::condcodes::FloatCC;
+use crate::ir::{self, Function, Inst, InstBuilder, InstructionData, ValueLoc};
 use crate::isa;
 use crate::isa::constraints::*;
 use crate::isa::enc_tables::*;
-use crate::isa::encoding::RecipeSizing;
+use crate::isa::encoding::base_size;
+use crate::isa::encoding::{Encoding, RecipeSizing};
+use crate::isa::{StackBaseMask, StackRef, TargetIsa};
+use crate::predicates;
+use crate::regalloc::RegDiversions;
 
 include!(concat!(env!("OUT_DIR"), "/encoding-arm64.rs"));
 include!(concat!(env!("OUT_DIR"), "/legalize-arm64.rs"));
+
+/// Accounts for the `movk` instructions needed to materialize an immediate.
+fn size_iconst(
+    _sizing: &RecipeSizing,
+    enc: Encoding,
+    inst: Inst,
+    _divert: &RegDiversions,
+    func: &Function,
+) -> u8 {
+    match func.dfg[inst] {
+        InstructionData::UnaryImm { imm, .. } => iconst_size(enc.bits(), imm.into()),
+        _ => panic!("Expected UnaryImm: {}", func.dfg.display_inst(inst, None)),
+    }
+}
+
+/// Accounts for materializing a comparison immediate in the scratch register.
+fn size_ifcmp_imm_x16(
+    _sizing: &RecipeSizing,
+    enc: Encoding,
+    inst: Inst,
+    _divert: &RegDiversions,
+    func: &Function,
+) -> u8 {
+    match func.dfg[inst] {
+        InstructionData::BinaryImm { imm, .. } => iconst_size(rrr_sf(enc.bits()), imm.into()) + 4,
+        _ => panic!("Expected BinaryImm: {}", func.dfg.display_inst(inst, None)),
+    }
+}
+
+/// Accounts for loads and stores with an offset that doesn't fit in the instruction.
+fn size_ldst(
+    _sizing: &RecipeSizing,
+    enc: Encoding,
+    inst: Inst,
+    _divert: &RegDiversions,
+    func: &Function,
+) -> u8 {
+    match func.dfg[inst] {
+        InstructionData::Load { offset, .. } | InstructionData::Store { offset, .. } => {
+            ldst_size(enc.bits(), offset.into())
+        }
+        _ => panic!(
+            "Expected Load or Store: {}",
+            func.dfg.display_inst(inst, None)
+        ),
+    }
+}
+
+/// Accounts for spills and fills to stack slots that are too far from the stack pointer.
+fn size_stack_ldst(
+    _sizing: &RecipeSizing,
+    enc: Encoding,
+    inst: Inst,
+    divert: &RegDiversions,
+    func: &Function,
+) -> u8 {
+    let slot = match func.dfg[inst] {
+        InstructionData::RegSpill { dst, .. } => Some(dst),
+        InstructionData::RegFill { src, .. } => Some(src),
+        _ => {
+            let value = match func.dfg.inst_results(inst).first() {
+                Some(&result) => result,
+                None => func.dfg.inst_args(inst)[0],
+            };
+            let value = match divert.get(value, &func.locations) {
+                ValueLoc::Stack(_) => value,
+                _ => func.dfg.inst_args(inst)[0],
+            };
+            match divert.get(value, &func.locations) {
+                ValueLoc::Stack(ss) => Some(ss),
+                _ => None,
+            }
+        }
+    };
+    match slot.and_then(|ss| StackRef::masked(ss, StackBaseMask(3), &func.stack_slots)) {
+        Some(stk) => ldst_size(enc.bits(), stk.offset.into()),
+        // The frame hasn't been laid out yet, so assume the worst.
+        None => ldst_size(enc.bits(), i64::from(core::i32::MAX)),
+    }
+}
+
+/// Accounts for stack slot addresses that don't fit in an `add` immediate.
+fn size_stack_addr(
+    _sizing: &RecipeSizing,
+    _enc: Encoding,
+    inst: Inst,
+    _divert: &RegDiversions,
+    func: &Function,
+) -> u8 {
+    match func.dfg[inst] {
+        InstructionData::StackLoad {
+            stack_slot, offset, ..
+        } => match StackRef::masked(stack_slot, StackBaseMask(3), &func.stack_slots) {
+            Some(stk) => {
+                let offset: i64 = offset.into();
+                add_imm_size(i64::from(stk.offset) + offset)
+            }
+            None => add_imm_size(i64::from(core::i32::MAX)),
+        },
+        _ => panic!("Expected StackLoad: {}", func.dfg.display_inst(inst, None)),
+    }
+}
+
+/// Accounts for stack adjustments that don't fit in an `add` immediate.
+fn size_adjust_sp(
+    _sizing: &RecipeSizing,
+    _enc: Encoding,
+    inst: Inst,
+    _divert: &RegDiversions,
+    func: &Function,
+) -> u8 {
+    match func.dfg[inst] {
+        InstructionData::UnaryImm { imm, .. } => add_imm_size(imm.into()),
+        _ => panic!("Expected UnaryImm: {}", func.dfg.display_inst(inst, None)),
+    }
+}
+
+/// Expand `fcvt_to_sint` into a saturating conversion guarded by traps for NaN and out of range
+/// inputs.
+fn expand_fcvt_to_sint(
+    inst: ir::Inst,
+    func: &mut ir::Function,
+    _cfg: &mut ControlFlowGraph,
+    _isa: &dyn TargetIsa,
+) {
+    use crate::ir::immediates::{Ieee32, Ieee64};
+
+    let x = match func.dfg[inst] {
+        ir::InstructionData::Unary {
+            opcode: ir::Opcode::FcvtToSint,
+            arg,
+        } => arg,
+        _ => panic!("Need fcvt_to_sint: {}", func.dfg.display_inst(inst, None)),
+    };
+    let xty = func.dfg.value_type(x);
+    let ty = func.dfg.ctrl_typevar(inst);
+    let output_bits = ty.lane_bits();
+
+    let mut pos = FuncCursor::new(func).at_inst(inst);
+    pos.use_srcloc(inst);
+
+    let is_nan = pos.ins().fcmp(FloatCC::Unordered, x, x);
+    pos.ins()
+        .trapnz(is_nan, ir::TrapCode::BadConversionToInteger);
+
+    // When the input type can represent `INT_MIN - 1` exactly, anything above it converts
+    // correctly to `INT_MIN`.
+    let mut overflow_cc = FloatCC::LessThan;
+    let (flimit, fupper) = match xty {
+        ir::types::F32 => {
+            let flimit = if output_bits < 32 {
+                overflow_cc = FloatCC::LessThanOrEqual;
+                Ieee32::fcvt_to_sint_negative_overflow(output_bits)
+            } else {
+                Ieee32::pow2(output_bits - 1).neg()
+            };
+            (
+                pos.ins().f32const(flimit),
+                pos.ins().f32const(Ieee32::pow2(output_bits - 1)),
+            )
+        }
+        ir::types::F64 => {
+            let flimit = if output_bits < 64 {
+                overflow_cc = FloatCC::LessThanOrEqual;
+                Ieee64::fcvt_to_sint_negative_overflow(output_bits)
+            } else {
+                Ieee64::pow2(output_bits - 1).neg()
+            };
+            (
+                pos.ins().f64const(flimit),
+                pos.ins().f64const(Ieee64::pow2(output_bits - 1)),
+            )
+        }
+        _ => panic!("Can't convert {}", xty),
+    };
+    let overflow = pos.ins().fcmp(overflow_cc, x, flimit);
+    pos.ins().trapnz(overflow, ir::TrapCode::IntegerOverflow);
+    let overflow = pos.ins().fcmp(FloatCC::GreaterThanOrEqual, x, fupper);
+    pos.ins().trapnz(overflow, ir::TrapCode::IntegerOverflow);
+
+    pos.func.dfg.replace(inst).fcvt_to_sint_sat(ty, x);
+}
+
+/// Expand `fcvt_to_uint` into a saturating conversion guarded by traps for NaN and out of range
+/// inputs.
+fn expand_fcvt_to_uint(
+    inst: ir::Inst,
+    func: &mut ir::Function,
+    _cfg: &mut ControlFlowGraph,
+    _isa: &dyn TargetIsa,
+) {
+    use crate::ir::immediates::{Ieee32, Ieee64};
+
+    let x = match func.dfg[inst] {
+        ir::InstructionData::Unary {
+            opcode: ir::Opcode::FcvtToUint,
+            arg,
+        } => arg,
+        _ => panic!("Need fcvt_to_uint: {}", func.dfg.display_inst(inst, None)),
+    };
+    let xty = func.dfg.value_type(x);
+    let ty = func.dfg.ctrl_typevar(inst);
+    let output_bits = ty.lane_bits();
+
+    let mut pos = FuncCursor::new(func).at_inst(inst);
+    pos.use_srcloc(inst);
+
+    let is_nan = pos.ins().fcmp(FloatCC::Unordered, x, x);
+    pos.ins()
+        .trapnz(is_nan, ir::TrapCode::BadConversionToInteger);
+
+    // Anything above -1.0 truncates to a value in range.
+    let (flimit, fupper) = match xty {
+        ir::types::F32 => (
+            pos.ins().f32const(Ieee32::with_float(-1.0)),
+            pos.ins().f32const(Ieee32::pow2(output_bits)),
+        ),
+        ir::types::F64 => (
+            pos.ins().f64const(Ieee64::with_float(-1.0)),
+            pos.ins().f64const(Ieee64::pow2(output_bits)),
+        ),
+        _ => panic!("Can't convert {}", xty),
+    };
+    let overflow = pos.ins().fcmp(FloatCC::LessThanOrEqual, x, flimit);
+    pos.ins().trapnz(overflow, ir::TrapCode::IntegerOverflow);
+    let overflow = pos.ins().fcmp(FloatCC::GreaterThanOrEqual, x, fupper);
+    pos.ins().trapnz(overflow, ir::TrapCode::IntegerOverflow);
+
+    pos.func.dfg.replace(inst).fcvt_to_uint_sat(ty, x);
+}
@@ -13,6 +13,9 @@ pub enum CallConv {
     Cold,
     /// System V-style convention used on many platforms
     SystemV,
+    /// System V-style convention where the callee pops its stack arguments, which makes tail
+    /// calls to functions with more stack arguments than the caller possible
+    Tail,
     /// Windows "fastcall" convention, also used for x64 and ARM
     WindowsFastcall,
     /// SpiderMonkey WebAssembly convention on systems using natively SystemV
@@ -42,6 +45,7 @@ impl CallConv {
             LibcallCallConv::Fast => Self::Fast,
             LibcallCallConv::Cold => Self::Cold,
             LibcallCallConv::SystemV => Self::SystemV,
+            LibcallCallConv::Tail => Self::Tail,
             LibcallCallConv::WindowsFastcall => Self::WindowsFastcall,
             LibcallCallConv::BaldrdashSystemV => Self::BaldrdashSystemV,
             LibcallCallConv::BaldrdashWindows => Self::BaldrdashWindows,
@@ -72,6 +76,7 @@ impl fmt::Display for CallConv {
             Self::Fast => "fast",
             Self::Cold => "cold",
             Self::SystemV => "system_v",
+            Self::Tail => "tail",
             Self::WindowsFastcall => "windows_fastcall",
             Self::BaldrdashSystemV => "baldrdash_system_v",
             Self::BaldrdashWindows => "baldrdash_windows",
@@ -87,6 +92,7 @@ impl str::FromStr for CallConv {
             "fast" => Ok(Self::Fast),
             "cold" => Ok(Self::Cold),
             "system_v" => Ok(Self::SystemV),
+            "tail" => Ok(Self::Tail),
             "windows_fastcall" => Ok(Self::WindowsFastcall),
             "baldrdash_system_v" => Ok(Self::BaldrdashSystemV),
             "baldrdash_windows" => Ok(Self::BaldrdashWindows),
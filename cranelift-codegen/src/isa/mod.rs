@@ -251,6 +251,11 @@ pub trait TargetIsa: fmt::Display + Send + Sync {
         false
     }
 
+    /// Can this target access thread-local storage with `tls_value`?
+    fn supports_tls(&self) -> bool {
+        false
+    }
+
     /// Get a data structure describing the registers in this ISA.
     fn register_info(&self) -> RegInfo;
 
@@ -385,4 +390,11 @@ pub trait TargetIsa: fmt::Display + Send + Sync {
     fn emit_unwind_info(&self, _func: &ir::Function, _mem: &mut Vec<u8>) {
         // No-op by default
     }
+
+    /// Get the machine code of the thunks that the code compiled for this ISA reaches through
+    /// `ir::ExternalName::LibCall`, and which each module must define once for itself rather
+    /// than import from the runtime, such as the x86 retpoline thunks.
+    fn libcall_thunks(&self) -> Vec<(ir::LibCall, Vec<u8>)> {
+        Vec::new()
+    }
 }
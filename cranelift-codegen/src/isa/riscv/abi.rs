@@ -3,11 +3,16 @@
 //! This module implements the RISC-V calling convention through the primary `legalize_signature()`
 //! entry point.
 //!
+//! When the 'D' extension is in use, floating point arguments follow the ILP32D and LP64D ABIs:
+//! they are assigned to `%f10-%f17` independently of the integer arguments, and passed like
+//! integers of the same size once those run out. Otherwise integer and floating point arguments
+//! share the same sequence of argument registers.
+//!
 //! This doesn't support the soft-float ABI at the moment.
 
 use super::registers::{FPR, GPR};
 use super::settings;
-use crate::abi::{legalize_args, ArgAction, ArgAssigner, ValueConversion};
+use crate::abi::{legalize_args, legalize_params, ArgAction, ArgAssigner, ValueConversion};
 use crate::ir::{self, AbiParam, ArgumentExtension, ArgumentLoc, ArgumentPurpose, Type};
 use crate::isa::RegClass;
 use crate::regalloc::RegisterSet;
@@ -21,17 +26,20 @@ struct Args {
     pointer_type: Type,
     regs: u32,
     reg_limit: u32,
+    /// Number of float registers assigned, when they are allocated separately.
+    fregs: Option<u32>,
     offset: u32,
 }
 
 impl Args {
-    fn new(bits: u8, enable_e: bool) -> Self {
+    fn new(bits: u8, isa_flags: &settings::Flags) -> Self {
         Self {
             pointer_bits: bits,
             pointer_bytes: bits / 8,
             pointer_type: Type::int(u16::from(bits)).unwrap(),
             regs: 0,
-            reg_limit: if enable_e { 6 } else { 8 },
+            reg_limit: if isa_flags.enable_e() { 6 } else { 8 },
+            fregs: if isa_flags.use_d() { Some(0) } else { None },
             offset: 0,
         }
     }
@@ -68,22 +76,41 @@ impl ArgAssigner for Args {
             }
         }
 
-        if self.regs < self.reg_limit {
-            // Assign to a register.
-            let reg = if ty.is_float() {
-                FPR.unit(10 + self.regs as usize)
-            } else {
-                GPR.unit(10 + self.regs as usize)
-            };
-            self.regs += 1;
-            ArgumentLoc::Reg(reg).into()
-        } else {
-            // Assign a stack location.
-            let loc = ArgumentLoc::Stack(self.offset as i32);
-            self.offset += u32::from(self.pointer_bytes);
-            debug_assert!(self.offset <= i32::MAX as u32);
-            loc.into()
+        match self.fregs {
+            Some(ref mut fregs) if ty.is_float() => {
+                if *fregs < 8 {
+                    let reg = FPR.unit(10 + *fregs as usize);
+                    *fregs += 1;
+                    return ArgumentLoc::Reg(reg).into();
+                }
+                // The hard-float ABIs pass floats in the integer registers once the float
+                // registers run out. The bits of the float go in the low bits of the register, or
+                // in a pair of them for a double on RV32.
+                if self.regs < self.reg_limit {
+                    return ValueConversion::IntBits.into();
+                }
+            }
+            _ => {
+                if self.regs < self.reg_limit {
+                    // Assign to a register.
+                    let reg = if ty.is_float() {
+                        FPR.unit(10 + self.regs as usize)
+                    } else {
+                        GPR.unit(10 + self.regs as usize)
+                    };
+                    self.regs += 1;
+                    return ArgumentLoc::Reg(reg).into();
+                }
+            }
         }
+
+        // Assign a stack location. Doubles on RV32 take two naturally aligned words.
+        let size = u32::from(self.pointer_bytes).max(ty.bytes());
+        self.offset = align(self.offset, size);
+        let loc = ArgumentLoc::Stack(self.offset as i32);
+        self.offset += size;
+        debug_assert!(self.offset <= i32::MAX as u32);
+        loc.into()
     }
 }
 
@@ -96,12 +123,10 @@ pub fn legalize_signature(
 ) {
     let bits = triple.pointer_width().unwrap().bits();
 
-    let mut args = Args::new(bits, isa_flags.enable_e());
-    if let Some(new_params) = legalize_args(&sig.params, &mut args) {
-        sig.to_mut().params = new_params;
-    }
+    let mut args = Args::new(bits, isa_flags);
+    legalize_params(sig, &mut args);
 
-    let mut rets = Args::new(bits, isa_flags.enable_e());
+    let mut rets = Args::new(bits, isa_flags);
     if let Some(new_returns) = legalize_args(&sig.returns, &mut rets) {
         sig.to_mut().returns = new_returns;
     }
@@ -1,14 +1,49 @@
 //! Emitting binary RISC-V machine code.
 
 use crate::binemit::{bad_encoding, CodeSink, Reloc};
-use crate::ir::{Function, Inst, InstructionData};
-use crate::isa::{RegUnit, StackBaseMask, StackRef, TargetIsa};
+use crate::ir::{AtomicOrdering, Function, Inst, InstructionData, TrapCode};
+use crate::isa::{RegUnit, StackBase, StackBaseMask, StackRef, TargetIsa};
 use crate::predicates::is_signed_int;
 use crate::regalloc::RegDiversions;
 use core::u32;
 
 include!(concat!(env!("OUT_DIR"), "/binemit-riscv.rs"));
 
+/// Encoding bits of `xori`.
+const XORI_BITS: u16 = 0b00100 | (0b100 << 5);
+
+/// Encoding bits of `addi`.
+const ADDI_BITS: u16 = 0b00100;
+
+/// Encoding bits of `bne`.
+const BNE_BITS: u16 = 0b11000 | (0b001 << 5);
+
+/// The funct5 field of `lr`, in the position used by AMO encoding bits.
+const LR_BITS: u16 = 0b00010 << 10;
+
+/// The funct5 field of `sc`, in the position used by AMO encoding bits.
+const SC_BITS: u16 = 0b00011 << 10;
+
+/// `fence rw,rw`.
+const FENCE_RW_RW: u32 = 0x0330_000f;
+
+/// `fence r,rw`.
+const FENCE_R_RW: u32 = 0x0230_000f;
+
+/// `fence rw,w`.
+const FENCE_RW_W: u32 = 0x0310_000f;
+
+/// `fence.tso`.
+const FENCE_TSO: u32 = 0x8330_000f;
+
+fn stk_base(base: StackBase) -> RegUnit {
+    match base {
+        StackBase::SP => 2,
+        StackBase::FP => 8,
+        StackBase::Zone => unimplemented!(),
+    }
+}
+
 /// R-type instructions.
 ///
 ///   31     24  19  14     11 6
@@ -180,3 +215,208 @@ fn put_uj<CS: CodeSink + ?Sized>(bits: u16, imm: i64, rd: RegUnit, sink: &mut CS
 
     sink.put4(i);
 }
+
+/// S-type store instructions.
+///
+///   31  24  19  14     11  6
+///   imm rs2 rs1 funct3 imm opcode
+///    25  20  15     12   7      0
+///
+/// Encoding bits: `opcode[6:2] | (funct3 << 5)`
+fn put_s<CS: CodeSink + ?Sized>(bits: u16, rs1: RegUnit, imm: i64, rs2: RegUnit, sink: &mut CS) {
+    let bits = u32::from(bits);
+    let opcode5 = bits & 0x1f;
+    let funct3 = (bits >> 5) & 0x7;
+    let rs1 = u32::from(rs1) & 0x1f;
+    let rs2 = u32::from(rs2) & 0x1f;
+
+    debug_assert!(is_signed_int(imm, 12, 0), "S out of range {:#x}", imm);
+    let imm = imm as u32;
+
+    // 0-6: opcode
+    let mut i = 0x3;
+    i |= opcode5 << 2;
+    i |= (imm & 0x1f) << 7;
+    i |= funct3 << 12;
+    i |= rs1 << 15;
+    i |= rs2 << 20;
+    i |= ((imm >> 5) & 0x7f) << 25;
+
+    sink.put4(i);
+}
+
+/// R4-type fused multiply-add instructions.
+///
+///   31  26     24  19  14     11 6
+///   rs3 funct2 rs2 rs1 funct3 rd opcode
+///    27     25  20  15     12  7      0
+///
+/// Encoding bits: `opcode[6:2] | (funct3 << 5) | (funct2 << 8)`.
+fn put_r4<CS: CodeSink + ?Sized>(
+    bits: u16,
+    rs1: RegUnit,
+    rs2: RegUnit,
+    rs3: RegUnit,
+    rd: RegUnit,
+    sink: &mut CS,
+) {
+    let bits = u32::from(bits);
+    let opcode5 = bits & 0x1f;
+    let funct3 = (bits >> 5) & 0x7;
+    let funct2 = (bits >> 8) & 0x3;
+    let rs1 = u32::from(rs1) & 0x1f;
+    let rs2 = u32::from(rs2) & 0x1f;
+    let rs3 = u32::from(rs3) & 0x1f;
+    let rd = u32::from(rd) & 0x1f;
+
+    // 0-6: opcode
+    let mut i = 0x3;
+    i |= opcode5 << 2;
+    i |= rd << 7;
+    i |= funct3 << 12;
+    i |= rs1 << 15;
+    i |= rs2 << 20;
+    i |= funct2 << 25;
+    i |= rs3 << 27;
+
+    sink.put4(i);
+}
+
+/// R-type OP-FP instructions where the rs2 field selects the operation.
+///
+/// Encoding bits: `funct3 | (funct7 << 3) | (rs2 << 10)`.
+fn put_opfp<CS: CodeSink + ?Sized>(bits: u16, rs1: RegUnit, rd: RegUnit, sink: &mut CS) {
+    let funct3 = bits & 0x7;
+    let funct7 = (bits >> 3) & 0x7f;
+    let rs2 = (bits >> 10) & 0x1f;
+    put_r(0b10100 | (funct3 << 5) | (funct7 << 8), rs1, rs2, rd, sink);
+}
+
+/// Get the fences to emit before and after an atomic load or store.
+///
+/// This follows the mapping from C/C++ atomics recommended by the RISC-V memory model.
+pub fn access_fences(inst_data: &InstructionData) -> (Option<u32>, Option<u32>) {
+    match *inst_data {
+        InstructionData::AtomicLoad { ordering, .. } => match ordering {
+            AtomicOrdering::Relaxed => (None, None),
+            AtomicOrdering::SeqCst => (Some(FENCE_RW_RW), Some(FENCE_R_RW)),
+            _ => (None, Some(FENCE_R_RW)),
+        },
+        InstructionData::AtomicStore { ordering, .. } => match ordering {
+            AtomicOrdering::Relaxed => (None, None),
+            _ => (Some(FENCE_RW_W), None),
+        },
+        _ => panic!("Expected an atomic load or store"),
+    }
+}
+
+/// Get the `fence` instruction implementing a memory fence with `ordering`.
+fn fence_for(ordering: AtomicOrdering) -> u32 {
+    match ordering {
+        AtomicOrdering::Acquire => FENCE_R_RW,
+        AtomicOrdering::Release => FENCE_RW_W,
+        AtomicOrdering::AcqRel => FENCE_TSO,
+        AtomicOrdering::SeqCst | AtomicOrdering::Relaxed => FENCE_RW_RW,
+    }
+}
+
+fn put_fence<CS: CodeSink + ?Sized>(fence: Option<u32>, sink: &mut CS) {
+    if let Some(fence) = fence {
+        sink.put4(fence);
+    }
+}
+
+/// Get the aq and rl bits of an AMO instruction implementing `ordering`, in the position used by
+/// the encoding bits.
+fn amo_aqrl(ordering: AtomicOrdering) -> u16 {
+    match ordering {
+        AtomicOrdering::Relaxed => 0b00 << 8,
+        AtomicOrdering::Acquire => 0b10 << 8,
+        AtomicOrdering::Release => 0b01 << 8,
+        AtomicOrdering::AcqRel | AtomicOrdering::SeqCst => 0b11 << 8,
+    }
+}
+
+/// Get the aq and rl bits of the `lr` and `sc` instructions in a compare-and-swap loop.
+///
+/// The `lr` acquires and the `sc` releases. A sequentially consistent `lr` also has the rl bit set
+/// so it can't be reordered with an earlier sequentially consistent store.
+fn lrsc_aqrl(ordering: AtomicOrdering) -> (u16, u16) {
+    match ordering {
+        AtomicOrdering::Relaxed => (0, 0),
+        AtomicOrdering::Acquire => (0b10 << 8, 0),
+        AtomicOrdering::Release => (0, 0b01 << 8),
+        AtomicOrdering::AcqRel => (0b10 << 8, 0b01 << 8),
+        AtomicOrdering::SeqCst => (0b11 << 8, 0b01 << 8),
+    }
+}
+
+/// Get the 3-bit register field of a compressed instruction operand in %x8-%x15.
+fn creg(reg: RegUnit) -> u16 {
+    debug_assert!((8..16).contains(&reg), "not a compressed register: {}", reg);
+    reg & 0x7
+}
+
+/// CR-type compressed instructions.
+///
+///   15    11     6   1
+///   funct4 rd/rs1 rs2 op
+///       12      7   2  0
+fn put_cr<CS: CodeSink + ?Sized>(bits: u16, rd: RegUnit, rs2: RegUnit, sink: &mut CS) {
+    let rd = rd & 0x1f;
+    let rs2 = rs2 & 0x1f;
+    sink.put2(bits | (rd << 7) | (rs2 << 2));
+}
+
+/// CI-type compressed instructions with a 6-bit immediate.
+///
+///   15     12     11     6       1
+///   funct3 imm[5] rd/rs1 imm[4:0] op
+///       13                      2  0
+fn put_ci<CS: CodeSink + ?Sized>(bits: u16, rd: RegUnit, imm: i64, sink: &mut CS) {
+    let rd = rd & 0x1f;
+    let imm = imm as u16;
+    sink.put2(bits | (((imm >> 5) & 0x1) << 12) | (rd << 7) | ((imm & 0x1f) << 2));
+}
+
+/// CA-type compressed instructions.
+///
+///   15     9        6      4    1
+///   funct6 rd'/rs1' funct2 rs2' op
+///       10        7      5    2  0
+fn put_ca<CS: CodeSink + ?Sized>(bits: u16, rd: RegUnit, rs2: RegUnit, sink: &mut CS) {
+    sink.put2(bits | (creg(rd) << 7) | (creg(rs2) << 2));
+}
+
+/// CB-type compressed instructions with a 6-bit immediate.
+///
+///   15     12     11     9        6       1
+///   funct3 imm[5] funct2 rd'/rs1' imm[4:0] op
+///       13            10        7        2  0
+fn put_cb<CS: CodeSink + ?Sized>(bits: u16, rd: RegUnit, imm: i64, sink: &mut CS) {
+    let imm = imm as u16;
+    sink.put2(bits | (((imm >> 5) & 0x1) << 12) | (creg(rd) << 7) | ((imm & 0x1f) << 2));
+}
+
+/// CJ-type compressed jump instructions.
+///
+///   15     12                          1
+///   funct3 imm[11|4|9:8|10|6|7|3:1|5] op
+///       13                           2  0
+fn put_cj<CS: CodeSink + ?Sized>(bits: u16, imm: i64, sink: &mut CS) {
+    debug_assert!(is_signed_int(imm, 12, 1), "CJ out of range {:#x}", imm);
+    let imm = imm as u16;
+
+    // The displacement is completely hashed up.
+    let mut i = bits;
+    i |= ((imm >> 5) & 0x1) << 2;
+    i |= ((imm >> 1) & 0x7) << 3;
+    i |= ((imm >> 7) & 0x1) << 6;
+    i |= ((imm >> 6) & 0x1) << 7;
+    i |= ((imm >> 10) & 0x1) << 8;
+    i |= ((imm >> 8) & 0x3) << 9;
+    i |= ((imm >> 4) & 0x1) << 11;
+    i |= ((imm >> 11) & 0x1) << 12;
+
+    sink.put2(i);
+}
@@ -1,12 +1,14 @@
 //! Encoding tables for RISC-V.
 
+use super::binemit::access_fences;
 use super::registers::*;
-use crate::ir;
+use crate::ir::{self, Function, Inst};
 use crate::isa;
 use crate::isa::constraints::*;
 use crate::isa::enc_tables::*;
-use crate::isa::encoding::{base_size, RecipeSizing};
+use crate::isa::encoding::{base_size, Encoding, RecipeSizing};
 use crate::predicates;
+use crate::regalloc::RegDiversions;
 
 // Include the generated encoding tables:
 // - `LEVEL1_RV32`
@@ -16,3 +18,15 @@ use crate::predicates;
 // - `INFO`
 include!(concat!(env!("OUT_DIR"), "/encoding-riscv.rs"));
 include!(concat!(env!("OUT_DIR"), "/legalize-riscv.rs"));
+
+/// Size of an atomic load or store, including the fences required by its memory ordering.
+fn size_plus_fences(
+    sizing: &RecipeSizing,
+    _enc: Encoding,
+    inst: Inst,
+    _divert: &RegDiversions,
+    func: &Function,
+) -> u8 {
+    let (before, after) = access_fences(&func.dfg[inst]);
+    sizing.base_size + 4 * (before.is_some() as u8 + after.is_some() as u8)
+}
@@ -27,7 +27,8 @@ mod tests {
              supports_f = false\n\
              supports_d = false\n\
              enable_m = true\n\
-             enable_e = false\n"
+             enable_e = false\n\
+             has_c = false\n"
         );
         // Predicates are not part of the Display output.
         assert_eq!(f.full_float(), false);
@@ -24,6 +24,15 @@ pub struct StackRef {
 impl StackRef {
     /// Get a reference to the stack slot `ss` using one of the base pointers in `mask`.
     pub fn masked(ss: StackSlot, mask: StackBaseMask, frame: &StackSlots) -> Option<Self> {
+        // Use the frame pointer when the stack pointer isn't fixed.
+        if frame.frame_pointer_offset.is_some() && frame[ss].kind != StackSlotKind::OutgoingArg {
+            return if mask.contains(StackBase::FP) {
+                Some(Self::fp(ss, frame))
+            } else {
+                None
+            };
+        }
+
         // Try an SP-relative reference.
         if mask.contains(StackBase::SP) {
             return Some(Self::sp(ss, frame));
@@ -53,6 +62,22 @@ impl StackRef {
             offset,
         }
     }
+
+    /// Get a reference to `ss` using the frame pointer as a base.
+    ///
+    /// The stack layout must have a frame pointer offset, and `ss` can't be an outgoing argument
+    /// slot.
+    pub fn fp(ss: StackSlot, frame: &StackSlots) -> Self {
+        let fp_offset = frame
+            .frame_pointer_offset
+            .expect("Frame pointer references need a frame pointer offset");
+        let slot = &frame[ss];
+        debug_assert_ne!(slot.kind, StackSlotKind::OutgoingArg);
+        Self {
+            base: StackBase::FP,
+            offset: slot.offset.unwrap() - fp_offset,
+        }
+    }
 }
 
 /// Generic base register for referencing stack slots.
@@ -4,20 +4,23 @@ use super::super::settings as shared_settings;
 use super::registers::{FPR, GPR, RU};
 use super::settings as isa_settings;
 use super::unwind::UnwindInfo;
-use crate::abi::{legalize_args, ArgAction, ArgAssigner, ValueConversion};
+use crate::abi::{legalize_args, legalize_params, ArgAction, ArgAssigner, ValueConversion};
 use crate::cursor::{Cursor, CursorPosition, EncCursor};
+use crate::entity::EntitySet;
 use crate::ir;
 use crate::ir::immediates::Imm64;
 use crate::ir::stackslot::{StackOffset, StackSize};
 use crate::ir::{
     get_probestack_funcref, AbiParam, ArgumentExtension, ArgumentLoc, ArgumentPurpose,
-    FrameLayoutChange, InstBuilder, ValueLoc,
+    FrameLayoutChange, InstBuilder, StructClass, StructLayout, ValueLoc,
 };
 use crate::isa::{CallConv, RegClass, RegUnit, TargetIsa};
 use crate::regalloc::RegisterSet;
-use crate::result::CodegenResult;
+use crate::result::{CodegenError, CodegenResult};
+use crate::settings::ProbestackStrategy;
 use crate::stack_layout::layout_stack;
 use alloc::borrow::Cow;
+use alloc::string::ToString;
 use alloc::vec::Vec;
 use core::i32;
 use std::boxed::Box;
@@ -29,6 +32,9 @@ static ARG_GPRS: [RU; 6] = [RU::rdi, RU::rsi, RU::rdx, RU::rcx, RU::r8, RU::r9];
 /// Return value registers.
 static RET_GPRS: [RU; 3] = [RU::rax, RU::rdx, RU::rcx];
 
+/// Argument registers for x86, when using windows fastcall
+static ARG_GPRS_WIN_FASTCALL_X86: [RU; 2] = [RU::rcx, RU::rdx];
+
 /// Argument registers for x86-64, when using windows fastcall
 static ARG_GPRS_WIN_FASTCALL_X64: [RU; 4] = [RU::rcx, RU::rdx, RU::r8, RU::r9];
 
@@ -58,6 +64,10 @@ const WIN_SHADOW_STACK_SPACE: i32 = 32;
 /// higher up in the stack.
 const STACK_ALIGNMENT: u32 = 16;
 
+/// Frames of up to this many pages get one inline stack probe per page. Larger frames are probed
+/// with a loop.
+const PROBESTACK_MAX_UNROLL: i64 = 4;
+
 #[derive(Clone)]
 struct Args {
     pointer_bytes: u8,
@@ -68,9 +78,12 @@ struct Args {
     fpr_limit: usize,
     fpr_used: usize,
     offset: u32,
+    /// Whether the parts of split arguments are passed on the stack, even with registers left.
+    split_on_stack: bool,
+    /// Number of parts of a split argument left to pass on the stack.
+    split_parts_on_stack: usize,
     call_conv: CallConv,
     shared_flags: shared_settings::Flags,
-    #[allow(dead_code)]
     isa_flags: isa_settings::Flags,
 }
 
@@ -83,7 +96,7 @@ impl Args {
         shared_flags: &shared_settings::Flags,
         isa_flags: &isa_settings::Flags,
     ) -> Self {
-        let offset = if call_conv.extends_windows_fastcall() {
+        let offset = if call_conv.extends_windows_fastcall() && bits == 64 {
             WIN_SHADOW_STACK_SPACE
         } else {
             0
@@ -98,20 +111,106 @@ impl Args {
             fpr_limit,
             fpr_used: 0,
             offset,
+            split_on_stack: false,
+            split_parts_on_stack: 0,
             call_conv,
             shared_flags: shared_flags.clone(),
             isa_flags: isa_flags.clone(),
         }
     }
+
+    /// Get the parts that a struct argument with `layout` is passed in by value, or `None` if it
+    /// is passed as a pointer to a copy.
+    fn struct_parts(&mut self, layout: &StructLayout) -> Option<Vec<AbiParam>> {
+        if layout.size == 0 {
+            return None;
+        }
+
+        // Windows x64 passes structs of 1, 2, 4, or 8 bytes like an integer of the same size.
+        if self.call_conv.extends_windows_fastcall() && self.pointer_bits == 64 {
+            return match layout.size {
+                1 | 2 | 4 | 8 => Some(vec![AbiParam::special(
+                    ir::Type::int(layout.size as u16 * 8).unwrap(),
+                    ArgumentPurpose::StructArgumentPart(0),
+                )]),
+                _ => None,
+            };
+        }
+
+        // System V passes structs of up to 16 bytes in registers, one per eightbyte, if there are
+        // enough registers left for the whole struct. The parts are assigned when they are
+        // revisited.
+        let classes = &layout.classes[..layout.num_classes()];
+        let gprs = classes
+            .iter()
+            .filter(|&&class| class == StructClass::Integer)
+            .count();
+        let fprs = classes
+            .iter()
+            .filter(|&&class| class == StructClass::Float)
+            .count();
+        if self.pointer_bits == 64
+            && !classes.is_empty()
+            && gprs + fprs == classes.len()
+            && self.gpr_used + gprs <= self.gpr.len()
+            && self.fpr_used + fprs <= self.fpr_limit
+        {
+            let parts = classes
+                .iter()
+                .enumerate()
+                .map(|(i, &class)| {
+                    let offset = i as u32 * 8;
+                    let ty = match class {
+                        StructClass::Float if layout.size - offset == 4 => ir::types::F32,
+                        StructClass::Float => ir::types::F64,
+                        _ => ir::types::I64,
+                    };
+                    AbiParam::special(ty, ArgumentPurpose::StructArgumentPart(offset))
+                })
+                .collect();
+            return Some(parts);
+        }
+
+        // Everything else is copied to the stack argument area in pointer-sized chunks.
+        let chunk = u32::from(self.pointer_bytes);
+        let align = layout.align.max(chunk);
+        debug_assert!(align.is_power_of_two());
+        self.offset = (self.offset + align - 1) & !(align - 1);
+        let parts = (0..layout.size)
+            .step_by(chunk as usize)
+            .map(|offset| {
+                let mut part = AbiParam::special(
+                    self.pointer_type,
+                    ArgumentPurpose::StructArgumentPart(offset),
+                );
+                part.location = ArgumentLoc::Stack((self.offset + offset) as i32);
+                part
+            })
+            .collect();
+        self.offset += (layout.size + chunk - 1) & !(chunk - 1);
+        debug_assert!(self.offset <= i32::MAX as u32);
+        Some(parts)
+    }
 }
 
 impl ArgAssigner for Args {
     fn assign(&mut self, arg: &AbiParam) -> ArgAction {
         let ty = arg.value_type;
 
-        // Vectors should stay in vector registers unless SIMD is not enabled--then they are split
+        // Structs passed by value are either split into parts or passed by reference, which is
+        // handled like any other pointer argument.
+        if let ArgumentPurpose::StructArgument(layout) = arg.purpose {
+            if let Some(parts) = self.struct_parts(&layout) {
+                return ArgAction::Expand(parts);
+            }
+        }
+
+        // Vectors should stay in vector registers unless SIMD is not enabled--then they are split.
+        // 256-bit vectors only fit in the YMM registers available with AVX.
         if ty.is_vector() {
-            if self.shared_flags.enable_simd() {
+            if self.shared_flags.enable_simd()
+                && (ty.bits() <= 128 || self.isa_flags.use_avx_simd())
+            {
                 let reg = FPR.unit(self.fpr_used);
                 self.fpr_used += 1;
                 return ArgumentLoc::Reg(reg).into();
@@ -121,6 +220,9 @@ impl ArgAssigner for Args {
 
         // Large integers and booleans are broken down to fit in a register.
         if !ty.is_float() && ty.bits() > u16::from(self.pointer_bits) {
+            if self.split_on_stack && self.split_parts_on_stack == 0 {
+                self.split_parts_on_stack = (ty.bits() / u16::from(self.pointer_bits)) as usize;
+            }
             return ValueConversion::IntSplit.into();
         }
 
@@ -159,14 +261,16 @@ impl ArgAssigner for Args {
         }
 
         // Try to use a GPR.
-        if !ty.is_float() && self.gpr_used < self.gpr.len() {
+        if self.split_parts_on_stack > 0 {
+            self.split_parts_on_stack -= 1;
+        } else if !ty.is_float() && self.gpr_used < self.gpr.len() {
             let reg = self.gpr[self.gpr_used] as RegUnit;
             self.gpr_used += 1;
             return ArgumentLoc::Reg(reg).into();
         }
 
         // Try to use an FPR.
-        let fpr_offset = if self.call_conv.extends_windows_fastcall() {
+        let fpr_offset = if self.call_conv.extends_windows_fastcall() && self.pointer_bits == 64 {
             // Float and general registers on windows share the same parameter index.
             // The used register depends entirely on the parameter index: Even if XMM0
             // is not used for the first parameter, it cannot be used for the second parameter.
@@ -182,9 +286,10 @@ impl ArgAssigner for Args {
             return ArgumentLoc::Reg(reg).into();
         }
 
-        // Assign a stack location.
+        // Assign a stack location. Every argument takes a multiple of the pointer size, so an `f64`
+        // takes two slots in 32-bit mode.
         let loc = ArgumentLoc::Stack(self.offset as i32);
-        self.offset += u32::from(self.pointer_bytes);
+        self.offset += core::cmp::max(ty.bytes(), u32::from(self.pointer_bytes));
         debug_assert!(self.offset <= i32::MAX as u32);
         loc.into()
     }
@@ -205,7 +310,21 @@ pub fn legalize_signature(
         PointerWidth::U16 => panic!(),
         PointerWidth::U32 => {
             bits = 32;
-            args = Args::new(bits, &[], 0, sig.call_conv, shared_flags, isa_flags);
+            args = if sig.call_conv == CallConv::WindowsFastcall {
+                let mut args = Args::new(
+                    bits,
+                    &ARG_GPRS_WIN_FASTCALL_X86[..],
+                    0,
+                    sig.call_conv,
+                    shared_flags,
+                    isa_flags,
+                );
+                // Only arguments that fit in a register are passed in registers.
+                args.split_on_stack = true;
+                args
+            } else {
+                Args::new(bits, &[], 0, sig.call_conv, shared_flags, isa_flags)
+            };
         }
         PointerWidth::U64 => {
             bits = 64;
@@ -231,7 +350,10 @@ pub fn legalize_signature(
         }
     }
 
-    let (ret_regs, ret_fpr_limit) = if sig.call_conv.extends_windows_fastcall() {
+    let (ret_regs, ret_fpr_limit) = if returns_floats_in_st0(bits, sig.call_conv) {
+        // There is only one x87 register to return a float in.
+        (&RET_GPRS[..], 1)
+    } else if sig.call_conv.extends_windows_fastcall() {
         // windows-x64 calling convention only uses XMM0 or RAX for return values
         (&RET_GPRS_WIN_FASTCALL_X64[..], 1)
     } else {
@@ -277,12 +399,13 @@ pub fn legalize_signature(
                 extension: ArgumentExtension::None,
                 location: ArgumentLoc::Unassigned,
             };
+            // On 32-bit x86, it is usually passed on the stack instead.
             match args.assign(&ret_ptr_param) {
-                ArgAction::Assign(ArgumentLoc::Reg(reg)) => {
-                    ret_ptr_param.location = ArgumentLoc::Reg(reg);
+                ArgAction::Assign(loc) => {
+                    ret_ptr_param.location = loc;
                     sig.to_mut().params.push(ret_ptr_param);
                 }
-                _ => unreachable!("return pointer should always get a register assignment"),
+                _ => unreachable!("return pointer should always get a location assignment"),
             }
 
             let mut backup_rets = backup_rets_for_struct_return.unwrap();
@@ -322,11 +445,32 @@ pub fn legalize_signature(
         }
     }
 
-    if let Some(new_params) = legalize_args(&sig.params, &mut args) {
-        sig.to_mut().params = new_params;
+    legalize_params(sig, &mut args);
+
+    // A variadic callee on System V x86-64 expects an upper bound on the number of vector
+    // registers used by the arguments in `%al`.
+    if sig.is_variadic()
+        && bits == 64
+        && !sig.call_conv.extends_windows_fastcall()
+        && !sig.uses_special_param(ArgumentPurpose::VarargVectors)
+    {
+        sig.to_mut().params.push(AbiParam::special_reg(
+            ir::types::I32,
+            ArgumentPurpose::VarargVectors,
+            RU::rax as RegUnit,
+        ));
     }
 }
 
+/// Do functions using `call_conv` return floats in the x87 `st(0)` register?
+///
+/// The native 32-bit conventions do, but values can't be allocated to the x87 register stack.
+/// Legalized signatures return floats in `xmm0` instead, and the values are moved between the two
+/// registers after calls and before returns.
+fn returns_floats_in_st0(pointer_bits: u8, call_conv: CallConv) -> bool {
+    pointer_bits == 32 && !call_conv.extends_baldrdash()
+}
+
 /// Get register class for a type appearing in a legalized signature.
 pub fn regclass_for_abi_type(ty: ir::Type) -> RegClass {
     if ty.is_int() || ty.is_bool() {
@@ -351,6 +495,10 @@ pub fn allocatable_registers(triple: &Triple, flags: &shared_settings::Flags) ->
         if flags.enable_pinned_reg() {
             unimplemented!("Pinned register not implemented on x86-32.");
         }
+        // Position-independent code keeps the GOT base in %ebx, as the PLT expects.
+        if flags.is_pic() {
+            regs.take(GPR, RU::rbx as RegUnit);
+        }
     } else {
         // Choose r15 as the pinned register on 64-bits: it is non-volatile on native ABIs and
         // isn't the fixed output register of any instruction.
@@ -430,21 +578,302 @@ fn callee_saved_gprs_used(isa: &dyn TargetIsa, func: &ir::Function) -> RegisterS
     }
 
     used.intersect(&all_callee_saved);
+
+    // The GOT base register is reserved, so it only needs saving when the prologue sets it up.
+    if needs_got_base(isa, func) {
+        used.free(GPR, RU::rbx as RegUnit);
+    }
+
+    // An inline stack probe loop needs a scratch register. On x86-32 the only ones free in the
+    // prologue are the callee-saved registers it has pushed.
+    if isa.pointer_bits() == 32
+        && isa.flags().probestack_enabled()
+        && isa.flags().probestack_strategy() == ProbestackStrategy::Inline
+        && i64::from(max_frame_size(func))
+            > PROBESTACK_MAX_UNROLL << isa.flags().probestack_size_log2()
+        && !used.is_avail(GPR, RU::rsi as RegUnit)
+    {
+        used.free(GPR, RU::rsi as RegUnit);
+    }
+
     used
 }
 
-pub fn prologue_epilogue(func: &mut ir::Function, isa: &dyn TargetIsa) -> CodegenResult<()> {
+/// An upper bound of the size of the frame `layout_stack` gives `func`, allowing for padding
+/// every slot. This is used to decide on stack probes before the frame is laid out.
+fn max_frame_size(func: &ir::Function) -> u32 {
+    func.stack_slots
+        .values()
+        .map(|ss| ss.size + STACK_ALIGNMENT)
+        .sum()
+}
+
+/// Does `func` need the GOT base in %ebx? Only 32-bit position-independent code uses a GOT base
+/// register, to address GOT entries, constants and jump tables, and to call through the PLT.
+fn needs_got_base(isa: &dyn TargetIsa, func: &ir::Function) -> bool {
+    if isa.pointer_bits() != 32 || !isa.flags().is_pic() {
+        return false;
+    }
+
+    // A stack probe call goes through the PLT too, unless libcalls are colocated.
+    if isa.flags().probestack_enabled()
+        && isa.flags().probestack_strategy() == ProbestackStrategy::Outline
+        && !isa.flags().colocated_libcalls()
+        && max_frame_size(func) > (1 << isa.flags().probestack_size_log2())
+    {
+        return true;
+    }
+
+    func.layout.ebbs().any(|ebb| {
+        func.layout.ebb_insts(ebb).any(|inst| match func.dfg[inst] {
+            ir::InstructionData::FuncAddr { .. }
+            | ir::InstructionData::UnaryGlobalValue { .. }
+            | ir::InstructionData::UnaryConst { .. }
+            | ir::InstructionData::BranchTableBase { .. } => true,
+            ir::InstructionData::Call { func_ref, .. }
+            | ir::InstructionData::Invoke { func_ref, .. } => {
+                !func.dfg.ext_funcs[func_ref].colocated
+            }
+            _ => false,
+        })
+    })
+}
+
+/// Get the number of bytes of stack arguments that a function with signature `sig` pops when it
+/// returns.
+///
+/// Only the `tail` convention makes the callee pop its own arguments. The popped area is rounded
+/// up to the stack alignment so that a tail call can move the return address by the difference
+/// between the caller's and the callee's areas without misaligning the stack.
+pub fn callee_popped_bytes(sig: &ir::Signature) -> u32 {
+    if sig.call_conv == CallConv::Tail {
+        (sig.stack_args_size() + STACK_ALIGNMENT - 1) & !(STACK_ALIGNMENT - 1)
+    } else {
+        0
+    }
+}
+
+/// The parts of the current frame that a tail call has to move or reload before it jumps to the
+/// callee. All offsets are relative to the stack pointer after the prologue.
+pub struct TailCallFrame {
+    /// Offset of the return address pushed by our caller. The prologue pushed the frame pointer
+    /// and then the callee-saved registers right below it.
+    pub ret_addr: i32,
+    /// Offset where the callee expects to find the return address, with its stack arguments right
+    /// above it.
+    pub callee_ret_addr: i32,
+    /// Number of words of outgoing stack arguments to move up to the callee's argument area.
+    pub arg_words: u32,
+}
+
+impl TailCallFrame {
+    /// Compute the frame teardown for a tail call from `func` to a function with signature
+    /// `callee_sig`. The stack layout of `func` must have been computed.
+    pub fn new(func: &ir::Function, callee_sig: &ir::Signature) -> Self {
+        let frame_size = func
+            .stack_slots
+            .frame_size
+            .expect("tail calls need a computed stack layout") as i32;
+        let ret_addr = frame_size - 8;
+
+        // Under the `tail` convention our caller expects the stack pointer to end up right above
+        // our own stack arguments, so the callee's return address has to be moved by the
+        // difference between the two argument areas. Other conventions leave the arguments to
+        // the caller, and the verifier made sure the callee's fit in ours.
+        let callee_ret_addr = ret_addr + callee_popped_bytes(&func.signature) as i32
+            - callee_popped_bytes(callee_sig) as i32;
+
+        Self {
+            ret_addr,
+            callee_ret_addr,
+            arg_words: (callee_sig.stack_args_size() + 7) / 8,
+        }
+    }
+}
+
+/// Does `func` contain a `return_call` or `return_call_indirect` instruction?
+fn has_tail_calls(func: &ir::Function) -> bool {
+    func.layout.ebbs().any(|ebb| {
+        func.layout.last_inst(ebb).map_or(false, |inst| {
+            let opcode = func.dfg[inst].opcode();
+            opcode.is_call() && opcode.is_terminator()
+        })
+    })
+}
+
+/// Does `func` allocate stack space dynamically, moving the stack pointer after the prologue?
+fn has_dynamic_stack_alloc(func: &ir::Function) -> bool {
+    func.layout.ebbs().any(|ebb| {
+        func.layout.ebb_insts(ebb).any(|inst| {
+            let opcode = func.dfg[inst].opcode();
+            opcode == ir::Opcode::DynamicStackAlloc || opcode == ir::Opcode::X86DynamicStackAlloc
+        })
+    })
+}
+
+/// Size of the outgoing argument area at the bottom of the frame of `func`, rounded up to the
+/// stack alignment. A dynamic stack allocation re-reserves this much below the allocated block.
+pub fn outgoing_args_size(func: &ir::Function) -> u32 {
+    let size = func
+        .stack_slots
+        .values()
+        .filter(|slot| slot.kind == ir::StackSlotKind::OutgoingArg)
+        .map(|slot| slot.offset.unwrap_or(0) as u32 + slot.size)
+        .max()
+        .unwrap_or(0);
+    (size + STACK_ALIGNMENT - 1) & !(STACK_ALIGNMENT - 1)
+}
+
+pub fn prologue_epilogue(
+    func: &mut ir::Function,
+    isa: &dyn TargetIsa,
+    isa_flags: &isa_settings::Flags,
+) -> CodegenResult<()> {
+    if has_dynamic_stack_alloc(func) && has_tail_calls(func) {
+        // The tail call sequence finds the return address and the saved registers relative to
+        // the stack pointer.
+        return Err(CodegenError::Unsupported(
+            "tail calls from functions with dynamic stack allocations".to_string(),
+        ));
+    }
+
+    if isa.pointer_bits() != 64 {
+        // The tail call sequences and the stack adjustment after calls to `tail` functions are
+        // only implemented for x86-64.
+        let uses_tail_conv = func.signature.call_conv == CallConv::Tail
+            || func
+                .dfg
+                .signatures
+                .values()
+                .any(|sig| sig.call_conv == CallConv::Tail);
+        if uses_tail_conv || has_tail_calls(func) {
+            return Err(CodegenError::Unsupported(
+                "tail calls on 32-bit x86".to_string(),
+            ));
+        }
+    }
+
     match func.signature.call_conv {
         // For now, just translate fast and cold as system_v.
-        CallConv::Fast | CallConv::Cold | CallConv::SystemV => {
-            system_v_prologue_epilogue(func, isa)
+        CallConv::Fast | CallConv::Cold | CallConv::SystemV | CallConv::Tail => {
+            system_v_prologue_epilogue(func, isa)?
         }
-        CallConv::WindowsFastcall => fastcall_prologue_epilogue(func, isa),
+        CallConv::WindowsFastcall => fastcall_prologue_epilogue(func, isa)?,
         CallConv::BaldrdashSystemV | CallConv::BaldrdashWindows => {
-            baldrdash_prologue_epilogue(func, isa)
+            baldrdash_prologue_epilogue(func, isa)?
         }
         CallConv::Probestack => unimplemented!("probestack calling convention"),
     }
+
+    if isa.pointer_bits() == 32 {
+        insert_x86_32_call_return_fixups(func, isa);
+    }
+    if isa_flags.enable_ibt() {
+        insert_endbr_landing_pads(func, isa);
+    }
+    Ok(())
+}
+
+/// Insert the `endbr` landing pads needed by CET indirect branch tracking, ahead of the prologue
+/// since any function may have its address taken, and at the start of every EBB that an indirect
+/// jump through a jump table may land on.
+fn insert_endbr_landing_pads(func: &mut ir::Function, isa: &dyn TargetIsa) {
+    let mut targets = Vec::new();
+    let mut seen = EntitySet::new();
+
+    // The embedder's own prologue comes first in baldrdash functions, so the landing pad at their
+    // entry is its responsibility.
+    if !func.signature.call_conv.extends_baldrdash() {
+        let entry_ebb = func.layout.entry_block().expect("missing entry block");
+        seen.insert(entry_ebb);
+        targets.push(entry_ebb);
+    }
+    for ebb in func.layout.ebbs() {
+        for inst in func.layout.ebb_insts(ebb) {
+            if let ir::InstructionData::IndirectJump { table, .. } = func.dfg[inst] {
+                for &dest in func.jump_tables[table].iter() {
+                    if seen.insert(dest) {
+                        targets.push(dest);
+                    }
+                }
+            }
+        }
+    }
+
+    let mut pos = EncCursor::new(func, isa);
+    for ebb in targets {
+        pos.goto_first_insertion_point(ebb);
+        pos.ins().x86_endbr();
+    }
+}
+
+/// Adapt the calls and returns of a 32-bit function to what the native conventions expect, now
+/// that the registers are known:
+///
+/// - Floats returned in `st(0)` are moved from `xmm0` before returning, and to `xmm0` after
+///   calls.
+/// - The `windows_fastcall` callees pop their stack arguments, so the caller reserves the
+///   outgoing argument area again after calling them.
+fn insert_x86_32_call_return_fixups(func: &mut ir::Function, isa: &dyn TargetIsa) {
+    let insts: Vec<ir::Inst> = func
+        .layout
+        .ebbs()
+        .flat_map(|ebb| func.layout.ebb_insts(ebb))
+        .filter(|&inst| {
+            let opcode = func.dfg[inst].opcode();
+            opcode.is_return() || opcode.is_call()
+        })
+        .collect();
+
+    let mut pos = EncCursor::new(func, isa);
+    for inst in insts {
+        if pos.func.dfg[inst].opcode().is_return() {
+            if !returns_floats_in_st0(32, pos.func.signature.call_conv) {
+                continue;
+            }
+            let float_ret = pos
+                .func
+                .dfg
+                .inst_args(inst)
+                .iter()
+                .cloned()
+                .find(|&arg| pos.func.dfg.value_type(arg).is_float());
+            if let Some(arg) = float_ret {
+                pos.goto_inst(inst);
+                pos.ins().x86_fld(arg);
+            }
+            continue;
+        }
+
+        let sig_ref = match pos.func.dfg.call_signature(inst) {
+            Some(sig_ref) => sig_ref,
+            None => continue,
+        };
+        let sig = &pos.func.dfg.signatures[sig_ref];
+        let popped = if sig.call_conv == CallConv::WindowsFastcall {
+            (sig.stack_args_size() + 3) & !3
+        } else {
+            0
+        };
+        let float_ret = if returns_floats_in_st0(32, sig.call_conv) {
+            sig.returns
+                .iter()
+                .find(|ret| ret.value_type.is_float())
+                .map(|ret| (ret.value_type, ret.location))
+        } else {
+            None
+        };
+
+        pos.goto_after_inst(inst);
+        if popped > 0 {
+            pos.ins().adjust_sp_down_imm(Imm64::new(i64::from(popped)));
+        }
+        if let Some((ty, ArgumentLoc::Reg(reg))) = float_ret {
+            // The x87 register stack has to be popped even if the result is unused.
+            let ret = pos.ins().x86_fstp(ty);
+            pos.func.locations[ret] = ValueLoc::Reg(reg);
+        }
+    }
 }
 
 fn baldrdash_prologue_epilogue(func: &mut ir::Function, isa: &dyn TargetIsa) -> CodegenResult<()> {
@@ -453,12 +882,25 @@ fn baldrdash_prologue_epilogue(func: &mut ir::Function, isa: &dyn TargetIsa) ->
         "baldrdash does not expect cranelift to emit stack probes"
     );
 
+    // The embedder owns the frame layout, so we don't know how to tear the frame down.
+    if has_tail_calls(func) {
+        return Err(CodegenError::Unsupported(
+            "tail calls from baldrdash functions".to_string(),
+        ));
+    }
+    if has_dynamic_stack_alloc(func) {
+        return Err(CodegenError::Unsupported(
+            "dynamic stack allocations in baldrdash functions".to_string(),
+        ));
+    }
+
     let word_size = StackSize::from(isa.pointer_bytes());
-    let shadow_store_size = if func.signature.call_conv.extends_windows_fastcall() {
-        WIN_SHADOW_STACK_SPACE as u32
-    } else {
-        0
-    };
+    let shadow_store_size =
+        if func.signature.call_conv.extends_windows_fastcall() && isa.pointer_bits() == 64 {
+            WIN_SHADOW_STACK_SPACE as u32
+        } else {
+            0
+        };
 
     let bytes =
         StackSize::from(isa.flags().baldrdash_prologue_words()) * word_size + shadow_store_size;
@@ -502,7 +944,9 @@ struct CFAState {
 /// [1] https://docs.microsoft.com/en-us/cpp/build/x64-calling-convention
 fn fastcall_prologue_epilogue(func: &mut ir::Function, isa: &dyn TargetIsa) -> CodegenResult<()> {
     if isa.triple().pointer_width().unwrap() != PointerWidth::U64 {
-        panic!("TODO: windows-fastcall: x86-32 not implemented yet");
+        // There is no shadow store on x86, so the frame is the same as with System V. The
+        // differences are in the argument registers and the popping of the stack arguments.
+        return system_v_prologue_epilogue(func, isa);
     }
 
     let csrs = callee_saved_gprs_used(isa, func);
@@ -529,6 +973,7 @@ fn fastcall_prologue_epilogue(func: &mut ir::Function, isa: &dyn TargetIsa) -> C
     let is_leaf = func.is_leaf();
     let total_stack_size = layout_stack(&mut func.stack_slots, is_leaf, STACK_ALIGNMENT)? as i32;
     let local_stack_size = i64::from(total_stack_size - csr_stack_size);
+    set_frame_pointer_offset(func, word_size);
 
     // Add CSRs to function signature
     let reg_type = isa.pointer_type();
@@ -590,6 +1035,7 @@ fn system_v_prologue_epilogue(func: &mut ir::Function, isa: &dyn TargetIsa) -> C
     let is_leaf = func.is_leaf();
     let total_stack_size = layout_stack(&mut func.stack_slots, is_leaf, STACK_ALIGNMENT)? as i32;
     let local_stack_size = i64::from(total_stack_size - csr_stack_size);
+    set_frame_pointer_offset(func, word_size);
 
     // Add CSRs to function signature
     let reg_type = ir::Type::int(u16::from(pointer_width.bits())).unwrap();
@@ -627,6 +1073,15 @@ fn system_v_prologue_epilogue(func: &mut ir::Function, isa: &dyn TargetIsa) -> C
     Ok(())
 }
 
+/// Once the stack pointer moves after the prologue, stack slots are addressed relative to the
+/// frame pointer instead. The prologue leaves it pointing at the saved frame pointer, right below
+/// the return address.
+fn set_frame_pointer_offset(func: &mut ir::Function, word_size: usize) {
+    if has_dynamic_stack_alloc(func) {
+        func.stack_slots.frame_pointer_offset = Some(-2 * word_size as StackOffset);
+    }
+}
+
 /// Insert the prologue for a given function.
 /// This is used by common calling conventions such as System V.
 fn insert_common_prologue(
@@ -748,11 +1203,19 @@ fn insert_common_prologue(
         }
     }
 
+    // Set up the GOT base register once its caller's value is saved.
+    if isa.pointer_bits() == 32 && isa.flags().is_pic() && csrs.is_avail(GPR, RU::rbx as RegUnit) {
+        let got_base = pos.ins().x86_got_base(reg_type);
+        pos.func.locations[got_base] = ir::ValueLoc::Reg(RU::rbx as RegUnit);
+    }
+
     // Allocate stack frame storage.
     if stack_size > 0 {
-        if isa.flags().probestack_enabled()
-            && stack_size > (1 << isa.flags().probestack_size_log2())
-        {
+        let probe = isa.flags().probestack_enabled()
+            && stack_size > (1 << isa.flags().probestack_size_log2());
+        if probe && isa.flags().probestack_strategy() == ProbestackStrategy::Inline {
+            insert_inline_probestack(pos, stack_size, reg_type, csrs, isa);
+        } else if probe {
             // Emit a stack probe.
             let rax = RU::rax as RegUnit;
             let rax_val = ir::ValueLoc::Reg(rax);
@@ -796,6 +1259,57 @@ fn insert_common_prologue(
     cfa_state
 }
 
+/// Probe the pages of a `stack_size` byte frame with inline code rather than the probestack
+/// function, then allocate it.
+///
+/// The pages are touched from the top down, each one at the stack pointer after moving it down,
+/// like the probestack function does. Linux kernels before 4.20 fault on accesses to the main
+/// thread's stack too far below the stack pointer, so the probes never get ahead of it. Frames of
+/// a few pages get a probe per page, and larger ones a loop that moves the stack pointer back up
+/// before the whole frame is allocated at once.
+fn insert_inline_probestack(
+    pos: &mut EncCursor,
+    stack_size: i64,
+    reg_type: ir::types::Type,
+    csrs: &RegisterSet,
+    isa: &dyn TargetIsa,
+) {
+    let page_size = 1i64 << isa.flags().probestack_size_log2();
+    let pages = stack_size / page_size;
+    if pages <= PROBESTACK_MAX_UNROLL {
+        let mut last = None;
+        for _ in 0..pages {
+            pos.ins().adjust_sp_down_imm(Imm64::new(page_size));
+            last = Some(pos.ins().x86_probe_page(Imm64::new(0)));
+        }
+        let rest = stack_size - pages * page_size;
+        if rest > 0 {
+            last = Some(pos.ins().adjust_sp_down_imm(Imm64::new(rest)));
+        }
+        pos.func.prologue_end = last;
+        return;
+    }
+
+    // Like the probestack function, the loop takes the size in %rax, where the unwind information
+    // expects to find it.
+    let rax = ir::ValueLoc::Reg(RU::rax as RegUnit);
+    let size = pos.ins().iconst(reg_type, stack_size);
+    pos.func.locations[size] = rax;
+
+    // %r11 may be clobbered under all supported x86-64 calling conventions. On x86-32,
+    // `callee_saved_gprs_used` makes sure %esi has been pushed for us.
+    let scratch = if isa.pointer_bits() == 64 {
+        RU::r11 as RegUnit
+    } else {
+        debug_assert!(csrs.is_avail(GPR, RU::rsi as RegUnit));
+        RU::rsi as RegUnit
+    };
+    let probe = pos.ins().x86_probe_stack(size, Imm64::new(page_size));
+    pos.func.locations[probe] = ir::ValueLoc::Reg(scratch);
+
+    pos.func.prologue_end = Some(pos.ins().adjust_sp_down(size));
+}
+
 /// Insert a check that generates a trap if the stack pointer goes
 /// below a value in `stack_limit_arg`.
 fn insert_stack_check(pos: &mut EncCursor, stack_size: i64, stack_limit_arg: ir::Value) {
@@ -877,7 +1391,16 @@ fn insert_common_epilogue(
     mut cfa_state: Option<CFAState>,
 ) {
     let word_size = isa.pointer_bytes() as isize;
-    if stack_size > 0 {
+    if pos.func.stack_slots.frame_pointer_offset.is_some() {
+        // The stack pointer has moved by an unknown amount, so recover it from the frame pointer
+        // and point it back at the callee-saved registers.
+        pos.ins()
+            .copy_special(RU::rbp as RegUnit, RU::rsp as RegUnit);
+        let csr_size = csrs.iter(GPR).len() as i64 * word_size as i64;
+        if csr_size > 0 {
+            pos.ins().adjust_sp_down_imm(Imm64::new(csr_size));
+        }
+    } else if stack_size > 0 {
         pos.ins().adjust_sp_up_imm(Imm64::new(stack_size));
     }
 
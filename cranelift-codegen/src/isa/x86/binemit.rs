@@ -1,12 +1,18 @@
 //! Emitting binary x86 machine code.
 
-use super::enc_tables::{needs_offset, needs_sib_byte};
+use super::abi::{callee_popped_bytes, outgoing_args_size, TailCallFrame};
+use super::enc_tables::{needs_offset, needs_sib_byte, needs_vex3};
 use super::registers::RU;
 use crate::binemit::{bad_encoding, CodeSink, Reloc};
 use crate::ir::condcodes::{CondCode, FloatCC, IntCC};
-use crate::ir::{Constant, Ebb, Function, Inst, InstructionData, JumpTable, Opcode, TrapCode};
+use crate::ir::immediates::Imm64;
+use crate::ir::{
+    ArgumentLoc, ArgumentPurpose, Constant, Ebb, ExternalName, Function, Inst, InstructionData,
+    JumpTable, LibCall, Opcode, SigRef, SourceLoc, StackSlot, TrapCode,
+};
 use crate::isa::{RegUnit, StackBase, StackBaseMask, StackRef, TargetIsa};
 use crate::regalloc::RegDiversions;
+use alloc::vec::Vec;
 
 use cranelift_codegen_shared::isa::x86::EncodingBits;
 
@@ -22,6 +28,12 @@ fn stk_base(base: StackBase) -> RegUnit {
     ru as RegUnit
 }
 
+// Get a reference to the stack slot `ss`, relative to the frame pointer if the stack pointer of
+// `func` isn't fixed.
+fn stk_ref(ss: StackSlot, func: &Function) -> StackRef {
+    StackRef::masked(ss, StackBaseMask(3), &func.stack_slots).unwrap()
+}
+
 // Mandatory prefix bytes for Mp* opcodes.
 const PREFIX: [u8; 3] = [0x66, 0xf3, 0xf2];
 
@@ -206,6 +218,46 @@ fn put_rexmp3<CS: CodeSink + ?Sized>(bits: u16, rex: u8, sink: &mut CS) {
     sink.put1(bits as u8);
 }
 
+/// Emit a VEX prefix followed by the opcode byte.
+///
+/// The R, X, and B bits are taken from a `rex` byte computed by the functions above, and are
+/// stored inverted. The `vvvv` register is an additional source or destination operand; it is
+/// stored inverted too, so an unused field reads as 0b1111. The two-byte form of the prefix is
+/// used whenever possible.
+fn vex_prefix<CS: CodeSink + ?Sized>(
+    bits: u16,
+    l: u8,
+    rex: u8,
+    vvvv: Option<RegUnit>,
+    sink: &mut CS,
+) {
+    debug_assert_eq!(rex & 0xf8, BASE_REX);
+    let enc = EncodingBits::from(bits);
+    let vvvv = vvvv.map_or(0, |reg| reg as u8 & 0xf);
+    // W/R vvvv L pp, with W or R in the top bit.
+    let low = ((!vvvv & 0xf) << 3) | (l << 2) | enc.pp();
+    if needs_vex3(bits, rex & 0b011 != 0) {
+        sink.put1(0xc4);
+        // R X B mmmmm.
+        sink.put1(((!rex & 0b111) << 5) | enc.mm());
+        sink.put1((enc.rex_w() << 7) | low);
+    } else {
+        sink.put1(0xc5);
+        sink.put1(((!rex & 0b100) << 5) | low);
+    }
+    sink.put1(bits as u8);
+}
+
+/// Emit a VEX-prefixed opcode for a scalar or 128-bit vector operation.
+fn put_vex128<CS: CodeSink + ?Sized>(bits: u16, rex: u8, vvvv: Option<RegUnit>, sink: &mut CS) {
+    vex_prefix(bits, 0, rex, vvvv, sink);
+}
+
+/// Emit a VEX-prefixed opcode for a 256-bit vector operation.
+fn put_vex256<CS: CodeSink + ?Sized>(bits: u16, rex: u8, vvvv: Option<RegUnit>, sink: &mut CS) {
+    vex_prefix(bits, 1, rex, vvvv, sink);
+}
+
 /// Emit a ModR/M byte for reg-reg operands.
 fn modrm_rr<CS: CodeSink + ?Sized>(rm: RegUnit, reg: RegUnit, sink: &mut CS) {
     let reg = reg as u8 & 7;
@@ -394,3 +446,365 @@ fn const_disp4<CS: CodeSink + ?Sized>(constant: Constant, func: &Function, sink:
     sink.put4(delta);
     sink.reloc_constant(Reloc::X86PCRelRodata4, offset);
 }
+
+/// Emit a four-byte reference to `jt`, relocated against the function itself.
+///
+/// 32-bit code can't address the trailing jump tables relative to the instruction pointer, so
+/// this uses an absolute or GOT-relative `reloc` instead.
+fn jt_ref4<CS: CodeSink + ?Sized>(reloc: Reloc, jt: JumpTable, func: &Function, sink: &mut CS) {
+    sink.reloc_external(reloc, &func.name, func.jt_offsets[jt].into());
+    sink.put4(0);
+}
+
+/// Emit a four-byte reference to `constant`, relocated against the function itself.
+fn const_ref4<CS: CodeSink + ?Sized>(
+    reloc: Reloc,
+    constant: Constant,
+    func: &Function,
+    sink: &mut CS,
+) {
+    let offset = func.dfg.constants.get_offset(constant);
+    sink.reloc_external(reloc, &func.name, offset.into());
+    sink.put4(0);
+}
+
+/// Emit `sub rsp, imm32` to re-allocate the stack arguments that a `tail` convention callee
+/// popped, if any.
+fn realloc_callee_popped_args<CS: CodeSink + ?Sized>(
+    func: &Function,
+    sig_ref: SigRef,
+    sink: &mut CS,
+) {
+    let popped = callee_popped_bytes(&func.dfg.signatures[sig_ref]);
+    if popped > 0 {
+        sink.put1(0x48);
+        sink.put1(0x81);
+        modrm_rr(RU::rsp as RegUnit, 5, sink);
+        sink.put4(popped);
+    }
+}
+
+/// Get the number of bytes emitted by `tail_call_epilogue`.
+pub fn tail_call_epilogue_size(func: &Function, sig_ref: SigRef) -> u8 {
+    let frame = TailCallFrame::new(func, &func.dfg.signatures[sig_ref]);
+    let csrs = func
+        .signature
+        .params
+        .iter()
+        .filter(|param| param.purpose == ArgumentPurpose::CalleeSaved)
+        .count() as u8;
+
+    // The return address, frame pointer and callee-saved register loads, and the final store and
+    // `lea`, are all 8 bytes. The argument copy loop adds 27 bytes.
+    let copy_loop = if frame.arg_words > 0 { 27 } else { 0 };
+    8 * (csrs + 4) + copy_loop
+}
+
+/// Emit a 64-bit instruction with `reg` and `[rsp + disp32]` operands, like `mov` or `lea`.
+fn rsp_disp32_op<CS: CodeSink + ?Sized>(opcode: u8, reg: RegUnit, disp: i32, sink: &mut CS) {
+    sink.put1(rex2(RU::rsp as RegUnit, reg) | 0b1000);
+    sink.put1(opcode);
+    modrm_sib_disp32(reg, sink);
+    sib_noindex(RU::rsp as RegUnit, sink);
+    sink.put4(disp as u32);
+}
+
+/// Tear down the current frame before a tail call to a function with signature `sig_ref`.
+///
+/// This undoes the System V prologue without moving the stack pointer until the very end, so
+/// everything we still need to read stays above it: the saved registers are reloaded from where
+/// the prologue pushed them, the outgoing stack arguments are copied up to the callee's argument
+/// area, highest word first since the areas can overlap, and our return address is stored right
+/// below them. Only `rax` and `r10` are clobbered, neither of which passes arguments.
+fn tail_call_epilogue<CS: CodeSink + ?Sized>(func: &Function, sig_ref: SigRef, sink: &mut CS) {
+    let frame = TailCallFrame::new(func, &func.dfg.signatures[sig_ref]);
+    let rax = RU::rax as RegUnit;
+    let r10 = RU::r10 as RegUnit;
+    let rsp = RU::rsp as RegUnit;
+
+    // mov rax, [rsp + ret_addr]
+    rsp_disp32_op(0x8b, rax, frame.ret_addr, sink);
+
+    // Reload rbp and the callee-saved registers, in the order they were pushed.
+    rsp_disp32_op(0x8b, RU::rbp as RegUnit, frame.ret_addr - 8, sink);
+    let mut offset = frame.ret_addr - 16;
+    for param in &func.signature.params {
+        if param.purpose == ArgumentPurpose::CalleeSaved {
+            if let ArgumentLoc::Reg(reg) = param.location {
+                rsp_disp32_op(0x8b, reg, offset, sink);
+                offset -= 8;
+            }
+        }
+    }
+
+    if frame.arg_words > 0 {
+        // mov r10d, arg_words
+        sink.put1(rex1(r10));
+        sink.put1(0xb8 | (r10 as u8 & 7));
+        sink.put4(frame.arg_words);
+
+        // push qword [rsp + r10 * 8 - 8]
+        sink.put1(rex3(rsp, 0, r10));
+        sink.put1(0xff);
+        modrm_sib_disp32(6, sink);
+        sib(3, r10, rsp, sink);
+        sink.put4(-8i32 as u32);
+
+        // pop qword [rsp + r10 * 8 + callee_ret_addr]
+        sink.put1(rex3(rsp, 0, r10));
+        sink.put1(0x8f);
+        modrm_sib_disp32(0, sink);
+        sib(3, r10, rsp, sink);
+        sink.put4(frame.callee_ret_addr as u32);
+
+        // dec r10
+        sink.put1(rex1(r10) | 0b1000);
+        sink.put1(0xff);
+        modrm_rr(r10, 1, sink);
+
+        // jnz back to the push.
+        sink.put1(0x75);
+        sink.put1(-21i8 as u8);
+    }
+
+    // mov [rsp + callee_ret_addr], rax
+    rsp_disp32_op(0x89, rax, frame.callee_ret_addr, sink);
+
+    // lea rsp, [rsp + callee_ret_addr]
+    rsp_disp32_op(0x8d, rsp, frame.callee_ret_addr, sink);
+}
+
+/// Size of the code emitted by `dynamic_stack_alloc` after the initial `sub`.
+pub fn dynamic_stack_alloc_extra_size(func: &Function, bits: u16) -> u8 {
+    let rex = EncodingBits::from(bits).rex_w();
+    let outgoing = if outgoing_args_size(func) > 0 {
+        6 + rex
+    } else {
+        0
+    };
+    (6 + rex) + (2 + rex) + outgoing
+}
+
+/// Allocate the number of bytes in `size` on the stack and put the address of the allocation in
+/// `addr`.
+///
+/// The stack pointer is aligned down to `align`, and never less than the 16 bytes calls expect.
+/// The outgoing argument area is addressed relative to the stack pointer, so it is reserved again
+/// below the new allocation.
+fn dynamic_stack_alloc<CS: CodeSink + ?Sized>(
+    bits: u16,
+    size: RegUnit,
+    addr: RegUnit,
+    align: Imm64,
+    func: &Function,
+    sink: &mut CS,
+) {
+    let rex_w = EncodingBits::from(bits).rex_w() == 1;
+    let rsp = RU::rsp as RegUnit;
+    let align: i64 = align.into();
+    let align = align.max(16);
+    debug_assert!(align <= 1 << 31, "dynamic_stack_alloc alignment too large");
+
+    // sub rsp, size
+    if rex_w {
+        sink.put1(rex2(rsp, size) | 0b1000);
+    }
+    sink.put1(0x29);
+    modrm_rr(rsp, size, sink);
+
+    // and rsp, -align
+    if rex_w {
+        sink.put1(rex1(rsp) | 0b1000);
+    }
+    sink.put1(0x81);
+    modrm_rr(rsp, 4, sink);
+    sink.put4(-align as u32);
+
+    // mov addr, rsp
+    if rex_w {
+        sink.put1(rex2(addr, rsp) | 0b1000);
+    }
+    sink.put1(0x89);
+    modrm_rr(addr, rsp, sink);
+
+    let outgoing = outgoing_args_size(func);
+    if outgoing > 0 {
+        // sub rsp, outgoing
+        if rex_w {
+            sink.put1(rex1(rsp) | 0b1000);
+        }
+        sink.put1(0x81);
+        modrm_rr(rsp, 5, sink);
+        sink.put4(outgoing);
+    }
+}
+
+/// Size of the code emitted by `probe_stack_loop` after the initial `mov`.
+pub fn probe_stack_loop_extra_size(bits: u16) -> u8 {
+    let rex = EncodingBits::from(bits).rex_w();
+    // cmp, jbe, sub, or, sub, jmp, sub, or, add.
+    (6 + rex) + 2 + (6 + rex) + (4 + rex) + (6 + rex) + 2 + (2 + rex) + (4 + rex) + (2 + rex)
+}
+
+/// Touch each `page`-sized page in the `size` bytes below the stack pointer, from the top down,
+/// once `size` has been copied to `scratch`.
+///
+/// Like the probestack function, this moves the stack pointer down a page at a time and touches
+/// the word it points to, so no access is ever further below the stack pointer than the kernel
+/// allows for stack growth. `scratch` counts down the bytes left to probe, and the stack pointer
+/// is moved back up by `size` at the end.
+fn probe_stack_loop<CS: CodeSink + ?Sized>(
+    bits: u16,
+    size: RegUnit,
+    scratch: RegUnit,
+    page: Imm64,
+    sink: &mut CS,
+) {
+    let rex = EncodingBits::from(bits).rex_w();
+    let rsp = RU::rsp as RegUnit;
+    let page: i64 = page.into();
+    debug_assert!(
+        page > 0 && page <= 1 << 30,
+        "probestack page size out of range"
+    );
+
+    // 1: cmp scratch, page
+    if rex == 1 {
+        sink.put1(rex1(scratch) | 0b1000);
+    }
+    sink.put1(0x81);
+    modrm_rr(scratch, 7, sink);
+    sink.put4(page as u32);
+
+    // jbe 2f
+    let skip = (6 + rex) + (4 + rex) + (6 + rex) + 2;
+    sink.put1(0x76);
+    sink.put1(skip);
+
+    // sub rsp, page
+    if rex == 1 {
+        sink.put1(rex1(rsp) | 0b1000);
+    }
+    sink.put1(0x81);
+    modrm_rr(rsp, 5, sink);
+    sink.put4(page as u32);
+
+    // or [rsp], 0
+    probe_stack_top(rex, sink);
+
+    // sub scratch, page
+    if rex == 1 {
+        sink.put1(rex1(scratch) | 0b1000);
+    }
+    sink.put1(0x81);
+    modrm_rr(scratch, 5, sink);
+    sink.put4(page as u32);
+
+    // jmp 1b
+    let back = (6 + rex) + 2 + skip;
+    sink.put1(0xeb);
+    sink.put1((-i16::from(back)) as u8);
+
+    // 2: sub rsp, scratch
+    if rex == 1 {
+        sink.put1(rex2(rsp, scratch) | 0b1000);
+    }
+    sink.put1(0x29);
+    modrm_rr(rsp, scratch, sink);
+
+    // or [rsp], 0
+    probe_stack_top(rex, sink);
+
+    // add rsp, size
+    if rex == 1 {
+        sink.put1(rex2(rsp, size) | 0b1000);
+    }
+    sink.put1(0x01);
+    modrm_rr(rsp, size, sink);
+}
+
+/// Touch the word at the stack pointer with `or [rsp], 0`.
+fn probe_stack_top<CS: CodeSink + ?Sized>(rex: u8, sink: &mut CS) {
+    let rsp = RU::rsp as RegUnit;
+    if rex == 1 {
+        sink.put1(rex1(rsp) | 0b1000);
+    }
+    sink.put1(0x83);
+    modrm_sib(1, sink);
+    sib_noindex(rsp, sink);
+    sink.put1(0);
+}
+
+/// The loop capturing the speculative execution of a retpoline's mispredicted `ret`:
+/// `1: pause; lfence; jmp 1b`.
+const RETPOLINE_CAPTURE: [u8; 7] = [0xf3, 0x90, 0x0f, 0xae, 0xe8, 0xeb, 0xf9];
+
+/// Size of the `mov reg, (%rsp)` of a retpoline, whose encoding `bits` describes.
+fn retpoline_mov_size(bits: u16) -> u8 {
+    3 + EncodingBits::from(bits).rex_w()
+}
+
+/// Emit the retpoline for an indirect call up to its `mov reg, (%rsp)`:
+///
+/// ```text
+///     jmp 3f
+/// 1:  call 2f
+///     <capture loop>
+/// 2:
+/// ```
+fn retpoline_call_head<CS: CodeSink + ?Sized>(mov_size: u8, srcloc: SourceLoc, sink: &mut CS) {
+    sink.put1(0xeb);
+    sink.put1(5 + RETPOLINE_CAPTURE.len() as u8 + mov_size + 1);
+    sink.trap(TrapCode::StackOverflow, srcloc);
+    sink.put1(0xe8);
+    sink.put4(RETPOLINE_CAPTURE.len() as u32);
+    for &byte in &RETPOLINE_CAPTURE {
+        sink.put1(byte);
+    }
+}
+
+/// Emit the rest of the retpoline for an indirect call after its `mov reg, (%rsp)`:
+///
+/// ```text
+///     ret
+/// 3:  call 1b
+/// ```
+fn retpoline_call_tail<CS: CodeSink + ?Sized>(mov_size: u8, srcloc: SourceLoc, sink: &mut CS) {
+    sink.put1(0xc3);
+    sink.trap(TrapCode::StackOverflow, srcloc);
+    sink.put1(0xe8);
+    let back = 5 + RETPOLINE_CAPTURE.len() as i32 + i32::from(mov_size) + 1 + 5;
+    sink.put4(-back as u32);
+}
+
+/// Emit the retpoline for an indirect jump up to its `mov reg, (%rsp)`:
+///
+/// ```text
+///     call 1f
+///     <capture loop>
+/// 1:
+/// ```
+fn retpoline_jump_head<CS: CodeSink + ?Sized>(srcloc: SourceLoc, sink: &mut CS) {
+    sink.trap(TrapCode::StackOverflow, srcloc);
+    sink.put1(0xe8);
+    sink.put4(RETPOLINE_CAPTURE.len() as u32);
+    for &byte in &RETPOLINE_CAPTURE {
+        sink.put1(byte);
+    }
+}
+
+/// The machine code of the retpoline thunk shared by a module, branching to the address in
+/// `%r11` on x86-64 and in `%eax` on x86-32.
+pub fn retpoline_thunk(pointer_bits: u8) -> Vec<u8> {
+    let mut code = vec![0xe8];
+    code.extend_from_slice(&(RETPOLINE_CAPTURE.len() as u32).to_le_bytes());
+    code.extend_from_slice(&RETPOLINE_CAPTURE);
+    // mov r11, (%rsp) or mov eax, (%esp)
+    if pointer_bits == 64 {
+        code.extend_from_slice(&[0x4c, 0x89, 0x1c, 0x24]);
+    } else {
+        code.extend_from_slice(&[0x89, 0x04, 0x24]);
+    }
+    code.push(0xc3);
+    code
+}
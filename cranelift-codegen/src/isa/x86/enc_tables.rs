@@ -1,5 +1,9 @@
 //! Encoding tables for x86 ISAs.
 
+use super::abi::callee_popped_bytes;
+use super::binemit::{
+    dynamic_stack_alloc_extra_size, probe_stack_loop_extra_size, tail_call_epilogue_size,
+};
 use super::registers::*;
 use crate::bitset::BitSet;
 use crate::cursor::{Cursor, FuncCursor};
@@ -13,6 +17,7 @@ use crate::isa::encoding::base_size;
 use crate::isa::encoding::{Encoding, RecipeSizing};
 use crate::isa::RegUnit;
 use crate::isa::{self, TargetIsa};
+use crate::legalizer::replace_with_libcall;
 use crate::predicates;
 use crate::regalloc::RegDiversions;
 
@@ -40,6 +45,15 @@ pub fn needs_sib_byte_or_offset(reg: RegUnit) -> bool {
     needs_sib_byte(reg) || needs_offset(reg)
 }
 
+/// Whether the three-byte VEX prefix is needed for an encoding.
+///
+/// The two-byte VEX prefix has no equivalent of REX.X and REX.B, it has no VEX.W bit, and it only
+/// supports the 0F opcode map.
+pub fn needs_vex3(bits: u16, extended_xb: bool) -> bool {
+    let enc = EncodingBits::from(bits);
+    extended_xb || enc.mm() != 1 || enc.rex_w() != 0
+}
+
 fn test_input(
     op_index: usize,
     inst: Inst,
@@ -122,6 +136,131 @@ fn size_plus_maybe_sib_or_offset_for_inreg_1(
     let needs_sib_or_offset = test_input(1, inst, divert, func, needs_sib_byte_or_offset);
     sizing.base_size + if needs_sib_or_offset { 1 } else { 0 }
 }
+fn size_plus_maybe_sib_or_offset_for_inreg_2(
+    sizing: &RecipeSizing,
+    _enc: Encoding,
+    inst: Inst,
+    divert: &RegDiversions,
+    func: &Function,
+) -> u8 {
+    let needs_sib_or_offset = test_input(2, inst, divert, func, needs_sib_byte_or_offset);
+    sizing.base_size + if needs_sib_or_offset { 1 } else { 0 }
+}
+
+/// Infers whether the three-byte VEX prefix will be emitted, for use with an r/m input reg.
+fn size_with_inferred_vex_for_inreg0(
+    sizing: &RecipeSizing,
+    enc: Encoding,
+    inst: Inst,
+    divert: &RegDiversions,
+    func: &Function,
+) -> u8 {
+    let needs_vex3 = needs_vex3(
+        enc.bits(),
+        test_input(0, inst, divert, func, is_extended_reg),
+    );
+    sizing.base_size + if needs_vex3 { 1 } else { 0 }
+}
+
+/// Infers whether the three-byte VEX prefix will be emitted, for use with an r/m input reg.
+fn size_with_inferred_vex_for_inreg1(
+    sizing: &RecipeSizing,
+    enc: Encoding,
+    inst: Inst,
+    divert: &RegDiversions,
+    func: &Function,
+) -> u8 {
+    let needs_vex3 = needs_vex3(
+        enc.bits(),
+        test_input(1, inst, divert, func, is_extended_reg),
+    );
+    sizing.base_size + if needs_vex3 { 1 } else { 0 }
+}
+
+/// Infers whether the three-byte VEX prefix will be emitted for a `regmove`, whose r/m operand is
+/// the source register.
+fn size_with_inferred_vex_for_regmove(
+    sizing: &RecipeSizing,
+    enc: Encoding,
+    inst: Inst,
+    _divert: &RegDiversions,
+    func: &Function,
+) -> u8 {
+    let extended = match func.dfg[inst] {
+        ir::InstructionData::RegMove { src, .. } => is_extended_reg(src),
+        _ => panic!("Expected a regmove"),
+    };
+    sizing.base_size
+        + if needs_vex3(enc.bits(), extended) {
+            1
+        } else {
+            0
+        }
+}
+
+/// Infers whether the three-byte VEX prefix will be emitted when addressing the stack, whose base
+/// registers are never extended.
+fn size_with_inferred_vex_for_stack(
+    sizing: &RecipeSizing,
+    enc: Encoding,
+    _inst: Inst,
+    _divert: &RegDiversions,
+    _func: &Function,
+) -> u8 {
+    sizing.base_size + if needs_vex3(enc.bits(), false) { 1 } else { 0 }
+}
+
+/// Infers the sizes of the VEX prefix and of the SIB byte or offset for a base address register.
+fn size_with_inferred_vex_plus_maybe_sib_or_offset_for_inreg_0(
+    sizing: &RecipeSizing,
+    enc: Encoding,
+    inst: Inst,
+    divert: &RegDiversions,
+    func: &Function,
+) -> u8 {
+    size_with_inferred_vex_for_inreg0(sizing, enc, inst, divert, func)
+        + size_plus_maybe_sib_or_offset_for_inreg_0(sizing, enc, inst, divert, func)
+        - sizing.base_size
+}
+
+/// Infers the sizes of the VEX prefix and of the SIB byte or offset for a base address register.
+fn size_with_inferred_vex_plus_maybe_sib_or_offset_for_inreg_1(
+    sizing: &RecipeSizing,
+    enc: Encoding,
+    inst: Inst,
+    divert: &RegDiversions,
+    func: &Function,
+) -> u8 {
+    size_with_inferred_vex_for_inreg1(sizing, enc, inst, divert, func)
+        + size_plus_maybe_sib_or_offset_for_inreg_1(sizing, enc, inst, divert, func)
+        - sizing.base_size
+}
+
+/// Infers the sizes of the VEX prefix and of the SIB byte for a base address register.
+fn size_with_inferred_vex_plus_maybe_sib_for_inreg_0(
+    sizing: &RecipeSizing,
+    enc: Encoding,
+    inst: Inst,
+    divert: &RegDiversions,
+    func: &Function,
+) -> u8 {
+    size_with_inferred_vex_for_inreg0(sizing, enc, inst, divert, func)
+        + size_plus_maybe_sib_for_inreg_0(sizing, enc, inst, divert, func)
+        - sizing.base_size
+}
+
+/// Infers the sizes of the VEX prefix and of the SIB byte for a base address register.
+fn size_with_inferred_vex_plus_maybe_sib_for_inreg_1(
+    sizing: &RecipeSizing,
+    enc: Encoding,
+    inst: Inst,
+    divert: &RegDiversions,
+    func: &Function,
+) -> u8 {
+    size_with_inferred_vex_for_inreg1(sizing, enc, inst, divert, func)
+        + size_plus_maybe_sib_for_inreg_1(sizing, enc, inst, divert, func)
+        - sizing.base_size
+}
 
 /// Infers whether a dynamic REX prefix will be emitted, for use with one input reg.
 ///
@@ -215,6 +354,76 @@ fn size_with_inferred_rex_for_cmov(
     sizing.base_size + if needs_rex { 1 } else { 0 }
 }
 
+/// Accounts for the `sub rsp` re-allocating the stack arguments popped by a `tail` callee.
+fn size_plus_callee_popped_args(
+    sizing: &RecipeSizing,
+    _enc: Encoding,
+    inst: Inst,
+    _divert: &RegDiversions,
+    func: &Function,
+) -> u8 {
+    let sig_ref = func
+        .dfg
+        .call_signature(inst)
+        .expect("call without a signature");
+    let popped = callee_popped_bytes(&func.dfg.signatures[sig_ref]);
+    sizing.base_size + if popped > 0 { 7 } else { 0 }
+}
+
+/// Accounts for the `ret imm16` popping the stack arguments of a `tail` function.
+fn size_plus_popped_args(
+    sizing: &RecipeSizing,
+    _enc: Encoding,
+    _inst: Inst,
+    _divert: &RegDiversions,
+    func: &Function,
+) -> u8 {
+    sizing.base_size
+        + if callee_popped_bytes(&func.signature) > 0 {
+            2
+        } else {
+            0
+        }
+}
+
+/// Accounts for the frame teardown that precedes the jump of a tail call.
+fn size_plus_tail_call_epilogue(
+    sizing: &RecipeSizing,
+    _enc: Encoding,
+    inst: Inst,
+    _divert: &RegDiversions,
+    func: &Function,
+) -> u8 {
+    let sig_ref = func
+        .dfg
+        .call_signature(inst)
+        .expect("call without a signature");
+    sizing.base_size + tail_call_epilogue_size(func, sig_ref)
+}
+
+/// Accounts for the stack pointer alignment and the copy that follow the `sub` of a dynamic stack
+/// allocation.
+fn size_dynamic_stack_alloc(
+    sizing: &RecipeSizing,
+    enc: Encoding,
+    _inst: Inst,
+    _divert: &RegDiversions,
+    func: &Function,
+) -> u8 {
+    sizing.base_size + dynamic_stack_alloc_extra_size(func, enc.bits())
+}
+
+/// Accounts for the loop that follows the `mov` of an inline stack probe.
+fn size_probe_stack_loop(
+    sizing: &RecipeSizing,
+    enc: Encoding,
+    _inst: Inst,
+    _divert: &RegDiversions,
+    _func: &Function,
+) -> u8 {
+    sizing.base_size + probe_stack_loop_extra_size(enc.bits())
+}
+
 /// If the value's definition is a constant immediate, returns its unpacked value, or None
 /// otherwise.
 fn maybe_iconst_imm(pos: &FuncCursor, value: ir::Value) -> Option<i64> {
@@ -512,7 +721,7 @@ fn expand_fcvt_from_uint(
     inst: ir::Inst,
     func: &mut ir::Function,
     cfg: &mut ControlFlowGraph,
-    _isa: &dyn TargetIsa,
+    isa: &dyn TargetIsa,
 ) {
     let x;
     match func.dfg[inst] {
@@ -528,15 +737,29 @@ fn expand_fcvt_from_uint(
     let mut pos = FuncCursor::new(func).at_inst(inst);
     pos.use_srcloc(inst);
 
-    // Conversion from an unsigned int smaller than 64bit is easy on x86-64.
+    // Conversion from an unsigned int narrower than the widest signed conversion is easy.
+    let widest = if isa.pointer_bits() == 64 {
+        ir::types::I64
+    } else {
+        ir::types::I32
+    };
     match xty {
-        ir::types::I8 | ir::types::I16 | ir::types::I32 => {
-            // TODO: This should be guarded by an ISA check.
-            let wide = pos.ins().uextend(ir::types::I64, x);
+        ir::types::I8 | ir::types::I16 | ir::types::I32 if xty.bits() < widest.bits() => {
+            let wide = pos.ins().uextend(widest, x);
             pos.func.dfg.replace(inst).fcvt_from_sint(ty, wide);
             return;
         }
-        ir::types::I64 => {}
+        ir::types::I32 => {}
+        ir::types::I64 if widest == ir::types::I64 => {}
+        ir::types::I64 => {
+            // There is no signed conversion from `i64` to halve into either.
+            let libcall = match ty {
+                ir::types::F32 => ir::LibCall::FcvtFromUintI64F32,
+                _ => ir::LibCall::FcvtFromUintI64F64,
+            };
+            replace_with_libcall(inst, libcall, pos.func, isa);
+            return;
+        }
         _ => unimplemented!(),
     }
 
@@ -821,11 +1044,35 @@ fn expand_fcvt_to_sint_sat(
     cfg.recompute_ebb(pos.func, done_ebb);
 }
 
+/// Does `value` occupy a pair of registers, so that it can't be compared with the flags?
+fn needs_register_pair(pos: &FuncCursor, isa: &dyn TargetIsa, value: ir::Value) -> bool {
+    pos.func.dfg.value_type(value).bits() > u16::from(isa.pointer_bits())
+}
+
+/// Branch to `dest` if the comparison `cond` of `x` with `imm` holds.
+fn br_icmp_imm(
+    pos: &mut FuncCursor,
+    isa: &dyn TargetIsa,
+    cond: IntCC,
+    x: ir::Value,
+    imm: i64,
+    dest: ir::Ebb,
+    args: &[ir::Value],
+) {
+    if needs_register_pair(pos, isa, x) {
+        let cmp = pos.ins().icmp_imm(cond, x, imm);
+        pos.ins().brnz(cmp, dest, args);
+    } else {
+        let flags = pos.ins().ifcmp_imm(x, imm);
+        pos.ins().brif(cond, flags, dest, args);
+    }
+}
+
 fn expand_fcvt_to_uint(
     inst: ir::Inst,
     func: &mut ir::Function,
     cfg: &mut ControlFlowGraph,
-    _isa: &dyn TargetIsa,
+    isa: &dyn TargetIsa,
 ) {
     use crate::ir::immediates::{Ieee32, Ieee64};
 
@@ -884,9 +1131,15 @@ fn expand_fcvt_to_uint(
 
     // Now we know that x < 2^(N-1) and not NaN.
     let sres = pos.ins().x86_cvtt2si(ty, x);
-    let is_neg = pos.ins().ifcmp_imm(sres, 0);
-    pos.ins()
-        .brif(IntCC::SignedGreaterThanOrEqual, is_neg, done, &[sres]);
+    br_icmp_imm(
+        &mut pos,
+        isa,
+        IntCC::SignedGreaterThanOrEqual,
+        sres,
+        0,
+        done,
+        &[sres],
+    );
     pos.ins().jump(below_zero_ebb, &[]);
 
     pos.insert_ebb(below_zero_ebb);
@@ -896,9 +1149,14 @@ fn expand_fcvt_to_uint(
     pos.insert_ebb(large);
     let adjx = pos.ins().fsub(x, pow2nm1);
     let lres = pos.ins().x86_cvtt2si(ty, adjx);
-    let is_neg = pos.ins().ifcmp_imm(lres, 0);
-    pos.ins()
-        .trapif(IntCC::SignedLessThan, is_neg, ir::TrapCode::IntegerOverflow);
+    if needs_register_pair(&pos, isa, lres) {
+        let is_neg = pos.ins().icmp_imm(IntCC::SignedLessThan, lres, 0);
+        pos.ins().trapnz(is_neg, ir::TrapCode::IntegerOverflow);
+    } else {
+        let is_neg = pos.ins().ifcmp_imm(lres, 0);
+        pos.ins()
+            .trapif(IntCC::SignedLessThan, is_neg, ir::TrapCode::IntegerOverflow);
+    }
     let lfinal = pos.ins().iadd_imm(lres, 1 << (ty.lane_bits() - 1));
 
     // Recycle the original instruction as a jump.
@@ -919,7 +1177,7 @@ fn expand_fcvt_to_uint_sat(
     inst: ir::Inst,
     func: &mut ir::Function,
     cfg: &mut ControlFlowGraph,
-    _isa: &dyn TargetIsa,
+    isa: &dyn TargetIsa,
 ) {
     use crate::ir::immediates::{Ieee32, Ieee64};
 
@@ -981,9 +1239,15 @@ fn expand_fcvt_to_uint_sat(
     // done; otherwise saturate to the minimum unsigned value, that is 0.
     pos.insert_ebb(below_pow2nm1_ebb);
     let sres = pos.ins().x86_cvtt2si(ty, x);
-    let is_neg = pos.ins().ifcmp_imm(sres, 0);
-    pos.ins()
-        .brif(IntCC::SignedGreaterThanOrEqual, is_neg, done, &[sres]);
+    br_icmp_imm(
+        &mut pos,
+        isa,
+        IntCC::SignedGreaterThanOrEqual,
+        sres,
+        0,
+        done,
+        &[sres],
+    );
     pos.ins().jump(done, &[zero]);
 
     // Handle the case where x >= 2^(N-1) and not NaN.
@@ -998,9 +1262,15 @@ fn expand_fcvt_to_uint_sat(
             _ => panic!("Can't convert {}", ty),
         },
     );
-    let is_neg = pos.ins().ifcmp_imm(lres, 0);
-    pos.ins()
-        .brif(IntCC::SignedLessThan, is_neg, done, &[max_value]);
+    br_icmp_imm(
+        &mut pos,
+        isa,
+        IntCC::SignedLessThan,
+        lres,
+        0,
+        done,
+        &[max_value],
+    );
     pos.ins().jump(uint_large_ebb, &[]);
 
     pos.insert_ebb(uint_large_ebb);
@@ -1021,6 +1291,231 @@ fn expand_fcvt_to_uint_sat(
     cfg.recompute_ebb(pos.func, done);
 }
 
+/// 32-bit x86 has no conversion between `i64` and the floating point types. Call the runtime
+/// library for the values in range, and produce the INT_MIN that `x86_cvtt2si` promises for NaN
+/// and overflow without calling it.
+fn expand_x86_cvtt2si(
+    inst: ir::Inst,
+    func: &mut ir::Function,
+    cfg: &mut ControlFlowGraph,
+    isa: &dyn TargetIsa,
+) {
+    use crate::ir::immediates::{Ieee32, Ieee64};
+
+    let x = match func.dfg[inst] {
+        ir::InstructionData::Unary {
+            opcode: ir::Opcode::X86Cvtt2si,
+            arg,
+        } => arg,
+        _ => panic!("Need x86_cvtt2si: {}", func.dfg.display_inst(inst, None)),
+    };
+    let old_ebb = func.layout.pp_ebb(inst);
+    let xty = func.dfg.value_type(x);
+    let result = func.dfg.first_result(inst);
+    let ty = func.dfg.value_type(result);
+    debug_assert_eq!(ty, ir::types::I64);
+
+    // EBB checking the upper bound.
+    let upper_ebb = func.dfg.make_ebb();
+
+    // EBB calling the runtime library.
+    let call_ebb = func.dfg.make_ebb();
+
+    // Final EBB with one argument representing the final result value.
+    let done = func.dfg.make_ebb();
+
+    let libcall = match xty {
+        ir::types::F32 => ir::LibCall::FcvtToSintF32I64,
+        ir::types::F64 => ir::LibCall::FcvtToSintF64I64,
+        _ => panic!("Can't convert {}", xty),
+    };
+
+    // The original instruction becomes the call, and its result value moves onto the `done` EBB.
+    func.dfg.clear_results(inst);
+    let converted = func.dfg.append_result(inst, ty);
+    func.dfg.attach_ebb_param(done, result);
+    replace_with_libcall(inst, libcall, func, isa);
+
+    let mut pos = FuncCursor::new(func).at_inst(inst);
+    pos.use_srcloc(inst);
+
+    // Both -2^63 and 2^63 are exact, and everything in between converts.
+    let (flow, fhigh) = match xty {
+        ir::types::F32 => (
+            pos.ins().f32const(Ieee32::pow2(63).neg()),
+            pos.ins().f32const(Ieee32::pow2(63)),
+        ),
+        _ => (
+            pos.ins().f64const(Ieee64::pow2(63).neg()),
+            pos.ins().f64const(Ieee64::pow2(63)),
+        ),
+    };
+    let int_min = pos.ins().iconst(ty, i64::min_value());
+
+    // NaN compares unordered, so it takes this branch too.
+    let too_low = pos.ins().fcmp(FloatCC::UnorderedOrLessThan, x, flow);
+    pos.ins().brnz(too_low, done, &[int_min]);
+    pos.ins().jump(upper_ebb, &[]);
+
+    pos.insert_ebb(upper_ebb);
+    let too_high = pos.ins().fcmp(FloatCC::GreaterThanOrEqual, x, fhigh);
+    pos.ins().brnz(too_high, done, &[int_min]);
+    pos.ins().jump(call_ebb, &[]);
+
+    pos.insert_ebb(call_ebb);
+    pos.goto_after_inst(inst);
+    pos.ins().jump(done, &[converted]);
+
+    pos.insert_ebb(done);
+
+    cfg.recompute_ebb(pos.func, old_ebb);
+    cfg.recompute_ebb(pos.func, upper_ebb);
+    cfg.recompute_ebb(pos.func, call_ebb);
+    cfg.recompute_ebb(pos.func, done);
+}
+
+/// Expand a `bitcast` that has no encoding by going through memory. On 32-bit x86, this is the
+/// case for the `i64` and `f64` bitcasts, since an `i64` occupies a pair of registers.
+fn expand_bitcast(
+    inst: ir::Inst,
+    func: &mut ir::Function,
+    _cfg: &mut ControlFlowGraph,
+    _isa: &dyn TargetIsa,
+) {
+    let x = match func.dfg[inst] {
+        ir::InstructionData::Unary {
+            opcode: ir::Opcode::Bitcast,
+            arg,
+        } => arg,
+        _ => panic!("Need bitcast: {}", func.dfg.display_inst(inst, None)),
+    };
+    let ty = func.dfg.value_type(func.dfg.first_result(inst));
+    let bytes = func.dfg.value_type(x).bytes().max(ty.bytes());
+
+    let ss = func.create_stack_slot(ir::StackSlotData::new(
+        ir::StackSlotKind::ExplicitSlot,
+        bytes,
+    ));
+
+    let mut pos = FuncCursor::new(func).at_inst(inst);
+    pos.use_srcloc(inst);
+    pos.ins().stack_store(x, ss, 0);
+    pos.func.dfg.replace(inst).stack_load(ty, ss, 0);
+}
+
+/// Expand a `tls_value` instruction into the access sequence selected by the `tls_model` setting.
+fn expand_tls_value(
+    inst: ir::Inst,
+    func: &mut ir::Function,
+    _cfg: &mut ControlFlowGraph,
+    isa: &dyn TargetIsa,
+) {
+    use crate::settings::TlsModel;
+
+    assert_eq!(
+        isa.pointer_type(),
+        I64,
+        "TLS accesses are only implemented for x86_64"
+    );
+
+    let gv = match func.dfg[inst] {
+        ir::InstructionData::UnaryGlobalValue {
+            opcode: ir::Opcode::TlsValue,
+            global_value,
+        } => global_value,
+        _ => panic!("Need tls_value: {}", func.dfg.display_inst(inst, None)),
+    };
+    let offset: i64 = match func.global_values[gv] {
+        ir::GlobalValueData::Symbol {
+            offset, tls: true, ..
+        } => offset.into(),
+        _ => panic!("tls_value must reference a tls symbol"),
+    };
+
+    let tls_model = isa.flags().tls_model();
+    if tls_model == TlsModel::ElfGd {
+        // The general-dynamic sequence calls `__tls_get_addr`, so this function must get the
+        // stack frame of a non-leaf function.
+        let mut sig = ir::Signature::new(isa::CallConv::SystemV);
+        sig.params.push(ir::AbiParam::new(I64));
+        sig.returns.push(ir::AbiParam::new(I64));
+        func.import_signature(sig);
+    }
+
+    let result = func.dfg.first_result(inst);
+    let mut pos = FuncCursor::new(func).at_inst(inst);
+    pos.use_srcloc(inst);
+    pos.func.dfg.clear_results(inst);
+
+    // The TLS relocations don't have room for the symbol offset, so add it separately.
+    let reuse = if offset == 0 { [Some(result)] } else { [None] };
+    let addr = match tls_model {
+        TlsModel::None => panic!("tls_model flag is not set"),
+        TlsModel::ElfGd => pos.ins().with_results(reuse).x86_elf_tls_gd_addr(gv),
+        TlsModel::ElfIe => pos.ins().with_results(reuse).x86_elf_tls_ie_addr(gv),
+    };
+    if offset != 0 {
+        pos.ins().with_result(result).iadd_imm(addr, offset);
+    }
+    pos.remove_inst();
+}
+
+/// Expand a `dynamic_stack_alloc` instruction, probing the new stack area first if stack probes
+/// are enabled.
+fn expand_dynamic_stack_alloc(
+    inst: ir::Inst,
+    func: &mut ir::Function,
+    _cfg: &mut ControlFlowGraph,
+    isa: &dyn TargetIsa,
+) {
+    use crate::settings::ProbestackStrategy;
+
+    let (size, align) = match func.dfg[inst] {
+        ir::InstructionData::BinaryImm {
+            opcode: ir::Opcode::DynamicStackAlloc,
+            arg,
+            imm,
+        } => (arg, imm),
+        _ => panic!(
+            "Need dynamic_stack_alloc: {}",
+            func.dfg.display_inst(inst, None)
+        ),
+    };
+
+    let mut pos = FuncCursor::new(func).at_inst(inst);
+    pos.use_srcloc(inst);
+
+    let size = if isa.flags().probestack_enabled() {
+        // Aligning the stack pointer can move it further down than `size`, so probe that too.
+        let reg_type = isa.pointer_type();
+        let align: i64 = align.into();
+        let slack = align.max(16) - 1;
+        let probe_size = pos.ins().iadd_imm(size, slack);
+        if isa.flags().probestack_strategy() == ProbestackStrategy::Inline {
+            // The inline probe moves the stack pointer back up when it is done.
+            let page_size = 1i64 << isa.flags().probestack_size_log2();
+            pos.ins().x86_probe_stack(probe_size, page_size);
+            size
+        } else {
+            let callee = ir::get_probestack_funcref(pos.func, reg_type, RU::rax as RegUnit, isa);
+            pos.ins().call(callee, &[probe_size]);
+            if isa.flags().probestack_func_adjusts_sp() {
+                // The stack pointer has already moved past the probed area.
+                pos.ins().iconst(reg_type, 0)
+            } else {
+                size
+            }
+        }
+    } else {
+        size
+    };
+
+    pos.func
+        .dfg
+        .replace(inst)
+        .x86_dynamic_stack_alloc(size, align);
+}
+
 /// Convert shuffle instructions.
 fn convert_shuffle(
     inst: ir::Inst,
@@ -1233,6 +1728,10 @@ fn convert_ineg(
             let zero_immediate = pos.func.dfg.constants.insert(vec![0; 16].into());
             let zero_value = pos.ins().vconst(value_type, zero_immediate); // this should be legalized to a PXOR
             pos.func.dfg.replace(inst).isub(zero_value, arg);
+        } else {
+            // Scalars too wide for a register are negated by narrowing a subtraction from zero.
+            let zero_value = pos.ins().iconst(value_type, 0);
+            pos.func.dfg.replace(inst).isub(zero_value, arg);
         }
     }
 }
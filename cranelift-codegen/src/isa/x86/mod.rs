@@ -80,6 +80,11 @@ impl TargetIsa for Isa {
         true
     }
 
+    /// The TLS access sequences are only implemented for x86-64.
+    fn supports_tls(&self) -> bool {
+        self.pointer_bits() == 64
+    }
+
     fn register_info(&self) -> RegInfo {
         registers::INFO.clone()
     }
@@ -143,7 +148,7 @@ impl TargetIsa for Isa {
 
     fn prologue_epilogue(&self, func: &mut ir::Function) -> CodegenResult<()> {
         let _tt = timing::prologue_epilogue();
-        abi::prologue_epilogue(func, self)
+        abi::prologue_epilogue(func, self, &self.isa_flags)
     }
 
     fn unsigned_add_overflow_condition(&self) -> ir::condcodes::IntCC {
@@ -160,6 +165,18 @@ impl TargetIsa for Isa {
     fn emit_unwind_info(&self, func: &ir::Function, mem: &mut Vec<u8>) {
         abi::emit_unwind_info(func, self, mem);
     }
+
+    /// The shared retpoline thunk, when indirect branches go through it.
+    fn libcall_thunks(&self) -> Vec<(ir::LibCall, Vec<u8>)> {
+        if !self.isa_flags.use_retpoline_thunks() {
+            return Vec::new();
+        }
+        let libcall = match self.pointer_bits() {
+            64 => ir::LibCall::RetpolineThunkR11,
+            _ => ir::LibCall::RetpolineThunkEax,
+        };
+        vec![(libcall, binemit::retpoline_thunk(self.pointer_bits()))]
+    }
 }
 
 impl fmt::Display for Isa {
@@ -0,0 +1,163 @@
+//! Legalization of atomic memory instructions.
+//!
+//! This module exports the `expand_atomic` function which rewrites atomic instructions that the
+//! target can't encode directly into simpler atomic instructions.
+
+use crate::cursor::{Cursor, FuncCursor};
+use crate::flowgraph::ControlFlowGraph;
+use crate::ir::condcodes::IntCC;
+use crate::ir::{self, AtomicOrdering, AtomicRmwOp, InstBuilder};
+use crate::isa::TargetIsa;
+
+/// Expand an atomic memory instruction that has no legal encoding.
+///
+/// - A nonzero offset is folded into the address, since targets generally only provide
+///   register-indirect addressing for atomic instructions.
+/// - An `atomic_rmw` subtraction becomes an addition of the negated operand.
+/// - Any other `atomic_rmw` becomes a compare-and-swap loop.
+pub fn expand_atomic(
+    inst: ir::Inst,
+    func: &mut ir::Function,
+    cfg: &mut ControlFlowGraph,
+    isa: &dyn TargetIsa,
+) {
+    if fold_offset(inst, func) {
+        return;
+    }
+
+    match func.dfg[inst] {
+        ir::InstructionData::AtomicRmw {
+            op: AtomicRmwOp::Sub,
+            args,
+            ..
+        } => {
+            let mut pos = FuncCursor::new(func).at_inst(inst);
+            pos.use_srcloc(inst);
+            // Not every target can encode `ineg`, so negate by subtracting from zero.
+            let ty = pos.func.dfg.value_type(args[0]);
+            let zero = pos.ins().iconst(ty, 0);
+            let neg = pos.ins().isub(zero, args[0]);
+            if let ir::InstructionData::AtomicRmw {
+                ref mut op,
+                ref mut args,
+                ..
+            } = pos.func.dfg[inst]
+            {
+                *op = AtomicRmwOp::Add;
+                args[0] = neg;
+            }
+        }
+        ir::InstructionData::AtomicRmw { .. } => expand_rmw_loop(inst, func, cfg),
+        _ => panic!(
+            "{} can't encode {}",
+            isa.name(),
+            func.dfg.display_inst(inst, None)
+        ),
+    }
+}
+
+/// Fold the immediate offset of an atomic memory instruction into its address.
+///
+/// Returns `false` if the offset was already zero, or if the instruction has no offset.
+fn fold_offset(inst: ir::Inst, func: &mut ir::Function) -> bool {
+    let (offset, addr_index) = match func.dfg[inst] {
+        ir::InstructionData::AtomicLoad { offset, .. } => (offset, 0),
+        ir::InstructionData::AtomicStore { offset, .. } => (offset, 1),
+        _ => return false,
+    };
+    let offset: i64 = offset.into();
+    if offset == 0 {
+        return false;
+    }
+
+    let mut pos = FuncCursor::new(func).at_inst(inst);
+    pos.use_srcloc(inst);
+    let addr = pos.func.dfg.inst_args(inst)[addr_index];
+    let addr = pos.ins().iadd_imm(addr, offset);
+    pos.func.dfg.inst_args_mut(inst)[addr_index] = addr;
+
+    match pos.func.dfg[inst] {
+        ir::InstructionData::AtomicLoad { ref mut offset, .. }
+        | ir::InstructionData::AtomicStore { ref mut offset, .. } => *offset = 0.into(),
+        _ => unreachable!(),
+    }
+    true
+}
+
+/// Expand an `atomic_rmw` instruction into a loop around `atomic_cas`:
+///
+/// ```text
+///     v0 = atomic_load.ty relaxed p
+///     jump ebb_loop(v0)
+/// ebb_loop(old):
+///     new = op old, x
+///     prev = atomic_cas ordering old, new, p
+///     failed = icmp ne prev, old
+///     brnz failed, ebb_loop(prev)
+///     jump ebb_done(prev)
+/// ebb_done(result):
+/// ```
+fn expand_rmw_loop(inst: ir::Inst, func: &mut ir::Function, cfg: &mut ControlFlowGraph) {
+    let (ordering, flags, op, x, p) = match func.dfg[inst] {
+        ir::InstructionData::AtomicRmw {
+            ordering,
+            flags,
+            op,
+            args,
+            ..
+        } => (ordering, flags, op, args[0], args[1]),
+        _ => panic!("Expected atomic_rmw: {}", func.dfg.display_inst(inst, None)),
+    };
+    let old_ebb = func.layout.pp_ebb(inst);
+    let loop_ebb = func.dfg.make_ebb();
+    let done = func.dfg.make_ebb();
+
+    // Move the `inst` result value onto the `done` EBB.
+    let result = func.dfg.first_result(inst);
+    let ty = func.dfg.value_type(result);
+    func.dfg.clear_results(inst);
+    func.dfg.attach_ebb_param(done, result);
+
+    let mut pos = FuncCursor::new(func).at_inst(inst);
+    pos.use_srcloc(inst);
+
+    // The initial load only provides a guess for the compare-and-swap, so it needs no ordering.
+    let init = pos
+        .ins()
+        .atomic_load(ty, AtomicOrdering::Relaxed, flags, p, 0);
+    pos.ins().jump(loop_ebb, &[init]);
+
+    pos.insert_ebb(loop_ebb);
+    let old = pos.func.dfg.append_ebb_param(loop_ebb, ty);
+    let new = match op {
+        AtomicRmwOp::Add => pos.ins().iadd(old, x),
+        AtomicRmwOp::Sub => pos.ins().isub(old, x),
+        AtomicRmwOp::And => pos.ins().band(old, x),
+        AtomicRmwOp::Or => pos.ins().bor(old, x),
+        AtomicRmwOp::Xor => pos.ins().bxor(old, x),
+        AtomicRmwOp::Xchg => x,
+        AtomicRmwOp::Umin | AtomicRmwOp::Umax | AtomicRmwOp::Smin | AtomicRmwOp::Smax => {
+            let cc = match op {
+                AtomicRmwOp::Umin => IntCC::UnsignedLessThan,
+                AtomicRmwOp::Umax => IntCC::UnsignedGreaterThan,
+                AtomicRmwOp::Smin => IntCC::SignedLessThan,
+                _ => IntCC::SignedGreaterThan,
+            };
+            let keep_old = pos.ins().icmp(cc, old, x);
+            pos.ins().select(keep_old, old, x)
+        }
+    };
+    let prev = pos.ins().atomic_cas(ordering, flags, old, new, p);
+    let failed = pos.ins().icmp(IntCC::NotEqual, prev, old);
+    pos.ins().brnz(failed, loop_ebb, &[prev]);
+
+    // Recycle the original instruction as the loop exit.
+    pos.func.dfg.replace(inst).jump(done, &[prev]);
+
+    pos.next_inst();
+    pos.insert_ebb(done);
+
+    cfg.recompute_ebb(pos.func, old_ebb);
+    cfg.recompute_ebb(pos.func, loop_ebb);
+    cfg.recompute_ebb(pos.func, done);
+}
@@ -30,6 +30,7 @@ use crate::legalizer::split::{isplit, vsplit};
 use alloc::borrow::Cow;
 use alloc::vec::Vec;
 use core::mem;
+use core::ops::Range;
 use cranelift_entity::EntityList;
 use log::debug;
 
@@ -51,6 +52,15 @@ pub fn legalize_signatures(func: &mut Function, isa: &dyn TargetIsa) {
         }
     }
 
+    if func
+        .dfg
+        .old_signatures
+        .values()
+        .any(|sig| sig.as_ref().map(Signature::uses_struct_arguments) == Some(true))
+    {
+        legalize_struct_call_arguments(func, isa);
+    }
+
     if let Some(entry) = func.layout.entry_block() {
         legalize_entry_params(func, entry);
         spill_entry_params(func, entry);
@@ -95,6 +105,7 @@ fn legalize_entry_params(func: &mut Function, entry: Ebb) {
     let mut has_vmctx = false;
     let mut has_sigid = false;
     let mut has_stack_limit = false;
+    let mut has_vararg_vectors = false;
 
     // Insert position for argument conversion code.
     // We want to insert instructions before the first instruction in the entry block.
@@ -113,7 +124,33 @@ fn legalize_entry_params(func: &mut Function, entry: Ebb) {
 
         let abi_type = pos.func.signature.params[abi_arg];
         let arg_type = pos.func.dfg.value_type(arg);
-        if arg_type == abi_type.value_type {
+        if let ArgumentPurpose::StructArgumentPart(_) = abi_type.purpose {
+            // The struct is passed by value. Store its parts to a stack slot, and use the address
+            // of the slot as `arg`.
+            let size = match pos.func.old_signature.as_ref().unwrap().params[old_arg - 1].purpose {
+                ArgumentPurpose::StructArgument(layout) => layout.size,
+                _ => panic!("Unexpected struct argument part {}", abi_type),
+            };
+            let ss = pos.func.create_stack_slot(StackSlotData::new(
+                StackSlotKind::ExplicitSlot,
+                round_up_to_multiple_of_pow2(size, 8),
+            ));
+            let first_part = abi_arg;
+            while let Some(&part) = pos.func.signature.params.get(abi_arg) {
+                match part.purpose {
+                    ArgumentPurpose::StructArgumentPart(offset)
+                        if offset > 0 || abi_arg == first_part =>
+                    {
+                        let value = pos.func.dfg.append_ebb_param(entry, part.value_type);
+                        pos.ins().stack_store(value, ss, offset as i32);
+                        abi_arg += 1;
+                    }
+                    _ => break,
+                }
+            }
+            let addr = pos.ins().stack_addr(arg_type, ss, 0);
+            pos.func.dfg.change_to_alias(arg, addr);
+        } else if arg_type == abi_type.value_type {
             // No value translation is necessary, this argument matches the ABI type.
             // Just use the original EBB argument value. This is the most common case.
             pos.func.dfg.attach_ebb_param(entry, arg);
@@ -137,6 +174,8 @@ fn legalize_entry_params(func: &mut Function, entry: Ebb) {
                     debug_assert!(!has_stack_limit, "Multiple stack_limit arguments found");
                     has_stack_limit = true;
                 }
+                // The struct is passed as a pointer to a copy made by the caller.
+                ArgumentPurpose::StructArgument(_) => {}
                 _ => panic!("Unexpected special-purpose arg {}", abi_type),
             }
             abi_arg += 1;
@@ -168,7 +207,9 @@ fn legalize_entry_params(func: &mut Function, entry: Ebb) {
     for &arg in &pos.func.signature.params[abi_arg..] {
         match arg.purpose {
             // Any normal parameters should have been processed above.
-            ArgumentPurpose::Normal => {
+            ArgumentPurpose::Normal
+            | ArgumentPurpose::StructArgument(_)
+            | ArgumentPurpose::StructArgumentPart(_) => {
                 panic!("Leftover arg: {}", arg);
             }
             // The callee-save parameters should not appear until after register allocation is
@@ -197,6 +238,13 @@ fn legalize_entry_params(func: &mut Function, entry: Ebb) {
                 debug_assert!(!has_stack_limit, "Multiple stack_limit parameters found");
                 has_stack_limit = true;
             }
+            ArgumentPurpose::VarargVectors => {
+                debug_assert!(
+                    !has_vararg_vectors,
+                    "Multiple vararg_vectors parameters found"
+                );
+                has_vararg_vectors = true;
+            }
         }
 
         // Just create entry block values to match here. We will use them in `handle_return_abi()`
@@ -205,6 +253,161 @@ fn legalize_entry_params(func: &mut Function, entry: Ebb) {
     }
 }
 
+/// Pass the struct arguments of all calls the way their legalized signatures expect.
+///
+/// Before legalization, a struct argument is a pointer to the struct. The struct is either loaded
+/// into the parts it is passed in, or copied to a new stack slot whose address is passed instead.
+///
+/// Unlike the rest of the call legalization, this is done once for the whole function, right after
+/// the signatures are legalized. A pointer to a struct can't be told apart from a pointer to its
+/// copy, so this rewrite is not idempotent.
+fn legalize_struct_call_arguments(func: &mut Function, isa: &dyn TargetIsa) {
+    let mut pos = FuncCursor::new(func);
+    while let Some(_ebb) = pos.next_ebb() {
+        while let Some(inst) = pos.next_inst() {
+            let sig_ref = match pos.func.dfg.call_signature(inst) {
+                Some(sig_ref) => sig_ref,
+                None => continue,
+            };
+            let old_sig = match pos.func.dfg.old_signatures[sig_ref] {
+                Some(ref old_sig) if old_sig.uses_struct_arguments() => old_sig.clone(),
+                _ => continue,
+            };
+            pos.use_srcloc(inst);
+
+            // The k-th struct in the legalized signature belongs to the k-th struct argument.
+            let mut structs = struct_argument_groups(&pos.func.dfg.signatures[sig_ref]).into_iter();
+            let old_args = pos.func.dfg.inst_variable_args(inst).to_vec();
+            let mut new_args = Vec::with_capacity(old_args.len());
+            for (param, &arg) in old_sig.params.iter().zip(&old_args) {
+                let layout = match param.purpose {
+                    ArgumentPurpose::StructArgument(layout) => layout,
+                    _ => {
+                        new_args.push(arg);
+                        continue;
+                    }
+                };
+                let parts = structs
+                    .next()
+                    .expect("struct argument missing from legalized signature");
+                for abi_arg in parts {
+                    let part = pos.func.dfg.signatures[sig_ref].params[abi_arg];
+                    new_args.push(match part.purpose {
+                        ArgumentPurpose::StructArgumentPart(offset) => load_struct_part(
+                            &mut pos,
+                            part.value_type,
+                            arg,
+                            offset,
+                            layout.size - offset,
+                        ),
+                        _ => copy_struct(&mut pos, isa.pointer_type(), arg, layout.size),
+                    });
+                }
+            }
+
+            let num_fixed_values = pos.func.dfg[inst]
+                .opcode()
+                .constraints()
+                .num_fixed_value_arguments();
+            let mut vlist = pos.func.dfg[inst]
+                .take_value_list()
+                .expect("Call must have a value list");
+            let pool = &mut pos.func.dfg.value_lists;
+            let fixed_values = vlist.as_slice(pool)[..num_fixed_values].to_vec();
+            vlist.clear(pool);
+            vlist.extend(fixed_values.into_iter().chain(new_args), pool);
+            pos.func.dfg[inst].put_value_list(vlist);
+        }
+    }
+}
+
+/// Get the ranges of parameters in the legalized signature `sig` that pass each struct argument.
+///
+/// A struct passed by value has a range of parts starting at offset 0, and a struct passed by
+/// reference has a single pointer.
+fn struct_argument_groups(sig: &Signature) -> Vec<Range<usize>> {
+    let mut groups: Vec<Range<usize>> = Vec::new();
+    for (i, param) in sig.params.iter().enumerate() {
+        match param.purpose {
+            ArgumentPurpose::StructArgument(_) | ArgumentPurpose::StructArgumentPart(0) => {
+                groups.push(i..i + 1)
+            }
+            ArgumentPurpose::StructArgumentPart(_) => groups.last_mut().unwrap().end = i + 1,
+            _ => {}
+        }
+    }
+    groups
+}
+
+/// Load the part of the struct at `ptr` that is passed as a `ty` value at `offset`.
+///
+/// The struct ends `len` bytes after `offset`. An integer part that is larger than that is
+/// assembled from smaller loads, so we never read past the end of the struct.
+fn load_struct_part(pos: &mut FuncCursor, ty: Type, ptr: Value, offset: u32, len: u32) -> Value {
+    let flags = MemFlags::new();
+    if ty.is_float() || ty.bytes() <= len {
+        return pos.ins().load(ty, flags, ptr, offset as i32);
+    }
+
+    let mut value = None;
+    let mut done = 0;
+    for &bytes in &[4, 2, 1] {
+        if len - done < bytes {
+            continue;
+        }
+        let at = (offset + done) as i32;
+        let mut piece = match bytes {
+            4 => pos.ins().uload32(flags, ptr, at),
+            2 => pos.ins().uload16(ty, flags, ptr, at),
+            _ => pos.ins().uload8(ty, flags, ptr, at),
+        };
+        if done > 0 {
+            piece = pos.ins().ishl_imm(piece, i64::from(done * 8));
+        }
+        value = Some(match value {
+            Some(value) => pos.ins().bor(value, piece),
+            None => piece,
+        });
+        done += bytes;
+    }
+    value.unwrap()
+}
+
+/// Copy the `size` bytes struct at `ptr` to a new stack slot, and return the address of the copy.
+fn copy_struct(pos: &mut FuncCursor, ptr_type: Type, ptr: Value, size: u32) -> Value {
+    let ss = pos
+        .func
+        .create_stack_slot(StackSlotData::new(StackSlotKind::ExplicitSlot, size));
+    let copy = pos.ins().stack_addr(ptr_type, ss, 0);
+
+    // Copy in pointer-sized chunks, and finish with smaller ones. The smaller chunks are loaded
+    // into pointer-sized registers, since not all ISAs can load narrower integers.
+    let (load_flags, store_flags) = (MemFlags::new(), MemFlags::trusted());
+    let mut offset = 0;
+    while offset < size {
+        let mut bytes = ptr_type.bytes();
+        while bytes > size - offset {
+            bytes /= 2;
+        }
+        let at = offset as i32;
+        if bytes == ptr_type.bytes() {
+            let value = pos.ins().load(ptr_type, load_flags, ptr, at);
+            pos.ins().store(store_flags, value, copy, at);
+        } else if bytes == 4 {
+            let value = pos.ins().uload32(load_flags, ptr, at);
+            pos.ins().istore32(store_flags, value, copy, at);
+        } else if bytes == 2 {
+            let value = pos.ins().uload16(ptr_type, load_flags, ptr, at);
+            pos.ins().istore16(store_flags, value, copy, at);
+        } else {
+            let value = pos.ins().uload8(ptr_type, load_flags, ptr, at);
+            pos.ins().istore8(store_flags, value, copy, at);
+        }
+        offset += bytes;
+    }
+    copy
+}
+
 /// Legalize the results returned from a call instruction to match the ABI signature.
 ///
 /// The cursor `pos` points to a call instruction with at least one return value. The cursor will
@@ -282,12 +485,12 @@ fn assert_is_valid_sret_legalization(
     let old_special_params: Vec<_> = old_sig
         .params
         .iter()
-        .filter(|r| r.purpose != ArgumentPurpose::Normal)
+        .filter(|r| r.purpose != ArgumentPurpose::Normal && !r.purpose.is_struct_argument())
         .collect();
     let new_special_params: Vec<_> = new_sig
         .params
         .iter()
-        .filter(|r| r.purpose != ArgumentPurpose::Normal)
+        .filter(|r| r.purpose != ArgumentPurpose::Normal && !r.purpose.is_struct_argument())
         .collect();
     debug_assert_eq!(old_special_params.len() + 1, new_special_params.len());
     debug_assert!(old_special_params
@@ -586,8 +789,11 @@ fn check_call_signature(dfg: &DataFlowGraph, inst: Inst) -> Result<(), SigRef> {
     };
     let sig = &dfg.signatures[sig_ref];
 
+    // Tail calls have no results; the callee's return values go straight to our caller.
+    let is_tail_call = dfg[inst].opcode().is_terminator();
+
     if check_arg_types(dfg, args, &sig.params[..])
-        && check_arg_types(dfg, dfg.inst_results(inst), &sig.returns[..])
+        && (is_tail_call || check_arg_types(dfg, dfg.inst_results(inst), &sig.returns[..]))
     {
         // All types check out.
         Ok(())
@@ -722,6 +928,24 @@ fn legalize_type_for_sret_store(pos: &mut FuncCursor, val: Value, ty: Type) -> (
     }
 }
 
+/// Append the number of vector registers used by the arguments of the variadic `call`, as
+/// required by its legalized signature.
+fn append_vararg_vectors_arg(pos: &mut FuncCursor, sig_ref: SigRef, call: Inst) {
+    pos.goto_inst(call);
+    let sig = &pos.func.dfg.signatures[sig_ref];
+    let ty = sig.params[sig
+        .special_param_index(ArgumentPurpose::VarargVectors)
+        .unwrap()]
+    .value_type;
+    let count = sig
+        .params
+        .iter()
+        .filter(|p| (p.value_type.is_float() || p.value_type.is_vector()) && p.location.is_reg())
+        .count();
+    let count_arg = pos.ins().iconst(ty, count as i64);
+    pos.func.dfg.append_inst_arg(call, count_arg);
+}
+
 /// Insert ABI conversion code before and after the call instruction at `pos`.
 ///
 /// Instructions inserted before the call will compute the appropriate ABI values for the
@@ -749,21 +973,46 @@ pub fn handle_call_abi(
 
     let sig = &pos.func.dfg.signatures[sig_ref];
     let old_sig = &pos.func.dfg.old_signatures[sig_ref];
+    let is_tail_call = pos.func.dfg[inst].opcode().is_terminator();
 
-    if sig.uses_struct_return_param()
+    let new_sret_param = sig.uses_struct_return_param()
         && old_sig
             .as_ref()
-            .map_or(false, |s| !s.uses_struct_return_param())
-    {
+            .map_or(false, |s| !s.uses_struct_return_param());
+    let new_vararg_vectors_param = match old_sig {
+        Some(old_sig) => {
+            sig.uses_special_param(ArgumentPurpose::VarargVectors)
+                && !old_sig.uses_special_param(ArgumentPurpose::VarargVectors)
+        }
+        None => false,
+    };
+
+    if new_sret_param && !is_tail_call {
         legalize_sret_call(isa, pos, sig_ref, inst);
+        if new_vararg_vectors_param {
+            append_vararg_vectors_arg(pos, sig_ref, inst);
+        }
     } else {
+        if new_sret_param {
+            // A tail call returns straight to our caller, so the callee stores its return values
+            // through our own `sret` pointer.
+            let sret_arg = pos
+                .func
+                .special_param(ArgumentPurpose::StructReturn)
+                .expect("tail call to an sret function from a function without sret");
+            pos.func.dfg.append_inst_arg(inst, sret_arg);
+        }
+        if new_vararg_vectors_param {
+            append_vararg_vectors_arg(pos, sig_ref, inst);
+        }
+
         // OK, we need to fix the call arguments to match the ABI signature.
         let abi_args = pos.func.dfg.signatures[sig_ref].params.len();
         legalize_inst_arguments(pos, cfg, abi_args, |func, abi_arg| {
             func.dfg.signatures[sig_ref].params[abi_arg]
         });
 
-        if !pos.func.dfg.signatures[sig_ref].returns.is_empty() {
+        if !is_tail_call && !pos.func.dfg.signatures[sig_ref].returns.is_empty() {
             inst = legalize_inst_results(pos, |func, abi_res| {
                 func.dfg.signatures[sig_ref].returns[abi_res]
             });
@@ -1,15 +1,17 @@
 //! Legalization of calls.
 //!
-//! This module exports the `expand_call` function which transforms a `call`
-//! instruction into `func_addr` and `call_indirect` instructions.
+//! This module exports the `expand_call` function which transforms a `call`,
+//! `return_call`, or `invoke` instruction into `func_addr` and the corresponding
+//! indirect call instruction.
 
 use crate::cursor::{Cursor, FuncCursor};
 use crate::flowgraph::ControlFlowGraph;
 use crate::ir::{self, InstBuilder};
 use crate::isa::TargetIsa;
 
-/// Expand a `call` instruction. This lowers it to a `call_indirect`, which
-/// is only done if the ABI doesn't support direct calls.
+/// Expand a `call`, `return_call`, or `invoke` instruction. This lowers it to a
+/// `call_indirect`, `return_call_indirect`, or `invoke_indirect`, which is only
+/// done if the ABI doesn't support direct calls.
 pub fn expand_call(
     inst: ir::Inst,
     func: &mut ir::Function,
@@ -17,15 +19,30 @@ pub fn expand_call(
     isa: &dyn TargetIsa,
 ) {
     // Unpack the instruction.
-    let (func_ref, old_args) = match func.dfg[inst] {
+    let (indirect_opcode, func_ref, old_args, landing_pad) = match func.dfg[inst] {
         ir::InstructionData::Call {
             opcode,
             ref args,
             func_ref,
         } => {
-            debug_assert_eq!(opcode, ir::Opcode::Call);
-            (func_ref, args.clone())
+            let indirect_opcode = match opcode {
+                ir::Opcode::Call => ir::Opcode::CallIndirect,
+                ir::Opcode::ReturnCall => ir::Opcode::ReturnCallIndirect,
+                _ => panic!("Unexpected call opcode: {}", opcode),
+            };
+            (indirect_opcode, func_ref, args.clone(), None)
         }
+        ir::InstructionData::Invoke {
+            ref args,
+            func_ref,
+            destination,
+            ..
+        } => (
+            ir::Opcode::InvokeIndirect,
+            func_ref,
+            args.clone(),
+            Some(destination),
+        ),
         _ => panic!("Wanted call: {}", func.dfg.display_inst(inst, None)),
     };
 
@@ -48,7 +65,20 @@ pub fn expand_call(
         );
     }
 
-    func.dfg
-        .replace(inst)
-        .CallIndirect(ir::Opcode::CallIndirect, ptr_ty, sig, new_args);
+    match landing_pad {
+        Some(destination) => {
+            func.dfg.replace(inst).InvokeIndirect(
+                indirect_opcode,
+                ptr_ty,
+                sig,
+                destination,
+                new_args,
+            );
+        }
+        None => {
+            func.dfg
+                .replace(inst)
+                .CallIndirect(indirect_opcode, ptr_ty, sig, new_args);
+        }
+    }
 }
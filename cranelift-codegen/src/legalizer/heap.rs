@@ -66,19 +66,19 @@ fn dynamic_addr(
 
     // Start with the bounds check. Trap if `offset + access_size > bound`.
     let bound = pos.ins().global_value(offset_ty, bound_gv);
-    let oob;
+    let (cc, lhs, rhs);
     if access_size == 1 {
         // `offset > bound - 1` is the same as `offset >= bound`.
-        oob = pos
-            .ins()
-            .icmp(IntCC::UnsignedGreaterThanOrEqual, offset, bound);
+        cc = IntCC::UnsignedGreaterThanOrEqual;
+        lhs = offset;
+        rhs = bound;
     } else if access_size <= min_size {
         // We know that bound >= min_size, so here we can compare `offset > bound - access_size`
         // without wrapping.
         let adj_bound = pos.ins().iadd_imm(bound, -(access_size as i64));
-        oob = pos
-            .ins()
-            .icmp(IntCC::UnsignedGreaterThan, offset, adj_bound);
+        cc = IntCC::UnsignedGreaterThan;
+        lhs = offset;
+        rhs = adj_bound;
     } else {
         // We need an overflow check for the adjusted offset.
         let access_size_val = pos.ins().iconst(offset_ty, access_size as i64);
@@ -88,13 +88,28 @@ fn dynamic_addr(
             overflow,
             ir::TrapCode::HeapOutOfBounds,
         );
-        oob = pos
-            .ins()
-            .icmp(IntCC::UnsignedGreaterThan, adj_offset, bound);
+        cc = IntCC::UnsignedGreaterThan;
+        lhs = adj_offset;
+        rhs = bound;
     }
+    let oob = pos.ins().icmp(cc, lhs, rhs);
     pos.ins().trapnz(oob, ir::TrapCode::HeapOutOfBounds);
 
-    compute_addr(isa, inst, heap, addr_ty, offset, offset_ty, pos.func);
+    let spectre_oob_comparison = if isa.flags().enable_heap_access_spectre_mitigation() {
+        Some((cc, lhs, rhs))
+    } else {
+        None
+    };
+    compute_addr(
+        isa,
+        inst,
+        heap,
+        addr_ty,
+        offset,
+        offset_ty,
+        pos.func,
+        spectre_oob_comparison,
+    );
 }
 
 /// Expand a `heap_addr` for a static heap.
@@ -134,23 +149,39 @@ fn static_addr(
 
     // We may be able to omit the check entirely for 32-bit offsets if the heap bound is 4 GB or
     // more.
+    let mut spectre_oob_comparison = None;
     if offset_ty != ir::types::I32 || limit < 0xffff_ffff {
-        let oob = if limit & 1 == 1 {
+        let (cc, imm) = if limit & 1 == 1 {
             // Prefer testing `offset >= limit - 1` when limit is odd because an even number is
             // likely to be a convenient constant on ARM and other RISC architectures.
-            pos.ins()
-                .icmp_imm(IntCC::UnsignedGreaterThanOrEqual, offset, limit as i64 - 1)
+            (IntCC::UnsignedGreaterThanOrEqual, limit as i64 - 1)
         } else {
-            pos.ins()
-                .icmp_imm(IntCC::UnsignedGreaterThan, offset, limit as i64)
+            (IntCC::UnsignedGreaterThan, limit as i64)
         };
+        let oob = pos.ins().icmp_imm(cc, offset, imm);
         pos.ins().trapnz(oob, ir::TrapCode::HeapOutOfBounds);
+        if isa.flags().enable_heap_access_spectre_mitigation() {
+            let limit = pos.ins().iconst(offset_ty, imm);
+            spectre_oob_comparison = Some((cc, offset, limit));
+        }
     }
 
-    compute_addr(isa, inst, heap, addr_ty, offset, offset_ty, pos.func);
+    compute_addr(
+        isa,
+        inst,
+        heap,
+        addr_ty,
+        offset,
+        offset_ty,
+        pos.func,
+        spectre_oob_comparison,
+    );
 }
 
 /// Emit code for the base address computation of a `heap_addr` instruction.
+///
+/// When Spectre mitigation is enabled, `spectre_oob_comparison` is the `(cc, lhs, rhs)` comparison
+/// of the bounds check, and the final address is replaced by null whenever it holds.
 fn compute_addr(
     isa: &dyn TargetIsa,
     inst: ir::Inst,
@@ -159,6 +190,7 @@ fn compute_addr(
     mut offset: ir::Value,
     offset_ty: ir::Type,
     func: &mut ir::Function,
+    spectre_oob_comparison: Option<(IntCC, ir::Value, ir::Value)>,
 ) {
     let mut pos = FuncCursor::new(func).at_inst(inst);
     pos.use_srcloc(inst);
@@ -186,5 +218,18 @@ fn compute_addr(
         pos.ins().global_value(addr_ty, base_gv)
     };
 
-    pos.func.dfg.replace(inst).iadd(base, offset);
+    match spectre_oob_comparison {
+        Some((cc, lhs, rhs)) => {
+            let final_addr = pos.ins().iadd(base, offset);
+            let zero = pos.ins().iconst(addr_ty, 0);
+            let flags = pos.ins().ifcmp(lhs, rhs);
+            pos.func
+                .dfg
+                .replace(inst)
+                .selectif(addr_ty, cc, flags, zero, final_addr);
+        }
+        _ => {
+            pos.func.dfg.replace(inst).iadd(base, offset);
+        }
+    }
 }
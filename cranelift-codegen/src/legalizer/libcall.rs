@@ -9,12 +9,37 @@ use alloc::vec::Vec;
 /// Try to expand `inst` as a library call, returning true is successful.
 pub fn expand_as_libcall(inst: ir::Inst, func: &mut ir::Function, isa: &dyn TargetIsa) -> bool {
     // Does the opcode/ctrl_type combo even have a well-known runtime library name.
-    let libcall = match ir::LibCall::for_inst(func.dfg[inst].opcode(), func.dfg.ctrl_typevar(inst))
-    {
+    let opcode = func.dfg[inst].opcode();
+    let libcall = match ir::LibCall::for_inst(opcode, func.dfg.ctrl_typevar(inst)) {
         Some(lc) => lc,
-        None => return false,
+        None => {
+            // Conversions are named after both their argument and result types.
+            match (func.dfg.inst_args(inst), func.dfg.inst_results(inst)) {
+                (&[arg], &[result]) => match ir::LibCall::for_conversion(
+                    opcode,
+                    func.dfg.value_type(arg),
+                    func.dfg.value_type(result),
+                ) {
+                    Some(lc) => lc,
+                    None => return false,
+                },
+                _ => return false,
+            }
+        }
     };
 
+    replace_with_libcall(inst, libcall, func, isa);
+    true
+}
+
+/// Replace `inst` with a call to `libcall`, passing the instruction arguments and preserving its
+/// result values.
+pub(crate) fn replace_with_libcall(
+    inst: ir::Inst,
+    libcall: ir::LibCall,
+    func: &mut ir::Function,
+    isa: &dyn TargetIsa,
+) {
     // Now we convert `inst` to a call. First save the arguments.
     let mut args = Vec::new();
     args.extend_from_slice(func.dfg.inst_args(inst));
@@ -35,6 +60,4 @@ pub fn expand_as_libcall(inst: ir::Inst, func: &mut ir::Function, isa: &dyn Targ
     let fn_data = &func.dfg.ext_funcs[funcref];
     let sig_data = &mut func.dfg.signatures[fn_data.signature];
     legalize_libcall_signature(sig_data, isa);
-
-    true
 }
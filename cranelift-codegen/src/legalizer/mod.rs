@@ -16,7 +16,7 @@
 use crate::bitset::BitSet;
 use crate::cursor::{Cursor, FuncCursor};
 use crate::flowgraph::ControlFlowGraph;
-use crate::ir::types::{I32, I64};
+use crate::ir::types::{I128, I32, I64};
 use crate::ir::{self, InstBuilder, MemFlags};
 use crate::isa::TargetIsa;
 use crate::predicates;
@@ -24,18 +24,20 @@ use crate::timing;
 use alloc::collections::BTreeSet;
 use alloc::vec::Vec;
 
+mod atomics;
 mod boundary;
 mod call;
 mod globalvalue;
 mod heap;
 mod libcall;
-mod split;
+pub(crate) mod split;
 mod table;
 
+use self::atomics::expand_atomic;
 use self::call::expand_call;
 use self::globalvalue::expand_global_value;
 use self::heap::expand_heap_addr;
-use self::libcall::expand_as_libcall;
+pub(crate) use self::libcall::{expand_as_libcall, replace_with_libcall};
 use self::table::expand_table_addr;
 
 enum LegalizeInstResult {
@@ -608,7 +610,9 @@ fn narrow_load(
         small_ty,
         flags,
         ptr,
-        offset.try_add_i64(8).expect("load offset overflow"),
+        offset
+            .try_add_i64(i64::from(small_ty.bytes()))
+            .expect("load offset overflow"),
     );
     pos.func.dfg.replace(inst).iconcat(al, ah);
 }
@@ -634,12 +638,15 @@ fn narrow_store(
     };
 
     let (al, ah) = pos.ins().isplit(val);
+    let small_ty = pos.func.dfg.value_type(al);
     pos.ins().store(flags, al, ptr, offset);
     pos.ins().store(
         flags,
         ah,
         ptr,
-        offset.try_add_i64(8).expect("store offset overflow"),
+        offset
+            .try_add_i64(i64::from(small_ty.bytes()))
+            .expect("store offset overflow"),
     );
     pos.remove_inst();
 }
@@ -673,6 +680,14 @@ fn narrow_iconst(
         return;
     }
 
+    if ty == I128 {
+        // The 64-bit immediate is sign-extended to 128 bits.
+        let low = pos.ins().iconst(I64, imm);
+        let high = pos.ins().iconst(I64, imm >> 63);
+        pos.func.dfg.replace(inst).iconcat(low, high);
+        return;
+    }
+
     unimplemented!("missing encoding or legalization for iconst.{:?}", ty);
 }
 
@@ -682,7 +697,7 @@ fn narrow_icmp_imm(
     _cfg: &mut ControlFlowGraph,
     _isa: &dyn TargetIsa,
 ) {
-    use crate::ir::condcodes::{CondCode, IntCC};
+    use crate::ir::condcodes::IntCC;
 
     let (arg, cond, imm): (ir::Value, IntCC, i64) = match func.dfg[inst] {
         ir::InstructionData::IntCompareImm {
@@ -700,12 +715,15 @@ fn narrow_icmp_imm(
     let ty = pos.func.dfg.ctrl_typevar(inst);
     let ty_half = ty.half_width().unwrap();
 
-    let imm_low = pos
-        .ins()
-        .iconst(ty_half, imm & (1u128 << (ty_half.bits() - 1)) as i64);
-    let imm_high = pos
-        .ins()
-        .iconst(ty_half, imm.wrapping_shr(ty_half.bits().into()));
+    // The immediate is sign-extended to the full width of `ty`.
+    let (imm_low, imm_high) = if ty_half.bits() >= 64 {
+        (imm, imm >> 63)
+    } else {
+        let bits = u32::from(ty_half.bits());
+        (imm & ((1i64 << bits) - 1), imm >> bits)
+    };
+    let imm_low = pos.ins().iconst(ty_half, imm_low);
+    let imm_high = pos.ins().iconst(ty_half, imm_high);
     let (arg_low, arg_high) = pos.ins().isplit(arg);
 
     match cond {
@@ -728,13 +746,10 @@ fn narrow_icmp_imm(
         | IntCC::UnsignedLessThan
         | IntCC::UnsignedLessThanOrEqual => {
             let b1 = pos.ins().icmp(cond.without_equal(), arg_high, imm_high);
-            let b2 = pos
-                .ins()
-                .icmp(cond.inverse().without_equal(), arg_high, imm_high);
+            let b2 = pos.ins().icmp(IntCC::Equal, arg_high, imm_high);
             let b3 = pos.ins().icmp(cond.unsigned(), arg_low, imm_low);
-            let c1 = pos.ins().bnot(b2);
-            let c2 = pos.ins().band(c1, b3);
-            pos.func.dfg.replace(inst).bor(b1, c2);
+            let c1 = pos.ins().band(b2, b3);
+            pos.func.dfg.replace(inst).bor(b1, c1);
         }
         _ => unimplemented!("missing legalization for condition {:?}", cond),
     }
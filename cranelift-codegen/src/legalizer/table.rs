@@ -15,7 +15,7 @@ pub fn expand_table_addr(
     inst: ir::Inst,
     func: &mut ir::Function,
     _cfg: &mut ControlFlowGraph,
-    _isa: &dyn TargetIsa,
+    isa: &dyn TargetIsa,
 ) {
     // Unpack the instruction.
     let (table, index, element_offset) = match func.dfg[inst] {
@@ -31,11 +31,12 @@ pub fn expand_table_addr(
         _ => panic!("Wanted table_addr: {}", func.dfg.display_inst(inst, None)),
     };
 
-    dynamic_addr(inst, table, index, element_offset, func);
+    dynamic_addr(isa, inst, table, index, element_offset, func);
 }
 
 /// Expand a `table_addr` for a dynamic table.
 fn dynamic_addr(
+    isa: &dyn TargetIsa,
     inst: ir::Inst,
     table: ir::Table,
     index: ir::Value,
@@ -57,6 +58,11 @@ fn dynamic_addr(
         .icmp(IntCC::UnsignedGreaterThanOrEqual, index, bound);
     pos.ins().trapnz(oob, ir::TrapCode::TableOutOfBounds);
 
+    let spectre_oob_bound = if isa.flags().enable_heap_access_spectre_mitigation() {
+        Some(bound)
+    } else {
+        None
+    };
     compute_addr(
         inst,
         table,
@@ -65,10 +71,14 @@ fn dynamic_addr(
         index_ty,
         element_offset,
         pos.func,
+        spectre_oob_bound,
     );
 }
 
 /// Emit code for the base address computation of a `table_addr` instruction.
+///
+/// When Spectre mitigation is enabled, `spectre_oob_bound` is the table bound, and the final
+/// address is replaced by null whenever `index >= bound`.
 fn compute_addr(
     inst: ir::Inst,
     table: ir::Table,
@@ -77,10 +87,13 @@ fn compute_addr(
     index_ty: ir::Type,
     element_offset: Offset32,
     func: &mut ir::Function,
+    spectre_oob_bound: Option<ir::Value>,
 ) {
     let mut pos = FuncCursor::new(func).at_inst(inst);
     pos.use_srcloc(inst);
 
+    let oob_index = index;
+
     // Convert `index` to `addr_ty`.
     if index_ty != addr_ty {
         index = pos.ins().uextend(addr_ty, index);
@@ -103,7 +116,22 @@ fn compute_addr(
         offset = pos.ins().imul_imm(index, element_size as i64);
     }
 
-    if element_offset == Offset32::new(0) {
+    if let Some(bound) = spectre_oob_bound {
+        let mut final_addr = pos.ins().iadd(base, offset);
+        if element_offset != Offset32::new(0) {
+            let imm: i64 = element_offset.into();
+            final_addr = pos.ins().iadd_imm(final_addr, imm);
+        }
+        let zero = pos.ins().iconst(addr_ty, 0);
+        let flags = pos.ins().ifcmp(oob_index, bound);
+        pos.func.dfg.replace(inst).selectif(
+            addr_ty,
+            IntCC::UnsignedGreaterThanOrEqual,
+            flags,
+            zero,
+            final_addr,
+        );
+    } else if element_offset == Offset32::new(0) {
         pos.func.dfg.replace(inst).iadd(base, offset);
     } else {
         let imm: i64 = element_offset.into();
@@ -80,6 +80,7 @@ pub mod write;
 pub use crate::entity::packed_option;
 
 mod abi;
+mod alias_analysis;
 mod bitset;
 mod constant_hash;
 mod context;
@@ -96,12 +97,15 @@ mod predicates;
 mod redundant_reload_remover;
 mod regalloc;
 mod result;
+mod rle;
+mod sccp;
 mod scoped_hash_map;
 mod simple_gvn;
 mod simple_preopt;
 mod stack_layout;
 mod topo_order;
 mod unreachable_code;
+mod unroll;
 mod value_label;
 
 pub use crate::result::{CodegenError, CodegenResult};
@@ -7,9 +7,11 @@ use crate::ir::condcodes::{CondCode, FloatCC, IntCC};
 use crate::ir::dfg::ValueDef;
 use crate::ir::immediates::{Imm64, Offset32};
 use crate::ir::instructions::{Opcode, ValueList};
+use crate::ir::types::IFLAGS;
 use crate::ir::{Ebb, Function, Inst, InstBuilder, InstructionData, MemFlags, Type, Value};
 use crate::isa::TargetIsa;
 use crate::timing;
+use crate::verifier::encoding_clobbers_flags;
 
 /// Information collected about a compare+branch sequence.
 struct CmpBrInfo {
@@ -36,6 +38,184 @@ enum CmpBrKind {
     Fcmp { cond: FloatCC, arg: Value },
 }
 
+/// The most recent instructions in the current EBB that clobber the CPU flags.
+#[derive(Clone, Copy, Default)]
+struct FlagsClobbers {
+    /// The last instruction that clobbered the flags.
+    last: Option<Inst>,
+    /// The instruction that clobbered the flags before `last`.
+    previous: Option<Inst>,
+}
+
+impl FlagsClobbers {
+    fn push(&mut self, inst: Inst) {
+        self.previous = self.last;
+        self.last = Some(inst);
+    }
+}
+
+/// Does `inst` clobber the CPU flags, as far as `verify_flags` is concerned?
+fn clobbers_flags(func: &Function, inst: Inst, isa: &dyn TargetIsa) -> bool {
+    encoding_clobbers_flags(func, &isa.encoding_info(), inst)
+        || func
+            .dfg
+            .inst_results(inst)
+            .iter()
+            .any(|&res| func.dfg.value_type(res).is_flags())
+}
+
+/// Get the variant of an arithmetic instruction that also produces the CPU flags describing its
+/// result.
+fn with_flags_output(data: &InstructionData) -> Option<InstructionData> {
+    match *data {
+        InstructionData::Binary { opcode, args } => {
+            let opcode = match opcode {
+                Opcode::Iadd => Opcode::IaddIfcout,
+                Opcode::Isub => Opcode::IsubIfbout,
+                Opcode::Band => Opcode::X86BandIfout,
+                _ => return None,
+            };
+            Some(InstructionData::Binary { opcode, args })
+        }
+        InstructionData::BinaryImm {
+            opcode: Opcode::BandImm,
+            arg,
+            imm,
+        } => Some(InstructionData::BinaryImm {
+            opcode: Opcode::X86BandImmIfout,
+            arg,
+            imm,
+        }),
+        _ => None,
+    }
+}
+
+/// Make a conditional branch use the flags set by the arithmetic instruction computing the value
+/// it tests, instead of comparing again.
+///
+/// This applies to branches testing whether the result of an `iadd`, `isub` or `band` is zero,
+/// either directly or with `icmp_imm eq|ne`, and to `icmp` comparing the operands of an `isub`.
+/// For example:
+///
+/// ```clif
+///     v2 = isub v0, v1
+///     v3 = icmp_imm eq v2, 0
+///     brnz v3, ebb1
+/// ```
+///
+/// becomes:
+///
+/// ```clif
+///     v2, v4 = isub_ifbout v0, v1
+///     v3 = trueif eq v4
+///     brif eq v4, ebb1
+/// ```
+///
+/// The flags stay live from the arithmetic instruction to the branch, so nothing in between may
+/// clobber them, except the comparison being replaced.
+///
+/// Returns `true` if the branch was changed.
+fn fuse_flags_producer(
+    pos: &mut EncCursor,
+    inst: Inst,
+    clobbers: FlagsClobbers,
+    isa: &dyn TargetIsa,
+) -> bool {
+    let (taken_if_zero, destination, tested) = match pos.func.dfg[inst] {
+        InstructionData::Branch {
+            opcode,
+            destination,
+            ref args,
+        } => {
+            let taken_if_zero = match opcode {
+                Opcode::Brz => true,
+                Opcode::Brnz => false,
+                _ => return false,
+            };
+            let tested = args.first(&pos.func.dfg.value_lists).unwrap();
+            (taken_if_zero, destination, tested)
+        }
+        _ => return false,
+    };
+    let tested_inst = match pos.func.dfg.value_def(tested) {
+        ValueDef::Result(tested_inst, _) if clobbers.last == Some(tested_inst) => tested_inst,
+        _ => return false,
+    };
+
+    // Find the arithmetic instruction, the comparison of its result if any, and the condition
+    // under which the branch argument is true.
+    let (arith_inst, cmp_inst, cond) = match pos.func.dfg[tested_inst] {
+        InstructionData::IntCompareImm {
+            opcode: Opcode::IcmpImm,
+            cond,
+            arg,
+            imm,
+        } => {
+            if (cond != IntCC::Equal && cond != IntCC::NotEqual) || imm != Imm64::new(0) {
+                return false;
+            }
+            match pos.func.dfg.value_def(arg) {
+                ValueDef::Result(arith_inst, _) => (arith_inst, Some(tested_inst), cond),
+                ValueDef::Param(..) => return false,
+            }
+        }
+        InstructionData::IntCompare {
+            opcode: Opcode::Icmp,
+            cond,
+            args,
+        } => {
+            // Subtraction sets the flags like a comparison of its operands.
+            let arith_inst = match clobbers.previous {
+                Some(arith_inst) => arith_inst,
+                None => return false,
+            };
+            match pos.func.dfg[arith_inst] {
+                InstructionData::Binary {
+                    opcode: Opcode::Isub,
+                    args: isub_args,
+                } if isub_args == args => (arith_inst, Some(tested_inst), cond),
+                _ => return false,
+            }
+        }
+        _ => (tested_inst, None, IntCC::NotEqual),
+    };
+    if cmp_inst.is_some() && clobbers.previous != Some(arith_inst) {
+        return false;
+    }
+
+    let ty = pos.func.dfg.ctrl_typevar(arith_inst);
+    let data = match with_flags_output(&pos.func.dfg[arith_inst]) {
+        Some(data) => data,
+        None => return false,
+    };
+    if !ty.is_int() || isa.encode(pos.func, &data, ty).is_err() {
+        return false;
+    }
+
+    // Add the flags to the results of the arithmetic instruction.
+    pos.func.dfg[arith_inst] = data;
+    let flags = pos.func.dfg.append_result(arith_inst, IFLAGS);
+    let ok = pos.func.update_encoding(arith_inst, isa).is_ok();
+    debug_assert!(ok);
+
+    // The comparison may have other uses.
+    if let Some(cmp_inst) = cmp_inst {
+        pos.func.dfg.replace(cmp_inst).trueif(cond, flags);
+        let ok = pos.func.update_encoding(cmp_inst, isa).is_ok();
+        debug_assert!(ok);
+    }
+
+    let args = pos.func.dfg.inst_variable_args(inst).to_vec();
+    let cond = if taken_if_zero { cond.inverse() } else { cond };
+    pos.func
+        .dfg
+        .replace(inst)
+        .brif(cond, flags, destination, &args);
+    let ok = pos.func.update_encoding(inst, isa).is_ok();
+    debug_assert!(ok);
+    true
+}
+
 /// Optimize comparisons to use flags values, to avoid materializing conditions
 /// in integer registers.
 ///
@@ -44,9 +224,13 @@ enum CmpBrKind {
 fn optimize_cpu_flags(
     pos: &mut EncCursor,
     inst: Inst,
-    last_flags_clobber: Option<Inst>,
+    clobbers: FlagsClobbers,
     isa: &dyn TargetIsa,
 ) {
+    if fuse_flags_producer(pos, inst, clobbers, isa) {
+        return;
+    }
+
     // Look for compare and branch patterns.
     // This code could be considerably simplified with non-lexical lifetimes.
     let info = match pos.func.dfg[inst] {
@@ -121,7 +305,7 @@ fn optimize_cpu_flags(
 
     // If any instructions clobber the flags between the comparison and the branch,
     // don't optimize them.
-    if last_flags_clobber != Some(info.cmp_inst) {
+    if clobbers.last != Some(info.cmp_inst) {
         return;
     }
 
@@ -211,6 +395,11 @@ fn optimize_complex_addresses(pos: &mut EncCursor, inst: Inst, isa: &dyn TargetI
         _ => return,
     };
 
+    // Leave volatile accesses exactly as they were written.
+    if info.flags.volatile() {
+        return;
+    }
+
     // Examine the instruction that defines the address operand.
     if let ValueDef::Result(result_inst, _) = pos.func.dfg.value_def(info.arg) {
         match pos.func.dfg[result_inst] {
@@ -353,6 +542,168 @@ fn optimize_complex_addresses(pos: &mut EncCursor, inst: Inst, isa: &dyn TargetI
     debug_assert!(ok);
 }
 
+/// A bit manipulation idiom that can be computed by a single instruction.
+enum BitManipKind {
+    /// `x & ~y`.
+    AndNot { x: Value, y: Value },
+    /// `x & (x - 1)`, `x & -x` or `x ^ (x - 1)`, computed by a unary instruction.
+    LowestBit { opcode: Opcode, x: Value },
+    /// `x & ((1 << n) - 1)`.
+    ZeroHighBits { x: Value, n: Value },
+}
+
+/// Get the instruction data defining `value`, if it is an instruction result.
+fn defining_inst(func: &Function, value: Value) -> Option<&InstructionData> {
+    match func.dfg.value_def(value) {
+        ValueDef::Result(inst, _) => Some(&func.dfg[inst]),
+        ValueDef::Param(_, _) => None,
+    }
+}
+
+/// Is `value` defined by an `iconst` with the immediate `imm`?
+fn is_iconst(func: &Function, value: Value, imm: i64) -> bool {
+    match defining_inst(func, value) {
+        Some(&InstructionData::UnaryImm {
+            opcode: Opcode::Iconst,
+            imm: iconst_imm,
+        }) => iconst_imm == Imm64::new(imm),
+        _ => false,
+    }
+}
+
+/// If `value` is defined by `iadd_imm arg, -1`, return `arg`.
+fn decremented(func: &Function, value: Value) -> Option<Value> {
+    match defining_inst(func, value) {
+        Some(&InstructionData::BinaryImm {
+            opcode: Opcode::IaddImm,
+            arg,
+            imm,
+        }) if imm == Imm64::new(-1) => Some(arg),
+        _ => None,
+    }
+}
+
+/// Match the bit manipulation idioms computing `opcode(x, y)`, where `y` is derived from `x`.
+fn match_bit_manipulation(
+    func: &Function,
+    opcode: Opcode,
+    x: Value,
+    y: Value,
+) -> Option<BitManipKind> {
+    match (opcode, defining_inst(func, y)?) {
+        (Opcode::Bxor, _) if decremented(func, y) == Some(x) => Some(BitManipKind::LowestBit {
+            opcode: Opcode::X86Blsmsk,
+            x,
+        }),
+        (Opcode::Band, _) if decremented(func, y) == Some(x) => Some(BitManipKind::LowestBit {
+            opcode: Opcode::X86Blsr,
+            x,
+        }),
+        (
+            Opcode::Band,
+            &InstructionData::Unary {
+                opcode: Opcode::Bnot,
+                arg,
+            },
+        ) => Some(BitManipKind::AndNot { x, y: arg }),
+        (
+            Opcode::Band,
+            &InstructionData::Unary {
+                opcode: Opcode::Ineg,
+                arg,
+            },
+        ) if arg == x => Some(BitManipKind::LowestBit {
+            opcode: Opcode::X86Blsi,
+            x,
+        }),
+        (
+            Opcode::Band,
+            &InstructionData::Binary {
+                opcode: Opcode::Isub,
+                args,
+            },
+        ) if args[1] == x && is_iconst(func, args[0], 0) => Some(BitManipKind::LowestBit {
+            opcode: Opcode::X86Blsi,
+            x,
+        }),
+        (Opcode::Band, _) => {
+            // The mask `(1 << n) - 1`.
+            let shifted = decremented(func, y)?;
+            match defining_inst(func, shifted)? {
+                &InstructionData::Binary {
+                    opcode: Opcode::Ishl,
+                    args,
+                } if is_iconst(func, args[0], 1) => {
+                    Some(BitManipKind::ZeroHighBits { x, n: args[1] })
+                }
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Replace bit manipulation idioms with single instructions, if the ISA can encode them.
+///
+/// For example, on x86 with BMI1, `band x, (iadd_imm x, -1)` is replaced with `x86_blsr x`.
+fn optimize_bit_manipulation(pos: &mut EncCursor, inst: Inst, isa: &dyn TargetIsa) {
+    let (opcode, args) = match pos.func.dfg[inst] {
+        InstructionData::Binary { opcode, args } => (opcode, args),
+        _ => return,
+    };
+    let ty = pos.func.dfg.ctrl_typevar(inst);
+    if !ty.is_int() {
+        return;
+    }
+
+    // Both operators are commutative, so the derived value may be either operand.
+    let kind = match match_bit_manipulation(pos.func, opcode, args[0], args[1])
+        .or_else(|| match_bit_manipulation(pos.func, opcode, args[1], args[0]))
+    {
+        Some(kind) => kind,
+        None => return,
+    };
+
+    // Check that the replacement can be encoded before changing anything.
+    let data = match kind {
+        BitManipKind::AndNot { x, y } => InstructionData::Binary {
+            opcode: Opcode::BandNot,
+            args: [x, y],
+        },
+        BitManipKind::LowestBit { opcode, x } => InstructionData::Unary { opcode, arg: x },
+        BitManipKind::ZeroHighBits { x, n } => {
+            // The shift amount is masked by `ishl`, but not by `x86_bzhi`, so it needs an
+            // explicit mask of the same type.
+            if pos.func.dfg.value_type(n) != ty {
+                return;
+            }
+            InstructionData::Binary {
+                opcode: Opcode::X86Bzhi,
+                args: [x, n],
+            }
+        }
+    };
+    if isa.encode(pos.func, &data, ty).is_err() {
+        return;
+    }
+
+    pos.use_srcloc(inst);
+    match kind {
+        BitManipKind::AndNot { x, y } => {
+            pos.func.dfg.replace(inst).band_not(x, y);
+        }
+        BitManipKind::LowestBit { opcode, x } => {
+            pos.func.dfg.replace(inst).Unary(opcode, ty, x);
+        }
+        BitManipKind::ZeroHighBits { x, n } => {
+            let n = pos.ins().band_imm(n, i64::from(ty.bits() - 1));
+            pos.func.dfg.replace(inst).x86_bzhi(x, n);
+        }
+    }
+    let ok = pos.func.update_encoding(inst, isa).is_ok();
+    debug_assert!(ok);
+}
+
 //----------------------------------------------------------------------
 //
 // The main post-opt pass.
@@ -361,26 +712,23 @@ pub fn do_postopt(func: &mut Function, isa: &dyn TargetIsa) {
     let _tt = timing::postopt();
     let mut pos = EncCursor::new(func, isa);
     while let Some(_ebb) = pos.next_ebb() {
-        let mut last_flags_clobber = None;
+        let mut clobbers = FlagsClobbers::default();
         while let Some(inst) = pos.next_inst() {
             if isa.uses_cpu_flags() {
                 // Optimize instructions to make use of flags.
-                optimize_cpu_flags(&mut pos, inst, last_flags_clobber, isa);
-
-                // Track the most recent seen instruction that clobbers the flags.
-                if let Some(constraints) = isa
-                    .encoding_info()
-                    .operand_constraints(pos.func.encodings[inst])
-                {
-                    if constraints.clobbers_flags {
-                        last_flags_clobber = Some(inst)
-                    }
+                optimize_cpu_flags(&mut pos, inst, clobbers, isa);
+
+                // Track the most recent seen instructions that clobber the flags.
+                if clobbers_flags(pos.func, inst, isa) {
+                    clobbers.push(inst);
                 }
             }
 
             if isa.uses_complex_addresses() {
                 optimize_complex_addresses(&mut pos, inst, isa);
             }
+
+            optimize_bit_manipulation(&mut pos, inst, isa);
         }
     }
 }
@@ -11,6 +11,7 @@
 
 use crate::ir;
 use crate::ir::ConstantData;
+use crate::isa::CallConv;
 
 /// Check that an integer value is zero.
 #[allow(dead_code)]
@@ -71,6 +72,11 @@ pub fn is_colocated_func(func_ref: ir::FuncRef, func: &ir::Function) -> bool {
     func.dfg.ext_funcs[func_ref].colocated
 }
 
+#[allow(dead_code)]
+pub fn has_stack_args_with_call_conv(func: &ir::Function, call_conv: CallConv) -> bool {
+    func.signature.call_conv == call_conv && func.signature.stack_args_size() > 0
+}
+
 #[allow(dead_code)]
 pub fn is_colocated_data(global_value: ir::GlobalValue, func: &ir::Function) -> bool {
     match func.global_values[global_value] {
@@ -621,6 +621,11 @@ impl RedundantReloadRemover {
                 // remain valid across the call.
                 avail_env.invalidate_all();
             }
+            data if data.memflags().map(|flags| flags.volatile()) == Some(true) => {
+                // A volatile access may have effects on memory that we can't see, so don't carry
+                // any bindings across it.
+                avail_env.invalidate_all();
+            }
             _ => {
                 // Invalidate: any `avail_env` entry associated with a reg written by `inst`.
                 invalidate_regs_written_by_inst(locations, diversions, dfg, avail_env, inst);
@@ -54,7 +54,8 @@ impl<'a> Context<'a> {
             let terminator_inst = self.cur.current_inst().expect("terminator");
             if let Some(inst) = self.cur.prev_inst() {
                 let opcode = self.cur.func.dfg[inst].opcode();
-                if opcode.is_branch() {
+                // Landing pads don't have parameters, so invokes never need splitting.
+                if opcode.is_branch() && !opcode.can_unwind() {
                     self.visit_conditional_branch(inst, opcode);
                     self.cur.goto_inst(terminator_inst);
                     self.visit_terminator_branch(terminator_inst);
@@ -205,8 +205,8 @@ impl<'a> Context<'a> {
 
             // We are not able to insert any regmove for diversion or un-diversion after the first
             // branch. Instead, we record the diversion to be restored at the entry of the next EBB,
-            // which should have a single predecessor.
-            if opcode.is_branch() && cfg!(feature = "basic-blocks") {
+            // which should have a single predecessor. Invokes are not followed by a terminator.
+            if opcode.is_branch() && !opcode.can_unwind() && cfg!(feature = "basic-blocks") {
                 // The next instruction is necessarily an unconditional branch.
                 if let Some(branch) = self.cur.next_inst() {
                     debug!(
@@ -442,6 +442,14 @@ impl<'a> Context<'a> {
         let call_sig = self.cur.func.dfg.call_signature(inst);
         if let Some(sig) = call_sig {
             self.program_input_abi(inst, AbiParams::Parameters(sig));
+            // An invoke may also unwind to its landing pad, where live-in values are expected in
+            // their global homes.
+            if self.cur.func.dfg[inst].opcode().can_unwind() {
+                let dest = self.cur.func.dfg[inst]
+                    .branch_destination()
+                    .expect("invoke without a landing pad");
+                self.undivert_regs(|lr, layout| lr.is_livein(dest, layout));
+            }
         } else if self.cur.func.dfg[inst].opcode().is_return() {
             self.program_input_abi(inst, AbiParams::Returns);
         } else if self.cur.func.dfg[inst].opcode().is_branch() {
@@ -526,7 +534,8 @@ impl<'a> Context<'a> {
             }
         }
 
-        if let Some(sig) = call_sig {
+        // Tail calls have no results to program.
+        if let Some(sig) = self.cur.func.dfg.non_tail_call_signature(inst) {
             self.program_output_abi(
                 sig,
                 defs,
@@ -261,13 +261,30 @@ impl<'a> Context<'a> {
         // Update the live value tracker with this instruction.
         let (throughs, kills, defs) = tracker.process_inst(inst, &self.cur.func.dfg, self.liveness);
 
+        // Values that are live in to the landing pad of an invoke end their local live range here,
+        // but they must still survive the call.
+        let opcode = self.cur.func.dfg[inst].opcode();
+        if opcode.can_unwind() {
+            let dest = self.cur.func.dfg[inst]
+                .branch_destination()
+                .expect("invoke without a landing pad");
+            for lv in kills {
+                if lv.affinity.is_reg()
+                    && !self.spills.contains(&lv.value)
+                    && self.liveness[lv.value].is_livein(dest, &self.cur.func.layout)
+                {
+                    self.spill_reg(lv.value);
+                }
+            }
+        }
+
         // Remove kills from the pressure tracker.
         self.free_regs(kills);
 
         // If inst is a call, spill all register values that are live across the call.
         // This means that we don't currently take advantage of callee-saved registers.
         // TODO: Be more sophisticated.
-        if call_sig.is_some() {
+        if call_sig.is_some() || opcode.clobbers_all_regs() {
             for lv in throughs {
                 if lv.affinity.is_reg() && !self.spills.contains(&lv.value) {
                     self.spill_reg(lv.value);
@@ -455,7 +472,8 @@ impl<'a> Context<'a> {
                     // at the branch destination. It is also necessary since there can be
                     // arbitrarily many EBB arguments.
                     match {
-                        let args = if self.cur.func.dfg[inst].opcode().is_branch() {
+                        let opcode = self.cur.func.dfg[inst].opcode();
+                        let args = if opcode.is_branch() && !opcode.can_unwind() {
                             self.cur.func.dfg.inst_fixed_args(inst)
                         } else {
                             self.cur.func.dfg.inst_args(inst)
@@ -1,6 +1,7 @@
 //! Result and error types representing the outcome of compiling a function.
 
 use crate::verifier::VerifierErrors;
+use alloc::string::String;
 use thiserror::Error;
 
 /// A compilation error.
@@ -30,6 +31,13 @@ pub enum CodegenError {
     /// is exceeded, compilation fails.
     #[error("Code for function is too large")]
     CodeTooLarge,
+
+    /// Something is not supported by the code generator.
+    ///
+    /// Some features are only implemented for a subset of the target ISAs or calling
+    /// conventions; using them elsewhere fails compilation with this error.
+    #[error("Unsupported feature: {0}")]
+    Unsupported(String),
 }
 
 /// A convenient alias for a `Result` that uses `CodegenError` as the error type.
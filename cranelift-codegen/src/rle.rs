@@ -0,0 +1,203 @@
+//! Redundant load elimination and store-to-load forwarding.
+//!
+//! This pass finds the loads whose result is already known: the same memory was loaded or stored
+//! on every path leading to them, and nothing that may write it happened since. Their result is
+//! replaced with the value loaded or stored before, and the load is removed.
+//!
+//! The known contents of memory are computed by a forward dataflow analysis over the EBBs, where
+//! the contents known on entry to an EBB are those known along every incoming edge. Stores forget
+//! the contents of the memory they may alias, as told by the alias analysis, and calls and other
+//! instructions with side effects forget everything that isn't `readonly`. Volatile loads are
+//! never removed or forwarded from.
+//!
+//! This runs before legalization, while `heap_addr`, `stack_load` and `global_value` still show
+//! which memory objects are accessed.
+
+use crate::alias_analysis::{Access, AliasAnalysis, Base};
+use crate::cursor::{Cursor, FuncCursor};
+use crate::dominator_tree::DominatorTree;
+use crate::entity::SecondaryMap;
+use crate::flowgraph::{BasicBlock, ControlFlowGraph};
+use crate::ir::{Ebb, Function, Inst, InstructionData, Opcode, Type, Value};
+use crate::timing;
+use alloc::vec::Vec;
+
+/// The maximum number of memory contents tracked at once, to bound compile time.
+const MAX_FACTS: usize = 64;
+
+/// The known contents of some memory.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Fact {
+    /// The memory.
+    access: Access,
+    /// The type of the value.
+    ty: Type,
+    /// A value holding the contents of the memory.
+    value: Value,
+}
+
+impl Fact {
+    /// Is this fact kept across any write to memory? This is the case for `readonly` memory at a
+    /// fixed address.
+    fn is_readonly(&self) -> bool {
+        self.access.flags.readonly()
+            && match self.access.base {
+                Base::Heap(_, _) | Base::Unknown => false,
+                _ => true,
+            }
+    }
+}
+
+/// The known contents of memory at a program point.
+type Facts = Vec<Fact>;
+
+/// Perform redundant load elimination and store-to-load forwarding on `func`.
+pub fn do_rle(func: &mut Function, cfg: &ControlFlowGraph, domtree: &DominatorTree) {
+    let _tt = timing::rle();
+    debug_assert!(domtree.is_valid());
+
+    let aa = AliasAnalysis::new(func);
+    let rpo: Vec<Ebb> = domtree.cfg_postorder().iter().rev().cloned().collect();
+
+    // The facts known on entry to each EBB, and when leaving an EBB through each branch
+    // instruction, or `None` if that point hasn't been reached yet.
+    let mut ebb_facts: SecondaryMap<Ebb, Option<Facts>> = SecondaryMap::new();
+    let mut branch_facts: SecondaryMap<Inst, Option<Facts>> = SecondaryMap::new();
+
+    // Iterate to a fixed point, assuming the best of back edges not visited yet. The facts known
+    // on entry to an EBB only ever shrink, which guarantees termination.
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for &ebb in &rpo {
+            let mut facts = entry_facts(func, cfg, &branch_facts, ebb);
+            if let Some(ref old) = ebb_facts[ebb] {
+                facts.retain(|fact| old.contains(fact));
+            }
+            if ebb_facts[ebb].as_ref() == Some(&facts) {
+                continue;
+            }
+            ebb_facts[ebb] = Some(facts.clone());
+            changed = true;
+            for inst in func.layout.ebb_insts(ebb) {
+                transfer(func, &aa, inst, &mut facts);
+                if func.dfg[inst].opcode().is_branch() {
+                    branch_facts[inst] = Some(facts.clone());
+                }
+            }
+        }
+    }
+
+    // Replace the loads whose result is known.
+    let mut pos = FuncCursor::new(func);
+    for &ebb in &rpo {
+        let mut facts = ebb_facts[ebb].take().unwrap_or_default();
+        pos.goto_top(ebb);
+        while let Some(inst) = pos.next_inst() {
+            if let Some(value) = known_result(pos.func, &aa, inst, &facts) {
+                let result = pos.func.dfg.first_result(inst);
+                pos.func.dfg.clear_results(inst);
+                pos.func.dfg.change_to_alias(result, value);
+                pos.remove_inst_and_step_back();
+            } else {
+                transfer(pos.func, &aa, inst, &mut facts);
+            }
+        }
+    }
+}
+
+/// Get the facts known along every edge into `ebb` that has been reached.
+fn entry_facts(
+    func: &Function,
+    cfg: &ControlFlowGraph,
+    branch_facts: &SecondaryMap<Inst, Option<Facts>>,
+    ebb: Ebb,
+) -> Facts {
+    if func.layout.entry_block() == Some(ebb) {
+        return Facts::new();
+    }
+    let mut result: Option<Facts> = None;
+    for BasicBlock { inst, .. } in cfg.pred_iter(ebb) {
+        if let Some(ref facts) = branch_facts[inst] {
+            result = Some(match result {
+                None => facts.clone(),
+                Some(mut known) => {
+                    known.retain(|fact| facts.contains(fact));
+                    known
+                }
+            });
+        }
+    }
+    result.unwrap_or_default()
+}
+
+/// Get a value holding the result of `inst`, if it is a load of memory with known contents.
+fn known_result(func: &Function, aa: &AliasAnalysis, inst: Inst, facts: &[Fact]) -> Option<Value> {
+    if !is_forwardable(func, inst) || !func.dfg.has_results(inst) {
+        return None;
+    }
+    let access = aa.access(func, inst)?;
+    if access.flags.volatile() {
+        return None;
+    }
+    let ty = func.dfg.value_type(func.dfg.first_result(inst));
+    facts
+        .iter()
+        .find(|fact| fact.ty == ty && fact.access.same_location(&access))
+        .map(|fact| fact.value)
+}
+
+/// Is `inst` a load or store of a whole value whose contents can be forwarded?
+fn is_forwardable(func: &Function, inst: Inst) -> bool {
+    match func.dfg[inst] {
+        InstructionData::Load {
+            opcode: Opcode::Load,
+            ..
+        }
+        | InstructionData::Store {
+            opcode: Opcode::Store,
+            ..
+        }
+        | InstructionData::StackLoad {
+            opcode: Opcode::StackLoad,
+            ..
+        }
+        | InstructionData::StackStore { .. }
+        | InstructionData::UnaryGlobalValue {
+            opcode: Opcode::GlobalValue,
+            ..
+        } => true,
+        _ => false,
+    }
+}
+
+/// Update `facts` for the execution of `inst`.
+fn transfer(func: &Function, aa: &AliasAnalysis, inst: Inst, facts: &mut Facts) {
+    let opcode = func.dfg[inst].opcode();
+    if let Some(access) = aa.access(func, inst) {
+        if opcode.can_store() {
+            facts.retain(|fact| fact.is_readonly() || !aa.may_alias(&fact.access, &access));
+        }
+        if !is_forwardable(func, inst) || access.flags.volatile() || access.base == Base::Unknown {
+            return;
+        }
+        let value = match func.dfg[inst] {
+            InstructionData::Store { args, .. } => args[0],
+            InstructionData::StackStore { arg, .. } => arg,
+            _ => func.dfg.first_result(inst),
+        };
+        let value = func.dfg.resolve_aliases(value);
+        let ty = func.dfg.value_type(value);
+        if facts
+            .iter()
+            .any(|fact| fact.ty == ty && fact.access.same_location(&access))
+        {
+            return;
+        }
+        if facts.len() < MAX_FACTS {
+            facts.push(Fact { access, ty, value });
+        }
+    } else if opcode.is_call() || opcode.can_store() || opcode.other_side_effects() {
+        facts.retain(Fact::is_readonly);
+    }
+}
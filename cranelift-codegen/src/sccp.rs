@@ -0,0 +1,564 @@
+//! Sparse conditional constant propagation.
+//!
+//! This pass finds the values that are constant on every path the function can take, where the
+//! paths it can take are themselves decided by the constants found: a `brz`, `brnz` or `br_table`
+//! on a known condition only reaches one of its destinations, and an EBB parameter only meets the
+//! arguments passed along the edges that can be taken. This is the algorithm of Wegman and
+//! Zadeck, "Constant propagation with conditional branches" (1991), iterated to a fixed point over
+//! the EBBs in reverse post-order.
+//!
+//! Constant values are then rematerialized with `iconst` and `bconst`, constant EBB parameters
+//! are removed along with the corresponding branch arguments, and branches on known conditions
+//! are folded into jumps. EBBs that become unreachable are left in place for the unreachable code
+//! elimination pass to delete.
+
+use crate::cursor::{Cursor, FuncCursor};
+use crate::dominator_tree::DominatorTree;
+use crate::entity::{EntitySet, SecondaryMap};
+use crate::flowgraph::{BasicBlock, ControlFlowGraph};
+use crate::ir::condcodes::IntCC;
+use crate::ir::instructions::BranchInfo;
+use crate::ir::{
+    DataFlowGraph, Ebb, Function, Inst, InstBuilder, InstructionData, Opcode, Type, Value,
+};
+use crate::timing;
+use alloc::vec::Vec;
+
+/// The abstract value of an SSA value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum LatticeValue {
+    /// The value isn't defined on any path found to be executable yet.
+    Top,
+    /// The value is this constant on every executable path. Integers are zero-extended from
+    /// their type, and booleans are 0 or 1.
+    Const(u64),
+    /// The value isn't a constant, or not one this pass can reason about.
+    Bottom,
+}
+
+impl Default for LatticeValue {
+    fn default() -> Self {
+        Self::Top
+    }
+}
+
+impl LatticeValue {
+    /// The greatest lower bound of `self` and `other`.
+    fn meet(self, other: Self) -> Self {
+        match (self, other) {
+            (Self::Top, x) | (x, Self::Top) => x,
+            (Self::Const(a), Self::Const(b)) if a == b => self,
+            _ => Self::Bottom,
+        }
+    }
+}
+
+/// Can values of type `ty` be propagated as constants?
+fn is_tracked(ty: Type) -> bool {
+    (ty.is_int() || ty.is_bool()) && ty.bits() <= 64
+}
+
+/// Truncate `x` to the width of the integer type `ty`.
+pub(crate) fn truncate(x: u64, ty: Type) -> u64 {
+    match ty.bits() {
+        64 => x,
+        bits => x & ((1 << bits) - 1),
+    }
+}
+
+/// Sign-extend `x` from the width of the integer type `ty`.
+fn sign_extend(x: u64, ty: Type) -> i64 {
+    let shift = 64 - u32::from(ty.bits());
+    ((x << shift) as i64) >> shift
+}
+
+/// Evaluate a unary operation on a constant `x` of type `arg_ty`, producing a `ty`.
+fn evaluate_unary(opcode: Opcode, x: u64, arg_ty: Type, ty: Type) -> Option<u64> {
+    match opcode {
+        Opcode::Copy | Opcode::Bint | Opcode::Uextend | Opcode::Breduce | Opcode::Bextend => {
+            Some(x)
+        }
+        Opcode::Bnot if ty.is_bool() => Some(x ^ 1),
+        Opcode::Bnot => Some(truncate(!x, ty)),
+        Opcode::Ineg => Some(truncate(x.wrapping_neg(), ty)),
+        Opcode::Sextend => Some(truncate(sign_extend(x, arg_ty) as u64, ty)),
+        Opcode::Ireduce => Some(truncate(x, ty)),
+        _ => None,
+    }
+}
+
+/// Evaluate a binary operation on constants `x` and `y`, producing a `ty`.
+///
+/// Divisions that would trap aren't evaluated.
+fn evaluate_binary(opcode: Opcode, x: u64, y: u64, ty: Type) -> Option<u64> {
+    let (sx, sy) = (sign_extend(x, ty), sign_extend(y, ty));
+    let shift = (y % u64::from(ty.bits())) as u32;
+    let result = match opcode {
+        Opcode::Iadd => x.wrapping_add(y),
+        Opcode::Isub => x.wrapping_sub(y),
+        Opcode::Imul => x.wrapping_mul(y),
+        Opcode::Band => x & y,
+        Opcode::Bor => x | y,
+        Opcode::Bxor => x ^ y,
+        Opcode::Ishl => x << shift,
+        Opcode::Ushr => x >> shift,
+        Opcode::Sshr => (sx >> shift) as u64,
+        Opcode::Udiv | Opcode::Urem if y == 0 => return None,
+        Opcode::Udiv => x / y,
+        Opcode::Urem => x % y,
+        Opcode::Sdiv | Opcode::Srem if sy == 0 || (sy == -1 && x == 1 << (ty.bits() - 1)) => {
+            return None
+        }
+        Opcode::Sdiv => (sx / sy) as u64,
+        Opcode::Srem => (sx % sy) as u64,
+        _ => return None,
+    };
+    Some(truncate(result, ty))
+}
+
+/// The binary operation performed by an `_imm` instruction, with the immediate on the right.
+fn binary_of_imm(opcode: Opcode) -> Option<Opcode> {
+    Some(match opcode {
+        Opcode::IaddImm => Opcode::Iadd,
+        Opcode::ImulImm => Opcode::Imul,
+        Opcode::UdivImm => Opcode::Udiv,
+        Opcode::SdivImm => Opcode::Sdiv,
+        Opcode::UremImm => Opcode::Urem,
+        Opcode::SremImm => Opcode::Srem,
+        Opcode::BandImm => Opcode::Band,
+        Opcode::BorImm => Opcode::Bor,
+        Opcode::BxorImm => Opcode::Bxor,
+        Opcode::IshlImm => Opcode::Ishl,
+        Opcode::UshrImm => Opcode::Ushr,
+        Opcode::SshrImm => Opcode::Sshr,
+        _ => return None,
+    })
+}
+
+/// Evaluate an integer comparison of the constants `x` and `y` of type `ty`.
+pub(crate) fn evaluate_icmp(cond: IntCC, x: u64, y: u64, ty: Type) -> Option<u64> {
+    let (sx, sy) = (sign_extend(x, ty), sign_extend(y, ty));
+    let result = match cond {
+        IntCC::Equal => x == y,
+        IntCC::NotEqual => x != y,
+        IntCC::SignedLessThan => sx < sy,
+        IntCC::SignedGreaterThanOrEqual => sx >= sy,
+        IntCC::SignedGreaterThan => sx > sy,
+        IntCC::SignedLessThanOrEqual => sx <= sy,
+        IntCC::UnsignedLessThan => x < y,
+        IntCC::UnsignedGreaterThanOrEqual => x >= y,
+        IntCC::UnsignedGreaterThan => x > y,
+        IntCC::UnsignedLessThanOrEqual => x <= y,
+        IntCC::Overflow | IntCC::NotOverflow => return None,
+    };
+    Some(result as u64)
+}
+
+/// Perform sparse conditional constant propagation on `func`, before legalization.
+///
+/// The dominator tree must be valid; it's only used for its reverse post-order. The control flow
+/// graph is recomputed afterwards.
+pub fn do_sccp(func: &mut Function, cfg: &mut ControlFlowGraph, domtree: &DominatorTree) {
+    let _tt = timing::sccp();
+    debug_assert!(domtree.is_valid());
+
+    let mut sccp = Sccp::new();
+    sccp.solve(func, cfg, domtree);
+    sccp.rewrite(func, cfg);
+    cfg.compute(func);
+}
+
+struct Sccp {
+    /// The abstract value of every SSA value.
+    values: SecondaryMap<Value, LatticeValue>,
+    /// EBBs found to be executable.
+    reachable: EntitySet<Ebb>,
+    /// Instructions found to be executable.
+    reached: EntitySet<Inst>,
+    /// Did anything change in the current iteration?
+    changed: bool,
+}
+
+impl Sccp {
+    fn new() -> Self {
+        Self {
+            values: SecondaryMap::new(),
+            reachable: EntitySet::new(),
+            reached: EntitySet::new(),
+            changed: false,
+        }
+    }
+
+    /// Iterate to a fixed point.
+    fn solve(&mut self, func: &Function, cfg: &ControlFlowGraph, domtree: &DominatorTree) {
+        let entry = match func.layout.entry_block() {
+            Some(entry) => entry,
+            None => return,
+        };
+        self.reachable.insert(entry);
+        for &param in func.dfg.ebb_params(entry) {
+            self.values[param] = LatticeValue::Bottom;
+        }
+
+        loop {
+            self.changed = false;
+            for &ebb in domtree.cfg_postorder().iter().rev() {
+                if self.reachable.contains(ebb) {
+                    self.visit_ebb(func, cfg, ebb, ebb == entry);
+                }
+            }
+            if !self.changed {
+                break;
+            }
+        }
+    }
+
+    fn value(&self, dfg: &DataFlowGraph, value: Value) -> LatticeValue {
+        self.values[dfg.resolve_aliases(value)]
+    }
+
+    fn set_value(&mut self, value: Value, lv: LatticeValue) {
+        if self.values[value] != lv {
+            self.values[value] = lv;
+            self.changed = true;
+        }
+    }
+
+    fn set_reachable(&mut self, ebb: Ebb) {
+        if self.reachable.insert(ebb) {
+            self.changed = true;
+        }
+    }
+
+    fn visit_ebb(&mut self, func: &Function, cfg: &ControlFlowGraph, ebb: Ebb, is_entry: bool) {
+        let dfg = &func.dfg;
+
+        if !is_entry {
+            for (num, &param) in dfg.ebb_params(ebb).iter().enumerate() {
+                let mut lv = LatticeValue::Top;
+                if is_tracked(dfg.value_type(param)) {
+                    for BasicBlock { ebb: pred, inst } in cfg.pred_iter(ebb) {
+                        if !self.is_executable_edge(func, pred, inst, ebb) {
+                            continue;
+                        }
+                        lv = match dfg.analyze_branch(inst) {
+                            BranchInfo::SingleDest(_, args) if num < args.len() => {
+                                lv.meet(self.value(dfg, args[num]))
+                            }
+                            _ => LatticeValue::Bottom,
+                        };
+                    }
+                } else {
+                    lv = LatticeValue::Bottom;
+                }
+                self.set_value(param, lv);
+            }
+        }
+
+        for inst in func.layout.ebb_insts(ebb) {
+            if self.reached.insert(inst) {
+                self.changed = true;
+            }
+            self.visit_inst(dfg, inst);
+            match dfg.analyze_branch(inst) {
+                BranchInfo::NotABranch => {}
+                BranchInfo::SingleDest(dest, _) => {
+                    if self.is_feasible(func, inst, dest) {
+                        self.set_reachable(dest);
+                    }
+                }
+                BranchInfo::Table(jt, default) => {
+                    for &dest in func.jump_tables[jt].iter().chain(default.iter()) {
+                        if self.is_feasible(func, inst, dest) {
+                            self.set_reachable(dest);
+                        }
+                    }
+                }
+            }
+            if !self.falls_through(dfg, inst) {
+                break;
+            }
+        }
+    }
+
+    /// Can control flow from the branch `inst` in `pred` to `dest`?
+    fn is_executable_edge(&self, func: &Function, pred: Ebb, inst: Inst, dest: Ebb) -> bool {
+        self.reachable.contains(pred)
+            && self.reached.contains(inst)
+            && self.is_feasible(func, inst, dest)
+    }
+
+    /// Can the branch `inst` go to `dest`, assuming it is executed?
+    fn is_feasible(&self, func: &Function, inst: Inst, dest: Ebb) -> bool {
+        let dfg = &func.dfg;
+        match dfg[inst] {
+            InstructionData::Branch { opcode, .. } => match self.value(dfg, dfg.inst_args(inst)[0])
+            {
+                LatticeValue::Top => false,
+                LatticeValue::Const(c) => (c == 0) == (opcode == Opcode::Brz),
+                LatticeValue::Bottom => true,
+            },
+            InstructionData::BranchTable { .. } => match self.value(dfg, dfg.inst_args(inst)[0]) {
+                LatticeValue::Top => false,
+                LatticeValue::Const(c) => table_destination(func, inst, c) == dest,
+                LatticeValue::Bottom => true,
+            },
+            _ => true,
+        }
+    }
+
+    /// Can control continue after `inst` to the next instruction in its EBB?
+    fn falls_through(&self, dfg: &DataFlowGraph, inst: Inst) -> bool {
+        match dfg[inst] {
+            InstructionData::Branch { opcode, .. } => match self.value(dfg, dfg.inst_args(inst)[0])
+            {
+                LatticeValue::Top => false,
+                LatticeValue::Const(c) => (c == 0) != (opcode == Opcode::Brz),
+                LatticeValue::Bottom => true,
+            },
+            ref data => !data.opcode().is_terminator(),
+        }
+    }
+
+    fn visit_inst(&mut self, dfg: &DataFlowGraph, inst: Inst) {
+        let lv = match *dfg.inst_results(inst) {
+            [] => return,
+            [result] if is_tracked(dfg.value_type(result)) => {
+                self.evaluate(dfg, inst, dfg.value_type(result))
+            }
+            _ => LatticeValue::Bottom,
+        };
+        for &result in dfg.inst_results(inst) {
+            self.set_value(result, lv);
+        }
+    }
+
+    /// Compute the abstract value of the single result of `inst`, of type `ty`.
+    fn evaluate(&self, dfg: &DataFlowGraph, inst: Inst, ty: Type) -> LatticeValue {
+        match dfg[inst] {
+            InstructionData::UnaryImm {
+                opcode: Opcode::Iconst,
+                imm,
+            } => {
+                let imm: i64 = imm.into();
+                LatticeValue::Const(truncate(imm as u64, ty))
+            }
+            InstructionData::UnaryBool {
+                opcode: Opcode::Bconst,
+                imm,
+            } => LatticeValue::Const(u64::from(imm)),
+            InstructionData::Unary { opcode, arg } => {
+                let arg_ty = dfg.value_type(arg);
+                self.apply(&[self.value(dfg, arg)], |x| {
+                    evaluate_unary(opcode, x[0], arg_ty, ty)
+                })
+            }
+            InstructionData::Binary { opcode, args } => {
+                let lvs = [self.value(dfg, args[0]), self.value(dfg, args[1])];
+                self.apply(&lvs, |x| evaluate_binary(opcode, x[0], x[1], ty))
+            }
+            InstructionData::BinaryImm { opcode, arg, imm } => {
+                let imm: i64 = imm.into();
+                let imm = truncate(imm as u64, ty);
+                self.apply(&[self.value(dfg, arg)], |x| match opcode {
+                    Opcode::IrsubImm => Some(truncate(imm.wrapping_sub(x[0]), ty)),
+                    _ => evaluate_binary(binary_of_imm(opcode)?, x[0], imm, ty),
+                })
+            }
+            InstructionData::IntCompare { cond, args, .. } => {
+                let arg_ty = dfg.value_type(args[0]);
+                let lvs = [self.value(dfg, args[0]), self.value(dfg, args[1])];
+                self.apply(&lvs, |x| evaluate_icmp(cond, x[0], x[1], arg_ty))
+            }
+            InstructionData::IntCompareImm { cond, arg, imm, .. } => {
+                let arg_ty = dfg.value_type(arg);
+                let imm: i64 = imm.into();
+                self.apply(&[self.value(dfg, arg)], |x| {
+                    evaluate_icmp(cond, x[0], truncate(imm as u64, arg_ty), arg_ty)
+                })
+            }
+            InstructionData::Ternary {
+                opcode: Opcode::Select,
+                args,
+            } => match self.value(dfg, args[0]) {
+                LatticeValue::Top => LatticeValue::Top,
+                LatticeValue::Const(c) => self.value(dfg, args[if c != 0 { 1 } else { 2 }]),
+                LatticeValue::Bottom => self.value(dfg, args[1]).meet(self.value(dfg, args[2])),
+            },
+            _ => LatticeValue::Bottom,
+        }
+    }
+
+    /// Apply `f` to the constants in `lvs`, if they are all constant.
+    fn apply<F>(&self, lvs: &[LatticeValue], f: F) -> LatticeValue
+    where
+        F: FnOnce(&[u64]) -> Option<u64>,
+    {
+        let mut consts = [0; 2];
+        for (i, &lv) in lvs.iter().enumerate() {
+            match lv {
+                LatticeValue::Const(c) => consts[i] = c,
+                LatticeValue::Top if !lvs.contains(&LatticeValue::Bottom) => {
+                    return LatticeValue::Top
+                }
+                _ => return LatticeValue::Bottom,
+            }
+        }
+        f(&consts[..lvs.len()]).map_or(LatticeValue::Bottom, LatticeValue::Const)
+    }
+
+    /// Rewrite the function according to the solution.
+    fn rewrite(&mut self, func: &mut Function, cfg: &ControlFlowGraph) {
+        // Remove constant EBB parameters first, while `cfg` still describes the branches.
+        let mut pos = FuncCursor::new(func);
+        while let Some(ebb) = pos.next_ebb() {
+            if !self.reachable.contains(ebb) {
+                continue;
+            }
+            let params = pos.func.dfg.ebb_params(ebb).to_vec();
+            for (num, param) in params.into_iter().enumerate().rev() {
+                let c = match self.values[param] {
+                    LatticeValue::Const(c) => c,
+                    _ => continue,
+                };
+                let mut preds: Vec<Inst> = cfg.pred_iter(ebb).map(|bb| bb.inst).collect();
+                preds.sort();
+                preds.dedup();
+                for inst in preds {
+                    remove_branch_arg(&mut pos.func.dfg, inst, num);
+                }
+                let ty = pos.func.dfg.value_type(param);
+                pos.func.dfg.remove_ebb_param(param);
+                pos.goto_first_insertion_point(ebb);
+                let value = materialize(&mut pos, ty, c);
+                pos.func.dfg.change_to_alias(param, value);
+                self.values[value] = LatticeValue::Const(c);
+            }
+        }
+
+        let mut pos = FuncCursor::new(func);
+        while let Some(ebb) = pos.next_ebb() {
+            if !self.reachable.contains(ebb) {
+                continue;
+            }
+            while let Some(inst) = pos.next_inst() {
+                match pos.func.dfg[inst] {
+                    InstructionData::UnaryImm {
+                        opcode: Opcode::Iconst,
+                        ..
+                    }
+                    | InstructionData::UnaryBool {
+                        opcode: Opcode::Bconst,
+                        ..
+                    } => {}
+                    InstructionData::Branch { .. } | InstructionData::BranchTable { .. } => {
+                        self.fold_branch(&mut pos, inst);
+                    }
+                    _ => {
+                        if let [result] = *pos.func.dfg.inst_results(inst) {
+                            if let LatticeValue::Const(c) = self.values[result] {
+                                let ty = pos.func.dfg.value_type(result);
+                                replace_with_const(&mut pos.func.dfg, inst, ty, c);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Fold a `brz`, `brnz` or `br_table` with a constant condition.
+    fn fold_branch(&self, pos: &mut FuncCursor, inst: Inst) {
+        let c = match self.value(&pos.func.dfg, pos.func.dfg.inst_args(inst)[0]) {
+            LatticeValue::Const(c) => c,
+            _ => return,
+        };
+        match pos.func.dfg[inst] {
+            InstructionData::Branch {
+                opcode,
+                destination,
+                ..
+            } => {
+                if (c == 0) != (opcode == Opcode::Brz) {
+                    pos.remove_inst_and_step_back();
+                    return;
+                }
+                let args = pos.func.dfg.inst_args(inst)[1..].to_vec();
+                pos.func.dfg.replace(inst).jump(destination, &args);
+            }
+            InstructionData::BranchTable { .. } => {
+                let destination = table_destination(pos.func, inst, c);
+                pos.func.dfg.replace(inst).jump(destination, &[]);
+            }
+            _ => unreachable!(),
+        }
+
+        // The rest of the EBB is now dead. Instructions without results are simply removed, but
+        // others may have uses in unreachable EBBs, so they are split off into an unreachable EBB
+        // of their own instead.
+        let tail = match pos.func.layout.next_inst(inst) {
+            Some(tail) => tail,
+            None => return,
+        };
+        let defines_values = pos
+            .func
+            .layout
+            .ebb_insts(pos.func.layout.pp_ebb(inst))
+            .skip_while(|&i| i != tail)
+            .any(|i| !pos.func.dfg.inst_results(i).is_empty());
+        if defines_values {
+            let new_ebb = pos.func.dfg.make_ebb();
+            pos.func.layout.split_ebb(new_ebb, tail);
+        } else {
+            while let Some(next) = pos.func.layout.next_inst(inst) {
+                pos.func.layout.remove_inst(next);
+            }
+        }
+    }
+}
+
+/// The destination of the `br_table` instruction `inst` for the constant index `index`.
+fn table_destination(func: &Function, inst: Inst, index: u64) -> Ebb {
+    match func.dfg[inst] {
+        InstructionData::BranchTable {
+            table, destination, ..
+        } => func.jump_tables[table]
+            .as_slice()
+            .get(index as usize)
+            .cloned()
+            .unwrap_or(destination),
+        _ => unreachable!(),
+    }
+}
+
+/// Remove argument `num` to the destination EBB of the branch `inst`.
+fn remove_branch_arg(dfg: &mut DataFlowGraph, inst: Inst, num: usize) {
+    match dfg.analyze_branch(inst) {
+        BranchInfo::SingleDest(_, args) if num < args.len() => {}
+        _ => return,
+    }
+    let num_fixed_args = dfg[inst].opcode().constraints().num_fixed_value_arguments();
+    let mut args = dfg[inst]
+        .take_value_list()
+        .expect("Branches must have value lists.");
+    args.remove(num_fixed_args + num, &mut dfg.value_lists);
+    dfg[inst].put_value_list(args);
+}
+
+/// Insert a constant `c` of type `ty` at `pos`.
+fn materialize(pos: &mut FuncCursor, ty: Type, c: u64) -> Value {
+    if ty.is_bool() {
+        pos.ins().bconst(ty, c != 0)
+    } else {
+        pos.ins().iconst(ty, sign_extend(c, ty))
+    }
+}
+
+/// Replace `inst` with a constant `c` of type `ty`, keeping its result value.
+fn replace_with_const(dfg: &mut DataFlowGraph, inst: Inst, ty: Type, c: u64) {
+    if ty.is_bool() {
+        dfg.replace(inst).bconst(ty, c != 0);
+    } else {
+        dfg.replace(inst).iconst(ty, sign_extend(c, ty));
+    }
+}
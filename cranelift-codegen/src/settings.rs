@@ -379,9 +379,12 @@ mod tests {
             f.to_string(),
             "[shared]\n\
              opt_level = \"none\"\n\
+             unroll_threshold = 32\n\
              libcall_call_conv = \"isa_default\"\n\
+             tls_model = \"none\"\n\
              baldrdash_prologue_words = 0\n\
              probestack_size_log2 = 12\n\
+             probestack_strategy = \"outline\"\n\
              enable_verifier = true\n\
              is_pic = false\n\
              colocated_libcalls = false\n\
@@ -390,6 +393,7 @@ mod tests {
              enable_nan_canonicalization = false\n\
              enable_pinned_reg = false\n\
              use_pinned_reg_as_heap_base = false\n\
+             enable_heap_access_spectre_mitigation = false\n\
              enable_simd = false\n\
              enable_atomics = true\n\
              enable_safepoints = false\n\
@@ -21,11 +21,12 @@ fn trivially_unsafe_for_gvn(opcode: Opcode) -> bool {
         || opcode.writes_cpu_flags()
 }
 
-/// Test that, if the specified instruction is a load, it doesn't have the `readonly` memflag.
+/// Test that, if the specified instruction is a load, it doesn't have the `readonly` memflag or
+/// it is volatile.
 fn is_load_and_not_readonly(inst_data: &InstructionData) -> bool {
     match *inst_data {
         InstructionData::Load { flags, .. } | InstructionData::LoadComplex { flags, .. } => {
-            !flags.readonly()
+            !flags.readonly() || flags.volatile()
         }
         _ => inst_data.opcode().can_load(),
     }
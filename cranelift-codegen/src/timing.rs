@@ -57,10 +57,13 @@ define_passes! {
     loop_analysis: "Loop analysis",
     postopt: "Post-legalization rewriting",
     preopt: "Pre-legalization rewriting",
+    sccp: "Sparse conditional constant propagation",
+    rle: "Redundant load elimination",
     dce: "Dead code elimination",
     legalize: "Legalization",
     gvn: "Global value numbering",
     licm: "Loop invariant code motion",
+    unroll: "Loop unrolling",
     unreachable_code: "Remove unreachable blocks",
 
     regalloc: "Register allocation",
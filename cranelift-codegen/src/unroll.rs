@@ -0,0 +1,426 @@
+//! Loop unrolling and peeling.
+//!
+//! This pass transforms the counted loops found by the loop analysis: loops made of a single EBB
+//! whose exit condition compares an induction variable with a constant. The induction variable
+//! is an EBB parameter of the loop header, incremented by a constant step on the back edge.
+//!
+//! When the initial value of the induction variable is a constant too, the trip count of the loop
+//! is known. If the whole unrolled loop is smaller than the unroll threshold, the loop is fully
+//! unrolled into a straight sequence of copies of its body. Otherwise, it is unrolled a few times
+//! and the iterations left over are peeled off in front of it, so that the exit condition only
+//! needs to be tested once per unrolled iteration.
+//!
+//! When the trip count isn't known, the loop is unrolled a few times but every copy of the body
+//! keeps its own exit test.
+//!
+//! This runs before legalization, while the exit condition is still an `icmp` feeding a `brz` or
+//! `brnz`.
+
+use crate::dominator_tree::DominatorTree;
+use crate::entity::SecondaryMap;
+use crate::flowgraph::{BasicBlock, ControlFlowGraph};
+use crate::ir::condcodes::{CondCode, IntCC};
+use crate::ir::instructions::BranchInfo;
+use crate::ir::{
+    Ebb, Function, Inst, InstBuilder, InstructionData, Opcode, Type, Value, ValueDef, ValueList,
+};
+use crate::loop_analysis::{Loop, LoopAnalysis};
+use crate::packed_option::PackedOption;
+use crate::sccp::{evaluate_icmp, truncate};
+use crate::timing;
+use alloc::vec::Vec;
+
+/// The maximum number of copies of a loop body in a partially unrolled loop.
+const MAX_UNROLL_FACTOR: usize = 8;
+
+/// The maximum trip count computed, to bound compile time.
+const MAX_TRIP_COUNT: u64 = 1 << 16;
+
+/// Unroll and peel the counted loops of `func`, keeping at most `threshold` instructions in each
+/// unrolled loop.
+///
+/// Changes the CFG, domtree and loop analysis in-place during the operation.
+pub fn do_unroll(
+    func: &mut Function,
+    cfg: &mut ControlFlowGraph,
+    domtree: &mut DominatorTree,
+    loop_analysis: &mut LoopAnalysis,
+    threshold: usize,
+) {
+    let _tt = timing::unroll();
+    debug_assert!(cfg.is_valid());
+    debug_assert!(domtree.is_valid());
+    debug_assert!(loop_analysis.is_valid());
+
+    let mut changed = false;
+    for lp in loop_analysis.loops() {
+        let counted = match CountedLoop::new(func, cfg, loop_analysis, lp) {
+            Some(counted) => counted,
+            None => continue,
+        };
+        if counted.unroll(func, threshold) {
+            // The loop exits may lead to the header of another loop.
+            cfg.compute(func);
+            changed = true;
+        }
+    }
+
+    if changed {
+        domtree.compute(func, cfg);
+        loop_analysis.compute(func, cfg, domtree);
+    }
+}
+
+/// A loop consisting of a single EBB, with a known exit condition.
+struct CountedLoop {
+    /// The header, and only EBB, of the loop.
+    header: Ebb,
+    /// The branches entering the loop.
+    entries: Vec<Inst>,
+    /// The branch back to the header at the end of the loop.
+    back_edge: Inst,
+    /// The branch leaving the loop at the end of the loop.
+    exit_edge: Inst,
+    /// The number of instructions in the loop.
+    size: usize,
+    /// The type of the induction variable.
+    ty: Type,
+    /// The initial value of the induction variable, if it is a constant.
+    init: Option<u64>,
+    /// The constant added to the induction variable in each iteration.
+    step: u64,
+    /// The comparison of the induction variable with `bound`.
+    cond: IntCC,
+    /// Is the incremented induction variable compared, rather than the current one?
+    compare_next: bool,
+    /// The constant the induction variable is compared with.
+    bound: u64,
+    /// The result of the comparison that leaves the loop.
+    exit_if: bool,
+}
+
+impl CountedLoop {
+    /// Recognize `lp` as a counted loop, if it is one.
+    fn new(
+        func: &Function,
+        cfg: &ControlFlowGraph,
+        loop_analysis: &LoopAnalysis,
+        lp: Loop,
+    ) -> Option<Self> {
+        let header = loop_analysis.loop_header(lp);
+        if func
+            .layout
+            .ebbs()
+            .any(|ebb| ebb != header && loop_analysis.is_in_loop(ebb, lp))
+        {
+            return None;
+        }
+
+        // The loop must end in a conditional branch and a jump, one of them going back to the
+        // header, with no other branches.
+        let last = func.layout.last_inst(header)?;
+        let cond_branch = func.layout.prev_inst(last)?;
+        let opcode = func.dfg[cond_branch].opcode();
+        if func.dfg[last].opcode() != Opcode::Jump
+            || (opcode != Opcode::Brz && opcode != Opcode::Brnz)
+            || func
+                .layout
+                .ebb_insts(header)
+                .filter(|&inst| func.dfg[inst].opcode().is_branch())
+                .count()
+                != 2
+        {
+            return None;
+        }
+        let (back_edge, exit_edge) = match (
+            func.dfg[cond_branch].branch_destination() == Some(header),
+            func.dfg[last].branch_destination() == Some(header),
+        ) {
+            (true, false) => (cond_branch, last),
+            (false, true) => (last, cond_branch),
+            _ => return None,
+        };
+
+        // All the entries must be plain branches, so they can be redirected.
+        let mut entries = Vec::new();
+        for BasicBlock { ebb, inst } in cfg.pred_iter(header) {
+            if ebb == header {
+                continue;
+            }
+            match func.dfg.analyze_branch(inst) {
+                BranchInfo::SingleDest(..) => entries.push(inst),
+                _ => return None,
+            }
+        }
+
+        // The exit condition must compare an induction variable with a constant.
+        let cond_value = func.dfg.inst_args(cond_branch)[0];
+        let (cond, iv_value, bound) = match defining_inst(func, header, cond_value)
+            .map(|inst| &func.dfg[inst])
+        {
+            Some(&InstructionData::IntCompare { cond, args, .. }) => {
+                match (iconst_value(func, args[0]), iconst_value(func, args[1])) {
+                    (_, Some(bound)) => (cond, args[0], bound),
+                    (Some(bound), None) => (cond.reverse(), args[1], bound),
+                    (None, None) => return None,
+                }
+            }
+            Some(&InstructionData::IntCompareImm { cond, arg, imm, .. }) => (cond, arg, imm.into()),
+            // Branching on an integer compares it with zero.
+            _ => (IntCC::NotEqual, cond_value, 0),
+        };
+        if cond == IntCC::Overflow || cond == IntCC::NotOverflow {
+            return None;
+        }
+        let iv_value = func.dfg.resolve_aliases(iv_value);
+
+        // Find the EBB parameter the compared value is derived from.
+        let params = func.dfg.ebb_params(header);
+        let back_args = func.dfg.inst_variable_args(back_edge);
+        let (num, step, compare_next) = params.iter().enumerate().find_map(|(num, &param)| {
+            let next = func.dfg.resolve_aliases(back_args[num]);
+            let step = increment(func, header, param, next)?;
+            if iv_value == param {
+                Some((num, step, false))
+            } else if iv_value == next {
+                Some((num, step, true))
+            } else {
+                None
+            }
+        })?;
+        let ty = func.dfg.value_type(params[num]);
+        if !ty.is_int() || ty.bits() > 64 {
+            return None;
+        }
+
+        // The initial value is known if all the entries pass the same constant.
+        let mut inits = entries
+            .iter()
+            .map(|&inst| iconst_value(func, func.dfg.inst_variable_args(inst)[num]));
+        let first = inits.next().and_then(|init| init);
+        let init = if inits.all(|init| init == first) {
+            first.map(|init| truncate(init as u64, ty))
+        } else {
+            None
+        };
+
+        Some(Self {
+            header,
+            entries,
+            back_edge,
+            exit_edge,
+            size: func.layout.ebb_insts(header).count(),
+            ty,
+            init,
+            step: truncate(step as u64, ty),
+            cond,
+            compare_next,
+            bound: truncate(bound as u64, ty),
+            exit_if: (exit_edge == cond_branch) == (opcode == Opcode::Brnz),
+        })
+    }
+
+    /// Does the loop run another iteration after the one where the induction variable is `iv`?
+    fn continues(&self, iv: u64) -> bool {
+        let x = if self.compare_next {
+            truncate(iv.wrapping_add(self.step), self.ty)
+        } else {
+            iv
+        };
+        (evaluate_icmp(self.cond, x, self.bound, self.ty) != Some(0)) != self.exit_if
+    }
+
+    /// The number of iterations of the loop, if it is known.
+    fn trip_count(&self) -> Option<u64> {
+        let mut iv = self.init?;
+        for count in 1..=MAX_TRIP_COUNT {
+            if !self.continues(iv) {
+                return Some(count);
+            }
+            iv = truncate(iv.wrapping_add(self.step), self.ty);
+        }
+        None
+    }
+
+    /// Unroll the loop if it is small enough. Returns whether it was changed.
+    fn unroll(&self, func: &mut Function, threshold: usize) -> bool {
+        let factor = (threshold / self.size).min(MAX_UNROLL_FACTOR);
+        let mut entries = self.entries.clone();
+        match self.trip_count() {
+            Some(count) if count as usize * self.size <= threshold => {
+                // Fully unroll the loop: peel all the iterations but the last one, which leaves
+                // the loop.
+                self.peel(func, &mut entries, count as usize - 1, true);
+                self.fold(func, self.exit_edge, self.back_edge);
+            }
+            Some(count) if factor >= 2 => {
+                // Peel the iterations left over, so that the loop is only left from the header.
+                self.peel(func, &mut entries, count as usize % factor, true);
+                if let Some(first) = self.peel(func, &mut entries, factor - 1, true) {
+                    func.change_branch_destination(self.back_edge, first);
+                }
+            }
+            None if factor >= 2 && !self.has_outside_uses(func) => {
+                if let Some(first) = self.peel(func, &mut entries, factor - 1, false) {
+                    func.change_branch_destination(self.back_edge, first);
+                }
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    /// Insert `count` copies of the loop body in front of the header, and redirect the branches
+    /// in `entries` to the first one. Each copy branches to the next one, and the last one to the
+    /// header; `entries` is left with that last branch. If `known` is set, the copies don't test
+    /// the exit condition.
+    ///
+    /// Returns the first copy.
+    fn peel(
+        &self,
+        func: &mut Function,
+        entries: &mut Vec<Inst>,
+        count: usize,
+        known: bool,
+    ) -> Option<Ebb> {
+        let mut first = None;
+        for _ in 0..count {
+            let (copy, back_edge, exit_edge) = self.copy_body(func);
+            for &inst in entries.iter() {
+                func.change_branch_destination(inst, copy);
+            }
+            if known {
+                self.fold(func, back_edge, exit_edge);
+            }
+            entries.clear();
+            entries.push(back_edge);
+            first = first.or(Some(copy));
+        }
+        first
+    }
+
+    /// Insert a copy of the loop body before the header.
+    ///
+    /// Returns the copy, and its copies of the back edge and exit edge.
+    fn copy_body(&self, func: &mut Function) -> (Ebb, Inst, Inst) {
+        let copy = func.dfg.make_ebb();
+        func.layout.insert_ebb(copy, self.header);
+        let mut values: SecondaryMap<Value, PackedOption<Value>> = SecondaryMap::new();
+        for i in 0..func.dfg.num_ebb_params(self.header) {
+            let param = func.dfg.ebb_params(self.header)[i];
+            let ty = func.dfg.value_type(param);
+            values[param] = func.dfg.append_ebb_param(copy, ty).into();
+        }
+
+        let mut back_edge = None;
+        let mut exit_edge = None;
+        let insts: Vec<Inst> = func.layout.ebb_insts(self.header).collect();
+        for inst in insts {
+            let mut data = func.dfg[inst].clone();
+            if let Some(list) = data.take_value_list() {
+                let args = list.as_slice(&func.dfg.value_lists).to_vec();
+                data.put_value_list(ValueList::from_slice(&args, &mut func.dfg.value_lists));
+            }
+            let new_inst = func.dfg.make_inst(data);
+            let ctrl_typevar = func.dfg.ctrl_typevar(inst);
+            func.dfg.make_inst_results(new_inst, ctrl_typevar);
+            for i in 0..func.dfg.inst_results(inst).len() {
+                let result = func.dfg.inst_results(inst)[i];
+                values[result] = func.dfg.inst_results(new_inst)[i].into();
+            }
+            for i in 0..func.dfg.inst_args(new_inst).len() {
+                let arg = func.dfg.resolve_aliases(func.dfg.inst_args(new_inst)[i]);
+                if let Some(value) = values[arg].expand() {
+                    func.dfg.inst_args_mut(new_inst)[i] = value;
+                }
+            }
+            func.layout.append_inst(new_inst, copy);
+            let srcloc = func.srclocs[inst];
+            if !srcloc.is_default() {
+                func.srclocs[new_inst] = srcloc;
+            }
+            if inst == self.back_edge {
+                back_edge = Some(new_inst);
+            } else if inst == self.exit_edge {
+                exit_edge = Some(new_inst);
+            }
+        }
+        (copy, back_edge.unwrap(), exit_edge.unwrap())
+    }
+
+    /// Replace the branches `keep` and `remove` at the end of a copy of the loop with a jump to
+    /// the destination of `keep`.
+    fn fold(&self, func: &mut Function, keep: Inst, remove: Inst) {
+        func.layout.remove_inst(remove);
+        if func.dfg[keep].opcode() != Opcode::Jump {
+            let destination = func.dfg[keep].branch_destination().unwrap();
+            let args = func.dfg.inst_variable_args(keep).to_vec();
+            func.dfg.replace(keep).jump(destination, &args);
+        }
+    }
+
+    /// Are values defined in the loop used outside of it? Their definitions no longer dominate
+    /// their uses when the loop can be left from several copies of its body.
+    fn has_outside_uses(&self, func: &Function) -> bool {
+        let defined_in_loop = |value: Value| match func.dfg.value_def(value) {
+            ValueDef::Result(inst, _) => func.layout.inst_ebb(inst) == Some(self.header),
+            ValueDef::Param(ebb, _) => ebb == self.header,
+        };
+        func.layout
+            .ebbs()
+            .filter(|&ebb| ebb != self.header)
+            .flat_map(|ebb| func.layout.ebb_insts(ebb))
+            .any(|inst| {
+                func.dfg
+                    .inst_args(inst)
+                    .iter()
+                    .any(|&arg| defined_in_loop(func.dfg.resolve_aliases(arg)))
+            })
+    }
+}
+
+/// Get the instruction in `header` defining `value`.
+fn defining_inst(func: &Function, header: Ebb, value: Value) -> Option<Inst> {
+    match func.dfg.value_def(func.dfg.resolve_aliases(value)) {
+        ValueDef::Result(inst, _) if func.layout.inst_ebb(inst) == Some(header) => Some(inst),
+        _ => None,
+    }
+}
+
+/// Get the constant value of `value`, if it is defined by an `iconst`.
+fn iconst_value(func: &Function, value: Value) -> Option<i64> {
+    match func.dfg.value_def(func.dfg.resolve_aliases(value)) {
+        ValueDef::Result(inst, _) => match func.dfg[inst] {
+            InstructionData::UnaryImm {
+                opcode: Opcode::Iconst,
+                imm,
+            } => Some(imm.into()),
+            _ => None,
+        },
+        ValueDef::Param(_, _) => None,
+    }
+}
+
+/// Get the constant added to `param` to compute `next` in `header`, if there is one.
+fn increment(func: &Function, header: Ebb, param: Value, next: Value) -> Option<i64> {
+    match func.dfg[defining_inst(func, header, next)?] {
+        InstructionData::BinaryImm {
+            opcode: Opcode::IaddImm,
+            arg,
+            imm,
+        } if func.dfg.resolve_aliases(arg) == param => Some(imm.into()),
+        InstructionData::Binary {
+            opcode: Opcode::Iadd,
+            args,
+        } => {
+            if func.dfg.resolve_aliases(args[0]) == param {
+                iconst_value(func, args[1])
+            } else if func.dfg.resolve_aliases(args[1]) == param {
+                iconst_value(func, args[0])
+            } else {
+                None
+            }
+        }
+        _ => None,
+    }
+}
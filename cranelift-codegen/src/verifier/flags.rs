@@ -111,8 +111,7 @@ impl<'a> FlagsVerifier<'a> {
                 if self
                     .encinfo
                     .as_ref()
-                    .and_then(|ei| ei.operand_constraints(self.func.encodings[inst]))
-                    .map_or(false, |c| c.clobbers_flags)
+                    .map_or(false, |ei| encoding_clobbers_flags(self.func, ei, inst))
                     && live_val.is_some()
                 {
                     errors.report((
@@ -158,6 +157,20 @@ impl<'a> FlagsVerifier<'a> {
     }
 }
 
+/// Does the encoding of `inst` clobber the CPU flags?
+///
+/// Passes that extend the live ranges of flags values use this to keep them acceptable to
+/// `verify_flags`.
+pub(crate) fn encoding_clobbers_flags(
+    func: &ir::Function,
+    encinfo: &isa::EncInfo,
+    inst: ir::Inst,
+) -> bool {
+    encinfo
+        .operand_constraints(func.encodings[inst])
+        .map_or(false, |c| c.clobbers_flags)
+}
+
 // Merge live flags values, or return an error on conflicting values.
 fn merge(
     a: &mut Option<ir::Value>,
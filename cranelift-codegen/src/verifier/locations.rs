@@ -79,7 +79,7 @@ impl<'a> LocationVerifier<'a> {
                 }
 
                 self.update_diversions(inst, &mut divert, errors)?;
-                is_after_branch = opcode.is_branch();
+                is_after_branch = opcode.is_branch() && !opcode.can_unwind();
             }
         }
 
@@ -56,6 +56,7 @@
 //! - Swizzle and shuffle instructions take a variable number of lane arguments. The number
 //!   of arguments must match the destination type, and the lane indexes must be in range.
 
+pub(crate) use self::flags::encoding_clobbers_flags;
 use self::flags::verify_flags;
 use crate::dbg::DisplayList;
 use crate::dominator_tree::DominatorTree;
@@ -68,10 +69,10 @@ use crate::ir::{
     types, ArgumentLoc, Ebb, FuncRef, Function, GlobalValue, Inst, InstructionData, JumpTable,
     Opcode, SigRef, StackSlot, StackSlotKind, Type, Value, ValueDef, ValueList, ValueLoc,
 };
-use crate::isa::TargetIsa;
+use crate::isa::{CallConv, TargetIsa};
 use crate::iterators::IteratorExtras;
 use crate::print_errors::pretty_verifier_error;
-use crate::settings::FlagsOrIsa;
+use crate::settings::{FlagsOrIsa, TlsModel};
 use crate::timing;
 use alloc::collections::BTreeSet;
 use alloc::string::{String, ToString};
@@ -546,6 +547,26 @@ impl<'a> Verifier<'a> {
             ));
         }
 
+        // The values read by `landing_pad` are only available when entering from an unwind edge.
+        if self.func.dfg[inst].opcode() == Opcode::LandingPad {
+            if self.func.layout.first_inst(ebb) != Some(inst) {
+                return errors.fatal((
+                    inst,
+                    self.context(inst),
+                    format!("landing_pad must be the first instruction in {}", ebb),
+                ));
+            }
+            for BasicBlock { inst: pred, .. } in self.expected_cfg.pred_iter(ebb) {
+                if !self.func.dfg[pred].opcode().can_unwind() {
+                    return errors.fatal((
+                        inst,
+                        self.context(inst),
+                        format!("{} is reached from {} which can't unwind", ebb, pred),
+                    ));
+                }
+            }
+        }
+
         // Parameters belong to the correct ebb.
         for &arg in self.func.dfg.ebb_params(ebb) {
             match self.func.dfg.value_def(arg) {
@@ -583,7 +604,7 @@ impl<'a> Verifier<'a> {
         let num_fixed_results = inst_data.opcode().constraints().num_fixed_results();
         // var_results is 0 if we aren't a call instruction
         let var_results = dfg
-            .call_signature(inst)
+            .non_tail_call_signature(inst)
             .map_or(0, |sig| dfg.signatures[sig].returns.len());
         let total_results = num_fixed_results + var_results;
 
@@ -683,6 +704,26 @@ impl<'a> Verifier<'a> {
                 self.verify_sig_ref(inst, sig_ref, errors)?;
                 self.verify_value_list(inst, args, errors)?;
             }
+            Invoke {
+                func_ref,
+                destination,
+                ref args,
+                ..
+            } => {
+                self.verify_func_ref(inst, func_ref, errors)?;
+                self.verify_ebb(inst, destination, errors)?;
+                self.verify_value_list(inst, args, errors)?;
+            }
+            InvokeIndirect {
+                sig_ref,
+                destination,
+                ref args,
+                ..
+            } => {
+                self.verify_sig_ref(inst, sig_ref, errors)?;
+                self.verify_ebb(inst, destination, errors)?;
+                self.verify_value_list(inst, args, errors)?;
+            }
             FuncAddr { func_ref, .. } => {
                 self.verify_func_ref(inst, func_ref, errors)?;
             }
@@ -742,6 +783,14 @@ impl<'a> Verifier<'a> {
                 self.verify_bitcast(inst, arg, errors)?;
             }
 
+            AtomicLoad { .. }
+            | AtomicStore { .. }
+            | AtomicRmw { .. }
+            | AtomicCas { .. }
+            | Fence { .. } => {
+                self.verify_atomic(inst, errors)?;
+            }
+
             // Exhaustive list so we can't forget to add new formats
             Unary { .. }
             | UnaryImm { .. }
@@ -989,6 +1038,20 @@ impl<'a> Verifier<'a> {
                             format!("uses value {} from itself", v),
                         ));
                     }
+                    // The results of an invoke are only defined when the callee returns
+                    // normally, which continues with the next instruction.
+                    if dfg[def_inst].opcode().can_unwind()
+                        && !self.func.layout.next_inst(def_inst).map_or(false, |next| {
+                            self.expected_domtree
+                                .dominates(next, loc_inst, &self.func.layout)
+                        })
+                    {
+                        return errors.fatal((
+                            loc_inst,
+                            self.context(loc_inst),
+                            format!("uses value {} on the unwind path of {}", v, def_inst),
+                        ));
+                    }
                 }
             }
             ValueDef::Param(ebb, _) => {
@@ -1076,6 +1139,76 @@ impl<'a> Verifier<'a> {
         }
     }
 
+    /// Check the memory ordering and alignment of an atomic instruction.
+    fn verify_atomic(&self, inst: Inst, errors: &mut VerifierErrors) -> VerifierStepResult<()> {
+        if let Some(isa) = &self.isa {
+            if !isa.flags().enable_atomics() {
+                return errors.fatal((
+                    inst,
+                    self.context(inst),
+                    "atomic instructions cannot be used without enable_atomics",
+                ));
+            }
+        }
+
+        let offset = match self.func.dfg[inst] {
+            ir::InstructionData::AtomicLoad {
+                ordering, offset, ..
+            } => {
+                if !ordering.is_valid_for_load() {
+                    return errors.fatal((
+                        inst,
+                        self.context(inst),
+                        format!("{} ordering is not valid on an atomic load", ordering),
+                    ));
+                }
+                offset
+            }
+            ir::InstructionData::AtomicStore {
+                ordering, offset, ..
+            } => {
+                if !ordering.is_valid_for_store() {
+                    return errors.fatal((
+                        inst,
+                        self.context(inst),
+                        format!("{} ordering is not valid on an atomic store", ordering),
+                    ));
+                }
+                offset
+            }
+            ir::InstructionData::AtomicRmw { .. } | ir::InstructionData::AtomicCas { .. } => {
+                return Ok(())
+            }
+            ir::InstructionData::Fence { ordering, .. } => {
+                if !ordering.is_valid_for_fence() {
+                    return errors.fatal((
+                        inst,
+                        self.context(inst),
+                        format!("{} ordering is not valid on a fence", ordering),
+                    ));
+                }
+                return Ok(());
+            }
+            _ => panic!("not an atomic instruction"),
+        };
+
+        // Atomic accesses must be naturally aligned. The base address is only known at runtime,
+        // but the static offset must preserve its alignment.
+        let bytes = i64::from(self.func.dfg.ctrl_typevar(inst).bytes());
+        let offset: i64 = offset.into();
+        if offset % bytes != 0 {
+            return errors.fatal((
+                inst,
+                self.context(inst),
+                format!(
+                    "atomic access offset {} is not a multiple of the {}-byte access size",
+                    offset, bytes
+                ),
+            ));
+        }
+        Ok(())
+    }
+
     fn domtree_integrity(
         &self,
         domtree: &DominatorTree,
@@ -1200,6 +1333,7 @@ impl<'a> Verifier<'a> {
         let _ = self.typecheck_fixed_args(inst, ctrl_type, errors);
         let _ = self.typecheck_variable_args(inst, errors);
         let _ = self.typecheck_return(inst, errors);
+        let _ = self.typecheck_tail_call(inst, errors);
         let _ = self.typecheck_special(inst, ctrl_type, errors);
 
         // Misuses of copy_nop instructions are fatal
@@ -1296,6 +1430,20 @@ impl<'a> Verifier<'a> {
         errors: &mut VerifierErrors,
     ) -> VerifierStepResult<()> {
         match self.func.dfg.analyze_branch(inst) {
+            // The variable arguments of an invoke are passed to the callee, not the landing pad.
+            BranchInfo::SingleDest(ebb, _) if self.func.dfg[inst].opcode().can_unwind() => {
+                let arg_count = self.func.dfg.num_ebb_params(ebb);
+                if arg_count != 0 {
+                    return errors.nonfatal((
+                        inst,
+                        self.context(inst),
+                        format!(
+                            "landing pad {} can't have parameters, but has {}",
+                            ebb, arg_count,
+                        ),
+                    ));
+                }
+            }
             BranchInfo::SingleDest(ebb, _) => {
                 let iter = self
                     .func
@@ -1501,6 +1649,69 @@ impl<'a> Verifier<'a> {
         Ok(())
     }
 
+    /// Check that a tail call can reuse the current function's frame and return values.
+    fn typecheck_tail_call(
+        &self,
+        inst: Inst,
+        errors: &mut VerifierErrors,
+    ) -> VerifierStepResult<()> {
+        match self.func.dfg[inst].opcode() {
+            Opcode::ReturnCall | Opcode::ReturnCallIndirect => {}
+            _ => return Ok(()),
+        }
+        let sig_ref = match self.func.dfg.call_signature(inst) {
+            Some(sig_ref) => sig_ref,
+            None => return Ok(()),
+        };
+        let caller_sig = &self.func.signature;
+        let callee_sig = &self.func.dfg.signatures[sig_ref];
+
+        if callee_sig.call_conv != caller_sig.call_conv {
+            return errors.nonfatal((
+                inst,
+                self.context(inst),
+                format!(
+                    "tail call to {} function from {} function",
+                    callee_sig.call_conv, caller_sig.call_conv
+                ),
+            ));
+        }
+        if callee_sig.return_types() != caller_sig.return_types() {
+            return errors.nonfatal((
+                inst,
+                self.context(inst),
+                "tail call callee must return the same types as the caller",
+            ));
+        }
+        // The parts or copies of a struct argument live in the caller's frame, which a tail call
+        // reuses.
+        if callee_sig.uses_struct_arguments() {
+            return errors.nonfatal((
+                inst,
+                self.context(inst),
+                "tail call can't pass structs by value",
+            ));
+        }
+        // Only the `tail` convention lets the callee pop a larger stack argument area than the
+        // one the caller received. The sizes are only known once both signatures are legalized.
+        if callee_sig.call_conv != CallConv::Tail
+            && caller_sig.params_assigned()
+            && callee_sig.params_assigned()
+            && callee_sig.stack_args_size() > caller_sig.stack_args_size()
+        {
+            return errors.nonfatal((
+                inst,
+                self.context(inst),
+                format!(
+                    "tail call needs {} bytes of stack arguments, but the caller only has {}",
+                    callee_sig.stack_args_size(),
+                    caller_sig.stack_args_size()
+                ),
+            ));
+        }
+        Ok(())
+    }
+
     // Check special-purpose type constraints that can't be expressed in the normal opcode
     // constraints.
     fn typecheck_special(
@@ -1588,8 +1799,53 @@ impl<'a> Verifier<'a> {
                     ));
                 }
             }
-            ir::InstructionData::UnaryGlobalValue { global_value, .. } => {
+            ir::InstructionData::UnaryGlobalValue {
+                opcode,
+                global_value,
+            } => {
+                let is_tls = match self.func.global_values[global_value] {
+                    ir::GlobalValueData::Symbol { tls, .. } => tls,
+                    _ => false,
+                };
+                match opcode {
+                    Opcode::SymbolValue if is_tls => {
+                        return errors.nonfatal((
+                            inst,
+                            self.context(inst),
+                            format!("symbol_value can't reference tls symbol {}", global_value),
+                        ));
+                    }
+                    Opcode::TlsValue if !is_tls => {
+                        return errors.nonfatal((
+                            inst,
+                            self.context(inst),
+                            format!(
+                                "tls_value must reference a tls symbol, not {}",
+                                global_value
+                            ),
+                        ));
+                    }
+                    _ => {}
+                }
                 if let Some(isa) = self.isa {
+                    // `global_value` of a tls symbol is legalized to `tls_value`, whose lowering
+                    // depends on the target and the `tls_model` setting.
+                    if is_tls && (opcode == Opcode::TlsValue || opcode == Opcode::GlobalValue) {
+                        if !isa.supports_tls() {
+                            return errors.fatal((
+                                inst,
+                                self.context(inst),
+                                format!("thread-local storage isn't supported by {}", isa.triple()),
+                            ));
+                        }
+                        if isa.flags().tls_model() == TlsModel::None {
+                            return errors.fatal((
+                                inst,
+                                self.context(inst),
+                                "thread-local storage cannot be used without tls_model",
+                            ));
+                        }
+                    }
                     let inst_type = self.func.dfg.value_type(self.func.dfg.first_result(inst));
                     let global_type = self.func.global_values[global_value].global_type(isa);
                     if inst_type != global_type {
@@ -1906,6 +2162,22 @@ impl<'a> Verifier<'a> {
                     Ok(())
                 }
             }
+            ir::InstructionData::BinaryImm {
+                opcode: ir::instructions::Opcode::DynamicStackAlloc,
+                imm,
+                ..
+            } => {
+                let align: i64 = imm.into();
+                if align <= 0 || align > 1 << 31 || align & (align - 1) != 0 {
+                    errors.fatal((
+                        inst,
+                        self.context(inst),
+                        format!("The alignment {} is not a power of two up to 2^31", align),
+                    ))
+                } else {
+                    Ok(())
+                }
+            }
             _ => Ok(()),
         }
     }
@@ -600,6 +600,34 @@ pub fn write_operands(
                 DisplayValues(&args[1..])
             )
         }
+        Invoke {
+            func_ref,
+            destination,
+            ref args,
+            ..
+        } => write!(
+            w,
+            " {}({}), {}",
+            func_ref,
+            DisplayValues(args.as_slice(pool)),
+            destination
+        ),
+        InvokeIndirect {
+            sig_ref,
+            destination,
+            ref args,
+            ..
+        } => {
+            let args = args.as_slice(pool);
+            write!(
+                w,
+                " {}, {}({}), {}",
+                sig_ref,
+                args[0],
+                DisplayValues(&args[1..]),
+                destination
+            )
+        }
         FuncAddr { func_ref, .. } => write!(w, " {}", func_ref),
         StackLoad {
             stack_slot, offset, ..
@@ -652,6 +680,48 @@ pub fn write_operands(
                 offset
             )
         }
+        AtomicLoad {
+            ordering,
+            flags,
+            arg,
+            offset,
+            ..
+        } => write!(w, " {}{} {}{}", ordering, flags, arg, offset),
+        AtomicStore {
+            ordering,
+            flags,
+            ref args,
+            offset,
+            ..
+        } => {
+            let args = args.as_slice(pool);
+            write!(
+                w,
+                " {}{} {}, {}{}",
+                ordering, flags, args[0], args[1], offset
+            )
+        }
+        AtomicRmw {
+            ordering,
+            flags,
+            op,
+            args,
+            ..
+        } => write!(w, " {}{} {} {}, {}", ordering, flags, op, args[0], args[1]),
+        AtomicCas {
+            ordering,
+            flags,
+            ref args,
+            ..
+        } => {
+            let args = args.as_slice(pool);
+            write!(
+                w,
+                " {}{} {}, {}, {}",
+                ordering, flags, args[0], args[1], args[2]
+            )
+        }
+        Fence { ordering, .. } => write!(w, " {}", ordering),
         RegMove { arg, src, dst, .. } => {
             if let Some(isa) = isa {
                 let regs = isa.register_info();
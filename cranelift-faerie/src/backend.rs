@@ -4,7 +4,8 @@ use crate::container;
 use crate::traps::{FaerieTrapManifest, FaerieTrapSink};
 use anyhow::Error;
 use cranelift_codegen::binemit::{
-    Addend, CodeOffset, NullStackmapSink, NullTrapSink, Reloc, RelocSink, Stackmap, StackmapSink,
+    Addend, CodeOffset, NullCallSiteSink, NullStackmapSink, NullTrapSink, Reloc, RelocSink,
+    Stackmap, StackmapSink,
 };
 use cranelift_codegen::isa::TargetIsa;
 use cranelift_codegen::{self, binemit, ir};
@@ -72,6 +73,9 @@ impl FaerieBuilder {
 /// A `FaerieBackend` implements `Backend` and emits ".o" files using the `faerie` library.
 ///
 /// See the `FaerieBuilder` for a convenient way to construct `FaerieBackend` instances.
+///
+/// Faerie has no `.tdata` or `.tbss` sections, so thread-local data objects can be declared and
+/// accessed, but not defined. Use the `cranelift-object` backend to define them.
 pub struct FaerieBackend {
     isa: Box<dyn TargetIsa>,
     artifact: faerie::Artifact,
@@ -135,6 +139,7 @@ impl Backend for FaerieBackend {
         name: &str,
         linkage: Linkage,
         writable: bool,
+        _tls: bool,
         align: Option<u8>,
     ) {
         self.artifact
@@ -153,6 +158,7 @@ impl Backend for FaerieBackend {
         let mut code: Vec<u8> = vec![0; total_size as usize];
         // TODO: Replace this with FaerieStackmapSink once it is implemented.
         let mut stackmap_sink = NullStackmapSink {};
+        let mut call_site_sink = NullCallSiteSink {};
 
         // Non-lexical lifetimes would obviate the braces here.
         {
@@ -173,6 +179,7 @@ impl Backend for FaerieBackend {
                         &mut reloc_sink,
                         &mut trap_sink,
                         &mut stackmap_sink,
+                        &mut call_site_sink,
                     )
                 };
                 trap_manifest.add_sink(trap_sink);
@@ -185,6 +192,7 @@ impl Backend for FaerieBackend {
                         &mut reloc_sink,
                         &mut trap_sink,
                         &mut stackmap_sink,
+                        &mut call_site_sink,
                     )
                 };
             }
@@ -205,10 +213,19 @@ impl Backend for FaerieBackend {
         _id: DataId,
         name: &str,
         _writable: bool,
+        tls: bool,
         _align: Option<u8>,
         data_ctx: &DataContext,
         namespace: &ModuleNamespace<Self>,
     ) -> ModuleResult<FaerieCompiledData> {
+        if tls {
+            // Imported TLS symbols work, since they only need relocations in the code.
+            return Err(ModuleError::Backend(
+                "faerie doesn't support defining TLS data; use cranelift-object instead"
+                    .to_string(),
+            ));
+        }
+
         let &DataDescription {
             ref init,
             ref function_decls,
@@ -281,6 +298,16 @@ impl Backend for FaerieBackend {
         unimplemented!()
     }
 
+    fn define_libcall_thunk(&mut self, libcall: ir::LibCall, code: &[u8]) {
+        self.artifact
+            .declare_with(
+                (self.libcall_names)(libcall),
+                faerie::Decl::function(),
+                code.to_vec(),
+            )
+            .expect("faerie definition of libcall thunk");
+    }
+
     fn finalize_function(
         &mut self,
         _id: FuncId,
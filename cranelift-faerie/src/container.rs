@@ -29,9 +29,31 @@ pub fn raw_relocation(reloc: Reloc, triple: &Triple) -> (u32, i64) {
                             // R_X86_64_GOTPCRELX/R_X86_64_REX_GOTPCRELX.
                             Reloc::X86CallPLTRel4 => elf::reloc::R_X86_64_PLT32,
                             Reloc::X86GOTPCRel4 => elf::reloc::R_X86_64_GOTPCREL,
+                            Reloc::ElfX86_64TlsGd => elf::reloc::R_X86_64_TLSGD,
+                            Reloc::ElfX86_64GotTpOff => elf::reloc::R_X86_64_GOTTPOFF,
                             _ => unimplemented!(),
                         }
                     }
+                    Architecture::Aarch64(_) => match reloc {
+                        Reloc::Abs4 => elf::reloc::R_AARCH64_ABS32,
+                        Reloc::Abs8 => elf::reloc::R_AARCH64_ABS64,
+                        Reloc::Arm64Call => elf::reloc::R_AARCH64_CALL26,
+                        Reloc::Arm64AdrPrelPgHi21 => elf::reloc::R_AARCH64_ADR_PREL_PG_HI21,
+                        Reloc::Arm64AddAbsLo12Nc => elf::reloc::R_AARCH64_ADD_ABS_LO12_NC,
+                        Reloc::Arm64AdrGotPage21 => elf::reloc::R_AARCH64_ADR_GOT_PAGE,
+                        Reloc::Arm64Ld64GotLo12Nc => elf::reloc::R_AARCH64_LD64_GOT_LO12_NC,
+                        _ => unimplemented!(),
+                    },
+                    Architecture::Arm(_) => match reloc {
+                        Reloc::Abs4 => elf::reloc::R_ARM_ABS32,
+                        Reloc::Arm32Call => elf::reloc::R_ARM_CALL,
+                        Reloc::Arm32MovwAbsNc => elf::reloc::R_ARM_MOVW_ABS_NC,
+                        Reloc::Arm32MovtAbs => elf::reloc::R_ARM_MOVT_ABS,
+                        Reloc::Arm32ThmCall => elf::reloc::R_ARM_THM_PC22,
+                        Reloc::Arm32ThmMovwAbsNc => elf::reloc::R_ARM_THM_MOVW_ABS_NC,
+                        Reloc::Arm32ThmMovtAbs => elf::reloc::R_ARM_THM_MOVT_ABS,
+                        _ => unimplemented!(),
+                    },
                     _ => unimplemented!("unsupported architecture: {}", triple),
                 },
                 // Most ELF relocations do not include an implicit addend.
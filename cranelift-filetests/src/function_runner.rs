@@ -1,5 +1,5 @@
 use core::mem;
-use cranelift_codegen::binemit::{NullRelocSink, NullStackmapSink, NullTrapSink};
+use cranelift_codegen::binemit::{NullCallSiteSink, NullRelocSink, NullStackmapSink, NullTrapSink};
 use cranelift_codegen::ir::Function;
 use cranelift_codegen::isa::TargetIsa;
 use cranelift_codegen::{settings, Context};
@@ -59,6 +59,7 @@ impl FunctionRunner {
         let relocs = &mut NullRelocSink {};
         let traps = &mut NullTrapSink {};
         let stackmaps = &mut NullStackmapSink {};
+        let call_sites = &mut NullCallSiteSink {};
         let code_info = context
             .compile(self.isa.as_ref())
             .map_err(|e| e.to_string())?;
@@ -72,6 +73,7 @@ impl FunctionRunner {
                 relocs,
                 traps,
                 stackmaps,
+                call_sites,
             );
         };
 
@@ -48,12 +48,15 @@ mod test_postopt;
 mod test_preopt;
 mod test_print_cfg;
 mod test_regalloc;
+mod test_rle;
 mod test_rodata;
 mod test_run;
 mod test_safepoint;
+mod test_sccp;
 mod test_shrink;
 mod test_simple_gvn;
 mod test_simple_preopt;
+mod test_unroll;
 mod test_unwind;
 mod test_verifier;
 
@@ -130,12 +133,15 @@ fn new_subtest(parsed: &TestCommand) -> subtest::SubtestResult<Box<dyn subtest::
         "simple_preopt" => test_simple_preopt::subtest(parsed),
         "print-cfg" => test_print_cfg::subtest(parsed),
         "regalloc" => test_regalloc::subtest(parsed),
+        "rle" => test_rle::subtest(parsed),
         "run" => test_run::subtest(parsed),
         "shrink" => test_shrink::subtest(parsed),
         "simple-gvn" => test_simple_gvn::subtest(parsed),
         "verifier" => test_verifier::subtest(parsed),
         "preopt" => test_preopt::subtest(parsed),
         "safepoint" => test_safepoint::subtest(parsed),
+        "sccp" => test_sccp::subtest(parsed),
+        "unroll" => test_unroll::subtest(parsed),
         "unwind" => test_unwind::subtest(parsed),
         _ => Err(format!("unknown test command '{}'", parsed.command)),
     }
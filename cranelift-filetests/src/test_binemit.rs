@@ -102,6 +102,10 @@ impl binemit::CodeSink for TextSink {
         write!(self.text, "{} ", code).unwrap();
     }
 
+    fn add_call_site(&mut self, landing_pad: binemit::CodeOffset, _srcloc: ir::SourceLoc) {
+        write!(self.text, "landing_pad({}) ", landing_pad).unwrap();
+    }
+
     fn begin_jumptables(&mut self) {
         self.code_size = self.offset
     }
@@ -110,6 +110,7 @@ impl binemit::CodeSink for RodataSink {
     fn reloc_constant(&mut self, _: binemit::Reloc, _: ir::ConstantOffset) {}
     fn reloc_jt(&mut self, _reloc: binemit::Reloc, _jt: ir::JumpTable) {}
     fn trap(&mut self, _code: ir::TrapCode, _srcloc: ir::SourceLoc) {}
+    fn add_call_site(&mut self, _: binemit::CodeOffset, _: ir::SourceLoc) {}
     fn begin_jumptables(&mut self) {
         assert!(!self.in_rodata);
     }
@@ -67,6 +67,7 @@ where
         name: &str,
         linkage: Linkage,
         writable: bool,
+        tls: bool,
         align: Option<u8>,
     );
 
@@ -84,12 +85,15 @@ where
 
     /// Define a zero-initialized data object of the given size.
     ///
-    /// Data objects must be declared before being defined.
+    /// Data objects must be declared before being defined. A `tls` data object goes in the
+    /// thread-local sections of the object file. Not every backend can define those: the faerie
+    /// backend returns an error for them, and only supports importing thread-local data.
     fn define_data(
         &mut self,
         id: DataId,
         name: &str,
         writable: bool,
+        tls: bool,
         align: Option<u8>,
         data_ctx: &DataContext,
         namespace: &ModuleNamespace<Self>,
@@ -114,6 +118,14 @@ where
         addend: binemit::Addend,
     );
 
+    /// Define a thunk that the target ISA expects the module to provide, such as a retpoline
+    /// thunk, with the given machine code. It is local to the module and named after `libcall`,
+    /// so that the references to `libcall` resolve to it.
+    ///
+    /// The `Module` defines each of the ISA's `libcall_thunks` once, right after creating the
+    /// backend.
+    fn define_libcall_thunk(&mut self, libcall: ir::LibCall, code: &[u8]);
+
     /// Perform all outstanding relocations on the given function. This requires all `Local`
     /// and `Export` entities referenced to be defined.
     fn finalize_function(
@@ -159,8 +171,34 @@ pub fn default_libcall_names() -> Box<dyn Fn(ir::LibCall) -> String> {
         ir::LibCall::TruncF64 => "trunc".to_owned(),
         ir::LibCall::NearestF32 => "nearbyintf".to_owned(),
         ir::LibCall::NearestF64 => "nearbyint".to_owned(),
+        ir::LibCall::UdivI32 => "__udivsi3".to_owned(),
+        ir::LibCall::SdivI32 => "__divsi3".to_owned(),
+        ir::LibCall::UremI32 => "__umodsi3".to_owned(),
+        ir::LibCall::SremI32 => "__modsi3".to_owned(),
+        ir::LibCall::UdivI64 => "__udivdi3".to_owned(),
+        ir::LibCall::SdivI64 => "__divdi3".to_owned(),
+        ir::LibCall::UremI64 => "__umoddi3".to_owned(),
+        ir::LibCall::SremI64 => "__moddi3".to_owned(),
+        ir::LibCall::UdivI128 => "__udivti3".to_owned(),
+        ir::LibCall::SdivI128 => "__divti3".to_owned(),
+        ir::LibCall::UremI128 => "__umodti3".to_owned(),
+        ir::LibCall::SremI128 => "__modti3".to_owned(),
+        ir::LibCall::FcvtFromSintI64F32 => "__floatdisf".to_owned(),
+        ir::LibCall::FcvtFromSintI64F64 => "__floatdidf".to_owned(),
+        ir::LibCall::FcvtFromUintI64F32 => "__floatundisf".to_owned(),
+        ir::LibCall::FcvtFromUintI64F64 => "__floatundidf".to_owned(),
+        ir::LibCall::FcvtToSintF32I64 => "__fixsfdi".to_owned(),
+        ir::LibCall::FcvtToSintF64I64 => "__fixdfdi".to_owned(),
+        ir::LibCall::FcvtToUintF32I64 => "__fixunssfdi".to_owned(),
+        ir::LibCall::FcvtToUintF64I64 => "__fixunsdfdi".to_owned(),
         ir::LibCall::Memcpy => "memcpy".to_owned(),
         ir::LibCall::Memset => "memset".to_owned(),
         ir::LibCall::Memmove => "memmove".to_owned(),
+
+        ir::LibCall::ElfTlsGetAddr => "__tls_get_addr".to_owned(),
+        ir::LibCall::ElfGlobalOffsetTable => "_GLOBAL_OFFSET_TABLE_".to_owned(),
+
+        ir::LibCall::RetpolineThunkR11 => "__x86_indirect_thunk_r11".to_owned(),
+        ir::LibCall::RetpolineThunkEax => "__x86_indirect_thunk_eax".to_owned(),
     })
 }
@@ -0,0 +1,621 @@
+//! Function inlining.
+//!
+//! The `Inliner` keeps the IR bodies of functions that may be inlined, and splices copies of them
+//! into their callers at `call` sites. The EBB containing the call is split after it; the call
+//! becomes a jump to the copy of the callee's entry EBB, and the callee's returns become jumps to
+//! the rest of the caller's EBB, which takes the return values as parameters.
+//!
+//! Only small callees are inlined, and the calls in the inlined code are themselves inlined up to
+//! a fixed depth. Both the caller and the callees must not be legalized yet.
+
+use crate::module::FuncId;
+use crate::HashMap;
+use cranelift_codegen::entity::packed_option::PackedOption;
+use cranelift_codegen::entity::{EntityRef, SecondaryMap};
+use cranelift_codegen::ir::{
+    self, ArgumentPurpose, Ebb, ExtFuncData, GlobalValue, GlobalValueData, HeapStyle, Inst,
+    InstBuilder, InstructionData, JumpTableData, Opcode, StackSlotKind, Value, ValueList,
+};
+use std::vec::Vec;
+
+/// The default maximum number of instructions in an inlined function.
+const DEFAULT_MAX_SIZE: usize = 16;
+
+/// The maximum depth of calls inlined into inlined code.
+const MAX_DEPTH: usize = 4;
+
+/// Function bodies available for inlining, and the heuristic deciding when to inline them.
+pub(crate) struct Inliner {
+    bodies: HashMap<FuncId, ir::Function>,
+    max_size: usize,
+}
+
+impl Inliner {
+    /// Create an inliner without any function bodies.
+    pub fn new() -> Self {
+        Self {
+            bodies: HashMap::new(),
+            max_size: DEFAULT_MAX_SIZE,
+        }
+    }
+
+    /// Set the maximum number of instructions in an inlined function.
+    pub fn set_max_size(&mut self, max_size: usize) {
+        self.max_size = max_size;
+    }
+
+    /// Make the body of `func` available for inlining, replacing any previous body.
+    pub fn add_body(&mut self, func: FuncId, body: ir::Function) {
+        self.bodies.insert(func, body);
+    }
+
+    /// Inline the calls in `caller` to functions with a body, when `can_inline` accepts them.
+    ///
+    /// Returns the number of calls inlined.
+    pub fn inline_calls<F>(&self, caller: &mut ir::Function, can_inline: F) -> usize
+    where
+        F: Fn(FuncId) -> bool,
+    {
+        let mut inlined = 0;
+        let mut calls: Vec<Inst> = caller
+            .layout
+            .ebbs()
+            .flat_map(|ebb| caller.layout.ebb_insts(ebb))
+            .collect();
+        for _ in 0..MAX_DEPTH {
+            let mut inlined_calls = Vec::new();
+            for call in calls {
+                let callee = match self.callee(caller, call) {
+                    Some((id, callee)) if can_inline(id) => callee,
+                    _ => continue,
+                };
+                if !self.should_inline(caller, call, callee) {
+                    continue;
+                }
+                inlined_calls.extend(inline_call(caller, call, callee));
+                inlined += 1;
+            }
+            if inlined_calls.is_empty() {
+                break;
+            }
+            calls = inlined_calls;
+        }
+        inlined
+    }
+
+    /// Get the function called by `inst`, if it is a direct `call` to a function with a body.
+    fn callee(&self, caller: &ir::Function, inst: Inst) -> Option<(FuncId, &ir::Function)> {
+        let func_ref = match caller.dfg[inst] {
+            InstructionData::Call {
+                opcode: Opcode::Call,
+                func_ref,
+                ..
+            } => func_ref,
+            _ => return None,
+        };
+        let name = &caller.dfg.ext_funcs[func_ref].name;
+        if *name == caller.name {
+            return None;
+        }
+        match *name {
+            ir::ExternalName::User {
+                namespace: 0,
+                index,
+            } => {
+                let id = FuncId::from_u32(index);
+                self.bodies.get(&id).map(|body| (id, body))
+            }
+            _ => None,
+        }
+    }
+
+    /// Should `callee` be inlined at `call`?
+    fn should_inline(&self, caller: &ir::Function, call: Inst, callee: &ir::Function) -> bool {
+        let entry = match callee.layout.entry_block() {
+            Some(entry) => entry,
+            None => return false,
+        };
+
+        // The size heuristic.
+        let size: usize = callee
+            .layout
+            .ebbs()
+            .map(|ebb| callee.layout.ebb_insts(ebb).count())
+            .sum();
+        if size > self.max_size {
+            return false;
+        }
+
+        // The call must pass the values the callee expects, and get back what it returns. A
+        // different calling convention could also give the callee's prologue behavior that
+        // inlining would drop, like the `baldrdash` and `probestack` conventions.
+        let sig = &caller.dfg.signatures[caller.dfg.call_signature(call).unwrap()];
+        if sig.call_conv != callee.signature.call_conv
+            || sig.params.len() != callee.signature.params.len()
+            || sig.returns.len() != callee.signature.returns.len()
+            || !callee
+                .dfg
+                .ebb_params(entry)
+                .iter()
+                .zip(caller.dfg.inst_args(call))
+                .all(|(&param, &arg)| callee.dfg.value_type(param) == caller.dfg.value_type(arg))
+            || !callee
+                .signature
+                .returns
+                .iter()
+                .zip(caller.dfg.inst_results(call))
+                .all(|(ret, &result)| ret.value_type == caller.dfg.value_type(result))
+        {
+            return false;
+        }
+
+        // Global values based on the VM context only keep their meaning if the callee gets the
+        // caller's VM context.
+        let uses_vmctx = callee.global_values.values().any(|data| match *data {
+            GlobalValueData::VMContext => true,
+            _ => false,
+        });
+        if uses_vmctx {
+            let passed = callee
+                .signature
+                .special_param_index(ArgumentPurpose::VMContext)
+                .map(|i| caller.dfg.resolve_aliases(caller.dfg.inst_args(call)[i]));
+            if passed.is_none() || passed != caller.special_param(ArgumentPurpose::VMContext) {
+                return false;
+            }
+        }
+
+        // Only explicit stack slots exist before legalization.
+        if callee
+            .stack_slots
+            .values()
+            .any(|slot| slot.kind != StackSlotKind::ExplicitSlot)
+        {
+            return false;
+        }
+
+        callee.layout.ebbs().all(|ebb| {
+            callee.layout.ebb_insts(ebb).all(|inst| {
+                !callee.encodings[inst].is_legal()
+                    && match callee.dfg[inst].opcode() {
+                        // Tail calls would return from the caller, and stack allocations would
+                        // only be released when the caller returns.
+                        Opcode::ReturnCall
+                        | Opcode::ReturnCallIndirect
+                        | Opcode::DynamicStackAlloc => false,
+                        _ => true,
+                    }
+            })
+        })
+    }
+}
+
+/// The caller's entities corresponding to the callee's.
+struct EntityMap {
+    ebbs: SecondaryMap<Ebb, PackedOption<Ebb>>,
+    values: SecondaryMap<Value, PackedOption<Value>>,
+    stack_slots: Vec<ir::StackSlot>,
+    global_values: Vec<GlobalValue>,
+    heaps: Vec<ir::Heap>,
+    tables: Vec<ir::Table>,
+    sig_refs: Vec<ir::SigRef>,
+    func_refs: Vec<ir::FuncRef>,
+    jump_tables: Vec<ir::JumpTable>,
+}
+
+impl EntityMap {
+    /// Create the caller's copies of the callee's preamble entities and EBBs.
+    ///
+    /// The copied EBBs are inserted in the caller's layout before `before`.
+    fn new(caller: &mut ir::Function, callee: &ir::Function, before: Ebb) -> Self {
+        let stack_slots = callee
+            .stack_slots
+            .values()
+            .map(|data| caller.create_stack_slot(data.clone()))
+            .collect();
+
+        // The VM context is the caller's, and other global values are copied.
+        let mut global_values = Vec::with_capacity(callee.global_values.len());
+        for data in callee.global_values.values() {
+            let gv = match *data {
+                GlobalValueData::VMContext => caller
+                    .global_values
+                    .iter()
+                    .find(|&(_, data)| match *data {
+                        GlobalValueData::VMContext => true,
+                        _ => false,
+                    })
+                    .map(|(gv, _)| gv),
+                _ => None,
+            };
+            global_values.push(gv.unwrap_or_else(|| caller.create_global_value(data.clone())));
+        }
+        for &gv in &global_values[..] {
+            if let GlobalValueData::Load { ref mut base, .. }
+            | GlobalValueData::IAddImm { ref mut base, .. } = caller.global_values[gv]
+            {
+                *base = global_values[base.index()];
+            }
+        }
+
+        let heaps = callee
+            .heaps
+            .values()
+            .map(|data| {
+                let mut data = data.clone();
+                data.base = global_values[data.base.index()];
+                if let HeapStyle::Dynamic { ref mut bound_gv } = data.style {
+                    *bound_gv = global_values[bound_gv.index()];
+                }
+                caller.create_heap(data)
+            })
+            .collect();
+
+        let tables = callee
+            .tables
+            .values()
+            .map(|data| {
+                let mut data = data.clone();
+                data.base_gv = global_values[data.base_gv.index()];
+                data.bound_gv = global_values[data.bound_gv.index()];
+                caller.create_table(data)
+            })
+            .collect::<Vec<_>>();
+
+        let sig_refs: Vec<ir::SigRef> = callee
+            .dfg
+            .signatures
+            .values()
+            .map(|sig| caller.import_signature(sig.clone()))
+            .collect();
+
+        let func_refs = callee
+            .dfg
+            .ext_funcs
+            .values()
+            .map(|data| {
+                caller.import_function(ExtFuncData {
+                    name: data.name.clone(),
+                    signature: sig_refs[data.signature.index()],
+                    colocated: data.colocated,
+                })
+            })
+            .collect();
+
+        let mut ebbs: SecondaryMap<Ebb, PackedOption<Ebb>> = SecondaryMap::new();
+        let mut values = SecondaryMap::new();
+        for callee_ebb in callee.layout.ebbs() {
+            let ebb = caller.dfg.make_ebb();
+            caller.layout.insert_ebb(ebb, before);
+            for &param in callee.dfg.ebb_params(callee_ebb) {
+                let ty = callee.dfg.value_type(param);
+                values[param] = caller.dfg.append_ebb_param(ebb, ty).into();
+            }
+            ebbs[callee_ebb] = ebb.into();
+        }
+
+        let jump_tables = callee
+            .jump_tables
+            .values()
+            .map(|data| {
+                let mut table = JumpTableData::with_capacity(data.len());
+                for &dest in data.iter() {
+                    table.push_entry(ebbs[dest].expect("jump table to an EBB not in the layout"));
+                }
+                caller.create_jump_table(table)
+            })
+            .collect();
+
+        Self {
+            ebbs,
+            values,
+            stack_slots,
+            global_values,
+            heaps,
+            tables,
+            sig_refs,
+            func_refs,
+            jump_tables,
+        }
+    }
+
+    /// Get the caller's EBB for the callee's `ebb`.
+    fn ebb(&self, ebb: Ebb) -> Ebb {
+        self.ebbs[ebb].expect("branch to an EBB not in the layout")
+    }
+
+    /// Replace the callee's entities referenced by `data` with the caller's.
+    fn remap_entities(
+        &self,
+        data: &mut InstructionData,
+        caller: &mut ir::Function,
+        callee: &ir::Function,
+    ) {
+        match *data {
+            InstructionData::Call {
+                ref mut func_ref, ..
+            }
+            | InstructionData::FuncAddr {
+                ref mut func_ref, ..
+            }
+            | InstructionData::Invoke {
+                ref mut func_ref, ..
+            } => *func_ref = self.func_refs[func_ref.index()],
+            InstructionData::CallIndirect {
+                ref mut sig_ref, ..
+            }
+            | InstructionData::InvokeIndirect {
+                ref mut sig_ref, ..
+            } => *sig_ref = self.sig_refs[sig_ref.index()],
+            InstructionData::StackLoad {
+                ref mut stack_slot, ..
+            }
+            | InstructionData::StackStore {
+                ref mut stack_slot, ..
+            } => *stack_slot = self.stack_slots[stack_slot.index()],
+            InstructionData::HeapAddr { ref mut heap, .. } => *heap = self.heaps[heap.index()],
+            InstructionData::TableAddr { ref mut table, .. } => *table = self.tables[table.index()],
+            InstructionData::UnaryGlobalValue {
+                ref mut global_value,
+                ..
+            } => *global_value = self.global_values[global_value.index()],
+            InstructionData::BranchTable {
+                ref mut destination,
+                ref mut table,
+                ..
+            } => {
+                *destination = self.ebb(*destination);
+                *table = self.jump_tables[table.index()];
+            }
+            InstructionData::BranchTableBase { ref mut table, .. }
+            | InstructionData::BranchTableEntry { ref mut table, .. }
+            | InstructionData::IndirectJump { ref mut table, .. } => {
+                *table = self.jump_tables[table.index()]
+            }
+            InstructionData::UnaryConst {
+                ref mut constant_handle,
+                ..
+            } => {
+                let constant = callee.dfg.constants.get(*constant_handle).clone();
+                *constant_handle = caller.dfg.constants.insert(constant);
+            }
+            InstructionData::Shuffle { ref mut mask, .. } => {
+                *mask = caller
+                    .dfg
+                    .immediates
+                    .push(callee.dfg.immediates[*mask].clone());
+            }
+            _ => {}
+        }
+        if let Some(destination) = data.branch_destination_mut() {
+            *destination = self.ebb(*destination);
+        }
+    }
+}
+
+/// Inline `callee` at `call` in `caller`.
+///
+/// Returns the calls in the inlined code.
+fn inline_call(caller: &mut ir::Function, call: Inst, callee: &ir::Function) -> Vec<Inst> {
+    let call_ebb = caller.layout.inst_ebb(call).unwrap();
+    let call_srcloc = caller.srclocs[call];
+
+    // Split the EBB after the call, and make the call results parameters of the new EBB.
+    let cont = caller.dfg.make_ebb();
+    let next = caller
+        .layout
+        .next_inst(call)
+        .expect("a call can't end an EBB");
+    caller.layout.split_ebb(cont, next);
+    let results = caller.dfg.inst_results(call).to_vec();
+    caller.dfg.clear_results(call);
+    for result in results {
+        caller.dfg.attach_ebb_param(cont, result);
+    }
+
+    let mut map = EntityMap::new(caller, callee, cont);
+
+    // Copy the instructions, keeping the callee's values as arguments for now, since they may be
+    // defined further down the layout.
+    let mut copied = Vec::new();
+    for callee_ebb in callee.layout.ebbs() {
+        let ebb = map.ebb(callee_ebb);
+        for callee_inst in callee.layout.ebb_insts(callee_ebb) {
+            let mut data = callee.dfg[callee_inst].clone();
+            if let Some(args) = data.take_value_list() {
+                let args = ValueList::from_slice(
+                    args.as_slice(&callee.dfg.value_lists),
+                    &mut caller.dfg.value_lists,
+                );
+                data.put_value_list(args);
+            }
+            map.remap_entities(&mut data, caller, callee);
+            if let Opcode::Return | Opcode::FallthroughReturn = data.opcode() {
+                data = InstructionData::Jump {
+                    opcode: Opcode::Jump,
+                    args: data.take_value_list().unwrap(),
+                    destination: cont,
+                };
+            }
+
+            let inst = caller.dfg.make_inst(data);
+            caller.layout.append_inst(inst, ebb);
+            for &result in callee.dfg.inst_results(callee_inst) {
+                let ty = callee.dfg.value_type(result);
+                map.values[result] = caller.dfg.append_result(inst, ty).into();
+            }
+            let srcloc = callee.srclocs[callee_inst];
+            caller.srclocs[inst] = if srcloc.is_default() {
+                call_srcloc
+            } else {
+                srcloc
+            };
+            copied.push(inst);
+        }
+    }
+
+    // Now that all the values have a copy, replace the arguments.
+    for &inst in &copied {
+        for arg in caller.dfg.inst_args_mut(inst) {
+            *arg = map.values[callee.dfg.resolve_aliases(*arg)]
+                .expect("use of a value not defined in the layout");
+        }
+    }
+
+    // Jump to the inlined code from the call.
+    let args = caller.dfg.inst_args(call).to_vec();
+    let entry = map.ebb(callee.layout.entry_block().unwrap());
+    caller.dfg.replace(call).jump(entry, &args);
+    debug_assert_eq!(caller.layout.last_inst(call_ebb), Some(call));
+
+    copied
+        .into_iter()
+        .filter(|&inst| caller.dfg[inst].opcode() == Opcode::Call)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Inliner, MAX_DEPTH};
+    use crate::module::FuncId;
+    use cranelift_codegen::cursor::{Cursor, FuncCursor};
+    use cranelift_codegen::ir::{
+        self, types, AbiParam, ExtFuncData, InstBuilder, Opcode, StackSlotData, StackSlotKind,
+    };
+    use cranelift_codegen::isa::CallConv;
+    use cranelift_codegen::{settings, verify_function};
+
+    fn signature() -> ir::Signature {
+        let mut sig = ir::Signature::new(CallConv::SystemV);
+        sig.params.push(AbiParam::new(types::I64));
+        sig.returns.push(AbiParam::new(types::I32));
+        sig
+    }
+
+    /// Create a function `u0:index` calling `u0:callee` twice with its parameter.
+    fn caller(index: u32, callee: u32) -> ir::Function {
+        let mut func =
+            ir::Function::with_name_signature(ir::ExternalName::user(0, index), signature());
+        let sig = func.import_signature(signature());
+        let fn0 = func.import_function(ExtFuncData {
+            name: ir::ExternalName::user(0, callee),
+            signature: sig,
+            colocated: true,
+        });
+        let ebb0 = func.dfg.make_ebb();
+        let v0 = func.dfg.append_ebb_param(ebb0, types::I64);
+        let mut pos = FuncCursor::new(&mut func);
+        pos.insert_ebb(ebb0);
+        let call0 = pos.ins().call(fn0, &[v0]);
+        let v1 = pos.func.dfg.first_result(call0);
+        let call1 = pos.ins().call(fn0, &[v0]);
+        let v2 = pos.func.dfg.first_result(call1);
+        let v3 = pos.ins().iadd(v1, v2);
+        pos.ins().return_(&[v3]);
+        func
+    }
+
+    /// Create a small function with a stack slot and two returns.
+    fn accessor() -> ir::Function {
+        let mut func = ir::Function::with_name_signature(ir::ExternalName::user(0, 1), signature());
+        let ss0 = func.create_stack_slot(StackSlotData::new(StackSlotKind::ExplicitSlot, 4));
+        let ebb0 = func.dfg.make_ebb();
+        let ebb1 = func.dfg.make_ebb();
+        let ebb2 = func.dfg.make_ebb();
+        let v0 = func.dfg.append_ebb_param(ebb0, types::I64);
+        let mut pos = FuncCursor::new(&mut func);
+        pos.insert_ebb(ebb0);
+        let v1 = pos.ins().load(types::I32, ir::MemFlags::new(), v0, 8);
+        pos.ins().stack_store(v1, ss0, 0);
+        let v2 = pos.ins().stack_load(types::I32, ss0, 0);
+        pos.ins().brz(v2, ebb2, &[]);
+        pos.ins().jump(ebb1, &[]);
+        pos.insert_ebb(ebb1);
+        pos.ins().return_(&[v2]);
+        pos.insert_ebb(ebb2);
+        let v3 = pos.ins().iconst(types::I32, 1);
+        pos.ins().return_(&[v3]);
+        func
+    }
+
+    fn count_calls(func: &ir::Function) -> usize {
+        func.layout
+            .ebbs()
+            .flat_map(|ebb| func.layout.ebb_insts(ebb))
+            .filter(|&inst| func.dfg[inst].opcode() == Opcode::Call)
+            .count()
+    }
+
+    fn verify(func: &ir::Function) {
+        let flags = settings::Flags::new(settings::builder());
+        if let Err(errors) = verify_function(func, &flags) {
+            panic!("{}\n{}", func.display(None), errors);
+        }
+    }
+
+    #[test]
+    fn inline_accessor() {
+        let mut inliner = Inliner::new();
+        inliner.add_body(FuncId::from_u32(1), accessor());
+        let mut func = caller(0, 1);
+        assert_eq!(inliner.inline_calls(&mut func, |_| true), 2);
+        verify(&func);
+        assert_eq!(count_calls(&func), 0);
+        assert_eq!(func.stack_slots.iter().count(), 2);
+        assert_eq!(func.layout.ebbs().count(), 1 + 2 * 4);
+    }
+
+    #[test]
+    fn size_limit() {
+        let mut inliner = Inliner::new();
+        inliner.add_body(FuncId::from_u32(1), accessor());
+        inliner.set_max_size(4);
+        let mut func = caller(0, 1);
+        assert_eq!(inliner.inline_calls(&mut func, |_| true), 0);
+        assert_eq!(count_calls(&func), 2);
+    }
+
+    #[test]
+    fn call_conv_mismatch() {
+        let mut inliner = Inliner::new();
+        let mut callee = accessor();
+        callee.signature.call_conv = CallConv::BaldrdashSystemV;
+        inliner.add_body(FuncId::from_u32(1), callee);
+        let mut func = caller(0, 1);
+        assert_eq!(inliner.inline_calls(&mut func, |_| true), 0);
+        assert_eq!(count_calls(&func), 2);
+    }
+
+    #[test]
+    fn rejected_callee() {
+        let mut inliner = Inliner::new();
+        inliner.add_body(FuncId::from_u32(1), accessor());
+        let mut func = caller(0, 1);
+        assert_eq!(
+            inliner.inline_calls(&mut func, |func| func != FuncId::from_u32(1)),
+            0
+        );
+        assert_eq!(count_calls(&func), 2);
+    }
+
+    #[test]
+    fn recursion_depth() {
+        // `u0:1` calls itself, so each inlined copy brings in two more calls.
+        let mut inliner = Inliner::new();
+        inliner.add_body(FuncId::from_u32(1), caller(1, 1));
+        let mut func = caller(0, 1);
+        let mut expected = 0;
+        let mut calls = 2;
+        for _ in 0..MAX_DEPTH {
+            expected += calls;
+            calls *= 2;
+        }
+        assert_eq!(inliner.inline_calls(&mut func, |_| true), expected);
+        verify(&func);
+        assert_eq!(count_calls(&func), calls);
+
+        // A function is never inlined into itself.
+        let mut func = caller(1, 1);
+        assert_eq!(inliner.inline_calls(&mut func, |_| true), 0);
+    }
+}
@@ -34,6 +34,7 @@ use std::collections::{hash_map, HashMap};
 
 mod backend;
 mod data_context;
+mod inline;
 mod module;
 
 pub use crate::backend::{default_libcall_names, Backend};
@@ -7,6 +7,7 @@
 
 use super::HashMap;
 use crate::data_context::DataContext;
+use crate::inline::Inliner;
 use crate::Backend;
 use cranelift_codegen::binemit::{self, CodeInfo};
 use cranelift_codegen::entity::{entity_impl, PrimaryMap};
@@ -183,6 +184,7 @@ pub struct DataDeclaration {
     pub name: String,
     pub linkage: Linkage,
     pub writable: bool,
+    pub tls: bool,
     pub align: Option<u8>,
 }
 
@@ -201,10 +203,20 @@ impl<B> ModuleData<B>
 where
     B: Backend,
 {
-    fn merge(&mut self, linkage: Linkage, writable: bool, align: Option<u8>) {
+    fn merge(
+        &mut self,
+        linkage: Linkage,
+        writable: bool,
+        tls: bool,
+        align: Option<u8>,
+    ) -> Result<(), ModuleError> {
+        if self.decl.tls != tls {
+            return Err(ModuleError::IncompatibleDeclaration(self.decl.name.clone()));
+        }
         self.decl.linkage = Linkage::merge(self.decl.linkage, linkage);
         self.decl.writable = self.decl.writable || writable;
         self.decl.align = self.decl.align.max(align);
+        Ok(())
     }
 }
 
@@ -339,6 +351,7 @@ where
     contents: ModuleContents<B>,
     functions_to_finalize: Vec<FuncId>,
     data_objects_to_finalize: Vec<DataId>,
+    inliner: Inliner,
     backend: B,
 }
 
@@ -348,6 +361,10 @@ where
 {
     /// Create a new `Module`.
     pub fn new(backend_builder: B::Builder) -> Self {
+        let mut backend = B::new(backend_builder);
+        for (libcall, code) in backend.isa().libcall_thunks() {
+            backend.define_libcall_thunk(libcall, &code);
+        }
         Self {
             names: HashMap::new(),
             contents: ModuleContents {
@@ -356,7 +373,8 @@ where
             },
             functions_to_finalize: Vec::new(),
             data_objects_to_finalize: Vec::new(),
-            backend: B::new(backend_builder),
+            inliner: Inliner::new(),
+            backend,
         }
     }
 
@@ -455,6 +473,7 @@ where
         name: &str,
         linkage: Linkage,
         writable: bool,
+        tls: bool,
         align: Option<u8>, // An alignment bigger than 128 is unlikely
     ) -> ModuleResult<DataId> {
         // TODO: Can we avoid allocating names so often?
@@ -463,12 +482,13 @@ where
             Occupied(entry) => match *entry.get() {
                 FuncOrDataId::Data(id) => {
                     let existing = &mut self.contents.data_objects[id];
-                    existing.merge(linkage, writable, align);
+                    existing.merge(linkage, writable, tls, align)?;
                     self.backend.declare_data(
                         id,
                         name,
                         existing.decl.linkage,
                         existing.decl.writable,
+                        existing.decl.tls,
                         existing.decl.align,
                     );
                     Ok(id)
@@ -484,13 +504,14 @@ where
                         name: name.to_owned(),
                         linkage,
                         writable,
+                        tls,
                         align,
                     },
                     compiled: None,
                 });
                 entry.insert(FuncOrDataId::Data(id));
                 self.backend
-                    .declare_data(id, name, linkage, writable, align);
+                    .declare_data(id, name, linkage, writable, tls, align);
                 Ok(id)
             }
         }
@@ -521,6 +542,7 @@ where
             name: ir::ExternalName::user(1, data.as_u32()),
             offset: ir::immediates::Imm64::new(0),
             colocated,
+            tls: decl.tls,
         })
     }
 
@@ -573,6 +595,48 @@ where
         Ok(total_size)
     }
 
+    /// Make the IR body of a function available for inlining into its callers.
+    ///
+    /// This is separate from `define_function`, which compiles the body in place. The body must
+    /// not be legalized yet, and the function must be defined in this module and not be
+    /// preemptible, so the body is the one that will be called.
+    pub fn define_inline_body(&mut self, func: FuncId, body: &ir::Function) -> ModuleResult<()> {
+        let decl = &self.contents.functions[func].decl;
+        if !decl.linkage.is_definable() {
+            return Err(ModuleError::InvalidImportDefinition(decl.name.clone()));
+        }
+        if decl.signature != body.signature {
+            return Err(ModuleError::IncompatibleSignature(
+                decl.name.clone(),
+                decl.signature.clone(),
+                body.signature.clone(),
+            ));
+        }
+        self.inliner.add_body(func, body.clone());
+        Ok(())
+    }
+
+    /// Set the maximum number of instructions in a function body inlined by `inline_calls`.
+    pub fn set_inline_size_limit(&mut self, size: usize) {
+        self.inliner.set_max_size(size);
+    }
+
+    /// Inline the calls in the function of `ctx` to functions with a body given to
+    /// `define_inline_body`, if they are small enough.
+    ///
+    /// This is an explicit step to run before `define_function`. Returns the number of calls
+    /// inlined.
+    pub fn inline_calls(&self, ctx: &mut Context) -> ModuleResult<usize> {
+        let functions = &self.contents.functions;
+        let inlined = self.inliner.inline_calls(&mut ctx.func, |func| {
+            functions[func].decl.linkage.is_final()
+        });
+        if inlined > 0 {
+            ctx.verify_if(self.backend.isa())?;
+        }
+        Ok(inlined)
+    }
+
     /// Define a data object, producing the data contents from the given `DataContext`.
     pub fn define_data(&mut self, data: DataId, data_ctx: &DataContext) -> ModuleResult<()> {
         let compiled = {
@@ -587,6 +651,7 @@ where
                 data,
                 &info.decl.name,
                 info.decl.writable,
+                info.decl.tls,
                 info.decl.align,
                 data_ctx,
                 &ModuleNamespace::<B> {
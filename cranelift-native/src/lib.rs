@@ -71,11 +71,15 @@ fn parse_x86_cpuid(isa_builder: &mut isa::Builder) -> Result<(), &'static str> {
         if info.has_popcnt() {
             isa_builder.enable("has_popcnt").unwrap();
         }
-        if info.has_avx() {
+        // VEX-encoded instructions fault unless the OS saves the extended register state.
+        if info.has_avx() && info.has_oxsave() {
             isa_builder.enable("has_avx").unwrap();
         }
     }
     if let Some(info) = cpuid.get_extended_feature_info() {
+        if info.has_avx2() {
+            isa_builder.enable("has_avx2").unwrap();
+        }
         if info.has_bmi1() {
             isa_builder.enable("has_bmi1").unwrap();
         }
@@ -2,7 +2,7 @@
 
 use crate::traps::{ObjectTrapSink, ObjectTrapSite};
 use cranelift_codegen::binemit::{
-    Addend, CodeOffset, NullStackmapSink, NullTrapSink, Reloc, RelocSink,
+    Addend, CodeOffset, NullCallSiteSink, NullStackmapSink, NullTrapSink, Reloc, RelocSink,
 };
 use cranelift_codegen::entity::SecondaryMap;
 use cranelift_codegen::isa::TargetIsa;
@@ -14,9 +14,34 @@ use cranelift_module::{
 use object::write::{
     Object, Relocation, SectionId, StandardSection, Symbol, SymbolId, SymbolSection,
 };
-use object::{RelocationEncoding, RelocationKind, SymbolFlags, SymbolKind, SymbolScope};
+use object::{
+    RelocationEncoding, RelocationKind, SectionKind, SymbolFlags, SymbolKind, SymbolScope,
+};
 use std::collections::HashMap;
-use target_lexicon::PointerWidth;
+use target_lexicon::{Architecture, BinaryFormat, PointerWidth};
+
+// The `object` crate doesn't export the ELF relocation constants, so define the ones that have no
+// generic `RelocationKind` here.
+const R_ARM_THM_CALL: u32 = 10;
+const R_ARM_CALL: u32 = 28;
+const R_ARM_MOVW_ABS_NC: u32 = 43;
+const R_ARM_MOVT_ABS: u32 = 44;
+const R_ARM_THM_MOVW_ABS_NC: u32 = 47;
+const R_ARM_THM_MOVT_ABS: u32 = 48;
+const R_X86_64_TLSGD: u32 = 19;
+const R_X86_64_GOTTPOFF: u32 = 22;
+const R_AARCH64_ADR_PREL_PG_HI21: u32 = 275;
+const R_AARCH64_ADD_ABS_LO12_NC: u32 = 277;
+const R_AARCH64_CALL26: u32 = 283;
+const R_AARCH64_ADR_GOT_PAGE: u32 = 311;
+const R_AARCH64_LD64_GOT_LO12_NC: u32 = 312;
+
+// Nor does it know about notes, so these are the constants for the GNU property note.
+const SHT_NOTE: u32 = 7;
+const NT_GNU_PROPERTY_TYPE_0: u32 = 5;
+const GNU_PROPERTY_X86_FEATURE_1_AND: u32 = 0xc000_0002;
+const GNU_PROPERTY_X86_FEATURE_1_IBT: u32 = 1;
+const GNU_PROPERTY_SECTION: &[u8] = b".note.gnu.property";
 
 #[derive(Debug)]
 /// Setting to enable collection of traps. Setting this to `Enabled` in
@@ -35,6 +60,7 @@ pub struct ObjectBuilder {
     collect_traps: ObjectTrapCollection,
     libcall_names: Box<dyn Fn(ir::LibCall) -> String>,
     function_alignment: u64,
+    ibt_note: bool,
 }
 
 impl ObjectBuilder {
@@ -60,6 +86,7 @@ impl ObjectBuilder {
             collect_traps,
             libcall_names,
             function_alignment: 1,
+            ibt_note: false,
         })
     }
 
@@ -68,6 +95,16 @@ impl ObjectBuilder {
         self.function_alignment = alignment;
         self
     }
+
+    /// Mark x86 ELF objects as compatible with CET indirect branch tracking, with the IBT
+    /// feature in a `.note.gnu.property` section. Other targets are not affected.
+    ///
+    /// Every function must then have been compiled with the x86 `enable_ibt` setting, and the
+    /// object must be written with [`ObjectProduct::emit`], which gives the note its section type.
+    pub fn ibt_note(&mut self, enable: bool) -> &mut Self {
+        self.ibt_note = enable;
+        self
+    }
 }
 
 /// A `ObjectBackend` implements `Backend` and emits ".o" files using the `object` library.
@@ -83,6 +120,7 @@ pub struct ObjectBackend {
     libcall_names: Box<dyn Fn(ir::LibCall) -> String>,
     collect_traps: ObjectTrapCollection,
     function_alignment: u64,
+    gnu_property: bool,
 }
 
 impl Backend for ObjectBackend {
@@ -103,6 +141,18 @@ impl Backend for ObjectBackend {
         let triple = builder.isa.triple();
         let mut object = Object::new(triple.binary_format, triple.architecture);
         object.add_file_symbol(builder.name.as_bytes().to_vec());
+        let gnu_property = builder.ibt_note
+            && triple.binary_format == BinaryFormat::Elf
+            && match triple.architecture {
+                Architecture::X86_64
+                | Architecture::I386
+                | Architecture::I586
+                | Architecture::I686 => true,
+                _ => false,
+            };
+        if gnu_property {
+            add_ibt_note(&mut object, triple.pointer_width().unwrap());
+        }
         Self {
             isa: builder.isa,
             object,
@@ -113,6 +163,7 @@ impl Backend for ObjectBackend {
             libcall_names: builder.libcall_names,
             collect_traps: builder.collect_traps,
             function_alignment: builder.function_alignment,
+            gnu_property,
         }
     }
 
@@ -148,8 +199,14 @@ impl Backend for ObjectBackend {
         name: &str,
         linkage: Linkage,
         _writable: bool,
+        tls: bool,
         _align: Option<u8>,
     ) {
+        let kind = if tls {
+            SymbolKind::Tls
+        } else {
+            SymbolKind::Data
+        };
         let (scope, weak) = translate_linkage(linkage);
 
         if let Some(data) = self.data_objects[id] {
@@ -161,7 +218,7 @@ impl Backend for ObjectBackend {
                 name: name.as_bytes().to_vec(),
                 value: 0,
                 size: 0,
-                kind: SymbolKind::Data,
+                kind,
                 scope,
                 weak,
                 section: SymbolSection::Undefined,
@@ -183,6 +240,7 @@ impl Backend for ObjectBackend {
         let mut reloc_sink = ObjectRelocSink::default();
         let mut trap_sink = ObjectTrapSink::default();
         let mut stackmap_sink = NullStackmapSink {};
+        let mut call_site_sink = NullCallSiteSink {};
 
         if let ObjectTrapCollection::Enabled = self.collect_traps {
             unsafe {
@@ -192,6 +250,7 @@ impl Backend for ObjectBackend {
                     &mut reloc_sink,
                     &mut trap_sink,
                     &mut stackmap_sink,
+                    &mut call_site_sink,
                 )
             };
         } else {
@@ -203,6 +262,7 @@ impl Backend for ObjectBackend {
                     &mut reloc_sink,
                     &mut trap_sink,
                     &mut stackmap_sink,
+                    &mut call_site_sink,
                 )
             };
         }
@@ -226,6 +286,7 @@ impl Backend for ObjectBackend {
         data_id: DataId,
         _name: &str,
         writable: bool,
+        tls: bool,
         align: Option<u8>,
         data_ctx: &DataContext,
         _namespace: &ModuleNamespace<Self>,
@@ -280,7 +341,9 @@ impl Backend for ObjectBackend {
         }
 
         let symbol = self.data_objects[data_id].unwrap();
-        let section = self.object.section_id(if writable {
+        let section = self.object.section_id(if tls {
+            StandardSection::Tls
+        } else if writable {
             StandardSection::Data
         } else if relocs.is_empty() {
             StandardSection::ReadOnlyData
@@ -316,6 +379,23 @@ impl Backend for ObjectBackend {
         unimplemented!()
     }
 
+    fn define_libcall_thunk(&mut self, libcall: ir::LibCall, code: &[u8]) {
+        let symbol = self.object.add_symbol(Symbol {
+            name: (self.libcall_names)(libcall).into_bytes(),
+            value: 0,
+            size: 0,
+            kind: SymbolKind::Text,
+            scope: SymbolScope::Compilation,
+            weak: false,
+            section: SymbolSection::Undefined,
+            flags: SymbolFlags::None,
+        });
+        let section = self.object.section_id(StandardSection::Text);
+        self.object
+            .add_symbol_data(symbol, section, code, self.function_alignment);
+        self.libcalls.insert(libcall, symbol);
+    }
+
     fn finalize_function(
         &mut self,
         _id: FuncId,
@@ -400,6 +480,7 @@ impl Backend for ObjectBackend {
             functions: self.functions,
             data_objects: self.data_objects,
             traps: self.traps,
+            gnu_property: self.gnu_property,
         }
     }
 }
@@ -448,6 +529,83 @@ impl ObjectBackend {
     }
 }
 
+/// Add a GNU property note with the x86 IBT feature, padded to the word size of the target.
+fn add_ibt_note(object: &mut Object, pointer_width: PointerWidth) {
+    let align = u64::from(pointer_width.bytes());
+    let mut desc = Vec::new();
+    desc.extend_from_slice(&GNU_PROPERTY_X86_FEATURE_1_AND.to_le_bytes());
+    desc.extend_from_slice(&4u32.to_le_bytes());
+    desc.extend_from_slice(&GNU_PROPERTY_X86_FEATURE_1_IBT.to_le_bytes());
+    desc.resize((desc.len() + align as usize - 1) & !(align as usize - 1), 0);
+
+    let mut note = Vec::new();
+    note.extend_from_slice(&4u32.to_le_bytes());
+    note.extend_from_slice(&(desc.len() as u32).to_le_bytes());
+    note.extend_from_slice(&NT_GNU_PROPERTY_TYPE_0.to_le_bytes());
+    note.extend_from_slice(b"GNU\0");
+    note.extend_from_slice(&desc);
+
+    let section = object.add_section(
+        Vec::new(),
+        GNU_PROPERTY_SECTION.to_vec(),
+        SectionKind::ReadOnlyData,
+    );
+    object.append_section_data(section, &note, align);
+}
+
+/// The `object` crate writes every section with data as `SHT_PROGBITS`, but linkers only read the
+/// properties of a `.note.gnu.property` section of type `SHT_NOTE`, so patch its section header.
+fn set_gnu_property_section_type(elf: &mut [u8]) -> Result<(), String> {
+    let is_64 = match elf.get(4) {
+        Some(1) => false,
+        Some(2) => true,
+        _ => return Err("not an ELF object".to_string()),
+    };
+    let read = |elf: &[u8], offset: usize, size: usize| -> Result<usize, String> {
+        let bytes = elf
+            .get(offset..offset + size)
+            .ok_or_else(|| "truncated ELF object".to_string())?;
+        Ok(bytes
+            .iter()
+            .rev()
+            .fold(0, |value, &byte| (value << 8) | usize::from(byte)))
+    };
+    let (shoff, shentsize, shnum, shstrndx) = if is_64 {
+        (
+            read(elf, 0x28, 8)?,
+            read(elf, 0x3a, 2)?,
+            read(elf, 0x3c, 2)?,
+            read(elf, 0x3e, 2)?,
+        )
+    } else {
+        (
+            read(elf, 0x20, 4)?,
+            read(elf, 0x2e, 2)?,
+            read(elf, 0x30, 2)?,
+            read(elf, 0x32, 2)?,
+        )
+    };
+    let sh_offset = |index: usize| -> Result<usize, String> {
+        let header = shoff + index * shentsize;
+        if is_64 {
+            read(elf, header + 0x18, 8)
+        } else {
+            read(elf, header + 0x10, 4)
+        }
+    };
+    let shstrtab = sh_offset(shstrndx)?;
+    for index in 1..shnum {
+        let header = shoff + index * shentsize;
+        let name = shstrtab + read(elf, header, 4)?;
+        let end = name + GNU_PROPERTY_SECTION.len();
+        if elf.get(name..end) == Some(GNU_PROPERTY_SECTION) && elf.get(end) == Some(&0) {
+            elf[header + 4..header + 8].copy_from_slice(&SHT_NOTE.to_le_bytes());
+            return Ok(());
+        }
+    }
+    Err("missing .note.gnu.property section".to_string())
+}
+
 fn translate_linkage(linkage: Linkage) -> (SymbolScope, bool) {
     let scope = match linkage {
         Linkage::Import => SymbolScope::Unknown,
@@ -487,6 +645,8 @@ pub struct ObjectProduct {
     pub data_objects: SecondaryMap<DataId, Option<SymbolId>>,
     /// Trap sites for defined functions.
     pub traps: SecondaryMap<FuncId, Vec<ObjectTrapSite>>,
+    /// Whether the object has a GNU property note, whose section type is set by `emit`.
+    gnu_property: bool,
 }
 
 impl ObjectProduct {
@@ -505,7 +665,11 @@ impl ObjectProduct {
     /// Write the object bytes in memory.
     #[inline]
     pub fn emit(self) -> Result<Vec<u8>, String> {
-        self.object.write()
+        let mut bytes = self.object.write()?;
+        if self.gnu_property {
+            set_gnu_property_section_type(&mut bytes)?;
+        }
+        Ok(bytes)
     }
 }
 
@@ -549,9 +713,96 @@ impl RelocSink for ObjectRelocSink {
                 32,
             ),
             Reloc::X86GOTPCRel4 => (RelocationKind::GotRelative, RelocationEncoding::Generic, 32),
+            Reloc::X86GOTPC4 => (
+                RelocationKind::GotBaseRelative,
+                RelocationEncoding::Generic,
+                32,
+            ),
+            Reloc::X86GOT4 => (RelocationKind::Got, RelocationEncoding::Generic, 32),
+            Reloc::X86GOTOff4 => (
+                RelocationKind::GotBaseOffset,
+                RelocationEncoding::Generic,
+                32,
+            ),
+            Reloc::ElfX86_64TlsGd => (
+                RelocationKind::Elf(R_X86_64_TLSGD),
+                RelocationEncoding::Generic,
+                32,
+            ),
+            Reloc::ElfX86_64GotTpOff => (
+                RelocationKind::Elf(R_X86_64_GOTTPOFF),
+                RelocationEncoding::Generic,
+                32,
+            ),
+            Reloc::Arm32Call => (
+                RelocationKind::Elf(R_ARM_CALL),
+                RelocationEncoding::Generic,
+                32,
+            ),
+            Reloc::Arm32MovwAbsNc => (
+                RelocationKind::Elf(R_ARM_MOVW_ABS_NC),
+                RelocationEncoding::Generic,
+                32,
+            ),
+            Reloc::Arm32MovtAbs => (
+                RelocationKind::Elf(R_ARM_MOVT_ABS),
+                RelocationEncoding::Generic,
+                32,
+            ),
+            Reloc::Arm32ThmCall => (
+                RelocationKind::Elf(R_ARM_THM_CALL),
+                RelocationEncoding::Generic,
+                32,
+            ),
+            Reloc::Arm32ThmMovwAbsNc => (
+                RelocationKind::Elf(R_ARM_THM_MOVW_ABS_NC),
+                RelocationEncoding::Generic,
+                32,
+            ),
+            Reloc::Arm32ThmMovtAbs => (
+                RelocationKind::Elf(R_ARM_THM_MOVT_ABS),
+                RelocationEncoding::Generic,
+                32,
+            ),
+            Reloc::Arm64Call => (
+                RelocationKind::Elf(R_AARCH64_CALL26),
+                RelocationEncoding::Generic,
+                32,
+            ),
+            Reloc::Arm64AdrPrelPgHi21 => (
+                RelocationKind::Elf(R_AARCH64_ADR_PREL_PG_HI21),
+                RelocationEncoding::Generic,
+                32,
+            ),
+            Reloc::Arm64AddAbsLo12Nc => (
+                RelocationKind::Elf(R_AARCH64_ADD_ABS_LO12_NC),
+                RelocationEncoding::Generic,
+                32,
+            ),
+            Reloc::Arm64AdrGotPage21 => (
+                RelocationKind::Elf(R_AARCH64_ADR_GOT_PAGE),
+                RelocationEncoding::Generic,
+                32,
+            ),
+            Reloc::Arm64Ld64GotLo12Nc => (
+                RelocationKind::Elf(R_AARCH64_LD64_GOT_LO12_NC),
+                RelocationEncoding::Generic,
+                32,
+            ),
             // FIXME
             _ => unimplemented!(),
         };
+        // ARM relocations keep their addend in the relocated instruction, where the emitted code
+        // already put it.
+        let addend = match reloc {
+            Reloc::Arm32Call
+            | Reloc::Arm32MovwAbsNc
+            | Reloc::Arm32MovtAbs
+            | Reloc::Arm32ThmCall
+            | Reloc::Arm32ThmMovwAbsNc
+            | Reloc::Arm32ThmMovtAbs => 0,
+            _ => addend,
+        };
         self.relocs.push(RelocRecord {
             offset,
             name: name.clone(),
@@ -32,10 +32,41 @@ use cranelift_codegen::{isa::TargetIsa, settings::FlagsOrIsa, CodegenResult, Con
 pub fn optimize(ctx: &mut Context, isa: &dyn TargetIsa) -> CodegenResult<()> {
     ctx.verify_if(isa)?;
     fold_constants(ctx, isa)?;
+    unroll_loops(ctx, isa)?;
+    propagate_constants(ctx, isa)?;
+    eliminate_redundant_loads(ctx, isa)?;
 
     Ok(())
 }
 
+/// Unroll and peel counted loops
+pub fn unroll_loops<'a, FOI>(ctx: &mut Context, fisa: FOI) -> CodegenResult<()>
+where
+    FOI: Into<FlagsOrIsa<'a>>,
+{
+    ctx.flowgraph();
+    ctx.compute_loop_analysis();
+    ctx.unroll(fisa)
+}
+
+/// Propagate constants through EBB parameters and fold branches on them
+pub fn propagate_constants<'a, FOI>(ctx: &mut Context, fisa: FOI) -> CodegenResult<()>
+where
+    FOI: Into<FlagsOrIsa<'a>>,
+{
+    ctx.flowgraph();
+    ctx.sccp(fisa)
+}
+
+/// Eliminate redundant loads and forward stored values to loads
+pub fn eliminate_redundant_loads<'a, FOI>(ctx: &mut Context, fisa: FOI) -> CodegenResult<()>
+where
+    FOI: Into<FlagsOrIsa<'a>>,
+{
+    ctx.flowgraph();
+    ctx.rle(fisa)
+}
+
 /// Fold constants
 pub fn fold_constants<'a, FOI>(ctx: &mut Context, fisa: FOI) -> CodegenResult<()>
 where
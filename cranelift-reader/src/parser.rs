@@ -14,10 +14,10 @@ use cranelift_codegen::ir::instructions::{InstructionData, InstructionFormat, Va
 use cranelift_codegen::ir::types::INVALID;
 use cranelift_codegen::ir::types::*;
 use cranelift_codegen::ir::{
-    AbiParam, ArgumentExtension, ArgumentLoc, ConstantData, Ebb, ExtFuncData, ExternalName,
-    FuncRef, Function, GlobalValue, GlobalValueData, Heap, HeapData, HeapStyle, JumpTable,
-    JumpTableData, MemFlags, Opcode, SigRef, Signature, StackSlot, StackSlotData, StackSlotKind,
-    Table, TableData, Type, Value, ValueLoc,
+    AbiParam, ArgumentExtension, ArgumentLoc, ArgumentPurpose, ConstantData, Ebb, ExtFuncData,
+    ExternalName, FuncRef, Function, GlobalValue, GlobalValueData, Heap, HeapData, HeapStyle,
+    JumpTable, JumpTableData, MemFlags, Opcode, SigRef, Signature, StackSlot, StackSlotData,
+    StackSlotKind, StructLayout, Table, TableData, Type, Value, ValueList, ValueLoc,
 };
 use cranelift_codegen::isa::{self, CallConv, Encoding, RegUnit, TargetIsa};
 use cranelift_codegen::packed_option::ReservedValue;
@@ -201,6 +201,7 @@ impl<'a> Context<'a> {
                 name: ExternalName::testcase(""),
                 offset: Imm64::new(0),
                 colocated: false,
+                tls: false,
             });
         }
         self.function.global_values[gv] = data;
@@ -1176,9 +1177,23 @@ impl<'a> Parser<'a> {
         let mut sig = Signature::new(self.default_calling_convention);
 
         self.match_token(Token::LPar, "expected function signature: ( args... )")?;
-        // signature ::=  "(" * [abi-param-list] ")" ["->" retlist] [callconv]
+        // signature ::=  "(" * [param-list] ")" ["->" retlist] [callconv]
         if self.token() != Some(Token::RPar) {
-            sig.params = self.parse_abi_param_list(unique_isa)?;
+            // param-list ::= * param-or-ellipsis { "," param-or-ellipsis }
+            // param-or-ellipsis ::= abi-param | "..."
+            loop {
+                if self.optional(Token::Ellipsis) {
+                    if sig.variadic.is_some() {
+                        return err!(self.loc, "multiple '...' in function arguments");
+                    }
+                    sig.variadic = Some(sig.params.len());
+                } else {
+                    sig.params.push(self.parse_abi_param(unique_isa)?);
+                }
+                if !self.optional(Token::Comma) {
+                    break;
+                }
+            }
         }
         self.match_token(Token::RPar, "expected ')' after function arguments")?;
         if self.optional(Token::Arrow) {
@@ -1231,6 +1246,19 @@ impl<'a> Parser<'a> {
             match s {
                 "uext" => arg.extension = ArgumentExtension::Uext,
                 "sext" => arg.extension = ArgumentExtension::Sext,
+                "sarg" => {
+                    self.consume();
+                    arg.purpose = ArgumentPurpose::StructArgument(self.parse_struct_layout()?);
+                    continue;
+                }
+                "sarg_part" => {
+                    self.consume();
+                    self.match_token(Token::LPar, "expected '(' before struct part offset")?;
+                    let offset = self.match_uimm32("expected struct part byte offset")?;
+                    self.match_token(Token::RPar, "expected ')' after struct part offset")?;
+                    arg.purpose = ArgumentPurpose::StructArgumentPart(offset.into());
+                    continue;
+                }
                 _ => {
                     if let Ok(purpose) = s.parse() {
                         arg.purpose = purpose;
@@ -1248,6 +1276,27 @@ impl<'a> Parser<'a> {
         Ok(arg)
     }
 
+    // Parse the layout of a struct argument.
+    fn parse_struct_layout(&mut self) -> ParseResult<StructLayout> {
+        // struct-layout ::= * "(" uimm32 "," uimm32 { "," struct-class } ")"
+        self.match_token(Token::LPar, "expected '(' before struct layout")?;
+        let size = self.match_uimm32("expected struct size in bytes")?;
+        self.match_token(Token::Comma, "expected ',' after struct size")?;
+        let align = self.match_uimm32("expected struct alignment in bytes")?;
+        let mut layout = StructLayout::new(size.into(), align.into());
+
+        // struct-layout ::= "(" uimm32 "," uimm32 * { "," struct-class } ")"
+        for i in 0..layout.num_classes() {
+            self.match_token(Token::Comma, "expected ',' before struct class")?;
+            layout.classes[i] = match self.match_any_identifier("expected struct class")?.parse() {
+                Ok(class) => class,
+                Err(()) => return err!(self.loc, "expected struct class: int, float, or mem"),
+            };
+        }
+        self.match_token(Token::RPar, "expected ')' after struct layout")?;
+        Ok(layout)
+    }
+
     // Parse an argument location specifier; either a register or a byte offset into the stack.
     fn parse_argument_location(
         &mut self,
@@ -1391,7 +1440,7 @@ impl<'a> Parser<'a> {
     // global-val-desc ::= "vmctx"
     //                   | "load" "." type "notrap" "aligned" GlobalValue(base) [offset]
     //                   | "iadd_imm" "(" GlobalValue(base) ")" imm64
-    //                   | "symbol" ["colocated"] name + imm64
+    //                   | "symbol" ["colocated"] ["tls"] name + imm64
     //
     fn parse_global_value_decl(&mut self) -> ParseResult<(GlobalValue, GlobalValueData)> {
         let gv = self.match_gv("expected global value number: gv«n»")?;
@@ -1440,12 +1489,14 @@ impl<'a> Parser<'a> {
             }
             "symbol" => {
                 let colocated = self.optional(Token::Identifier("colocated"));
+                let tls = self.optional(Token::Identifier("tls"));
                 let name = self.parse_external_name()?;
                 let offset = self.optional_offset_imm64()?;
                 GlobalValueData::Symbol {
                     name,
                     offset,
                     colocated,
+                    tls,
                 }
             }
             other => return err!(self.loc, "Unknown global value kind '{}'", other),
@@ -2579,6 +2630,38 @@ impl<'a> Parser<'a> {
                     args: args.into_value_list(&[callee], &mut ctx.function.dfg.value_lists),
                 }
             }
+            InstructionFormat::Invoke => {
+                let func_ref = self.match_fn("expected function reference")?;
+                ctx.check_fn(func_ref, self.loc)?;
+                self.match_token(Token::LPar, "expected '(' before arguments")?;
+                let args = self.parse_value_list()?;
+                self.match_token(Token::RPar, "expected ')' after arguments")?;
+                self.match_token(Token::Comma, "expected ',' between operands")?;
+                let ebb_num = self.match_ebb("expected landing pad EBB")?;
+                InstructionData::Invoke {
+                    opcode,
+                    func_ref,
+                    destination: ebb_num,
+                    args: args.into_value_list(&[], &mut ctx.function.dfg.value_lists),
+                }
+            }
+            InstructionFormat::InvokeIndirect => {
+                let sig_ref = self.match_sig("expected signature reference")?;
+                ctx.check_sig(sig_ref, self.loc)?;
+                self.match_token(Token::Comma, "expected ',' between operands")?;
+                let callee = self.match_value("expected SSA value callee operand")?;
+                self.match_token(Token::LPar, "expected '(' before arguments")?;
+                let args = self.parse_value_list()?;
+                self.match_token(Token::RPar, "expected ')' after arguments")?;
+                self.match_token(Token::Comma, "expected ',' between operands")?;
+                let ebb_num = self.match_ebb("expected landing pad EBB")?;
+                InstructionData::InvokeIndirect {
+                    opcode,
+                    sig_ref,
+                    destination: ebb_num,
+                    args: args.into_value_list(&[callee], &mut ctx.function.dfg.value_lists),
+                }
+            }
             InstructionFormat::FuncAddr => {
                 let func_ref = self.match_fn("expected function reference")?;
                 ctx.check_fn(func_ref, self.loc)?;
@@ -2738,6 +2821,71 @@ impl<'a> Parser<'a> {
                     dst,
                 }
             }
+            InstructionFormat::AtomicLoad => {
+                let ordering = self.match_enum("expected atomic memory ordering")?;
+                let flags = self.optional_memflags();
+                let addr = self.match_value("expected SSA value address")?;
+                let offset = self.optional_offset32()?;
+                InstructionData::AtomicLoad {
+                    opcode,
+                    ordering,
+                    flags,
+                    arg: addr,
+                    offset,
+                }
+            }
+            InstructionFormat::AtomicStore => {
+                let ordering = self.match_enum("expected atomic memory ordering")?;
+                let flags = self.optional_memflags();
+                let arg = self.match_value("expected SSA value operand")?;
+                self.match_token(Token::Comma, "expected ',' between operands")?;
+                let addr = self.match_value("expected SSA value address")?;
+                let offset = self.optional_offset32()?;
+                InstructionData::AtomicStore {
+                    opcode,
+                    ordering,
+                    flags,
+                    args: ValueList::from_slice(&[arg, addr], &mut ctx.function.dfg.value_lists),
+                    offset,
+                }
+            }
+            InstructionFormat::AtomicRmw => {
+                let ordering = self.match_enum("expected atomic memory ordering")?;
+                let flags = self.optional_memflags();
+                let op = self.match_enum("expected atomic rmw operation")?;
+                let arg = self.match_value("expected SSA value operand")?;
+                self.match_token(Token::Comma, "expected ',' between operands")?;
+                let addr = self.match_value("expected SSA value address")?;
+                InstructionData::AtomicRmw {
+                    opcode,
+                    ordering,
+                    flags,
+                    op,
+                    args: [arg, addr],
+                }
+            }
+            InstructionFormat::AtomicCas => {
+                let ordering = self.match_enum("expected atomic memory ordering")?;
+                let flags = self.optional_memflags();
+                let expected = self.match_value("expected SSA value operand")?;
+                self.match_token(Token::Comma, "expected ',' between operands")?;
+                let replacement = self.match_value("expected SSA value operand")?;
+                self.match_token(Token::Comma, "expected ',' between operands")?;
+                let addr = self.match_value("expected SSA value address")?;
+                InstructionData::AtomicCas {
+                    opcode,
+                    ordering,
+                    flags,
+                    args: ValueList::from_slice(
+                        &[expected, replacement, addr],
+                        &mut ctx.function.dfg.value_lists,
+                    ),
+                }
+            }
+            InstructionFormat::Fence => {
+                let ordering = self.match_enum("expected atomic memory ordering")?;
+                InstructionData::Fence { opcode, ordering }
+            }
             InstructionFormat::Trap => {
                 let code = self.match_enum("expected trap code")?;
                 InstructionData::Trap { opcode, code }
@@ -2806,6 +2954,28 @@ mod tests {
         assert!(!is_warning);
     }
 
+    #[test]
+    fn struct_argument_type() {
+        let arg = Parser::new("i64 sarg(12, 4, float, int)")
+            .parse_abi_param(None)
+            .unwrap();
+        assert_eq!(arg.to_string(), "i64 sarg(12, 4, float, int)");
+        let arg = Parser::new("i64 sarg_part(8)")
+            .parse_abi_param(None)
+            .unwrap();
+        assert_eq!(arg.purpose, ArgumentPurpose::StructArgumentPart(8));
+
+        // Only structs of up to 16 bytes are classified.
+        let err = Parser::new("i64 sarg(24, 8, int)")
+            .parse_abi_param(None)
+            .unwrap_err();
+        assert_eq!(err.message, "expected ')' after struct layout");
+        let err = Parser::new("i64 sarg(8, 8, vec)")
+            .parse_abi_param(None)
+            .unwrap_err();
+        assert_eq!(err.message, "expected struct class: int, float, or mem");
+    }
+
     #[test]
     fn aliases() {
         let (func, details) = Parser::new(
@@ -2885,6 +3055,28 @@ mod tests {
         );
     }
 
+    #[test]
+    fn variadic_signature() {
+        let sig = Parser::new("(i64, ..., f64, i32) -> i32 system_v")
+            .parse_signature(None)
+            .unwrap();
+        assert_eq!(sig.params.len(), 3);
+        assert_eq!(sig.variadic, Some(1));
+        assert_eq!(sig.to_string(), "(i64, ..., f64, i32) -> i32 system_v");
+
+        let sig = Parser::new("(...)").parse_signature(None).unwrap();
+        assert_eq!(sig.variadic, Some(0));
+        assert_eq!(sig.to_string(), "(...) fast");
+
+        assert_eq!(
+            Parser::new("(i64, ..., ...)")
+                .parse_signature(None)
+                .unwrap_err()
+                .to_string(),
+            "1: multiple '...' in function arguments"
+        );
+    }
+
     #[test]
     fn stack_slot_decl() {
         let (func, _) = Parser::new(
@@ -153,6 +153,18 @@ pub enum SerInstData {
         args: Vec<String>,
         sig_ref: String,
     },
+    Invoke {
+        opcode: String,
+        args: Vec<String>,
+        func_ref: String,
+        destination: String,
+    },
+    InvokeIndirect {
+        opcode: String,
+        args: Vec<String>,
+        sig_ref: String,
+        destination: String,
+    },
     FuncAddr {
         opcode: String,
         func_ref: String,
@@ -181,6 +193,37 @@ pub enum SerInstData {
         flags: String,
         offset: String,
     },
+    AtomicLoad {
+        opcode: String,
+        arg: String,
+        ordering: String,
+        flags: String,
+        offset: String,
+    },
+    AtomicStore {
+        opcode: String,
+        args: [String; 2],
+        ordering: String,
+        flags: String,
+        offset: String,
+    },
+    AtomicRmw {
+        opcode: String,
+        args: [String; 2],
+        ordering: String,
+        flags: String,
+        op: String,
+    },
+    AtomicCas {
+        opcode: String,
+        args: [String; 3],
+        ordering: String,
+        flags: String,
+    },
+    Fence {
+        opcode: String,
+        ordering: String,
+    },
     StackLoad {
         opcode: String,
         stack_slot: String,
@@ -559,6 +602,42 @@ pub fn get_inst_data(inst_index: Inst, func: &Function) -> SerInstData {
                 sig_ref: sig_ref.to_string(),
             }
         }
+        InstructionData::Invoke {
+            opcode,
+            ref args,
+            func_ref,
+            destination,
+        } => {
+            let mut hold_args = Vec::new();
+            let args_iter = args.as_slice(&func.dfg.value_lists);
+            for arg in args_iter {
+                hold_args.push(arg.to_string());
+            }
+            SerInstData::Invoke {
+                opcode: opcode.to_string(),
+                args: hold_args,
+                func_ref: func_ref.to_string(),
+                destination: destination.to_string(),
+            }
+        }
+        InstructionData::InvokeIndirect {
+            opcode,
+            ref args,
+            sig_ref,
+            destination,
+        } => {
+            let mut hold_args = Vec::new();
+            let args_iter = args.as_slice(&func.dfg.value_lists);
+            for arg in args_iter {
+                hold_args.push(arg.to_string());
+            }
+            SerInstData::InvokeIndirect {
+                opcode: opcode.to_string(),
+                args: hold_args,
+                sig_ref: sig_ref.to_string(),
+                destination: destination.to_string(),
+            }
+        }
         InstructionData::FuncAddr { opcode, func_ref } => SerInstData::FuncAddr {
             opcode: opcode.to_string(),
             func_ref: func_ref.to_string(),
@@ -624,6 +703,70 @@ pub fn get_inst_data(inst_index: Inst, func: &Function) -> SerInstData {
                 offset: offset.to_string(),
             }
         }
+        InstructionData::AtomicLoad {
+            opcode,
+            arg,
+            ordering,
+            flags,
+            offset,
+        } => SerInstData::AtomicLoad {
+            opcode: opcode.to_string(),
+            arg: arg.to_string(),
+            ordering: ordering.to_string(),
+            flags: flags.to_string(),
+            offset: offset.to_string(),
+        },
+        InstructionData::AtomicStore {
+            opcode,
+            ref args,
+            ordering,
+            flags,
+            offset,
+        } => {
+            let args = args.as_slice(&func.dfg.value_lists);
+            SerInstData::AtomicStore {
+                opcode: opcode.to_string(),
+                args: [args[0].to_string(), args[1].to_string()],
+                ordering: ordering.to_string(),
+                flags: flags.to_string(),
+                offset: offset.to_string(),
+            }
+        }
+        InstructionData::AtomicRmw {
+            opcode,
+            args,
+            ordering,
+            flags,
+            op,
+        } => SerInstData::AtomicRmw {
+            opcode: opcode.to_string(),
+            args: [args[0].to_string(), args[1].to_string()],
+            ordering: ordering.to_string(),
+            flags: flags.to_string(),
+            op: op.to_string(),
+        },
+        InstructionData::AtomicCas {
+            opcode,
+            ref args,
+            ordering,
+            flags,
+        } => {
+            let args = args.as_slice(&func.dfg.value_lists);
+            SerInstData::AtomicCas {
+                opcode: opcode.to_string(),
+                args: [
+                    args[0].to_string(),
+                    args[1].to_string(),
+                    args[2].to_string(),
+                ],
+                ordering: ordering.to_string(),
+                flags: flags.to_string(),
+            }
+        }
+        InstructionData::Fence { opcode, ordering } => SerInstData::Fence {
+            opcode: opcode.to_string(),
+            ordering: ordering.to_string(),
+        },
         InstructionData::StackLoad {
             opcode,
             stack_slot,
@@ -2,13 +2,13 @@
 
 use crate::memory::Memory;
 use cranelift_codegen::binemit::{
-    Addend, CodeOffset, NullTrapSink, Reloc, RelocSink, Stackmap, StackmapSink,
+    Addend, CodeOffset, NullCallSiteSink, NullTrapSink, Reloc, RelocSink, Stackmap, StackmapSink,
 };
 use cranelift_codegen::isa::TargetIsa;
 use cranelift_codegen::{self, ir, settings};
 use cranelift_module::{
-    Backend, DataContext, DataDescription, DataId, FuncId, Init, Linkage, ModuleNamespace,
-    ModuleResult,
+    Backend, DataContext, DataDescription, DataId, FuncId, Init, Linkage, ModuleError,
+    ModuleNamespace, ModuleResult,
 };
 use cranelift_native;
 #[cfg(not(windows))]
@@ -191,6 +191,20 @@ impl SimpleJITBackend {
             _ => panic!("invalid ExternalName {}", name),
         }
     }
+
+    /// Get the Thumb bit to set in the address of `name`, which is only needed for the functions
+    /// compiled here. Symbols that are looked up already have it.
+    fn thumb_bit(&self, namespace: &ModuleNamespace<Self>, name: &ir::ExternalName) -> u32 {
+        match *name {
+            ir::ExternalName::User { .. }
+                if namespace.is_function(name)
+                    && namespace.get_function_definition(name).0.is_some() =>
+            {
+                1
+            }
+            _ => 0,
+        }
+    }
 }
 
 impl<'simple_jit_backend> Backend for SimpleJITBackend {
@@ -247,6 +261,7 @@ impl<'simple_jit_backend> Backend for SimpleJITBackend {
         _name: &str,
         _linkage: Linkage,
         _writable: bool,
+        _tls: bool,
         _align: Option<u8>,
     ) {
         // Nothing to do.
@@ -282,6 +297,7 @@ impl<'simple_jit_backend> Backend for SimpleJITBackend {
         // that traps.
         let mut trap_sink = NullTrapSink {};
         let mut stackmap_sink = SimpleJITStackmapSink::new();
+        let mut call_site_sink = NullCallSiteSink {};
         unsafe {
             ctx.emit_to_memory(
                 &*self.isa,
@@ -289,6 +305,7 @@ impl<'simple_jit_backend> Backend for SimpleJITBackend {
                 &mut reloc_sink,
                 &mut trap_sink,
                 &mut stackmap_sink,
+                &mut call_site_sink,
             )
         };
 
@@ -304,10 +321,17 @@ impl<'simple_jit_backend> Backend for SimpleJITBackend {
         _id: DataId,
         _name: &str,
         writable: bool,
+        tls: bool,
         align: Option<u8>,
         data: &DataContext,
         _namespace: &ModuleNamespace<Self>,
     ) -> ModuleResult<Self::CompiledData> {
+        if tls {
+            return Err(ModuleError::Backend(
+                "SimpleJIT doesn't yet support TLS data".to_string(),
+            ));
+        }
+
         let &DataDescription {
             ref init,
             ref function_decls,
@@ -391,6 +415,16 @@ impl<'simple_jit_backend> Backend for SimpleJITBackend {
         unimplemented!();
     }
 
+    fn define_libcall_thunk(&mut self, libcall: ir::LibCall, code: &[u8]) {
+        let ptr = self
+            .memory
+            .code
+            .allocate(code.len(), EXECUTABLE_DATA_ALIGNMENT)
+            .expect("TODO: handle OOM etc.");
+        unsafe { ptr::copy_nonoverlapping(code.as_ptr(), ptr, code.len()) };
+        self.symbols.insert((self.libcall_names)(libcall), ptr);
+    }
+
     fn finalize_function(
         &mut self,
         _id: FuncId,
@@ -434,7 +468,75 @@ impl<'simple_jit_backend> Backend for SimpleJITBackend {
                         write_unaligned(at as *mut i32, pcrel)
                     };
                 }
-                Reloc::X86GOTPCRel4 | Reloc::X86CallPLTRel4 => panic!("unexpected PIC relocation"),
+                Reloc::X86GOTPCRel4
+                | Reloc::X86CallPLTRel4
+                | Reloc::X86GOTPC4
+                | Reloc::X86GOT4
+                | Reloc::X86GOTOff4 => panic!("unexpected PIC relocation"),
+                Reloc::Arm64Call => {
+                    // TODO: Handle overflow.
+                    let pcrel = ((what as isize) - (at as isize)) >> 2;
+                    patch_arm64(at, 0x03ff_ffff, pcrel as u32);
+                }
+                Reloc::Arm64AdrPrelPgHi21 => {
+                    // TODO: Handle overflow.
+                    let pages = ((what as isize) >> 12) - ((at as isize) >> 12);
+                    let pages = pages as u32;
+                    patch_arm64(
+                        at,
+                        0x60ff_ffe0,
+                        (pages & 3) << 29 | (pages >> 2 & 0x7_ffff) << 5,
+                    );
+                }
+                Reloc::Arm64AddAbsLo12Nc => {
+                    patch_arm64(at, 0x003f_fc00, (what as u32 & 0xfff) << 10);
+                }
+                Reloc::Arm64AdrGotPage21 | Reloc::Arm64Ld64GotLo12Nc => {
+                    panic!("unexpected PIC relocation")
+                }
+                Reloc::Arm32Call => {
+                    // TODO: Handle overflow.
+                    let pcrel = ((what as isize) - (at as isize)) >> 2;
+                    patch_arm64(at, 0x00ff_ffff, pcrel as u32);
+                }
+                Reloc::Arm32MovwAbsNc | Reloc::Arm32MovtAbs => {
+                    let imm = if reloc == Reloc::Arm32MovtAbs {
+                        what as u32 >> 16
+                    } else {
+                        what as u32 & 0xffff
+                    };
+                    patch_arm64(at, 0x000f_0fff, (imm >> 12) << 16 | imm & 0xfff);
+                }
+                Reloc::Arm32ThmCall => {
+                    // TODO: Handle overflow.
+                    let imm = (((what as isize) - (at as isize)) >> 1) as u32;
+                    let s = imm >> 23 & 1;
+                    let j1 = !(imm >> 22 ^ s) & 1;
+                    let j2 = !(imm >> 21 ^ s) & 1;
+                    patch_thumb2(
+                        at,
+                        0x07ff_2fff,
+                        s << 26 | (imm >> 11 & 0x3ff) << 16 | j1 << 13 | j2 << 11 | imm & 0x7ff,
+                    );
+                }
+                Reloc::Arm32ThmMovwAbsNc | Reloc::Arm32ThmMovtAbs => {
+                    // The functions compiled here are Thumb code, so calls through their address
+                    // need the Thumb bit set.
+                    let what = what as u32 | self.thumb_bit(namespace, name);
+                    let imm = if reloc == Reloc::Arm32ThmMovtAbs {
+                        what >> 16
+                    } else {
+                        what & 0xffff
+                    };
+                    patch_thumb2(
+                        at,
+                        0x040f_70ff,
+                        (imm >> 12) << 16
+                            | (imm >> 11 & 1) << 26
+                            | (imm >> 8 & 7) << 12
+                            | imm & 0xff,
+                    );
+                }
                 _ => unimplemented!(),
             }
         }
@@ -512,6 +614,26 @@ impl<'simple_jit_backend> Backend for SimpleJITBackend {
     }
 }
 
+/// Replace the bits in `mask` of the AArch64 or A32 instruction at `at` with `bits`.
+fn patch_arm64(at: *mut u8, mask: u32, bits: u32) {
+    #[cfg_attr(feature = "cargo-clippy", allow(clippy::cast_ptr_alignment))]
+    unsafe {
+        let inst = ptr::read_unaligned(at as *const u32);
+        ptr::write_unaligned(at as *mut u32, inst & !mask | bits & mask)
+    };
+}
+
+/// Replace the bits in `mask` of the 32-bit Thumb-2 instruction at `at` with `bits`, where the
+/// first halfword of the instruction is in the high half of `mask` and `bits`.
+fn patch_thumb2(at: *mut u8, mask: u32, bits: u32) {
+    #[cfg_attr(feature = "cargo-clippy", allow(clippy::cast_ptr_alignment))]
+    unsafe {
+        let inst = ptr::read_unaligned(at as *const u32).rotate_left(16);
+        let inst = inst & !mask | bits & mask;
+        ptr::write_unaligned(at as *mut u32, inst.rotate_left(16))
+    };
+}
+
 #[cfg(not(windows))]
 fn lookup_with_dlsym(name: &str) -> *const u8 {
     let c_str = CString::new(name).unwrap();
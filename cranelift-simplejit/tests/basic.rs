@@ -14,6 +14,7 @@ fn error_on_incompatible_sig_in_declare_function() {
         params: vec![AbiParam::new(types::I64)],
         returns: vec![],
         call_conv: CallConv::SystemV,
+        variadic: None,
     };
     module
         .declare_function("abc", Linkage::Local, &sig)
@@ -30,6 +31,7 @@ fn define_simple_function(module: &mut Module<SimpleJITBackend>) -> FuncId {
         params: vec![],
         returns: vec![],
         call_conv: CallConv::SystemV,
+        variadic: None,
     };
 
     let func_id = module
@@ -83,6 +85,7 @@ fn switch_error() {
         params: vec![AbiParam::new(types::I32)],
         returns: vec![AbiParam::new(types::I32)],
         call_conv: CallConv::SystemV,
+        variadic: None,
     };
 
     let mut func = Function::with_name_signature(ExternalName::user(0, 0), sig);
@@ -157,6 +160,7 @@ fn libcall_function() {
         params: vec![],
         returns: vec![],
         call_conv: CallConv::SystemV,
+        variadic: None,
     };
 
     let func_id = module
@@ -1597,7 +1597,8 @@ fn translate_load<FE: FuncEnvironment + ?Sized>(
     // Note that we don't set `is_aligned` here, even if the load instruction's
     // alignment immediate says it's aligned, because WebAssembly's immediate
     // field is just a hint, while Cranelift's aligned flag needs a guarantee.
-    let flags = MemFlags::new();
+    let mut flags = MemFlags::new();
+    flags.set_alias_region(Some(ir::AliasRegion::Heap));
     let (load, dfg) = builder
         .ins()
         .Load(opcode, result_ty, flags, offset.into(), base);
@@ -1620,7 +1621,8 @@ fn translate_store<FE: FuncEnvironment + ?Sized>(
     let heap = state.get_heap(builder.func, 0, environ)?;
     let (base, offset) = get_heap_addr(heap, addr32, offset, environ.pointer_type(), builder);
     // See the comments in `translate_load` about the flags.
-    let flags = MemFlags::new();
+    let mut flags = MemFlags::new();
+    flags.set_alias_region(Some(ir::AliasRegion::Heap));
     builder
         .ins()
         .Store(opcode, val_ty, flags, offset.into(), val, base);
@@ -1,6 +1,6 @@
 //! CLI tool to reduce Cranelift IR files crashing during compilation.
 
-use crate::disasm::{PrintRelocs, PrintStackmaps, PrintTraps};
+use crate::disasm::{PrintCallSites, PrintRelocs, PrintStackmaps, PrintTraps};
 use crate::utils::{parse_sets_and_triple, read_to_string};
 use cranelift_codegen::cursor::{Cursor, FuncCursor};
 use cranelift_codegen::flowgraph::ControlFlowGraph;
@@ -892,6 +892,7 @@ impl<'a> CrashCheckContext<'a> {
             let mut relocs = PrintRelocs::new(false);
             let mut traps = PrintTraps::new(false);
             let mut stackmaps = PrintStackmaps::new(false);
+            let mut call_sites = PrintCallSites::new(false);
 
             let _ = self.context.compile_and_emit(
                 self.isa,
@@ -899,6 +900,7 @@ impl<'a> CrashCheckContext<'a> {
                 &mut relocs,
                 &mut traps,
                 &mut stackmaps,
+                &mut call_sites,
             );
         })) {
             Ok(()) => CheckResult::Succeed,
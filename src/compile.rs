@@ -1,6 +1,6 @@
 //! CLI tool to read Cranelift IR files and compile them into native code.
 
-use crate::disasm::{print_all, PrintRelocs, PrintStackmaps, PrintTraps};
+use crate::disasm::{print_all, PrintCallSites, PrintRelocs, PrintStackmaps, PrintTraps};
 use crate::utils::{parse_sets_and_triple, read_to_string};
 use cranelift_codegen::print_errors::pretty_error;
 use cranelift_codegen::settings::FlagsOrIsa;
@@ -64,11 +64,19 @@ fn handle_module(
         let mut relocs = PrintRelocs::new(flag_print);
         let mut traps = PrintTraps::new(flag_print);
         let mut stackmaps = PrintStackmaps::new(flag_print);
+        let mut call_sites = PrintCallSites::new(flag_print);
         let mut mem = vec![];
 
         // Compile and encode the result to machine code.
         let code_info = context
-            .compile_and_emit(isa, &mut mem, &mut relocs, &mut traps, &mut stackmaps)
+            .compile_and_emit(
+                isa,
+                &mut mem,
+                &mut relocs,
+                &mut traps,
+                &mut stackmaps,
+                &mut call_sites,
+            )
             .map_err(|err| pretty_error(&context.func, Some(isa), err))?;
 
         if flag_print {
@@ -84,6 +92,7 @@ fn handle_module(
                 &relocs,
                 &traps,
                 &stackmaps,
+                &call_sites,
             )?;
         }
     }
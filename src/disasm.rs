@@ -113,6 +113,38 @@ impl binemit::StackmapSink for PrintStackmaps {
     }
 }
 
+pub struct PrintCallSites {
+    pub flag_print: bool,
+    pub text: String,
+}
+
+impl PrintCallSites {
+    pub fn new(flag_print: bool) -> Self {
+        Self {
+            flag_print,
+            text: String::new(),
+        }
+    }
+}
+
+impl binemit::CallSiteSink for PrintCallSites {
+    fn add_call_site(
+        &mut self,
+        offset: binemit::CodeOffset,
+        landing_pad: binemit::CodeOffset,
+        _srcloc: ir::SourceLoc,
+    ) {
+        if self.flag_print {
+            writeln!(
+                &mut self.text,
+                "call_site: {} unwinds to {}",
+                offset, landing_pad
+            )
+            .unwrap();
+        }
+    }
+}
+
 cfg_if! {
     if #[cfg(feature = "disas")] {
         use capstone::prelude::*;
@@ -208,11 +240,15 @@ pub fn print_all(
     relocs: &PrintRelocs,
     traps: &PrintTraps,
     stackmaps: &PrintStackmaps,
+    call_sites: &PrintCallSites,
 ) -> Result<(), String> {
     print_bytes(&mem);
     print_disassembly(isa, &mem[0..code_size as usize])?;
     print_readonly_data(&mem[code_size as usize..(code_size + rodata_size) as usize]);
-    println!("\n{}\n{}\n{}", &relocs.text, &traps.text, &stackmaps.text);
+    println!(
+        "\n{}\n{}\n{}\n{}",
+        &relocs.text, &traps.text, &stackmaps.text, &call_sites.text
+    );
     Ok(())
 }
 
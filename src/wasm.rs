@@ -7,7 +7,7 @@
     allow(clippy::too_many_arguments, clippy::cognitive_complexity)
 )]
 
-use crate::disasm::{print_all, PrintRelocs, PrintStackmaps, PrintTraps};
+use crate::disasm::{print_all, PrintCallSites, PrintRelocs, PrintStackmaps, PrintTraps};
 use crate::utils::{parse_sets_and_triple, read_to_end};
 use cranelift_codegen::ir::DisplayFunctionAnnotations;
 use cranelift_codegen::print_errors::{pretty_error, pretty_verifier_error};
@@ -193,13 +193,21 @@ fn handle_module(
         let mut relocs = PrintRelocs::new(flag_print);
         let mut traps = PrintTraps::new(flag_print);
         let mut stackmaps = PrintStackmaps::new(flag_print);
+        let mut call_sites = PrintCallSites::new(flag_print);
         if flag_check_translation {
             if let Err(errors) = context.verify(fisa) {
                 return Err(pretty_verifier_error(&context.func, fisa.isa, None, errors));
             }
         } else {
             let code_info = context
-                .compile_and_emit(isa, &mut mem, &mut relocs, &mut traps, &mut stackmaps)
+                .compile_and_emit(
+                    isa,
+                    &mut mem,
+                    &mut relocs,
+                    &mut traps,
+                    &mut stackmaps,
+                    &mut call_sites,
+                )
                 .map_err(|err| pretty_error(&context.func, fisa.isa, err))?;
 
             if flag_print_size {
@@ -263,6 +271,7 @@ fn handle_module(
                 &relocs,
                 &traps,
                 &stackmaps,
+                &call_sites,
             )?;
         }
 